@@ -1,3 +1,8 @@
 fn main() {
+    // For the names of bundled sidecars (see `bundled`)
+    println!(
+        "cargo:rustc-env=SIMPLESTCLAW_TARGET_TRIPLE={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
     tauri_build::build();
 }
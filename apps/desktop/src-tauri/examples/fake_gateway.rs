@@ -0,0 +1,291 @@
+//! Fake OpenClaw gateway used by the integration tests.
+//!
+//! Behaves like `openclaw gateway --port <port> --token <token> ...` closely
+//! enough for `SidecarManager`: it binds the requested port (on `--host`,
+//! 127.0.0.1 by default), or the Unix socket given with `--socket`, prints
+//! a startup line and answers every connection with a small HTTP 200 response, or
+//! with the start of the gateway protocol for WebSocket upgrades: a
+//! `connect.challenge` event, `connect` checked against `--token` and
+//! `health`.
+//!
+//! Its behaviour is controlled by a `<exe name>.conf` file next to the binary
+//! (written by `tests/support`), with one `key=value` per line:
+//!
+//! - `mode`: `serve` (default), `exit` (exit immediately) or `hang`
+//!   (never bind the port)
+//! - `exit_code`: exit code for `exit` mode, and for `serve` mode when
+//!   `exit_after_ms` is set
+//! - `exit_after_ms`: in `serve` mode, exit this long after binding
+//! - `stderr`: line to print to stderr before doing anything else
+//! - `stdout`: line to print to stdout right after it
+//! - `stderr_bytes`: about this many bytes of warning lines to print to
+//!   stderr before binding, like npx does
+//! - `env_dump`: file to write the process environment to, as `KEY=VALUE` lines
+//! - `port_override`: port to bind instead of `--port`, like a gateway whose
+//!   own config overrides the CLI flag
+//! - `version`: what `--version` prints (default `openclaw 2026.2.1`)
+//! - `flags`: space-separated flags `gateway --help` lists. When set, any
+//!   other flag makes it exit with an error, like openclaw; otherwise help
+//!   lists every flag in `capabilities::FLAGS`
+//! - `auth`: `check` (default) compares the `connect` token with
+//!   `--token`, `reject` refuses every token
+//! - `stdout_tail`: written to stdout after the startup line as is, with
+//!   `\n` for a newline and `~` for a 0xff byte (not UTF-8); nothing is
+//!   added at the end
+//! - `sigterm`: `ignore` to keep running through SIGTERM (Unix), so only a
+//!   kill stops it
+//! - `upgrade`: `token` to answer 401 to WebSocket upgrades without
+//!   `--token` as their `?token=`
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use simplestclaw_desktop::capabilities::FLAGS;
+use simplestclaw_desktop::protocol::{write_frame, Frame, FrameReader};
+
+fn main() {
+    let conf = read_conf();
+
+    if std::env::args().any(|arg| arg == "--version") {
+        let version = conf
+            .get("version")
+            .map_or("openclaw 2026.2.1", String::as_str);
+        println!("{}", version);
+        return;
+    }
+
+    let flags: Vec<String> = match conf.get("flags") {
+        Some(flags) => flags.split_whitespace().map(str::to_string).collect(),
+        None => FLAGS.iter().map(|flag| flag.flag.to_string()).collect(),
+    };
+    if std::env::args().any(|arg| arg == "--help") {
+        println!("Usage: openclaw gateway [options]\n\nRun the gateway\n\nOptions:");
+        for flag in &flags {
+            println!("  {} <value>", flag);
+        }
+        println!("  -h, --help  display help for command");
+        return;
+    }
+    if conf.contains_key("flags") {
+        let unknown = std::env::args()
+            .skip(1)
+            .find(|arg| arg.starts_with("--") && !flags.contains(arg));
+        if let Some(flag) = unknown {
+            eprintln!("error: unknown option '{}'", flag);
+            std::process::exit(1);
+        }
+    }
+
+    #[cfg(unix)]
+    if conf.get("sigterm").is_some_and(|sigterm| sigterm == "ignore") {
+        unsafe {
+            libc::signal(libc::SIGTERM, libc::SIG_IGN);
+        }
+    }
+
+    if let Some(line) = conf.get("stderr") {
+        eprintln!("{}", line);
+    }
+    if let Some(line) = conf.get("stdout") {
+        println!("{}", line);
+    }
+    if let Some(bytes) = conf.get("stderr_bytes").and_then(|b| b.parse::<usize>().ok()) {
+        let line = "npm warn deprecated some-package@1.0.0: no longer supported\n";
+        let mut stderr = std::io::stderr();
+        for _ in 0..bytes.div_ceil(line.len()) {
+            let _ = stderr.write_all(line.as_bytes());
+        }
+    }
+
+    if let Some(path) = conf.get("env_dump") {
+        let dump: String = std::env::vars()
+            .map(|(key, value)| format!("{}={}\n", key, value))
+            .collect();
+        std::fs::write(path, dump).expect("failed to write env dump");
+    }
+
+    let exit_code = conf
+        .get("exit_code")
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(1);
+
+    match conf.get("mode").map(String::as_str).unwrap_or("serve") {
+        "exit" => std::process::exit(exit_code),
+        "hang" => loop {
+            std::thread::sleep(Duration::from_secs(60));
+        },
+        _ => {}
+    }
+
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+    };
+    let handler = Handler {
+        token: arg("--token").unwrap_or_default(),
+        reject: conf.get("auth").is_some_and(|auth| auth == "reject"),
+        upgrade_token: conf.get("upgrade").is_some_and(|upgrade| upgrade == "token"),
+    };
+
+    #[cfg(unix)]
+    if let Some(path) = arg("--socket") {
+        let listener = UnixListener::bind(&path).expect("failed to bind socket");
+        println!("[gateway] listening on ws+unix://{}", path);
+        after_bind(&conf, exit_code);
+        for stream in listener.incoming().flatten() {
+            let handler = handler.clone();
+            std::thread::spawn(move || handler.handle(stream));
+        }
+        return;
+    }
+
+    let port = arg("--port")
+        .and_then(|p| p.parse::<u16>().ok())
+        .expect("missing --port");
+    let port = conf
+        .get("port_override")
+        .and_then(|p| p.parse::<u16>().ok())
+        .unwrap_or(port);
+    let host = arg("--host").unwrap_or_else(|| "127.0.0.1".to_string());
+
+    let listener = TcpListener::bind((host.as_str(), port)).expect("failed to bind port");
+    println!("[gateway] listening on ws://{}:{}", host, port);
+    after_bind(&conf, exit_code);
+    for stream in listener.incoming().flatten() {
+        let handler = handler.clone();
+        std::thread::spawn(move || handler.handle(stream));
+    }
+}
+
+/// What the gateway does once it listens, as configured
+fn after_bind(conf: &HashMap<String, String>, exit_code: i32) {
+    if let Some(tail) = conf.get("stdout_tail") {
+        let bytes: Vec<u8> = tail
+            .replace("\\n", "\n")
+            .bytes()
+            .map(|b| if b == b'~' { 0xff } else { b })
+            .collect();
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(&bytes);
+        let _ = stdout.flush();
+    }
+
+    if let Some(ms) = conf.get("exit_after_ms").and_then(|m| m.parse().ok()) {
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(ms));
+            std::process::exit(exit_code);
+        });
+    }
+}
+
+/// A connection on the port or the socket
+trait Connection: Read + Write {
+    fn set_timeout(&self, timeout: Duration);
+}
+
+impl Connection for TcpStream {
+    fn set_timeout(&self, timeout: Duration) {
+        let _ = self.set_read_timeout(Some(timeout));
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn set_timeout(&self, timeout: Duration) {
+        let _ = self.set_read_timeout(Some(timeout));
+    }
+}
+
+/// Answers one connection
+#[derive(Clone)]
+struct Handler {
+    token: String,
+    reject: bool,
+    upgrade_token: bool,
+}
+
+impl Handler {
+    fn handle(&self, mut stream: impl Connection) {
+        stream.set_timeout(Duration::from_millis(500));
+        let mut buf = [0u8; 1024];
+        // Health probes may connect and close without sending anything
+        let Ok(n) = stream.read(&mut buf) else {
+            return;
+        };
+        let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+        let token_param = format!("token={}", self.token.to_lowercase());
+        if request.contains("upgrade: websocket")
+            && self.upgrade_token
+            && !request.contains(&token_param)
+        {
+            let _ = stream.write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n");
+        } else if request.contains("upgrade: websocket") {
+            let accepted = (!self.reject).then_some(self.token.as_str());
+            let _ = serve_protocol(stream, accepted);
+        } else if n > 0 {
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+        }
+    }
+}
+
+/// Speak enough of the gateway protocol for the auth self-test. `token` is
+/// the one `connect` must present, None to refuse all.
+fn serve_protocol(mut stream: impl Connection, token: Option<&str>) -> std::io::Result<()> {
+    stream.set_timeout(Duration::from_secs(10));
+    // The accept key isn't checked by our client
+    stream.write_all(
+        b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+          Sec-WebSocket-Accept: fake\r\n\r\n",
+    )?;
+    let send = |mut stream: &mut dyn Write, message: Value| {
+        write_frame(&mut stream, &Frame::Text(message.to_string()), None)
+    };
+    send(
+        &mut stream,
+        json!({ "type": "event", "event": "connect.challenge", "payload": { "nonce": "n" } }),
+    )?;
+
+    let mut frames = FrameReader::default();
+    loop {
+        let Frame::Text(text) = frames.read(&mut stream)? else {
+            return Ok(());
+        };
+        let request: Value = serde_json::from_str(&text).unwrap_or_default();
+        let id = request["id"].clone();
+        let response = match request["method"].as_str() {
+            Some("connect")
+                if token.is_some_and(|token| request["params"]["auth"]["token"] == token) =>
+            {
+                json!({ "type": "res", "id": id, "ok": true, "payload": { "type": "hello-ok" } })
+            }
+            Some("connect") => {
+                json!({ "type": "res", "id": id, "ok": false, "error": { "message": "unauthorized: gateway token mismatch" } })
+            }
+            Some("health") => {
+                json!({ "type": "res", "id": id, "ok": true, "payload": { "ok": true } })
+            }
+            _ => {
+                json!({ "type": "res", "id": id, "ok": false, "error": { "message": "unknown method" } })
+            }
+        };
+        send(&mut stream, response)?;
+    }
+}
+
+fn read_conf() -> HashMap<String, String> {
+    let exe = std::env::current_exe().expect("no current exe");
+    let contents = std::fs::read_to_string(exe.with_extension("conf")).unwrap_or_default();
+    contents
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
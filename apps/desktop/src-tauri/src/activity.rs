@@ -4,6 +4,9 @@ use std::path::PathBuf;
 use std::sync::Mutex;
 use thiserror::Error;
 
+use crate::error::AppError;
+use crate::paths::AppPaths;
+
 const MAX_ENTRIES: usize = 500;
 
 #[derive(Error, Debug)]
@@ -38,10 +41,8 @@ struct ActivityLog {
 
 impl ActivityLog {
     fn activity_path() -> Result<PathBuf, ActivityError> {
-        let data_dir = dirs::data_local_dir().ok_or(ActivityError::NoDataDir)?;
-        let app_dir = data_dir.join("simplestclaw");
-        fs::create_dir_all(&app_dir)?;
-        Ok(app_dir.join("activity.json"))
+        let paths = AppPaths::resolve().ok_or(ActivityError::NoDataDir)?;
+        Ok(paths.activity_log_file()?)
     }
 
     fn load() -> Result<Self, ActivityError> {
@@ -155,15 +156,15 @@ pub fn log_activity(
 #[tauri::command]
 pub fn get_activity_log(
     manager: tauri::State<'_, ActivityManager>,
-) -> Result<Vec<ActivityLogEntry>, String> {
-    manager.get_entries().map_err(|e| e.to_string())
+) -> Result<Vec<ActivityLogEntry>, AppError> {
+    Ok(manager.get_entries()?)
 }
 
 #[tauri::command]
 pub fn clear_activity_log(
     manager: tauri::State<'_, ActivityManager>,
-) -> Result<(), String> {
-    manager.clear().map_err(|e| e.to_string())
+) -> Result<(), AppError> {
+    Ok(manager.clear()?)
 }
 
 #[tauri::command]
@@ -173,7 +174,7 @@ pub fn add_activity_entry(
     details: String,
     status: String,
     path: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let entry = ActivityLogEntry {
         id: generate_id(),
         timestamp: current_timestamp(),
@@ -182,5 +183,5 @@ pub fn add_activity_entry(
         status,
         path,
     };
-    manager.add_entry(entry).map_err(|e| e.to_string())
+    Ok(manager.add_entry(entry)?)
 }
@@ -0,0 +1,117 @@
+//! Adopted Gateway
+//!
+//! A user may start `openclaw gateway` by hand on the port the app was
+//! going to use. A start that finds the port taken asks what listens
+//! there: an OpenClaw gateway fails the start with
+//! `external_gateway_detected`, anything else not recorded as a leftover
+//! of the app's own (see `pid_file`) is still `port_in_use`. Neither is
+//! ever signalled. With the token the user started it with,
+//! `adopt_external_gateway` then takes it on as the running gateway.
+//!
+//! An adopted gateway isn't managed: the status reports it running with
+//! `managed` false for as long as its port takes connections, a stop only
+//! lets go of it, and it going away is neither a crash nor restarted.
+
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpStream};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::process::{GatewayProcess, ProcessExit, StopKind};
+use crate::protocol;
+
+/// How long a gateway gets to show itself or accept a connection
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Whether an OpenClaw gateway listens at `addr`
+pub fn detect(addr: SocketAddr) -> bool {
+    protocol::is_gateway(addr, PROBE_TIMEOUT)
+}
+
+/// Pid of the process listening on `port`, if it can be found
+#[cfg(unix)]
+pub fn listener_pid(port: u16) -> Option<u32> {
+    let output = Command::new("lsof")
+        .args(["-ti", &format!("tcp:{}", port), "-sTCP:LISTEN"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().parse().ok())
+}
+
+/// Pid of the process listening on `port`, if it can be found
+#[cfg(windows)]
+pub fn listener_pid(port: u16) -> Option<u32> {
+    let output = Command::new("netstat")
+        .args(["-ano", "-p", "TCP"])
+        .output()
+        .ok()?;
+    let suffix = format!(":{}", port);
+    String::from_utf8_lossy(&output.stdout).lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [_, local, _, "LISTENING", pid] if local.ends_with(&suffix) => pid.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// The adopted gateway, standing in for a child the app spawned
+pub struct AdoptedProcess {
+    addr: SocketAddr,
+    pid: Option<u32>,
+}
+
+impl AdoptedProcess {
+    /// Take on the gateway at `addr`, from now until the app quits
+    pub fn adopt(addr: SocketAddr, pid: Option<u32>) -> Self {
+        Self { addr, pid }
+    }
+}
+
+impl GatewayProcess for AdoptedProcess {
+    /// 0 when the listener's pid couldn't be found
+    fn id(&self) -> u32 {
+        self.pid.unwrap_or(0)
+    }
+
+    /// It has gone once its port refuses connections
+    fn try_wait(&mut self) -> io::Result<Option<ProcessExit>> {
+        match TcpStream::connect_timeout(&self.addr, PROBE_TIMEOUT) {
+            Ok(_) => Ok(None),
+            Err(_) => Ok(Some(ProcessExit {
+                code: None,
+                signal: None,
+            })),
+        }
+    }
+
+    /// Not the app's to kill, so only let go of it
+    fn kill(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn stop(&mut self, _grace: Duration) -> io::Result<StopKind> {
+        Ok(StopKind::Detached)
+    }
+
+    fn wait(&mut self) -> io::Result<ProcessExit> {
+        Ok(ProcessExit {
+            code: None,
+            signal: None,
+        })
+    }
+
+    fn managed(&self) -> bool {
+        false
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        None
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        None
+    }
+}
@@ -0,0 +1,174 @@
+//! Anthropic Endpoint
+//!
+//! Companies that route model traffic through an LLM proxy of their own
+//! need the gateway to talk to it instead of api.anthropic.com.
+//! `anthropicBaseUrl` is passed as `ANTHROPIC_BASE_URL`, which the
+//! Anthropic SDK inside openclaw reads. Proxies that want the key in a
+//! header of their own name it in `anthropicAuthHeader`: `Authorization`
+//! sends it as a bearer token in `ANTHROPIC_AUTH_TOKEN`, any other name as
+//! `ANTHROPIC_CUSTOM_HEADERS`. Unset, none of these is passed and the SDK
+//! goes to Anthropic with `x-api-key` as usual.
+//!
+//! The API key check in `api_key` uses the same URL and header, so a key
+//! only the proxy knows checks out. Neither setting is a secret; both show
+//! in `get_config`.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::api_key::ANTHROPIC_API;
+use crate::config::Config;
+
+/// Variable the base URL is passed in
+pub const BASE_URL_ENV: &str = "ANTHROPIC_BASE_URL";
+
+/// Variable a key sent as `Authorization: Bearer` is passed in
+pub const AUTH_TOKEN_ENV: &str = "ANTHROPIC_AUTH_TOKEN";
+
+/// Variable other headers are passed in, as `Name: value` lines
+pub const CUSTOM_HEADERS_ENV: &str = "ANTHROPIC_CUSTOM_HEADERS";
+
+/// The header Anthropic takes the key in
+pub const DEFAULT_AUTH_HEADER: &str = "x-api-key";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EndpointError {
+    #[error("{0} is not an http(s) URL without credentials, like https://llm.example.com")]
+    InvalidBaseUrl(String),
+    #[error("{0:?} is not an HTTP header name like X-Api-Key")]
+    InvalidAuthHeader(String),
+}
+
+impl EndpointError {
+    /// The config field at fault
+    pub fn field(&self) -> &'static str {
+        match self {
+            EndpointError::InvalidBaseUrl(_) => "anthropicBaseUrl",
+            EndpointError::InvalidAuthHeader(_) => "anthropicAuthHeader",
+        }
+    }
+}
+
+/// Check `url` is an http(s) URL with a host. Credentials would show in
+/// `get_config`, so they aren't allowed.
+pub fn validate_base_url(url: &str) -> Result<(), EndpointError> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed)
+            if matches!(parsed.scheme(), "http" | "https")
+                && parsed.has_host()
+                && parsed.username().is_empty()
+                && parsed.password().is_none() =>
+        {
+            Ok(())
+        }
+        _ => Err(EndpointError::InvalidBaseUrl(url.to_string())),
+    }
+}
+
+/// Check `name` is a header name, letters, digits and the few symbols
+/// HTTP allows
+pub fn validate_auth_header(name: &str) -> Result<(), EndpointError> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if name.is_empty() || !name.chars().all(allowed) {
+        return Err(EndpointError::InvalidAuthHeader(name.to_string()));
+    }
+    Ok(())
+}
+
+/// The endpoint the gateway and the key check talk to
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AnthropicEndpoint {
+    /// Without a trailing `/`; Anthropic's own API when None
+    pub base_url: Option<String>,
+    /// `x-api-key` when None
+    pub auth_header: Option<String>,
+}
+
+impl AnthropicEndpoint {
+    /// `base_url` and `auth_header` trimmed and checked, with empty ones
+    /// unset
+    pub fn new(
+        base_url: Option<String>,
+        auth_header: Option<String>,
+    ) -> Result<Self, EndpointError> {
+        let field = |value: Option<String>| {
+            value
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let endpoint = Self {
+            base_url: field(base_url).map(|url| url.trim_end_matches('/').to_string()),
+            auth_header: field(auth_header),
+        };
+        endpoint.validate()?;
+        Ok(endpoint)
+    }
+
+    pub fn validate(&self) -> Result<(), EndpointError> {
+        if let Some(ref url) = self.base_url {
+            validate_base_url(url)?;
+        }
+        if let Some(ref name) = self.auth_header {
+            validate_auth_header(name)?;
+        }
+        Ok(())
+    }
+
+    /// Nothing is overridden
+    pub fn is_default(&self) -> bool {
+        self.base_url.is_none() && self.auth_header.is_none()
+    }
+
+    /// Where the API is
+    pub fn base_url(&self) -> &str {
+        self.base_url.as_deref().unwrap_or(ANTHROPIC_API)
+    }
+
+    /// The header `key` goes in, and its value
+    pub fn auth(&self, key: &str) -> (String, String) {
+        match self.auth_header.as_deref() {
+            Some(name) if name.eq_ignore_ascii_case("authorization") => {
+                ("Authorization".to_string(), format!("Bearer {}", key))
+            }
+            Some(name) => (name.to_string(), key.to_string()),
+            None => (DEFAULT_AUTH_HEADER.to_string(), key.to_string()),
+        }
+    }
+
+    /// Variables to inject; the header ones only with a `key` to send
+    pub fn env(&self, key: Option<&str>) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        if let Some(ref url) = self.base_url {
+            env.push((BASE_URL_ENV.to_string(), url.clone()));
+        }
+        match (self.auth_header.as_deref(), key) {
+            (Some(name), Some(key)) if name.eq_ignore_ascii_case("authorization") => {
+                env.push((AUTH_TOKEN_ENV.to_string(), key.to_string()));
+            }
+            (Some(name), Some(key)) => {
+                env.push((CUSTOM_HEADERS_ENV.to_string(), format!("{}: {}", name, key)));
+            }
+            _ => {}
+        }
+        env
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "Anthropic API at {}, key in {}",
+            self.base_url(),
+            self.auth_header.as_deref().unwrap_or(DEFAULT_AUTH_HEADER)
+        )
+    }
+}
+
+/// The endpoint in `config`, checked. None when nothing is overridden.
+pub fn plan(config: &Config) -> Result<Option<AnthropicEndpoint>, EndpointError> {
+    let endpoint = AnthropicEndpoint {
+        base_url: config.anthropic_base_url.clone(),
+        auth_header: config.anthropic_auth_header.clone(),
+    };
+    endpoint.validate()?;
+    Ok((!endpoint.is_default()).then_some(endpoint))
+}
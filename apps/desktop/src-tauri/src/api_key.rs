@@ -0,0 +1,203 @@
+//! API Key Check
+//!
+//! `test_api_key` tells a key that is wrong apart from one that can't be
+//! checked right now, before it is saved: the pasted text is trimmed and
+//! must look like an Anthropic key, then one cheap authenticated request
+//! (`MODELS_PATH`) must succeed. Only a malformed or rejected key is
+//! refused by `set_api_key`, so being offline or rate limited never stops
+//! anyone from saving. The check goes through the gateway's proxy (see
+//! `proxy`) to the API the gateway uses (see `anthropic_endpoint`). A
+//! proxy's keys needn't look like Anthropic's, so with `anthropicBaseUrl`
+//! set only spaces make a key malformed.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::anthropic_endpoint::{self, AnthropicEndpoint, EndpointError};
+use crate::config::Config;
+use crate::proxy::{self, GatewayProxy};
+
+pub const ANTHROPIC_API: &str = "https://api.anthropic.com";
+
+/// Lists models, which any working key may do
+pub const MODELS_PATH: &str = "/v1/models?limit=1";
+
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+pub const KEY_PREFIX: &str = "sk-ant-";
+
+/// Shorter than any key Anthropic issues
+pub const MIN_KEY_LEN: usize = 40;
+
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiKeyErrorKind {
+    /// Not shaped like an Anthropic key, e.g. an OpenAI one
+    Malformed,
+    /// Rejected by the API
+    Unauthorized,
+    /// The API couldn't be reached
+    Network,
+    RateLimited,
+    /// Any other answer, e.g. the API is overloaded
+    Unexpected,
+    /// Not checked, as a setting it depends on is invalid
+    Settings,
+}
+
+impl ApiKeyErrorKind {
+    /// The key is known not to work, rather than not checked
+    pub fn is_rejection(self) -> bool {
+        matches!(
+            self,
+            ApiKeyErrorKind::Malformed | ApiKeyErrorKind::Unauthorized
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyCheck {
+    pub valid: bool,
+    pub error_kind: Option<ApiKeyErrorKind>,
+    pub message: Option<String>,
+    /// From the `anthropic-organization-id` header of a successful check
+    pub organization_id: Option<String>,
+}
+
+impl ApiKeyCheck {
+    fn failed(kind: ApiKeyErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            valid: false,
+            error_kind: Some(kind),
+            message: Some(message.into()),
+            organization_id: None,
+        }
+    }
+
+    /// Not checked, as `e` makes the endpoint unusable
+    pub fn invalid_endpoint(e: &EndpointError) -> Self {
+        Self::failed(
+            ApiKeyErrorKind::Settings,
+            format!("Fix {} in settings first: {}", e.field(), e),
+        )
+    }
+}
+
+/// Why `key`, already trimmed, can't be an Anthropic key
+pub fn check_format(key: &str) -> Result<(), String> {
+    if !key.starts_with(KEY_PREFIX) {
+        return Err(format!("Anthropic API keys start with {}.", KEY_PREFIX));
+    }
+    if key.len() < MIN_KEY_LEN {
+        return Err("The key is too short; it may have been cut off when copying.".to_string());
+    }
+    if key.chars().any(char::is_whitespace) {
+        return Err("The key contains spaces or line breaks.".to_string());
+    }
+    Ok(())
+}
+
+/// Why `key`, already trimmed, can't be a key for a proxy's API
+pub fn check_proxy_format(key: &str) -> Result<(), String> {
+    if key.is_empty() {
+        return Err("The key is empty.".to_string());
+    }
+    if key.chars().any(char::is_whitespace) {
+        return Err("The key contains spaces or line breaks.".to_string());
+    }
+    Ok(())
+}
+
+/// What an HTTP status from the check means, None for success
+pub fn classify_status(status: u16) -> Option<ApiKeyErrorKind> {
+    match status {
+        200..=299 => None,
+        401 | 403 => Some(ApiKeyErrorKind::Unauthorized),
+        429 => Some(ApiKeyErrorKind::RateLimited),
+        _ => Some(ApiKeyErrorKind::Unexpected),
+    }
+}
+
+/// Check `key` against the API at `endpoint`, through `proxy` like the
+/// gateway
+pub async fn check_api_key(
+    endpoint: &AnthropicEndpoint,
+    key: &str,
+    proxy: Option<&GatewayProxy>,
+) -> ApiKeyCheck {
+    let key = key.trim();
+    let shape = match endpoint.base_url {
+        Some(_) => check_proxy_format(key),
+        None => check_format(key),
+    };
+    if let Err(message) = shape {
+        return ApiKeyCheck::failed(ApiKeyErrorKind::Malformed, message);
+    }
+    let base_url = endpoint.base_url();
+    let (auth_name, auth_value) = endpoint.auth(key);
+
+    let client = match proxy::client(proxy, CHECK_TIMEOUT) {
+        Ok(client) => client,
+        Err(e) => return ApiKeyCheck::failed(ApiKeyErrorKind::Network, e.to_string()),
+    };
+    let response = client
+        .get(format!("{}{}", base_url.trim_end_matches('/'), MODELS_PATH))
+        .header(auth_name, auth_value)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            return ApiKeyCheck::failed(
+                ApiKeyErrorKind::Network,
+                format!("Could not reach {}: {}", base_url, e),
+            )
+        }
+    };
+
+    let status = response.status().as_u16();
+    let api = match endpoint.base_url {
+        Some(ref url) => url.as_str(),
+        None => "Anthropic",
+    };
+    match classify_status(status) {
+        None => ApiKeyCheck {
+            valid: true,
+            error_kind: None,
+            message: None,
+            organization_id: response
+                .headers()
+                .get("anthropic-organization-id")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        },
+        Some(kind) => {
+            let message = match kind {
+                ApiKeyErrorKind::Unauthorized => format!("{} did not accept this key.", api),
+                ApiKeyErrorKind::RateLimited => {
+                    format!("{} is rate limiting requests; try again shortly.", api)
+                }
+                _ => format!("{} answered with HTTP {}.", api, status),
+            };
+            ApiKeyCheck::failed(kind, message)
+        }
+    }
+}
+
+/// Check a key before saving it (see the module docs)
+#[tauri::command]
+pub async fn test_api_key(key: String) -> ApiKeyCheck {
+    let config = Config::load().unwrap_or_default();
+    let endpoint = match anthropic_endpoint::plan(&config) {
+        Ok(endpoint) => endpoint.unwrap_or_default(),
+        Err(e) => return ApiKeyCheck::invalid_endpoint(&e),
+    };
+    match proxy::plan(&config) {
+        Ok(proxy) => check_api_key(&endpoint, &key, proxy.as_ref()).await,
+        Err(e) => ApiKeyCheck::failed(ApiKeyErrorKind::Network, e.to_string()),
+    }
+}
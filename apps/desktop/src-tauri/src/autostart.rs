@@ -0,0 +1,89 @@
+//! Gateway Auto Start
+//!
+//! With `autoStartGateway` on (the default) the gateway is started when the
+//! app launches, after the runtime check, instead of waiting for the
+//! frontend to ask. It runs on a background task so setup and the window
+//! aren't held up, and only with an API key: without one onboarding has to
+//! run first. The outcome is emitted as `gateway://started` like any start.
+//! A start that fails is emitted as `gateway://start-failed` by the
+//! manager with the phase it failed in (see `status_events`), or here with
+//! just the error when it failed before getting that far, e.g. on an
+//! external gateway that can't be reached. A failed start leaves the
+//! manager stopped, as every failed start does, so the frontend's own start
+//! or `ensure_ready` simply tries again.
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::sidecar::{SidecarManager, StartResult};
+use crate::status_events::START_FAILED_EVENT;
+
+/// What `auto_start` did
+#[derive(Debug)]
+pub enum AutoStart {
+    /// `autoStartGateway` is off
+    Disabled,
+    /// There is no API key to start with yet
+    NoApiKey,
+    Started(StartResult),
+    Failed(AppError),
+}
+
+/// Start the gateway on `manager` if `config` asks for it on launch
+pub fn auto_start(manager: &SidecarManager, config: &Config) -> AutoStart {
+    if !config.auto_start_gateway {
+        return AutoStart::Disabled;
+    }
+    if config.anthropic_api_key.as_deref().is_none_or(str::is_empty) {
+        return AutoStart::NoApiKey;
+    }
+    match manager.start() {
+        Ok(result) => AutoStart::Started(result),
+        Err(e) => AutoStart::Failed(e),
+    }
+}
+
+/// Auto start the app's gateway off the calling thread and announce the
+/// outcome; see the module docs
+pub async fn auto_start_on_launch(app: AppHandle) {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[autostart] Failed to load config, not starting the gateway: {}", e);
+            return;
+        }
+    };
+    let outcome = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || match app.try_state::<SidecarManager>() {
+            Some(manager) => {
+                let generation = manager.generation();
+                let outcome = auto_start(&manager, &config);
+                (outcome, manager.generation() != generation)
+            }
+            None => (AutoStart::Disabled, false),
+        }
+    })
+    .await;
+
+    match outcome {
+        Ok((AutoStart::Disabled, _)) => println!("[autostart] autoStartGateway is off"),
+        Ok((AutoStart::NoApiKey, _)) => println!("[autostart] No API key yet, not starting the gateway"),
+        Ok((AutoStart::Started(result), _)) => {
+            println!("[autostart] Gateway started on {}", result.info.listen_address());
+            let _ = app.emit("gateway://started", &result);
+        }
+        Ok((AutoStart::Failed(e), attempted)) => {
+            eprintln!("[autostart] Failed to start the gateway: {}", e);
+            // Otherwise the manager emitted it, with its phase
+            if !attempted {
+                let _ = app.emit(START_FAILED_EVENT, &e);
+            }
+        }
+        Err(e) => {
+            eprintln!("[autostart] Gateway start task failed: {}", e);
+            let _ = app.emit(START_FAILED_EVENT, &AppError::from(e));
+        }
+    }
+}
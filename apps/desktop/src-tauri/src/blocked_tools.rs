@@ -0,0 +1,180 @@
+//! Blocked Tools
+//!
+//! `blockedTools` in config.json lists gateway tools that must never run,
+//! e.g. `["exec"]`. Each one is passed to the gateway as `--deny-tool <id>`
+//! when it is spawned, so changes take effect on the next start.
+//!
+//! Gateway releases learned to disable tools one at a time, so `TOOLS`
+//! records the first release that accepts `--deny-tool` for each. Before a
+//! start with tools blocked, the installed gateway is asked for its version;
+//! if it can't disable one of them (or won't say which version it is) the
+//! start is refused rather than run with the tool available.
+
+use std::fmt;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Gateway flag disabling one tool, repeated per tool
+pub const DENY_FLAG: &str = "--deny-tool";
+
+/// A gateway release, `year.month.patch` like `2026.1.29`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GatewayVersion(pub u32, pub u32, pub u32);
+
+impl GatewayVersion {
+    /// The first version number in `openclaw --version` output. Prerelease
+    /// suffixes (`2026.2.1-beta.3`) are ignored.
+    pub fn parse(output: &str) -> Option<Self> {
+        output.split_whitespace().find_map(|word| {
+            let word = word.trim_start_matches('v');
+            let core = word.split(['-', '+']).next()?;
+            let mut parts = core.split('.').map(|part| part.parse::<u32>().ok());
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(Some(major)), Some(Some(minor)), Some(Some(patch)), None) => {
+                    Some(GatewayVersion(major, minor, patch))
+                }
+                _ => None,
+            }
+        })
+    }
+}
+
+impl fmt::Display for GatewayVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// A gateway tool that can be listed in `blockedTools`
+#[derive(Debug, Clone, Copy)]
+pub struct BlockableTool {
+    pub id: &'static str,
+    /// First release that can disable it, None if none can yet
+    pub since: Option<GatewayVersion>,
+}
+
+const fn tool(id: &'static str, since: Option<GatewayVersion>) -> BlockableTool {
+    BlockableTool { id, since }
+}
+
+/// The gateway's tool identifiers. Update when a release adds tools or
+/// learns to disable more of them.
+pub const TOOLS: &[BlockableTool] = &[
+    tool("exec", Some(GatewayVersion(2026, 1, 20))),
+    tool("process", Some(GatewayVersion(2026, 1, 20))),
+    tool("read", Some(GatewayVersion(2026, 1, 20))),
+    tool("write", Some(GatewayVersion(2026, 1, 20))),
+    tool("edit", Some(GatewayVersion(2026, 1, 20))),
+    tool("apply_patch", Some(GatewayVersion(2026, 1, 20))),
+    tool("web_fetch", Some(GatewayVersion(2026, 1, 20))),
+    tool("web_search", Some(GatewayVersion(2026, 1, 20))),
+    tool("browser", Some(GatewayVersion(2026, 2, 1))),
+    tool("canvas", Some(GatewayVersion(2026, 2, 1))),
+    tool("cron", Some(GatewayVersion(2026, 2, 1))),
+    tool("message", Some(GatewayVersion(2026, 2, 1))),
+    tool("gateway", None),
+];
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BlockedToolsError {
+    #[error("Unknown tool: {0}")]
+    UnknownTool(String),
+    #[error("The installed gateway ({version}) cannot disable the {tool} tool")]
+    Unsupported { tool: String, version: String },
+}
+
+pub fn find(id: &str) -> Option<&'static BlockableTool> {
+    TOOLS.iter().find(|tool| tool.id == id)
+}
+
+/// Check `tools` against the known identifiers, returning them trimmed,
+/// lowercased, sorted and without duplicates
+pub fn validate(tools: &[String]) -> Result<Vec<String>, BlockedToolsError> {
+    let mut valid = Vec::new();
+    for tool in tools {
+        let id = tool.trim().to_lowercase();
+        if find(&id).is_none() {
+            return Err(BlockedToolsError::UnknownTool(tool.clone()));
+        }
+        valid.push(id);
+    }
+    valid.sort();
+    valid.dedup();
+    Ok(valid)
+}
+
+/// Gateway arguments disabling `tools` (already validated) on `version`.
+/// Fails on the first tool that version can't disable; an unknown version
+/// can't disable any.
+pub fn gateway_args(
+    tools: &[String],
+    version: Option<GatewayVersion>,
+) -> Result<Vec<String>, BlockedToolsError> {
+    let mut args = Vec::new();
+    for id in tools {
+        let tool = find(id).ok_or_else(|| BlockedToolsError::UnknownTool(id.clone()))?;
+        let supported = match (version, tool.since) {
+            (Some(version), Some(since)) => version >= since,
+            _ => false,
+        };
+        if !supported {
+            return Err(BlockedToolsError::Unsupported {
+                tool: id.clone(),
+                version: version.map_or("unknown version".to_string(), |v| v.to_string()),
+            });
+        }
+        args.push(DENY_FLAG.to_string());
+        args.push(id.clone());
+    }
+    Ok(args)
+}
+
+/// Run `program args --version` and parse the answer. None when it fails,
+/// prints no version or takes longer than `timeout`.
+pub fn query_version(
+    program: &str,
+    args: &[String],
+    env: &[(String, String)],
+    timeout: Duration,
+) -> Option<GatewayVersion> {
+    let mut args = args.to_vec();
+    args.push("--version".to_string());
+    GatewayVersion::parse(&cli_output(program, &args, env, timeout)?)
+}
+
+/// Stdout of `program args`, when it exits successfully within `timeout`
+pub fn cli_output(
+    program: &str,
+    args: &[String],
+    env: &[(String, String)],
+    timeout: Duration,
+) -> Option<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    Some(output)
+}
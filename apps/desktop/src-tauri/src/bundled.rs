@@ -0,0 +1,133 @@
+//! Bundled openclaw
+//!
+//! A packaged app can ship the openclaw CLI as a Tauri sidecar, so it
+//! works without a global install: listed under `bundle.externalBin` as
+//! `binaries/openclaw`, with one `binaries/openclaw-<target triple>` per
+//! platform built. The bundler puts it next to the app's executable,
+//! where `tauri dev` copies it too; depending on the version it keeps the
+//! triple in the name or not, so both are looked for.
+//!
+//! When there is a bundled binary and it is executable, starts run it
+//! directly. `openclawPath` still comes first, and `preferGlobalOpenclaw`
+//! skips the bundled one for developers testing a global install. Without
+//! one, starts fall back to `npx openclaw` with the node `resolve` finds,
+//! logging why.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::resolve;
+use crate::sidecar::{ExecutableSource, SidecarManager};
+
+/// Name of the sidecar in `bundle.externalBin`, without the directory
+pub const BINARY_NAME: &str = "openclaw";
+
+/// Triple the app was built for, which the bundler appends to sidecar
+/// names
+pub const TARGET_TRIPLE: &str = env!("SIMPLESTCLAW_TARGET_TRIPLE");
+
+/// The names the bundled binary may have in `dir`, in the order tried
+pub fn candidates(dir: &Path) -> Vec<PathBuf> {
+    let suffix = std::env::consts::EXE_SUFFIX;
+    vec![
+        dir.join(format!("{}{}", BINARY_NAME, suffix)),
+        dir.join(format!("{}-{}{}", BINARY_NAME, TARGET_TRIPLE, suffix)),
+    ]
+}
+
+/// The bundled binary in `dir`, or why there is none that can be run
+pub fn find_in(dir: &Path) -> Result<PathBuf, String> {
+    let candidates = candidates(dir);
+    if let Some(binary) = candidates.iter().find(|path| resolve::is_executable(path)) {
+        return Ok(binary.clone());
+    }
+    match candidates.iter().find(|path| path.exists()) {
+        Some(binary) => Err(format!("{} is not executable", binary.display())),
+        None => Err(format!("there is no {} in {}", BINARY_NAME, dir.display())),
+    }
+}
+
+/// The bundled binary next to the running app, or why there is none
+pub fn find() -> Result<PathBuf, String> {
+    let exe =
+        std::env::current_exe().map_err(|e| format!("the app's own location is unknown: {}", e))?;
+    let dir = exe
+        .parent()
+        .ok_or_else(|| format!("{} has no parent directory", exe.display()))?;
+    find_in(dir)
+}
+
+/// The bundled binary a start with `config` would run, or why not
+pub fn for_config(config: &Config) -> Result<PathBuf, String> {
+    if config.prefer_global_openclaw {
+        return Err("preferGlobalOpenclaw is on".to_string());
+    }
+    find()
+}
+
+/// Where the openclaw a start runs comes from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OpenclawKind {
+    /// Shipped with the app
+    Bundled,
+    /// `openclawPath` from config
+    Config,
+    /// `npx openclaw`, a global or cached install
+    Global,
+}
+
+/// What `get_openclaw_source` reports
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenclawSource {
+    /// What the running gateway was started from; None when none runs or
+    /// it is external
+    pub running: Option<OpenclawKind>,
+    /// What the next start would use
+    pub next: OpenclawKind,
+    /// The bundled binary, when there is one
+    pub bundled_path: Option<String>,
+    /// Why the bundled binary isn't used, when it isn't
+    pub bundled_unavailable: Option<String>,
+}
+
+impl OpenclawKind {
+    /// The kind for a gateway started from `source`
+    pub fn of(source: ExecutableSource) -> Self {
+        match source {
+            ExecutableSource::Bundled => OpenclawKind::Bundled,
+            ExecutableSource::Config | ExecutableSource::Override => OpenclawKind::Config,
+            _ => OpenclawKind::Global,
+        }
+    }
+}
+
+/// Whether the running gateway and the next start use the bundled
+/// openclaw or another one
+#[tauri::command]
+pub fn get_openclaw_source(manager: State<'_, SidecarManager>) -> Result<OpenclawSource, AppError> {
+    let config = Config::load()?;
+    let running = manager
+        .status()
+        .executable
+        .and_then(|executable| executable.source)
+        .map(OpenclawKind::of);
+    let bundled = for_config(&config);
+    let next = if config.openclaw_path.is_some() {
+        OpenclawKind::Config
+    } else if bundled.is_ok() {
+        OpenclawKind::Bundled
+    } else {
+        OpenclawKind::Global
+    };
+    Ok(OpenclawSource {
+        running,
+        next,
+        bundled_path: find().ok().map(|path| path.display().to_string()),
+        bundled_unavailable: bundled.err(),
+    })
+}
@@ -0,0 +1,212 @@
+//! Gateway CLI Capabilities
+//!
+//! openclaw exits straight away on a flag it doesn't know, and releases
+//! differ in which of ours they accept. Before a start the installed
+//! gateway's `gateway --help` is read and every flag in `FLAGS` looked up
+//! in it; the result is cached per binary and version for the rest of the
+//! run.
+//!
+//! A missing flag the gateway can run without is left out with a start
+//! warning. A missing required one refuses the start. Each flag is matched
+//! as a plain substring, so reworded or reflowed help still works. Help
+//! that can't be read or mentions none of the flags says nothing, and every
+//! flag is then assumed to be supported.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::blocked_tools::{self, GatewayVersion};
+use crate::limits;
+use crate::log_level;
+use crate::tls;
+
+pub const PORT_FLAG: &str = "--port";
+pub const TOKEN_FLAG: &str = "--token";
+/// Only passed for a `gatewayHost` other than the default
+pub const HOST_FLAG: &str = "--host";
+/// Passed instead of `PORT_FLAG` with the `unix` transport
+pub const SOCKET_FLAG: &str = "--socket";
+pub const ALLOW_UNCONFIGURED_FLAG: &str = "--allow-unconfigured";
+
+/// A flag the app may pass to `openclaw gateway`
+#[derive(Debug, Clone, Copy)]
+pub struct GatewayFlag {
+    pub flag: &'static str,
+    /// Whether a start that needs the flag fails without it rather than
+    /// running without it
+    pub required: bool,
+}
+
+const fn flag(flag: &'static str, required: bool) -> GatewayFlag {
+    GatewayFlag { flag, required }
+}
+
+/// Every flag the app passes. Blocked tools and TLS are required: a tool is
+/// never left enabled, nor TLS silently turned off. Limits have fallbacks
+/// of their own.
+pub const FLAGS: &[GatewayFlag] = &[
+    flag(PORT_FLAG, true),
+    flag(TOKEN_FLAG, true),
+    flag(HOST_FLAG, true),
+    flag(SOCKET_FLAG, true),
+    flag(ALLOW_UNCONFIGURED_FLAG, false),
+    flag(blocked_tools::DENY_FLAG, true),
+    flag(limits::SESSIONS_FLAG, false),
+    flag(limits::REQUESTS_FLAG, false),
+    flag(log_level::VERBOSE_FLAG, false),
+    flag(tls::CERT_FLAG, true),
+    flag(tls::KEY_FLAG, true),
+];
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CapabilityError {
+    #[error("The installed gateway ({version}) does not accept {flag}")]
+    Unsupported { flag: String, version: String },
+}
+
+/// Support for one of `FLAGS`, as listed in diagnostics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagSupport {
+    pub flag: String,
+    pub required: bool,
+    /// None when the help couldn't be read
+    pub supported: Option<bool>,
+}
+
+/// The flags of `FLAGS` an installed gateway accepts
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayCapabilities {
+    /// None when nothing is known
+    pub flags: Option<BTreeSet<String>>,
+}
+
+impl GatewayCapabilities {
+    pub fn unknown() -> Self {
+        Self::default()
+    }
+
+    /// The flags `help` mentions. Unknown when it mentions none of them.
+    pub fn from_help(help: &str) -> Self {
+        let flags: BTreeSet<String> = FLAGS
+            .iter()
+            .filter(|flag| mentions(help, flag.flag))
+            .map(|flag| flag.flag.to_string())
+            .collect();
+        Self {
+            flags: (!flags.is_empty()).then_some(flags),
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.flags.is_some()
+    }
+
+    /// Whether the gateway accepts `flag`, None when unknown
+    pub fn supports(&self, flag: &str) -> Option<bool> {
+        self.flags.as_ref().map(|flags| flags.contains(flag))
+    }
+
+    /// Whether `flag` may be passed: it isn't known to be unsupported
+    pub fn allows(&self, flag: &str) -> bool {
+        self.supports(flag) != Some(false)
+    }
+
+    /// `allows` as an error for a required flag, naming `version`
+    pub fn require(
+        &self,
+        flag: &str,
+        version: Option<GatewayVersion>,
+    ) -> Result<(), CapabilityError> {
+        if self.allows(flag) {
+            return Ok(());
+        }
+        Err(CapabilityError::Unsupported {
+            flag: flag.to_string(),
+            version: version.map_or("unknown version".to_string(), |v| v.to_string()),
+        })
+    }
+
+    pub fn table(&self) -> Vec<FlagSupport> {
+        FLAGS
+            .iter()
+            .map(|flag| FlagSupport {
+                flag: flag.flag.to_string(),
+                required: flag.required,
+                supported: self.supports(flag.flag),
+            })
+            .collect()
+    }
+
+    pub fn describe(&self) -> String {
+        if !self.is_known() {
+            return "Could not read the gateway's --help; assuming every flag is supported"
+                .to_string();
+        }
+        let flags: Vec<String> = self
+            .table()
+            .iter()
+            .map(|row| match row.supported {
+                Some(true) => format!("{} yes", row.flag),
+                _ => format!("{} no", row.flag),
+            })
+            .collect();
+        format!("Gateway flags: {}", flags.join(", "))
+    }
+}
+
+/// `flag` appears in `help` as a whole flag, not as part of a longer one
+fn mentions(help: &str, flag: &str) -> bool {
+    let is_flag_char = |c: char| c.is_ascii_alphanumeric() || c == '-';
+    help.match_indices(flag).any(|(at, _)| {
+        let before = help[..at].chars().next_back();
+        let after = help[at + flag.len()..].chars().next();
+        !before.is_some_and(is_flag_char) && !after.is_some_and(is_flag_char)
+    })
+}
+
+fn cache() -> &'static Mutex<HashMap<(PathBuf, GatewayVersion), GatewayCapabilities>> {
+    static CACHE: OnceLock<Mutex<HashMap<(PathBuf, GatewayVersion), GatewayCapabilities>>> =
+        OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// What `program args gateway --help` lists, from the cache when this
+/// binary was asked before at `version`. Only known answers from a gateway
+/// with a version are cached, so a failed probe is retried next start.
+pub fn probe(
+    program: &str,
+    args: &[String],
+    env: &[(String, String)],
+    version: Option<GatewayVersion>,
+    timeout: Duration,
+) -> GatewayCapabilities {
+    let key = version.map(|version| (PathBuf::from(program), version));
+    if let Some(ref key) = key {
+        if let Some(cached) = cache()
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(key).cloned())
+        {
+            return cached;
+        }
+    }
+
+    let mut help_args = args.to_vec();
+    help_args.extend(["gateway".to_string(), "--help".to_string()]);
+    let capabilities = blocked_tools::cli_output(program, &help_args, env, timeout)
+        .map(|help| GatewayCapabilities::from_help(&help))
+        .unwrap_or_default();
+
+    if let (Some(key), true) = (key, capabilities.is_known()) {
+        if let Ok(mut cache) = cache().lock() {
+            cache.insert(key, capabilities.clone());
+        }
+    }
+    capabilities
+}
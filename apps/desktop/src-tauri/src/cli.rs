@@ -0,0 +1,263 @@
+//! Command Line Verbs
+//!
+//! Run with a verb, the app binary talks to an already running instance
+//! over the control socket instead of opening a window:
+//!
+//! ```text
+//! simplestclaw status [--pretty]
+//! simplestclaw start-gateway | stop-gateway | doctor [--pretty]
+//! simplestclaw logs [--tail N] [--pretty]
+//! simplestclaw --headless --status [--pretty]
+//! ```
+//!
+//! It prints the JSON result (or a readable rendering with `--pretty`) and
+//! exits with one of the `EXIT_*` codes. `--headless` alone isn't a verb:
+//! it runs the app without a window, see `headless`.
+
+use serde_json::Value;
+
+use crate::control::{self, ControlRequest, ControlResponse};
+use crate::deep_link;
+use crate::paths::AppPaths;
+
+pub const EXIT_OK: i32 = 0;
+/// The app ran the command and it failed
+pub const EXIT_FAILED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+/// No running app to talk to
+pub const EXIT_UNAVAILABLE: i32 = 3;
+
+/// Runs the app without a window, see `headless`
+pub const HEADLESS_FLAG: &str = "--headless";
+
+const USAGE: &str = "\
+Usage: simplestclaw <command> [--pretty]
+       simplestclaw --headless [--status [--pretty]]
+
+Commands:
+  status           Gateway status
+  start-gateway    Start the gateway
+  stop-gateway     Stop the gateway
+  doctor           Preflight checks of a gateway start
+  logs [--tail N]  Newest gateway output (default 100 lines)
+
+Options:
+  --headless       Run the gateway without a window until interrupted
+  --status         With --headless, status of the running instance";
+
+/// A parsed command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub request: ControlRequest,
+    pub pretty: bool,
+}
+
+/// Parse the arguments after the program name. `Ok(None)` means there's no
+/// verb and the app should start normally.
+pub fn parse_args(args: &[String]) -> Result<Option<Invocation>, String> {
+    let Some(verb) = args.first() else {
+        return Ok(None);
+    };
+    if verb == HEADLESS_FLAG {
+        return parse_headless(&args[1..]);
+    }
+    // Options the OS or a launcher may pass to the GUI, e.g. -psn_0_123 on macOS
+    if verb.starts_with('-') && verb != "--help" && verb != "-h" {
+        return Ok(None);
+    }
+    // A simplestclaw:// link the OS opens the app with
+    if deep_link::is_link(verb) {
+        return Ok(None);
+    }
+
+    let mut pretty = false;
+    let mut tail = None;
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--pretty" => pretty = true,
+            "--tail" if verb == "logs" => {
+                let value = rest.next().ok_or("--tail needs a number of lines")?;
+                let lines = value
+                    .parse()
+                    .map_err(|_| format!("--tail needs a number of lines, got {}", value))?;
+                tail = Some(lines);
+            }
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+
+    let request = match verb.as_str() {
+        "status" => ControlRequest::Status,
+        "start-gateway" => ControlRequest::Start,
+        "stop-gateway" => ControlRequest::Stop,
+        "doctor" => ControlRequest::Doctor,
+        "logs" => ControlRequest::Logs { tail },
+        "--help" | "-h" | "help" => return Err(String::new()),
+        other => return Err(format!("unknown command: {}", other)),
+    };
+    Ok(Some(Invocation { request, pretty }))
+}
+
+/// Whether `args` ask for the headless mode rather than a window
+pub fn is_headless(args: &[String]) -> bool {
+    args.first().is_some_and(|arg| arg == HEADLESS_FLAG)
+}
+
+/// `--headless` options: `--status` asks the running instance, anything
+/// else runs one
+fn parse_headless(args: &[String]) -> Result<Option<Invocation>, String> {
+    let mut status = false;
+    let mut pretty = false;
+    for arg in args {
+        match arg.as_str() {
+            "--status" => status = true,
+            "--pretty" => pretty = true,
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+    if pretty && !status {
+        return Err("--pretty needs --status".to_string());
+    }
+    Ok(status.then_some(Invocation {
+        request: ControlRequest::Status,
+        pretty,
+    }))
+}
+
+/// Run the CLI if `args` has a verb, returning the exit code. `None` means
+/// the app should start normally.
+pub fn run(args: &[String]) -> Option<i32> {
+    let invocation = match parse_args(args) {
+        Ok(invocation) => invocation?,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("simplestclaw: {}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            return Some(if message.is_empty() {
+                EXIT_OK
+            } else {
+                EXIT_USAGE
+            });
+        }
+    };
+
+    let Some(paths) = AppPaths::resolve() else {
+        eprintln!("simplestclaw: could not determine the app data directory");
+        return Some(EXIT_UNAVAILABLE);
+    };
+    let response = match control::send(&control::socket_path(&paths), &invocation.request) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("simplestclaw: the app is not running ({})", e);
+            return Some(EXIT_UNAVAILABLE);
+        }
+    };
+
+    let output = if invocation.pretty {
+        render_pretty(&invocation.request, &response)
+    } else {
+        serde_json::to_string(&response).unwrap_or_default()
+    };
+    if response.ok {
+        println!("{}", output);
+        Some(EXIT_OK)
+    } else {
+        eprintln!("{}", output);
+        Some(EXIT_FAILED)
+    }
+}
+
+/// Human-readable rendering of `response`
+pub fn render_pretty(request: &ControlRequest, response: &ControlResponse) -> String {
+    if !response.ok {
+        let error = response.error.as_ref();
+        let message = error
+            .and_then(|error| error["message"].as_str())
+            .unwrap_or("unknown error");
+        let code = error
+            .and_then(|error| error["code"].as_str())
+            .unwrap_or("error");
+        return format!("Error ({}): {}", code, message);
+    }
+    let result = response.result.clone().unwrap_or_default();
+    match request {
+        ControlRequest::Status => render_status(&result),
+        ControlRequest::Start => match (
+            result["info"]["port"].as_u64(),
+            result["info"]["socketPath"].as_str(),
+        ) {
+            (Some(port), _) => format!("Gateway running on port {}", port),
+            (None, Some(socket)) => format!("Gateway running on socket {}", socket),
+            (None, None) => "Gateway started".to_string(),
+        },
+        ControlRequest::Stop => "Gateway stopped".to_string(),
+        ControlRequest::Focus => "Window shown".to_string(),
+        ControlRequest::OpenUrl { .. } => "Link opened".to_string(),
+        ControlRequest::Doctor => render_checks(&result),
+        ControlRequest::Logs { .. } => result
+            .as_array()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|entry| {
+                        format!(
+                            "{:<5} {}",
+                            entry["level"].as_str().unwrap_or(""),
+                            entry["text"].as_str().unwrap_or("")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default(),
+    }
+}
+
+fn render_status(status: &Value) -> String {
+    if status["running"].as_bool() != Some(true) {
+        return match status["error"].as_str() {
+            Some(error) => format!("Gateway: stopped ({})", error),
+            None => "Gateway: stopped".to_string(),
+        };
+    }
+    let mut line = match status["info"]["socketPath"].as_str() {
+        Some(socket) => format!("Gateway: running on socket {}", socket),
+        None => format!(
+            "Gateway: running on port {}",
+            status["effectivePort"].as_u64().unwrap_or_default()
+        ),
+    };
+    if let Some(pid) = status["pid"].as_u64() {
+        line.push_str(&format!(", pid {}", pid));
+    }
+    if let Some(health) = status["health"].as_str() {
+        line.push_str(&format!(", {}", health));
+    }
+    line
+}
+
+fn render_checks(preview: &Value) -> String {
+    let mut lines: Vec<String> = preview["checks"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|check| {
+            format!(
+                "[{}] {}: {}",
+                if check["passed"].as_bool() == Some(true) {
+                    "ok"
+                } else {
+                    "!!"
+                },
+                check["name"].as_str().unwrap_or(""),
+                check["detail"].as_str().unwrap_or("")
+            )
+        })
+        .collect();
+    for error in preview["errors"].as_array().into_iter().flatten() {
+        lines.push(format!("Error: {}", error.as_str().unwrap_or("")));
+    }
+    lines.join("\n")
+}
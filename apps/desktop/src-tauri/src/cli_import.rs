@@ -0,0 +1,263 @@
+//! Import from the openclaw CLI
+//!
+//! Someone who already runs the `openclaw` CLI has an API key, a model and
+//! a port in its config. `import_from_openclaw` copies those into
+//! config.json so onboarding doesn't ask for them again. It only runs when
+//! called; `get_setup_status` reports `cliConfigDetected` so the UI can
+//! offer it.
+//!
+//! The CLI's config is looked for at `OPENCLAW_CONFIG_PATH`, then
+//! `openclaw.json` in `OPENCLAW_STATE_DIR`, `~/.openclaw` and, from before
+//! the rename, `~/.clawdbot/clawdbot.json`. It is JSON5. Taken from it:
+//!
+//! - the Anthropic key: `env.ANTHROPIC_API_KEY` (or `env.vars.…`), else
+//!   the first Anthropic `api_key` in the main agent's `auth-profiles.json`
+//! - `agents.defaults.model.primary` as `defaultModel`
+//! - `gateway.port` as `gatewayPort`
+//!
+//! A field is only imported when ours is unset, so nothing already
+//! configured is overwritten; the key goes to the keychain like one
+//! entered in the app. The summary says what was imported and why the rest
+//! was skipped. The CLI's files are only ever read.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+
+use crate::config::{self, save_config, Config, Provider};
+use crate::error::AppError;
+use crate::keychain::{self, KeychainError, SecretStore};
+use crate::metrics::AppMetrics;
+use crate::models;
+use crate::settings::MIN_PORT;
+use crate::tray;
+
+/// Variable the CLI reads its config path from
+pub const CONFIG_PATH_ENV: &str = "OPENCLAW_CONFIG_PATH";
+
+/// Variable the CLI reads its state directory from
+pub const STATE_DIR_ENV: &str = "OPENCLAW_STATE_DIR";
+
+/// Where the main agent keeps its credentials, under the state directory
+const AUTH_PROFILES: &str = "agents/main/agent/auth-profiles.json";
+
+/// The port the CLI and the app both default to
+const DEFAULT_PORT: u16 = 18789;
+
+#[derive(Error, Debug)]
+pub enum CliImportError {
+    #[error("No openclaw CLI config was found")]
+    NotFound,
+    #[error("could not read {}: {detail}", path.display())]
+    Unreadable { path: PathBuf, detail: String },
+}
+
+/// What the CLI's config has for us
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliSettings {
+    /// The config file read
+    pub source: PathBuf,
+    pub api_key: Option<String>,
+    pub default_model: Option<String>,
+    pub gateway_port: Option<u16>,
+}
+
+/// A field `import_from_openclaw` left alone, and why
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedField {
+    /// As named in config.json, e.g. `defaultModel`
+    pub field: String,
+    pub reason: String,
+}
+
+/// What `import_from_openclaw` did
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    /// The CLI config read
+    pub source: String,
+    /// Fields written to config.json
+    pub imported: Vec<String>,
+    pub skipped: Vec<SkippedField>,
+}
+
+/// Where the CLI's config may be, most specific first. `env` looks up
+/// environment variables.
+pub fn candidates(home: Option<&Path>, env: &dyn Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let set = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+    if let Some(path) = set(CONFIG_PATH_ENV) {
+        paths.push(PathBuf::from(path));
+    }
+    if let Some(dir) = set(STATE_DIR_ENV) {
+        paths.push(Path::new(&dir).join("openclaw.json"));
+    }
+    if let Some(home) = home {
+        paths.push(home.join(".openclaw").join("openclaw.json"));
+        paths.push(home.join(".clawdbot").join("clawdbot.json"));
+    }
+    paths
+}
+
+/// The first of `candidates` that exists
+pub fn find_in(home: Option<&Path>, env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    candidates(home, env).into_iter().find(|path| path.is_file())
+}
+
+/// The CLI config of this user, if there is one
+pub fn find() -> Option<PathBuf> {
+    find_in(dirs::home_dir().as_deref(), &|name| std::env::var(name).ok())
+}
+
+/// What the CLI config at `path` and the auth profiles next to it have
+pub fn read(path: &Path) -> Result<CliSettings, CliImportError> {
+    let value = read_json5(path)?;
+    let string = |pointer: &str| {
+        value
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let auth_profiles = path.parent().map(|dir| dir.join(AUTH_PROFILES));
+    let api_key = string("/env/ANTHROPIC_API_KEY")
+        .or_else(|| string("/env/vars/ANTHROPIC_API_KEY"))
+        .or_else(|| {
+            // Credentials are optional; an unreadable file just has none
+            let profiles = read_json5(auth_profiles.as_deref()?).ok()?;
+            anthropic_profile_key(&profiles)
+        });
+    let default_model = string("/agents/defaults/model/primary")
+        .or_else(|| string("/agents/defaults/model"));
+    // JSON5 numbers may come as floats
+    let gateway_port = value
+        .pointer("/gateway/port")
+        .and_then(|port| port.as_u64().or_else(|| Some(port.as_f64()? as u64)))
+        .and_then(|port| u16::try_from(port).ok());
+
+    Ok(CliSettings {
+        source: path.to_path_buf(),
+        api_key,
+        default_model,
+        gateway_port,
+    })
+}
+
+fn read_json5(path: &Path) -> Result<Value, CliImportError> {
+    let unreadable = |detail: String| CliImportError::Unreadable {
+        path: path.to_path_buf(),
+        detail,
+    };
+    let text = fs::read_to_string(path).map_err(|e| unreadable(e.to_string()))?;
+    json5::from_str(&text).map_err(|e| unreadable(e.to_string()))
+}
+
+/// The key of the first Anthropic API key profile in `auth-profiles.json`
+fn anthropic_profile_key(profiles: &Value) -> Option<String> {
+    profiles
+        .get("profiles")?
+        .as_object()?
+        .values()
+        .filter(|profile| profile.get("provider").and_then(Value::as_str) == Some("anthropic"))
+        .filter(|profile| profile.get("type").and_then(Value::as_str) == Some("api_key"))
+        .find_map(|profile| {
+            profile
+                .get("key")
+                .or_else(|| profile.get("apiKey"))
+                .and_then(Value::as_str)
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+        })
+}
+
+/// Copy `cli` into `config` where it has nothing yet, the key through
+/// `store`. Save afterwards.
+pub fn apply(
+    config: &mut Config,
+    cli: &CliSettings,
+    store: &dyn SecretStore,
+) -> Result<ImportSummary, KeychainError> {
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    let mut skip = |field: &str, reason: &str| {
+        skipped.push(SkippedField {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+
+    match cli.api_key {
+        None => skip("anthropicApiKey", "Not in the CLI config"),
+        // Resolved by the CLI from its environment, not here
+        Some(ref key) if key.starts_with("${") => {
+            skip("anthropicApiKey", "The CLI config refers to a variable")
+        }
+        Some(_) if config.provider_key(Provider::Anthropic).is_some() => {
+            skip("anthropicApiKey", "Already set")
+        }
+        Some(ref key) => {
+            config.set_provider_key(Provider::Anthropic, Some(key.clone()), store)?;
+            imported.push("anthropicApiKey".to_string());
+        }
+    }
+
+    match cli.default_model {
+        None => skip("defaultModel", "Not in the CLI config"),
+        Some(_) if config.default_model.is_some() => skip("defaultModel", "Already set"),
+        Some(ref model) if models::validate("defaultModel", model).is_err() => {
+            skip("defaultModel", "Not a model name")
+        }
+        Some(ref model) => {
+            config.default_model = Some(model.clone());
+            imported.push("defaultModel".to_string());
+        }
+    }
+
+    match cli.gateway_port {
+        None => skip("gatewayPort", "Not in the CLI config"),
+        Some(port) if port == config.gateway_port => skip("gatewayPort", "Already the same"),
+        Some(_) if config.gateway_port != DEFAULT_PORT => skip("gatewayPort", "Already set"),
+        Some(port) if port < MIN_PORT => skip("gatewayPort", "A privileged port"),
+        Some(port) => {
+            config.gateway_port = port;
+            imported.push("gatewayPort".to_string());
+        }
+    }
+
+    Ok(ImportSummary {
+        source: cli.source.display().to_string(),
+        imported,
+        skipped,
+    })
+}
+
+/// Copy the API key, default model and gateway port from the openclaw
+/// CLI's config where config.json has none. Fails with
+/// `cli_config_not_found` when there is no CLI config.
+#[tauri::command]
+pub async fn import_from_openclaw(app: AppHandle) -> Result<ImportSummary, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let path = find().ok_or(CliImportError::NotFound)?;
+        let cli = read(&path)?;
+        let mut config = Config::load()?;
+        let summary = apply(&mut config, &cli, keychain::system())?;
+        if !summary.imported.is_empty() {
+            save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+            println!(
+                "[config] Imported from {}: {}",
+                summary.source,
+                summary.imported.join(", ")
+            );
+            tray::config_changed(&app);
+            config::emit_changed(&app);
+        }
+        Ok(summary)
+    })
+    .await?
+}
@@ -1,8 +1,43 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
 use thiserror::Error;
 
+use crate::anthropic_endpoint::{self, AnthropicEndpoint};
+use crate::api_key;
+use crate::blocked_tools;
+use crate::config_watch;
+use crate::error::AppError;
+use crate::external::{self, ExternalGateway};
+use crate::gateway_options;
+use crate::gateway_socket;
+use crate::keychain::{self, KeychainError, SecretStore};
+use crate::locale;
+use crate::log_level;
+use crate::login_item;
+use crate::metrics::AppMetrics;
+use crate::migrations::{self, MigrationError, CONFIG_VERSION};
+use crate::models;
+use crate::overrides::{self, Applied};
+use crate::paths::{self, AppPaths};
+use crate::port;
+use crate::proxy;
+use crate::resolve;
+use crate::sandbox;
+use crate::sealed_keys;
+use crate::settings::MIN_PORT;
+use crate::sidecar::{SidecarManager, StartResult};
+use crate::spend;
+use crate::stored_keys;
+use crate::tls;
+use crate::tray;
+
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error("Failed to get config directory")]
@@ -11,138 +46,2135 @@ pub enum ConfigError {
     Io(#[from] std::io::Error),
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+    /// The config dir can't be written, e.g. on a read-only mount
+    #[error("{} is read-only", path.display())]
+    ReadOnly { path: PathBuf },
+    /// The config dir can't be created, e.g. in a network home that isn't
+    /// mounted
+    #[error("Could not create {}: {detail}", path.display())]
+    Unavailable { path: PathBuf, detail: String },
+    #[error("{variable}={value}: {detail}")]
+    InvalidOverride {
+        variable: String,
+        value: String,
+        detail: String,
+    },
+}
+
+/// How an unreadable config.json was got past on load, for the UI to
+/// warn about
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigRecovery {
+    /// Why config.json couldn't be read
+    pub detail: String,
+    /// Whether config.json.bak was used; otherwise the settings are back
+    /// to their defaults
+    pub from_backup: bool,
+    /// Where the unreadable file was moved
+    pub moved_to: PathBuf,
+}
+
+/// The last recovery, until the UI takes it
+static RECOVERY: Mutex<Option<ConfigRecovery>> = Mutex::new(None);
+
+/// config.json was readable by others and has been made private, for the
+/// UI to warn about since the key may have been read
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionsFix {
+    pub path: PathBuf,
+    /// The mode it had, in octal like `644`
+    pub previous_mode: String,
+}
+
+/// The last fix, until the UI takes it
+static PERMISSIONS_FIX: Mutex<Option<PermissionsFix>> = Mutex::new(None);
+
+/// Make `path` private to its owner when others could access it, and say
+/// what it was. Nothing to do when there is no such file, or on Windows.
+pub fn restrict_permissions(path: &Path) -> io::Result<Option<PermissionsFix>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let previous = paths::restrict_mode(path, paths::PRIVATE_FILE_MODE)?;
+    Ok(previous.map(|mode| PermissionsFix {
+        path: path.to_path_buf(),
+        previous_mode: format!("{:o}", mode),
+    }))
+}
+
+/// Serializes saves, which share the temp file
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// `config.json.bak` for `config.json`
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// Name of the file next to config.json the secrets are kept in
+pub const SECRETS_FILE: &str = "secrets.json";
+
+/// Fields of config.json kept in `SECRETS_FILE` instead, so a config
+/// directory synced or shared with a dotfile repo doesn't carry them along.
+/// Only written when not in the keychain.
+pub const SECRET_FIELDS: [&str; 3] = ["anthropicApiKey", "gatewayToken", "providerKeys"];
+
+/// The key field of each stored key in `apiKeys` and of each profile in
+/// `profiles`, kept in `SECRETS_FILE` too, by label and by name
+pub const NESTED_SECRET_FIELDS: [(&str, &str); 2] = [("apiKeys", "key"), ("profiles", "apiKey")];
+
+/// `secrets.json` next to `config.json`
+pub fn secrets_path(path: &Path) -> PathBuf {
+    path.with_file_name(SECRETS_FILE)
+}
+
+/// Whether `name` is kept in `SECRETS_FILE`
+fn is_secret(name: &str) -> bool {
+    SECRET_FIELDS.contains(&name) || NESTED_SECRET_FIELDS.iter().any(|(field, _)| *field == name)
+}
+
+/// The stored keys or profiles in `entries`, by label or by name
+fn named_entries(
+    entries: Option<&mut serde_json::Value>,
+) -> Vec<(String, &mut serde_json::Map<String, serde_json::Value>)> {
+    match entries {
+        Some(serde_json::Value::Array(keys)) => keys
+            .iter_mut()
+            .filter_map(serde_json::Value::as_object_mut)
+            .filter_map(|key| {
+                let label = key.get("label")?.as_str()?.to_string();
+                Some((label, key))
+            })
+            .collect(),
+        Some(serde_json::Value::Object(profiles)) => profiles
+            .iter_mut()
+            .filter_map(|(name, profile)| Some((name.clone(), profile.as_object_mut()?)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Take `SECRET_FIELDS` and `NESTED_SECRET_FIELDS` out of config.json
+/// `value`, returning those that are set
+fn split_secrets(value: &mut serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    let mut secrets = serde_json::Map::new();
+    let Some(fields) = value.as_object_mut() else {
+        return secrets;
+    };
+    for name in SECRET_FIELDS {
+        match fields.remove(name) {
+            Some(serde_json::Value::Null) | None => {}
+            Some(serde_json::Value::Object(keys)) if keys.is_empty() => {}
+            Some(secret) => {
+                secrets.insert(name.to_string(), secret);
+            }
+        }
+    }
+    for (field, key) in NESTED_SECRET_FIELDS {
+        let mut keys = serde_json::Map::new();
+        for (name, entry) in named_entries(fields.get_mut(field)) {
+            match entry.remove(key) {
+                Some(serde_json::Value::Null) | None => {}
+                Some(secret) => {
+                    keys.insert(name, secret);
+                }
+            }
+        }
+        if !keys.is_empty() {
+            secrets.insert(field.to_string(), serde_json::Value::Object(keys));
+        }
+    }
+    secrets
+}
+
+/// Add `more` to `secrets`, replacing what both have; a provider, stored
+/// key or profile at a time for those kept by name
+fn extend_secrets(
+    secrets: &mut serde_json::Map<String, serde_json::Value>,
+    more: serde_json::Map<String, serde_json::Value>,
+) {
+    for (name, secret) in more {
+        let by_name = name == "providerKeys"
+            || NESTED_SECRET_FIELDS.iter().any(|(field, _)| *field == name);
+        if let (true, Some(serde_json::Value::Object(keys)), serde_json::Value::Object(more)) =
+            (by_name, secrets.get_mut(&name), &secret)
+        {
+            keys.extend(more.clone());
+            continue;
+        }
+        secrets.insert(name, secret);
+    }
+}
+
+/// Put `secrets` back into config.json `value`, as `split_secrets` took
+/// them out
+fn merge_secrets(
+    value: &mut serde_json::Value,
+    secrets: serde_json::Map<String, serde_json::Value>,
+) {
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+    for (name, secret) in secrets {
+        let nested = NESTED_SECRET_FIELDS.iter().find(|(field, _)| *field == name);
+        let Some((_, key)) = nested else {
+            fields.insert(name, secret);
+            continue;
+        };
+        let serde_json::Value::Object(keys) = secret else {
+            continue;
+        };
+        for (entry_name, entry) in named_entries(fields.get_mut(&name)) {
+            if let Some(secret) = keys.get(&entry_name) {
+                entry.insert(key.to_string(), secret.clone());
+            }
+        }
+    }
+}
+
+/// The secrets in `path`, empty when there is no such file. One that
+/// doesn't parse is reported and taken for empty, so the key is asked for
+/// again rather than config.json taken for corrupt.
+fn read_secrets(path: &Path) -> Result<serde_json::Map<String, serde_json::Value>, ConfigError> {
+    if !path.exists() {
+        return Ok(serde_json::Map::new());
+    }
+    match serde_json::from_slice(&fs::read(path)?) {
+        Ok(serde_json::Value::Object(mut fields)) => {
+            fields.retain(|name, secret| is_secret(name) && !secret.is_null());
+            Ok(fields)
+        }
+        Ok(_) => {
+            eprintln!("[config] {} is not a JSON object, ignoring it", path.display());
+            Ok(serde_json::Map::new())
+        }
+        Err(e) => {
+            eprintln!("[config] {} is unreadable, ignoring it: {}", path.display(), e);
+            Ok(serde_json::Map::new())
+        }
+    }
+}
+
+/// Write `contents` to a private temp file next to `path`, for renaming
+/// over it
+fn stage(path: &Path, contents: &str) -> io::Result<PathBuf> {
+    let staged = with_suffix(path, ".tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(paths::PRIVATE_FILE_MODE);
+    }
+    let mut file = options.open(&staged)?;
+    // The mode only applies to a new file, not one left by a crash
+    paths::restrict_mode(&staged, paths::PRIVATE_FILE_MODE)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    Ok(staged)
+}
+
+/// `e`, from writing in `dir`, as `ConfigError::ReadOnly` when that is
+/// what it means
+fn write_error(dir: &Path, e: io::Error) -> ConfigError {
+    match e.kind() {
+        io::ErrorKind::ReadOnlyFilesystem | io::ErrorKind::PermissionDenied => {
+            ConfigError::ReadOnly {
+                path: dir.to_path_buf(),
+            }
+        }
+        _ => ConfigError::Io(e),
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// `path` upgraded to the current version and parsed with the secrets
+/// from `secrets_path`, with the version it had and whether config.json
+/// still held secrets itself, as versions before secrets.json wrote it;
+/// None if there is no such file
+fn read_file(path: &Path) -> Result<Option<(Config, u32, bool)>, ConfigError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut value: serde_json::Value = serde_json::from_slice(&fs::read(path)?)?;
+    let version = migrations::migrate(&mut value)?;
+    // secrets.json wins over what config.json still has
+    let mut secrets = split_secrets(&mut value);
+    let secrets_in_config = !secrets.is_empty();
+    extend_secrets(&mut secrets, read_secrets(&secrets_path(path))?);
+    merge_secrets(&mut value, secrets);
+    let unreadable_keys = sealed_keys::open_keys(&mut value, sealed_keys::machine_key());
+    let mut config: Config = serde_json::from_value(value)?;
+    if !unreadable_keys.is_empty() {
+        eprintln!(
+            "[config] Keys sealed on another machine can't be read: {}",
+            unreadable_keys.keys().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+    config.unreadable_keys = unreadable_keys;
+    Ok(Some((config, version, secrets_in_config)))
+}
+
+/// Information about app data stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppDataInfo {
+    pub config_path: Option<String>,
+    pub data_path: Option<String>,
+    pub total_size_bytes: u64,
+    pub total_size_formatted: String,
+}
+
+/// Supported AI providers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Anthropic,
+    Openai,
+    Google,
+    Openrouter,
+}
+
+impl Default for Provider {
+    fn default() -> Self {
+        Provider::Anthropic
+    }
+}
+
+impl Provider {
+    pub const ALL: [Provider; 4] = [
+        Provider::Anthropic,
+        Provider::Openai,
+        Provider::Google,
+        Provider::Openrouter,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::Anthropic => "anthropic",
+            Provider::Openai => "openai",
+            Provider::Google => "google",
+            Provider::Openrouter => "openrouter",
+        }
+    }
+
+    /// Case-insensitive
+    pub fn parse(provider: &str) -> Result<Self, AppError> {
+        Provider::ALL
+            .into_iter()
+            .find(|known| known.as_str() == provider.to_lowercase())
+            .ok_or_else(|| AppError::UnknownProvider {
+                provider: provider.to_string(),
+            })
+    }
+
+    /// Variable the gateway reads this provider's key from
+    pub fn env_var(&self) -> &'static str {
+        match self {
+            Provider::Anthropic => "ANTHROPIC_API_KEY",
+            Provider::Openai => "OPENAI_API_KEY",
+            Provider::Google => "GOOGLE_API_KEY",
+            Provider::Openrouter => "OPENROUTER_API_KEY",
+        }
+    }
+}
+
+/// How the gateway takes connections
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// `gatewayPort` on `gatewayHost`
+    Tcp,
+    /// A Unix domain socket only its owner can connect to (see
+    /// `gateway_socket`)
+    Unix,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::Tcp
+    }
+}
+
+impl Transport {
+    pub const ALL: [Transport; 2] = [Transport::Tcp, Transport::Unix];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transport::Tcp => "tcp",
+            Transport::Unix => "unix",
+        }
+    }
+
+    /// Case-insensitive
+    pub fn parse(transport: &str) -> Option<Self> {
+        Transport::ALL
+            .into_iter()
+            .find(|known| known.as_str() == transport.to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    /// Schema version of the file (see `migrations`); always
+    /// `CONFIG_VERSION` once loaded
+    #[serde(default)]
+    pub config_version: u32,
+    /// What the environment overrides, never saved
+    #[serde(skip)]
+    pub overrides: Applied,
+    /// Keys in the file sealed on another machine, by JSON pointer, left
+    /// out of the fields below and written back as they were (see
+    /// `sealed_keys`)
+    #[serde(skip)]
+    pub unreadable_keys: BTreeMap<String, serde_json::Value>,
+    /// The selected AI provider
+    #[serde(default)]
+    pub provider: Provider,
+    /// API key (used for the selected provider). Filled in from the system
+    /// keychain on load when `api_key_in_keychain`, and then never saved
+    /// to the file (see `keychain`).
+    pub anthropic_api_key: Option<String>,
+    /// The API key is in the system keychain
+    #[serde(default)]
+    pub api_key_in_keychain: bool,
+    /// Keep the API key in secrets.json next to this file instead of the
+    /// system keychain, for machines without one such as headless Linux
+    #[serde(default)]
+    pub store_api_key_in_file: bool,
+    /// Keys for providers besides `provider`, which the gateway can route
+    /// to as well. Kept like the API key, which is the one used for
+    /// `provider` itself.
+    #[serde(default)]
+    pub provider_keys: BTreeMap<Provider, String>,
+    /// Providers whose key in `provider_keys` is in the system keychain
+    #[serde(default)]
+    pub provider_keys_in_keychain: Vec<Provider>,
+    /// Saved settings to switch to, by name. The active profile isn't
+    /// among them: its settings are the API key, `gatewayPort` and
+    /// `gatewayExtraArgs` above (see `profiles`).
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// Name of the profile in use; `default` when unset
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    /// Anthropic keys to pick the one in use from, by label (see
+    /// `stored_keys`)
+    #[serde(default)]
+    pub api_keys: Vec<NamedKey>,
+    /// Label of the stored key in use
+    #[serde(default)]
+    pub active_api_key: Option<String>,
+    #[serde(default = "default_port")]
+    pub gateway_port: u16,
+    /// Address the gateway listens on. Anything but loopback exposes it to
+    /// the LAN (see `connection`).
+    #[serde(default = "default_gateway_host")]
+    pub gateway_host: String,
+    /// `unix` listens on a socket instead of `gateway_port`, for machines
+    /// shared with other users
+    #[serde(default)]
+    pub transport: Transport,
+    /// The socket of the `unix` transport, instead of `gateway.sock` in
+    /// the runtime dir
+    #[serde(default)]
+    pub gateway_socket_path: Option<PathBuf>,
+    /// Start the gateway on a free port when `gateway_port` is taken,
+    /// instead of failing with `port_in_use`
+    #[serde(default)]
+    pub auto_select_port: bool,
+    #[serde(default = "default_auto_start")]
+    pub auto_start_gateway: bool,
+    /// Hide the window to the tray on close instead of stopping the
+    /// gateway. Also read as `minimizeToTray`.
+    #[serde(default, alias = "minimizeToTray")]
+    pub keep_running_in_background: bool,
+    /// Start the app when the user logs in. Changed with
+    /// `set_launch_at_login`; `get_config` reports the system's own
+    /// registration (see `login_item`).
+    #[serde(default)]
+    pub launch_at_login: bool,
+    /// Cleanup of old logs and crash reports
+    #[serde(default)]
+    pub log_retention: LogRetention,
+    /// Write the gateway's output to `gateway.log` in the logs dir as well
+    /// as keeping it in memory. Takes effect on the next launch.
+    #[serde(default = "default_true")]
+    pub file_logging_enabled: bool,
+    /// Size at which `gateway.log` is rotated
+    #[serde(default = "default_gateway_log_max_file_mb")]
+    pub gateway_log_max_file_mb: u64,
+    /// Rotated gateway logs kept next to `gateway.log`
+    #[serde(default = "default_gateway_log_keep_files")]
+    pub gateway_log_keep_files: usize,
+    /// Memory limit for the gateway. Passed to node as `--max-old-space-size`
+    /// and enforced by the watchdog, which restarts the gateway when exceeded.
+    #[serde(default)]
+    pub gateway_max_memory_mb: Option<u64>,
+    /// Start node with `--openssl-legacy-provider` in `NODE_OPTIONS`, the
+    /// workaround for OpenSSL 3 rejecting old algorithms. Never turned on
+    /// automatically.
+    #[serde(default)]
+    pub openssl_legacy_provider: bool,
+    /// Run the gateway at reduced CPU priority so it doesn't compete with
+    /// foreground apps
+    #[serde(default)]
+    pub gateway_low_priority: bool,
+    /// Restart the gateway when requests go unanswered for this long while
+    /// it still accepts connections. 0 disables the check.
+    #[serde(default = "default_heartbeat_timeout_secs")]
+    pub gateway_heartbeat_timeout_secs: u64,
+    /// How long a start waits for the gateway to accept connections before
+    /// giving up and stopping it, between `MIN_STARTUP_TIMEOUT_SECS` and
+    /// `MAX_STARTUP_TIMEOUT_SECS`. A start can be given its own.
+    #[serde(default = "default_startup_timeout_secs")]
+    pub gateway_startup_timeout_secs: u64,
+    /// How many more times a start tries when the spawn fails or the
+    /// gateway doesn't become ready for a reason that may pass, at most
+    /// `MAX_START_RETRIES`
+    #[serde(default = "default_start_max_retries")]
+    pub start_max_retries: u32,
+    /// Start the gateway again when it crashes (see `supervisor`)
+    #[serde(default = "default_auto_restart_gateway")]
+    pub auto_restart_gateway: bool,
+    /// Restart a running gateway when config.json is changed outside the
+    /// app in a setting it was started with (see `config_watch`)
+    #[serde(default)]
+    pub auto_restart_on_config_change: bool,
+    /// Restart a running gateway that stopped answering after the machine
+    /// slept (see `wake`)
+    #[serde(default = "default_restart_on_wake")]
+    pub restart_on_wake: bool,
+    /// Restart the gateway once it has run this long, when it is idle, to
+    /// shed memory node leaks over days (see `scheduled_restart`)
+    #[serde(default)]
+    pub auto_restart_interval_hours: Option<u32>,
+    /// Minutes without an answered request after which a gateway with
+    /// clients still connected counts as idle for a scheduled restart
+    #[serde(default = "default_auto_restart_quiet_minutes")]
+    pub auto_restart_quiet_minutes: u32,
+    /// Ask the npm registry once a day whether a newer openclaw is out
+    /// (see `openclaw_update`)
+    #[serde(default = "default_check_openclaw_updates")]
+    pub check_openclaw_updates: bool,
+    /// Unix timestamp (ms) of the last successful update check
+    #[serde(default)]
+    pub last_openclaw_update_check: Option<i64>,
+    /// How long a stopped gateway gets to shut down before it is killed
+    #[serde(default = "default_stop_grace_secs")]
+    pub gateway_stop_grace_secs: u64,
+    /// Run the gateway in a dedicated workspace directory (see `sandbox`)
+    #[serde(default)]
+    pub sandbox_workspace: bool,
+    /// Directory the gateway works in, also the sandbox workspace instead
+    /// of the one under app data (see `sandbox`)
+    #[serde(default)]
+    pub workspace_dir: Option<PathBuf>,
+    /// Where the gateway keeps sessions and memory, instead of
+    /// `gateway-state` under app data (see `gateway_state`)
+    #[serde(default)]
+    pub gateway_state_dir: Option<PathBuf>,
+    /// Node.js binary to run the gateway with, instead of searching for
+    /// one (see `resolve`)
+    #[serde(default)]
+    pub node_path: Option<PathBuf>,
+    /// openclaw CLI to run directly, e.g. a build from a local checkout,
+    /// instead of through node and npx
+    #[serde(default)]
+    pub openclaw_path: Option<PathBuf>,
+    /// Run `npx openclaw` even when the app bundles openclaw, for testing
+    /// a global install (see `bundled`)
+    #[serde(default)]
+    pub prefer_global_openclaw: bool,
+    /// Start openclaw releases older than `MIN_OPENCLAW_VERSION`, for forks
+    /// numbered differently (see `openclaw_version`)
+    #[serde(default)]
+    pub skip_openclaw_version_check: bool,
+    /// Locale for the gateway, e.g. `en-GB`; the system's when unset
+    /// (see `locale`)
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// IANA timezone for the gateway, e.g. `Europe/Berlin`; the system's
+    /// when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Model the gateway's agent uses; openclaw's choice when unset
+    /// (see `models`)
+    #[serde(default)]
+    pub default_model: Option<String>,
+    /// Model for quick background work like titles
+    #[serde(default)]
+    pub small_model: Option<String>,
+    /// The gateway's own log level, its default when unset (see
+    /// `log_level`)
+    #[serde(default)]
+    pub gateway_log_level: Option<String>,
+    /// HTTP proxy for the gateway and the API key check; the app's own
+    /// proxy variables when unset (see `proxy`)
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Anthropic-compatible API the gateway and the API key check talk
+    /// to instead of Anthropic's, e.g. a company LLM proxy (see
+    /// `anthropic_endpoint`)
+    #[serde(default)]
+    pub anthropic_base_url: Option<String>,
+    /// Header the key goes in instead of `x-api-key`, for proxies that
+    /// want their own
+    #[serde(default)]
+    pub anthropic_auth_header: Option<String>,
+    /// Serve `wss://` with this certificate and key (see `tls`)
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Connect to this gateway instead of starting one (see `external`)
+    #[serde(default)]
+    pub external_gateway: Option<ExternalGateway>,
+    /// Passed to the gateway after the app's own arguments; the flags the
+    /// app manages are refused (see `gateway_options`)
+    #[serde(default)]
+    pub gateway_extra_args: Vec<String>,
+    /// Set for the gateway after the app's own variables, also in clean
+    /// mode; the token and API key variables are refused
+    #[serde(default)]
+    pub gateway_extra_env: BTreeMap<String, String>,
+    /// Start the gateway with only an allow-listed environment plus what
+    /// the app sets explicitly, instead of inheriting everything
+    #[serde(default)]
+    pub clean_environment: bool,
+    /// Pass SSH agent, GPG and git identity variables from the login shell
+    /// to the gateway. Extends the allow-list in clean-environment mode.
+    #[serde(default)]
+    pub forward_dev_env: bool,
+    /// Also embed the token in `GatewayInfo.url` as `?token=`, for
+    /// WebSocket clients that can't send it separately
+    #[serde(default)]
+    pub token_in_url: bool,
+    /// Let `get_connection_string` hand out the gateway's URL and token,
+    /// for other tools to connect with
+    #[serde(default)]
+    pub allow_external_clients: bool,
+    /// Start the gateway with the same token every time instead of a new
+    /// one, so saved connections keep working; `regenerate_token` replaces
+    /// it
+    #[serde(default)]
+    pub persist_gateway_token: bool,
+    /// The token kept with `persistGatewayToken`, stored like the API key
+    #[serde(default)]
+    pub gateway_token: Option<String>,
+    /// `gateway_token` is in the system keychain
+    #[serde(default)]
+    pub gateway_token_in_keychain: bool,
+    /// Once the gateway is ready, connect with its token and make one
+    /// request, failing the start if the token is rejected
+    #[serde(default = "default_self_test_on_start")]
+    pub self_test_on_start: bool,
+    /// Gateway tools to disable, by id (see `blocked_tools`). Takes effect
+    /// on the next start.
+    #[serde(default)]
+    pub blocked_tools: Vec<String>,
+    /// Run the gateway as this user (Unix, needs the app to run as root;
+    /// see `run_as`)
+    #[serde(default)]
+    pub run_as_user: Option<String>,
+    /// Cap on sessions running at once (see `limits`)
+    #[serde(default)]
+    pub max_concurrent_sessions: Option<u32>,
+    /// Cap on model requests in flight at once
+    #[serde(default)]
+    pub max_concurrent_requests: Option<u32>,
+    /// Dollars a gateway may spend on the API (see `spend`)
+    #[serde(default)]
+    pub spend_limit_usd: Option<f64>,
+    /// Share of `spend_limit_usd` at which the app warns
+    #[serde(default = "default_warn_at_fraction")]
+    pub warn_at_fraction: f64,
+    /// Stop the gateway once its estimated spend reaches the limit
+    #[serde(default)]
+    pub hard_stop_on_limit: bool,
+    /// Warn at start when less than this is free where the gateway
+    /// writes. 0 disables the warning; below `paths::HARD_MIN_FREE_BYTES`
+    /// the start is refused either way.
+    #[serde(default = "default_min_free_disk_mb")]
+    pub min_free_disk_mb: u64,
+    /// Emit `gateway://metrics` this often while a gateway runs (see
+    /// `gateway_metrics`)
+    #[serde(default)]
+    pub metrics_interval_secs: Option<u64>,
+    /// Serve metrics for Prometheus over HTTP (see `prometheus`)
+    #[serde(default)]
+    pub metrics_exporter: MetricsExporter,
+    /// Serve `/healthz` and `/readyz` for external supervisors (see `health`)
+    #[serde(default)]
+    pub health_endpoint: HealthEndpoint,
+}
+
+/// Where the Prometheus exporter listens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsExporter {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Loopback unless set, the metrics are not authenticated
+    #[serde(default = "default_loopback")]
+    pub bind_address: String,
+    #[serde(default = "default_metrics_port")]
+    pub port: u16,
+}
+
+impl Default for MetricsExporter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: default_loopback(),
+            port: default_metrics_port(),
+        }
+    }
+}
+
+/// Where the health endpoint listens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthEndpoint {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_loopback")]
+    pub bind_address: String,
+    #[serde(default = "default_health_port")]
+    pub port: u16,
+}
+
+impl Default for HealthEndpoint {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: default_loopback(),
+            port: default_health_port(),
+        }
+    }
+}
+
+/// A stored API key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct NamedKey {
+    pub label: String,
+    /// Unix timestamp (ms) it was added
+    pub created_at: i64,
+    /// The key as `redact_api_key` shows it, to tell keys apart without
+    /// reading them
+    #[serde(default)]
+    pub hint: String,
+    /// Only in the file with `storeApiKeyInFile`; otherwise in the system
+    /// keychain under `keychain::stored_key_account`
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub key_in_keychain: bool,
+}
+
+/// A profile not in use
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileConfig {
+    /// Only in the file with `storeApiKeyInFile`; otherwise in the system
+    /// keychain under the profile's own account
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub api_key_in_keychain: bool,
+    #[serde(default = "default_port")]
+    pub gateway_port: u16,
+    #[serde(default)]
+    pub gateway_extra_args: Vec<String>,
+}
+
+/// Certificate and private key the gateway serves TLS with, both PEM
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// Proxies the gateway is started with
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    #[serde(default)]
+    pub http_proxy: Option<String>,
+    #[serde(default)]
+    pub https_proxy: Option<String>,
+    /// Comma-separated hosts reached directly, like `localhost,.corp`
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+}
+
+/// How long logs and crash reports are kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogRetention {
+    /// Set to false to keep everything, e.g. for forensic setups
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Rotated log files older than this are deleted
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+    /// Upper bound for the total size of the logs directory
+    #[serde(default = "default_max_logs_mb")]
+    pub max_logs_mb: u64,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: default_retention_days(),
+            max_logs_mb: default_max_logs_mb(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_retention_days() -> u32 {
+    14
+}
+
+fn default_max_logs_mb() -> u64 {
+    200
+}
+
+fn default_gateway_log_max_file_mb() -> u64 {
+    5
+}
+
+fn default_gateway_log_keep_files() -> usize {
+    5
+}
+
+fn default_heartbeat_timeout_secs() -> u64 {
+    60
+}
+
+/// Bounds of `gateway_startup_timeout_secs`
+pub const MIN_STARTUP_TIMEOUT_SECS: u64 = 3;
+pub const MAX_STARTUP_TIMEOUT_SECS: u64 = 120;
+
+fn default_startup_timeout_secs() -> u64 {
+    15
+}
+
+/// Check `secs` can be a startup timeout, as `field`
+pub fn check_startup_timeout(field: &str, secs: u64) -> Result<u64, AppError> {
+    if !(MIN_STARTUP_TIMEOUT_SECS..=MAX_STARTUP_TIMEOUT_SECS).contains(&secs) {
+        return Err(AppError::InvalidSetting {
+            field: field.to_string(),
+            detail: format!(
+                "Must be between {} and {} seconds.",
+                MIN_STARTUP_TIMEOUT_SECS, MAX_STARTUP_TIMEOUT_SECS
+            ),
+        });
+    }
+    Ok(secs)
+}
+
+/// Upper bound of `start_max_retries`
+pub const MAX_START_RETRIES: u32 = 5;
+
+fn default_start_max_retries() -> u32 {
+    2
+}
+
+fn default_auto_restart_gateway() -> bool {
+    true
+}
+
+/// `gateway_stop_grace_secs` when unset
+pub const DEFAULT_STOP_GRACE_SECS: u64 = 5;
+
+fn default_stop_grace_secs() -> u64 {
+    DEFAULT_STOP_GRACE_SECS
+}
+
+fn default_self_test_on_start() -> bool {
+    true
+}
+
+fn default_restart_on_wake() -> bool {
+    true
+}
+
+fn default_auto_restart_quiet_minutes() -> u32 {
+    15
+}
+
+fn default_check_openclaw_updates() -> bool {
+    true
+}
+
+fn default_warn_at_fraction() -> f64 {
+    0.8
+}
+
+fn default_min_free_disk_mb() -> u64 {
+    1024
+}
+
+fn default_loopback() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_metrics_port() -> u16 {
+    9464
+}
+
+fn default_health_port() -> u16 {
+    9465
+}
+
+/// `gateway_host` when unset: loopback only
+pub const DEFAULT_GATEWAY_HOST: &str = "127.0.0.1";
+
+fn default_gateway_host() -> String {
+    DEFAULT_GATEWAY_HOST.to_string()
+}
+
+fn default_port() -> u16 {
+    port::DEFAULT_PORT
+}
+
+fn default_auto_start() -> bool {
+    true
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            config_version: CONFIG_VERSION,
+            overrides: Applied::default(),
+            unreadable_keys: BTreeMap::new(),
+            provider: Provider::default(),
+            anthropic_api_key: None,
+            api_key_in_keychain: false,
+            store_api_key_in_file: false,
+            provider_keys: BTreeMap::new(),
+            provider_keys_in_keychain: Vec::new(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            api_keys: Vec::new(),
+            active_api_key: None,
+            gateway_port: default_port(),
+            gateway_host: default_gateway_host(),
+            transport: Transport::default(),
+            gateway_socket_path: None,
+            auto_select_port: false,
+            auto_start_gateway: default_auto_start(),
+            keep_running_in_background: false,
+            launch_at_login: false,
+            log_retention: LogRetention::default(),
+            file_logging_enabled: true,
+            gateway_log_max_file_mb: default_gateway_log_max_file_mb(),
+            gateway_log_keep_files: default_gateway_log_keep_files(),
+            gateway_max_memory_mb: None,
+            openssl_legacy_provider: false,
+            gateway_low_priority: false,
+            gateway_heartbeat_timeout_secs: default_heartbeat_timeout_secs(),
+            gateway_startup_timeout_secs: default_startup_timeout_secs(),
+            start_max_retries: default_start_max_retries(),
+            auto_restart_gateway: default_auto_restart_gateway(),
+            auto_restart_on_config_change: false,
+            restart_on_wake: default_restart_on_wake(),
+            auto_restart_interval_hours: None,
+            auto_restart_quiet_minutes: default_auto_restart_quiet_minutes(),
+            check_openclaw_updates: default_check_openclaw_updates(),
+            last_openclaw_update_check: None,
+            gateway_stop_grace_secs: default_stop_grace_secs(),
+            sandbox_workspace: false,
+            workspace_dir: None,
+            gateway_state_dir: None,
+            node_path: None,
+            openclaw_path: None,
+            prefer_global_openclaw: false,
+            skip_openclaw_version_check: false,
+            locale: None,
+            timezone: None,
+            default_model: None,
+            small_model: None,
+            gateway_log_level: None,
+            proxy: None,
+            anthropic_base_url: None,
+            anthropic_auth_header: None,
+            tls: None,
+            external_gateway: None,
+            gateway_extra_args: Vec::new(),
+            gateway_extra_env: BTreeMap::new(),
+            clean_environment: false,
+            forward_dev_env: false,
+            token_in_url: false,
+            allow_external_clients: false,
+            persist_gateway_token: false,
+            gateway_token: None,
+            gateway_token_in_keychain: false,
+            self_test_on_start: default_self_test_on_start(),
+            blocked_tools: Vec::new(),
+            run_as_user: None,
+            max_concurrent_sessions: None,
+            max_concurrent_requests: None,
+            spend_limit_usd: None,
+            warn_at_fraction: default_warn_at_fraction(),
+            hard_stop_on_limit: false,
+            min_free_disk_mb: default_min_free_disk_mb(),
+            metrics_interval_secs: None,
+            metrics_exporter: MetricsExporter::default(),
+            health_endpoint: HealthEndpoint::default(),
+        }
+    }
+}
+
+impl Config {
+    fn config_path() -> Result<PathBuf, ConfigError> {
+        let paths = AppPaths::resolve().ok_or(ConfigError::NoConfigDir)?;
+        Ok(paths.config_file())
+    }
+
+    /// config.json with the environment overrides applied (see `overrides`)
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = Self::load_from(&Self::config_path()?, keychain::system())?;
+        overrides::apply(&mut config)?;
+        Ok(config)
+    }
+
+    /// Load `path`, taking the API key from `store`. A key still in the
+    /// file is moved to `store` unless `store_api_key_in_file`.
+    ///
+    /// A file that doesn't parse is recovered from as `load_recovering`
+    /// does, and the recovery kept for `take_recovery`. One others can
+    /// read is made private first, and the fix kept for
+    /// `take_permissions_fix`.
+    pub fn load_from(path: &Path, store: &dyn SecretStore) -> Result<Self, ConfigError> {
+        match restrict_permissions(path) {
+            Ok(Some(fix)) => {
+                eprintln!(
+                    "[config] {} was readable by others (mode {}), made it private",
+                    path.display(),
+                    fix.previous_mode
+                );
+                if let Ok(mut last) = PERMISSIONS_FIX.lock() {
+                    *last = Some(fix);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[config] Could not restrict {}: {}", path.display(), e),
+        }
+        // They hold the same keys
+        let _ = restrict_permissions(&backup_path(path));
+        let _ = restrict_permissions(&secrets_path(path));
+        let (config, recovery) = Self::load_recovering(path, store)?;
+        if let Some(recovery) = recovery {
+            if let Ok(mut last) = RECOVERY.lock() {
+                *last = Some(recovery);
+            }
+        }
+        Ok(config)
+    }
+
+    /// `load_from`, but when `path` doesn't parse it is moved aside to
+    /// `config.json.corrupt-<timestamp>` and config.json.bak restored in
+    /// its place, or the defaults used if that doesn't parse either. The
+    /// second value says which.
+    pub fn load_recovering(
+        path: &Path,
+        store: &dyn SecretStore,
+    ) -> Result<(Self, Option<ConfigRecovery>), ConfigError> {
+        let (mut config, recovery) = match read_file(path) {
+            Ok(Some((config, version, secrets_in_config))) => {
+                if version < CONFIG_VERSION || secrets_in_config {
+                    config.save_if_writable(path)?;
+                }
+                if version < CONFIG_VERSION {
+                    println!(
+                        "[config] Upgraded config.json from version {} to {}",
+                        version, CONFIG_VERSION
+                    );
+                }
+                if secrets_in_config {
+                    println!("[config] Moved the secrets from config.json to {}", SECRETS_FILE);
+                }
+                (config, None)
+            }
+            Ok(None) => return Ok((Config::default(), None)),
+            Err(ConfigError::Json(e)) => {
+                let (config, recovery) = Self::recover(path, e, store)?;
+                (config, Some(recovery))
+            }
+            Err(e) => return Err(e),
+        };
+
+        if config.api_key_in_keychain {
+            match store.get(keychain::API_KEY_ACCOUNT) {
+                Ok(key) => config.anthropic_api_key = key,
+                Err(e) => eprintln!("[config] Could not read the API key: {}", e),
+            }
+        } else if !config.store_api_key_in_file {
+            if let Some(ref key) = config.anthropic_api_key {
+                match store.set(keychain::API_KEY_ACCOUNT, key) {
+                    Ok(()) => {
+                        config.api_key_in_keychain = true;
+                        config.save_if_writable(path)?;
+                        println!("[config] Moved the API key from config.json to the keychain");
+                    }
+                    Err(e) => {
+                        static WARNED: Once = Once::new();
+                        WARNED.call_once(|| eprintln!("[config] API key stays in config.json: {}", e));
+                    }
+                }
+            }
+        }
+
+        // Same for the other providers' keys
+        let mut moved = false;
+        for provider in Provider::ALL {
+            let account = keychain::provider_key_account(provider.as_str());
+            if config.provider_keys_in_keychain.contains(&provider) {
+                match store.get(&account) {
+                    Ok(Some(key)) => {
+                        config.provider_keys.insert(provider, key);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("[config] Could not read the {} key: {}", provider.as_str(), e),
+                }
+            } else if let Some(key) = config.provider_keys.get(&provider) {
+                if !config.store_api_key_in_file && store.set(&account, key).is_ok() {
+                    config.provider_keys_in_keychain.push(provider);
+                    moved = true;
+                }
+            }
+        }
+        // And the kept gateway token
+        if config.gateway_token_in_keychain {
+            match store.get(keychain::GATEWAY_TOKEN_ACCOUNT) {
+                Ok(token) => config.gateway_token = token,
+                Err(e) => eprintln!("[config] Could not read the gateway token: {}", e),
+            }
+        } else if let Some(ref token) = config.gateway_token {
+            if !config.store_api_key_in_file
+                && store.set(keychain::GATEWAY_TOKEN_ACCOUNT, token).is_ok()
+            {
+                config.gateway_token_in_keychain = true;
+                moved = true;
+            }
+        }
+        // And the keys of profiles not in use, which are only read when
+        // switching to them
+        if !config.store_api_key_in_file {
+            for (name, profile) in config.profiles.iter_mut() {
+                if profile.api_key_in_keychain {
+                    continue;
+                }
+                let Some(key) = profile.api_key.take() else {
+                    continue;
+                };
+                if store.set(&keychain::profile_key_account(name), &key).is_ok() {
+                    profile.api_key_in_keychain = true;
+                    moved = true;
+                } else {
+                    profile.api_key = Some(key);
+                }
+            }
+        }
+        // And the stored keys
+        if !config.store_api_key_in_file {
+            for named in config.api_keys.iter_mut() {
+                if named.key_in_keychain {
+                    continue;
+                }
+                let Some(key) = named.key.take() else {
+                    continue;
+                };
+                if store.set(&keychain::stored_key_account(&named.label), &key).is_ok() {
+                    named.key_in_keychain = true;
+                    moved = true;
+                } else {
+                    named.key = Some(key);
+                }
+            }
+        }
+        if moved {
+            config.save_if_writable(path)?;
+            println!("[config] Moved further keys from config.json to the keychain");
+        }
+        Ok((config, recovery))
+    }
+
+    fn recover(
+        path: &Path,
+        error: serde_json::Error,
+        store: &dyn SecretStore,
+    ) -> Result<(Self, ConfigRecovery), ConfigError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let moved_to = with_suffix(path, &format!(".corrupt-{}", timestamp));
+        fs::rename(path, &moved_to)?;
+        eprintln!(
+            "[config] {} is unreadable ({}), moved it to {}",
+            path.display(),
+            error,
+            moved_to.display()
+        );
+
+        let backup = backup_path(path);
+        let (config, from_backup) = match read_file(&backup) {
+            Ok(Some((config, ..))) => {
+                config.save_to(path)?;
+                println!("[config] Restored {} from {}", path.display(), backup.display());
+                (config, true)
+            }
+            _ => {
+                // The key is likely still in the keychain
+                let config = Config {
+                    api_key_in_keychain: matches!(
+                        store.get(keychain::API_KEY_ACCOUNT),
+                        Ok(Some(_))
+                    ),
+                    ..Config::default()
+                };
+                eprintln!("[config] No usable backup, using the default settings");
+                (config, false)
+            }
+        };
+        Ok((
+            config,
+            ConfigRecovery {
+                detail: error.to_string(),
+                from_backup,
+                moved_to,
+            },
+        ))
+    }
+
+    /// The recovery from an unreadable config.json since the last call,
+    /// if there was one
+    pub fn take_recovery() -> Option<ConfigRecovery> {
+        RECOVERY.lock().ok()?.take()
+    }
+
+    /// The fix of a config.json others could read since the last call, if
+    /// there was one
+    pub fn take_permissions_fix() -> Option<PermissionsFix> {
+        PERMISSIONS_FIX.lock().ok()?.take()
+    }
+
+    /// Save to config.json, creating the config dir first if needed.
+    /// Nothing is kept of a failed save, so it can simply be tried again.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        self.save_to(&Self::config_path()?)
+    }
+
+    /// Write to `path`, without the keys that are in the keychain and with
+    /// the secret fields in `secrets_path` instead, keeping the previous file
+    /// as config.json.bak. All are private to the owner, and so is the
+    /// directory, which is created if needed.
+    ///
+    /// A directory that can't be created is `ConfigError::Unavailable`,
+    /// and one that can't be written `ConfigError::ReadOnly`.
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if let Err(e) = paths::ensure_dir(dir) {
+            return Err(match write_error(dir, e) {
+                ConfigError::Io(e) => ConfigError::Unavailable {
+                    path: dir.to_path_buf(),
+                    detail: e.to_string(),
+                },
+                e => e,
+            });
+        }
+        self.write_to(path).map_err(|e| match e {
+            ConfigError::Io(e) => write_error(dir, e),
+            e => e,
+        })
+    }
+
+    /// `save_to` while loading, to upgrade the file or move keys out of
+    /// it. The config loaded is good either way, so a file that can't be
+    /// written is left for the next save.
+    fn save_if_writable(&self, path: &Path) -> Result<(), ConfigError> {
+        match self.save_to(path) {
+            Err(e @ (ConfigError::ReadOnly { .. } | ConfigError::Unavailable { .. })) => {
+                eprintln!("[config] Could not update {}: {}", path.display(), e);
+                Ok(())
+            }
+            saved => saved,
+        }
+    }
+
+    fn write_to(&self, path: &Path) -> Result<(), ConfigError> {
+        let mut saved = self.clone();
+        saved.config_version = CONFIG_VERSION;
+        overrides::restore(&mut saved);
+        if saved.api_key_in_keychain {
+            saved.anthropic_api_key = None;
+        }
+        if saved.gateway_token_in_keychain {
+            saved.gateway_token = None;
+        }
+        saved
+            .provider_keys
+            .retain(|provider, _| !self.provider_keys_in_keychain.contains(provider));
+        let mut value = serde_json::to_value(&saved)?;
+        match sealed_keys::machine_key() {
+            Some(key) => sealed_keys::seal_keys(&mut value, key)
+                .map_err(|e| io::Error::other(e.to_string()))?,
+            None => {
+                static WARNED: Once = Once::new();
+                WARNED.call_once(|| {
+                    eprintln!("[config] No machine ID to seal keys with, they stay in plain text")
+                });
+            }
+        }
+        sealed_keys::restore_unreadable(&mut value, &self.unreadable_keys);
+        let secrets = split_secrets(&mut value);
+        let contents = serde_json::to_string_pretty(&value)?;
+
+        // Not overwritten by this older app
+        let previous = fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok());
+        if let Some(ref previous) = previous {
+            let found = migrations::version_of(previous);
+            if found > CONFIG_VERSION {
+                return Err(MigrationError::TooNew {
+                    found,
+                    supported: CONFIG_VERSION,
+                }
+                .into());
+            }
+        }
+
+        // Written whole next to it and renamed over it, so config.json is
+        // never left half written; the previous one stays as the backup
+        let _saving = SAVING.lock().unwrap_or_else(|e| e.into_inner());
+        config_watch::record_save(contents.as_bytes());
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            paths::restrict_mode(dir, paths::PRIVATE_DIR_MODE)?;
+        }
+        // Secrets first, so a failure in between doesn't lose them
+        let secrets_file = secrets_path(path);
+        if !secrets.is_empty() {
+            let secrets = serde_json::to_string_pretty(&secrets)?;
+            fs::rename(stage(&secrets_file, &secrets)?, &secrets_file)?;
+        } else if secrets_file.exists() {
+            fs::remove_file(&secrets_file)?;
+        }
+        let staged = stage(path, &contents)?;
+        if let Some(mut previous) = previous {
+            let backup = backup_path(path);
+            // A config.json from before secrets.json isn't kept with them
+            if split_secrets(&mut previous).is_empty() {
+                fs::copy(path, &backup)?;
+            } else {
+                fs::write(&backup, serde_json::to_string_pretty(&previous)?)?;
+            }
+            paths::restrict_mode(&backup, paths::PRIVATE_FILE_MODE)?;
+        }
+        fs::rename(&staged, path)?;
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            let _ = fs::File::open(dir).and_then(|dir| dir.sync_all());
+        }
+        Ok(())
+    }
+
+    /// Set or, with None, remove the API key: in `store`, or in the file
+    /// with `store_api_key_in_file`, replacing one that couldn't be read.
+    /// Save afterwards.
+    pub fn set_api_key(
+        &mut self,
+        key: Option<String>,
+        store: &dyn SecretStore,
+    ) -> Result<(), KeychainError> {
+        match key {
+            Some(ref key) if !self.store_api_key_in_file => {
+                store.set(keychain::API_KEY_ACCOUNT, key)?;
+                self.api_key_in_keychain = true;
+            }
+            _ => {
+                if self.api_key_in_keychain {
+                    store.delete(keychain::API_KEY_ACCOUNT)?;
+                }
+                self.api_key_in_keychain = false;
+            }
+        }
+        self.anthropic_api_key = key;
+        self.unreadable_keys.remove(sealed_keys::API_KEY_FIELD);
+        Ok(())
+    }
+
+    /// Set or, with None, remove the kept gateway token, stored like the
+    /// API key. Save afterwards.
+    pub fn set_gateway_token(
+        &mut self,
+        token: Option<String>,
+        store: &dyn SecretStore,
+    ) -> Result<(), KeychainError> {
+        match token {
+            Some(ref token) if !self.store_api_key_in_file => {
+                store.set(keychain::GATEWAY_TOKEN_ACCOUNT, token)?;
+                self.gateway_token_in_keychain = true;
+            }
+            _ => {
+                if self.gateway_token_in_keychain {
+                    store.delete(keychain::GATEWAY_TOKEN_ACCOUNT)?;
+                }
+                self.gateway_token_in_keychain = false;
+            }
+        }
+        self.gateway_token = token;
+        Ok(())
+    }
+
+    /// The token to start the gateway with instead of a new one
+    pub fn persisted_gateway_token(&self) -> Option<String> {
+        self.gateway_token
+            .clone()
+            .filter(|token| self.persist_gateway_token && !token.is_empty())
+    }
+
+    /// The key the gateway gets for `provider`: the API key for the
+    /// selected one
+    pub fn provider_key(&self, provider: Provider) -> Option<&str> {
+        if provider == self.provider {
+            self.anthropic_api_key.as_deref()
+        } else {
+            self.provider_keys.get(&provider).map(String::as_str)
+        }
+    }
+
+    /// `set_api_key` for `provider`, which sets the API key itself for the
+    /// selected provider. Save afterwards.
+    pub fn set_provider_key(
+        &mut self,
+        provider: Provider,
+        key: Option<String>,
+        store: &dyn SecretStore,
+    ) -> Result<(), KeychainError> {
+        if provider == self.provider {
+            return self.set_api_key(key, store);
+        }
+        let account = keychain::provider_key_account(provider.as_str());
+        match key {
+            Some(ref key) if !self.store_api_key_in_file => {
+                store.set(&account, key)?;
+                if !self.provider_keys_in_keychain.contains(&provider) {
+                    self.provider_keys_in_keychain.push(provider);
+                }
+            }
+            _ => {
+                if self.provider_keys_in_keychain.contains(&provider) {
+                    store.delete(&account)?;
+                }
+                self.provider_keys_in_keychain.retain(|p| *p != provider);
+            }
+        }
+        match key {
+            Some(key) => self.provider_keys.insert(provider, key),
+            None => self.provider_keys.remove(&provider),
+        };
+        self.unreadable_keys
+            .remove(&format!("/providerKeys/{}", provider.as_str()));
+        Ok(())
+    }
+
+    /// Why there is no API key to start with: not entered, or sealed on
+    /// another machine
+    pub fn missing_api_key_error(&self) -> AppError {
+        if self.unreadable_keys.contains_key(sealed_keys::API_KEY_FIELD) {
+            AppError::ApiKeyUnreadable
+        } else {
+            AppError::ApiKeyMissing
+        }
+    }
+
+    /// Check the values the setters check, for a config edited by hand
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.gateway_port == 0 {
+            return Err(AppError::InvalidPort {
+                port: self.gateway_port.to_string(),
+            });
+        }
+        // Saved before the setters refused them, with a port to move to
+        if self.gateway_port < MIN_PORT {
+            let host = self.gateway_ip().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+            return Err(port::not_permitted(host, self.gateway_port));
+        }
+        check_startup_timeout("gatewayStartupTimeoutSecs", self.gateway_startup_timeout_secs)?;
+        if let Some(ref locale) = self.locale {
+            locale::validate_locale(locale)?;
+        }
+        if let Some(ref timezone) = self.timezone {
+            locale::validate_timezone(timezone)?;
+        }
+        if let Some(ref model) = self.default_model {
+            models::validate("defaultModel", model)?;
+        }
+        if let Some(ref model) = self.small_model {
+            models::validate("smallModel", model)?;
+        }
+        if let Some(ref level) = self.gateway_log_level {
+            log_level::validate(level)?;
+        }
+        if let Some(ref path) = self.openclaw_path {
+            validate_openclaw_path(path)?;
+        }
+        if let Some(ref proxy) = self.proxy {
+            proxy::validate(proxy)?;
+        }
+        anthropic_endpoint::plan(self)?;
+        if let Some(ref external) = self.external_gateway {
+            external::parse_url(&external.url)?;
+        }
+        if let Some(usd) = self.spend_limit_usd {
+            spend::check_limit(usd).map_err(|detail| AppError::InvalidSetting {
+                field: "spendLimitUsd".to_string(),
+                detail,
+            })?;
+        }
+        spend::check_warn_at(self.warn_at_fraction).map_err(|detail| {
+            AppError::InvalidSetting {
+                field: "warnAtFraction".to_string(),
+                detail,
+            }
+        })?;
+        gateway_options::validate(&self.gateway_extra_args, &self.gateway_extra_env)?;
+        for profile in self.profiles.values() {
+            gateway_options::validate(&profile.gateway_extra_args, &BTreeMap::new())?;
+        }
+        self.gateway_ip()?;
+        gateway_socket::plan(self)?;
+        Ok(())
+    }
+
+    /// `gateway_host`, which must be an IP address
+    pub fn gateway_ip(&self) -> Result<IpAddr, AppError> {
+        self.gateway_host
+            .trim()
+            .parse()
+            .map_err(|_| AppError::InvalidGatewayHost {
+                host: self.gateway_host.clone(),
+            })
+    }
 }
 
-/// Information about app data stored on disk
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// `openclawPath` must be an executable file
+pub fn validate_openclaw_path(path: &Path) -> Result<(), AppError> {
+    if resolve::is_executable(path) {
+        Ok(())
+    } else {
+        Err(AppError::InvalidOpenclawPath {
+            path: path.display().to_string(),
+        })
+    }
+}
+
+/// The config as `get_config` returns it, with `anthropicApiKey` shown
+/// only as `redact_api_key` so the key never reaches the webview
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct AppDataInfo {
-    pub config_path: Option<String>,
-    pub data_path: Option<String>,
-    pub total_size_bytes: u64,
-    pub total_size_formatted: String,
+pub struct ConfigView {
+    #[serde(flatten)]
+    config: Config,
+    /// What a start would run: `openclawPath` or the node found. None when
+    /// there is nothing to run.
+    pub effective_program: Option<String>,
+    /// Fields set from the environment, which the UI can't change
+    pub overridden: Vec<&'static str>,
 }
 
-/// Supported AI providers
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "lowercase")]
-pub enum Provider {
-    Anthropic,
-    Openai,
-    Google,
-    Openrouter,
+impl ConfigView {
+    pub fn new(mut config: Config, effective_program: Option<String>) -> Self {
+        config.anthropic_api_key = config.anthropic_api_key.as_deref().map(redact_api_key);
+        for key in config.provider_keys.values_mut() {
+            *key = redact_api_key(key);
+        }
+        for profile in config.profiles.values_mut() {
+            profile.api_key = profile.api_key.as_deref().map(redact_api_key);
+        }
+        for named in config.api_keys.iter_mut() {
+            named.key = named.key.as_deref().map(redact_api_key);
+        }
+        if let Some(ref mut external) = config.external_gateway {
+            external.token = redact_api_key(&external.token);
+        }
+        config.gateway_token = config.gateway_token.as_deref().map(redact_api_key);
+        Self {
+            overridden: config.overrides.fields(),
+            config,
+            effective_program,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
 }
 
-impl Default for Provider {
-    fn default() -> Self {
-        Provider::Anthropic
+/// `key` as its prefix and last 4 characters, e.g. `sk-ant-…abcd`, enough
+/// to tell keys apart. Short keys show nothing.
+pub fn redact_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 12 {
+        return "…".to_string();
     }
+    // Up to two short dash-separated parts, like `sk-ant-` or `sk-or-`
+    let prefix_len = chars
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == '-')
+        .map(|(i, _)| i + 1)
+        .take(2)
+        .take_while(|end| *end <= 8)
+        .last()
+        .unwrap_or(0);
+    let prefix: String = chars[..prefix_len].iter().collect();
+    let suffix: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", prefix, suffix)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Config {
-    /// The selected AI provider
-    #[serde(default)]
-    pub provider: Provider,
-    /// API key (used for the selected provider)
-    pub anthropic_api_key: Option<String>,
-    #[serde(default = "default_port")]
-    pub gateway_port: u16,
-    #[serde(default = "default_auto_start")]
-    pub auto_start_gateway: bool,
+// Tauri commands
+#[tauri::command]
+pub async fn get_config(app: AppHandle) -> Result<ConfigView, AppError> {
+    // Finding the program may ask a login shell
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        let effective_program = app.state::<SidecarManager>().find_program(&config).ok();
+        // It may have been switched off in system settings
+        config.launch_at_login = login_item::is_registered();
+        Ok(ConfigView::new(config, effective_program))
+    })
+    .await?
 }
 
-fn default_port() -> u16 {
-    18789
+/// How config.json was recovered from being unreadable since the last
+/// call, for a one-time warning
+#[tauri::command]
+pub fn take_config_recovery() -> Option<ConfigRecovery> {
+    Config::take_recovery()
 }
 
-fn default_auto_start() -> bool {
-    true
+/// Set once after config.json was found readable by others and made
+/// private, then None again
+#[tauri::command]
+pub fn take_config_permissions_fix() -> Option<PermissionsFix> {
+    Config::take_permissions_fix()
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            provider: Provider::default(),
-            anthropic_api_key: None,
-            gateway_port: default_port(),
-            auto_start_gateway: default_auto_start(),
+/// Save `key`, trimmed; empty removes it. With `validate`, an Anthropic
+/// key is checked first (see `api_key`) and refused when it's malformed
+/// or rejected, but saved when it couldn't be checked.
+#[tauri::command]
+pub async fn set_api_key(
+    key: String,
+    validate: Option<bool>,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<(), AppError> {
+    let mut config = Config::load()?;
+    let key = Some(key.trim().to_string()).filter(|key| !key.is_empty());
+    if let Some(ref key) = key {
+        if validate.unwrap_or(false) && config.provider == Provider::Anthropic {
+            let proxy = proxy::plan(&config)?;
+            let endpoint = anthropic_endpoint::plan(&config)?.unwrap_or_default();
+            let check = api_key::check_api_key(&endpoint, key, proxy.as_ref()).await;
+            match check.error_kind {
+                Some(kind) if kind.is_rejection() => {
+                    return Err(AppError::ApiKeyRejected {
+                        detail: check.message.unwrap_or_default(),
+                    });
+                }
+                Some(_) => eprintln!(
+                    "[config] Saving the API key unchecked: {}",
+                    check.message.unwrap_or_default()
+                ),
+                None => {}
+            }
+        }
+    }
+    match key {
+        // Kept as the `default` stored key, see `stored_keys`
+        Some(key) if config.provider == Provider::Anthropic => {
+            config.active_api_key = Some(stored_keys::DEFAULT_LABEL.to_string());
+            stored_keys::add(&mut config, stored_keys::DEFAULT_LABEL, key, keychain::system())?;
         }
+        key => config.set_api_key(key, keychain::system())?,
     }
+    save_config(&config, &metrics)
 }
 
-impl Config {
-    fn config_path() -> Result<PathBuf, ConfigError> {
-        let config_dir = dirs::config_dir().ok_or(ConfigError::NoConfigDir)?;
-        let app_dir = config_dir.join("simplestclaw");
-        fs::create_dir_all(&app_dir)?;
-        Ok(app_dir.join("config.json"))
+/// Remove the API key and the kept gateway token, and stop the gateway,
+/// which was started with them
+#[tauri::command]
+pub async fn delete_api_key(app: AppHandle) -> Result<(), AppError> {
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        config.set_api_key(None, keychain::system())?;
+        // The stored keys stay, none of them in use
+        config.active_api_key = None;
+        config.set_gateway_token(None, keychain::system())?;
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+        app.state::<SidecarManager>().stop()?;
+        Ok(())
+    })
+    .await?
+}
+
+#[tauri::command]
+pub fn set_provider(provider: String, metrics: State<'_, Arc<AppMetrics>>) -> Result<(), AppError> {
+    let mut config = Config::load()?;
+    config.provider = Provider::parse(&provider)?;
+    save_config(&config, &metrics)
+}
+
+/// Set the key for `provider`, which reaches the gateway from the next
+/// start. For the selected provider this is `set_api_key` without the
+/// check.
+#[tauri::command]
+pub fn set_provider_key(
+    provider: String,
+    key: String,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<(), AppError> {
+    let mut config = Config::load()?;
+    let provider = Provider::parse(&provider)?;
+    let key = Some(key.trim().to_string()).filter(|key| !key.is_empty());
+    config.set_provider_key(provider, key, keychain::system())?;
+    save_config(&config, &metrics)
+}
+
+#[tauri::command]
+pub fn has_provider_key(provider: String) -> Result<bool, AppError> {
+    let config = Config::load()?;
+    Ok(config.provider_key(Provider::parse(&provider)?).is_some())
+}
+
+/// Remove the key for `provider`. Like `delete_api_key` this stops the
+/// gateway when it is the selected provider's.
+#[tauri::command]
+pub async fn delete_provider_key(app: AppHandle, provider: String) -> Result<(), AppError> {
+    let provider = Provider::parse(&provider)?;
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        config.set_provider_key(provider, None, keychain::system())?;
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+        if provider == config.provider {
+            app.state::<SidecarManager>().stop()?;
+        }
+        Ok(())
+    })
+    .await?
+}
+
+/// Outcome of `set_gateway_port`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortChange {
+    pub port: u16,
+    /// The running gateway keeps its old port until it is restarted
+    pub restart_required: bool,
+    /// Other software commonly uses the port, see `port::conflict_warning`
+    pub warning: Option<String>,
+}
+
+#[tauri::command]
+pub async fn set_gateway_port(app: AppHandle, port: u16) -> Result<PortChange, AppError> {
+    if port < MIN_PORT {
+        return Err(AppError::InvalidPort { port: port.to_string() });
     }
+    // Asking the manager waits out a start
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        config.gateway_port = port;
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+        Ok(PortChange {
+            port,
+            restart_required: app.state::<SidecarManager>().port_change_requires_restart(port),
+            warning: port::conflict_warning(port),
+        })
+    })
+    .await?
+}
 
-    pub fn load() -> Result<Self, ConfigError> {
-        let path = Self::config_path()?;
-        if path.exists() {
-            let contents = fs::read_to_string(&path)?;
-            let config: Config = serde_json::from_str(&contents)?;
-            Ok(config)
-        } else {
-            Ok(Config::default())
+/// Move the gateway to `port`, the `suggested` port of `port_not_permitted`
+/// or `port_blocked`: save it as `gatewayPort`, then restart the gateway on
+/// it, or start one when none runs. Fails with `port_in_use` when the port
+/// was taken since. Emitted as `gateway://started` like a start.
+#[tauri::command]
+pub async fn apply_suggested_port(app: AppHandle, port: u16) -> Result<StartResult, AppError> {
+    let result = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || {
+            let mut config = Config::load()?;
+            port::check_usable(config.gateway_ip()?, port)?;
+            config.gateway_port = port;
+            save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+            tray::config_changed(&app);
+            emit_changed(&app);
+            let manager = app.state::<SidecarManager>();
+            if manager.gateway_pid().is_some() {
+                manager.restart_keeping_token("Moved to a free port")
+            } else {
+                manager.start()
+            }
         }
+    })
+    .await??;
+
+    let _ = app.emit("gateway://started", &result);
+    Ok(result)
+}
+
+/// Outcome of `set_blocked_tools`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockedToolsChange {
+    /// The list as saved, normalized
+    pub blocked_tools: Vec<String>,
+    /// The running gateway was started with a different list
+    pub restart_required: bool,
+}
+
+#[tauri::command]
+pub async fn set_blocked_tools(
+    app: AppHandle,
+    tools: Vec<String>,
+) -> Result<BlockedToolsChange, AppError> {
+    let blocked_tools = blocked_tools::validate(&tools)?;
+    // Asking the manager waits out a start
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        config.blocked_tools = blocked_tools.clone();
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+        let manager = app.state::<SidecarManager>();
+        Ok(BlockedToolsChange {
+            restart_required: manager.blocked_tools_change_requires_restart(&blocked_tools),
+            blocked_tools,
+        })
+    })
+    .await?
+}
+
+/// Set the gateway's locale and timezone; None goes back to the system's.
+/// Takes effect on the next start.
+#[tauri::command]
+pub fn set_locale(
+    locale: Option<String>,
+    timezone: Option<String>,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<(), AppError> {
+    let locale = locale.filter(|locale| !locale.trim().is_empty());
+    let timezone = timezone.filter(|timezone| !timezone.trim().is_empty());
+    if let Some(ref locale) = locale {
+        locale::validate_locale(locale.trim())?;
     }
+    if let Some(ref timezone) = timezone {
+        locale::validate_timezone(timezone.trim())?;
+    }
+    let mut config = Config::load()?;
+    config.locale = locale.map(|locale| locale.trim().to_string());
+    config.timezone = timezone.map(|timezone| timezone.trim().to_string());
+    save_config(&config, &metrics)
+}
 
-    pub fn save(&self) -> Result<(), ConfigError> {
-        let path = Self::config_path()?;
-        let contents = serde_json::to_string_pretty(self)?;
-        fs::write(path, contents)?;
+/// Proxy the gateway through `proxy`, trimmed; None or all fields empty
+/// goes back to the app's own proxy variables. Takes effect on the next
+/// start.
+#[tauri::command]
+pub fn set_proxy_config(
+    proxy: Option<ProxyConfig>,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<(), AppError> {
+    let proxy = match proxy {
+        Some(proxy) => proxy::normalize(proxy)?,
+        None => None,
+    };
+    let mut config = Config::load()?;
+    config.proxy = proxy;
+    save_config(&config, &metrics)
+}
+
+/// Send the gateway's Anthropic requests to `base_url`, with the key in
+/// the `auth_header` header, both trimmed. None or empty goes back to
+/// Anthropic's API and `x-api-key`. Takes effect on the next start.
+#[tauri::command]
+pub fn set_anthropic_endpoint(
+    base_url: Option<String>,
+    auth_header: Option<String>,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<(), AppError> {
+    let endpoint = AnthropicEndpoint::new(base_url, auth_header)?;
+    let mut config = Config::load()?;
+    config.anthropic_base_url = endpoint.base_url;
+    config.anthropic_auth_header = endpoint.auth_header;
+    save_config(&config, &metrics)
+}
+
+/// Serve the gateway over TLS with `tls`, None for plain `ws://`. Rejects
+/// with `tls_config_invalid`, naming the file, when the certificate or key
+/// can't be read. Takes effect on the next start.
+#[tauri::command]
+pub fn set_tls_config(
+    tls: Option<TlsConfig>,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<(), AppError> {
+    if let Some(ref tls) = tls {
+        tls::validate(tls)?;
+    }
+    let mut config = Config::load()?;
+    config.tls = tls;
+    save_config(&config, &metrics)
+}
+
+/// Start the gateway with the same token every time, keeping the running
+/// gateway's, or a new one each time as before. Turning it off forgets the
+/// kept token.
+#[tauri::command]
+pub fn set_persist_gateway_token(
+    enabled: bool,
+    metrics: State<'_, Arc<AppMetrics>>,
+    manager: State<'_, SidecarManager>,
+) -> Result<(), AppError> {
+    let mut config = Config::load()?;
+    config.persist_gateway_token = enabled;
+    let running = manager
+        .status()
+        .info
+        .filter(|_| config.external_gateway.is_none())
+        .map(|info| info.token);
+    if !enabled {
+        config.set_gateway_token(None, keychain::system())?;
+    } else if let Some(token) = running {
+        config.set_gateway_token(Some(token), keychain::system())?;
+    }
+    save_config(&config, &metrics)
+}
+
+/// Remove the kept gateway token from the keychain, where it would outlive
+/// a deleted config.json
+pub fn forget_gateway_token() {
+    if let Err(e) = keychain::system().delete(keychain::GATEWAY_TOKEN_ACCOUNT) {
+        eprintln!("[reset] Failed to remove the gateway token: {}", e);
+    }
+}
+
+/// Keep the tokens the gateway is started with while `persistGatewayToken`
+/// is on
+pub fn install_token_saver(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<SidecarManager>()
+        .set_token_saver(Box::new(move |token| {
+            let mut config = Config::load()?;
+            config.set_gateway_token(Some(token.to_string()), keychain::system())?;
+            save_config(&config, &handle.state::<Arc<AppMetrics>>())
+        }));
+}
+
+/// Connect to `external` instead of starting a gateway, or go back to
+/// starting one with None. A gateway the app started is stopped when
+/// switching to an external one, so it isn't left running unmanaged.
+#[tauri::command]
+pub async fn set_external_gateway(
+    external: Option<ExternalGateway>,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    let external = external.map(external::normalize).transpose()?;
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        let switching = external.is_some() && config.external_gateway.is_none();
+        config.external_gateway = external;
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+
+        let manager = app.state::<SidecarManager>();
+        if switching && manager.gateway_pid().is_some() {
+            println!("[config] Stopping the gateway, switching to an external one");
+            manager.stop()?;
+        }
         Ok(())
+    })
+    .await?
+}
+
+/// Run the gateway from `path` instead of through node and npx; None or
+/// empty goes back to that. Takes effect on the next start.
+#[tauri::command]
+pub fn set_openclaw_path(path: Option<String>, metrics: State<'_, Arc<AppMetrics>>) -> Result<(), AppError> {
+    let path = path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    if let Some(ref path) = path {
+        validate_openclaw_path(path)?;
     }
+    let mut config = Config::load()?;
+    config.openclaw_path = path;
+    save_config(&config, &metrics)
 }
 
-// Tauri commands
+/// Outcome of `set_workspace_dir`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDirChange {
+    /// The running gateway keeps working in its directory until it is
+    /// restarted
+    pub restart_required: bool,
+}
+
+/// Have the gateway work in `path`, which must be an existing directory
+/// the app can write to; None or empty goes back to the home directory.
+/// Rejects with `invalid_setting` for `workspaceDir` otherwise.
+#[tauri::command]
+pub async fn set_workspace_dir(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<WorkspaceDirChange, AppError> {
+    let path = path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        if let Some(ref path) = path {
+            sandbox::check_workspace_dir(path)
+                .and_then(|()| {
+                    if !config.sandbox_workspace {
+                        return Ok(());
+                    }
+                    let paths = AppPaths::resolve();
+                    sandbox::validate_workspace(path, paths.as_ref(), dirs::home_dir().as_deref())
+                })
+                .map_err(|detail| AppError::InvalidSetting {
+                    field: "workspaceDir".to_string(),
+                    detail,
+                })?;
+        }
+        let changed = config.workspace_dir != path;
+        config.workspace_dir = path;
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+        Ok(WorkspaceDirChange {
+            restart_required: changed && app.state::<SidecarManager>().gateway_pid().is_some(),
+        })
+    })
+    .await?
+}
+
+/// Outcome of `set_gateway_options`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayOptionsChange {
+    /// The running gateway keeps the options it was started with until
+    /// it is restarted
+    pub restart_required: bool,
+}
+
+/// Replace `gatewayExtraArgs` and `gatewayExtraEnv`. Rejects with
+/// `invalid_gateway_option`, naming the field, when either would override
+/// what the app sets.
 #[tauri::command]
-pub fn get_config() -> Result<Config, String> {
-    Config::load().map_err(|e| e.to_string())
+pub async fn set_gateway_options(
+    app: AppHandle,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+) -> Result<GatewayOptionsChange, AppError> {
+    gateway_options::validate(&args, &env)?;
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        let changed = config.gateway_extra_args != args || config.gateway_extra_env != env;
+        config.gateway_extra_args = args;
+        config.gateway_extra_env = env;
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+        Ok(GatewayOptionsChange {
+            restart_required: changed && app.state::<SidecarManager>().gateway_pid().is_some(),
+        })
+    })
+    .await?
 }
 
+// Async so they run off the main thread, which the tray menu needs
 #[tauri::command]
-pub fn set_api_key(key: String) -> Result<(), String> {
-    let mut config = Config::load().map_err(|e| e.to_string())?;
-    config.anthropic_api_key = if key.is_empty() { None } else { Some(key) };
-    config.save().map_err(|e| e.to_string())
+pub async fn set_auto_start_gateway(app: AppHandle, enabled: bool) -> Result<(), AppError> {
+    update_tray_setting(&app, |config| config.auto_start_gateway = enabled)
 }
 
 #[tauri::command]
-pub fn set_provider(provider: String) -> Result<(), String> {
-    let mut config = Config::load().map_err(|e| e.to_string())?;
-    config.provider = match provider.to_lowercase().as_str() {
-        "anthropic" => Provider::Anthropic,
-        "openai" => Provider::Openai,
-        "google" => Provider::Google,
-        "openrouter" => Provider::Openrouter,
-        _ => return Err(format!("Unknown provider: {}", provider)),
-    };
-    config.save().map_err(|e| e.to_string())
+pub async fn set_keep_running_in_background(app: AppHandle, enabled: bool) -> Result<(), AppError> {
+    update_tray_setting(&app, |config| config.keep_running_in_background = enabled)
+}
+
+/// Change and save a setting shown in the tray menu, then bring the menu
+/// and the frontend (via `emit_changed`) up to date. Also used by the
+/// tray's own checkboxes.
+pub fn update_tray_setting(app: &AppHandle, change: impl FnOnce(&mut Config)) -> Result<(), AppError> {
+    let mut config = Config::load()?;
+    change(&mut config);
+    let saved = save_config(&config, &app.state::<Arc<AppMetrics>>());
+    // On failure this restores the checkmarks from what's on disk
+    tray::config_changed(app);
+    if saved.is_ok() {
+        emit_changed(app);
+    }
+    saved
+}
+
+/// Tell the frontend config.json changed, with the config as `get_config`
+/// returns it
+pub fn emit_changed(app: &AppHandle) {
+    match Config::load() {
+        Ok(mut config) => {
+            let effective_program = app.state::<SidecarManager>().find_program(&config).ok();
+            config.launch_at_login = login_item::is_registered();
+            let _ = app.emit("config://changed", ConfigView::new(config, effective_program));
+        }
+        Err(e) => eprintln!("[config] Failed to reload config.json: {}", e),
+    }
+}
+
+/// Save the config, counting failures in the app metrics
+pub(crate) fn save_config(config: &Config, metrics: &AppMetrics) -> Result<(), AppError> {
+    config.save().map_err(|e| {
+        metrics.record_config_save_failure();
+        e.into()
+    })
 }
 
 #[tauri::command]
-pub fn has_api_key() -> Result<bool, String> {
-    let config = Config::load().map_err(|e| e.to_string())?;
-    Ok(config.anthropic_api_key.is_some())
+pub fn has_api_key() -> Result<bool, AppError> {
+    let config = Config::load()?;
+    Ok(config.provider_key(config.provider).is_some())
 }
 
 /// Get the config directory path for the app
 fn get_config_app_dir() -> Option<PathBuf> {
-    dirs::config_dir().map(|d| d.join("simplestclaw"))
+    AppPaths::resolve().map(|paths| paths.config_dir)
 }
 
 /// Get the data directory path for the app (may be same as config on macOS)
 fn get_data_app_dir() -> Option<PathBuf> {
-    dirs::data_local_dir().map(|d| d.join("simplestclaw"))
+    AppPaths::resolve().map(|paths| paths.data_dir)
 }
 
 /// Calculate directory size recursively
-fn calculate_dir_size(path: &PathBuf) -> u64 {
+pub(crate) fn calculate_dir_size(path: &PathBuf) -> u64 {
     if !path.exists() {
         return 0;
     }
@@ -164,7 +2196,7 @@ fn calculate_dir_size(path: &PathBuf) -> u64 {
 }
 
 /// Format bytes into human-readable string
-fn format_bytes(bytes: u64) -> String {
+pub(crate) fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
@@ -182,7 +2214,7 @@ fn format_bytes(bytes: u64) -> String {
 
 /// Get information about all app data stored on disk
 #[tauri::command]
-pub async fn get_app_data_info() -> Result<AppDataInfo, String> {
+pub async fn get_app_data_info() -> Result<AppDataInfo, AppError> {
     let config_dir = get_config_app_dir();
     let data_dir = get_data_app_dir();
 
@@ -211,8 +2243,7 @@ pub async fn get_app_data_info() -> Result<AppDataInfo, String> {
 
         (config_size, data_size)
     })
-    .await
-    .map_err(|e| format!("Failed to calculate sizes: {}", e))?;
+    .await?;
 
     let total_size = config_size + data_size;
 
@@ -227,35 +2258,26 @@ pub async fn get_app_data_info() -> Result<AppDataInfo, String> {
 /// Delete all app data - config, runtime, openclaw package, and openclaw data
 /// This will reset the app to a completely fresh state
 #[tauri::command]
-pub async fn delete_all_app_data() -> Result<(), String> {
-    let config_dir = get_config_app_dir();
-    let data_dir = get_data_app_dir();
+pub async fn delete_all_app_data() -> Result<(), AppError> {
+    let roots = AppPaths::resolve().map(|paths| paths.roots()).unwrap_or_default();
 
     tokio::task::spawn_blocking(move || {
-        // Delete config directory (includes runtime on macOS)
-        if let Some(path) = config_dir {
+        // Delete config, data (includes runtime and logs) and cache directories.
+        // These may coincide, e.g. config and data on macOS.
+        for path in roots {
             if path.exists() {
-                println!("[reset] Deleting config directory: {:?}", path);
+                println!("[reset] Deleting app directory: {:?}", path);
                 if let Err(e) = fs::remove_dir_all(&path) {
-                    eprintln!("[reset] Failed to delete config directory: {}", e);
-                    return Err(format!("Failed to delete config directory: {}", e));
+                    eprintln!("[reset] Failed to delete app directory: {}", e);
+                    return Err(AppError::DeleteFailed {
+                        path: path.display().to_string(),
+                        detail: e.to_string(),
+                    });
                 }
             }
         }
 
-        // Delete data directory (if different from config, e.g. on Linux/Windows)
-        if let Some(path) = data_dir {
-            if path.exists() {
-                let config_dir_check = get_config_app_dir();
-                if config_dir_check.as_ref() != Some(&path) {
-                    println!("[reset] Deleting data directory: {:?}", path);
-                    if let Err(e) = fs::remove_dir_all(&path) {
-                        eprintln!("[reset] Failed to delete data directory: {}", e);
-                        return Err(format!("Failed to delete data directory: {}", e));
-                    }
-                }
-            }
-        }
+        forget_gateway_token();
 
         // Delete openclaw package from npx cache
         delete_openclaw_from_npx_cache();
@@ -267,8 +2289,7 @@ pub async fn delete_all_app_data() -> Result<(), String> {
         println!("[reset] All app data deleted successfully");
         Ok(())
     })
-    .await
-    .map_err(|e| format!("Task failed: {}", e))?
+    .await?
 }
 
 /// Delete OpenClaw's own data directory
@@ -317,20 +2338,34 @@ fn delete_openclaw_data_directory() {
     }
 }
 
-/// Delete only openclaw-related entries from the npx cache
-/// This is safe because we only delete folders that contain openclaw,
-/// leaving other npx-cached packages untouched
-fn delete_openclaw_from_npx_cache() {
-    // Get npx cache directory
-    let npx_cache = if cfg!(windows) {
+/// The npx package cache directory
+pub(crate) fn npx_cache_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
         // Windows: %LocalAppData%/npm-cache/_npx
         dirs::data_local_dir().map(|d| d.join("npm-cache").join("_npx"))
     } else {
         // macOS/Linux: ~/.npm/_npx
         dirs::home_dir().map(|d| d.join(".npm").join("_npx"))
+    }
+}
+
+/// Hash-named folders in the npx cache that contain openclaw
+pub fn openclaw_npx_entries(npx_cache: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(npx_cache) else {
+        return Vec::new();
     };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && path.join("node_modules").join("openclaw").exists())
+        .collect()
+}
 
-    let Some(npx_cache) = npx_cache else {
+/// Delete only openclaw-related entries from the npx cache
+/// This is safe because we only delete folders that contain openclaw,
+/// leaving other npx-cached packages untouched
+fn delete_openclaw_from_npx_cache() {
+    let Some(npx_cache) = npx_cache_dir() else {
         println!("[reset] Could not determine npx cache location");
         return;
     };
@@ -342,27 +2377,12 @@ fn delete_openclaw_from_npx_cache() {
 
     println!("[reset] Scanning npx cache for openclaw: {:?}", npx_cache);
 
-    // Iterate through hash-named folders in _npx
-    let Ok(entries) = fs::read_dir(&npx_cache) else {
-        println!("[reset] Could not read npx cache directory");
-        return;
-    };
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !path.is_dir() {
-            continue;
-        }
-
-        // Check if this folder contains openclaw in node_modules
-        let openclaw_path = path.join("node_modules").join("openclaw");
-        if openclaw_path.exists() {
-            println!("[reset] Found openclaw cache, deleting: {:?}", path);
-            if let Err(e) = fs::remove_dir_all(&path) {
-                eprintln!("[reset] Warning: Failed to delete openclaw cache: {}", e);
-            } else {
-                println!("[reset] Deleted openclaw cache successfully");
-            }
+    for path in openclaw_npx_entries(&npx_cache) {
+        println!("[reset] Found openclaw cache, deleting: {:?}", path);
+        if let Err(e) = fs::remove_dir_all(&path) {
+            eprintln!("[reset] Warning: Failed to delete openclaw cache: {}", e);
+        } else {
+            println!("[reset] Deleted openclaw cache successfully");
         }
     }
 }
@@ -0,0 +1,425 @@
+//! Config Import and Export
+//!
+//! `export_config` writes the settings to one JSON file for setting up
+//! another machine, and `import_config` reads such a file back:
+//!
+//! ```json
+//! { "format": "simplestclaw-config", "appVersion": "…", "exportedAt": 0,
+//!   "containsSecrets": false, "config": { … } }
+//! ```
+//!
+//! `config` is config.json as this version writes it, without what only
+//! holds on this machine: which keys are in the keychain, the persisted
+//! gateway token and `storeApiKeyInFile`. API keys (the stored ones too),
+//! the external gateway's token and proxy credentials are only exported
+//! when asked for; such a file says `containsSecrets` and a `warning`, and
+//! is private to the owner like config.json.
+//!
+//! An import is checked whole before anything changes: the file is migrated
+//! like config.json, so one from a newer app is refused with
+//! `config_too_new`, and the result must pass `Config::validate`. Merging
+//! takes the file's fields over the current ones, profiles, provider keys
+//! and stored keys by name; replacing starts from the defaults instead.
+//! Either way a key the file doesn't have is kept, and imported keys go to
+//! the keychain on the next load like any key found in config.json.
+//! Nothing is restarted; the reply says `restartRequired` when the running
+//! gateway was started with settings that changed.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+
+use crate::config::{self, save_config, Config, ConfigView};
+use crate::error::AppError;
+use crate::keychain::{self, SecretStore};
+use crate::metrics::AppMetrics;
+use crate::migrations::{self, MigrationError};
+use crate::overrides;
+use crate::paths;
+use crate::proxy;
+use crate::settings;
+use crate::sidecar::SidecarManager;
+use crate::tray;
+
+/// What an export says it is
+pub const FORMAT: &str = "simplestclaw-config";
+
+/// Fields that only hold on the machine they were written on
+const MACHINE_FIELDS: &[&str] = &[
+    "apiKeyInKeychain",
+    "providerKeysInKeychain",
+    "gatewayToken",
+    "gatewayTokenInKeychain",
+    "storeApiKeyInFile",
+];
+
+/// Key fields, which need a restart like the settings the gateway starts with
+const KEY_FIELDS: &[&str] = &["anthropicApiKey", "providerKeys", "apiKeys", "activeApiKey"];
+
+/// Maps merged by entry rather than replaced
+const MERGED_MAPS: &[&str] = &["profiles", "providerKeys"];
+
+const SECRETS_WARNING: &str =
+    "This file contains API keys in plain text. Keep it private and delete it once imported.";
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("could not read it: {0}")]
+    Io(#[from] io::Error),
+    #[error("it is not a config exported by simplestclaw")]
+    NotAnExport,
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+}
+
+#[derive(Error, Debug)]
+#[error("could not write {}: {error}", path.display())]
+pub struct ExportError {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+/// How an import treats the current config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// The file's fields over the current ones
+    #[default]
+    Merge,
+    /// The file's fields over the defaults
+    Replace,
+}
+
+/// `config` as an export, with its keys when `include_secrets`. Keys of
+/// profiles not in use are read from `store`.
+pub fn export(
+    config: &Config,
+    store: &dyn SecretStore,
+    include_secrets: bool,
+    app_version: &str,
+) -> Result<Value, serde_json::Error> {
+    let mut saved = config.clone();
+    overrides::restore(&mut saved);
+    if include_secrets {
+        for (name, profile) in saved.profiles.iter_mut() {
+            if profile.api_key_in_keychain && profile.api_key.is_none() {
+                match store.get(&keychain::profile_key_account(name)) {
+                    Ok(key) => profile.api_key = key,
+                    Err(e) => eprintln!("[config] Could not read the key of profile {}: {}", name, e),
+                }
+            }
+        }
+        for named in saved.api_keys.iter_mut() {
+            if named.key_in_keychain && named.key.is_none() {
+                match store.get(&keychain::stored_key_account(&named.label)) {
+                    Ok(key) => named.key = key,
+                    Err(e) => eprintln!("[config] Could not read API key {}: {}", named.label, e),
+                }
+            }
+        }
+    } else {
+        if let Some(ref mut external) = saved.external_gateway {
+            external.token.clear();
+        }
+        if let Some(ref mut proxy) = saved.proxy {
+            for url in [&mut proxy.http_proxy, &mut proxy.https_proxy]
+                .into_iter()
+                .flatten()
+            {
+                *url = proxy::without_credentials(url);
+            }
+        }
+    }
+    let mut value = serde_json::to_value(&saved)?;
+    strip_machine_fields(&mut value);
+    if !include_secrets {
+        strip_keys(&mut value);
+    }
+
+    let mut export = Map::new();
+    export.insert("format".to_string(), FORMAT.into());
+    export.insert("appVersion".to_string(), app_version.into());
+    export.insert("exportedAt".to_string(), now_ms().into());
+    export.insert("containsSecrets".to_string(), include_secrets.into());
+    if include_secrets {
+        export.insert("warning".to_string(), SECRETS_WARNING.into());
+    }
+    export.insert("config".to_string(), value);
+    Ok(Value::Object(export))
+}
+
+/// Write `export` to `path`, private to the owner
+pub fn write_export(path: &Path, export: &Value) -> Result<(), ExportError> {
+    let fail = |error: io::Error| ExportError {
+        path: path.to_path_buf(),
+        error,
+    };
+    let contents = serde_json::to_string_pretty(export).map_err(|e| fail(e.into()))?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(fail)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(paths::PRIVATE_FILE_MODE);
+    }
+    let mut file = options.open(path).map_err(fail)?;
+    paths::restrict_mode(path, paths::PRIVATE_FILE_MODE).map_err(fail)?;
+    file.write_all(contents.as_bytes()).map_err(fail)?;
+    file.sync_all().map_err(fail)
+}
+
+/// The export in the file at `path`
+pub fn read_export(path: &Path) -> Result<Value, ImportError> {
+    let export: Value = serde_json::from_slice(&fs::read(path)?)?;
+    if export.get("format").and_then(Value::as_str) != Some(FORMAT) {
+        return Err(ImportError::NotAnExport);
+    }
+    Ok(export)
+}
+
+/// What importing `export` into `current` makes of it: the config to save
+/// and the fields that changed, as named in config.json. Fails without
+/// anything applied when the file is from a newer app or the result
+/// doesn't pass `Config::validate`.
+pub fn import(
+    current: &Config,
+    export: &Value,
+    mode: ImportMode,
+) -> Result<(Config, Vec<String>), AppError> {
+    let mut imported = export
+        .get("config")
+        .filter(|config| config.is_object())
+        .cloned()
+        .ok_or(ImportError::NotAnExport)?;
+    migrations::migrate(&mut imported).map_err(ImportError::from)?;
+    strip_machine_fields(&mut imported);
+    let Value::Object(imported) = imported else {
+        return Err(ImportError::NotAnExport.into());
+    };
+
+    let mut saved = current.clone();
+    overrides::restore(&mut saved);
+    let before = serde_json::to_value(&saved).map_err(ImportError::from)?;
+    let mut next = match mode {
+        ImportMode::Merge => before.clone(),
+        ImportMode::Replace => {
+            let mut defaults = serde_json::to_value(Config::default()).map_err(ImportError::from)?;
+            // The keys stay unless the file has them
+            for field in MACHINE_FIELDS.iter().chain(KEY_FIELDS) {
+                if let Some(value) = before.get(*field) {
+                    defaults[*field] = value.clone();
+                }
+            }
+            defaults
+        }
+    };
+
+    for (field, value) in &imported {
+        // A key left out is kept
+        if KEY_FIELDS.contains(&field.as_str()) && value.is_null() {
+            continue;
+        }
+        if let (Some(entries), Some(from_file)) = (
+            next.get_mut(field.as_str())
+                .filter(|_| MERGED_MAPS.contains(&field.as_str()))
+                .and_then(Value::as_object_mut),
+            value.as_object(),
+        ) {
+            for (name, entry) in from_file {
+                entries.insert(name.clone(), entry.clone());
+            }
+            continue;
+        }
+        // Stored keys by label
+        if let (Some(Value::Array(keys)), Some(from_file)) =
+            (next.get_mut("apiKeys").filter(|_| field == "apiKeys"), value.as_array())
+        {
+            for entry in from_file {
+                let label = entry.get("label");
+                match keys.iter_mut().find(|key| key.get("label") == label) {
+                    Some(key) => *key = entry.clone(),
+                    None => keys.push(entry.clone()),
+                }
+            }
+            continue;
+        }
+        next[field.as_str()] = value.clone();
+    }
+
+    // Imported keys are stored like those found in config.json, and
+    // profiles without one keep the key they have here
+    if imported.get("anthropicApiKey").is_some_and(Value::is_string) {
+        next["apiKeyInKeychain"] = false.into();
+    }
+    if let Some(keys) = imported.get("providerKeys").and_then(Value::as_object) {
+        if let Some(in_keychain) = next["providerKeysInKeychain"].as_array_mut() {
+            in_keychain.retain(|provider| !provider.as_str().is_some_and(|p| keys.contains_key(p)));
+        }
+    }
+    if let Some(profiles) = next.get_mut("profiles").and_then(Value::as_object_mut) {
+        for (name, profile) in profiles.iter_mut() {
+            let Some(profile) = profile.as_object_mut() else {
+                continue;
+            };
+            if profile.get("apiKey").is_some_and(Value::is_string) {
+                continue;
+            }
+            for field in ["apiKey", "apiKeyInKeychain"] {
+                if let Some(value) = before.pointer(&format!("/profiles/{}/{}", escape(name), field)) {
+                    profile.insert(field.to_string(), value.clone());
+                }
+            }
+        }
+    }
+
+    let mut config: Config = serde_json::from_value(next).map_err(ImportError::from)?;
+    config.validate()?;
+    config.unreadable_keys = current.unreadable_keys.clone();
+
+    let after = serde_json::to_value(&config).map_err(ImportError::from)?;
+    let changed = after
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(field, value)| {
+            !MACHINE_FIELDS.contains(&field.as_str()) && before.get(field.as_str()) != Some(*value)
+        })
+        .map(|(field, _)| field.clone())
+        .collect();
+    Ok((config, changed))
+}
+
+/// Whether the running gateway would need a restart for `changed`
+pub fn requires_restart(changed: &[String]) -> bool {
+    let changed: Vec<&str> = changed.iter().map(String::as_str).collect();
+    settings::requires_restart(&changed) || changed.iter().any(|field| KEY_FIELDS.contains(field))
+}
+
+fn strip_machine_fields(value: &mut Value) {
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+    for field in MACHINE_FIELDS {
+        fields.remove(*field);
+    }
+    if let Some(profiles) = fields.get_mut("profiles").and_then(Value::as_object_mut) {
+        for profile in profiles.values_mut().filter_map(Value::as_object_mut) {
+            profile.remove("apiKeyInKeychain");
+        }
+    }
+    if let Some(keys) = fields.get_mut("apiKeys").and_then(Value::as_array_mut) {
+        for key in keys.iter_mut().filter_map(Value::as_object_mut) {
+            key.remove("keyInKeychain");
+        }
+    }
+}
+
+/// Take the API keys out of config.json `value`
+fn strip_keys(value: &mut Value) {
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+    for field in KEY_FIELDS {
+        fields.remove(*field);
+    }
+    if let Some(profiles) = fields.get_mut("profiles").and_then(Value::as_object_mut) {
+        for profile in profiles.values_mut().filter_map(Value::as_object_mut) {
+            profile.remove("apiKey");
+        }
+    }
+}
+
+fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Outcome of `import_config`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigImport {
+    /// The config as saved, like `get_config` returns it
+    pub config: ConfigView,
+    /// The settings that changed, as named in config.json
+    pub changed: Vec<String>,
+    /// The running gateway was started with some of the old settings
+    pub restart_required: bool,
+}
+
+/// Write the settings to `path` for another machine, with the API keys
+/// only when `include_secrets`. Returns the path written.
+#[tauri::command]
+pub async fn export_config(
+    app: AppHandle,
+    path: String,
+    include_secrets: bool,
+) -> Result<String, AppError> {
+    let version = app.package_info().version.to_string();
+    tokio::task::spawn_blocking(move || {
+        let config = Config::load()?;
+        let path = PathBuf::from(path);
+        let export = export(&config, keychain::system(), include_secrets, &version).map_err(|e| {
+            ExportError {
+                path: path.clone(),
+                error: e.into(),
+            }
+        })?;
+        write_export(&path, &export)?;
+        println!(
+            "[config] Exported to {}{}",
+            path.display(),
+            if include_secrets { " with the API keys" } else { "" }
+        );
+        Ok(path.display().to_string())
+    })
+    .await?
+}
+
+/// Import the settings exported to `path`, merged into the current ones
+/// or, with `mode: "replace"`, instead of them
+#[tauri::command]
+pub async fn import_config(
+    app: AppHandle,
+    path: String,
+    mode: Option<ImportMode>,
+) -> Result<ConfigImport, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let export = read_export(Path::new(&path))?;
+        let current = Config::load()?;
+        let (config, changed) = import(&current, &export, mode.unwrap_or_default())?;
+        if !changed.is_empty() {
+            save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+            println!("[config] Imported {}: {}", path, changed.join(", "));
+            tray::config_changed(&app);
+            config::emit_changed(&app);
+        }
+        // Loaded again, which moves imported keys to the keychain
+        let config = Config::load()?;
+        let manager = app.state::<SidecarManager>();
+        let restart_required = manager.gateway_pid().is_some() && requires_restart(&changed);
+        let effective_program = manager.find_program(&config).ok();
+        Ok(ConfigImport {
+            config: ConfigView::new(config, effective_program),
+            changed,
+            restart_required,
+        })
+    })
+    .await?
+}
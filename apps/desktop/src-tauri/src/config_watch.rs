@@ -0,0 +1,216 @@
+//! Watching config.json
+//!
+//! config.json may be rewritten while the app runs, e.g. by a dotfiles
+//! sync. A background thread checks it every `POLL_INTERVAL` and, once a
+//! change has settled for `DEBOUNCE`, loads it again and compares it with
+//! the copy it had. When settings changed the tray menu is updated and
+//! `config://changed` is emitted with the new config, redacted like
+//! `get_config` returns it. With `autoRestartOnConfigChange` a running
+//! gateway started with any of the old settings is restarted, keeping its
+//! token.
+//!
+//! A change is recognized by the hash of the file's contents. The app's
+//! own saves record theirs (see `record_save`) and are only taken in, not
+//! reported, so a save never comes back as an outside change.
+//!
+//! The file is polled rather than watched with OS notifications, which
+//! also copes with syncs that replace config.json instead of writing to
+//! it. `stop` ends the thread on exit.
+
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::{self, Config};
+use crate::paths::AppPaths;
+use crate::settings;
+use crate::sidecar::SidecarManager;
+use crate::tray;
+
+/// Time between checks of config.json
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long config.json must stay the same before a change is taken in,
+/// so a sync writing it in several steps is read once, complete
+pub const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Saves remembered, so one overwritten by the next before the watcher
+/// got to it isn't taken for an outside change
+const RECENT_SAVES: usize = 8;
+
+/// Hashes of the contents the app last saved config.json with
+static RECENT: Mutex<VecDeque<[u8; 32]>> = Mutex::new(VecDeque::new());
+
+fn hash(contents: &[u8]) -> [u8; 32] {
+    Sha256::digest(contents).into()
+}
+
+/// Note `contents` as written by the app itself
+pub fn record_save(contents: &[u8]) {
+    if let Ok(mut recent) = RECENT.lock() {
+        if recent.len() == RECENT_SAVES {
+            recent.pop_front();
+        }
+        recent.push_back(hash(contents));
+    }
+}
+
+fn is_own_save(fingerprint: &[u8; 32]) -> bool {
+    RECENT
+        .lock()
+        .is_ok_and(|recent| recent.contains(fingerprint))
+}
+
+/// Hash of `path`'s contents, None while it doesn't exist
+fn fingerprint(path: &Path) -> Option<[u8; 32]> {
+    fs::read(path).ok().map(|contents| hash(&contents))
+}
+
+/// The settings that differ between `old` and `new`, as named in
+/// config.json
+pub fn changed_fields(old: &Config, new: &Config) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    let mut fields: Vec<String> = old
+        .iter()
+        .filter(|(field, value)| new.get(*field) != Some(*value))
+        .map(|(field, _)| field.clone())
+        .collect();
+    fields.extend(
+        new.keys()
+            .filter(|field| !old.contains_key(*field))
+            .cloned(),
+    );
+    fields
+}
+
+/// The watcher thread, kept so it can be stopped
+#[derive(Default)]
+pub struct ConfigWatcher {
+    stopping: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ConfigWatcher {
+    /// End the thread and wait for it
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let thread = self.thread.lock().ok().and_then(|mut thread| thread.take());
+        if let Some(thread) = thread {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Start watching config.json
+pub fn spawn_config_watcher(app: &AppHandle) {
+    let Some(path) = AppPaths::resolve().map(|paths| paths.config_file()) else {
+        eprintln!("[config] Not watching config.json: no config directory");
+        return;
+    };
+    let watcher = ConfigWatcher::default();
+    let stopping = watcher.stopping.clone();
+    let handle = app.clone();
+    let spawned = std::thread::Builder::new()
+        .name("config-watch".to_string())
+        .spawn(move || watch(&handle, &path, &stopping));
+    match spawned {
+        Ok(thread) => {
+            if let Ok(mut slot) = watcher.thread.lock() {
+                *slot = Some(thread);
+            }
+            app.manage(watcher);
+        }
+        Err(e) => eprintln!("[config] Failed to start watching config.json: {}", e),
+    }
+}
+
+/// Stop watching, if it was started
+pub fn stop(app: &AppHandle) {
+    if let Some(watcher) = app.try_state::<ConfigWatcher>() {
+        watcher.stop();
+    }
+}
+
+fn watch(app: &AppHandle, path: &Path, stopping: &AtomicBool) {
+    let mut seen = fingerprint(path);
+    let mut current = Config::load().ok();
+    loop {
+        std::thread::park_timeout(POLL_INTERVAL);
+        if stopping.load(Ordering::SeqCst) {
+            return;
+        }
+        let mut latest = fingerprint(path);
+        if latest.is_none() || latest == seen {
+            continue;
+        }
+        // Wait for the writes to settle
+        loop {
+            std::thread::park_timeout(DEBOUNCE);
+            if stopping.load(Ordering::SeqCst) {
+                return;
+            }
+            let again = fingerprint(path);
+            if again == latest {
+                break;
+            }
+            latest = again;
+        }
+        seen = latest;
+        let Some(ref fingerprint) = latest else {
+            continue;
+        };
+
+        let own = is_own_save(fingerprint);
+        let loaded = match Config::load() {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("[config] config.json changed but can't be loaded: {}", e);
+                continue;
+            }
+        };
+        let changed = match current {
+            Some(ref current) if !own => changed_fields(current, &loaded),
+            _ => Vec::new(),
+        };
+        current = Some(loaded.clone());
+        // Status polls go by what the manager loaded last
+        let _ = app.state::<SidecarManager>().load_config();
+        if !changed.is_empty() {
+            reload(app, &loaded, &changed);
+        }
+    }
+}
+
+/// Bring everything up to date with `config`, which changed in `changed`
+/// outside the app
+fn reload(app: &AppHandle, config: &Config, changed: &[String]) {
+    println!("[config] config.json changed: {}", changed.join(", "));
+    tray::config_changed(app);
+    config::emit_changed(app);
+
+    let manager = app.state::<SidecarManager>();
+    let changed: Vec<&str> = changed.iter().map(String::as_str).collect();
+    if !config.auto_restart_on_config_change
+        || manager.gateway_pid().is_none()
+        || !settings::requires_restart(&changed)
+    {
+        return;
+    }
+    match manager.restart_keeping_token("config.json changed") {
+        Ok(result) => {
+            let _ = app.emit("gateway://started", &result);
+        }
+        Err(e) => eprintln!("[config] Failed to restart the gateway: {}", e),
+    }
+}
@@ -0,0 +1,199 @@
+//! Gateway Connection URLs
+//!
+//! `GatewayInfo.url` is normally just `ws://localhost:<port>`, with the
+//! token passed separately. With `tokenInUrl` enabled the token is also
+//! embedded as `?token=...` for WebSocket clients that can't send it any
+//! other way. Such URLs end up in logs easily, so everything the app logs
+//! or captures goes through `redact_tokens` first.
+//!
+//! A gateway on a Unix socket (`transport: unix`, see `gateway_socket`)
+//! has a `ws+unix://<path>` URL instead, which only clients on this
+//! machine that can open the socket can use.
+//!
+//! A gateway bound to another `gatewayHost` than loopback is reachable
+//! from the LAN. Its URL names that address, or for a wildcard host such
+//! as `0.0.0.0` the machine's primary LAN address, so it can be copied to
+//! another device. The app itself still connects over loopback where it
+//! can (`probe_ip`).
+//!
+//! Other tools speaking the gateway protocol (editor plugins, scripts) can
+//! connect to the app's gateway with its `connection_string`, the URL with
+//! the token as `?token=`. Handing the token out is opt-in with
+//! `allowExternalClients`; `copy_connection_string` puts it on the
+//! clipboard without it passing through the webview.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::gateway_socket;
+use crate::sidecar::SidecarManager;
+
+/// Query parameter carrying the token
+pub const TOKEN_PARAM: &str = "token";
+
+const REDACTED: &str = "[redacted]";
+
+/// The gateway's WebSocket URL on `host`, `wss://` when it serves `tls`,
+/// with `token` as a query parameter if given
+pub fn gateway_url(host: &str, port: u16, tls: bool, token: Option<&str>) -> String {
+    let scheme = if tls { "wss" } else { "ws" };
+    match token {
+        Some(token) => format!(
+            "{}://{}:{}?{}={}",
+            scheme,
+            host,
+            port,
+            TOKEN_PARAM,
+            encode_query_value(token)
+        ),
+        None => format!("{}://{}:{}", scheme, host, port),
+    }
+}
+
+/// The URL of a gateway listening on the socket at `path`, with `token`
+/// as a query parameter if given
+pub fn socket_url(path: &Path, token: Option<&str>) -> String {
+    let url = format!("{}://{}", gateway_socket::URL_SCHEME, path.display());
+    match token {
+        Some(token) => format!("{}?{}={}", url, TOKEN_PARAM, encode_query_value(token)),
+        None => url,
+    }
+}
+
+/// `url` of a gateway with `token` as its only query parameter, whether
+/// or not it had the token already
+pub fn connection_string(url: &str, token: &str) -> String {
+    let base = url.split_once('?').map_or(url, |(base, _)| base);
+    format!("{}?{}={}", base, TOKEN_PARAM, encode_query_value(token))
+}
+
+/// Whether a gateway bound to `bind` accepts connections from other
+/// machines
+pub fn is_lan_exposed(bind: IpAddr) -> bool {
+    !bind.is_loopback()
+}
+
+/// The host to put in the URL of a gateway bound to `bind`, given the
+/// machine's primary LAN address for a wildcard bind
+pub fn url_host(bind: IpAddr, lan_ip: Option<IpAddr>) -> String {
+    let ip = match bind {
+        ip if ip.is_loopback() => return "localhost".to_string(),
+        ip if ip.is_unspecified() => match lan_ip {
+            Some(lan_ip) => lan_ip,
+            None => return "localhost".to_string(),
+        },
+        ip => ip,
+    };
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{}]", ip),
+    }
+}
+
+/// The address the app connects to a gateway bound to `bind` on
+pub fn probe_ip(bind: IpAddr) -> IpAddr {
+    match bind {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    }
+}
+
+/// The local address of the route to the internet, in the family of
+/// `bind`. Connecting a UDP socket sends nothing.
+pub fn primary_lan_ip(bind: IpAddr) -> Option<IpAddr> {
+    let (local, remote): (IpAddr, IpAddr) = match bind {
+        IpAddr::V4(_) => (Ipv4Addr::UNSPECIFIED.into(), Ipv4Addr::new(192, 0, 2, 1).into()),
+        IpAddr::V6(_) => (
+            Ipv6Addr::UNSPECIFIED.into(),
+            Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1).into(),
+        ),
+    };
+    let socket = UdpSocket::bind((local, 0)).ok()?;
+    socket.connect((remote, 9)).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified() && !ip.is_loopback()).then_some(ip)
+}
+
+/// Percent-encode everything but the RFC 3986 unreserved characters
+pub fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Replace the value of every `token=` query parameter in `text`
+pub fn redact_tokens(text: &str) -> String {
+    let needle = format!("{}=", TOKEN_PARAM);
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(&needle) {
+        let (before, after) = rest.split_at(index + needle.len());
+        redacted.push_str(before);
+        // Only query parameters, not words that happen to end in "token="
+        let is_param = before[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c == '?' || c == '&');
+        let end = after
+            .find(|c: char| c == '&' || c == '#' || c.is_whitespace() || c == '"' || c == '\'')
+            .unwrap_or(after.len());
+        if is_param && end > 0 {
+            redacted.push_str(REDACTED);
+            rest = &after[end..];
+        } else {
+            rest = after;
+        }
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// The running gateway's `connection_string`, if `allowExternalClients`
+/// permits handing it out
+fn current_connection_string(app: &AppHandle) -> Result<String, AppError> {
+    if !Config::load()?.allow_external_clients {
+        return Err(AppError::ExternalClientsNotAllowed);
+    }
+    let status = app.state::<SidecarManager>().status();
+    let info = status
+        .info
+        .filter(|_| status.running)
+        .ok_or(AppError::GatewayNotRunning)?;
+    Ok(connection_string(&info.url, &info.token))
+}
+
+// Tauri commands
+/// URL and token of the running gateway for other clients. Fails with
+/// `external_clients_not_allowed` unless `allowExternalClients` is on and
+/// with `gateway_not_running`.
+#[tauri::command]
+pub async fn get_connection_string(app: AppHandle) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || current_connection_string(&app)).await?
+}
+
+/// `get_connection_string`, put on the clipboard instead of returned
+#[tauri::command]
+pub async fn copy_connection_string(app: AppHandle) -> Result<(), AppError> {
+    tokio::task::spawn_blocking(move || {
+        let connection = current_connection_string(&app)?;
+        app.clipboard()
+            .write_text(connection)
+            .map_err(|e| AppError::ClipboardFailed {
+                detail: e.to_string(),
+            })?;
+        println!("[connection] Copied the gateway connection string");
+        Ok(())
+    })
+    .await?
+}
@@ -0,0 +1,280 @@
+//! Gateway Connectivity Check
+//!
+//! A firewall blocking the gateway port looks like a working gateway from
+//! here: the process runs and nothing fails until the frontend can't
+//! connect. `run_connectivity_check` tries the connections clients make,
+//! in stages, each with a hint in plain words when it fails:
+//!
+//! - `loopback`: a TCP connect where the app connects (`probe_ip`)
+//! - `handshake`: the WebSocket upgrade with the token
+//!   (`protocol::upgrade_handshake`); a `101` is enough, the gateway
+//!   protocol isn't spoken. Skipped over TLS, which our client can't.
+//! - `lan`: when `gatewayHost` isn't loopback, a TCP connect to the
+//!   machine's LAN address. This one leaves the machine only on some
+//!   systems, so a pass doesn't prove other devices get through.
+//!
+//! A gateway on a Unix socket has no port for a firewall to block, and
+//! isn't checked (`transport_unsupported`).
+//!
+//! The report also goes into the diagnostics bundle.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::config::Transport;
+use crate::connection;
+use crate::error::AppError;
+use crate::protocol::{self, ProtocolError};
+use crate::sidecar::SidecarManager;
+
+/// How long each stage may take
+pub const STAGE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectivityStage {
+    Loopback,
+    Handshake,
+    Lan,
+}
+
+/// The outcome of one stage
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StageResult {
+    pub stage: ConnectivityStage,
+    pub passed: bool,
+    /// What happened, e.g. "upgrade rejected: token mismatch"
+    pub detail: String,
+    /// What to do about a failure
+    pub hint: Option<String>,
+}
+
+impl StageResult {
+    fn passed(stage: ConnectivityStage, detail: impl Into<String>) -> Self {
+        Self {
+            stage,
+            passed: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn failed(
+        stage: ConnectivityStage,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            stage,
+            passed: false,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectivityReport {
+    pub port: u16,
+    /// Every stage passed
+    pub passed: bool,
+    pub stages: Vec<StageResult>,
+}
+
+/// The gateway to check and where
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectivityTarget {
+    pub port: u16,
+    pub token: String,
+    pub tls: bool,
+    /// Where the app connects to it
+    pub local_ip: IpAddr,
+    /// Listening on more than loopback
+    pub lan_exposed: bool,
+    /// The machine's address other devices use, if there is one
+    pub lan_ip: Option<IpAddr>,
+}
+
+impl ConnectivityTarget {
+    /// The running gateway of `manager`. Fails with `gateway_not_running`
+    /// when there is none; an external gateway isn't checked. One on a
+    /// socket fails with `transport_unsupported`.
+    pub fn of(manager: &SidecarManager) -> Result<Self, AppError> {
+        let status = manager.status();
+        let info = status
+            .info
+            .filter(|_| status.running && !status.external)
+            .ok_or(AppError::GatewayNotRunning)?;
+        let Some(port) = info.port else {
+            return Err(AppError::TransportUnsupported {
+                transport: Transport::Unix.as_str().to_string(),
+                detail: "the gateway listens on a socket, which has no port to check.".to_string(),
+            });
+        };
+        let bind = manager.load_config()?.gateway_ip()?;
+        let lan_ip = if bind.is_unspecified() {
+            connection::primary_lan_ip(bind)
+        } else {
+            Some(bind).filter(|_| info.lan_exposed)
+        };
+        Ok(Self {
+            port,
+            tls: info.url.starts_with("wss://"),
+            token: info.token,
+            local_ip: connection::probe_ip(bind),
+            lan_exposed: info.lan_exposed,
+            lan_ip,
+        })
+    }
+}
+
+/// Run the stages against `target`, each within `timeout`. The handshake
+/// needs the loopback connect to pass.
+pub fn check(target: &ConnectivityTarget, timeout: Duration) -> ConnectivityReport {
+    let local = SocketAddr::new(target.local_ip, target.port);
+    let mut stages = vec![check_loopback(local, timeout)];
+    if stages[0].passed && !target.tls {
+        stages.push(check_handshake(local, &target.token, timeout));
+    }
+    if target.lan_exposed {
+        stages.push(check_lan(target.lan_ip, target.port, timeout));
+    }
+    ConnectivityReport {
+        port: target.port,
+        passed: stages.iter().all(|stage| stage.passed),
+        stages,
+    }
+}
+
+fn check_loopback(address: SocketAddr, timeout: Duration) -> StageResult {
+    let stage = ConnectivityStage::Loopback;
+    match TcpStream::connect_timeout(&address, timeout) {
+        Ok(_) => StageResult::passed(stage, format!("connected to {}", address)),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => StageResult::failed(
+            stage,
+            format!("connect to {} refused", address),
+            "Nothing listens on the port. The gateway may have exited or be \
+             serving on another port; restart it.",
+        ),
+        Err(e) if is_timeout(&e) => StageResult::failed(
+            stage,
+            format!("connect to {} timed out", address),
+            format!(
+                "A firewall or security tool may be blocking local connections to \
+                 port {}. Allow simplestclaw and node in it.",
+                address.port()
+            ),
+        ),
+        Err(e) => StageResult::failed(
+            stage,
+            format!("connect to {} failed: {}", address, e),
+            "Check that no firewall or security tool blocks local connections.",
+        ),
+    }
+}
+
+fn check_handshake(address: SocketAddr, token: &str, timeout: Duration) -> StageResult {
+    let stage = ConnectivityStage::Handshake;
+    let error = match protocol::upgrade_handshake(address, token, timeout) {
+        Ok(()) => return StageResult::passed(stage, "WebSocket upgrade accepted"),
+        Err(e) => e,
+    };
+    match error {
+        ProtocolError::Upgrade(status) => match status.split_whitespace().nth(1) {
+            Some("401" | "403") => StageResult::failed(
+                stage,
+                "upgrade rejected: token mismatch",
+                "The gateway doesn't accept the app's token. Restart the gateway so \
+                 both use the same one.",
+            ),
+            _ => StageResult::failed(
+                stage,
+                format!("upgrade rejected: {}", status),
+                format!(
+                    "Something other than the gateway may be answering on port {}, \
+                     such as a proxy or another program.",
+                    address.port()
+                ),
+            ),
+        },
+        ProtocolError::Io(ref e) if is_timeout(e) => StageResult::failed(
+            stage,
+            "upgrade timed out",
+            "The port accepts connections but nothing answers. The gateway may be \
+             stuck; restart it.",
+        ),
+        ProtocolError::Closed => StageResult::failed(
+            stage,
+            "connection closed during the upgrade",
+            "Security software inspecting local traffic may be cutting the \
+             connection. Allow simplestclaw and node in it.",
+        ),
+        other => StageResult::failed(
+            stage,
+            format!("upgrade failed: {}", other),
+            "Restart the gateway and try again.",
+        ),
+    }
+}
+
+fn check_lan(lan_ip: Option<IpAddr>, port: u16, timeout: Duration) -> StageResult {
+    let stage = ConnectivityStage::Lan;
+    let Some(lan_ip) = lan_ip else {
+        return StageResult::failed(
+            stage,
+            "no LAN address found",
+            "The machine doesn't seem to be on a network. Connect it to one, or set \
+             gatewayHost to its address.",
+        );
+    };
+    let address = SocketAddr::new(lan_ip, port);
+    match TcpStream::connect_timeout(&address, timeout) {
+        Ok(_) => StageResult::passed(stage, format!("connected to {}", address)),
+        Err(e) if is_timeout(&e) => StageResult::failed(
+            stage,
+            "LAN connect timed out: check firewall",
+            format!(
+                "Allow incoming connections to port {} in the firewall, for \
+                 private networks at least.",
+                port
+            ),
+        ),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => StageResult::failed(
+            stage,
+            format!("LAN connect to {} refused", address),
+            "The gateway doesn't listen on this address. Check gatewayHost and \
+             restart the gateway.",
+        ),
+        Err(e) => StageResult::failed(
+            stage,
+            format!("LAN connect to {} failed: {}", address, e),
+            "Check the network connection and the firewall.",
+        ),
+    }
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    )
+}
+
+/// Check the running gateway of `manager`, see `check`
+pub fn run(manager: &SidecarManager) -> Result<ConnectivityReport, AppError> {
+    let target = ConnectivityTarget::of(manager)?;
+    Ok(check(&target, STAGE_TIMEOUT))
+}
+
+// Tauri commands
+/// Whether clients can reach the running gateway, stage by stage, with
+/// hints for what fails. Fails with `gateway_not_running`.
+#[tauri::command]
+pub async fn run_connectivity_check(app: AppHandle) -> Result<ConnectivityReport, AppError> {
+    tokio::task::spawn_blocking(move || run(&app.state::<SidecarManager>())).await?
+}
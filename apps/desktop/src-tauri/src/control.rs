@@ -0,0 +1,274 @@
+//! Control Socket
+//!
+//! The running app listens on a unix socket (`control.sock` in the data
+//! dir) so scripts can drive it, usually through the CLI verbs in `cli`.
+//! Only the owner can connect: the socket has mode 0600 before anyone can
+//! reach it, and that permission is the whole authentication.
+//!
+//! The protocol is newline-delimited JSON. Each request line is an object
+//! with a `command`, answered by one response line:
+//!
+//! ```json
+//! {"command":"logs","tail":50}
+//! {"ok":true,"result":[...]}
+//! {"ok":false,"error":{"code":"gateway_start_failed","data":{...},"message":"..."}}
+//! ```
+//!
+//! Named pipes aren't implemented yet, so there's no control socket on
+//! Windows.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::deep_link;
+use crate::error::AppError;
+use crate::log_stream::LogEntry;
+use crate::paths::AppPaths;
+use crate::sidecar::SidecarManager;
+use crate::tray;
+
+pub const SOCKET_NAME: &str = "control.sock";
+
+/// Longest request line accepted
+pub const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Lines `logs` returns without a `tail`
+pub const DEFAULT_TAIL: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlRequest {
+    Status,
+    Start,
+    Stop,
+    /// The preflight checks of a start, without starting
+    Doctor,
+    /// The newest captured gateway output, oldest first
+    Logs {
+        #[serde(default)]
+        tail: Option<usize>,
+    },
+    /// Show and focus the main window, sent by a second launch
+    Focus,
+    /// Open a `simplestclaw://` link, sent by a second launch the OS
+    /// started with one
+    OpenUrl { url: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// A serialized `AppError`, or `{code, message}` for protocol errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+impl ControlResponse {
+    pub fn success(result: Value) -> Self {
+        Self {
+            ok: true,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn failure(error: &AppError) -> Self {
+        Self {
+            ok: false,
+            result: None,
+            error: serde_json::to_value(error).ok(),
+        }
+    }
+
+    /// A request that couldn't be read or understood
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            result: None,
+            error: Some(serde_json::json!({ "code": "bad_request", "message": message.into() })),
+        }
+    }
+}
+
+pub fn socket_path(paths: &AppPaths) -> PathBuf {
+    paths.data_dir.join(SOCKET_NAME)
+}
+
+/// Run `request` against `manager`
+pub fn dispatch(manager: &SidecarManager, request: &ControlRequest) -> Result<Value, AppError> {
+    let value = match request {
+        ControlRequest::Status => serde_json::to_value(manager.status()),
+        ControlRequest::Start => serde_json::to_value(manager.start()?),
+        ControlRequest::Stop => serde_json::to_value(manager.stop()?),
+        ControlRequest::Doctor => serde_json::to_value(manager.preview()),
+        ControlRequest::Logs { tail } => {
+            let tail = tail.unwrap_or(DEFAULT_TAIL);
+            let logs = manager.logs().lock();
+            let mut entries: Vec<LogEntry> = logs
+                .lines()
+                .rev()
+                .take(tail)
+                .map(LogEntry::from_line)
+                .collect();
+            entries.reverse();
+            serde_json::to_value(entries)
+        }
+        // Need the app, see `spawn_control_server`
+        ControlRequest::Focus | ControlRequest::OpenUrl { .. } => Ok(Value::Null),
+    };
+    value.map_err(|e| AppError::TaskFailed {
+        detail: e.to_string(),
+    })
+}
+
+/// Answer request lines on `stream` until the client closes it
+pub fn handle_connection<S: Read + Write>(
+    stream: S,
+    handle: impl Fn(&ControlRequest) -> Result<Value, AppError>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = Vec::new();
+        let read = (&mut reader)
+            .take(MAX_REQUEST_BYTES as u64 + 1)
+            .read_until(b'\n', &mut line)?;
+        if read == 0 {
+            return Ok(());
+        }
+        if line.len() > MAX_REQUEST_BYTES {
+            // Nothing sensible can follow a cut-off line
+            let response = ControlResponse::bad_request("request too long");
+            return write_response(reader.get_mut(), &response);
+        }
+        let line = String::from_utf8_lossy(&line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<ControlRequest>(line.trim()) {
+            Ok(request) => match handle(&request) {
+                Ok(result) => ControlResponse::success(result),
+                Err(e) => ControlResponse::failure(&e),
+            },
+            Err(e) => ControlResponse::bad_request(format!("invalid request: {}", e)),
+        };
+        write_response(reader.get_mut(), &response)?;
+    }
+}
+
+fn write_response(stream: &mut impl Write, response: &ControlResponse) -> io::Result<()> {
+    let mut line = serde_json::to_vec(response)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    stream.flush()
+}
+
+/// Send one request to the app listening at `path` and wait for the answer
+#[cfg(unix)]
+pub fn send(path: &Path, request: &ControlRequest) -> io::Result<ControlResponse> {
+    let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    stream.write_all(&line)?;
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    Ok(serde_json::from_str(&response)?)
+}
+
+#[cfg(not(unix))]
+pub fn send(_path: &Path, _request: &ControlRequest) -> io::Result<ControlResponse> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the control socket is not available on this platform",
+    ))
+}
+
+/// Listen at `path`, answering every connection on its own thread. Fails
+/// if another instance is already listening there; a stale socket file
+/// from a crashed run is replaced.
+#[cfg(unix)]
+pub fn listen<F>(path: &Path, handle: F) -> io::Result<()>
+where
+    F: Fn(&ControlRequest) -> Result<Value, AppError> + Clone + Send + 'static,
+{
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("another instance is listening on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Bound under another name and moved into place once it's private,
+    // so nobody can connect while it still has the umask's permissions
+    let staging = path.with_extension(format!("{}.tmp", std::process::id()));
+    let _ = std::fs::remove_file(&staging);
+    let listener = UnixListener::bind(&staging)?;
+    std::fs::set_permissions(&staging, std::fs::Permissions::from_mode(0o600))
+        .and_then(|()| std::fs::rename(&staging, path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&staging);
+        })?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            let handle = handle.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(stream, handle) {
+                    eprintln!("[control] Connection failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen<F>(_path: &Path, _handle: F) -> io::Result<()>
+where
+    F: Fn(&ControlRequest) -> Result<Value, AppError> + Clone + Send + 'static,
+{
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the control socket is not available on this platform",
+    ))
+}
+
+/// Start the control socket for the app
+pub fn spawn_control_server(app: &tauri::AppHandle) {
+    use tauri::{Emitter, Manager};
+
+    let Some(paths) = AppPaths::resolve() else {
+        return;
+    };
+    let path = socket_path(&paths);
+    let app = app.clone();
+    let handle = move |request: &ControlRequest| {
+        match request {
+            ControlRequest::Focus => tray::show_main_window(&app),
+            ControlRequest::OpenUrl { url } => deep_link::handle_link(&app, url),
+            _ => {}
+        }
+        let result = dispatch(&app.state::<SidecarManager>(), request)?;
+        if *request == ControlRequest::Start {
+            // Same as the start_gateway command, so the window follows
+            let _ = app.emit("gateway://started", &result);
+        }
+        Ok(result)
+    };
+    match listen(&path, handle) {
+        Ok(()) => println!("[control] Listening on {}", path.display()),
+        Err(e) => eprintln!("[control] Warning: no control socket: {}", e),
+    }
+}
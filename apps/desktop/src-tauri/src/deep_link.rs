@@ -0,0 +1,186 @@
+//! Deep Links
+//!
+//! Onboarding docs can link to `simplestclaw://set-key?key=sk-ant-...` to
+//! open the app with the API key filled in. The key is never saved from
+//! the link: it is emitted as `setup://key-received` for the frontend to
+//! show, and saved only once the user confirms, through `set_api_key`.
+//!
+//! macOS hands links to the running app, which the deep-link plugin
+//! reports with `on_open_url`. Windows and Linux launch the app with the
+//! link as an argument instead: a first launch reads it from its own
+//! arguments, and a second launch sends it to the running app over the
+//! control socket (`ControlRequest::OpenUrl`) and exits like any second
+//! launch, so no second gateway starts. Without a control socket, as on
+//! Windows, a second launch can't reach the running app and its link is
+//! lost.
+//!
+//! Links come from anywhere, so `parse` refuses anything but a well-formed
+//! `set-key` link with a key that looks like an Anthropic key, and never
+//! panics. The key isn't logged. A link received before the window
+//! listens is also kept for `take_received_key`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+use thiserror::Error;
+
+use crate::api_key;
+use crate::tray;
+
+pub const SCHEME: &str = "simplestclaw";
+
+/// Event a key from a link is emitted as
+pub const KEY_RECEIVED_EVENT: &str = "setup://key-received";
+
+/// Longest link accepted, far more than a key needs
+pub const MAX_LINK_LEN: usize = 2048;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkError {
+    #[error("The link is longer than {MAX_LINK_LEN} characters")]
+    TooLong,
+    #[error("Not a simplestclaw:// link")]
+    NotOurs,
+    #[error("Unknown link action {0:?}")]
+    UnknownAction(String),
+    #[error("The link has no key")]
+    MissingKey,
+    #[error("The link is malformed: {0}")]
+    Malformed(String),
+    #[error("{0}")]
+    InvalidKey(String),
+}
+
+/// What a link asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    /// Fill in `key` as the API key, for the user to confirm
+    SetKey { key: String },
+}
+
+/// Payload of `setup://key-received`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyReceivedEvent {
+    pub key: String,
+}
+
+/// Key of the last link, until the frontend takes it
+#[derive(Default)]
+pub struct ReceivedKey(Mutex<Option<String>>);
+
+/// Whether `arg` is one of our links, which the OS may pass as the first
+/// argument
+pub fn is_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", SCHEME)))
+}
+
+/// The first link in the arguments the app was launched with
+pub fn link_from_args(args: impl IntoIterator<Item = String>) -> Option<String> {
+    args.into_iter().skip(1).find(|arg| is_link(arg))
+}
+
+/// What `link` asks for
+pub fn parse(link: &str) -> Result<DeepLink, DeepLinkError> {
+    if link.len() > MAX_LINK_LEN {
+        return Err(DeepLinkError::TooLong);
+    }
+    if !is_link(link) {
+        return Err(DeepLinkError::NotOurs);
+    }
+    let rest = &link[SCHEME.len() + 1..];
+    let rest = rest.strip_prefix("//").unwrap_or(rest);
+    let rest = rest.split_once('#').map_or(rest, |(rest, _)| rest);
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    match action.trim_end_matches('/') {
+        "set-key" => {}
+        other => return Err(DeepLinkError::UnknownAction(other.to_string())),
+    }
+    let mut key = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if name == "key" {
+            if key.is_some() {
+                return Err(DeepLinkError::Malformed("more than one key".to_string()));
+            }
+            key = Some(decode_query_value(value)?);
+        }
+    }
+    let key = key
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .ok_or(DeepLinkError::MissingKey)?;
+    api_key::check_format(&key).map_err(DeepLinkError::InvalidKey)?;
+    Ok(DeepLink::SetKey { key })
+}
+
+/// Undo percent-encoding
+fn decode_query_value(value: &str) -> Result<String, DeepLinkError> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| DeepLinkError::Malformed("bad percent-encoding".to_string()))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| DeepLinkError::Malformed("not UTF-8".to_string()))
+}
+
+/// Act on `link`: show the window and pass its key to the frontend
+pub fn handle_link(app: &AppHandle, link: &str) {
+    match parse(link) {
+        Ok(DeepLink::SetKey { key }) => {
+            println!("[deep-link] Received an API key to confirm");
+            let received = app.state::<ReceivedKey>();
+            *received.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(key.clone());
+            tray::show_main_window(app);
+            let _ = app.emit(KEY_RECEIVED_EVENT, &KeyReceivedEvent { key });
+        }
+        Err(e) => eprintln!("[deep-link] Ignored a link: {}", e),
+    }
+}
+
+/// Handle links the OS opens, and the one this launch was started with
+pub fn install(app: &AppHandle) {
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    app.manage(ReceivedKey::default());
+    // Installed builds are registered by their bundle; this covers the rest
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(e) = app.deep_link().register_all() {
+        eprintln!("[deep-link] Could not register {}:// links: {}", SCHEME, e);
+    }
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            handle_link(&handle, url.as_str());
+        }
+    });
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => {
+            for url in urls {
+                handle_link(app, url.as_str());
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("[deep-link] Could not read the launch link: {}", e),
+    }
+}
+
+// Tauri commands
+/// The key of the last link, once; for a window that wasn't listening yet
+#[tauri::command]
+pub fn take_received_key(received: State<'_, ReceivedKey>) -> Option<String> {
+    received.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
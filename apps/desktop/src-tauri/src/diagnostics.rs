@@ -0,0 +1,249 @@
+//! Diagnostics Export
+//!
+//! `export_diagnostics` writes what a bug report about a gateway that
+//! won't start needs into one zip, so people attach a file instead of
+//! answering questions:
+//!
+//! - `diagnostics.json`: the app version, OS and architecture, the
+//!   openclaw a start would run with its version, the directories searched
+//!   for it, the `GatewayStatus`, and with a gateway running the
+//!   `connectivity` report, and how the last gateway was launched
+//! - `config.json`: the config as `get_config` shows it
+//! - `gateway.log`: the newest `LOG_LINES` lines of gateway output
+//!
+//! No secret may end up in the archive. The config is redacted like
+//! `get_config`, `token=` parameters like in the logs, and as a last pass
+//! every API key and gateway token the app knows of is replaced wherever it
+//! still appears, in every file, before anything is written.
+
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+
+use crate::config::{Config, ConfigView};
+use crate::connection;
+use crate::connectivity::{self, ConnectivityReport};
+use crate::error::AppError;
+use crate::gateway_logs;
+use crate::openclaw_version::VersionInfo;
+use crate::resolve::SearchContext;
+use crate::sidecar::{GatewayStatus, LaunchDescriptor, SidecarManager};
+
+/// How many lines of gateway output are included
+pub const LOG_LINES: usize = 1000;
+
+/// What a secret is replaced with
+const REDACTED: &str = "[redacted]";
+
+/// Secrets shorter than this aren't replaced, as they would match all over
+const MIN_SECRET_LEN: usize = 4;
+
+#[derive(Error, Debug)]
+#[error("could not write {}: {error}", path.display())]
+pub struct DiagnosticsError {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+/// The openclaw a start would run and where it was looked for
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenclawDiagnostics {
+    /// openclaw itself, or node for npx
+    pub program: Option<String>,
+    /// Why there is none
+    pub error: Option<String>,
+    pub version: Option<VersionInfo>,
+    pub searched: Vec<String>,
+}
+
+/// `diagnostics.json`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    /// Unix timestamp (ms) of the export
+    pub created_at: i64,
+    pub openclaw: OpenclawDiagnostics,
+    pub status: GatewayStatus,
+    /// Whether clients can reach the gateway; None when none runs
+    pub connectivity: Option<ConnectivityReport>,
+    /// How the most recent gateway was launched, also after it exited
+    pub launch: Option<LaunchDescriptor>,
+}
+
+/// The files of the archive, named, before secrets are scrubbed
+pub fn collect(
+    manager: &SidecarManager,
+    config: &Config,
+    status: &GatewayStatus,
+    app_version: &str,
+) -> Vec<(&'static str, String)> {
+    let program = manager.find_program(config);
+    let mut searched: Vec<String> = match program {
+        Err(AppError::GatewayNotFound { ref searched, .. }) => searched.clone(),
+        _ => Vec::new(),
+    };
+    if searched.is_empty() {
+        searched = SearchContext::current(config)
+            .searched_dirs()
+            .iter()
+            .map(|dir| dir.display().to_string())
+            .collect();
+    }
+    let openclaw = OpenclawDiagnostics {
+        version: program
+            .is_ok()
+            .then(|| manager.openclaw_version().ok())
+            .flatten(),
+        error: program.as_ref().err().map(ToString::to_string),
+        program: program.ok(),
+        searched,
+    };
+    let diagnostics = Diagnostics {
+        app_version: app_version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        created_at: gateway_logs::now_ms(),
+        openclaw,
+        status: status.clone(),
+        connectivity: connectivity::run(manager).ok(),
+        launch: manager.launch_info(),
+    };
+
+    let view = ConfigView::new(config.clone(), diagnostics.openclaw.program.clone());
+    let mut log = String::new();
+    for line in manager.logs().recent(Some(LOG_LINES)) {
+        log.push_str(&format!(
+            "{}\t{}\t{}\n",
+            line.timestamp.unwrap_or_default(),
+            line.stream,
+            line.text
+        ));
+    }
+    vec![
+        ("diagnostics.json", pretty(&diagnostics)),
+        ("config.json", pretty(&view)),
+        ("gateway.log", log),
+    ]
+}
+
+fn pretty(value: &impl Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Every API key and gateway token in `config` and the running gateway's
+pub fn secrets(config: &Config, status: &GatewayStatus) -> Vec<String> {
+    let mut secrets: Vec<String> = config
+        .anthropic_api_key
+        .iter()
+        .chain(config.provider_keys.values())
+        .chain(config.profiles.values().filter_map(|p| p.api_key.as_ref()))
+        .chain(config.api_keys.iter().filter_map(|k| k.key.as_ref()))
+        .chain(config.external_gateway.as_ref().map(|e| &e.token))
+        .chain(config.gateway_token.as_ref())
+        .chain(status.info.as_ref().map(|info| &info.token))
+        .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+        .cloned()
+        .collect();
+    // Longest first, so a key containing another is replaced whole
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    secrets.dedup();
+    secrets
+}
+
+/// `text` with `token=` parameters and every one of `secrets` replaced
+pub fn scrub(text: &str, secrets: &[String]) -> String {
+    let mut text = connection::redact_tokens(text);
+    for secret in secrets {
+        text = text.replace(secret.as_str(), REDACTED);
+    }
+    text
+}
+
+/// Write `files` to the zip `path`, scrubbed of `secrets`
+pub fn write_archive(
+    path: &Path,
+    files: &[(&str, String)],
+    secrets: &[String],
+) -> Result<(), DiagnosticsError> {
+    let fail = |error: io::Error| DiagnosticsError {
+        path: path.to_path_buf(),
+        error,
+    };
+    // Scrubbed up front, so a secret is never written even to a partial file
+    let files: Vec<(&str, String)> = files
+        .iter()
+        .map(|(name, contents)| (*name, scrub(contents, secrets)))
+        .collect();
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(fail)?;
+    }
+    let mut zip = zip::ZipWriter::new(File::create(path).map_err(fail)?);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in &files {
+        zip.start_file(*name, options)
+            .map_err(|e| fail(io::Error::other(e)))?;
+        zip.write_all(contents.as_bytes()).map_err(fail)?;
+    }
+    zip.finish().map_err(|e| fail(io::Error::other(e)))?;
+    Ok(())
+}
+
+/// Where to write the archive: `dest` when it names a `.zip`, otherwise a
+/// new file in `dest` or the downloads folder
+pub fn archive_path(dest: Option<&Path>) -> PathBuf {
+    let name = format!(
+        "simplestclaw-diagnostics-{}.zip",
+        gateway_logs::now_ms() / 1000
+    );
+    match dest {
+        Some(dest)
+            if dest
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) =>
+        {
+            dest.to_path_buf()
+        }
+        Some(dest) => dest.join(name),
+        None => dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(std::env::temp_dir)
+            .join(name),
+    }
+}
+
+/// Collect the diagnostics of `manager` and write them to `archive_path(dest)`
+pub fn export(
+    manager: &SidecarManager,
+    app_version: &str,
+    dest: Option<&Path>,
+) -> Result<PathBuf, AppError> {
+    let config = manager.load_config()?;
+    let status = manager.status();
+    let files = collect(manager, &config, &status, app_version);
+    let secrets = secrets(&config, &status);
+    let path = archive_path(dest);
+    write_archive(&path, &files, &secrets)?;
+    println!("[diagnostics] Exported to {}", path.display());
+    Ok(path)
+}
+
+/// Write a diagnostics zip for a bug report, to `dest` (a `.zip`, or a
+/// folder to put one in) or the downloads folder, returning its path
+#[tauri::command]
+pub async fn export_diagnostics(app: AppHandle, dest: Option<String>) -> Result<String, AppError> {
+    let version = app.package_info().version.to_string();
+    tokio::task::spawn_blocking(move || {
+        let manager = app.state::<SidecarManager>();
+        export(&manager, &version, dest.as_deref().map(Path::new))
+            .map(|path| path.display().to_string())
+    })
+    .await?
+}
@@ -0,0 +1,277 @@
+//! Gateway Environment
+//!
+//! By default the gateway inherits the app's whole environment, so stray
+//! `ANTHROPIC_*`, `HTTP_PROXY` or `NODE_OPTIONS` variables from the user's
+//! session silently change its behaviour. With `cleanEnvironment` enabled
+//! the gateway starts from an empty environment instead: only the
+//! variables in `ALLOWED_VARS` (plus the locale) are taken from the app,
+//! and everything else is what `SidecarManager` injects explicitly. That
+//! includes the developer variables from `shell_env` when `forwardDevEnv`
+//! is on, which effectively extends the allow-list.
+//!
+//! The spawn path records every variable it sets in a `GatewayEnv`, with
+//! where it came from, so `get_effective_gateway_env` can show what the
+//! gateway got. Only the values of `VISIBLE_VARS` are shown; everything
+//! else is reported by length.
+
+use serde::{Deserialize, Serialize};
+
+use crate::anthropic_endpoint;
+use crate::gateway_state;
+use crate::log_level;
+use crate::models;
+use crate::spend;
+
+/// Variables passed through in clean mode. The Windows ones are needed for
+/// node to start at all (`SystemRoot`) or to find the user's profile.
+pub const ALLOWED_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "TMPDIR",
+    "LANG",
+    "LANGUAGE",
+    // Windows
+    "SystemRoot",
+    "SystemDrive",
+    "windir",
+    "ComSpec",
+    "PATHEXT",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "APPDATA",
+    "LOCALAPPDATA",
+];
+
+/// Whether `name` is passed through from the app in clean mode.
+/// Windows variable names are case-insensitive.
+pub fn is_allowed(name: &str) -> bool {
+    name.starts_with("LC_") || listed(ALLOWED_VARS, name)
+}
+
+fn listed(list: &[&str], name: &str) -> bool {
+    list.iter().any(|listed| {
+        if cfg!(windows) {
+            listed.eq_ignore_ascii_case(name)
+        } else {
+            *listed == name
+        }
+    })
+}
+
+/// The complete environment for a clean-mode gateway: the allowed
+/// variables from `inherited`, overridden by `injected`
+pub fn clean_env(
+    inherited: impl IntoIterator<Item = (String, String)>,
+    injected: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut env = allowed(inherited, |name| {
+        injected.iter().any(|(injected, _)| injected == name)
+    });
+    env.extend(injected.iter().cloned());
+    env
+}
+
+/// The allowed variables from `inherited` that aren't `overridden`, sorted
+fn allowed(
+    inherited: impl IntoIterator<Item = (String, String)>,
+    overridden: impl Fn(&str) -> bool,
+) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = inherited
+        .into_iter()
+        .filter(|(name, _)| is_allowed(name) && !overridden(name))
+        .collect();
+    env.sort();
+    env
+}
+
+/// The app's own environment, skipping variables that aren't valid UTF-8
+pub fn app_env() -> impl Iterator<Item = (String, String)> {
+    std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+}
+
+/// Variables whose values are shown in full. They point at directories or
+/// hosts; anything that may hold a secret, proxy URLs included, is not
+/// listed.
+pub const VISIBLE_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "TMPDIR",
+    "LANG",
+    "LANGUAGE",
+    "TZ",
+    "NO_PROXY",
+    "no_proxy",
+    "NODE_OPTIONS",
+    gateway_state::STATE_DIR_ENV,
+    models::MODEL_ENV,
+    models::SMALL_MODEL_ENV,
+    log_level::LOG_LEVEL_ENV,
+    spend::SPEND_LIMIT_ENV,
+    anthropic_endpoint::BASE_URL_ENV,
+    // Windows
+    "SystemRoot",
+    "SystemDrive",
+    "windir",
+    "ComSpec",
+    "PATHEXT",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "APPDATA",
+    "LOCALAPPDATA",
+];
+
+pub const REDACTED: &str = "\u{2022}\u{2022}\u{2022}";
+
+pub fn is_visible(name: &str) -> bool {
+    name.starts_with("LC_") || listed(VISIBLE_VARS, name)
+}
+
+/// What set a variable the gateway gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvSource {
+    /// PATH with the node runtime's directory first
+    Runtime,
+    /// The gateway token and the provider's API key
+    Credentials,
+    /// The heap limit and OpenSSL flag in `NODE_OPTIONS`
+    NodeOptions,
+    Sandbox,
+    GatewayState,
+    RunAs,
+    Locale,
+    /// `defaultModel` and `smallModel`
+    Model,
+    /// `gatewayLogLevel`
+    LogLevel,
+    /// `spendLimitUsd`
+    SpendLimit,
+    /// `proxy`, or the app's own proxy variables
+    Proxy,
+    /// `anthropicBaseUrl` and `anthropicAuthHeader`
+    AnthropicEndpoint,
+    /// `forwardDevEnv`
+    DevEnv,
+    /// `gatewayExtraEnv`
+    ExtraEnv,
+    /// Passed through from the app by clean mode's `ALLOWED_VARS`
+    AllowList,
+}
+
+/// The variables set for a gateway spawn, in order, each with its source.
+/// Setting a variable again replaces it.
+#[derive(Debug, Clone, Default)]
+pub struct GatewayEnv {
+    vars: Vec<(String, String, EnvSource)>,
+}
+
+impl GatewayEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>, source: EnvSource) {
+        let name = name.into();
+        self.vars.retain(|(existing, _, _)| *existing != name);
+        self.vars.push((name, value.into(), source));
+    }
+
+    pub fn extend(&mut self, vars: impl IntoIterator<Item = (String, String)>, source: EnvSource) {
+        for (name, value) in vars {
+            self.set(name, value, source);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars
+            .iter()
+            .find(|(existing, _, _)| existing == name)
+            .map(|(_, value, _)| value.as_str())
+    }
+
+    pub fn source(&self, name: &str) -> Option<EnvSource> {
+        self.vars
+            .iter()
+            .find(|(existing, _, _)| existing == name)
+            .map(|(_, _, source)| *source)
+    }
+
+    /// The variables to pass to the process
+    pub fn vars(&self) -> Vec<(String, String)> {
+        self.vars
+            .iter()
+            .map(|(name, value, _)| (name.clone(), value.clone()))
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.vars.iter().map(|(name, _, _)| name.clone()).collect()
+    }
+
+    /// `clean_env` for these variables: the allowed ones from `inherited`
+    /// first, then these
+    pub fn into_clean(self, inherited: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut vars: Vec<(String, String, EnvSource)> =
+            allowed(inherited, |name| self.get(name).is_some())
+                .into_iter()
+                .map(|(name, value)| (name, value, EnvSource::AllowList))
+                .collect();
+        vars.extend(self.vars);
+        Self { vars }
+    }
+
+    /// Every variable with its value redacted unless `is_visible`
+    pub fn report(&self) -> Vec<EnvVarReport> {
+        self.vars
+            .iter()
+            .map(|(name, value, source)| {
+                let redacted = !is_visible(name);
+                EnvVarReport {
+                    name: name.clone(),
+                    source: *source,
+                    value: if redacted {
+                        REDACTED.to_string()
+                    } else {
+                        value.clone()
+                    },
+                    redacted,
+                    length: value.chars().count(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A variable the gateway got, safe to display
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvVarReport {
+    pub name: String,
+    pub source: EnvSource,
+    /// The value, or `REDACTED` when `redacted`
+    pub value: String,
+    pub redacted: bool,
+    /// Length of the real value in characters
+    pub length: usize,
+}
+
+/// What the app set in a gateway's environment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveEnv {
+    /// From the most recent spawn; otherwise what the next start would set
+    pub spawned: bool,
+    /// Whether `vars` is the whole environment rather than what was set on
+    /// top of the app's own
+    pub clean_environment: bool,
+    pub vars: Vec<EnvVarReport>,
+}
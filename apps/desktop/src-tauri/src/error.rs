@@ -0,0 +1,718 @@
+//! Command Errors
+//!
+//! `AppError` is the error type returned by Tauri commands. Every variant
+//! has a stable `code` and string `data`, and is serialized as
+//!
+//! ```json
+//! { "code": "unknown_provider", "data": { "provider": "foo" }, "message": "Unknown provider: foo" }
+//! ```
+//!
+//! so the frontend can localize by code and fall back to the English
+//! `message` from the catalog in `messages`. The frontend should decide
+//! what to show from `code` and `data` only; messages may be reworded.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::activity::ActivityError;
+use crate::anthropic_endpoint::EndpointError;
+use crate::blocked_tools::BlockedToolsError;
+use crate::capabilities::CapabilityError;
+use crate::cli_import::CliImportError;
+use crate::config::ConfigError;
+use crate::config_transfer::{ExportError, ImportError};
+use crate::diagnostics::DiagnosticsError;
+use crate::external::ExternalGatewayError;
+use crate::gateway_options::GatewayOptionsError;
+use crate::installer::InstallError;
+use crate::keychain::KeychainError;
+use crate::locale::LocaleError;
+use crate::log_level::LogLevelError;
+use crate::log_search::SearchError;
+use crate::login_item::LoginItemError;
+use crate::messages;
+use crate::models::ModelError;
+use crate::migrations::MigrationError;
+use crate::openclaw_version::VersionError;
+use crate::paths::InsufficientSpace;
+use crate::profiles::ProfileError;
+use crate::stored_keys::StoredKeyError;
+use crate::proxy::ProxyError;
+use crate::tls::TlsError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    ConfigDirUnavailable,
+    ConfigIo { detail: String },
+    /// The config dir can't be written, so settings can't be saved
+    ConfigReadOnly { path: String },
+    /// The config dir can't be created
+    ConfigUnavailable { path: String, detail: String },
+    ConfigInvalid { detail: String },
+    /// config.json is from a newer app; both are config versions
+    ConfigTooNew { version: String, supported: String },
+    ConfigExportFailed { path: String, detail: String },
+    /// The file to import can't be read or isn't an export
+    ConfigImportFailed { detail: String },
+    CliConfigNotFound,
+    CliConfigUnreadable { path: String, detail: String },
+    /// `variable` is the environment variable, e.g. `SIMPLESTCLAW_GATEWAY_PORT`
+    InvalidConfigOverride { variable: String, value: String, detail: String },
+    UnknownProvider { provider: String },
+    InvalidPort { port: String },
+    InvalidGatewayHost { host: String },
+    UnknownTool { tool: String },
+    InvalidLocale { locale: String },
+    InvalidTimezone { timezone: String },
+    InvalidOpenclawPath { path: String },
+    /// `field` is the config field, e.g. `gatewayExtraArgs`
+    InvalidGatewayOption { field: String, detail: String },
+    /// `field` is the setting in `set_config`'s patch, e.g. `gatewayPort`
+    InvalidSetting { field: String, detail: String },
+    InvalidProxyUrl { url: String },
+    InvalidExternalGatewayUrl { url: String },
+    UnknownProfile { name: String },
+    ProfileExists { name: String },
+    InvalidProfileName { name: String },
+    ProfileActive { name: String },
+    UnknownApiKey { label: String },
+    InvalidApiKeyLabel { label: String },
+    /// The stored key `label` is the one in use
+    ApiKeyInUse { label: String },
+    ApiKeyMissing,
+    /// The key in config.json was sealed on another machine
+    ApiKeyUnreadable,
+    LanTokenRequired,
+    ApiKeyRejected { detail: String },
+    KeychainFailed { detail: String },
+    LaunchAtLoginFailed { detail: String },
+    ToolBlockUnsupported { tool: String, version: String },
+    GatewayFlagUnsupported { flag: String, version: String },
+    OpenclawTooOld { version: String, required: String },
+    SessionLimitReached { limit: String },
+    DiskFull { path: String, available: String, required: String },
+    DataDirUnavailable,
+    ActivityLogFailed { detail: String },
+    RuntimeNotInstalled,
+    /// `searched` lists the directories looked in, in order
+    GatewayNotFound { detail: String, searched: Vec<String> },
+    PortInUse { port: String },
+    /// Another of the app's gateway instances serves `port`
+    PortInUseByInstance { port: String, instance: String },
+    /// `port` is privileged or refused by the OS; `suggested` is free
+    PortNotPermitted { port: String, suggested: String },
+    /// Something that isn't a leftover gateway held `port` for the last
+    /// `starts` starts; `suggested` is free
+    PortBlocked { port: String, suggested: String, starts: String },
+    /// An OpenClaw gateway the app didn't start serves `port`; `pid` is
+    /// empty when it couldn't be found
+    ExternalGatewayDetected { port: String, pid: String },
+    /// `transport` is the config value, e.g. `unix`
+    TransportUnsupported { transport: String, detail: String },
+    /// A gateway still accepts connections on the socket at `path`
+    SocketInUse { path: String },
+    ExternalGatewayUnreachable { url: String, detail: String },
+    GatewaySpawnFailed { detail: String },
+    GatewayStartFailed { detail: String },
+    /// A stop came while the gateway was starting
+    GatewayStartCancelled,
+    AuthSelfTestFailed { detail: String },
+    /// `path` is the TLS certificate or key that can't be used
+    TlsConfigInvalid { path: String, detail: String },
+    GatewayStopFailed { detail: String },
+    GatewayNotRunning,
+    /// Pausing a process isn't available on this platform
+    GatewayPauseUnsupported,
+    GatewayPauseFailed { detail: String },
+    /// `allowExternalClients` is off, so the token isn't handed out
+    ExternalClientsNotAllowed,
+    ClipboardFailed { detail: String },
+    RuntimeInstallFailed { detail: String },
+    InstallerNotFound,
+    /// `path` is what npm may not write to, e.g. the global prefix
+    InstallPermissionDenied { path: String },
+    InstallNetworkFailed { detail: String },
+    InstallFailed { detail: String },
+    /// The npm registry couldn't be asked for the latest openclaw
+    UpdateCheckFailed { detail: String },
+    DeleteFailed { path: String, detail: String },
+    DiagnosticsExportFailed { path: String, detail: String },
+    PurgeNotConfirmed,
+    InvalidLogPattern { detail: String },
+    OpenLogsDirFailed { detail: String },
+    TaskFailed { detail: String },
+}
+
+impl AppError {
+    /// Stable machine-readable code, also the message catalog key
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::ConfigDirUnavailable => "config_dir_unavailable",
+            AppError::ConfigIo { .. } => "config_io",
+            AppError::ConfigReadOnly { .. } => "config_read_only",
+            AppError::ConfigUnavailable { .. } => "config_unavailable",
+            AppError::ConfigInvalid { .. } => "config_invalid",
+            AppError::ConfigTooNew { .. } => "config_too_new",
+            AppError::ConfigExportFailed { .. } => "config_export_failed",
+            AppError::ConfigImportFailed { .. } => "config_import_failed",
+            AppError::CliConfigNotFound => "cli_config_not_found",
+            AppError::CliConfigUnreadable { .. } => "cli_config_unreadable",
+            AppError::InvalidConfigOverride { .. } => "invalid_config_override",
+            AppError::UnknownProvider { .. } => "unknown_provider",
+            AppError::InvalidPort { .. } => "invalid_port",
+            AppError::InvalidGatewayHost { .. } => "invalid_gateway_host",
+            AppError::UnknownTool { .. } => "unknown_tool",
+            AppError::InvalidLocale { .. } => "invalid_locale",
+            AppError::InvalidTimezone { .. } => "invalid_timezone",
+            AppError::InvalidOpenclawPath { .. } => "invalid_openclaw_path",
+            AppError::InvalidGatewayOption { .. } => "invalid_gateway_option",
+            AppError::InvalidSetting { .. } => "invalid_setting",
+            AppError::InvalidProxyUrl { .. } => "invalid_proxy_url",
+            AppError::InvalidExternalGatewayUrl { .. } => "invalid_external_gateway_url",
+            AppError::UnknownProfile { .. } => "unknown_profile",
+            AppError::ProfileExists { .. } => "profile_exists",
+            AppError::InvalidProfileName { .. } => "invalid_profile_name",
+            AppError::ProfileActive { .. } => "profile_active",
+            AppError::UnknownApiKey { .. } => "unknown_api_key",
+            AppError::InvalidApiKeyLabel { .. } => "invalid_api_key_label",
+            AppError::ApiKeyInUse { .. } => "api_key_in_use",
+            AppError::ApiKeyMissing => "api_key_missing",
+            AppError::ApiKeyUnreadable => "api_key_unreadable",
+            AppError::LanTokenRequired => "lan_token_required",
+            AppError::ApiKeyRejected { .. } => "api_key_rejected",
+            AppError::KeychainFailed { .. } => "keychain_failed",
+            AppError::LaunchAtLoginFailed { .. } => "launch_at_login_failed",
+            AppError::ToolBlockUnsupported { .. } => "tool_block_unsupported",
+            AppError::GatewayFlagUnsupported { .. } => "gateway_flag_unsupported",
+            AppError::OpenclawTooOld { .. } => "openclaw_too_old",
+            AppError::SessionLimitReached { .. } => "session_limit_reached",
+            AppError::DiskFull { .. } => "disk_full",
+            AppError::DataDirUnavailable => "data_dir_unavailable",
+            AppError::ActivityLogFailed { .. } => "activity_log_failed",
+            AppError::RuntimeNotInstalled => "runtime_not_installed",
+            AppError::GatewayNotFound { .. } => "gateway_not_found",
+            AppError::PortInUse { .. } => "port_in_use",
+            AppError::PortInUseByInstance { .. } => "port_in_use_by_instance",
+            AppError::PortNotPermitted { .. } => "port_not_permitted",
+            AppError::PortBlocked { .. } => "port_blocked",
+            AppError::ExternalGatewayDetected { .. } => "external_gateway_detected",
+            AppError::TransportUnsupported { .. } => "transport_unsupported",
+            AppError::SocketInUse { .. } => "socket_in_use",
+            AppError::ExternalGatewayUnreachable { .. } => "external_gateway_unreachable",
+            AppError::GatewaySpawnFailed { .. } => "gateway_spawn_failed",
+            AppError::GatewayStartFailed { .. } => "gateway_start_failed",
+            AppError::GatewayStartCancelled => "gateway_start_cancelled",
+            AppError::AuthSelfTestFailed { .. } => "auth_self_test_failed",
+            AppError::TlsConfigInvalid { .. } => "tls_config_invalid",
+            AppError::GatewayStopFailed { .. } => "gateway_stop_failed",
+            AppError::GatewayNotRunning => "gateway_not_running",
+            AppError::GatewayPauseUnsupported => "gateway_pause_unsupported",
+            AppError::GatewayPauseFailed { .. } => "gateway_pause_failed",
+            AppError::ExternalClientsNotAllowed => "external_clients_not_allowed",
+            AppError::ClipboardFailed { .. } => "clipboard_failed",
+            AppError::RuntimeInstallFailed { .. } => "runtime_install_failed",
+            AppError::InstallerNotFound => "installer_not_found",
+            AppError::InstallPermissionDenied { .. } => "install_permission_denied",
+            AppError::InstallNetworkFailed { .. } => "install_network_failed",
+            AppError::InstallFailed { .. } => "install_failed",
+            AppError::UpdateCheckFailed { .. } => "update_check_failed",
+            AppError::DeleteFailed { .. } => "delete_failed",
+            AppError::DiagnosticsExportFailed { .. } => "diagnostics_export_failed",
+            AppError::PurgeNotConfirmed => "purge_not_confirmed",
+            AppError::InvalidLogPattern { .. } => "invalid_log_pattern",
+            AppError::OpenLogsDirFailed { .. } => "open_logs_dir_failed",
+            AppError::TaskFailed { .. } => "task_failed",
+        }
+    }
+
+    /// Parameters for the message template
+    pub fn data(&self) -> BTreeMap<String, String> {
+        let searched;
+        let pairs: Vec<(&str, &String)> = match self {
+            AppError::ConfigDirUnavailable
+            | AppError::CliConfigNotFound
+            | AppError::ApiKeyMissing
+            | AppError::ApiKeyUnreadable
+            | AppError::LanTokenRequired
+            | AppError::RuntimeNotInstalled
+            | AppError::DataDirUnavailable
+            | AppError::InstallerNotFound
+            | AppError::GatewayNotRunning
+            | AppError::GatewayPauseUnsupported
+            | AppError::ExternalClientsNotAllowed
+            | AppError::GatewayStartCancelled
+            | AppError::PurgeNotConfirmed => Vec::new(),
+            AppError::UnknownProvider { provider } => vec![("provider", provider)],
+            AppError::InvalidPort { port } | AppError::PortInUse { port } => vec![("port", port)],
+            AppError::UnknownTool { tool } => vec![("tool", tool)],
+            AppError::InvalidGatewayHost { host } => vec![("host", host)],
+            AppError::InvalidLocale { locale } => vec![("locale", locale)],
+            AppError::InvalidTimezone { timezone } => vec![("timezone", timezone)],
+            AppError::InvalidOpenclawPath { path }
+            | AppError::InstallPermissionDenied { path }
+            | AppError::SocketInUse { path }
+            | AppError::ConfigReadOnly { path } => vec![("path", path)],
+            AppError::InvalidProxyUrl { url } | AppError::InvalidExternalGatewayUrl { url } => {
+                vec![("url", url)]
+            }
+            AppError::UnknownProfile { name }
+            | AppError::ProfileExists { name }
+            | AppError::InvalidProfileName { name }
+            | AppError::ProfileActive { name } => vec![("name", name)],
+            AppError::UnknownApiKey { label }
+            | AppError::InvalidApiKeyLabel { label }
+            | AppError::ApiKeyInUse { label } => vec![("label", label)],
+            AppError::InvalidGatewayOption { field, detail }
+            | AppError::InvalidSetting { field, detail } => {
+                vec![("field", field), ("detail", detail)]
+            }
+            AppError::SessionLimitReached { limit } => vec![("limit", limit)],
+            AppError::ToolBlockUnsupported { tool, version } => {
+                vec![("tool", tool), ("version", version)]
+            }
+            AppError::GatewayFlagUnsupported { flag, version } => {
+                vec![("flag", flag), ("version", version)]
+            }
+            AppError::OpenclawTooOld { version, required } => {
+                vec![("version", version), ("required", required)]
+            }
+            AppError::DiskFull { path, available, required } => {
+                vec![("path", path), ("available", available), ("required", required)]
+            }
+            AppError::DeleteFailed { path, detail }
+            | AppError::DiagnosticsExportFailed { path, detail }
+            | AppError::ConfigExportFailed { path, detail }
+            | AppError::CliConfigUnreadable { path, detail }
+            | AppError::TlsConfigInvalid { path, detail }
+            | AppError::ConfigUnavailable { path, detail } => {
+                vec![("path", path), ("detail", detail)]
+            }
+            AppError::ConfigTooNew { version, supported } => {
+                vec![("version", version), ("supported", supported)]
+            }
+            AppError::InvalidConfigOverride { variable, value, detail } => {
+                vec![("variable", variable), ("value", value), ("detail", detail)]
+            }
+            AppError::PortInUseByInstance { port, instance } => {
+                vec![("port", port), ("instance", instance)]
+            }
+            AppError::PortNotPermitted { port, suggested } => {
+                vec![("port", port), ("suggested", suggested)]
+            }
+            AppError::PortBlocked { port, suggested, starts } => {
+                vec![("port", port), ("suggested", suggested), ("starts", starts)]
+            }
+            AppError::ExternalGatewayDetected { port, pid } => vec![("port", port), ("pid", pid)],
+            AppError::TransportUnsupported { transport, detail } => {
+                vec![("transport", transport), ("detail", detail)]
+            }
+            AppError::ExternalGatewayUnreachable { url, detail } => {
+                vec![("url", url), ("detail", detail)]
+            }
+            // One directory per line
+            AppError::GatewayNotFound { detail, searched: dirs } => {
+                searched = dirs.join("\n");
+                vec![("detail", detail), ("searched", &searched)]
+            }
+            AppError::ConfigIo { detail }
+            | AppError::ConfigInvalid { detail }
+            | AppError::ConfigImportFailed { detail }
+            | AppError::ActivityLogFailed { detail }
+            | AppError::ApiKeyRejected { detail }
+            | AppError::KeychainFailed { detail }
+            | AppError::LaunchAtLoginFailed { detail }
+            | AppError::GatewaySpawnFailed { detail }
+            | AppError::GatewayStartFailed { detail }
+            | AppError::AuthSelfTestFailed { detail }
+            | AppError::GatewayStopFailed { detail }
+            | AppError::GatewayPauseFailed { detail }
+            | AppError::ClipboardFailed { detail }
+            | AppError::RuntimeInstallFailed { detail }
+            | AppError::InstallNetworkFailed { detail }
+            | AppError::InstallFailed { detail }
+            | AppError::UpdateCheckFailed { detail }
+            | AppError::InvalidLogPattern { detail }
+            | AppError::OpenLogsDirFailed { detail }
+            | AppError::TaskFailed { detail } => vec![("detail", detail)],
+        };
+        pairs
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    /// English fallback text
+    pub fn message(&self) -> String {
+        messages::render(self.code(), &self.data())
+    }
+
+    /// One value of every variant. Keep in sync when adding variants; the
+    /// catalog tests rely on it.
+    pub fn examples() -> Vec<AppError> {
+        let detail = || "detail".to_string();
+        vec![
+            AppError::ConfigDirUnavailable,
+            AppError::ConfigIo { detail: detail() },
+            AppError::ConfigReadOnly { path: "path".to_string() },
+            AppError::ConfigUnavailable { path: "path".to_string(), detail: detail() },
+            AppError::ConfigInvalid { detail: detail() },
+            AppError::ConfigTooNew { version: "2".to_string(), supported: "1".to_string() },
+            AppError::ConfigExportFailed { path: "path".to_string(), detail: detail() },
+            AppError::ConfigImportFailed { detail: detail() },
+            AppError::CliConfigNotFound,
+            AppError::CliConfigUnreadable { path: "path".to_string(), detail: detail() },
+            AppError::InvalidConfigOverride {
+                variable: "SIMPLESTCLAW_GATEWAY_PORT".to_string(),
+                value: "0".to_string(),
+                detail: detail(),
+            },
+            AppError::UnknownProvider { provider: "provider".to_string() },
+            AppError::InvalidPort { port: "0".to_string() },
+            AppError::InvalidGatewayHost { host: "host".to_string() },
+            AppError::UnknownTool { tool: "tool".to_string() },
+            AppError::InvalidLocale { locale: "locale".to_string() },
+            AppError::InvalidTimezone { timezone: "timezone".to_string() },
+            AppError::InvalidOpenclawPath { path: "path".to_string() },
+            AppError::InvalidGatewayOption { field: "gatewayExtraArgs".to_string(), detail: detail() },
+            AppError::InvalidSetting { field: "gatewayPort".to_string(), detail: detail() },
+            AppError::InvalidProxyUrl { url: "url".to_string() },
+            AppError::InvalidExternalGatewayUrl { url: "url".to_string() },
+            AppError::UnknownProfile { name: "name".to_string() },
+            AppError::ProfileExists { name: "name".to_string() },
+            AppError::InvalidProfileName { name: "name".to_string() },
+            AppError::ProfileActive { name: "name".to_string() },
+            AppError::UnknownApiKey { label: "label".to_string() },
+            AppError::InvalidApiKeyLabel { label: "label".to_string() },
+            AppError::ApiKeyInUse { label: "label".to_string() },
+            AppError::ApiKeyMissing,
+            AppError::ApiKeyUnreadable,
+            AppError::LanTokenRequired,
+            AppError::ApiKeyRejected { detail: detail() },
+            AppError::KeychainFailed { detail: detail() },
+            AppError::LaunchAtLoginFailed { detail: detail() },
+            AppError::ToolBlockUnsupported { tool: "tool".to_string(), version: "version".to_string() },
+            AppError::GatewayFlagUnsupported { flag: "--flag".to_string(), version: "version".to_string() },
+            AppError::OpenclawTooOld { version: "2025.12.1".to_string(), required: "2026.1.20".to_string() },
+            AppError::SessionLimitReached { limit: "2".to_string() },
+            AppError::DiskFull {
+                path: "path".to_string(),
+                available: "1 MB".to_string(),
+                required: "100 MB".to_string(),
+            },
+            AppError::DataDirUnavailable,
+            AppError::ActivityLogFailed { detail: detail() },
+            AppError::RuntimeNotInstalled,
+            AppError::GatewayNotFound { detail: detail(), searched: vec!["/usr/bin".to_string()] },
+            AppError::PortInUse { port: "18789".to_string() },
+            AppError::PortInUseByInstance { port: "18789".to_string(), instance: "work".to_string() },
+            AppError::PortNotPermitted { port: "443".to_string(), suggested: "18789".to_string() },
+            AppError::PortBlocked {
+                port: "5432".to_string(),
+                suggested: "18789".to_string(),
+                starts: "3".to_string(),
+            },
+            AppError::ExternalGatewayDetected {
+                port: "18789".to_string(),
+                pid: "4242".to_string(),
+            },
+            AppError::TransportUnsupported { transport: "unix".to_string(), detail: detail() },
+            AppError::SocketInUse { path: "/run/user/1000/simplestclaw/gateway.sock".to_string() },
+            AppError::ExternalGatewayUnreachable { url: "ws://localhost:18789".to_string(), detail: detail() },
+            AppError::GatewaySpawnFailed { detail: detail() },
+            AppError::GatewayStartFailed { detail: detail() },
+            AppError::GatewayStartCancelled,
+            AppError::AuthSelfTestFailed { detail: detail() },
+            AppError::TlsConfigInvalid { path: "path".to_string(), detail: detail() },
+            AppError::GatewayStopFailed { detail: detail() },
+            AppError::GatewayNotRunning,
+            AppError::GatewayPauseUnsupported,
+            AppError::GatewayPauseFailed { detail: detail() },
+            AppError::ExternalClientsNotAllowed,
+            AppError::ClipboardFailed { detail: detail() },
+            AppError::RuntimeInstallFailed { detail: detail() },
+            AppError::InstallerNotFound,
+            AppError::InstallPermissionDenied { path: "/usr/local/lib/node_modules".to_string() },
+            AppError::InstallNetworkFailed { detail: detail() },
+            AppError::InstallFailed { detail: detail() },
+            AppError::UpdateCheckFailed { detail: detail() },
+            AppError::DeleteFailed { path: "path".to_string(), detail: detail() },
+            AppError::DiagnosticsExportFailed { path: "path".to_string(), detail: detail() },
+            AppError::PurgeNotConfirmed,
+            AppError::InvalidLogPattern { detail: detail() },
+            AppError::OpenLogsDirFailed { detail: detail() },
+            AppError::TaskFailed { detail: detail() },
+        ]
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = self.data();
+        let mut state = serializer.serialize_struct("AppError", 4)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &messages::render(self.code(), &data))?;
+        // The cause, in English whatever the message is translated to
+        state.serialize_field("detail", &data.get("detail"))?;
+        state.serialize_field("data", &data)?;
+        state.end()
+    }
+}
+
+impl From<ConfigError> for AppError {
+    fn from(e: ConfigError) -> Self {
+        match e {
+            ConfigError::NoConfigDir => AppError::ConfigDirUnavailable,
+            ConfigError::Io(e) => AppError::ConfigIo { detail: e.to_string() },
+            ConfigError::Json(e) => AppError::ConfigInvalid { detail: e.to_string() },
+            ConfigError::ReadOnly { path } => AppError::ConfigReadOnly {
+                path: path.display().to_string(),
+            },
+            ConfigError::Unavailable { path, detail } => AppError::ConfigUnavailable {
+                path: path.display().to_string(),
+                detail,
+            },
+            ConfigError::Migration(MigrationError::TooNew { found, supported }) => {
+                AppError::ConfigTooNew {
+                    version: found.to_string(),
+                    supported: supported.to_string(),
+                }
+            }
+            ConfigError::InvalidOverride { variable, value, detail } => {
+                AppError::InvalidConfigOverride { variable, value, detail }
+            }
+        }
+    }
+}
+
+impl From<ImportError> for AppError {
+    fn from(e: ImportError) -> Self {
+        match e {
+            ImportError::Migration(MigrationError::TooNew { found, supported }) => {
+                AppError::ConfigTooNew {
+                    version: found.to_string(),
+                    supported: supported.to_string(),
+                }
+            }
+            e => AppError::ConfigImportFailed { detail: e.to_string() },
+        }
+    }
+}
+
+impl From<CliImportError> for AppError {
+    fn from(e: CliImportError) -> Self {
+        match e {
+            CliImportError::NotFound => AppError::CliConfigNotFound,
+            CliImportError::Unreadable { path, detail } => AppError::CliConfigUnreadable {
+                path: path.display().to_string(),
+                detail,
+            },
+        }
+    }
+}
+
+impl From<ExportError> for AppError {
+    fn from(e: ExportError) -> Self {
+        AppError::ConfigExportFailed {
+            path: e.path.display().to_string(),
+            detail: e.error.to_string(),
+        }
+    }
+}
+
+impl From<KeychainError> for AppError {
+    fn from(e: KeychainError) -> Self {
+        AppError::KeychainFailed { detail: e.to_string() }
+    }
+}
+
+impl From<ActivityError> for AppError {
+    fn from(e: ActivityError) -> Self {
+        match e {
+            ActivityError::NoDataDir => AppError::DataDirUnavailable,
+            e => AppError::ActivityLogFailed { detail: e.to_string() },
+        }
+    }
+}
+
+impl From<BlockedToolsError> for AppError {
+    fn from(e: BlockedToolsError) -> Self {
+        match e {
+            BlockedToolsError::UnknownTool(tool) => AppError::UnknownTool { tool },
+            BlockedToolsError::Unsupported { tool, version } => {
+                AppError::ToolBlockUnsupported { tool, version }
+            }
+        }
+    }
+}
+
+impl From<CapabilityError> for AppError {
+    fn from(e: CapabilityError) -> Self {
+        match e {
+            CapabilityError::Unsupported { flag, version } => {
+                AppError::GatewayFlagUnsupported { flag, version }
+            }
+        }
+    }
+}
+
+impl From<VersionError> for AppError {
+    fn from(e: VersionError) -> Self {
+        match e {
+            VersionError::TooOld { found, required } => AppError::OpenclawTooOld {
+                version: found,
+                required,
+            },
+        }
+    }
+}
+
+impl From<LocaleError> for AppError {
+    fn from(e: LocaleError) -> Self {
+        match e {
+            LocaleError::InvalidLocale(locale) => AppError::InvalidLocale { locale },
+            LocaleError::InvalidTimezone(timezone) => AppError::InvalidTimezone { timezone },
+        }
+    }
+}
+
+impl From<InsufficientSpace> for AppError {
+    fn from(e: InsufficientSpace) -> Self {
+        AppError::DiskFull {
+            path: e.path.display().to_string(),
+            available: crate::config::format_bytes(e.available),
+            required: crate::config::format_bytes(e.required),
+        }
+    }
+}
+
+impl From<GatewayOptionsError> for AppError {
+    fn from(e: GatewayOptionsError) -> Self {
+        AppError::InvalidGatewayOption { field: e.field().to_string(), detail: e.to_string() }
+    }
+}
+
+impl From<ProfileError> for AppError {
+    fn from(e: ProfileError) -> Self {
+        match e {
+            ProfileError::Unknown(name) => AppError::UnknownProfile { name },
+            ProfileError::Exists(name) => AppError::ProfileExists { name },
+            ProfileError::InvalidName(name) => AppError::InvalidProfileName { name },
+            ProfileError::Active(name) => AppError::ProfileActive { name },
+            ProfileError::Keychain(e) => e.into(),
+        }
+    }
+}
+
+impl From<StoredKeyError> for AppError {
+    fn from(e: StoredKeyError) -> Self {
+        match e {
+            StoredKeyError::Unknown(label) => AppError::UnknownApiKey { label },
+            StoredKeyError::InvalidLabel(label) => AppError::InvalidApiKeyLabel { label },
+            StoredKeyError::Active(label) => AppError::ApiKeyInUse { label },
+            StoredKeyError::Keychain(e) => e.into(),
+        }
+    }
+}
+
+impl From<InstallError> for AppError {
+    fn from(e: InstallError) -> Self {
+        match e {
+            InstallError::NoInstaller => AppError::InstallerNotFound,
+            InstallError::PermissionDenied { path } => AppError::InstallPermissionDenied { path },
+            InstallError::Network(detail) => AppError::InstallNetworkFailed { detail },
+            InstallError::Failed(detail) => AppError::InstallFailed { detail },
+        }
+    }
+}
+
+impl From<LoginItemError> for AppError {
+    fn from(e: LoginItemError) -> Self {
+        AppError::LaunchAtLoginFailed { detail: e.to_string() }
+    }
+}
+
+impl From<DiagnosticsError> for AppError {
+    fn from(e: DiagnosticsError) -> Self {
+        AppError::DiagnosticsExportFailed {
+            path: e.path.display().to_string(),
+            detail: e.error.to_string(),
+        }
+    }
+}
+
+impl From<TlsError> for AppError {
+    fn from(e: TlsError) -> Self {
+        AppError::TlsConfigInvalid {
+            path: e.path.display().to_string(),
+            detail: e.detail,
+        }
+    }
+}
+
+impl From<LogLevelError> for AppError {
+    fn from(e: LogLevelError) -> Self {
+        AppError::InvalidSetting {
+            field: "gatewayLogLevel".to_string(),
+            detail: e.to_string(),
+        }
+    }
+}
+
+impl From<ModelError> for AppError {
+    fn from(e: ModelError) -> Self {
+        AppError::InvalidSetting {
+            field: e.field.to_string(),
+            detail: e.to_string(),
+        }
+    }
+}
+
+impl From<EndpointError> for AppError {
+    fn from(e: EndpointError) -> Self {
+        AppError::InvalidSetting {
+            field: e.field().to_string(),
+            detail: e.to_string(),
+        }
+    }
+}
+
+impl From<ProxyError> for AppError {
+    fn from(e: ProxyError) -> Self {
+        match e {
+            ProxyError::InvalidUrl(url) => AppError::InvalidProxyUrl { url },
+        }
+    }
+}
+
+impl From<ExternalGatewayError> for AppError {
+    fn from(e: ExternalGatewayError) -> Self {
+        match e {
+            ExternalGatewayError::InvalidUrl(url) => AppError::InvalidExternalGatewayUrl { url },
+            ExternalGatewayError::Unreachable { url, detail } => {
+                AppError::ExternalGatewayUnreachable { url, detail }
+            }
+        }
+    }
+}
+
+impl From<SearchError> for AppError {
+    fn from(e: SearchError) -> Self {
+        AppError::InvalidLogPattern { detail: e.to_string() }
+    }
+}
+
+impl From<tokio::task::JoinError> for AppError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        AppError::TaskFailed { detail: e.to_string() }
+    }
+}
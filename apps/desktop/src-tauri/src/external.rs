@@ -0,0 +1,103 @@
+//! External Gateway
+//!
+//! With `externalGateway` set, the app runs no gateway of its own and
+//! connects to one managed elsewhere, such as `openclaw gateway` under
+//! systemd. A start only checks the configured URL accepts connections and
+//! hands it out with the configured token; a stop leaves it running, and
+//! the status reflects whether it can be reached. Nothing in the app ever
+//! signals it.
+//!
+//! A gateway the app started before switching to external mode is
+//! stopped on the switch, or at the latest on the next start, so it isn't
+//! left running unmanaged.
+
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::sidecar::GatewayInfo;
+
+/// A gateway the app connects to instead of starting one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalGateway {
+    /// Its WebSocket URL, like `ws://localhost:18789`
+    pub url: String,
+    /// The token it was started with
+    #[serde(default)]
+    pub token: String,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ExternalGatewayError {
+    #[error("{0} is not a gateway URL like ws://localhost:18789")]
+    InvalidUrl(String),
+    #[error("The gateway at {url} can't be reached: {detail}")]
+    Unreachable { url: String, detail: String },
+}
+
+/// Host and port of `url`, which must be `ws://` or `wss://`
+pub fn parse_url(url: &str) -> Result<(String, u16), ExternalGatewayError> {
+    let invalid = || ExternalGatewayError::InvalidUrl(url.to_string());
+    let parsed = reqwest::Url::parse(url).map_err(|_| invalid())?;
+    if !matches!(parsed.scheme(), "ws" | "wss") {
+        return Err(invalid());
+    }
+    let host = parsed.host_str().ok_or_else(invalid)?;
+    let port = parsed.port_or_known_default().ok_or_else(invalid)?;
+    // `Url` keeps IPv6 hosts in brackets, which `ToSocketAddrs` doesn't take
+    Ok((host.trim_matches(['[', ']']).to_string(), port))
+}
+
+/// `external` trimmed and checked
+pub fn normalize(external: ExternalGateway) -> Result<ExternalGateway, ExternalGatewayError> {
+    let external = ExternalGateway {
+        url: external.url.trim().to_string(),
+        token: external.token.trim().to_string(),
+    };
+    parse_url(&external.url)?;
+    Ok(external)
+}
+
+/// The connection info handed out for `external`
+pub fn info(external: &ExternalGateway) -> Result<GatewayInfo, ExternalGatewayError> {
+    let (host, port) = parse_url(&external.url)?;
+    let loopback = host == "localhost"
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    Ok(GatewayInfo {
+        url: external.url.clone(),
+        port: Some(port),
+        socket_path: None,
+        token: external.token.clone(),
+        lan_exposed: !loopback,
+        workspace: None,
+        generation: 0,
+    })
+}
+
+/// The first address of `external` that accepts a connection within
+/// `timeout`
+pub fn probe(
+    external: &ExternalGateway,
+    timeout: Duration,
+) -> Result<SocketAddr, ExternalGatewayError> {
+    let unreachable = |detail: String| ExternalGatewayError::Unreachable {
+        url: external.url.clone(),
+        detail,
+    };
+    let (host, port) = parse_url(&external.url)?;
+    let addresses = (host.as_str(), port)
+        .to_socket_addrs()
+        .map_err(|e| unreachable(e.to_string()))?;
+    let mut last_error = "no address found".to_string();
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(_) => return Ok(address),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(unreachable(last_error))
+}
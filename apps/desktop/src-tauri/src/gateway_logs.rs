@@ -0,0 +1,552 @@
+//! Captured Gateway Output
+//!
+//! Every line the gateway prints goes into an in-memory ring buffer of the
+//! newest `RING_CAPACITY` lines and, when a logs directory is configured,
+//! is appended to `gateway.log` there. The file is rotated to
+//! `gateway.log.1`, `gateway.log.2`, ... once it reaches `max_file_bytes`;
+//! older rotations beyond `keep_files` are deleted, and the retention sweep
+//! takes care of the rest. The app sizes these from `gatewayLogMaxFileMb`
+//! and `gatewayLogKeepFiles`, and with `fileLoggingEnabled` off keeps the
+//! output in memory only.
+//!
+//! Writing to disk must never get in the way of the gateway. After the
+//! first error - a full disk, a directory that became unwritable - the
+//! output is only kept in memory, and the error is passed to the file
+//! error listener, which the app emits as `gateway://file-logging-disabled`.
+//!
+//! Every line gets the next sequence number, so a reader paging through
+//! `recent` can tell which lines it already has. The buffer belongs to the
+//! manager rather than to one gateway process and is kept across restarts,
+//! with a `RESTART_MARKER` line between the output of two processes.
+//!
+//! Lines are stored on disk as `<unix ms>\t<stream>\t<text>`. The newest
+//! lines are in both the ring buffer and the files, so `lines_on_disk` tells
+//! a reader how many of the newest file lines to skip.
+//!
+//! Each line is also handed to the `log_stream` subscribers and broadcast
+//! as `gateway://log`.
+//!
+//! The gateway's pipes are read by `capture` threads, which end when the
+//! pipe closes - when the gateway exits or is killed - after passing on a
+//! last line that had no newline. Bytes that aren't UTF-8 are replaced so
+//! one bad line can't stop the reading and leave the pipe to fill up.
+//!
+//! Nothing the gateway prints is trusted to be sensible. A line is cut at
+//! `MAX_LINE_BYTES` while it is read, so a gateway printing hundreds of MB
+//! without a newline costs no more than a short line, and escape sequences
+//! and other control characters are stripped before a line is stored or
+//! passed on. Besides its line count the ring buffer holds at most
+//! `RING_MAX_BYTES`, dropping its oldest lines to stay below.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::thread::JoinHandle;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::connection;
+use crate::log_stream::LogSubscribers;
+
+/// Lines kept in memory
+pub const RING_CAPACITY: usize = 2000;
+
+/// Text of the lines kept in memory, in bytes at most
+pub const RING_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+/// Longest line kept, in bytes; the rest of a longer one is dropped and
+/// `truncated_marker` says how much
+pub const MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// Stream of the line recorded between the output of two gateway processes
+pub const MARKER_STREAM: &str = "marker";
+pub const RESTART_MARKER: &str = "--- gateway restarted ---";
+
+/// Name of the file currently written to
+pub const ACTIVE_FILE: &str = "gateway.log";
+
+const DEFAULT_MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const DEFAULT_KEEP_FILES: usize = 5;
+
+/// Event a write error turning file logging off is announced with
+pub const FILE_LOGGING_DISABLED_EVENT: &str = "gateway://file-logging-disabled";
+
+/// Payload of `gateway://file-logging-disabled`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileLoggingDisabled {
+    pub dir: String,
+    pub error: String,
+}
+
+/// Told when writing to disk failed and file logging is off
+pub type FileErrorListener = Box<dyn Fn(&FileLoggingDisabled) + Send + Sync>;
+
+/// One captured line
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+    /// Position in capture order since the app started, None for lines
+    /// read back from a file
+    pub seq: Option<u64>,
+    /// Unix timestamp (ms), None for file lines in an unknown format
+    pub timestamp: Option<i64>,
+    /// `stdout`, `stderr` or `MARKER_STREAM`
+    pub stream: String,
+    pub text: String,
+}
+
+impl LogLine {
+    /// Parse a line as written to disk. Lines in another format are kept
+    /// whole as the text.
+    pub fn parse(line: &str) -> Self {
+        let mut parts = line.splitn(3, '\t');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(timestamp), Some(stream), Some(text)) if timestamp.parse::<i64>().is_ok() => Self {
+                seq: None,
+                timestamp: timestamp.parse().ok(),
+                stream: stream.to_string(),
+                text: text.to_string(),
+            },
+            _ => Self {
+                seq: None,
+                timestamp: None,
+                stream: String::new(),
+                text: line.to_string(),
+            },
+        }
+    }
+
+    fn to_file_line(&self) -> String {
+        format!("{}\t{}\t{}\n", self.timestamp.unwrap_or(0), self.stream, self.text)
+    }
+}
+
+/// A ring buffer entry, remembering whether it also made it to disk
+#[derive(Debug, Clone)]
+struct Entry {
+    line: LogLine,
+    on_disk: bool,
+}
+
+struct LogFile {
+    dir: PathBuf,
+    writer: BufWriter<File>,
+    size: u64,
+}
+
+pub struct LogsState {
+    ring: VecDeque<Entry>,
+    /// Bytes of text in `ring`
+    ring_bytes: usize,
+    file: Option<LogFile>,
+}
+
+impl LogsState {
+    /// The ring buffer, oldest first
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &LogLine> {
+        self.ring.iter().map(|entry| &entry.line)
+    }
+
+    /// How many of the ring buffer lines are also the newest lines on disk
+    pub fn lines_on_disk(&self) -> usize {
+        self.ring.iter().filter(|entry| entry.on_disk).count()
+    }
+
+    /// Log files, newest first, with everything written so far flushed.
+    /// Empty when nothing is written to disk.
+    pub fn files(&mut self) -> Vec<PathBuf> {
+        let Some(ref mut file) = self.file else {
+            return Vec::new();
+        };
+        let _ = file.writer.flush();
+        log_files(&file.dir)
+    }
+}
+
+/// Captured output of the gateway, shared by the reader threads of every
+/// gateway process the manager starts
+pub struct GatewayLogs {
+    state: Mutex<LogsState>,
+    max_file_bytes: u64,
+    keep_files: usize,
+    subscribers: LogSubscribers,
+    /// `capture` threads still reading
+    readers: AtomicUsize,
+    /// Sequence number of the next line
+    next_seq: AtomicU64,
+    on_file_error: RwLock<Option<FileErrorListener>>,
+}
+
+impl Default for GatewayLogs {
+    /// In-memory only
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(LogsState {
+                ring: VecDeque::new(),
+                ring_bytes: 0,
+                file: None,
+            }),
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            keep_files: DEFAULT_KEEP_FILES,
+            subscribers: LogSubscribers::default(),
+            readers: AtomicUsize::new(0),
+            next_seq: AtomicU64::new(0),
+            on_file_error: RwLock::new(None),
+        }
+    }
+}
+
+impl GatewayLogs {
+    /// Capture to `dir` as well as memory, appending to an existing
+    /// `gateway.log`
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let logs = Self::default();
+        let file = open_active(&dir.into())?;
+        logs.lock().file = Some(file);
+        Ok(logs)
+    }
+
+    /// Rotate the file at `max_file_bytes` and keep `keep_files` rotations
+    pub fn with_rotation(mut self, max_file_bytes: u64, keep_files: usize) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self.keep_files = keep_files;
+        self
+    }
+
+    /// Tell `listener` when file logging is turned off by an error
+    pub fn set_file_error_listener(&self, listener: FileErrorListener) {
+        if let Ok(mut slot) = self.on_file_error.write() {
+            *slot = Some(listener);
+        }
+    }
+
+    /// Delete the log files and start over with an empty `gateway.log`,
+    /// returning how many files were removed. Does nothing when nothing is
+    /// written to disk, and keeps the ring buffer.
+    pub fn clear_files(&self) -> io::Result<usize> {
+        let mut state = self.lock();
+        let Some(ref mut file) = state.file else {
+            return Ok(0);
+        };
+        let dir = file.dir.clone();
+        let _ = file.writer.flush();
+        let files = log_files(&dir);
+        for path in &files {
+            fs::remove_file(path)?;
+        }
+        match open_active(&dir) {
+            Ok(active) => state.file = Some(active),
+            Err(e) => {
+                state.file = None;
+                return Err(e);
+            }
+        }
+        for entry in state.ring.iter_mut() {
+            entry.on_disk = false;
+        }
+        Ok(files.len())
+    }
+
+    /// Record a line printed by the gateway on `stream`
+    pub fn push(&self, stream: &str, text: &str) {
+        self.push_stripped(stream, &strip_control(text));
+    }
+
+    /// `push` for a line `strip_control` has been through
+    fn push_stripped(&self, stream: &str, text: &str) {
+        // Gateway URLs may carry the token
+        let text = connection::redact_tokens(text.trim_end());
+        let line = {
+            let mut state = self.lock();
+            let line = self.next_line(stream, text);
+            self.record(&mut state, line.clone());
+            line
+        };
+        self.subscribers.publish(&line);
+    }
+
+    /// Record `RESTART_MARKER` when there is output from an earlier gateway
+    /// to tell apart from the next one's. It isn't gateway output, so
+    /// subscribers don't see it.
+    pub fn mark_restart(&self) {
+        let mut state = self.lock();
+        if self.next_seq.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let line = self.next_line(MARKER_STREAM, RESTART_MARKER.to_string());
+        self.record(&mut state, line);
+    }
+
+    /// The newest `limit` lines of the ring buffer, or all of them, oldest
+    /// first
+    pub fn recent(&self, limit: Option<usize>) -> Vec<LogLine> {
+        let state = self.lock();
+        let skip = limit.map_or(0, |limit| state.ring.len().saturating_sub(limit));
+        state.lines().skip(skip).cloned().collect()
+    }
+
+    /// Numbered under the lock, so lines are in the ring buffer in `seq`
+    /// order
+    fn next_line(&self, stream: &str, text: String) -> LogLine {
+        LogLine {
+            seq: Some(self.next_seq.fetch_add(1, Ordering::SeqCst)),
+            timestamp: Some(now_ms()),
+            stream: stream.to_string(),
+            text,
+        }
+    }
+
+    /// Add `line` to the ring buffer, dropping the oldest lines while it
+    /// holds too many or too much
+    fn record(&self, state: &mut LogsState, line: LogLine) {
+        let on_disk = self.write(state, &line);
+        state.ring_bytes += line.text.len();
+        state.ring.push_back(Entry { line, on_disk });
+        while state.ring.len() > RING_CAPACITY || state.ring_bytes > RING_MAX_BYTES {
+            let Some(oldest) = state.ring.pop_front() else {
+                break;
+            };
+            state.ring_bytes -= oldest.line.text.len();
+        }
+    }
+
+    /// Record every line of `reader` as `stream`, on a thread that ends
+    /// when `reader` is closed. `on_line` sees each line after it's recorded.
+    pub fn capture(
+        self: &Arc<Self>,
+        stream: &'static str,
+        reader: impl Read + Send + 'static,
+        mut on_line: impl FnMut(&str) + Send + 'static,
+    ) -> JoinHandle<()> {
+        let logs = Arc::clone(self);
+        logs.readers.fetch_add(1, Ordering::SeqCst);
+        std::thread::spawn(move || {
+            read_lines(reader, |line| {
+                let line = strip_control(line);
+                logs.push_stripped(stream, &line);
+                on_line(&line);
+            });
+            logs.readers.fetch_sub(1, Ordering::SeqCst);
+        })
+    }
+
+    /// How many `capture` threads haven't reached the end of their pipe
+    pub fn active_readers(&self) -> usize {
+        self.readers.load(Ordering::SeqCst)
+    }
+
+    /// Live subscribers to new lines
+    pub fn subscribers(&self) -> &LogSubscribers {
+        &self.subscribers
+    }
+
+    /// Exclusive access for readers. New lines wait until it's dropped, so
+    /// the files don't change underneath a search.
+    pub fn lock(&self) -> MutexGuard<'_, LogsState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Append `line` to the active file, rotating first if it's full.
+    /// Stops writing to disk after the first error.
+    fn write(&self, state: &mut LogsState, line: &LogLine) -> bool {
+        let Some(ref mut file) = state.file else {
+            return false;
+        };
+        let contents = line.to_file_line();
+        let result = (|| {
+            if file.size > 0 && file.size + contents.len() as u64 > self.max_file_bytes {
+                file.writer.flush()?;
+                rotate(&file.dir, self.keep_files)?;
+                *file = open_active(&file.dir)?;
+            }
+            file.writer.write_all(contents.as_bytes())?;
+            file.size += contents.len() as u64;
+            Ok::<_, io::Error>(())
+        })();
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("[gateway-logs] Failed to write {}: {}, keeping logs in memory only", ACTIVE_FILE, e);
+                let disabled = FileLoggingDisabled {
+                    dir: file.dir.display().to_string(),
+                    error: e.to_string(),
+                };
+                state.file = None;
+                if let Ok(listener) = self.on_file_error.read() {
+                    if let Some(ref listener) = *listener {
+                        listener(&disabled);
+                    }
+                }
+                false
+            }
+        }
+    }
+}
+
+/// Call `f` with every line of `reader` until it's closed, without the line
+/// ending. Invalid UTF-8 is replaced, and a last line without a newline is
+/// passed on too. Only the first `MAX_LINE_BYTES` of a line are kept, so
+/// memory stays bounded however long it is.
+pub fn read_lines(reader: impl Read, mut f: impl FnMut(&str)) {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    // Bytes of the current line past `MAX_LINE_BYTES`, skipped
+    let mut cut = 0;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            // Whatever was read before the error is still a line
+            Err(_) => &[],
+        };
+        if available.is_empty() {
+            if !buf.is_empty() {
+                pass_line(&buf, cut, &mut f);
+            }
+            return;
+        }
+        let newline = available.iter().position(|byte| *byte == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+        let keep = chunk.len().min(MAX_LINE_BYTES - buf.len());
+        buf.extend_from_slice(&chunk[..keep]);
+        cut += chunk.len() - keep;
+        let consumed = chunk.len() + usize::from(newline.is_some());
+        reader.consume(consumed);
+        if newline.is_some() {
+            pass_line(&buf, cut, &mut f);
+            buf.clear();
+            cut = 0;
+        }
+    }
+}
+
+/// Hand `f` a line read into `buf`, of which `cut` more bytes were dropped
+fn pass_line(buf: &[u8], cut: usize, f: &mut impl FnMut(&str)) {
+    if cut > 0 {
+        f(&format!("{}{}", String::from_utf8_lossy(buf), truncated_marker(cut)));
+    } else {
+        let line = buf.strip_suffix(b"\r").unwrap_or(buf);
+        f(&String::from_utf8_lossy(line));
+    }
+}
+
+/// Ends a line cut at `MAX_LINE_BYTES`, `cut` being the bytes dropped
+pub fn truncated_marker(cut: usize) -> String {
+    format!(" [... {} bytes cut]", cut)
+}
+
+/// `text` without ANSI escape sequences - colours, cursor movement, window
+/// titles - and the other control characters but tabs, which would only
+/// show as garbage
+pub fn strip_control(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(|c| c.is_control() && c != '\t') {
+        return Cow::Borrowed(text);
+    }
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let csi = match c {
+            '\u{1b}' => match chars.next() {
+                Some('[') => true,
+                // OSC and the other strings, up to BEL or ESC \
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\u{7}' || (c == '\u{1b}' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                    false
+                }
+                // Character set designations have one more byte
+                Some('(' | ')' | '*' | '+') => {
+                    chars.next();
+                    false
+                }
+                _ => false,
+            },
+            '\u{9b}' => true,
+            '\t' => {
+                stripped.push(c);
+                false
+            }
+            c if c.is_control() => false,
+            c => {
+                stripped.push(c);
+                false
+            }
+        };
+        // Parameters and intermediates, then the final byte if there is one
+        if csi {
+            while chars.next_if(|c| ('\u{20}'..='\u{3f}').contains(c)).is_some() {}
+            chars.next_if(|c| ('\u{40}'..='\u{7e}').contains(c));
+        }
+    }
+    Cow::Owned(stripped)
+}
+
+fn open_active(dir: &Path) -> io::Result<LogFile> {
+    fs::create_dir_all(dir)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(ACTIVE_FILE))?;
+    let size = file.metadata()?.len();
+    Ok(LogFile {
+        dir: dir.to_path_buf(),
+        writer: BufWriter::new(file),
+        size,
+    })
+}
+
+/// Shift `gateway.log` to `gateway.log.1` and so on, dropping the oldest
+fn rotate(dir: &Path, keep_files: usize) -> io::Result<()> {
+    let rotated = |n: usize| dir.join(format!("{}.{}", ACTIVE_FILE, n));
+    if keep_files == 0 {
+        return fs::remove_file(dir.join(ACTIVE_FILE));
+    }
+    match fs::remove_file(rotated(keep_files)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    for n in (1..keep_files).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    fs::rename(dir.join(ACTIVE_FILE), rotated(1))
+}
+
+/// `gateway.log` and its rotations in `dir`, newest first
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut rotations: Vec<(usize, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let n = name.strip_prefix(ACTIVE_FILE)?.strip_prefix('.')?.parse().ok()?;
+            Some((n, entry.path()))
+        })
+        .collect();
+    rotations.sort();
+
+    let mut files = Vec::new();
+    let active = dir.join(ACTIVE_FILE);
+    if active.is_file() {
+        files.push(active);
+    }
+    files.extend(rotations.into_iter().map(|(_, path)| path));
+    files
+}
+
+pub(crate) fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,101 @@
+//! Gateway Resource Metrics
+//!
+//! `get_gateway_metrics` reports what the running gateway costs: resident
+//! memory and CPU of its process tree (see `resources`), uptime, and the
+//! established connections on its port. CPU is a percentage of one core
+//! since the previous sample, so it can go over 100 on several cores; the
+//! previous sample is kept with the child in `SidecarState`, and the first
+//! sample of a gateway has no percentage yet.
+//!
+//! With `metricsIntervalSecs` set, the same metrics are emitted as
+//! `gateway://metrics` every that many seconds while a gateway runs, so the
+//! frontend can chart them without polling. The command and the events
+//! share the previous sample, so each measures since the last of either.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::sidecar::SidecarManager;
+
+/// Event the periodic metrics are emitted as
+pub const METRICS_EVENT: &str = "gateway://metrics";
+
+/// How often the publisher checks the interval and the gateway
+const TICK: Duration = Duration::from_secs(1);
+
+/// What the running gateway uses
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayMetrics {
+    pub pid: u32,
+    /// Resident memory of the gateway's process tree
+    pub rss_bytes: u64,
+    /// Percent of one core since the previous sample; None on the first
+    pub cpu_percent: Option<f64>,
+    pub uptime_secs: u64,
+    /// Established TCP connections on the gateway's port; None when they
+    /// can't be listed
+    pub open_connections: Option<u32>,
+    /// Unix timestamp (ms) of the sample
+    pub sampled_at: i64,
+}
+
+/// CPU time of a gateway at one point, for the next percentage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuSample {
+    pub pid: u32,
+    pub cpu_ms: u64,
+    pub at: Instant,
+}
+
+/// Percent of one core `current` used since `previous`. None when there
+/// is nothing to compare: another process, or no time passed.
+pub fn cpu_percent(previous: &CpuSample, current: &CpuSample) -> Option<f64> {
+    if previous.pid != current.pid {
+        return None;
+    }
+    let elapsed_ms = current.at.checked_duration_since(previous.at)?.as_secs_f64() * 1000.0;
+    if elapsed_ms <= 0.0 {
+        return None;
+    }
+    // Exited children drop out of the tree total, so it can go down
+    let used_ms = current.cpu_ms.saturating_sub(previous.cpu_ms) as f64;
+    Some(used_ms / elapsed_ms * 100.0)
+}
+
+/// Start the thread that emits `gateway://metrics` every
+/// `metricsIntervalSecs` while a gateway runs
+pub fn spawn_metrics_publisher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut last_emit: Option<Instant> = None;
+        loop {
+            std::thread::sleep(TICK);
+
+            let Some(interval) = Config::load().ok().and_then(|c| c.metrics_interval_secs) else {
+                last_emit = None;
+                continue;
+            };
+            let interval = Duration::from_secs(interval.max(1));
+            if last_emit.is_some_and(|at| at.elapsed() < interval) {
+                continue;
+            }
+            let Some(manager) = app.try_state::<SidecarManager>() else {
+                continue;
+            };
+            if let Ok(metrics) = manager.gateway_metrics() {
+                let _ = app.emit(METRICS_EVENT, &metrics);
+            }
+            last_emit = Some(Instant::now());
+        }
+    });
+}
+
+/// Memory, CPU, uptime and connections of the running gateway. Fails with
+/// `gateway_not_running` when there is none.
+#[tauri::command]
+pub async fn get_gateway_metrics(app: AppHandle) -> Result<GatewayMetrics, AppError> {
+    tokio::task::spawn_blocking(move || app.state::<SidecarManager>().gateway_metrics()).await?
+}
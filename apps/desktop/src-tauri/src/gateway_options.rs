@@ -0,0 +1,78 @@
+//! Extra Gateway Options
+//!
+//! `gatewayExtraArgs` and `gatewayExtraEnv` in config.json pass flags and
+//! variables the app doesn't model straight to the gateway, e.g. proxy
+//! settings or experimental openclaw flags. The arguments come after
+//! everything the app passes, and the variables are set last, so they win
+//! over the app's own `NODE_OPTIONS` and survive clean mode.
+//!
+//! What the app manages itself can't be overridden: `--port`, `--token`
+//! and `--host` are refused as arguments, and the token and provider key
+//! variables as names, since the app's connection and credentials depend
+//! on them.
+
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use crate::capabilities;
+use crate::config::Provider;
+
+/// Variable the gateway token is passed in
+pub const TOKEN_ENV: &str = "OPENCLAW_GATEWAY_TOKEN";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GatewayOptionsError {
+    #[error("{0} is set by the app")]
+    ManagedFlag(String),
+    #[error("{0} is set by the app")]
+    ManagedEnv(String),
+    #[error("{0:?} is not a valid environment variable name")]
+    InvalidEnvName(String),
+}
+
+impl GatewayOptionsError {
+    /// Config field the error is about
+    pub fn field(&self) -> &'static str {
+        match self {
+            GatewayOptionsError::ManagedFlag(_) => "gatewayExtraArgs",
+            GatewayOptionsError::ManagedEnv(_) | GatewayOptionsError::InvalidEnvName(_) => {
+                "gatewayExtraEnv"
+            }
+        }
+    }
+}
+
+/// Flags the app passes itself
+pub const MANAGED_FLAGS: [&str; 4] = [
+    capabilities::PORT_FLAG,
+    capabilities::TOKEN_FLAG,
+    capabilities::HOST_FLAG,
+    capabilities::SOCKET_FLAG,
+];
+
+/// Check `args` and `env` can be passed without clobbering what the app
+/// sets
+pub fn validate(
+    args: &[String],
+    env: &BTreeMap<String, String>,
+) -> Result<(), GatewayOptionsError> {
+    for arg in args {
+        let flag = arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag);
+        if MANAGED_FLAGS.contains(&flag) {
+            return Err(GatewayOptionsError::ManagedFlag(flag.to_string()));
+        }
+    }
+    for name in env.keys() {
+        if name.is_empty() || name.contains(['=', '\0']) {
+            return Err(GatewayOptionsError::InvalidEnvName(name.clone()));
+        }
+        let managed = name.eq_ignore_ascii_case(TOKEN_ENV)
+            || Provider::ALL
+                .iter()
+                .any(|provider| name.eq_ignore_ascii_case(provider.env_var()));
+        if managed {
+            return Err(GatewayOptionsError::ManagedEnv(name.clone()));
+        }
+    }
+    Ok(())
+}
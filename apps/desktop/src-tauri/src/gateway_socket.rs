@@ -0,0 +1,249 @@
+//! Gateway Unix Socket
+//!
+//! Every user of the machine can connect to a gateway on a localhost TCP
+//! port, and only the token keeps them out. With `transport: unix` the
+//! gateway listens on a Unix domain socket instead (`--socket`), and file
+//! permissions decide who connects: the socket is `gateway.sock` in
+//! `$XDG_RUNTIME_DIR/simplestclaw` (the app data dir where there is no
+//! runtime dir) or `gatewaySocketPath`. A directory created for it is
+//! private, and the socket itself is set to 0600 once the gateway made it.
+//!
+//! `GatewayInfo.url` is then `ws+unix://<path>`, `socketPath` the path and
+//! `port` null. A socket left behind by a gateway that crashed is removed
+//! before the next start; one a gateway still accepts connections on
+//! fails the start with `socket_in_use` rather than taking it over.
+//!
+//! Windows has no Unix sockets here, so the transport fails there with
+//! `transport_unsupported` rather than falling back to a port. TLS is only
+//! served on a port and fails the same way.
+//!
+//! `Endpoint` is where the app connects to its own gateway, on either
+//! transport.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::{Config, Transport};
+use crate::error::AppError;
+use crate::paths::{self, AppPaths};
+
+/// Directory the socket is created in under `RUNTIME_DIR_ENV`
+pub const SOCKET_DIR: &str = "simplestclaw";
+
+pub const SOCKET_FILE: &str = "gateway.sock";
+
+/// Environment variable naming the user's runtime dir
+pub const RUNTIME_DIR_ENV: &str = "XDG_RUNTIME_DIR";
+
+/// Scheme of `GatewayInfo.url` for a gateway on a socket
+pub const URL_SCHEME: &str = "ws+unix";
+
+/// Longest socket path every Unix accepts; macOS has the smallest
+/// `sun_path`, 104 bytes with the terminating NUL
+pub const MAX_PATH_BYTES: usize = 103;
+
+/// The socket `config` has the gateway listen on, None for the `tcp`
+/// transport. Fails with `transport_unsupported` where the `unix`
+/// transport can't work.
+pub fn plan(config: &Config) -> Result<Option<PathBuf>, AppError> {
+    if config.transport == Transport::Tcp {
+        return Ok(None);
+    }
+    let unsupported = |detail: String| AppError::TransportUnsupported {
+        transport: config.transport.as_str().to_string(),
+        detail,
+    };
+    if cfg!(not(unix)) {
+        return Err(unsupported(
+            "Unix domain sockets aren't supported on this platform; use tcp.".to_string(),
+        ));
+    }
+    if config.tls.is_some() {
+        return Err(unsupported(
+            "TLS is only served on a port; remove the TLS certificate or use tcp.".to_string(),
+        ));
+    }
+    let path = match config.gateway_socket_path {
+        Some(ref path) => path.clone(),
+        None => default_path().ok_or_else(|| {
+            unsupported("there is no runtime or app data directory for the socket.".to_string())
+        })?,
+    };
+    if !path.is_absolute() {
+        return Err(unsupported(format!(
+            "gatewaySocketPath {} must be an absolute path.",
+            path.display()
+        )));
+    }
+    if path.as_os_str().len() > MAX_PATH_BYTES {
+        return Err(unsupported(format!(
+            "the socket path {} is longer than {} bytes; set a shorter gatewaySocketPath.",
+            path.display(),
+            MAX_PATH_BYTES
+        )));
+    }
+    Ok(Some(path))
+}
+
+/// `gateway.sock` in the runtime dir, or in the app data dir without one
+fn default_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os(RUNTIME_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join(SOCKET_DIR),
+        None => AppPaths::resolve()?.data_dir,
+    };
+    Some(dir.join(SOCKET_FILE))
+}
+
+/// Get `path` ready for a gateway to listen on: create its directory,
+/// private when it is new, and remove a socket a crashed gateway left.
+/// Returns whether one was removed. Fails with `socket_in_use` when a
+/// gateway still accepts connections on it, and refuses to remove
+/// anything that isn't a socket.
+pub fn prepare(path: &Path) -> Result<bool, AppError> {
+    let failed = |detail: String| AppError::GatewayStartFailed { detail };
+    if let Some(dir) = path.parent().filter(|dir| !dir.is_dir()) {
+        fs::create_dir_all(dir)
+            .and_then(|()| paths::restrict_mode(dir, paths::PRIVATE_DIR_MODE))
+            .map_err(|e| {
+                failed(format!("Could not create the socket directory {}: {}", dir.display(), e))
+            })?;
+    }
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(false);
+    };
+    if !is_socket(&metadata) {
+        return Err(failed(format!(
+            "{} exists and is not a socket, so the gateway can't listen there.",
+            path.display()
+        )));
+    }
+    if Endpoint::Unix(path.to_path_buf()).accepts() {
+        return Err(AppError::SocketInUse {
+            path: path.display().to_string(),
+        });
+    }
+    fs::remove_file(path).map_err(|e| {
+        failed(format!("Could not remove the leftover socket {}: {}", path.display(), e))
+    })?;
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn is_socket(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    metadata.file_type().is_socket()
+}
+
+#[cfg(not(unix))]
+fn is_socket(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Keep the socket at `path` to its owner
+pub fn restrict(path: &Path) -> io::Result<()> {
+    paths::restrict_mode(path, paths::PRIVATE_FILE_MODE).map(|_| ())
+}
+
+/// Where the app connects to its gateway
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    /// Connect within `timeout`. A local socket connects or fails at once.
+    pub fn connect(&self, timeout: Duration) -> io::Result<GatewayStream> {
+        match self {
+            Endpoint::Tcp(address) => {
+                TcpStream::connect_timeout(address, timeout).map(GatewayStream::Tcp)
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                std::os::unix::net::UnixStream::connect(path).map(GatewayStream::Unix)
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unix domain sockets aren't supported on this platform",
+            )),
+        }
+    }
+
+    /// Whether something accepts connections here
+    pub fn accepts(&self) -> bool {
+        self.connect(Duration::from_secs(1)).is_ok()
+    }
+
+    /// The `Host` header for requests to it
+    pub fn host(&self) -> String {
+        match self {
+            Endpoint::Tcp(address) => address.to_string(),
+            Endpoint::Unix(_) => "localhost".to_string(),
+        }
+    }
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(address: SocketAddr) -> Self {
+        Endpoint::Tcp(address)
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(address) => write!(f, "{}", address),
+            Endpoint::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A connection to an `Endpoint`
+pub enum GatewayStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl GatewayStream {
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            GatewayStream::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            GatewayStream::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for GatewayStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            GatewayStream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            GatewayStream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for GatewayStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            GatewayStream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            GatewayStream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            GatewayStream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            GatewayStream::Unix(stream) => stream.flush(),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Gateway State Directory
+//!
+//! The gateway keeps its sessions, memory and transcripts on disk, so a
+//! restart (by the watchdog or anyone else) resumes them. Every spawn gets
+//! `OPENCLAW_STATE_DIR` pointing at `gateway-state` in the app data dir, or
+//! at `gatewayStateDir` when that is set, instead of the default
+//! `~/.openclaw`. The directory is created on the first start; an existing
+//! `~/.openclaw` is not moved over.
+//!
+//! After a restart the frontend reports the sessions the new gateway lists
+//! with `report_sessions`. Compared with the last report for the previous
+//! gateway, that emits `gateway://sessions-restored` when every known
+//! session is back and `gateway://sessions-lost` otherwise.
+//!
+//! `diagnostics_files` picks what a diagnostics bundle may take from the
+//! directory: metadata such as session indexes and gateway config, but no
+//! credentials, and transcripts only when asked for.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::paths::AppPaths;
+use crate::sidecar::SidecarManager;
+
+/// Environment variable the gateway reads its state directory from
+pub const STATE_DIR_ENV: &str = "OPENCLAW_STATE_DIR";
+
+/// The state directory for `config`. `paths` is only needed when no
+/// `gatewayStateDir` is configured.
+pub fn state_dir(config: &Config, paths: Option<&AppPaths>) -> Result<PathBuf, String> {
+    let dir = match (config.gateway_state_dir.as_ref(), paths) {
+        (Some(dir), _) => dir.clone(),
+        (None, Some(paths)) => paths.gateway_state_dir.clone(),
+        (None, None) => {
+            return Err(
+                "Could not determine the app data directory for the gateway state.".to_string(),
+            )
+        }
+    };
+    if !dir.is_absolute() {
+        return Err(format!(
+            "gatewayStateDir {} must be an absolute path.",
+            dir.display()
+        ));
+    }
+    if dir.components().any(|c| c == Component::ParentDir) {
+        return Err(format!(
+            "gatewayStateDir {} must not contain '..'.",
+            dir.display()
+        ));
+    }
+    if dir.parent().is_none() {
+        return Err(format!(
+            "gatewayStateDir {} is a filesystem root.",
+            dir.display()
+        ));
+    }
+    if dir.is_file() {
+        return Err(format!("gatewayStateDir {} is a file.", dir.display()));
+    }
+    Ok(dir)
+}
+
+pub fn env(dir: &Path) -> (String, String) {
+    (STATE_DIR_ENV.to_string(), dir.display().to_string())
+}
+
+/// Create `dir` on the first start, private to its owner on Unix since it
+/// holds conversations
+pub fn ensure(dir: &Path) -> io::Result<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// Sessions the previous gateway had, sorted by whether they came back
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionCheck {
+    pub restored: Vec<String>,
+    pub lost: Vec<String>,
+}
+
+impl SessionCheck {
+    pub fn compare(known: &[String], listed: &[String]) -> Self {
+        let (restored, lost) = known
+            .iter()
+            .cloned()
+            .partition(|session| listed.contains(session));
+        Self { restored, lost }
+    }
+
+    pub fn event(&self) -> &'static str {
+        if self.lost.is_empty() {
+            "gateway://sessions-restored"
+        } else {
+            "gateway://sessions-lost"
+        }
+    }
+}
+
+/// The sessions last reported for each gateway run
+#[derive(Default)]
+pub struct SessionTracker {
+    /// `startedAt` of the reporting gateway and its sessions
+    last: Mutex<Option<(i64, Vec<String>)>>,
+}
+
+impl SessionTracker {
+    /// Record `sessions` for the gateway started at `started_at`. The first
+    /// report for a new gateway is checked against what the previous one
+    /// had; None when there is nothing to compare.
+    pub fn report(&self, started_at: i64, sessions: Vec<String>) -> Option<SessionCheck> {
+        let Ok(mut last) = self.last.lock() else {
+            return None;
+        };
+        let check = match last.as_ref() {
+            Some((previous, known)) if *previous != started_at && !known.is_empty() => {
+                Some(SessionCheck::compare(known, &sessions))
+            }
+            _ => None,
+        };
+        *last = Some((started_at, sessions));
+        check
+    }
+}
+
+/// What a file in the state directory holds, by its path relative to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StateFileKind {
+    /// Session indexes, gateway config and the like
+    Metadata,
+    /// Session transcripts
+    Conversation,
+    /// Credentials and auth profiles, never bundled
+    Secret,
+}
+
+pub fn classify(relative: &Path) -> StateFileKind {
+    let under = |name: &str| relative.components().any(|c| c.as_os_str() == name);
+    let file_name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if under("credentials")
+        || file_name == ".env"
+        || file_name.ends_with(".env")
+        || file_name.starts_with("auth-profiles")
+    {
+        StateFileKind::Secret
+    } else if relative.extension().is_some_and(|ext| ext == "jsonl") {
+        StateFileKind::Conversation
+    } else {
+        StateFileKind::Metadata
+    }
+}
+
+/// Files under `dir` a diagnostics bundle may include, sorted. Symlinks
+/// are skipped so nothing outside the directory is picked up.
+pub fn diagnostics_files(dir: &Path, include_conversations: bool) -> Vec<PathBuf> {
+    fn walk(root: &Path, dir: &Path, include_conversations: bool, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                walk(root, &path, include_conversations, files);
+            } else if file_type.is_file() {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                match classify(relative) {
+                    StateFileKind::Metadata => files.push(path),
+                    StateFileKind::Conversation if include_conversations => files.push(path),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(dir, dir, include_conversations, &mut files);
+    files.sort();
+    files
+}
+
+// Tauri commands
+
+/// Called by the frontend with the session ids from `list_sessions` after
+/// connecting to a gateway
+#[tauri::command]
+pub fn report_sessions(
+    app: AppHandle,
+    sessions: Vec<String>,
+    manager: State<'_, SidecarManager>,
+    tracker: State<'_, SessionTracker>,
+) -> Result<Option<SessionCheck>, AppError> {
+    let Some(started_at) = manager.status().started_at else {
+        return Ok(None);
+    };
+    let check = tracker.report(started_at, sessions);
+    if let Some(ref check) = check {
+        println!(
+            "[openclaw] {} of {} sessions restored after the restart",
+            check.restored.len(),
+            check.restored.len() + check.lost.len()
+        );
+        let _ = app.emit(check.event(), check);
+    }
+    Ok(check)
+}
@@ -0,0 +1,94 @@
+//! Gateway Client and Request Counts
+//!
+//! The gateway prints a line for every client that connects or goes away
+//! and every request it answers. Counting those lets the UI show "3 clients
+//! connected, 42 requests" without speaking the gateway protocol.
+//!
+//! `classify` looks for the phrases in `SIGNATURES` in the lowercased line,
+//! so prefixes, timestamps and the rest of the wording can change without
+//! breaking it. A line matching none is not counted; either way the line
+//! goes to the log buffer as it was printed.
+//!
+//! The counts belong to one gateway process: they are kept with the child
+//! in `SidecarState` and start from zero with the next one.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::sidecar::SidecarManager;
+
+/// What a line of gateway output reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsEvent {
+    ClientConnected,
+    ClientDisconnected,
+    RequestCompleted,
+}
+
+/// Lowercase phrases and what a line containing them reports, checked in
+/// order so a disconnect isn't taken for a connect
+pub const SIGNATURES: &[(&str, StatsEvent)] = &[
+    ("client disconnected", StatsEvent::ClientDisconnected),
+    ("ws disconnected", StatsEvent::ClientDisconnected),
+    ("websocket disconnected", StatsEvent::ClientDisconnected),
+    ("websocket closed", StatsEvent::ClientDisconnected),
+    ("connection closed", StatsEvent::ClientDisconnected),
+    ("client connected", StatsEvent::ClientConnected),
+    ("ws connected", StatsEvent::ClientConnected),
+    ("websocket connected", StatsEvent::ClientConnected),
+    ("new connection", StatsEvent::ClientConnected),
+    ("connection opened", StatsEvent::ClientConnected),
+    ("connection accepted", StatsEvent::ClientConnected),
+    ("request completed", StatsEvent::RequestCompleted),
+    ("request finished", StatsEvent::RequestCompleted),
+    ("response sent", StatsEvent::RequestCompleted),
+    ("res ✓", StatsEvent::RequestCompleted),
+    ("res ✗", StatsEvent::RequestCompleted),
+];
+
+/// What `line` of gateway output reports, if anything
+pub fn classify(line: &str) -> Option<StatsEvent> {
+    let line = line.to_lowercase();
+    SIGNATURES
+        .iter()
+        .find(|(phrase, _)| line.contains(phrase))
+        .map(|&(_, event)| event)
+}
+
+/// Clients and requests of the running gateway, from its output
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayStats {
+    pub clients_connected: u32,
+    /// Requests answered since the gateway started
+    pub total_requests: u64,
+    /// Unix timestamp (ms) of the last answered request
+    pub last_request_at: Option<i64>,
+}
+
+impl GatewayStats {
+    /// Count `line`, printed at `now` (Unix ms). Returns what it reported.
+    pub fn observe(&mut self, line: &str, now: i64) -> Option<StatsEvent> {
+        let event = classify(line)?;
+        match event {
+            StatsEvent::ClientConnected => self.clients_connected += 1,
+            // One that connected before the counting started
+            StatsEvent::ClientDisconnected => {
+                self.clients_connected = self.clients_connected.saturating_sub(1)
+            }
+            StatsEvent::RequestCompleted => {
+                self.total_requests += 1;
+                self.last_request_at = Some(now);
+            }
+        }
+        Some(event)
+    }
+}
+
+// Tauri commands
+/// Clients and requests of the running gateway; all zero when none runs
+#[tauri::command]
+pub async fn get_gateway_stats(app: AppHandle) -> Result<GatewayStats, AppError> {
+    Ok(tokio::task::spawn_blocking(move || app.state::<SidecarManager>().stats()).await?)
+}
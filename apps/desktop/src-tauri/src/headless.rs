@@ -0,0 +1,173 @@
+//! Headless Mode
+//!
+//! `simplestclaw --headless` runs the gateway without a window or tray, for
+//! a home server or a machine reached over SSH. It takes the same
+//! single-instance lock and starts the gateway through the same
+//! `SidecarManager` and `Config` as the app, prints how to connect, and
+//! runs until SIGINT or SIGTERM (Ctrl+C on Windows), then stops the
+//! gateway the way the app does on exit (`shutdown::stop_gateway`).
+//!
+//! The control socket is served too, so `simplestclaw --headless --status`
+//! and the other verbs work against it. There's no onboarding without a
+//! window, so an API key must already be configured. Nothing restarts the
+//! gateway here: when it stops, crashed or through `stop-gateway`, the
+//! headless run ends with `EXIT_FAILED` and a service manager can start it
+//! again. A run stopped by a signal exits with `EXIT_OK`. Release builds
+//! on Windows have no console, so the output shows only when redirected.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cli::{EXIT_FAILED, EXIT_OK};
+use crate::config::Config;
+use crate::control;
+use crate::error::AppError;
+use crate::metrics::AppMetrics;
+use crate::paths::{self, AppPaths};
+use crate::runtime::RuntimeManager;
+use crate::shutdown;
+use crate::sidecar::{SidecarManager, StartResult};
+use crate::single_instance;
+
+/// How often the gateway is checked for having stopped
+pub const GATEWAY_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Run the gateway until interrupted, returning the exit code
+pub fn run() -> i32 {
+    let _instance_lock = match single_instance::lock_app() {
+        Ok(lock) => lock,
+        Err(holder) => {
+            eprintln!(
+                "[headless] simplestclaw is already running as pid {}; \
+                 see `simplestclaw --headless --status`",
+                holder.pid
+            );
+            return EXIT_FAILED;
+        }
+    };
+
+    // The gateway a crashed run left is reaped by `gateway_manager`
+    paths::migrate_on_startup();
+
+    let code = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime.block_on(serve()),
+        Err(e) => {
+            eprintln!("[headless] Failed to start the async runtime: {}", e);
+            EXIT_FAILED
+        }
+    };
+    code
+}
+
+async fn serve() -> i32 {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[headless] Failed to load config: {}", e);
+            return EXIT_FAILED;
+        }
+    };
+    if config.anthropic_api_key.as_deref().is_none_or(str::is_empty) {
+        eprintln!(
+            "[headless] {} Set one in the app before running headless.",
+            config.missing_api_key_error()
+        );
+        return EXIT_FAILED;
+    }
+
+    if !RuntimeManager::is_installed() {
+        println!("[runtime] Node.js runtime not found, starting download...");
+        if let Err(e) = RuntimeManager::default().install().await {
+            eprintln!("[runtime] Failed to install: {}", e);
+        }
+    }
+
+    let manager = Arc::new(crate::gateway_manager(&config, Arc::new(AppMetrics::default())));
+    if let Some(paths) = AppPaths::resolve() {
+        let path = control::socket_path(&paths);
+        let handle = {
+            let manager = manager.clone();
+            move |request: &control::ControlRequest| control::dispatch(&manager, request)
+        };
+        match control::listen(&path, handle) {
+            Ok(()) => println!("[control] Listening on {}", path.display()),
+            Err(e) => eprintln!("[control] Warning: no control socket: {}", e),
+        }
+    }
+
+    let started = tokio::task::spawn_blocking({
+        let manager = manager.clone();
+        move || manager.start()
+    })
+    .await
+    .unwrap_or_else(|e| Err(AppError::from(e)));
+    match started {
+        Ok(result) => print_connection_info(&result),
+        Err(e) => {
+            eprintln!("[headless] Failed to start the gateway: {}", e);
+            return EXIT_FAILED;
+        }
+    }
+
+    let (reason, code) = tokio::select! {
+        name = interrupted() => (format!("Received {}", name), EXIT_OK),
+        () = gateway_stopped(&manager) => ("The gateway stopped".to_string(), EXIT_FAILED),
+    };
+    println!("[headless] {}, cleaning up...", reason);
+    let _ = tokio::task::spawn_blocking(move || shutdown::stop_gateway(&manager)).await;
+    code
+}
+
+/// Tell the user on stdout where to connect
+fn print_connection_info(result: &StartResult) {
+    for warning in &result.warnings {
+        eprintln!("[headless] Warning: {}", warning.message);
+    }
+    println!("Gateway running on {}", result.info.listen_address());
+    println!("URL:   {}", result.info.url);
+    println!("Token: {}", result.info.token);
+    if !result.info.lan_exposed && result.info.socket_path.is_none() {
+        println!("Only this machine can connect; set gatewayHost to listen on the LAN");
+    }
+}
+
+/// Resolves when the gateway is no longer running and no start is under way
+async fn gateway_stopped(manager: &SidecarManager) {
+    let mut ticks = tokio::time::interval(GATEWAY_CHECK_INTERVAL);
+    loop {
+        ticks.tick().await;
+        if !manager.is_starting() && !manager.status().running {
+            return;
+        }
+    }
+}
+
+/// The name of the signal that asked to stop
+#[cfg(unix)]
+async fn interrupted() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (Ok(mut interrupt), Ok(mut terminate)) = (
+        signal(SignalKind::interrupt()),
+        signal(SignalKind::terminate()),
+    ) else {
+        eprintln!("[headless] Failed to install the signal handler");
+        return std::future::pending().await;
+    };
+    tokio::select! {
+        _ = interrupt.recv() => "SIGINT",
+        _ = terminate.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+async fn interrupted() -> &'static str {
+    if tokio::signal::ctrl_c().await.is_err() {
+        eprintln!("[headless] Failed to install the Ctrl+C handler");
+        return std::future::pending().await;
+    }
+    "Ctrl+C"
+}
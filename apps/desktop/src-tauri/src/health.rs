@@ -0,0 +1,146 @@
+//! Health Endpoint
+//!
+//! With `healthEndpoint.enabled` the app answers on loopback HTTP so
+//! supervisors (systemd, launchd scripts, uptime checkers) can check it
+//! without Tauri IPC:
+//!
+//! - `GET /healthz`: 200 with a JSON summary, 503 if the gateway failed
+//! - `GET /readyz`: 200 once the gateway has passed its readiness check
+//!
+//! A gateway counts as failed from a failed start or a crash until it next
+//! starts successfully. Responses never include the token or the API key.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::config::HealthEndpoint;
+use crate::gateway_logs;
+use crate::heartbeat::GatewayHealth;
+use crate::http::{self, Handler, HttpResponse};
+use crate::metrics::{AppMetrics, MetricsSnapshot};
+use crate::sidecar::{GatewayStatus, SidecarManager};
+
+pub const HEALTH_PATH: &str = "/healthz";
+pub const READY_PATH: &str = "/readyz";
+
+const JSON: &str = "application/json";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GatewayPhase {
+    Stopped,
+    Running,
+    /// Not running after a failed start or a crash
+    Failed,
+}
+
+/// Body of `/healthz`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthSummary {
+    /// False exactly when the response is a 503
+    pub ok: bool,
+    pub app_uptime_secs: u64,
+    pub gateway: GatewaySummary,
+    /// Reason of the last failed start, or `crashed`
+    pub last_error_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewaySummary {
+    pub phase: GatewayPhase,
+    pub health: GatewayHealth,
+    pub ready: bool,
+    pub uptime_secs: Option<u64>,
+}
+
+/// Current gateway status and metrics, see `handler`. Take the status
+/// first: that's where a crash is noticed and counted.
+pub type Source = dyn Fn() -> (GatewayStatus, MetricsSnapshot) + Send + Sync;
+
+pub fn summarize(metrics: &MetricsSnapshot, status: &GatewayStatus, now_ms: i64) -> HealthSummary {
+    let phase = if status.running {
+        GatewayPhase::Running
+    } else if metrics.last_failure.is_some() {
+        GatewayPhase::Failed
+    } else {
+        GatewayPhase::Stopped
+    };
+    HealthSummary {
+        ok: phase != GatewayPhase::Failed,
+        app_uptime_secs: metrics.uptime_secs,
+        gateway: GatewaySummary {
+            phase,
+            health: status.health,
+            // The child is only recorded once it accepts connections
+            ready: status.running,
+            uptime_secs: status
+                .started_at
+                .map(|started| ((now_ms - started).max(0) / 1000) as u64),
+        },
+        last_error_code: metrics
+            .last_failure
+            .as_ref()
+            .map(|failure| failure.code.clone()),
+    }
+}
+
+/// The response to a `GET` of `path`
+pub fn respond(
+    path: &str,
+    metrics: &MetricsSnapshot,
+    status: &GatewayStatus,
+    now_ms: i64,
+) -> HttpResponse {
+    let summary = summarize(metrics, status, now_ms);
+    let (ok, body) = match path {
+        HEALTH_PATH => (summary.ok, serde_json::to_string(&summary)),
+        READY_PATH => (
+            summary.gateway.ready,
+            serde_json::to_string(&serde_json::json!({ "ready": summary.gateway.ready })),
+        ),
+        _ => return HttpResponse::not_found(),
+    };
+    let body = body.unwrap_or_default();
+    HttpResponse::new(if ok { 200 } else { 503 }, JSON, body)
+}
+
+/// Route handler answering from `source`
+pub fn handler(source: Arc<Source>) -> Arc<Handler> {
+    Arc::new(move |path: &str| {
+        let (status, metrics) = source();
+        respond(path, &metrics, &status, gateway_logs::now_ms())
+    })
+}
+
+/// Start the endpoint if enabled. A port that's already taken only logs a
+/// warning; the app runs on without it.
+pub fn spawn_health_endpoint(app: &AppHandle, endpoint: &HealthEndpoint) {
+    if !endpoint.enabled {
+        return;
+    }
+    let listener = match http::bind(&endpoint.bind_address, endpoint.port) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "[health] Warning: no health endpoint, can't listen on {}:{}: {}",
+                endpoint.bind_address, endpoint.port, e
+            );
+            return;
+        }
+    };
+
+    // Both are managed in setup before the endpoint starts
+    let metrics = app.state::<Arc<AppMetrics>>().inner().clone();
+    let app = app.clone();
+    let source = Arc::new(move || (app.state::<SidecarManager>().status(), metrics.snapshot()));
+    match http::serve(listener, "health-endpoint", handler(source)) {
+        Ok(addr) => println!("[health] Health endpoint at http://{}{}", addr, HEALTH_PATH),
+        Err(e) => eprintln!(
+            "[health] Warning: failed to start the health endpoint: {}",
+            e
+        ),
+    }
+}
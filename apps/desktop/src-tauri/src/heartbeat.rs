@@ -0,0 +1,249 @@
+//! Gateway Heartbeats
+//!
+//! A gateway whose node event loop is blocked still accepts TCP
+//! connections, so the port probe alone can't tell a hung gateway from a
+//! healthy one. The frontend therefore reports its protocol activity via
+//! `report_gateway_activity`: how many requests it has sent, how many got a
+//! response, and when the last response arrived.
+//!
+//! Requests that fail with an error response still count as responses,
+//! since the gateway had to process them. The gateway is `unresponsive`
+//! when requests were sent after the last response and none has been
+//! answered for `gatewayHeartbeatTimeoutSecs`. A gateway nobody is talking
+//! to is `idle`, never unresponsive.
+//!
+//! The other way round, a gateway that is alive but no longer accepts
+//! connections, is caught by `status()` connecting to its port. The
+//! `PortProbe` answer is reused for `PROBE_INTERVAL`, so a UI polling the
+//! status doesn't hammer the port. A gateway refusing connections is
+//! unresponsive whatever the frontend reported, and one that stays
+//! unresponsive for `UNHEALTHY_AFTER` is announced as `gateway://unhealthy`
+//! so the frontend can offer a restart.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How long a port probe's answer is reused
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a port probe waits for the connection
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// How long the gateway is unresponsive before `gateway://unhealthy`
+pub const UNHEALTHY_AFTER: Duration = Duration::from_secs(5);
+
+/// Event a lasting unresponsiveness is announced with
+pub const UNHEALTHY_EVENT: &str = "gateway://unhealthy";
+
+/// Cumulative request counters from one frontend client. They start at zero
+/// for every new client, so counters going backwards mean a new client.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityReport {
+    /// Requests sent to the gateway
+    pub attempts: u64,
+    /// Responses received, including error responses
+    pub successes: u64,
+    /// Unix timestamp (ms) of the last response
+    pub last_success_at: Option<i64>,
+}
+
+/// Gateway health as far as the app can tell
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GatewayHealth {
+    /// No gateway process
+    Stopped,
+    /// A start is in progress
+    Starting,
+    /// Running, nothing has been sent to it yet
+    Idle,
+    /// Running and answering requests
+    Healthy,
+    /// Running, but refusing connections or leaving requests unanswered
+    /// for too long
+    Unresponsive,
+}
+
+impl GatewayHealth {
+    pub const ALL: [GatewayHealth; 5] = [
+        GatewayHealth::Stopped,
+        GatewayHealth::Starting,
+        GatewayHealth::Idle,
+        GatewayHealth::Healthy,
+        GatewayHealth::Unresponsive,
+    ];
+
+    /// Same as the serialized name
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GatewayHealth::Stopped => "stopped",
+            GatewayHealth::Starting => "starting",
+            GatewayHealth::Idle => "idle",
+            GatewayHealth::Healthy => "healthy",
+            GatewayHealth::Unresponsive => "unresponsive",
+        }
+    }
+}
+
+/// Tracks reported activity for the current gateway. Takes the time as a
+/// parameter so it can be tested without waiting.
+#[derive(Debug, Clone)]
+pub struct HeartbeatMonitor {
+    /// How long requests may go unanswered; zero disables the check
+    timeout: Duration,
+    last: Option<ActivityReport>,
+    /// Attempts already covered by the last observed response
+    answered_attempts: u64,
+    /// When requests were first seen waiting since the last response
+    waiting_since: Option<Instant>,
+}
+
+impl HeartbeatMonitor {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last: None,
+            answered_attempts: 0,
+            waiting_since: None,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The most recent report, if any
+    pub fn last_report(&self) -> Option<&ActivityReport> {
+        self.last.as_ref()
+    }
+
+    /// Record a report received at `now`
+    pub fn report(&mut self, report: ActivityReport, now: Instant) {
+        let new_client = self.last.as_ref().is_none_or(|last| {
+            report.attempts < last.attempts || report.successes < last.successes
+        });
+        let answered = match self.last {
+            Some(ref last) if !new_client => report.successes > last.successes,
+            _ => report.successes > 0,
+        };
+
+        if new_client {
+            self.answered_attempts = 0;
+            self.waiting_since = None;
+        }
+        // A response covers every request sent before it. Requests that timed
+        // out on the client before that no longer count against the gateway.
+        if answered {
+            self.answered_attempts = report.attempts;
+            self.waiting_since = None;
+        }
+        if report.attempts > self.answered_attempts && self.waiting_since.is_none() {
+            self.waiting_since = Some(now);
+        }
+        self.last = Some(report);
+    }
+
+    /// Health of a running gateway as of `now`
+    pub fn health(&self, now: Instant) -> GatewayHealth {
+        let Some(ref last) = self.last else {
+            return GatewayHealth::Idle;
+        };
+        let hung = !self.timeout.is_zero()
+            && self
+                .waiting_since
+                .is_some_and(|since| now.duration_since(since) >= self.timeout);
+        if hung {
+            GatewayHealth::Unresponsive
+        } else if last.attempts == 0 {
+            GatewayHealth::Idle
+        } else {
+            GatewayHealth::Healthy
+        }
+    }
+}
+
+/// Whether the gateway accepts connections, probing at most once per
+/// interval. Takes the time and the probe as parameters so it can be
+/// tested without a socket or waiting.
+#[derive(Debug, Clone)]
+pub struct PortProbe {
+    interval: Duration,
+    /// When the port was last probed and whether it accepted
+    last: Option<(Instant, bool)>,
+}
+
+impl PortProbe {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Whether the port accepts connections as of `now`, running `probe`
+    /// unless the last answer is recent enough
+    pub fn check(&mut self, now: Instant, probe: impl FnOnce() -> bool) -> bool {
+        match self.last {
+            Some((at, accepting)) if now.duration_since(at) < self.interval => accepting,
+            _ => {
+                let accepting = probe();
+                self.last = Some((now, accepting));
+                accepting
+            }
+        }
+    }
+
+    /// Forget the previous gateway's answer
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Payload of `gateway://unhealthy`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnhealthyEvent {
+    /// Of the unresponsive gateway, see `GatewayInfo.generation`
+    pub generation: u64,
+    pub unresponsive_for_ms: u64,
+    /// Whether the port still accepts connections, i.e. only requests
+    /// went unanswered
+    pub accepting_connections: bool,
+}
+
+/// Decides when an unresponsive gateway has been so for long enough to
+/// announce, once per stretch
+#[derive(Debug, Clone)]
+pub struct UnhealthyAlarm {
+    after: Duration,
+    since: Option<Instant>,
+    fired: bool,
+}
+
+impl UnhealthyAlarm {
+    pub fn new(after: Duration) -> Self {
+        Self {
+            after,
+            since: None,
+            fired: false,
+        }
+    }
+
+    /// Record `health` seen at `now`, returning how long the gateway has
+    /// been unresponsive when that is first past the threshold
+    pub fn observe(&mut self, health: GatewayHealth, now: Instant) -> Option<Duration> {
+        if health != GatewayHealth::Unresponsive {
+            self.since = None;
+            self.fired = false;
+            return None;
+        }
+        let since = *self.since.get_or_insert(now);
+        let unresponsive_for = now.duration_since(since);
+        if self.fired || unresponsive_for < self.after {
+            return None;
+        }
+        self.fired = true;
+        Some(unresponsive_for)
+    }
+}
@@ -0,0 +1,121 @@
+//! Minimal HTTP Listener
+//!
+//! Just enough HTTP/1.1 for scrapers and supervisors: one `GET` per
+//! connection, handled in order on a single thread, then the connection is
+//! closed. Requests larger than `MAX_REQUEST_BYTES` or slower than
+//! `READ_TIMEOUT` are dropped.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const MAX_REQUEST_BYTES: usize = 8 * 1024;
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(404, "text/plain; charset=utf-8", "not found\n")
+    }
+
+    pub fn method_not_allowed() -> Self {
+        Self::new(405, "text/plain; charset=utf-8", "method not allowed\n")
+    }
+
+    fn to_bytes(&self, head_only: bool) -> Vec<u8> {
+        let mut bytes = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        if !head_only {
+            bytes.extend_from_slice(self.body.as_bytes());
+        }
+        bytes
+    }
+}
+
+/// Answers a request path, without the query string
+pub type Handler = dyn Fn(&str) -> HttpResponse + Send + Sync;
+
+/// Bind `address:port`. Port 0 picks a free one, see `local_addr`.
+pub fn bind(address: &str, port: u16) -> io::Result<TcpListener> {
+    TcpListener::bind((address, port))
+}
+
+/// Answer requests on `listener` with `handler` on a background thread
+/// named `name`. Returns the address actually listened on.
+pub fn serve(listener: TcpListener, name: &str, handler: Arc<Handler>) -> io::Result<SocketAddr> {
+    let addr = listener.local_addr()?;
+    std::thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                if let Err(e) = respond(stream, handler.as_ref()) {
+                    eprintln!("[http] Request on {} failed: {}", addr, e);
+                }
+            }
+        })?;
+    Ok(addr)
+}
+
+fn respond(mut stream: TcpStream, handler: &Handler) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let Some(head) = read_head(&mut stream)? else {
+        return Ok(());
+    };
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or("/"),
+    );
+    let path = target.split(['?', '#']).next().unwrap_or(target);
+    let response = match method {
+        "GET" | "HEAD" => handler(path),
+        _ => HttpResponse::method_not_allowed(),
+    };
+    stream.write_all(&response.to_bytes(method == "HEAD"))?;
+    stream.flush()
+}
+
+/// The request line and headers, None if the client gave up or sent too much
+fn read_head(stream: &mut TcpStream) -> io::Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf)?;
+        if read == 0 || head.len() + read > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    Ok(Some(String::from_utf8_lossy(&head).into_owned()))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
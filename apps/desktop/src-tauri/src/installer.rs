@@ -0,0 +1,369 @@
+//! Installing openclaw
+//!
+//! `install_openclaw` installs the openclaw CLI globally from the app, for
+//! people who would rather not open a terminal, and `upgrade_openclaw`
+//! moves it to `openclaw@latest`, restarting a running gateway afterwards
+//! when asked to. npm is preferred: the one that came with the node the
+//! gateway runs on, run as `node npm-cli.js` like npx (see `resolve`),
+//! otherwise an npm found on its own. pnpm and bun are the fallbacks.
+//!
+//! The installer's output is emitted line by line as
+//! `openclaw://install-progress`. A failed install is told apart by that
+//! output: no package manager (`installer_not_found`), a global prefix npm
+//! may not write to (`install_permission_denied`, with the path), the
+//! registry out of reach (`install_network_failed`) and anything else
+//! (`install_failed`, with the last line). The app's proxy settings are
+//! passed on, as to the gateway.
+//!
+//! On success openclaw is looked up again and its path and version
+//! returned; the version cache is cleared so the next start asks anew.
+//! One install runs at a time.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::openclaw_version;
+use crate::proxy;
+use crate::resolve::{self, SearchContext};
+use crate::sidecar::{self, SidecarManager};
+
+/// Event each line of installer output is emitted as
+pub const PROGRESS_EVENT: &str = "openclaw://install-progress";
+
+/// The npm package
+pub const PACKAGE: &str = "openclaw";
+
+/// How long `--version` may take after an install
+const VERSION_TIMEOUT: Duration = Duration::from_secs(15);
+
+static INSTALLING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum InstallError {
+    #[error("npm, pnpm and bun were not found")]
+    NoInstaller,
+    #[error("permission denied writing to {path}")]
+    PermissionDenied { path: String },
+    #[error("{0}")]
+    Network(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// A package manager that can install openclaw globally
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Bun,
+}
+
+/// How to run a package manager
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installer {
+    pub manager: PackageManager,
+    pub program: PathBuf,
+    /// Before the install arguments, e.g. npm-cli.js when `program` is node
+    pub args: Vec<String>,
+    /// Put first on PATH, so install scripts find the same node
+    pub bin_dir: Option<PathBuf>,
+}
+
+impl Installer {
+    fn new(manager: PackageManager, program: PathBuf) -> Self {
+        Self {
+            manager,
+            bin_dir: program.parent().map(Path::to_path_buf),
+            program,
+            args: Vec::new(),
+        }
+    }
+
+    /// The arguments installing `package` globally
+    pub fn install_args(&self, package: &str) -> Vec<String> {
+        let install: &[&str] = match self.manager {
+            PackageManager::Npm => &["install", "--global", "--no-fund", "--no-audit"],
+            PackageManager::Pnpm | PackageManager::Bun => &["add", "--global"],
+        };
+        self.args
+            .iter()
+            .cloned()
+            .chain(install.iter().map(|arg| arg.to_string()))
+            .chain(std::iter::once(package.to_string()))
+            .collect()
+    }
+}
+
+/// The package manager to install with, in the order documented above
+pub fn find(context: &SearchContext) -> Option<Installer> {
+    if let Some(node) = sidecar::find_node_and_npx(context) {
+        let npm_cli = Path::new(&node.npx_cli).with_file_name("npm-cli.js");
+        if npm_cli.is_file() {
+            let mut installer = Installer::new(PackageManager::Npm, PathBuf::from(&node.node));
+            installer.args.push(npm_cli.to_string_lossy().to_string());
+            return Some(installer);
+        }
+    }
+    [
+        ("npm", PackageManager::Npm),
+        ("pnpm", PackageManager::Pnpm),
+        ("bun", PackageManager::Bun),
+    ]
+    .into_iter()
+    .find_map(|(name, manager)| {
+        resolve::resolve(name, context).map(|binary| Installer::new(manager, binary.path))
+    })
+}
+
+/// npm's lines without their `npm error` / `npm ERR!` prefix
+fn error_lines(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            ["npm error", "npm ERR!"]
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+                .unwrap_or(line)
+                .trim()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Error codes of a registry that couldn't be reached
+const NETWORK_CODES: &[&str] = &[
+    "ENOTFOUND",
+    "EAI_AGAIN",
+    "ETIMEDOUT",
+    "ECONNREFUSED",
+    "ECONNRESET",
+    "ENETUNREACH",
+    "ERR_SOCKET_TIMEOUT",
+];
+
+/// What went wrong, from the output of a failed install
+pub fn classify(output: &str) -> InstallError {
+    let lines = error_lines(output);
+    if lines
+        .iter()
+        .any(|line| line.contains("EACCES") || line.contains("EPERM"))
+    {
+        let path = lines
+            .iter()
+            .find_map(|line| line.strip_prefix("path "))
+            .map(|path| path.trim().to_string())
+            .unwrap_or_else(|| "the global prefix".to_string());
+        return InstallError::PermissionDenied { path };
+    }
+    let network = lines.iter().find(|line| {
+        NETWORK_CODES.iter().any(|code| line.contains(code)) || line.starts_with("network ")
+    });
+    if let Some(line) = network {
+        // npm explains the failed request on a line of its own
+        let detail = lines
+            .iter()
+            .find(|line| line.contains("request to"))
+            .unwrap_or(line);
+        return InstallError::Network(detail.to_string());
+    }
+    InstallError::Failed(
+        lines
+            .last()
+            .map_or("the installer printed nothing".to_string(), |line| {
+                line.to_string()
+            }),
+    )
+}
+
+/// Each line of `reader` to `on_line` with `stream`, and into `output`
+fn forward(
+    reader: impl Read,
+    stream: &'static str,
+    output: &Mutex<String>,
+    on_line: &(dyn Fn(&str, &str) + Sync),
+) {
+    for line in BufReader::new(reader).lines().map_while(Result::ok) {
+        on_line(stream, &line);
+        if let Ok(mut output) = output.lock() {
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+}
+
+/// Install `package` with `installer`, passing each line printed to
+/// `on_line` with its stream
+pub fn run(
+    installer: &Installer,
+    package: &str,
+    env: &[(String, String)],
+    on_line: &(dyn Fn(&str, &str) + Sync),
+) -> Result<(), InstallError> {
+    let mut child = Command::new(&installer.program)
+        .args(installer.install_args(package))
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            InstallError::Failed(format!(
+                "{} could not be run: {}",
+                installer.program.display(),
+                e
+            ))
+        })?;
+
+    let output = Mutex::new(String::new());
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    std::thread::scope(|scope| {
+        if let Some(stderr) = stderr {
+            scope.spawn(|| forward(stderr, "stderr", &output, on_line));
+        }
+        if let Some(stdout) = stdout {
+            forward(stdout, "stdout", &output, on_line);
+        }
+    });
+    let status = child
+        .wait()
+        .map_err(|e| InstallError::Failed(e.to_string()))?;
+    if status.success() {
+        return Ok(());
+    }
+    let output = output.into_inner().unwrap_or_default();
+    Err(classify(&output))
+}
+
+/// One line of installer output
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallProgress {
+    pub stream: String,
+    pub line: String,
+}
+
+/// What an install or upgrade did
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallResult {
+    pub installer: PackageManager,
+    /// The openclaw found afterwards; None when the installer's global bin
+    /// directory isn't anywhere the app looks
+    pub path: Option<String>,
+    pub version: Option<String>,
+    /// The running gateway was restarted onto it
+    pub restarted: bool,
+}
+
+/// Clears `INSTALLING` when the install ends
+struct InstallGuard;
+
+impl Drop for InstallGuard {
+    fn drop(&mut self) {
+        INSTALLING.store(false, Ordering::SeqCst);
+    }
+}
+
+fn install(app: &AppHandle, package: &str) -> Result<InstallResult, AppError> {
+    if INSTALLING.swap(true, Ordering::SeqCst) {
+        return Err(InstallError::Failed("another install is still running".to_string()).into());
+    }
+    let _guard = InstallGuard;
+
+    let config = Config::load()?;
+    let context = SearchContext::current(&config);
+    let installer = find(&context).ok_or(InstallError::NoInstaller)?;
+    println!(
+        "[openclaw] Installing {} with {}",
+        package,
+        installer.program.display()
+    );
+
+    let mut env: Vec<(String, String)> = Vec::new();
+    if let Some(ref dir) = installer.bin_dir {
+        let existing = std::env::var_os("PATH").unwrap_or_default();
+        let dirs = std::iter::once(dir.clone()).chain(std::env::split_paths(&existing));
+        if let Ok(path) = std::env::join_paths(dirs) {
+            env.push(("PATH".to_string(), path.to_string_lossy().to_string()));
+        }
+    }
+    if let Ok(Some(proxy)) = proxy::plan(&config) {
+        env.extend(proxy.env());
+    }
+
+    let emitter = app.clone();
+    run(&installer, package, &env, &move |stream, line| {
+        let progress = InstallProgress {
+            stream: stream.to_string(),
+            line: line.to_string(),
+        };
+        let _ = emitter.emit(PROGRESS_EVENT, &progress);
+    })
+    .inspect_err(|e| eprintln!("[openclaw] Install failed: {}", e))?;
+    openclaw_version::clear_cache();
+
+    // Where the installer put it, next to its own binary first
+    let found = installer
+        .bin_dir
+        .as_deref()
+        .and_then(|dir| context.find_in(dir, PACKAGE))
+        .or_else(|| resolve::resolve(PACKAGE, &context).map(|binary| binary.path));
+    let version = found.as_ref().and_then(|path| {
+        openclaw_version::query(&path.to_string_lossy(), &[], &env, VERSION_TIMEOUT)
+    });
+    println!(
+        "[openclaw] Installed {} at {}",
+        version.map_or("openclaw".to_string(), |v| format!("openclaw {}", v)),
+        found
+            .as_ref()
+            .map_or("an unknown location".to_string(), |path| path
+                .display()
+                .to_string())
+    );
+    Ok(InstallResult {
+        installer: installer.manager,
+        path: found.map(|path| path.display().to_string()),
+        version: version.map(|version| version.to_string()),
+        restarted: false,
+    })
+}
+
+/// Install openclaw globally, emitting the output as
+/// `openclaw://install-progress`
+#[tauri::command]
+pub async fn install_openclaw(app: AppHandle) -> Result<InstallResult, AppError> {
+    tokio::task::spawn_blocking(move || install(&app, PACKAGE)).await?
+}
+
+/// Upgrade openclaw to the latest release. With `restart_gateway` a
+/// running gateway is restarted onto it, keeping its token, and announced
+/// as `gateway://started`.
+#[tauri::command]
+pub async fn upgrade_openclaw(
+    app: AppHandle,
+    restart_gateway: Option<bool>,
+) -> Result<InstallResult, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let mut result = install(&app, &format!("{}@latest", PACKAGE))?;
+        let manager = app.state::<SidecarManager>();
+        if restart_gateway.unwrap_or(false) && manager.gateway_pid().is_some() {
+            let started = manager.restart_keeping_token("openclaw upgraded")?;
+            let _ = app.emit("gateway://started", &started);
+            result.restarted = true;
+        }
+        Ok(result)
+    })
+    .await?
+}
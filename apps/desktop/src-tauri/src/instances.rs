@@ -0,0 +1,227 @@
+//! Gateway Instances
+//!
+//! Besides the usual gateway, which runs the active profile and is the
+//! `default` instance, any other profile can run at the same time as an
+//! instance named after it, with that profile's key, port and arguments
+//! and a state directory and socket of its own. Each gets its own manager
+//! here, created the first time it is started.
+//!
+//! Every manager asks the others which ports they serve before starting,
+//! so one instance never takes another for a stranger on its port. Tokens
+//! are new for every start, so they differ anyway.
+//!
+//! Only the default instance has a pid file, crash supervision, the
+//! watchdog and `gateway://status` events; the others are started and
+//! stopped by hand.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::gateway_socket;
+use crate::gateway_state;
+use crate::metrics::AppMetrics;
+use crate::paths::AppPaths;
+use crate::profiles::{self, ProfileError};
+use crate::sidecar::{GatewayStatus, SidecarManager};
+
+/// Id of the gateway running the active profile
+pub const DEFAULT_INSTANCE: &str = "default";
+
+/// The other running instances and the ports they serve on
+pub type Peers = Box<dyn Fn() -> Vec<(String, u16)> + Send + Sync>;
+
+/// Managers of the instances besides the default one, by id
+#[derive(Default)]
+pub struct GatewayInstances {
+    managers: Mutex<BTreeMap<String, Arc<SidecarManager>>>,
+}
+
+impl GatewayInstances {
+    pub fn get(&self, id: &str) -> Option<Arc<SidecarManager>> {
+        self.managers.lock().ok()?.get(id).cloned()
+    }
+
+    /// The manager of instance `id`, created for profile `id` if there is
+    /// none yet
+    fn get_or_create(&self, app: &AppHandle, id: &str) -> Result<Arc<SidecarManager>, AppError> {
+        if let Some(manager) = self.get(id) {
+            return Ok(manager);
+        }
+        let config = Config::load()?;
+        if !config.profiles.contains_key(id) {
+            profiles::validate_name(id)?;
+            return Err(if id == profiles::active(&config) {
+                ProfileError::Active(id.to_string())
+            } else {
+                ProfileError::Unknown(id.to_string())
+            }
+            .into());
+        }
+
+        let manager = SidecarManager::default()
+            .with_metrics(app.state::<Arc<AppMetrics>>().inner().clone())
+            .with_profile(id);
+        manager.set_peers({
+            let app = app.clone();
+            let id = id.to_string();
+            Box::new(move || {
+                let mut peers = Vec::new();
+                if let Some(port) = app.state::<SidecarManager>().serving_port() {
+                    peers.push((DEFAULT_INSTANCE.to_string(), port));
+                }
+                peers.extend(app.state::<GatewayInstances>().running(Some(&id)));
+                peers
+            })
+        });
+        let manager = Arc::new(manager);
+        let mut managers = self
+            .managers
+            .lock()
+            .map_err(|e| AppError::GatewayStartFailed {
+                detail: e.to_string(),
+            })?;
+        Ok(managers.entry(id.to_string()).or_insert(manager).clone())
+    }
+
+    /// Instances serving right now and their ports, besides `except`
+    pub fn running(&self, except: Option<&str>) -> Vec<(String, u16)> {
+        let Ok(managers) = self.managers.lock() else {
+            return Vec::new();
+        };
+        managers
+            .iter()
+            .filter(|(id, _)| Some(id.as_str()) != except)
+            .filter_map(|(id, manager)| Some((id.clone(), manager.serving_port()?)))
+            .collect()
+    }
+
+    fn all(&self) -> Vec<(String, Arc<SidecarManager>)> {
+        match self.managers.lock() {
+            Ok(managers) => managers
+                .iter()
+                .map(|(id, manager)| (id.clone(), manager.clone()))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Stop every instance besides the default one
+    pub fn stop_all(&self) {
+        for (id, manager) in self.all() {
+            if manager.gateway_pid().is_none() {
+                continue;
+            }
+            println!("[instances] Stopping instance {}", id);
+            if let Err(e) = manager.stop() {
+                eprintln!("[instances] Failed to stop instance {}: {}", id, e);
+            }
+        }
+    }
+}
+
+/// Let the default manager know about the running instances
+pub fn install_peers(app: &AppHandle) {
+    let handle = app.clone();
+    app.state::<SidecarManager>().set_peers(Box::new(move || {
+        handle.state::<GatewayInstances>().running(None)
+    }));
+}
+
+/// Stop all instances but the default one, if the app got that far
+pub fn stop_all(app: &AppHandle) {
+    if let Some(instances) = app.try_state::<GatewayInstances>() {
+        instances.stop_all();
+    }
+}
+
+/// Where instance `id` keeps its state, given the default instance's
+pub fn state_dir_for(dir: &Path, id: &str) -> PathBuf {
+    let mut name = dir.file_name().unwrap_or_default().to_os_string();
+    name.push(format!("-{}", id));
+    dir.with_file_name(name)
+}
+
+/// Where instance `id` listens with the `unix` transport, given the
+/// default instance's socket: `gateway.sock` becomes `gateway-<id>.sock`
+pub fn socket_path_for(path: &Path, id: &str) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!("-{}", id));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// Move `config`'s gateway state next to the default instance's, in
+/// `<dir>-<id>`, so instances don't share sessions, and its socket the
+/// same way. Left alone when the directory or socket can't be
+/// determined, which `prepare` reports.
+pub fn separate_state(config: &mut Config, id: &str) {
+    if let Ok(dir) = gateway_state::state_dir(config, AppPaths::resolve().as_ref()) {
+        config.gateway_state_dir = Some(state_dir_for(&dir, id));
+    }
+    if let Ok(Some(socket)) = gateway_socket::plan(config) {
+        config.gateway_socket_path = Some(socket_path_for(&socket, id));
+    }
+}
+
+/// Run `f` with the manager of `instance`, the default one when None or
+/// `default`, creating it if needed
+pub fn with_manager<T>(
+    app: &AppHandle,
+    instance: Option<&str>,
+    f: impl FnOnce(&SidecarManager) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    match instance {
+        None | Some(DEFAULT_INSTANCE) => f(&app.state::<SidecarManager>()),
+        Some(id) => {
+            let manager = app.state::<GatewayInstances>().get_or_create(app, id)?;
+            f(&manager)
+        }
+    }
+}
+
+/// `with_manager`, but None for an instance that was never started
+pub fn with_existing<T>(
+    app: &AppHandle,
+    instance: Option<&str>,
+    f: impl FnOnce(&SidecarManager) -> Result<T, AppError>,
+) -> Result<Option<T>, AppError> {
+    match instance {
+        None | Some(DEFAULT_INSTANCE) => f(&app.state::<SidecarManager>()).map(Some),
+        Some(id) => match app.state::<GatewayInstances>().get(id) {
+            Some(manager) => f(&manager).map(Some),
+            None => Ok(None),
+        },
+    }
+}
+
+/// One instance's status in the list `get_gateway_status` returns
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceStatus {
+    pub instance: String,
+    #[serde(flatten)]
+    pub status: GatewayStatus,
+}
+
+/// The default instance's status and then every other's, by id
+pub fn statuses(app: &AppHandle) -> Vec<InstanceStatus> {
+    let mut statuses = vec![InstanceStatus {
+        instance: DEFAULT_INSTANCE.to_string(),
+        status: app.state::<SidecarManager>().status(),
+    }];
+    for (id, manager) in app.state::<GatewayInstances>().all() {
+        statuses.push(InstanceStatus {
+            instance: id,
+            status: manager.status(),
+        });
+    }
+    statuses
+}
@@ -0,0 +1,346 @@
+//! API Key Storage
+//!
+//! The API key is kept in the system credential store - the Keychain on
+//! macOS, the Secret Service (through `secret-tool`) on Linux and the
+//! Credential Manager on Windows - rather than in config.json, which
+//! backup tools sweep up and a default umask leaves readable to others.
+//! config.json only records that the key is there (`apiKeyInKeychain`).
+//!
+//! A key found in config.json is moved to the store on load. Where there
+//! is no store, e.g. on headless Linux, `storeApiKeyInFile` keeps the key
+//! in secrets.json next to config.json, private to the owner and sealed
+//! with a machine-bound key (see `sealed_keys`). Keys for further
+//! providers (`providerKeys`) are kept the same way, in secrets.json
+//! without a store, each under its own account, with
+//! `providerKeysInKeychain` listing which are in the store, and so are the
+//! keys of the profiles not in use (see `profiles`) and the stored keys
+//! (see `stored_keys`).
+//!
+//! `SystemKeychain` caches what it read or wrote, and that there is no
+//! store, since the config is loaded far more often than the key changes.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use thiserror::Error;
+
+/// Service the app's secrets are stored under
+pub const SERVICE: &str = "simplestclaw";
+
+/// Account of the API key
+pub const API_KEY_ACCOUNT: &str = "anthropic-api-key";
+
+/// Account of the token kept with `persistGatewayToken`
+pub const GATEWAY_TOKEN_ACCOUNT: &str = "gateway-token";
+
+/// Account of the key for `provider` in `providerKeys`
+pub fn provider_key_account(provider: &str) -> String {
+    format!("provider-key-{}", provider)
+}
+
+/// Account of the API key of profile `name` while it isn't active
+pub fn profile_key_account(name: &str) -> String {
+    format!("profile-key-{}", name)
+}
+
+/// Account of the stored API key `label` (see `stored_keys`)
+pub fn stored_key_account(label: &str) -> String {
+    format!("stored-key-{}", label)
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum KeychainError {
+    #[error("No system keychain is available: {0}")]
+    Unavailable(String),
+    #[error("The system keychain failed: {0}")]
+    Failed(String),
+}
+
+/// Secrets by account under `SERVICE`, behind a trait so tests can use a
+/// map instead of the user's keychain
+pub trait SecretStore: Send + Sync {
+    /// None when nothing is stored for `account`
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError>;
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError>;
+    /// Succeeds when nothing was stored
+    fn delete(&self, account: &str) -> Result<(), KeychainError>;
+}
+
+/// The OS credential store
+#[derive(Default)]
+pub struct SystemKeychain {
+    cache: Mutex<HashMap<String, Option<String>>>,
+    unavailable: OnceLock<KeychainError>,
+}
+
+/// The keychain shared by the whole app
+pub fn system() -> &'static SystemKeychain {
+    static KEYCHAIN: OnceLock<SystemKeychain> = OnceLock::new();
+    KEYCHAIN.get_or_init(SystemKeychain::default)
+}
+
+impl SystemKeychain {
+    fn remember(&self, account: &str, secret: Option<String>) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(account.to_string(), secret);
+        }
+    }
+
+    /// Run `call` unless there is known to be no store, remembering when
+    /// it finds none
+    fn platform<T>(
+        &self,
+        call: impl FnOnce() -> Result<T, KeychainError>,
+    ) -> Result<T, KeychainError> {
+        if let Some(e) = self.unavailable.get() {
+            return Err(e.clone());
+        }
+        call().inspect_err(|e| {
+            if let KeychainError::Unavailable(_) = e {
+                let _ = self.unavailable.set(e.clone());
+            }
+        })
+    }
+}
+
+impl SecretStore for SystemKeychain {
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+        if let Some(cached) = self
+            .cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(account).cloned())
+        {
+            return Ok(cached);
+        }
+        let secret = self.platform(|| platform::get(account))?;
+        self.remember(account, secret.clone());
+        Ok(secret)
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+        self.platform(|| platform::set(account, secret))?;
+        self.remember(account, Some(secret.to_string()));
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<(), KeychainError> {
+        self.platform(|| platform::delete(account))?;
+        self.remember(account, None);
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::{KeychainError, SERVICE};
+    use security_framework::passwords;
+
+    /// errSecItemNotFound
+    const ITEM_NOT_FOUND: i32 = -25300;
+
+    fn failed(e: security_framework::base::Error) -> KeychainError {
+        KeychainError::Failed(e.to_string())
+    }
+
+    pub fn get(account: &str) -> Result<Option<String>, KeychainError> {
+        match passwords::get_generic_password(SERVICE, account) {
+            Ok(bytes) => String::from_utf8(bytes)
+                .map(Some)
+                .map_err(|e| KeychainError::Failed(e.to_string())),
+            Err(e) if e.code() == ITEM_NOT_FOUND => Ok(None),
+            Err(e) => Err(failed(e)),
+        }
+    }
+
+    pub fn set(account: &str, secret: &str) -> Result<(), KeychainError> {
+        passwords::set_generic_password(SERVICE, account, secret.as_bytes()).map_err(failed)
+    }
+
+    pub fn delete(account: &str) -> Result<(), KeychainError> {
+        match passwords::delete_generic_password(SERVICE, account) {
+            Err(e) if e.code() != ITEM_NOT_FOUND => Err(failed(e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::{KeychainError, SERVICE};
+    use std::io::{self, Write};
+    use std::process::{Command, Output, Stdio};
+
+    /// `secret-tool` with `args`, writing `input` to its stdin
+    fn secret_tool(args: &[&str], input: Option<&str>) -> Result<Output, KeychainError> {
+        let mut child = Command::new("secret-tool")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => {
+                    KeychainError::Unavailable("secret-tool is not installed".to_string())
+                }
+                _ => KeychainError::Unavailable(e.to_string()),
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Some(input) = input {
+                stdin
+                    .write_all(input.as_bytes())
+                    .map_err(|e| KeychainError::Failed(e.to_string()))?;
+            }
+        }
+        child
+            .wait_with_output()
+            .map_err(|e| KeychainError::Failed(e.to_string()))
+    }
+
+    fn failed(output: &Output) -> KeychainError {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        KeychainError::Failed(if stderr.is_empty() {
+            format!("secret-tool exited with {}", output.status)
+        } else {
+            stderr
+        })
+    }
+
+    pub fn get(account: &str) -> Result<Option<String>, KeychainError> {
+        let output = secret_tool(&["lookup", "service", SERVICE, "account", account], None)?;
+        if output.status.success() {
+            return Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()));
+        }
+        // Nothing stored exits with 1 and says nothing
+        if output.stderr.is_empty() {
+            return Ok(None);
+        }
+        Err(failed(&output))
+    }
+
+    pub fn set(account: &str, secret: &str) -> Result<(), KeychainError> {
+        let label = format!("--label=simplestclaw {}", account);
+        let args = ["store", &label, "service", SERVICE, "account", account];
+        let output = secret_tool(&args, Some(secret))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(failed(&output))
+        }
+    }
+
+    pub fn delete(account: &str) -> Result<(), KeychainError> {
+        let output = secret_tool(&["clear", "service", SERVICE, "account", account], None)?;
+        if output.status.success() || output.stderr.is_empty() {
+            Ok(())
+        } else {
+            Err(failed(&output))
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{KeychainError, SERVICE};
+    use std::io;
+    use windows_sys::Win32::Foundation::ERROR_NOT_FOUND;
+    use windows_sys::Win32::Security::Credentials::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE,
+        CRED_TYPE_GENERIC,
+    };
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(Some(0)).collect()
+    }
+
+    fn target(account: &str) -> Vec<u16> {
+        wide(&format!("{}/{}", SERVICE, account))
+    }
+
+    fn not_found(e: &io::Error) -> bool {
+        e.raw_os_error() == Some(ERROR_NOT_FOUND as i32)
+    }
+
+    pub fn get(account: &str) -> Result<Option<String>, KeychainError> {
+        let target = target(account);
+        let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+        // SAFETY: `target` is NUL-terminated and the out pointer is valid
+        if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+            let e = io::Error::last_os_error();
+            return if not_found(&e) {
+                Ok(None)
+            } else {
+                Err(KeychainError::Failed(e.to_string()))
+            };
+        }
+        // SAFETY: CredReadW succeeded, so `credential` points to a
+        // credential whose blob holds `CredentialBlobSize` bytes until
+        // it is freed
+        let secret = unsafe {
+            let blob = std::slice::from_raw_parts(
+                (*credential).CredentialBlob,
+                (*credential).CredentialBlobSize as usize,
+            );
+            let secret = String::from_utf8(blob.to_vec());
+            CredFree(credential as *const _);
+            secret
+        };
+        secret
+            .map(Some)
+            .map_err(|e| KeychainError::Failed(e.to_string()))
+    }
+
+    pub fn set(account: &str, secret: &str) -> Result<(), KeychainError> {
+        let mut target = target(account);
+        let mut user = wide(account);
+        let mut blob = secret.as_bytes().to_vec();
+        // SAFETY: all-zero is a valid CREDENTIALW: no flags, attributes,
+        // alias or comment
+        let mut credential: CREDENTIALW = unsafe { std::mem::zeroed() };
+        credential.Type = CRED_TYPE_GENERIC;
+        credential.TargetName = target.as_mut_ptr();
+        credential.UserName = user.as_mut_ptr();
+        credential.CredentialBlobSize = blob.len() as u32;
+        credential.CredentialBlob = blob.as_mut_ptr();
+        credential.Persist = CRED_PERSIST_LOCAL_MACHINE;
+        // SAFETY: the strings and blob outlive the call
+        if unsafe { CredWriteW(&credential, 0) } == 0 {
+            return Err(KeychainError::Failed(
+                io::Error::last_os_error().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn delete(account: &str) -> Result<(), KeychainError> {
+        let target = target(account);
+        // SAFETY: `target` is NUL-terminated
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            let e = io::Error::last_os_error();
+            if !not_found(&e) {
+                return Err(KeychainError::Failed(e.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use super::KeychainError;
+
+    fn unavailable() -> KeychainError {
+        KeychainError::Unavailable("not supported on this platform".to_string())
+    }
+
+    pub fn get(_account: &str) -> Result<Option<String>, KeychainError> {
+        Err(unavailable())
+    }
+
+    pub fn set(_account: &str, _secret: &str) -> Result<(), KeychainError> {
+        Err(unavailable())
+    }
+
+    pub fn delete(_account: &str) -> Result<(), KeychainError> {
+        Err(unavailable())
+    }
+}
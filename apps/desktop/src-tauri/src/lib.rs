@@ -1,33 +1,235 @@
 mod activity;
-mod config;
+pub mod adopted;
+pub mod anthropic_endpoint;
+pub mod api_key;
+pub mod autostart;
+pub mod blocked_tools;
+pub mod bundled;
+pub mod capabilities;
+pub mod cli;
+pub mod cli_import;
+pub mod config;
+pub mod config_transfer;
+pub mod config_watch;
+pub mod connection;
+pub mod connectivity;
+pub mod control;
+pub mod deep_link;
+pub mod diagnostics;
+pub mod environment;
+pub mod error;
+pub mod external;
+pub mod gateway_logs;
+pub mod gateway_metrics;
+pub mod gateway_options;
+pub mod gateway_socket;
+pub mod gateway_state;
+pub mod gateway_stats;
+pub mod headless;
+pub mod health;
+pub mod heartbeat;
+pub mod http;
+pub mod installer;
+pub mod instances;
+pub mod keychain;
+pub mod limits;
+pub mod locale;
+pub mod log_level;
+pub mod log_search;
+pub mod log_stream;
+pub mod login_item;
+pub mod messages;
+pub mod metrics;
+pub mod migrations;
+pub mod models;
+pub mod openclaw_update;
+pub mod openclaw_version;
+pub mod overrides;
+pub mod paths;
+pub mod pid_file;
+pub mod port;
+pub mod process;
+pub mod profiles;
+pub mod prometheus;
+pub mod protocol;
+pub mod proxy;
+pub mod purge;
+pub mod ready;
+pub mod resolve;
+pub mod resources;
+pub mod retention;
+pub mod run_as;
 pub mod runtime;
-mod sidecar;
+pub mod sandbox;
+pub mod scheduled_restart;
+pub mod sealed_keys;
+pub mod settings;
+pub mod setup;
+pub mod shell_env;
+pub mod shutdown;
+pub mod sidecar;
+pub mod single_instance;
+pub mod spawn_failure;
+pub mod spend;
+pub mod status_events;
+pub mod status_history;
+pub mod stored_keys;
+pub mod supervisor;
+pub mod tls;
+pub mod tray;
+pub mod wake;
+pub mod watchdog;
 
 use activity::ActivityManager;
+use gateway_logs::GatewayLogs;
+use metrics::AppMetrics;
+use pid_file::PidFile;
+use purge::PurgeGuard;
 use runtime::RuntimeManager;
-use sidecar::{SidecarManager, kill_orphaned_gateway_processes};
+use sidecar::SidecarManager;
+use status_history::StatusHistory;
+use std::sync::Arc;
 use tauri::Manager;
+use tray::TrayMenu;
+
+/// The app's gateway manager, logging, recording history and keeping a pid
+/// file where `config` and the data dir allow. A gateway an earlier run
+/// left behind is killed first.
+pub(crate) fn gateway_manager(
+    config: &config::Config,
+    metrics: Arc<AppMetrics>,
+) -> SidecarManager {
+    let gateway_logs = paths::AppPaths::resolve()
+        .filter(|_| config.file_logging_enabled)
+        .and_then(|paths| match GatewayLogs::open(&paths.logs_dir) {
+            Ok(logs) => Some(logs.with_rotation(
+                config.gateway_log_max_file_mb.max(1) * 1024 * 1024,
+                config.gateway_log_keep_files,
+            )),
+            Err(e) => {
+                eprintln!("[startup] Failed to open the gateway log: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default();
+    let history = paths::AppPaths::resolve()
+        .and_then(|paths| {
+            let opened = paths.status_history_file().and_then(|path| {
+                StatusHistory::open(path, status_history::MAX_ENTRIES)
+            });
+            match opened {
+                Ok(history) => Some(history),
+                Err(e) => {
+                    eprintln!("[startup] Failed to open the status history: {}", e);
+                    None
+                }
+            }
+        })
+        .unwrap_or_default();
+    let mut manager = SidecarManager::default()
+        .with_metrics(metrics)
+        .with_logs(Arc::new(gateway_logs))
+        .with_history(Arc::new(history));
+    // Kill a gateway a crashed or force-quit run left behind
+    let pid_file = paths::AppPaths::resolve()
+        .and_then(|paths| paths.gateway_pid_file().ok())
+        .map(PidFile::new);
+    if let Some(pid_file) = pid_file {
+        pid_file::reap(&pid_file);
+        manager = manager.with_pid_file(pid_file);
+    }
+    manager
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Clean up any orphaned gateway processes from previous runs
-    // This handles cases where the app crashed or was force-quit
-    println!("[startup] Cleaning up any orphaned gateway processes...");
-    kill_orphaned_gateway_processes();
-    
-    // Small delay to ensure processes are fully killed
-    std::thread::sleep(std::time::Duration::from_millis(500));
-    
+    // A second launch hands over to the running app, before the leftover
+    // gateway `gateway_manager` reaps could be that app's
+    let instance_lock = match single_instance::lock_app() {
+        Ok(lock) => lock,
+        Err(holder) => {
+            single_instance::hand_over(&holder, deep_link::link_from_args(std::env::args()));
+            return;
+        }
+    };
+
+    // Move files from older versions into the current directory layout
+    paths::migrate_on_startup();
+
     let app = tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
-        .setup(|app| {
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .setup(move |app| {
+            let config = config::Config::load().unwrap_or_default();
+
             // Initialize managers
-            app.manage(SidecarManager::default());
+            let metrics = Arc::new(AppMetrics::default());
+            app.manage(gateway_manager(&config, metrics.clone()));
+            app.manage(instances::GatewayInstances::default());
+            instances::install_peers(app.handle());
+            config::install_token_saver(app.handle());
+            app.manage(metrics);
             app.manage(RuntimeManager::default());
             app.manage(ActivityManager::default());
+            app.manage(PurgeGuard::default());
+            app.manage(gateway_state::SessionTracker::default());
+            app.manage(TrayMenu::default());
+            if let Some(lock) = instance_lock {
+                app.manage(lock);
+            }
+
+            // Before the status publisher, which updates the menu
+            if let Err(e) = tray::create_tray(app.handle()) {
+                eprintln!("[startup] Failed to create the tray icon: {}", e);
+            }
+
+            // Send captured gateway output to subscribed windows
+            log_stream::install_emitter(app.handle());
+
+            // Push status snapshots and transitions to the frontend
+            status_events::install_change_emitter(app.handle());
+            status_events::spawn_status_publisher(app.handle().clone());
+            gateway_metrics::spawn_metrics_publisher(app.handle().clone());
 
-            // Auto-install runtime in background if not installed
+            // Serve /metrics and /healthz when enabled
+            prometheus::spawn_metrics_exporter(app.handle(), &config.metrics_exporter);
+            health::spawn_health_endpoint(app.handle(), &config.health_endpoint);
+
+            // Pick up edits to config.json made outside the app
+            config_watch::spawn_config_watcher(app.handle());
+
+            // simplestclaw:// links, before the control socket forwards any
+            deep_link::install(app.handle());
+
+            // Let `simplestclaw <verb>` drive this instance
+            control::spawn_control_server(app.handle());
+
+            // Restart the gateway when it exceeds its limits
+            watchdog::spawn_watchdog(app.handle().clone());
+            // And when it crashes
+            supervisor::spawn_supervisor(app.handle().clone());
+            // And when it stopped answering while the machine slept
+            wake::spawn_wake_monitor(app.handle().clone());
+            // And after autoRestartIntervalHours, once it is idle
+            scheduled_restart::spawn_scheduled_restarts(app.handle().clone());
+
+            // Clean up old logs and crash reports now and then daily
+            retention::spawn_retention_task();
+
+            // Tell the user about a newer openclaw once a day
+            openclaw_update::spawn_update_checker(app.handle().clone());
+
+            // Warn about, and with hardStopOnLimit stop, a gateway spending
+            // past spendLimitUsd
+            spend::spawn_spend_monitor(app.handle().clone());
+
+            // Stop the gateway when the app is killed rather than quit
+            shutdown::spawn_signal_handler(app.handle().clone());
+
+            // Auto-install runtime in background if not installed, then
+            // start the gateway if autoStartGateway is on
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 if !RuntimeManager::is_installed() {
@@ -40,31 +242,130 @@ pub fn run() {
                 } else {
                     println!("[runtime] Node.js runtime already installed");
                 }
+                autostart::auto_start_on_launch(app_handle).await;
             });
 
             Ok(())
         })
-        .on_window_event(|window, event| {
-            // Stop the gateway when the window close is requested
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
+        .on_window_event(|window, event| match event {
+            // Stop the gateway when the window close is requested, or just
+            // hide the window if it should keep running
+            tauri::WindowEvent::CloseRequested { api, .. } => {
+                let keep_running = config::Config::load()
+                    .map(|config| config.keep_running_in_background)
+                    .unwrap_or(false);
+                if keep_running {
+                    println!("[window] Window close requested, keeping gateway running in the background");
+                    api.prevent_close();
+                    let _ = window.hide();
+                    return;
+                }
+                // Other windows still use it; the last one stops it
+                if window.app_handle().webview_windows().len() > 1 {
+                    println!("[window] Window close requested, other windows keep the gateway running");
+                    return;
+                }
                 println!("[window] Window close requested, stopping gateway...");
+                instances::stop_all(window.app_handle());
                 if let Some(manager) = window.app_handle().try_state::<SidecarManager>() {
                     let _ = manager.stop();
                 }
             }
+            // A closed window can't receive its log subscriptions anymore
+            tauri::WindowEvent::Destroyed => {
+                if let Some(manager) = window.app_handle().try_state::<SidecarManager>() {
+                    manager.logs().subscribers().remove_window(window.label());
+                }
+            }
+            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             // Config
             config::get_config,
+            config::take_config_recovery,
+            config::take_config_permissions_fix,
             config::set_api_key,
+            config::delete_api_key,
+            api_key::test_api_key,
             config::set_provider,
+            config::set_provider_key,
+            config::has_provider_key,
+            config::delete_provider_key,
+            config::set_gateway_port,
+            config::apply_suggested_port,
+            config::set_blocked_tools,
+            config::set_gateway_options,
+            config::set_locale,
+            messages::get_error_catalog,
+            config::set_openclaw_path,
+            config::set_workspace_dir,
+            models::set_model_config,
+            log_level::set_gateway_log_level,
+            config::set_anthropic_endpoint,
+            config::set_proxy_config,
+            config::set_tls_config,
+            config::set_external_gateway,
+            config::set_persist_gateway_token,
+            settings::set_config,
+            config_transfer::export_config,
+            config_transfer::import_config,
+            cli_import::import_from_openclaw,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::delete_profile,
+            profiles::switch_profile,
+            stored_keys::list_api_keys,
+            stored_keys::add_api_key,
+            stored_keys::remove_api_key,
+            stored_keys::set_active_api_key,
+            limits::check_session_limit,
+            config::set_auto_start_gateway,
+            config::set_keep_running_in_background,
+            login_item::set_launch_at_login,
             config::has_api_key,
             config::get_app_data_info,
             config::delete_all_app_data,
+            purge::prepare_purge,
+            purge::purge_app_data,
+            diagnostics::export_diagnostics,
             // Gateway
+            ready::ensure_ready,
+            setup::get_setup_status,
             sidecar::start_gateway,
+            bundled::get_openclaw_source,
+            openclaw_version::get_openclaw_version,
+            openclaw_update::check_for_openclaw_update,
+            installer::install_openclaw,
+            installer::upgrade_openclaw,
+            sidecar::preview_gateway_start,
+            sidecar::get_effective_gateway_env,
             sidecar::stop_gateway,
+            sidecar::restart_gateway,
+            sidecar::regenerate_token,
             sidecar::get_gateway_status,
+            sidecar::get_gateway_info,
+            sidecar::adopt_external_gateway,
+            sidecar::validate_gateway_info,
+            sidecar::get_last_crash,
+            sidecar::get_launch_info,
+            sidecar::pause_gateway,
+            sidecar::resume_gateway,
+            connection::get_connection_string,
+            connection::copy_connection_string,
+            connectivity::run_connectivity_check,
+            deep_link::take_received_key,
+            sidecar::report_gateway_activity,
+            gateway_state::report_sessions,
+            sidecar::get_gateway_logs,
+            sidecar::clear_gateway_logs,
+            sidecar::search_gateway_logs,
+            status_history::get_status_history,
+            gateway_metrics::get_gateway_metrics,
+            gateway_stats::get_gateway_stats,
+            spend::get_usage_estimate,
+            // Log streaming
+            log_stream::subscribe_logs,
+            log_stream::unsubscribe_logs,
             // Runtime
             runtime::get_runtime_status,
             runtime::install_runtime,
@@ -74,25 +375,25 @@ pub fn run() {
             activity::get_activity_log,
             activity::clear_activity_log,
             activity::add_activity_entry,
+            // Metrics
+            metrics::get_app_metrics,
+            // Paths
+            paths::get_app_paths,
+            paths::open_logs_dir,
+            paths::get_log_directory,
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
 
-    // Use run() with event handler for proper cleanup on exit
+    // Use run() with event handler for proper cleanup on exit. Exit alone
+    // fires when quitting with no window left, so both stop the gateway.
     app.run(|app_handle, event| {
         match event {
             tauri::RunEvent::ExitRequested { .. } => {
-                println!("[app] Exit requested, cleaning up...");
-                if let Some(manager) = app_handle.try_state::<SidecarManager>() {
-                    let _ = manager.stop();
-                }
-                // Also run the orphan cleanup
-                kill_orphaned_gateway_processes();
+                shutdown::shutdown(app_handle, "Exit requested");
             }
             tauri::RunEvent::Exit => {
-                println!("[app] Exiting, final cleanup...");
-                // Final cleanup attempt
-                kill_orphaned_gateway_processes();
+                shutdown::shutdown(app_handle, "Exiting");
             }
             _ => {}
         }
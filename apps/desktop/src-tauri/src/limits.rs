@@ -0,0 +1,135 @@
+//! Concurrency Limits
+//!
+//! `maxConcurrentSessions` and `maxConcurrentRequests` cap how much of the
+//! API quota the gateway can use at once. A gateway release that supports
+//! the matching flag enforces the limit itself. Otherwise the session limit
+//! falls back to the app, which refuses to create sessions past it (see
+//! `ensure_session_allowed`); that only covers sessions created through the
+//! app, so `ConcurrencyLimits` in `GatewayStatus` says where each limit is
+//! enforced. The request limit has no app-side fallback.
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::blocked_tools::GatewayVersion;
+use crate::capabilities::GatewayCapabilities;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::sidecar::SidecarManager;
+
+pub const SESSIONS_FLAG: &str = "--max-concurrent-sessions";
+pub const REQUESTS_FLAG: &str = "--max-concurrent-requests";
+
+/// First gateway releases accepting each flag
+pub const SESSIONS_SINCE: GatewayVersion = GatewayVersion(2026, 2, 1);
+pub const REQUESTS_SINCE: GatewayVersion = GatewayVersion(2026, 1, 20);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LimitEnforcement {
+    /// The gateway refuses work past the limit
+    Gateway,
+    /// Only sessions created through the app are counted
+    App,
+    NotEnforced,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyLimit {
+    pub max: u32,
+    pub enforced_by: LimitEnforcement,
+}
+
+/// The configured limits and where they are enforced
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcurrencyLimits {
+    pub sessions: Option<ConcurrencyLimit>,
+    pub requests: Option<ConcurrencyLimit>,
+}
+
+impl ConcurrencyLimits {
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_none() && self.requests.is_none()
+    }
+}
+
+/// Gateway arguments for the limits in `config`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LimitsPlan {
+    pub args: Vec<String>,
+    pub limits: ConcurrencyLimits,
+}
+
+/// Pass each configured limit to a gateway at `version` when it supports
+/// the flag (and its help doesn't say otherwise), and decide who enforces
+/// it otherwise. A limit of 0 is an error.
+pub fn plan(
+    config: &Config,
+    version: Option<GatewayVersion>,
+    capabilities: &GatewayCapabilities,
+) -> Result<LimitsPlan, String> {
+    let supports = |flag: &str, since: GatewayVersion| {
+        version.is_some_and(|version| version >= since) && capabilities.allows(flag)
+    };
+    let mut plan = LimitsPlan::default();
+
+    if let Some(max) = config.max_concurrent_sessions {
+        if max == 0 {
+            return Err("maxConcurrentSessions must be at least 1.".to_string());
+        }
+        let enforced_by = if supports(SESSIONS_FLAG, SESSIONS_SINCE) {
+            plan.args
+                .extend([SESSIONS_FLAG.to_string(), max.to_string()]);
+            LimitEnforcement::Gateway
+        } else {
+            LimitEnforcement::App
+        };
+        plan.limits.sessions = Some(ConcurrencyLimit { max, enforced_by });
+    }
+
+    if let Some(max) = config.max_concurrent_requests {
+        if max == 0 {
+            return Err("maxConcurrentRequests must be at least 1.".to_string());
+        }
+        let enforced_by = if supports(REQUESTS_FLAG, REQUESTS_SINCE) {
+            plan.args
+                .extend([REQUESTS_FLAG.to_string(), max.to_string()]);
+            LimitEnforcement::Gateway
+        } else {
+            LimitEnforcement::NotEnforced
+        };
+        plan.limits.requests = Some(ConcurrencyLimit { max, enforced_by });
+    }
+
+    Ok(plan)
+}
+
+/// Whether the app may create another session with `live_sessions` open.
+/// Only refuses when the app is the one enforcing the limit; a gateway
+/// enforcing it answers for itself.
+pub fn ensure_session_allowed(
+    limits: &ConcurrencyLimits,
+    live_sessions: u32,
+) -> Result<(), AppError> {
+    match limits.sessions {
+        Some(ConcurrencyLimit {
+            max,
+            enforced_by: LimitEnforcement::App,
+        }) if live_sessions >= max => Err(AppError::SessionLimitReached {
+            limit: max.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Called before creating a session, with the live session count from
+/// the protocol client
+#[tauri::command]
+pub fn check_session_limit(
+    live_sessions: u32,
+    manager: State<'_, SidecarManager>,
+) -> Result<(), AppError> {
+    ensure_session_allowed(&manager.status().limits, live_sessions)
+}
@@ -0,0 +1,291 @@
+//! Gateway Locale and Timezone
+//!
+//! A gateway launched from the Dock or a desktop launcher, and any gateway
+//! in clean-environment mode, tends to get no `LANG` or `TZ`, so node
+//! falls back to en-US and UTC and the agent gets date formats and "today"
+//! wrong. Every spawn gets `LANG`, `LC_ALL` and `TZ` from `locale` and
+//! `timezone` in config, or from what this machine uses when those are
+//! unset.
+//!
+//! Locales are accepted as BCP 47 tags (`de-DE`) or POSIX names
+//! (`de_DE.UTF-8`) and passed on in POSIX form; scripts (`zh-Hant-TW`) are
+//! dropped since POSIX locales have no place for them. Timezones must be
+//! IANA names like `Europe/Berlin`. Only the syntax is checked, not
+//! whether the system's zone database knows the name.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path};
+use thiserror::Error;
+
+use crate::config::Config;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LocaleError {
+    #[error("{0} is not a locale like en-GB or de_DE.UTF-8")]
+    InvalidLocale(String),
+    #[error("{0} is not an IANA timezone like Europe/Berlin")]
+    InvalidTimezone(String),
+}
+
+/// The POSIX form of `locale` for `LANG`, UTF-8 unless it names a charset
+pub fn validate_locale(locale: &str) -> Result<String, LocaleError> {
+    let invalid = || LocaleError::InvalidLocale(locale.to_string());
+    if matches!(locale, "C" | "POSIX" | "C.UTF-8" | "C.utf8") {
+        return Ok(locale.to_string());
+    }
+
+    let (rest, modifier) = match locale.split_once('@') {
+        Some((rest, modifier)) => (rest, Some(modifier)),
+        None => (locale, None),
+    };
+    let (tag, charset) = match rest.split_once('.') {
+        Some((tag, charset)) => (tag, Some(charset)),
+        None => (rest, None),
+    };
+    let mut parts = tag.split(['-', '_']);
+    let language = parts.next().unwrap_or_default();
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid());
+    }
+    let mut region = None;
+    for part in parts {
+        let is_script = part.len() == 4 && part.chars().all(|c| c.is_ascii_alphabetic());
+        let is_region = (part.len() == 2 && part.chars().all(|c| c.is_ascii_alphabetic()))
+            || (part.len() == 3 && part.chars().all(|c| c.is_ascii_digit()));
+        match (is_script, is_region) {
+            (true, _) if region.is_none() => {}
+            (_, true) if region.is_none() => region = Some(part),
+            _ => return Err(invalid()),
+        }
+    }
+    let word = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if charset.is_some_and(|s| !word(s)) || modifier.is_some_and(|s| !word(s)) {
+        return Err(invalid());
+    }
+
+    let mut posix = language.to_ascii_lowercase();
+    if let Some(region) = region {
+        posix.push('_');
+        posix.push_str(&region.to_ascii_uppercase());
+    }
+    posix.push('.');
+    posix.push_str(charset.unwrap_or("UTF-8"));
+    if let Some(modifier) = modifier {
+        posix.push('@');
+        posix.push_str(modifier);
+    }
+    Ok(posix)
+}
+
+/// Check `timezone` looks like an IANA zone name: `UTC`, `Europe/Berlin`,
+/// `America/Argentina/Buenos_Aires`, `Etc/GMT+5`
+pub fn validate_timezone(timezone: &str) -> Result<(), LocaleError> {
+    let part = |part: &str| {
+        part.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+    };
+    if timezone.len() <= 64 && timezone.split('/').all(part) {
+        Ok(())
+    } else {
+        Err(LocaleError::InvalidTimezone(timezone.to_string()))
+    }
+}
+
+/// The locale in `LC_ALL` or `LANG` as read by `var`, when it's a real one
+pub fn locale_from_vars(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    ["LC_ALL", "LANG"]
+        .iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.is_empty())
+        // A bare C locale is what GUI sessions get, not a choice
+        .filter(|value| !matches!(value.as_str(), "C" | "POSIX"))
+        .and_then(|value| validate_locale(&value).ok())
+}
+
+/// `TZ` as read by `var`, when it names a zone rather than a rule
+pub fn timezone_from_vars(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let tz = var("TZ")?;
+    let tz = tz.trim_start_matches(':');
+    validate_timezone(tz).ok().map(|()| tz.to_string())
+}
+
+/// The zone `/etc/localtime` under `root` links to, or the name in
+/// `/etc/timezone`
+pub fn timezone_from_root(root: &Path) -> Option<String> {
+    let from_link = fs::read_link(root.join("etc/localtime"))
+        .ok()
+        .and_then(|target| zone_from_path(&target));
+    from_link.or_else(|| {
+        let contents = fs::read_to_string(root.join("etc/timezone")).ok()?;
+        let name = contents.lines().next()?.trim();
+        validate_timezone(name).ok().map(|()| name.to_string())
+    })
+}
+
+/// `Europe/Berlin` from `/usr/share/zoneinfo/Europe/Berlin` and the like
+fn zone_from_path(path: &Path) -> Option<String> {
+    let parts: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    let start = parts
+        .iter()
+        .rposition(|part| part.starts_with("zoneinfo"))?
+        + 1;
+    // Variants of the database kept next to the default one
+    let start = match parts.get(start).map(String::as_str) {
+        Some("posix" | "right") => start + 1,
+        _ => start,
+    };
+    let name = parts.get(start..)?.join("/");
+    validate_timezone(&name).ok().map(|()| name)
+}
+
+/// `AppleLocale` as printed by `defaults read -g AppleLocale`, e.g.
+/// `en_GB@rg=dezzzz` or `de_DE`
+pub fn parse_apple_locale(output: &str) -> Option<String> {
+    let name = output.trim();
+    // Region overrides and calendars are not POSIX modifiers
+    let name = name.split('@').next()?;
+    validate_locale(name).ok()
+}
+
+/// This machine's locale in POSIX form
+pub fn detect_locale() -> Option<String> {
+    locale_from_vars(|name| std::env::var(name).ok()).or_else(platform_locale)
+}
+
+/// This machine's IANA timezone. None on Windows, where node reads the
+/// system's timezone itself.
+pub fn detect_timezone() -> Option<String> {
+    timezone_from_vars(|name| std::env::var(name).ok()).or_else(|| {
+        if cfg!(unix) {
+            timezone_from_root(Path::new("/"))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn platform_locale() -> Option<String> {
+    let output = std::process::Command::new("defaults")
+        .args(["read", "-g", "AppleLocale"])
+        .output()
+        .ok()?;
+    parse_apple_locale(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(windows)]
+fn platform_locale() -> Option<String> {
+    use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+
+    let mut buf = [0u16; 85];
+    // SAFETY: the buffer is LOCALE_NAME_MAX_LENGTH wide characters long,
+    // as passed
+    let len = unsafe { GetUserDefaultLocaleName(buf.as_mut_ptr(), buf.len() as i32) };
+    if len <= 1 {
+        return None;
+    }
+    // Without the terminating NUL
+    validate_locale(&String::from_utf16_lossy(&buf[..len as usize - 1])).ok()
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn platform_locale() -> Option<String> {
+    None
+}
+
+/// Whether a value came from config or from this machine
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ValueSource {
+    Configured,
+    Detected,
+}
+
+/// The locale and timezone a gateway gets
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayLocale {
+    /// POSIX form, for `LANG` and `LC_ALL`
+    pub locale: Option<String>,
+    pub locale_source: Option<ValueSource>,
+    pub timezone: Option<String>,
+    pub timezone_source: Option<ValueSource>,
+}
+
+impl GatewayLocale {
+    /// Variables to inject; nothing for what couldn't be determined
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        if let Some(ref locale) = self.locale {
+            env.push(("LANG".to_string(), locale.clone()));
+            env.push(("LC_ALL".to_string(), locale.clone()));
+        }
+        if let Some(ref timezone) = self.timezone {
+            env.push(("TZ".to_string(), timezone.clone()));
+        }
+        env
+    }
+
+    pub fn describe(&self) -> String {
+        let value = |value: &Option<String>, source: Option<ValueSource>| match (value, source) {
+            (Some(value), Some(ValueSource::Configured)) => format!("{} (configured)", value),
+            (Some(value), _) => format!("{} (detected)", value),
+            (None, _) => "not detected".to_string(),
+        };
+        format!(
+            "Locale {}, timezone {}",
+            value(&self.locale, self.locale_source),
+            value(&self.timezone, self.timezone_source)
+        )
+    }
+}
+
+/// The configured locale and timezone, each falling back to `detected`
+/// when unset
+pub fn resolve(
+    config: &Config,
+    detected: (Option<String>, Option<String>),
+) -> Result<GatewayLocale, LocaleError> {
+    let (locale, locale_source) = match config.locale {
+        Some(ref locale) => (
+            Some(validate_locale(locale)?),
+            Some(ValueSource::Configured),
+        ),
+        None => {
+            let source = detected.0.as_ref().map(|_| ValueSource::Detected);
+            (detected.0, source)
+        }
+    };
+    let (timezone, timezone_source) = match config.timezone {
+        Some(ref timezone) => {
+            validate_timezone(timezone)?;
+            (Some(timezone.clone()), Some(ValueSource::Configured))
+        }
+        None => {
+            let source = detected.1.as_ref().map(|_| ValueSource::Detected);
+            (detected.1, source)
+        }
+    };
+    Ok(GatewayLocale {
+        locale,
+        locale_source,
+        timezone,
+        timezone_source,
+    })
+}
+
+/// `resolve` with what this machine uses
+pub fn plan(config: &Config) -> Result<GatewayLocale, LocaleError> {
+    let locale = config.locale.is_none().then(detect_locale).flatten();
+    let timezone = config.timezone.is_none().then(detect_timezone).flatten();
+    resolve(config, (locale, timezone))
+}
@@ -0,0 +1,111 @@
+//! Gateway Log Level
+//!
+//! `gatewayLogLevel` turns up the gateway's own logging, for debugging
+//! protocol issues without running openclaw by hand. The level is passed
+//! as `OPENCLAW_LOG_LEVEL`, and `debug` and `trace` also pass `--verbose`
+//! when the installed gateway accepts it. Unset, the gateway logs as it
+//! does by default.
+//!
+//! Only `LEVELS` are accepted, so a typo is refused as a setting rather
+//! than passed on to make the spawn fail.
+
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use thiserror::Error;
+
+use crate::capabilities::GatewayCapabilities;
+use crate::config::{self, save_config, Config};
+use crate::error::AppError;
+use crate::metrics::AppMetrics;
+use crate::sidecar::{GatewayInfo, SidecarManager};
+
+/// The levels, quietest first
+pub const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Variable the level is passed in
+pub const LOG_LEVEL_ENV: &str = "OPENCLAW_LOG_LEVEL";
+
+/// Passed for `debug` and `trace`
+pub const VERBOSE_FLAG: &str = "--verbose";
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{0:?} is not one of error, warn, info, debug or trace")]
+pub struct LogLevelError(pub String);
+
+/// Check `level` is one of `LEVELS`
+pub fn validate(level: &str) -> Result<(), LogLevelError> {
+    if LEVELS.contains(&level) {
+        Ok(())
+    } else {
+        Err(LogLevelError(level.to_string()))
+    }
+}
+
+/// How a gateway is started at one level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLevelPlan {
+    pub level: String,
+    pub args: Vec<String>,
+}
+
+impl LogLevelPlan {
+    pub fn env(&self) -> Vec<(String, String)> {
+        vec![(LOG_LEVEL_ENV.to_string(), self.level.clone())]
+    }
+}
+
+/// The level in `config` for a gateway with `capabilities`, None when unset
+pub fn plan(
+    config: &Config,
+    capabilities: &GatewayCapabilities,
+) -> Result<Option<LogLevelPlan>, LogLevelError> {
+    let Some(ref level) = config.gateway_log_level else {
+        return Ok(None);
+    };
+    validate(level)?;
+    let verbose = matches!(level.as_str(), "debug" | "trace") && capabilities.allows(VERBOSE_FLAG);
+    Ok(Some(LogLevelPlan {
+        level: level.clone(),
+        args: verbose
+            .then(|| VERBOSE_FLAG.to_string())
+            .into_iter()
+            .collect(),
+    }))
+}
+
+/// Set `gatewayLogLevel`, None for the gateway's default, and restart a
+/// running gateway onto it with the same token, announced as
+/// `gateway://started`. Resolves to the restarted gateway's info, None
+/// when none was running. Rejects with `invalid_setting` for
+/// `gatewayLogLevel` for anything but `LEVELS`.
+#[tauri::command]
+pub async fn set_gateway_log_level(
+    app: AppHandle,
+    level: Option<String>,
+) -> Result<Option<GatewayInfo>, AppError> {
+    let level = level
+        .map(|level| level.trim().to_ascii_lowercase())
+        .filter(|level| !level.is_empty());
+    if let Some(ref level) = level {
+        validate(level)?;
+    }
+    let mut config = Config::load()?;
+    let changed = config.gateway_log_level != level;
+    config.gateway_log_level = level;
+    save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+    config::emit_changed(&app);
+
+    tokio::task::spawn_blocking(move || {
+        let manager = app.state::<SidecarManager>();
+        if manager.gateway_pid().is_none() {
+            return Ok(None);
+        }
+        if !changed {
+            return Ok(manager.status().info);
+        }
+        let started = manager.restart_keeping_token("log level changed")?;
+        let _ = app.emit("gateway://started", &started);
+        Ok(Some(started.info))
+    })
+    .await?
+}
@@ -0,0 +1,208 @@
+//! Gateway Log Search
+//!
+//! Backs `search_gateway_logs`: the pattern is compiled here rather than in
+//! the webview, so only the matches cross the IPC boundary. Sources are
+//! searched newest first - the in-memory ring buffer, then `gateway.log`
+//! and its rotations one file at a time - and the search stops at the
+//! result limit.
+//!
+//! The regex engine runs in linear time, so catastrophic backtracking is
+//! not a concern, but a pattern can still compile into a huge automaton.
+//! Patterns are therefore limited in length, nesting depth and compiled
+//! size, and rejected when they exceed any of them.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+use crate::gateway_logs::{GatewayLogs, LogLine};
+
+/// Longest accepted pattern, in bytes
+pub const MAX_PATTERN_LEN: usize = 1000;
+
+/// Limit on the compiled program and the lazy DFA cache
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+const REGEX_NEST_LIMIT: u32 = 32;
+
+/// Upper bounds for the corresponding options
+pub const MAX_CONTEXT: usize = 20;
+pub const MAX_LIMIT: usize = 1000;
+
+/// Source name of the ring buffer in results
+pub const MEMORY_SOURCE: &str = "memory";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+    pub case_insensitive: bool,
+    /// Match the pattern as a plain substring instead of a regex
+    pub literal: bool,
+    /// Lines of context before and after each match
+    pub context: usize,
+    /// Maximum number of matches returned
+    pub limit: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            case_insensitive: false,
+            literal: false,
+            context: 2,
+            limit: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogMatch {
+    /// `memory` or the log file name
+    pub source: String,
+    /// 1-based line number within the source
+    pub line_number: usize,
+    pub timestamp: Option<i64>,
+    pub stream: String,
+    pub text: String,
+    /// Context lines, oldest first
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    /// Newest first
+    pub matches: Vec<LogMatch>,
+    /// More matches exist beyond `limit`
+    pub truncated: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("The pattern is empty")]
+    Empty,
+    #[error("The pattern is longer than {MAX_PATTERN_LEN} characters")]
+    TooLong,
+    #[error("The pattern is too complex")]
+    TooComplex,
+    #[error("Invalid pattern: {0}")]
+    Invalid(String),
+}
+
+/// Compile `pattern` with the size limits applied
+pub fn compile(pattern: &str, options: &SearchOptions) -> Result<Regex, SearchError> {
+    if pattern.is_empty() {
+        return Err(SearchError::Empty);
+    }
+    if pattern.len() > MAX_PATTERN_LEN {
+        return Err(SearchError::TooLong);
+    }
+    let pattern = if options.literal {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .nest_limit(REGEX_NEST_LIMIT)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => SearchError::TooComplex,
+            // regex reports the nest limit only as a syntax error
+            e if e.to_string().contains("maximum number of nested") => SearchError::TooComplex,
+            e => SearchError::Invalid(e.to_string()),
+        })
+}
+
+/// Search the captured gateway output for `pattern`
+pub fn search(
+    logs: &GatewayLogs,
+    pattern: &str,
+    options: &SearchOptions,
+) -> Result<SearchResult, SearchError> {
+    let regex = compile(pattern, options)?;
+    let mut search = Search {
+        regex,
+        context: options.context.min(MAX_CONTEXT),
+        limit: options.limit.clamp(1, MAX_LIMIT),
+        result: SearchResult::default(),
+    };
+
+    // Held for the whole search so lines can't move between the ring
+    // buffer and the files while we look at both
+    let mut state = logs.lock();
+    let memory: Vec<LogLine> = state.lines().cloned().collect();
+    if search.scan(MEMORY_SOURCE, &memory, 0) {
+        return Ok(search.result);
+    }
+
+    // The newest file lines are the ones just searched in memory
+    let mut skip = state.lines_on_disk();
+    for path in state.files() {
+        let lines = read_lines(&path);
+        let skipped = skip.min(lines.len());
+        skip -= skipped;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if search.scan(&name, &lines, skipped) {
+            break;
+        }
+    }
+    Ok(search.result)
+}
+
+struct Search {
+    regex: Regex,
+    context: usize,
+    limit: usize,
+    result: SearchResult,
+}
+
+impl Search {
+    /// Collect matches in `lines` (oldest first) from the newest line
+    /// backwards, ignoring the newest `skip` lines except as context.
+    /// Returns true once the limit was exceeded.
+    fn scan(&mut self, source: &str, lines: &[LogLine], skip: usize) -> bool {
+        let end = lines.len() - skip.min(lines.len());
+        for index in (0..end).rev() {
+            let line = &lines[index];
+            if !self.regex.is_match(&line.text) {
+                continue;
+            }
+            if self.result.matches.len() == self.limit {
+                self.result.truncated = true;
+                return true;
+            }
+            let text = |range: &[LogLine]| range.iter().map(|line| line.text.clone()).collect();
+            self.result.matches.push(LogMatch {
+                source: source.to_string(),
+                line_number: index + 1,
+                timestamp: line.timestamp,
+                stream: line.stream.clone(),
+                text: line.text.clone(),
+                before: text(&lines[index.saturating_sub(self.context)..index]),
+                after: text(&lines[index + 1..(index + 1 + self.context).min(lines.len())]),
+            });
+        }
+        false
+    }
+}
+
+/// Lines of a log file, oldest first. Unreadable files have no lines.
+fn read_lines(path: &Path) -> Vec<LogLine> {
+    match fs::read(path) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).lines().map(LogLine::parse).collect(),
+        Err(e) => {
+            eprintln!("[gateway-logs] Failed to read {:?}: {}", path, e);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,505 @@
+//! Live Gateway Log Streaming
+//!
+//! Windows that want live gateway output call `subscribe_logs` with a
+//! `LogFilter` and listen on `logs://<id>`. Every captured line is
+//! classified into a `LogEntry` and emitted only to the subscriptions whose
+//! filter it passes, and only to the window that subscribed. Subscriptions
+//! end with `unsubscribe_logs` or when their window is destroyed.
+//!
+//! Every line is also broadcast unfiltered to all windows as
+//! `gateway://log`, for views that simply show the gateway's output.
+//!
+//! Fan-out happens in `GatewayLogs::push`. With no subscribers that costs
+//! one atomic load per line plus the broadcast; entries are only built when
+//! someone subscribed.
+//!
+//! A gateway flooding its output would flood the windows with events, so
+//! only `LINES_PER_INTERVAL` lines go out on their own per
+//! `BATCH_INTERVAL`. The lines past that are held and sent together once
+//! the interval is over: a `GatewayLogBatch` on `gateway://log-batch`, and
+//! an array of entries on `logs://<id>`. At most `MAX_BATCH_LINES` are
+//! held, the oldest going first; the batch counts them as `dropped`, and
+//! they are still in the ring buffer and the files.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, Window};
+
+use crate::error::AppError;
+use crate::gateway_logs::{self, LogLine};
+use crate::log_search::{self, SearchError, SearchOptions};
+use crate::sidecar::SidecarManager;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// A captured line as sent to subscribers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: Option<i64>,
+    pub stream: String,
+    pub level: LogLevel,
+    /// Leading `[name]` tag of the line, e.g. `gateway`
+    pub component: Option<String>,
+    pub text: String,
+}
+
+impl LogEntry {
+    /// Classify a captured line. The gateway has no structured output, so
+    /// the level comes from the first word after the component tag
+    /// (`ERROR`, `warn:` ...), defaulting to `warn` for stderr and `info`
+    /// for stdout.
+    pub fn from_line(line: &LogLine) -> Self {
+        let text = line.text.trim_start();
+        let (component, rest) = match text.strip_prefix('[').and_then(|t| t.split_once(']')) {
+            Some((component, rest)) if !component.is_empty() => (Some(component.to_string()), rest),
+            _ => (None, text),
+        };
+        let word = rest
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .find(|word| !word.is_empty())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let level = match word.as_str() {
+            "error" | "err" | "fatal" => LogLevel::Error,
+            "warn" | "warning" => LogLevel::Warn,
+            "info" => LogLevel::Info,
+            "debug" | "trace" | "verbose" => LogLevel::Debug,
+            _ if line.stream == "stderr" => LogLevel::Warn,
+            _ => LogLevel::Info,
+        };
+        Self {
+            timestamp: line.timestamp,
+            stream: line.stream.clone(),
+            level,
+            component,
+            text: line.text.clone(),
+        }
+    }
+}
+
+/// What a subscriber wants to receive. Unset fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogFilter {
+    pub min_level: Option<LogLevel>,
+    /// `stdout` or `stderr`
+    pub stream: Option<String>,
+    /// Regex the component has to match; lines without one never match
+    pub component: Option<String>,
+}
+
+/// A `LogFilter` with its regex compiled
+#[derive(Debug, Clone)]
+pub struct CompiledFilter {
+    min_level: Option<LogLevel>,
+    stream: Option<String>,
+    component: Option<Regex>,
+}
+
+impl CompiledFilter {
+    /// Compile `filter`, with the same limits on the regex as log search
+    pub fn new(filter: &LogFilter) -> Result<Self, SearchError> {
+        let component = match filter.component {
+            Some(ref pattern) => Some(log_search::compile(pattern, &SearchOptions::default())?),
+            None => None,
+        };
+        Ok(Self {
+            min_level: filter.min_level,
+            stream: filter.stream.clone(),
+            component,
+        })
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.min_level.is_none_or(|min| entry.level >= min)
+            && self.stream.as_ref().is_none_or(|stream| *stream == entry.stream)
+            && self.component.as_ref().is_none_or(|regex| {
+                entry.component.as_deref().is_some_and(|component| regex.is_match(component))
+            })
+    }
+}
+
+/// Event every captured line is broadcast on
+pub const LOG_EVENT: &str = "gateway://log";
+
+/// Payload of `gateway://log`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayLogEvent {
+    /// `stdout` or `stderr`
+    pub stream: String,
+    pub line: String,
+    /// Unix timestamp (ms)
+    pub timestamp: u64,
+}
+
+impl GatewayLogEvent {
+    pub fn from_line(line: &LogLine) -> Self {
+        Self {
+            stream: line.stream.clone(),
+            line: line.text.clone(),
+            timestamp: line.timestamp.unwrap_or(0).max(0) as u64,
+        }
+    }
+}
+
+/// Event the lines held back during a flood are broadcast on together
+pub const LOG_BATCH_EVENT: &str = "gateway://log-batch";
+
+/// Payload of `gateway://log-batch`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayLogBatch {
+    pub lines: Vec<GatewayLogEvent>,
+    /// Lines left out for not fitting into the batch
+    pub dropped: u64,
+}
+
+/// Lines sent on their own per `BATCH_INTERVAL`
+pub const LINES_PER_INTERVAL: usize = 50;
+
+pub const BATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Lines held for one batch at most
+pub const MAX_BATCH_LINES: usize = 500;
+
+/// Lines held back by `LogRateLimiter`, to send together
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeldLines {
+    pub lines: Vec<LogLine>,
+    /// Held lines dropped for newer ones
+    pub dropped: u64,
+}
+
+/// Decides which lines go out on their own and holds the rest of a flood
+/// for one batch per interval
+#[derive(Debug, Default)]
+pub struct LogRateLimiter {
+    interval_start: Option<Instant>,
+    sent: usize,
+    held: VecDeque<LogLine>,
+    dropped: u64,
+}
+
+impl LogRateLimiter {
+    /// Whether `line`, published at `now`, can go out on its own. If not
+    /// it is held, after the lines held before it.
+    pub fn offer(&mut self, line: &LogLine, now: Instant) -> bool {
+        if self
+            .interval_start
+            .is_none_or(|start| now.duration_since(start) >= BATCH_INTERVAL)
+        {
+            self.interval_start = Some(now);
+            self.sent = 0;
+        }
+        if self.held.is_empty() && self.sent < LINES_PER_INTERVAL {
+            self.sent += 1;
+            return true;
+        }
+        if self.held.len() == MAX_BATCH_LINES {
+            self.held.pop_front();
+            self.dropped += 1;
+        }
+        self.held.push_back(line.clone());
+        false
+    }
+
+    /// The held lines, once the interval they were held in is over. The
+    /// batch counts as one line of the next interval.
+    pub fn take_held(&mut self, now: Instant) -> Option<HeldLines> {
+        let current = self
+            .interval_start
+            .is_some_and(|start| now.duration_since(start) < BATCH_INTERVAL);
+        if self.held.is_empty() || current {
+            return None;
+        }
+        self.interval_start = Some(now);
+        self.sent = 1;
+        Some(HeldLines {
+            lines: self.held.drain(..).collect(),
+            dropped: std::mem::take(&mut self.dropped),
+        })
+    }
+}
+
+/// Delivers `entry` on `event` to the window labelled `window`
+pub type LogEmitter = Box<dyn Fn(&str, &str, &LogEntry) + Send + Sync>;
+
+/// Delivers held `entries` on `event` to the window labelled `window`
+pub type LogBatchEmitter = Box<dyn Fn(&str, &str, &[LogEntry]) + Send + Sync>;
+
+/// Delivers a `gateway://log` event to every window
+pub type LogBroadcaster = Box<dyn Fn(&GatewayLogEvent) + Send + Sync>;
+
+/// Delivers a `gateway://log-batch` event to every window
+pub type LogBatchBroadcaster = Box<dyn Fn(&GatewayLogBatch) + Send + Sync>;
+
+struct Subscription {
+    window: String,
+    filter: CompiledFilter,
+}
+
+/// Active subscriptions, shared with the capture pipeline
+pub struct LogSubscribers {
+    subscriptions: RwLock<HashMap<u64, Subscription>>,
+    /// Mirrors `subscriptions.len()` so publishing can skip the lock
+    count: AtomicUsize,
+    next_id: AtomicU64,
+    emitter: RwLock<Option<LogEmitter>>,
+    batch_emitter: RwLock<Option<LogBatchEmitter>>,
+    broadcaster: RwLock<Option<LogBroadcaster>>,
+    batch_broadcaster: RwLock<Option<LogBatchBroadcaster>>,
+    limiter: Mutex<LogRateLimiter>,
+}
+
+impl Default for LogSubscribers {
+    fn default() -> Self {
+        Self {
+            subscriptions: RwLock::new(HashMap::new()),
+            count: AtomicUsize::new(0),
+            next_id: AtomicU64::new(1),
+            emitter: RwLock::new(None),
+            batch_emitter: RwLock::new(None),
+            broadcaster: RwLock::new(None),
+            batch_broadcaster: RwLock::new(None),
+            limiter: Mutex::default(),
+        }
+    }
+}
+
+/// Event a subscription's entries are emitted on
+pub fn event_name(id: u64) -> String {
+    format!("logs://{}", id)
+}
+
+impl LogSubscribers {
+    /// Use `emitter` to deliver entries. Until one is set nothing is sent.
+    pub fn set_emitter(&self, emitter: LogEmitter) {
+        if let Ok(mut slot) = self.emitter.write() {
+            *slot = Some(emitter);
+        }
+    }
+
+    /// Use `emitter` to deliver the entries held during a flood
+    pub fn set_batch_emitter(&self, emitter: LogBatchEmitter) {
+        if let Ok(mut slot) = self.batch_emitter.write() {
+            *slot = Some(emitter);
+        }
+    }
+
+    /// Use `broadcaster` to send every line as `gateway://log`
+    pub fn set_broadcaster(&self, broadcaster: LogBroadcaster) {
+        if let Ok(mut slot) = self.broadcaster.write() {
+            *slot = Some(broadcaster);
+        }
+    }
+
+    /// Use `broadcaster` to send the lines held during a flood as
+    /// `gateway://log-batch`
+    pub fn set_batch_broadcaster(&self, broadcaster: LogBatchBroadcaster) {
+        if let Ok(mut slot) = self.batch_broadcaster.write() {
+            *slot = Some(broadcaster);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count.load(Ordering::Relaxed) == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Subscribe `window` to entries passing `filter`, returning the id
+    pub fn subscribe(&self, window: &str, filter: CompiledFilter) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut subscriptions) = self.subscriptions.write() {
+            subscriptions.insert(
+                id,
+                Subscription {
+                    window: window.to_string(),
+                    filter,
+                },
+            );
+            self.count.store(subscriptions.len(), Ordering::Relaxed);
+        }
+        id
+    }
+
+    /// End a subscription. Returns false if it didn't exist.
+    pub fn unsubscribe(&self, id: u64) -> bool {
+        let Ok(mut subscriptions) = self.subscriptions.write() else {
+            return false;
+        };
+        let removed = subscriptions.remove(&id).is_some();
+        self.count.store(subscriptions.len(), Ordering::Relaxed);
+        removed
+    }
+
+    /// End every subscription of `window`, returning how many there were
+    pub fn remove_window(&self, window: &str) -> usize {
+        let Ok(mut subscriptions) = self.subscriptions.write() else {
+            return 0;
+        };
+        let before = subscriptions.len();
+        subscriptions.retain(|_, subscription| subscription.window != window);
+        self.count.store(subscriptions.len(), Ordering::Relaxed);
+        before - subscriptions.len()
+    }
+
+    /// Broadcast `line` and send it to every subscription whose filter it
+    /// passes, or hold it for a batch during a flood
+    pub fn publish(&self, line: &LogLine) {
+        let now = Instant::now();
+        let (held, send) = {
+            let mut limiter = self.limiter.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            (limiter.take_held(now), limiter.offer(line, now))
+        };
+        if let Some(held) = held {
+            self.deliver_batch(&held);
+        }
+        if send {
+            self.deliver(line);
+        }
+    }
+
+    /// Send the lines held for a batch, once their interval is over. Called
+    /// on a timer, so the end of a flood isn't held until the next line.
+    pub fn flush(&self) {
+        let held = self
+            .limiter
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take_held(Instant::now());
+        if let Some(held) = held {
+            self.deliver_batch(&held);
+        }
+    }
+
+    fn deliver(&self, line: &LogLine) {
+        if let Ok(broadcaster) = self.broadcaster.read() {
+            if let Some(ref broadcast) = *broadcaster {
+                broadcast(&GatewayLogEvent::from_line(line));
+            }
+        }
+        if self.is_empty() {
+            return;
+        }
+        let entry = LogEntry::from_line(line);
+        let Ok(emitter) = self.emitter.read() else {
+            return;
+        };
+        let Some(ref emit) = *emitter else {
+            return;
+        };
+        let Ok(subscriptions) = self.subscriptions.read() else {
+            return;
+        };
+        for (id, subscription) in subscriptions.iter() {
+            if subscription.filter.matches(&entry) {
+                emit(&subscription.window, &event_name(*id), &entry);
+            }
+        }
+    }
+
+    fn deliver_batch(&self, held: &HeldLines) {
+        if let Ok(broadcaster) = self.batch_broadcaster.read() {
+            if let Some(ref broadcast) = *broadcaster {
+                broadcast(&GatewayLogBatch {
+                    lines: held.lines.iter().map(GatewayLogEvent::from_line).collect(),
+                    dropped: held.dropped,
+                });
+            }
+        }
+        if self.is_empty() {
+            return;
+        }
+        let entries: Vec<LogEntry> = held.lines.iter().map(LogEntry::from_line).collect();
+        let Ok(emitter) = self.batch_emitter.read() else {
+            return;
+        };
+        let Some(ref emit) = *emitter else {
+            return;
+        };
+        let Ok(subscriptions) = self.subscriptions.read() else {
+            return;
+        };
+        for (id, subscription) in subscriptions.iter() {
+            let matching: Vec<LogEntry> = entries
+                .iter()
+                .filter(|entry| subscription.filter.matches(entry))
+                .cloned()
+                .collect();
+            if !matching.is_empty() {
+                emit(&subscription.window, &event_name(*id), &matching);
+            }
+        }
+    }
+}
+
+/// Deliver entries to windows through Tauri events, held lines on a
+/// timer, and file logging errors too
+pub fn install_emitter(app: &AppHandle) {
+    let manager = app.state::<SidecarManager>();
+    let subscribers = manager.logs().subscribers();
+    let handle = app.clone();
+    subscribers.set_emitter(Box::new(move |window, event, entry| {
+        let _ = handle.emit_to(window, event, entry);
+    }));
+    let handle = app.clone();
+    subscribers.set_batch_emitter(Box::new(move |window, event, entries| {
+        let _ = handle.emit_to(window, event, entries);
+    }));
+    let handle = app.clone();
+    subscribers.set_broadcaster(Box::new(move |event| {
+        let _ = handle.emit(LOG_EVENT, event);
+    }));
+    let handle = app.clone();
+    subscribers.set_batch_broadcaster(Box::new(move |batch| {
+        let _ = handle.emit(LOG_BATCH_EVENT, batch);
+    }));
+    let handle = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(BATCH_INTERVAL);
+        handle.state::<SidecarManager>().logs().subscribers().flush();
+    });
+    let handle = app.clone();
+    manager.logs().set_file_error_listener(Box::new(move |disabled| {
+        let _ = handle.emit(gateway_logs::FILE_LOGGING_DISABLED_EVENT, disabled);
+    }));
+}
+
+// Tauri commands
+
+/// Stream gateway output matching `filter` to the calling window on
+/// `logs://<id>`
+#[tauri::command]
+pub fn subscribe_logs(
+    window: Window,
+    app: AppHandle,
+    filter: Option<LogFilter>,
+) -> Result<u64, AppError> {
+    let filter = CompiledFilter::new(&filter.unwrap_or_default())?;
+    let manager = app.state::<SidecarManager>();
+    let id = manager.logs().subscribers().subscribe(window.label(), filter);
+    println!("[logs] Window {} subscribed to gateway logs ({})", window.label(), id);
+    Ok(id)
+}
+
+/// End a subscription. Unknown ids are ignored and return false.
+#[tauri::command]
+pub fn unsubscribe_logs(app: AppHandle, id: u64) -> bool {
+    app.state::<SidecarManager>().logs().subscribers().unsubscribe(id)
+}
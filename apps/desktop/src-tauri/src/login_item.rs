@@ -0,0 +1,290 @@
+//! Launch at Login
+//!
+//! With `launchAtLogin` the app is started when the user logs in, so
+//! together with `autoStartGateway` the gateway is up after a reboot
+//! without anyone opening the app. The registration is the platform's own:
+//!
+//! - macOS: a LaunchAgent, `~/Library/LaunchAgents/com.simplestclaw.desktop.plist`
+//! - Windows: a value under `HKCU\Software\Microsoft\Windows\CurrentVersion\Run`
+//! - Linux: an XDG autostart entry, `~/.config/autostart/simplestclaw.desktop`
+//!
+//! People turn these off in system settings too, so the saved flag isn't
+//! what `get_config` reports: it reports `is_registered`. And
+//! `set_launch_at_login` changes the registration first and only saves the
+//! flag once that worked, so a sandbox refusing it leaves the flag as it
+//! was.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::config::{self, save_config, Config};
+use crate::error::AppError;
+use crate::metrics::AppMetrics;
+
+/// Name of the LaunchAgent, the app's bundle identifier
+pub const LABEL: &str = "com.simplestclaw.desktop";
+
+/// Name of the autostart entry and the Run value
+pub const NAME: &str = "simplestclaw";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoginItemError {
+    #[error("the app's own location is unknown: {0}")]
+    NoExecutable(io::Error),
+    #[error("there is no home directory to register in")]
+    NoHome,
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// What the login item runs: the app itself, or on Linux the AppImage it
+/// was started from rather than its temporary mount
+pub fn program() -> Result<PathBuf, LoginItemError> {
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE").filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe().map_err(LoginItemError::NoExecutable)
+}
+
+/// An XDG autostart entry starting `program`
+pub fn desktop_entry(program: &Path) -> String {
+    // Quoted as the Desktop Entry spec asks for Exec arguments
+    let mut exec = String::new();
+    for c in program.display().to_string().chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            exec.push('\\');
+        }
+        exec.push(c);
+    }
+    format!(
+        "[Desktop Entry]\nType=Application\nName=SimplestClaw\nExec=\"{}\"\nX-GNOME-Autostart-enabled=true\n",
+        exec
+    )
+}
+
+/// Whether the autostart entry `contents` is switched on; desktops switch
+/// one off by setting `Hidden` or `X-GNOME-Autostart-enabled`
+pub fn desktop_entry_enabled(contents: &str) -> bool {
+    !contents.lines().any(|line| {
+        let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+        line.eq_ignore_ascii_case("Hidden=true")
+            || line.eq_ignore_ascii_case("X-GNOME-Autostart-enabled=false")
+    })
+}
+
+/// A LaunchAgent starting `program` at login
+pub fn launch_agent(program: &Path) -> String {
+    let program = program
+        .display()
+        .to_string()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        LABEL, program
+    )
+}
+
+/// Write `contents` to the registration file `path`, or remove it for None
+pub fn write_entry(path: &Path, contents: Option<&str>) -> Result<(), LoginItemError> {
+    match contents {
+        Some(contents) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, contents)?;
+        }
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        },
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    fn agent_path() -> Result<PathBuf, LoginItemError> {
+        let home = dirs::home_dir().ok_or(LoginItemError::NoHome)?;
+        Ok(home
+            .join("Library/LaunchAgents")
+            .join(format!("{}.plist", LABEL)))
+    }
+
+    /// launchd's domain for the user's login session
+    fn gui_domain() -> String {
+        // SAFETY: getuid has no preconditions and can't fail
+        format!("gui/{}", unsafe { libc::getuid() })
+    }
+
+    /// Whether launchd has the agent disabled, as the Login Items settings
+    /// do; older versions print `true` rather than `disabled`
+    fn disabled() -> bool {
+        let Ok(output) = Command::new("launchctl")
+            .args(["print-disabled", &gui_domain()])
+            .output()
+        else {
+            return false;
+        };
+        let quoted = format!("\"{}\"", LABEL);
+        String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+            let line = line.trim();
+            line.starts_with(&quoted)
+                && (line.ends_with("=> disabled") || line.ends_with("=> true"))
+        })
+    }
+
+    pub fn is_registered() -> bool {
+        agent_path().is_ok_and(|path| path.is_file()) && !disabled()
+    }
+
+    pub fn set(enabled: bool) -> Result<(), LoginItemError> {
+        let path = agent_path()?;
+        if !enabled {
+            return write_entry(&path, None);
+        }
+        write_entry(&path, Some(&launch_agent(&program()?)))?;
+        // Clear a switch-off from the settings, which would outlast the file
+        let _ = Command::new("launchctl")
+            .args(["enable", &format!("{}/{}", gui_domain(), LABEL)])
+            .output();
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+    /// Where Task Manager records startup apps it switched off
+    const APPROVED_KEY: &str =
+        r"HKCU\Software\Microsoft\Windows\CurrentVersion\Explorer\StartupApproved\Run";
+
+    /// Run `reg` with `args`, returning its output
+    fn reg(args: &[&str]) -> Result<String, LoginItemError> {
+        let output = Command::new("reg").args(args).output()?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(LoginItemError::Failed(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+
+    /// Whether Task Manager switched the app off: the value's first byte is
+    /// odd then
+    fn disabled() -> bool {
+        let Ok(output) = reg(&["query", APPROVED_KEY, "/v", NAME]) else {
+            return false;
+        };
+        output
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(2))
+            .any(|data| {
+                u8::from_str_radix(data.get(..2).unwrap_or(""), 16).is_ok_and(|b| b % 2 == 1)
+            })
+    }
+
+    pub fn is_registered() -> bool {
+        reg(&["query", RUN_KEY, "/v", NAME]).is_ok() && !disabled()
+    }
+
+    pub fn set(enabled: bool) -> Result<(), LoginItemError> {
+        if !enabled {
+            if reg(&["query", RUN_KEY, "/v", NAME]).is_ok() {
+                reg(&["delete", RUN_KEY, "/v", NAME, "/f"])?;
+            }
+            return Ok(());
+        }
+        let command = format!("\"{}\"", program()?.display());
+        reg(&[
+            "add", RUN_KEY, "/v", NAME, "/t", "REG_SZ", "/d", &command, "/f",
+        ])?;
+        // Clear a switch-off from Task Manager, which would outlast the value
+        let _ = reg(&["delete", APPROVED_KEY, "/v", NAME, "/f"]);
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    use super::*;
+
+    fn entry_path() -> Result<PathBuf, LoginItemError> {
+        let dir = dirs::config_dir().ok_or(LoginItemError::NoHome)?;
+        Ok(dir.join("autostart").join(format!("{}.desktop", NAME)))
+    }
+
+    pub fn is_registered() -> bool {
+        entry_path()
+            .and_then(|path| Ok(fs::read_to_string(path)?))
+            .is_ok_and(|contents| desktop_entry_enabled(&contents))
+    }
+
+    pub fn set(enabled: bool) -> Result<(), LoginItemError> {
+        let path = entry_path()?;
+        if !enabled {
+            return write_entry(&path, None);
+        }
+        write_entry(&path, Some(&desktop_entry(&program()?)))
+    }
+}
+
+/// Whether the app is registered to start at login, as the system has it
+pub fn is_registered() -> bool {
+    platform::is_registered()
+}
+
+/// Register the app to start at login, or stop it from doing so
+pub fn set(enabled: bool) -> Result<(), LoginItemError> {
+    platform::set(enabled)
+}
+
+/// Start the app at login or not, and save that as `launchAtLogin`.
+/// Rejects with `launch_at_login_failed` when the system refused, with
+/// nothing saved.
+#[tauri::command]
+pub async fn set_launch_at_login(app: AppHandle, enabled: bool) -> Result<(), AppError> {
+    tokio::task::spawn_blocking(move || set(enabled)).await??;
+    let mut config = Config::load()?;
+    config.launch_at_login = enabled;
+    if let Err(e) = save_config(&config, &app.state::<Arc<AppMetrics>>()) {
+        // Keep the registration in line with what stays saved
+        let _ = set(!enabled);
+        return Err(e);
+    }
+    println!(
+        "[app] Launch at login {}",
+        if enabled { "enabled" } else { "disabled" }
+    );
+    config::emit_changed(&app);
+    Ok(())
+}
@@ -2,5 +2,14 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // With a verb (`simplestclaw status` ...) act as a client of the running app
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = simplestclaw_desktop::cli::run(&args) {
+        std::process::exit(code);
+    }
+    // `--headless` runs the gateway without a window
+    if simplestclaw_desktop::cli::is_headless(&args) {
+        std::process::exit(simplestclaw_desktop::headless::run());
+    }
     simplestclaw_desktop::run();
 }
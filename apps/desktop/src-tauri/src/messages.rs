@@ -0,0 +1,275 @@
+//! Message Catalog
+//!
+//! English text for every `AppError` code. Templates use `{name}`
+//! placeholders filled from the error's data. The frontend gets the code and
+//! data with every error and can render its own translation; the English
+//! text rendered here is the fallback.
+//!
+//! `get_error_catalog` lists every code with its parameters and template,
+//! so translations can be checked against it. A `detail` parameter is the
+//! underlying cause, like an OS error or the end of the gateway's stderr:
+//! it stays in English, and errors also carry it on its own next to the
+//! message.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::error::AppError;
+
+/// `(code, template)` for every error code
+pub const CATALOG: &[(&str, &str)] = &[
+    ("config_dir_unavailable", "Could not determine the config directory."),
+    ("config_io", "Could not read or write the config file: {detail}"),
+    (
+        "config_read_only",
+        "Settings can't be saved because {path} is read-only. Save again once it is writable.",
+    ),
+    (
+        "config_unavailable",
+        "Settings can't be saved because {path} could not be created: {detail}",
+    ),
+    ("config_invalid", "The config file is not valid JSON: {detail}"),
+    (
+        "config_too_new",
+        "The config file was written by a newer version of the app (config version {version}, this one reads up to {supported}). Update the app to use it.",
+    ),
+    ("config_export_failed", "Could not export the config to {path}: {detail}"),
+    ("config_import_failed", "The file can't be imported: {detail}"),
+    (
+        "cli_config_not_found",
+        "No openclaw CLI config was found in ~/.openclaw or OPENCLAW_CONFIG_PATH.",
+    ),
+    ("cli_config_unreadable", "Could not read the openclaw CLI config {path}: {detail}"),
+    ("invalid_config_override", "{variable} is set to {value}: {detail}"),
+    ("unknown_provider", "Unknown provider: {provider}"),
+    ("invalid_port", "{port} is not a valid gateway port."),
+    (
+        "invalid_gateway_host",
+        "{host} is not an address the gateway can listen on, like 127.0.0.1 or 0.0.0.0.",
+    ),
+    ("unknown_tool", "Unknown tool: {tool}"),
+    ("invalid_locale", "{locale} is not a locale like en-GB or de_DE.UTF-8."),
+    ("invalid_timezone", "{timezone} is not an IANA timezone like Europe/Berlin."),
+    ("invalid_openclaw_path", "openclawPath {path} is not an executable file."),
+    ("invalid_gateway_option", "{field}: {detail}"),
+    ("invalid_setting", "{field}: {detail}"),
+    ("invalid_proxy_url", "{url} is not a proxy URL like http://proxy.example.com:3128."),
+    (
+        "invalid_external_gateway_url",
+        "{url} is not a gateway URL like ws://localhost:18789.",
+    ),
+    ("unknown_profile", "There is no profile named {name}."),
+    ("profile_exists", "A profile named {name} already exists."),
+    (
+        "invalid_profile_name",
+        "{name} is not a profile name. Use letters, digits, - and _, up to 64 characters.",
+    ),
+    ("profile_active", "{name} is the active profile. Switch to another one first."),
+    ("unknown_api_key", "There is no stored API key labelled {label}."),
+    (
+        "invalid_api_key_label",
+        "{label} is not a key label. Use letters, digits, - and _.",
+    ),
+    (
+        "api_key_in_use",
+        "The API key {label} is in use. Switch to another key before removing it.",
+    ),
+    ("api_key_missing", "No API key is configured. Enter your API key to continue."),
+    (
+        "api_key_unreadable",
+        "The API key in config.json was encrypted on another machine and can't be read here. Enter your API key again.",
+    ),
+    (
+        "lan_token_required",
+        "The gateway was not started on the LAN because it has no token to require from clients.",
+    ),
+    ("api_key_rejected", "The API key was not saved. {detail}"),
+    (
+        "keychain_failed",
+        "The API key could not be stored in the system keychain. {detail}. Turn on storeApiKeyInFile to keep it in config.json instead.",
+    ),
+    (
+        "launch_at_login_failed",
+        "The app could not be set to start at login: {detail}",
+    ),
+    ("session_limit_reached", "{limit} sessions are already running, which is the configured limit."),
+    ("disk_full", "Only {available} is free at {path}; at least {required} is needed."),
+    (
+        "openclaw_too_old",
+        "The installed openclaw ({version}) is too old; {required} or newer is needed. Update it, or turn on skipOpenclawVersionCheck for a fork.",
+    ),
+    (
+        "tool_block_unsupported",
+        "The installed gateway ({version}) cannot disable the {tool} tool, so it was not started.",
+    ),
+    (
+        "gateway_flag_unsupported",
+        "The installed gateway ({version}) does not accept {flag}, so it was not started.",
+    ),
+    ("data_dir_unavailable", "Could not determine the app data directory."),
+    ("activity_log_failed", "Could not update the activity log: {detail}"),
+    (
+        "runtime_not_installed",
+        "The Node.js runtime is not installed yet. Wait for the download to finish, or click 'Install Runtime' in Settings.",
+    ),
+    ("gateway_not_found", "The gateway could not be found. {detail}"),
+    (
+        "port_in_use",
+        "Port {port} is still in use. Another gateway may be running; close all simplestclaw windows and try again.",
+    ),
+    (
+        "port_in_use_by_instance",
+        "Port {port} is used by the {instance} gateway. Give this profile another port, or stop that gateway first.",
+    ),
+    (
+        "port_not_permitted",
+        "The gateway isn't allowed to use port {port}. Port {suggested} is free; switch to it?",
+    ),
+    (
+        "port_blocked",
+        "Port {port} has been taken by another program for the last {starts} starts. Port {suggested} is free; switch to it?",
+    ),
+    (
+        "external_gateway_detected",
+        "An OpenClaw gateway you started yourself is already running on port {port}. Enter its token to use it, or stop it.",
+    ),
+    ("transport_unsupported", "The {transport} transport can't be used: {detail}"),
+    (
+        "socket_in_use",
+        "Another gateway is still listening on {path}. Stop it, or set gatewaySocketPath to another socket.",
+    ),
+    ("external_gateway_unreachable", "The gateway at {url} can't be reached: {detail}"),
+    ("gateway_spawn_failed", "The gateway process could not be launched: {detail}"),
+    ("gateway_start_failed", "The gateway could not be started. {detail}"),
+    ("gateway_start_cancelled", "The gateway was stopped while it was starting."),
+    (
+        "auth_self_test_failed",
+        "The gateway started but did not accept the app's connection, so it was stopped. {detail}",
+    ),
+    ("tls_config_invalid", "The TLS file {path} can't be used: {detail}"),
+    ("gateway_stop_failed", "The gateway could not be stopped: {detail}"),
+    ("gateway_not_running", "The gateway is not running."),
+    ("gateway_pause_unsupported", "Pausing the gateway isn't supported on this system."),
+    ("gateway_pause_failed", "The gateway could not be paused or resumed: {detail}"),
+    (
+        "external_clients_not_allowed",
+        "Connecting other clients to the gateway is turned off. Allow external clients in the settings first.",
+    ),
+    ("clipboard_failed", "Could not copy to the clipboard: {detail}"),
+    ("runtime_install_failed", "The Node.js runtime could not be installed: {detail}"),
+    (
+        "installer_not_found",
+        "openclaw can't be installed because npm was not found. Install Node.js, or wait for the app to finish downloading its runtime.",
+    ),
+    (
+        "install_permission_denied",
+        "npm may not write to {path}. Run `npm config set prefix ~/.npm-global`, add ~/.npm-global/bin to PATH, then try again.",
+    ),
+    (
+        "install_network_failed",
+        "openclaw could not be downloaded: {detail}. Check the network connection and proxy settings.",
+    ),
+    ("install_failed", "Installing openclaw failed: {detail}"),
+    (
+        "update_check_failed",
+        "Could not check for a newer openclaw: {detail}. Check the network connection and proxy settings.",
+    ),
+    ("delete_failed", "Could not delete {path}: {detail}"),
+    (
+        "diagnostics_export_failed",
+        "Could not write the diagnostics to {path}: {detail}",
+    ),
+    ("purge_not_confirmed", "Removing app data needs a fresh confirmation."),
+    ("invalid_log_pattern", "The search pattern can't be used: {detail}"),
+    ("open_logs_dir_failed", "Could not open the logs folder: {detail}"),
+    ("task_failed", "A background task failed: {detail}"),
+];
+
+/// What translating one error code takes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorDescriptor {
+    pub code: String,
+    /// Names of the `data` parameters the error comes with
+    pub params: Vec<String>,
+    /// `params` has a `detail`, which isn't translated
+    pub has_detail: bool,
+    /// The English template, with `{name}` for each parameter it shows
+    pub fallback: String,
+}
+
+/// Every error code, in catalog order
+pub fn error_catalog() -> Vec<ErrorDescriptor> {
+    let examples = AppError::examples();
+    CATALOG
+        .iter()
+        .map(|(code, template)| {
+            let params: Vec<String> = examples
+                .iter()
+                .find(|error| error.code() == *code)
+                .map(|error| error.data().into_keys().collect())
+                .unwrap_or_default();
+            ErrorDescriptor {
+                code: code.to_string(),
+                has_detail: params.iter().any(|param| param == "detail"),
+                params,
+                fallback: template.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// The English template for `code`
+pub fn template(code: &str) -> Option<&'static str> {
+    CATALOG
+        .iter()
+        .find(|(entry, _)| *entry == code)
+        .map(|(_, template)| *template)
+}
+
+/// Render the English message for `code`. Placeholders without a value are
+/// left as they are, and unknown codes get a generic message.
+pub fn render(code: &str, params: &BTreeMap<String, String>) -> String {
+    match template(code) {
+        Some(template) => interpolate(template, params),
+        None => format!("Unexpected error ({})", code),
+    }
+}
+
+/// Replace `{name}` placeholders in `template` with values from `params`
+pub fn interpolate(template: &str, params: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        match after.find('}') {
+            Some(close) => {
+                let name = &after[..close];
+                match params.get(name) {
+                    Some(value) => out.push_str(value),
+                    None => {
+                        out.push('{');
+                        out.push_str(name);
+                        out.push('}');
+                    }
+                }
+                rest = &after[close + 1..];
+            }
+            None => {
+                // Unterminated placeholder, keep the rest verbatim
+                out.push_str(&rest[open..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Tauri commands
+/// Every error code, its parameters and English template
+#[tauri::command]
+pub fn get_error_catalog() -> Vec<ErrorDescriptor> {
+    error_catalog()
+}
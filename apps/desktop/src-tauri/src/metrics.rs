@@ -0,0 +1,206 @@
+//! In-memory failure counters
+//!
+//! Counters are plain atomics so they can be bumped from any thread (the
+//! blocking gateway start, status polling, command handlers) without
+//! serialising on a lock. Keyed counters only take a write lock the first
+//! time a key is seen. Nothing is persisted; everything resets when the app
+//! restarts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+/// Counters keyed by a short reason or error code
+#[derive(Default)]
+struct KeyedCounters(RwLock<HashMap<String, AtomicU64>>);
+
+impl KeyedCounters {
+    fn increment(&self, key: &str) {
+        if let Ok(counters) = self.0.read() {
+            if let Some(counter) = counters.get(key) {
+                counter.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        if let Ok(mut counters) = self.0.write() {
+            counters
+                .entry(key.to_string())
+                .or_default()
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, u64> {
+        self.0
+            .read()
+            .map(|counters| {
+                counters
+                    .iter()
+                    .map(|(key, counter)| (key.clone(), counter.load(Ordering::Relaxed)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+pub struct AppMetrics {
+    /// Unix timestamp (ms) of when the app started
+    started_at: i64,
+    started: Instant,
+    start_attempts: AtomicU64,
+    start_failures: KeyedCounters,
+    crashes: AtomicU64,
+    auto_restarts: AtomicU64,
+    config_save_failures: AtomicU64,
+    command_errors: KeyedCounters,
+    /// Most recent failed start or crash, until the next successful start
+    last_failure: Mutex<Option<LastFailure>>,
+    last_auth_self_test: Mutex<Option<AuthSelfTest>>,
+}
+
+impl Default for AppMetrics {
+    fn default() -> Self {
+        Self {
+            started_at: now_ms(),
+            started: Instant::now(),
+            start_attempts: AtomicU64::new(0),
+            start_failures: KeyedCounters::default(),
+            crashes: AtomicU64::new(0),
+            auto_restarts: AtomicU64::new(0),
+            config_save_failures: AtomicU64::new(0),
+            command_errors: KeyedCounters::default(),
+            last_failure: Mutex::new(None),
+            last_auth_self_test: Mutex::new(None),
+        }
+    }
+}
+
+impl AppMetrics {
+    pub fn record_start_attempt(&self) {
+        self.start_attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a failed start, keyed by a short reason such as `timeout`
+    pub fn record_start_failure(&self, reason: &str) {
+        self.start_failures.increment(reason);
+        self.set_last_failure(Some(reason));
+    }
+
+    /// Record a gateway that became ready, clearing the last failure
+    pub fn record_start_success(&self) {
+        self.set_last_failure(None);
+    }
+
+    /// Record a gateway that exited on its own after starting successfully
+    pub fn record_crash(&self) {
+        self.crashes.fetch_add(1, Ordering::Relaxed);
+        self.set_last_failure(Some("crashed"));
+    }
+
+    fn set_last_failure(&self, code: Option<&str>) {
+        if let Ok(mut last_failure) = self.last_failure.lock() {
+            *last_failure = code.map(|code| LastFailure {
+                code: code.to_string(),
+                at: now_ms(),
+            });
+        }
+    }
+
+    /// Record the auth self-test after a start and how long it took
+    pub fn record_auth_self_test(&self, passed: bool, duration: Duration) {
+        if let Ok(mut last) = self.last_auth_self_test.lock() {
+            *last = Some(AuthSelfTest {
+                passed,
+                duration_ms: duration.as_millis() as u64,
+                at: now_ms(),
+            });
+        }
+    }
+
+    pub fn record_auto_restart(&self) {
+        self.auto_restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_config_save_failure(&self) {
+        self.config_save_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a command that returned an error, keyed by its error code
+    pub fn record_command_error(&self, code: &str) {
+        self.command_errors.increment(code);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let start_failures_by_reason = self.start_failures.snapshot();
+        MetricsSnapshot {
+            started_at: self.started_at,
+            uptime_secs: self.started.elapsed().as_secs(),
+            start_attempts: self.start_attempts.load(Ordering::Relaxed),
+            start_failures: start_failures_by_reason.values().sum(),
+            start_failures_by_reason,
+            crashes: self.crashes.load(Ordering::Relaxed),
+            auto_restarts: self.auto_restarts.load(Ordering::Relaxed),
+            config_save_failures: self.config_save_failures.load(Ordering::Relaxed),
+            command_errors: self.command_errors.snapshot(),
+            last_failure: self.last_failure.lock().ok().and_then(|last| last.clone()),
+            last_auth_self_test: self
+                .last_auth_self_test
+                .lock()
+                .ok()
+                .and_then(|last| last.clone()),
+        }
+    }
+}
+
+/// Point-in-time copy of the counters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSnapshot {
+    /// Unix timestamp (ms) of when the app started
+    pub started_at: i64,
+    pub uptime_secs: u64,
+    pub start_attempts: u64,
+    pub start_failures: u64,
+    pub start_failures_by_reason: BTreeMap<String, u64>,
+    pub crashes: u64,
+    pub auto_restarts: u64,
+    pub config_save_failures: u64,
+    pub command_errors: BTreeMap<String, u64>,
+    pub last_failure: Option<LastFailure>,
+    pub last_auth_self_test: Option<AuthSelfTest>,
+}
+
+/// A failed start (keyed like `startFailuresByReason`) or `crashed`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LastFailure {
+    pub code: String,
+    /// Unix timestamp (ms)
+    pub at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthSelfTest {
+    pub passed: bool,
+    pub duration_ms: u64,
+    /// Unix timestamp (ms)
+    pub at: i64,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub fn get_app_metrics(metrics: State<'_, Arc<AppMetrics>>) -> MetricsSnapshot {
+    metrics.snapshot()
+}
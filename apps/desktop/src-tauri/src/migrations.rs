@@ -0,0 +1,64 @@
+//! Config Migrations
+//!
+//! config.json records the schema it was written in as `configVersion`;
+//! files from before there was one are version 0. On load the JSON is
+//! upgraded one version at a time, by the steps in `STEPS`, before it is
+//! read into `Config`, so a renamed or restructured field is carried over
+//! rather than dropped as unknown. Saving always writes `CONFIG_VERSION`.
+//!
+//! A file from a newer app is refused, on load and on save, instead of
+//! being read with its new fields ignored and then overwritten without
+//! them.
+//!
+//! To change the schema, bump `CONFIG_VERSION` and add the step from the
+//! previous version, with a fixture in `tests/fixtures/config`.
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// The schema this app reads and writes
+pub const CONFIG_VERSION: u32 = 1;
+
+/// The field holding the version
+const VERSION_FIELD: &str = "configVersion";
+
+/// `STEPS[n]` upgrades a version `n` file to `n + 1`
+const STEPS: &[fn(&mut Map<String, Value>)] = &[v0_to_v1];
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    #[error("config.json was written by a newer version of the app (config version {found}, this one supports up to {supported})")]
+    TooNew { found: u32, supported: u32 },
+}
+
+/// The version `value` was written in, 0 if it doesn't say
+pub fn version_of(value: &Value) -> u32 {
+    value
+        .get(VERSION_FIELD)
+        .and_then(Value::as_u64)
+        .map_or(0, |version| version.min(u32::MAX as u64) as u32)
+}
+
+/// Upgrade `value` to `CONFIG_VERSION`. Returns the version it had.
+pub fn migrate(value: &mut Value) -> Result<u32, MigrationError> {
+    let found = version_of(value);
+    if found > CONFIG_VERSION {
+        return Err(MigrationError::TooNew {
+            found,
+            supported: CONFIG_VERSION,
+        });
+    }
+    // Anything but an object fails to read as `Config` afterwards
+    let Some(fields) = value.as_object_mut() else {
+        return Ok(found);
+    };
+    for (version, step) in STEPS.iter().enumerate().skip(found as usize) {
+        step(fields);
+        fields.insert(VERSION_FIELD.to_string(), Value::from(version as u32 + 1));
+    }
+    Ok(found)
+}
+
+/// Files from before versioning have the version 1 fields already and
+/// only gain the number
+fn v0_to_v1(_fields: &mut Map<String, Value>) {}
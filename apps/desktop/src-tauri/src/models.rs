@@ -0,0 +1,140 @@
+//! Gateway Model Selection
+//!
+//! `defaultModel` pins the model the gateway's agent uses and `smallModel`
+//! the one it uses for quick background work like titles and summaries,
+//! without editing openclaw's own config files. They are passed as
+//! `OPENCLAW_MODEL` and `OPENCLAW_SMALL_MODEL`; unset, openclaw picks for
+//! itself.
+//!
+//! Model names change too often to keep a list, so only the shape is
+//! checked: a single word of letters, digits and `. _ - : / @ +`, like
+//! `anthropic/claude-sonnet-4-5` or `openrouter/meta-llama/llama-3:free`.
+//! Neither is a secret; both show in `get_config`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+
+use crate::config::{save_config, Config};
+use crate::error::AppError;
+use crate::metrics::AppMetrics;
+use crate::sidecar::SidecarManager;
+
+/// Variable the default model is passed in
+pub const MODEL_ENV: &str = "OPENCLAW_MODEL";
+
+/// Variable the small model is passed in
+pub const SMALL_MODEL_ENV: &str = "OPENCLAW_SMALL_MODEL";
+
+/// Longest model name accepted
+pub const MAX_LEN: usize = 200;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{model:?} is not a model name like anthropic/claude-sonnet-4-5")]
+pub struct ModelError {
+    /// `defaultModel` or `smallModel`
+    pub field: &'static str,
+    pub model: String,
+}
+
+/// Check `model` looks like a model name
+pub fn validate(field: &'static str, model: &str) -> Result<(), ModelError> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || ".-_:/@+".contains(c);
+    if model.is_empty() || model.len() > MAX_LEN || !model.chars().all(allowed) {
+        return Err(ModelError {
+            field,
+            model: model.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The models a gateway is started with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelPlan {
+    pub default_model: Option<String>,
+    pub small_model: Option<String>,
+}
+
+impl ModelPlan {
+    pub fn env(&self) -> Vec<(String, String)> {
+        [
+            (MODEL_ENV, &self.default_model),
+            (SMALL_MODEL_ENV, &self.small_model),
+        ]
+        .into_iter()
+        .filter_map(|(name, model)| Some((name.to_string(), model.clone()?)))
+        .collect()
+    }
+
+    pub fn describe(&self) -> String {
+        let model =
+            |model: &Option<String>| model.as_deref().unwrap_or("openclaw's choice").to_string();
+        format!(
+            "Model {}, small model {}",
+            model(&self.default_model),
+            model(&self.small_model)
+        )
+    }
+}
+
+/// The models in `config`, None when neither is set
+pub fn plan(config: &Config) -> Result<Option<ModelPlan>, ModelError> {
+    if let Some(ref model) = config.default_model {
+        validate("defaultModel", model)?;
+    }
+    if let Some(ref model) = config.small_model {
+        validate("smallModel", model)?;
+    }
+    let plan = ModelPlan {
+        default_model: config.default_model.clone(),
+        small_model: config.small_model.clone(),
+    };
+    Ok((plan != ModelPlan::default()).then_some(plan))
+}
+
+/// Outcome of `set_model_config`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelConfigChange {
+    /// The running gateway keeps its models until it is restarted
+    pub restart_required: bool,
+}
+
+/// Trimmed, with empty as None
+fn optional(model: Option<String>) -> Option<String> {
+    model
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty())
+}
+
+/// Set `defaultModel` and `smallModel`; None or empty leaves the choice to
+/// openclaw. Rejects with `invalid_setting`, naming the field, for
+/// something that isn't a model name.
+#[tauri::command]
+pub async fn set_model_config(
+    app: AppHandle,
+    default_model: Option<String>,
+    small_model: Option<String>,
+) -> Result<ModelConfigChange, AppError> {
+    let default_model = optional(default_model);
+    let small_model = optional(small_model);
+    if let Some(ref model) = default_model {
+        validate("defaultModel", model)?;
+    }
+    if let Some(ref model) = small_model {
+        validate("smallModel", model)?;
+    }
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        let changed = config.default_model != default_model || config.small_model != small_model;
+        config.default_model = default_model;
+        config.small_model = small_model;
+        save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+        Ok(ModelConfigChange {
+            restart_required: changed && app.state::<SidecarManager>().gateway_pid().is_some(),
+        })
+    })
+    .await?
+}
@@ -0,0 +1,177 @@
+//! openclaw Update Check
+//!
+//! The gateway protocol moves on, and an openclaw installed globally long
+//! ago breaks in ways that are hard to place. With `checkOpenclawUpdates`
+//! (the default) a background task asks the npm registry for the latest
+//! release at most once every `CHECK_INTERVAL`, and emits
+//! `openclaw://update-available` with both versions when it is newer than
+//! the one a start would run. `check_for_openclaw_update` asks right away,
+//! for a button in the settings.
+//!
+//! The time of the last successful check is kept in config.json
+//! (`lastOpenclawUpdateCheck`), so restarting the app doesn't ask again. A
+//! failed background check is only logged and tried again on the next
+//! tick; being offline is normal. The registry is reached through the
+//! app's proxy settings, like the gateway's connections.
+//!
+//! `canAutoUpgrade` says whether `upgrade_openclaw` has a package manager
+//! to upgrade with.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::blocked_tools::GatewayVersion;
+use crate::config::{self, Config};
+use crate::error::AppError;
+use crate::gateway_logs;
+use crate::installer::{self, PACKAGE};
+use crate::metrics::AppMetrics;
+use crate::proxy;
+use crate::resolve::SearchContext;
+use crate::sidecar::SidecarManager;
+
+/// Event a newer release is announced with
+pub const UPDATE_AVAILABLE_EVENT: &str = "openclaw://update-available";
+
+/// The registry document of the latest release
+pub const REGISTRY_URL: &str = "https://registry.npmjs.org/openclaw/latest";
+
+/// Least time between two checks
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+
+/// Time between looks whether a check is due
+pub const TICK: Duration = Duration::from_secs(3600);
+
+/// Delay before the first look, so the check doesn't compete with startup
+pub const FIRST_TICK: Duration = Duration::from_secs(60);
+
+/// How long the registry gets to answer
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The result of a check, and the payload of `openclaw://update-available`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    /// The openclaw a start would run; None when there is none or its
+    /// version can't be read
+    pub current: Option<String>,
+    pub latest: String,
+    pub update_available: bool,
+    /// `upgrade_openclaw` found a package manager to upgrade with
+    pub can_auto_upgrade: bool,
+}
+
+/// Whether the last check at `last` (Unix ms) is old enough at `now`
+pub fn is_check_due(last: Option<i64>, now: i64) -> bool {
+    last.is_none_or(|last| now - last >= CHECK_INTERVAL.as_millis() as i64 || now < last)
+}
+
+/// The version in the registry's document of a release
+pub fn parse_latest(body: &str) -> Option<GatewayVersion> {
+    let document: serde_json::Value = serde_json::from_str(body).ok()?;
+    GatewayVersion::parse(document["version"].as_str()?)
+}
+
+/// Whether `latest` is newer than `current`. An unknown current version
+/// isn't worth an update notice.
+pub fn is_newer(current: Option<GatewayVersion>, latest: GatewayVersion) -> bool {
+    current.is_some_and(|current| latest > current)
+}
+
+/// Ask the registry for the latest release
+async fn fetch_latest(config: &Config) -> Result<GatewayVersion, String> {
+    let proxy = proxy::plan(config).map_err(|e| e.to_string())?;
+    let client = proxy::client(proxy.as_ref(), REQUEST_TIMEOUT).map_err(|e| e.to_string())?;
+    let response = client
+        .get(REGISTRY_URL)
+        .header("accept", "application/json")
+        .send()
+        .await
+        .map_err(|e| format!("could not reach the npm registry: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("the npm registry answered {}", response.status()));
+    }
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    parse_latest(&body).ok_or_else(|| format!("no {} version in the registry's answer", PACKAGE))
+}
+
+/// Compare the latest release with the installed one and note the time of
+/// the check in config.json
+async fn check(app: &AppHandle) -> Result<UpdateInfo, AppError> {
+    let config = Config::load()?;
+    let latest = fetch_latest(&config)
+        .await
+        .map_err(|detail| AppError::UpdateCheckFailed { detail })?;
+
+    let (current, can_auto_upgrade) = tokio::task::spawn_blocking({
+        let app = app.clone();
+        let config = config.clone();
+        move || {
+            // Not installed at all is no error here
+            let current = app
+                .state::<SidecarManager>()
+                .openclaw_version()
+                .ok()
+                .and_then(|info| info.version)
+                .and_then(|version| GatewayVersion::parse(&version));
+            let installer = installer::find(&SearchContext::current(&config));
+            (current, installer.is_some())
+        }
+    })
+    .await?;
+
+    // Loaded again, so a setting saved meanwhile isn't undone
+    let mut config = Config::load()?;
+    config.last_openclaw_update_check = Some(gateway_logs::now_ms());
+    config::save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+
+    Ok(UpdateInfo {
+        current: current.map(|version| version.to_string()),
+        latest: latest.to_string(),
+        update_available: is_newer(current, latest),
+        can_auto_upgrade,
+    })
+}
+
+/// Start the task that checks for a newer openclaw once a day
+pub fn spawn_update_checker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_TICK).await;
+        loop {
+            let due = Config::load().is_ok_and(|config| {
+                config.check_openclaw_updates
+                    && is_check_due(config.last_openclaw_update_check, gateway_logs::now_ms())
+            });
+            if due {
+                match check(&app).await {
+                    Ok(info) if info.update_available => {
+                        println!(
+                            "[openclaw] openclaw {} is available, {} is installed",
+                            info.latest,
+                            info.current.as_deref().unwrap_or("unknown")
+                        );
+                        let _ = app.emit(UPDATE_AVAILABLE_EVENT, &info);
+                    }
+                    Ok(_) => println!("[openclaw] openclaw is up to date"),
+                    Err(e) => println!("[openclaw] Update check skipped: {}", e),
+                }
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    });
+}
+
+// Tauri commands
+/// Ask the npm registry for the latest openclaw now. Emits
+/// `openclaw://update-available` like the daily check when it is newer.
+/// Fails with `update_check_failed` when the registry can't be reached.
+#[tauri::command]
+pub async fn check_for_openclaw_update(app: AppHandle) -> Result<UpdateInfo, AppError> {
+    let info = check(&app).await?;
+    if info.update_available {
+        let _ = app.emit(UPDATE_AVAILABLE_EVENT, &info);
+    }
+    Ok(info)
+}
@@ -0,0 +1,135 @@
+//! openclaw Version
+//!
+//! Releases older than `MIN_OPENCLAW_VERSION` lack flags the app passes,
+//! `--allow-unconfigured` among them, and exit straight away with an error
+//! that doesn't say why. Before a start the installed openclaw's
+//! `--version` is read, and a release that is too old refuses the start
+//! with `openclaw_too_old`. Forks with their own numbering can turn the
+//! check off with `skipOpenclawVersionCheck`. A version that can't be read
+//! doesn't refuse the start; `capabilities` still checks the flags.
+//! Releases from there up to `RECOMMENDED_OPENCLAW_VERSION` start with
+//! an `openclaw_outdated` warning.
+//!
+//! The answer is cached per program, arguments and modification time, so
+//! `--version` isn't run on every start but an upgrade in place is
+//! noticed. Through npx the program is node, so a newer openclaw in npx's
+//! cache is picked up on the next launch.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+use thiserror::Error;
+
+use crate::blocked_tools::{self, GatewayVersion};
+use crate::config::Config;
+use crate::error::AppError;
+use crate::sidecar::SidecarManager;
+
+/// The oldest openclaw the app starts
+pub const MIN_OPENCLAW_VERSION: GatewayVersion = GatewayVersion(2026, 1, 20);
+
+/// The oldest openclaw the app is tested with
+pub const RECOMMENDED_OPENCLAW_VERSION: GatewayVersion = GatewayVersion(2026, 2, 1);
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum VersionError {
+    #[error("openclaw {found} is too old, {required} or newer is needed")]
+    TooOld { found: String, required: String },
+}
+
+/// Whether `version` may be started with `config`: a known version below
+/// `MIN_OPENCLAW_VERSION` may not, unless the check is skipped
+pub fn check(version: Option<GatewayVersion>, config: &Config) -> Result<(), VersionError> {
+    match version {
+        Some(version) if version < MIN_OPENCLAW_VERSION && !config.skip_openclaw_version_check => {
+            Err(VersionError::TooOld {
+                found: version.to_string(),
+                required: MIN_OPENCLAW_VERSION.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+type CacheKey = (PathBuf, Vec<String>, SystemTime);
+
+fn cache() -> &'static Mutex<HashMap<CacheKey, GatewayVersion>> {
+    static CACHE: OnceLock<Mutex<HashMap<CacheKey, GatewayVersion>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Forget every cached version, after openclaw was installed or upgraded
+pub fn clear_cache() {
+    if let Ok(mut cache) = cache().lock() {
+        cache.clear();
+    }
+}
+
+/// The version cached for `program` with any arguments, if it hasn't
+/// changed since. Runs nothing.
+pub fn cached(program: &str) -> Option<GatewayVersion> {
+    let modified = std::fs::metadata(program).and_then(|m| m.modified()).ok()?;
+    let path = Path::new(program);
+    let cache = cache().lock().ok()?;
+    cache
+        .iter()
+        .find(|((cached, _, at), _)| cached == path && *at == modified)
+        .map(|(_, version)| *version)
+}
+
+/// `program args --version`, from the cache when `program` hasn't changed
+/// since it was asked. Only versions are cached, so a failed query is
+/// retried next start.
+pub fn query(
+    program: &str,
+    args: &[String],
+    env: &[(String, String)],
+    timeout: Duration,
+) -> Option<GatewayVersion> {
+    let modified = std::fs::metadata(program)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    let key = modified.map(|modified| (PathBuf::from(program), args.to_vec(), modified));
+    if let Some(ref key) = key {
+        let cached = cache()
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(key).copied());
+        if cached.is_some() {
+            return cached;
+        }
+    }
+
+    let version = blocked_tools::query_version(program, args, env, timeout)?;
+    if let (Some(key), Ok(mut cache)) = (key, cache().lock()) {
+        cache.insert(key, version);
+    }
+    Some(version)
+}
+
+/// What `get_openclaw_version` reports
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    /// None when it couldn't be read
+    pub version: Option<String>,
+    /// The program run for it: openclaw itself, or node for npx
+    pub path: Option<String>,
+    pub uses_npx: bool,
+    pub minimum: String,
+    /// Whether it is `minimum` or newer. None when the version is unknown,
+    /// which doesn't stop a start.
+    pub supported: Option<bool>,
+    /// `skipOpenclawVersionCheck` is on
+    pub check_skipped: bool,
+}
+
+/// The installed openclaw's version and whether it is new enough. Rejects
+/// like a start when there is no openclaw to ask.
+#[tauri::command]
+pub async fn get_openclaw_version(app: AppHandle) -> Result<VersionInfo, AppError> {
+    tokio::task::spawn_blocking(move || app.state::<SidecarManager>().openclaw_version()).await?
+}
@@ -0,0 +1,148 @@
+//! Environment Overrides
+//!
+//! For CI runs and kiosk setups, a few settings can be forced from the
+//! environment without touching config.json:
+//!
+//! - `SIMPLESTCLAW_GATEWAY_PORT`: `gatewayPort`
+//! - `SIMPLESTCLAW_AUTO_START`: `autoStartGateway`, `true` or `false`
+//!   (also `1`/`0`, `yes`/`no`, `on`/`off`)
+//! - `SIMPLESTCLAW_OPENCLAW_PATH`: `openclawPath`
+//!
+//! `SIMPLESTCLAW_CONFIG_DIR` moves config.json itself (see `paths`).
+//!
+//! `Config::load` applies them over the file; an empty variable counts as
+//! unset and an invalid value fails the load rather than being ignored.
+//! A save writes the file's own value for a field that still has the
+//! override, so they never end up in config.json. `get_config` lists the
+//! overridden fields so the UI can show them as fixed.
+
+use std::path::PathBuf;
+use std::sync::Once;
+
+use crate::config::{Config, ConfigError};
+
+pub const GATEWAY_PORT_ENV: &str = "SIMPLESTCLAW_GATEWAY_PORT";
+pub const AUTO_START_ENV: &str = "SIMPLESTCLAW_AUTO_START";
+pub const OPENCLAW_PATH_ENV: &str = "SIMPLESTCLAW_OPENCLAW_PATH";
+
+/// The overrides applied to a config, each with the file's value
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Applied {
+    gateway_port: Option<(u16, u16)>,
+    auto_start_gateway: Option<(bool, bool)>,
+    openclaw_path: Option<(PathBuf, Option<PathBuf>)>,
+}
+
+impl Applied {
+    /// The overridden fields, as named in config.json
+    pub fn fields(&self) -> Vec<&'static str> {
+        let mut fields = Vec::new();
+        if self.gateway_port.is_some() {
+            fields.push("gatewayPort");
+        }
+        if self.auto_start_gateway.is_some() {
+            fields.push("autoStartGateway");
+        }
+        if self.openclaw_path.is_some() {
+            fields.push("openclawPath");
+        }
+        fields
+    }
+}
+
+fn invalid(variable: &str, value: &str, detail: &str) -> ConfigError {
+    ConfigError::InvalidOverride {
+        variable: variable.to_string(),
+        value: value.to_string(),
+        detail: detail.to_string(),
+    }
+}
+
+fn parse_port(value: &str) -> Result<u16, ConfigError> {
+    match value.trim().parse::<u16>() {
+        Ok(0) => Err(invalid(GATEWAY_PORT_ENV, value, "the port can't be 0")),
+        Ok(port) => Ok(port),
+        Err(_) => Err(invalid(
+            GATEWAY_PORT_ENV,
+            value,
+            "not a port number between 1 and 65535",
+        )),
+    }
+}
+
+fn parse_bool(variable: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(invalid(variable, value, "expected true or false")),
+    }
+}
+
+/// Apply the overrides from the process environment to `config`
+pub fn apply(config: &mut Config) -> Result<(), ConfigError> {
+    apply_from(config, |variable| std::env::var(variable).ok())
+}
+
+/// Apply the overrides `lookup` finds to `config`, remembering the file's
+/// values in `config.overrides`
+pub fn apply_from(
+    config: &mut Config,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    let lookup = |variable: &str| lookup(variable).filter(|value| !value.trim().is_empty());
+    let mut applied = Applied::default();
+    if let Some(value) = lookup(GATEWAY_PORT_ENV) {
+        let port = parse_port(&value)?;
+        applied.gateway_port = Some((port, config.gateway_port));
+    }
+    if let Some(value) = lookup(AUTO_START_ENV) {
+        let enabled = parse_bool(AUTO_START_ENV, &value)?;
+        applied.auto_start_gateway = Some((enabled, config.auto_start_gateway));
+    }
+    if let Some(value) = lookup(OPENCLAW_PATH_ENV) {
+        let path = PathBuf::from(value.trim());
+        applied.openclaw_path = Some((path, config.openclaw_path.clone()));
+    }
+
+    if let Some((port, _)) = applied.gateway_port {
+        config.gateway_port = port;
+    }
+    if let Some((enabled, _)) = applied.auto_start_gateway {
+        config.auto_start_gateway = enabled;
+    }
+    if let Some((ref path, _)) = applied.openclaw_path {
+        config.openclaw_path = Some(path.clone());
+    }
+    if applied != Applied::default() {
+        static LOGGED: Once = Once::new();
+        LOGGED.call_once(|| {
+            println!(
+                "[config] Overridden from the environment: {}",
+                applied.fields().join(", ")
+            )
+        });
+    }
+    config.overrides = applied;
+    Ok(())
+}
+
+/// Put the file's values back into `saved` where it still has the
+/// overrides; a field changed since is saved as changed
+pub fn restore(saved: &mut Config) {
+    let applied = std::mem::take(&mut saved.overrides);
+    if let Some((port, file)) = applied.gateway_port {
+        if saved.gateway_port == port {
+            saved.gateway_port = file;
+        }
+    }
+    if let Some((enabled, file)) = applied.auto_start_gateway {
+        if saved.auto_start_gateway == enabled {
+            saved.auto_start_gateway = file;
+        }
+    }
+    if let Some((path, file)) = applied.openclaw_path {
+        if saved.openclaw_path.as_ref() == Some(&path) {
+            saved.openclaw_path = file;
+        }
+    }
+}
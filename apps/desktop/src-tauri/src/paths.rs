@@ -0,0 +1,417 @@
+//! App Directory Layout
+//!
+//! Single source of truth for where simplestclaw keeps its files:
+//!
+//! - config dir: `config.json`
+//! - data dir: app state such as the activity history
+//! - logs dir: log files (under the data dir)
+//! - crash dir: crash reports (under the data dir)
+//! - runtime dir: the bundled Node.js runtime (under the data dir)
+//! - workspace dir: the gateway's sandbox workspace (under the data dir)
+//! - gateway state dir: the gateway's own sessions and memory (under the
+//!   data dir)
+//! - cache dir: disposable downloads and caches
+//!
+//! By default these follow the platform conventions from `dirs`. Setting
+//! `SIMPLESTCLAW_HOME` puts everything under that one directory instead,
+//! which keeps dev builds and tests away from the real user data.
+//! `SIMPLESTCLAW_CONFIG_DIR` moves just the config dir, on top of either.
+//! The fields are plain locations; directories are only created when a
+//! file inside them is requested or a caller uses `ensure_dir`.
+//!
+//! Files holding secrets, like `config.json`, are kept to their owner with
+//! `restrict_mode`; Windows has no modes and leaves them as they are.
+//!
+//! `DiskSpace` reports free space on the volumes these live on, so starts
+//! and large writes can be refused before a full disk truncates files.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::AppError;
+
+/// Environment variable that relocates every app directory under one root
+pub const HOME_ENV: &str = "SIMPLESTCLAW_HOME";
+
+/// Environment variable that relocates the config dir alone
+pub const CONFIG_DIR_ENV: &str = "SIMPLESTCLAW_CONFIG_DIR";
+
+const APP_DIR_NAME: &str = "simplestclaw";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppPaths {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+    pub logs_dir: PathBuf,
+    pub crash_dir: PathBuf,
+    pub runtime_dir: PathBuf,
+    pub workspace_dir: PathBuf,
+    pub gateway_state_dir: PathBuf,
+    pub cache_dir: PathBuf,
+}
+
+impl AppPaths {
+    /// Resolve the layout for this machine, honouring `SIMPLESTCLAW_HOME`
+    /// and `SIMPLESTCLAW_CONFIG_DIR`
+    pub fn resolve() -> Option<Self> {
+        let mut paths = match std::env::var_os(HOME_ENV) {
+            Some(root) if !root.is_empty() => Self::from_root(root),
+            _ => Self::platform()?,
+        };
+        match std::env::var_os(CONFIG_DIR_ENV) {
+            Some(dir) if !dir.is_empty() => paths.config_dir = PathBuf::from(dir),
+            _ => {}
+        }
+        Some(paths)
+    }
+
+    /// Layout with every directory under `root`
+    pub fn from_root(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let data_dir = root.join("data");
+        Self {
+            config_dir: root.join("config"),
+            logs_dir: data_dir.join("logs"),
+            crash_dir: data_dir.join("crashes"),
+            runtime_dir: data_dir.join("runtime"),
+            workspace_dir: data_dir.join("workspace"),
+            gateway_state_dir: data_dir.join("gateway-state"),
+            cache_dir: root.join("cache"),
+            data_dir,
+        }
+    }
+
+    /// Layout following the platform conventions
+    fn platform() -> Option<Self> {
+        let config_dir = dirs::config_dir()?.join(APP_DIR_NAME);
+        // May be the same as the config dir (e.g. on macOS)
+        let data_dir = dirs::data_local_dir()?.join(APP_DIR_NAME);
+        let cache_dir = dirs::cache_dir()
+            .map(|d| d.join(APP_DIR_NAME))
+            .unwrap_or_else(|| data_dir.join("cache"));
+        Some(Self {
+            config_dir,
+            logs_dir: data_dir.join("logs"),
+            crash_dir: data_dir.join("crashes"),
+            runtime_dir: data_dir.join("runtime"),
+            workspace_dir: data_dir.join("workspace"),
+            gateway_state_dir: data_dir.join("gateway-state"),
+            cache_dir,
+            data_dir,
+        })
+    }
+
+    /// `config.json`, which may not exist yet and neither may the config
+    /// dir: reading it then gives the defaults, and saving creates both
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.json")
+    }
+
+    /// The record of the running gateway (see `pid_file`), creating the
+    /// config dir if needed
+    pub fn gateway_pid_file(&self) -> io::Result<PathBuf> {
+        Ok(ensure_dir(&self.config_dir)?.join(crate::pid_file::FILE_NAME))
+    }
+
+    /// The lock of the running app (see `single_instance`), creating the
+    /// config dir if needed
+    pub fn instance_lock_file(&self) -> io::Result<PathBuf> {
+        Ok(ensure_dir(&self.config_dir)?.join(crate::single_instance::FILE_NAME))
+    }
+
+    /// The activity history, creating the logs dir if needed
+    pub fn activity_log_file(&self) -> io::Result<PathBuf> {
+        Ok(ensure_dir(&self.logs_dir)?.join("activity.json"))
+    }
+
+    /// The gateway status history, creating the logs dir if needed
+    pub fn status_history_file(&self) -> io::Result<PathBuf> {
+        Ok(ensure_dir(&self.logs_dir)?.join("status-history.jsonl"))
+    }
+
+    /// Top-level directories owned by the app, without duplicates or
+    /// directories nested inside another one, e.g. for deleting all app data
+    pub fn roots(&self) -> Vec<PathBuf> {
+        let all = [
+            &self.config_dir,
+            &self.data_dir,
+            &self.logs_dir,
+            &self.crash_dir,
+            &self.runtime_dir,
+            &self.workspace_dir,
+            &self.gateway_state_dir,
+            &self.cache_dir,
+        ];
+        let mut roots: Vec<PathBuf> = Vec::new();
+        for dir in all {
+            let covered = all.iter().any(|other| *other != dir && dir.starts_with(other));
+            if !covered && !roots.contains(dir) {
+                roots.push(dir.clone());
+            }
+        }
+        roots
+    }
+
+    /// Move files left behind by older versions into the current layout.
+    /// Files already present at the new location are never overwritten.
+    /// Returns the `(from, to)` pairs that were moved.
+    pub fn migrate_legacy_layout(&self) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+        // The activity log used to live directly in the data dir
+        let moves = [(
+            self.data_dir.join("activity.json"),
+            self.logs_dir.join("activity.json"),
+        )];
+
+        let mut moved = Vec::new();
+        for (from, to) in moves {
+            if !from.is_file() || to.exists() {
+                continue;
+            }
+            if let Some(parent) = to.parent() {
+                ensure_dir(parent)?;
+            }
+            if fs::rename(&from, &to).is_err() {
+                // rename fails across filesystems, fall back to copy + delete
+                fs::copy(&from, &to)?;
+                fs::remove_file(&from)?;
+            }
+            moved.push((from, to));
+        }
+        Ok(moved)
+    }
+}
+
+/// Create `dir` (and its parents) if it doesn't exist yet
+pub fn ensure_dir(dir: &Path) -> io::Result<&Path> {
+    fs::create_dir_all(dir)?;
+    Ok(dir)
+}
+
+/// Mode of files only their owner may read, like config.json
+pub const PRIVATE_FILE_MODE: u32 = 0o600;
+
+/// Mode of directories only their owner may list, like the config dir
+pub const PRIVATE_DIR_MODE: u32 = 0o700;
+
+/// Set `path` to `mode` when its group or others have any access. Returns
+/// the mode it had then, or None when it was private already.
+#[cfg(unix)]
+pub fn restrict_mode(path: &Path, mode: u32) -> io::Result<Option<u32>> {
+    use std::os::unix::fs::PermissionsExt;
+    let previous = fs::metadata(path)?.permissions().mode() & 0o777;
+    if previous & 0o077 == 0 {
+        return Ok(None);
+    }
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(Some(previous))
+}
+
+/// Access is inherited from the folder on Windows, so there is no mode to
+/// restrict
+#[cfg(not(unix))]
+pub fn restrict_mode(_path: &Path, _mode: u32) -> io::Result<Option<u32>> {
+    Ok(None)
+}
+
+/// Refuse to start the gateway with less than this free where it writes
+pub const HARD_MIN_FREE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Free space on volumes, behind a trait so tests can fake the filesystem
+pub trait DiskSpace: Send + Sync {
+    /// Bytes an unprivileged writer can use on the volume holding `path`,
+    /// which exists
+    fn available(&self, path: &Path) -> io::Result<u64>;
+}
+
+/// Asks the OS
+pub struct SystemDiskSpace;
+
+impl DiskSpace for SystemDiskSpace {
+    #[cfg(unix)]
+    fn available(&self, path: &Path) -> io::Result<u64> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        // SAFETY: statvfs only writes into `stat`, which outlives the call
+        unsafe {
+            let mut stat: libc::statvfs = std::mem::zeroed();
+            if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // The field types differ between platforms
+            #[allow(clippy::useless_conversion)]
+            let available = u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize));
+            Ok(available)
+        }
+    }
+
+    #[cfg(windows)]
+    fn available(&self, path: &Path) -> io::Result<u64> {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut available = 0u64;
+        // SAFETY: `wide` is NUL-terminated and the out pointer is valid;
+        // the totals we don't need may be null
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                wide.as_ptr(),
+                &mut available,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(available)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn available(&self, _path: &Path) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "free space is not available on this platform",
+        ))
+    }
+}
+
+/// Free bytes where `path` is or will be created, from its closest
+/// existing ancestor
+pub fn available_space(disk: &dyn DiskSpace, path: &Path) -> io::Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|dir| !dir.as_os_str().is_empty() && dir.exists())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no existing parent of {}", path.display()),
+            )
+        })?;
+    disk.available(existing)
+}
+
+/// Free space for one of the directories the app writes to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeSpace {
+    pub path: PathBuf,
+    /// None when the OS couldn't tell
+    pub available_bytes: Option<u64>,
+}
+
+/// Free space for each of `dirs`. A directory inside one listed before it
+/// is taken to be on the same volume and skipped.
+pub fn volume_space(disk: &dyn DiskSpace, dirs: &[&Path]) -> Vec<VolumeSpace> {
+    let mut volumes: Vec<VolumeSpace> = Vec::new();
+    for dir in dirs {
+        if volumes.iter().any(|volume| dir.starts_with(&volume.path)) {
+            continue;
+        }
+        volumes.push(VolumeSpace {
+            path: dir.to_path_buf(),
+            available_bytes: available_space(disk, dir).ok(),
+        });
+    }
+    volumes
+}
+
+/// Not enough room at `path` for a start or a write
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsufficientSpace {
+    pub path: PathBuf,
+    pub available: u64,
+    pub required: u64,
+}
+
+/// Room needed to copy `sources` (files or directories) to another
+/// volume: their size plus a tenth and 1 MB for filesystem overhead
+pub fn estimate_write_size(sources: &[PathBuf]) -> u64 {
+    let bytes: u64 = sources
+        .iter()
+        .map(|source| match fs::metadata(source) {
+            Ok(meta) if meta.is_dir() => crate::config::calculate_dir_size(source),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum();
+    bytes + bytes / 10 + 1024 * 1024
+}
+
+/// Check `dest` has room for `required` bytes before writing there. When
+/// the free space can't be determined the write is let through.
+pub fn ensure_space(
+    disk: &dyn DiskSpace,
+    dest: &Path,
+    required: u64,
+) -> Result<(), InsufficientSpace> {
+    match available_space(disk, dest) {
+        Ok(available) if available < required => Err(InsufficientSpace {
+            path: dest.to_path_buf(),
+            available,
+            required,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Resolve the app layout, mapping a missing home directory to an IO error
+pub fn app_paths() -> io::Result<AppPaths> {
+    AppPaths::resolve().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "Could not determine app directories")
+    })
+}
+
+/// Move legacy files into the current layout, logging what happened
+pub fn migrate_on_startup() {
+    let result = app_paths().and_then(|paths| paths.migrate_legacy_layout());
+    match result {
+        Ok(moved) => {
+            for (from, to) in moved {
+                println!("[paths] Moved {:?} to {:?}", from, to);
+            }
+        }
+        Err(e) => eprintln!("[paths] Failed to migrate app data layout: {}", e),
+    }
+}
+
+/// Open a directory in the platform file manager
+fn open_in_file_manager(dir: &Path) -> io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let program = "xdg-open";
+
+    Command::new(program).arg(dir).spawn()?;
+    Ok(())
+}
+
+// Tauri Commands
+
+#[tauri::command]
+pub fn get_app_paths() -> Result<AppPaths, AppError> {
+    AppPaths::resolve().ok_or(AppError::DataDirUnavailable)
+}
+
+/// Where the gateway log files and the app's other logs are kept
+#[tauri::command]
+pub fn get_log_directory() -> Result<String, AppError> {
+    let paths = AppPaths::resolve().ok_or(AppError::DataDirUnavailable)?;
+    Ok(paths.logs_dir.display().to_string())
+}
+
+#[tauri::command]
+pub fn open_logs_dir() -> Result<(), AppError> {
+    let paths = AppPaths::resolve().ok_or(AppError::DataDirUnavailable)?;
+    ensure_dir(&paths.logs_dir)
+        .and_then(open_in_file_manager)
+        .map_err(|e| AppError::OpenLogsDirFailed { detail: e.to_string() })
+}
@@ -0,0 +1,169 @@
+//! Gateway PID File
+//!
+//! A gateway outlives an app that crashes or is force quit, keeping its
+//! port and the API key it was started with, so the next launch would
+//! either fail with `port_in_use` or talk to the stale gateway. Every
+//! spawned gateway is therefore recorded in `gateway.pid.json` in the
+//! config dir - pid, port, token and start time - and the record is
+//! removed again when it is stopped.
+//!
+//! A record still there at launch names a leftover gateway, which `reap`
+//! kills before anything starts. By then the pid may belong to something
+//! else, so the process is only killed while its command line carries the
+//! recorded token, which every gateway gets with `--token`. The file is
+//! private to the user, like the token in it.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name of the file in the config dir
+pub const FILE_NAME: &str = "gateway.pid.json";
+
+/// A spawned gateway
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayRecord {
+    pub pid: u32,
+    pub port: u16,
+    pub token: String,
+    /// Unix timestamp (ms)
+    pub started_at: i64,
+}
+
+/// Where the record of the running gateway is kept
+#[derive(Debug, Clone)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record `record`, replacing whatever was recorded
+    pub fn write(&self, record: &GatewayRecord) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&self.path)?;
+        file.write_all(serde_json::to_string(record)?.as_bytes())
+    }
+
+    /// None when there is no record or it can't be read
+    pub fn read(&self) -> Option<GatewayRecord> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Succeeds when there was no record
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What `reap` found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Leftover {
+    /// Nothing was recorded
+    None,
+    /// The recorded gateway was still running and is now killed
+    Killed(GatewayRecord),
+    /// The recorded gateway had already exited, or its pid now belongs to
+    /// another process, which was left alone
+    Gone(GatewayRecord),
+}
+
+/// Kill the gateway `pid_file` records if it is still running, then
+/// remove the record
+pub fn reap(pid_file: &PidFile) -> Leftover {
+    let Some(record) = pid_file.read() else {
+        let _ = pid_file.clear();
+        return Leftover::None;
+    };
+    let leftover = if is_gateway(&record) {
+        println!(
+            "[openclaw] Killing gateway {} left running on port {}",
+            record.pid, record.port
+        );
+        kill_tree(record.pid);
+        Leftover::Killed(record)
+    } else {
+        Leftover::Gone(record)
+    };
+    if let Err(e) = pid_file.clear() {
+        eprintln!("[openclaw] Failed to remove {}: {}", pid_file.path().display(), e);
+    }
+    leftover
+}
+
+/// Whether `record.pid` is still the recorded gateway
+fn is_gateway(record: &GatewayRecord) -> bool {
+    !record.token.is_empty()
+        && command_line(record.pid).is_some_and(|line| line.contains(&record.token))
+}
+
+/// Command line of the running process `pid`
+#[cfg(unix)]
+pub fn command_line(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-ww", "-o", "command=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !line.is_empty()).then_some(line)
+}
+
+#[cfg(windows)]
+pub fn command_line(pid: u32) -> Option<String> {
+    let filter = format!("ProcessId={}", pid);
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "(Get-CimInstance Win32_Process -Filter '{}').CommandLine",
+                filter
+            ),
+        ])
+        .output()
+        .ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !line.is_empty()).then_some(line)
+}
+
+/// Kill `pid` and its process group, which it leads when we spawned it
+#[cfg(unix)]
+fn kill_tree(pid: u32) {
+    let pid = pid as i32;
+    unsafe {
+        if libc::getpgid(pid) == pid {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+        libc::kill(pid, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill_tree(pid: u32) {
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .output();
+}
@@ -0,0 +1,210 @@
+//! Gateway Port Tracking
+//!
+//! The port in config.json and the port the gateway actually serves on can
+//! diverge: the port may have been picked automatically, the gateway's own
+//! config can override `--port` (it announces the real one in its startup
+//! line), an already running gateway may have been adopted, or the port was
+//! changed in config.json after the gateway started. `GatewayStatus`
+//! carries both ports and a `PortMismatch` explaining the difference, so
+//! the settings screen never shows a port nothing is listening on.
+//!
+//! A configured port the gateway can never use - a privileged one, or one
+//! a service holds start after start - fails the start with a free port to
+//! move to, found by `suggest` as near the default as it can, which
+//! `apply_suggested_port` saves and restarts on.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{IpAddr, TcpListener};
+
+use crate::error::AppError;
+use crate::settings::MIN_PORT;
+
+/// `gatewayPort` when unset
+pub const DEFAULT_PORT: u16 = 18789;
+
+/// How far from `DEFAULT_PORT` `suggest` looks, each way
+pub const SUGGEST_RANGE: u16 = 100;
+
+/// Ports other software commonly listens on, with what it is
+pub const KNOWN_SERVICES: &[(u16, &str)] = &[
+    (3000, "development web servers"),
+    (3306, "MySQL"),
+    (5000, "AirPlay Receiver on macOS"),
+    (5432, "PostgreSQL"),
+    (5900, "screen sharing (VNC)"),
+    (6379, "Redis"),
+    (7000, "AirPlay Receiver on macOS"),
+    (8000, "development web servers"),
+    (8080, "web proxies and development servers"),
+    (8443, "HTTPS alternates"),
+    (9000, "PHP-FPM and development tools"),
+    (27017, "MongoDB"),
+];
+
+/// Where the port of the running gateway came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PortSource {
+    /// `gatewayPort` from config.json at start time
+    Configured,
+    /// Picked automatically because the configured port was taken
+    AutoSelected,
+    /// The gateway reported a different port in its startup line
+    GatewayOverride,
+    /// An already running gateway was adopted instead of spawning one
+    Adopted,
+}
+
+/// Why the effective port differs from the configured one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PortMismatch {
+    /// `PortSource` of the effective port
+    pub source: PortSource,
+    pub message: String,
+    /// Whether restarting the gateway would move it to the configured port
+    pub restart_resolves: bool,
+}
+
+/// Explain a difference between `configured` and `effective`, or `None`
+/// when they agree
+pub fn port_mismatch(configured: u16, effective: u16, source: PortSource) -> Option<PortMismatch> {
+    if configured == effective {
+        return None;
+    }
+    let (message, restart_resolves) = match source {
+        PortSource::Configured => (
+            format!(
+                "The gateway was started on port {}; the new port {} takes effect after a restart.",
+                effective, configured
+            ),
+            true,
+        ),
+        PortSource::AutoSelected => (
+            format!(
+                "Port {} was in use, so the gateway was started on port {} instead.",
+                configured, effective
+            ),
+            false,
+        ),
+        PortSource::GatewayOverride => (
+            format!(
+                "The gateway's own configuration overrides port {}; it is listening on port {}.",
+                configured, effective
+            ),
+            false,
+        ),
+        PortSource::Adopted => (
+            format!(
+                "An already running gateway on port {} was adopted; restart it to use port {}.",
+                effective, configured
+            ),
+            true,
+        ),
+    };
+    Some(PortMismatch {
+        source,
+        message,
+        restart_resolves,
+    })
+}
+
+/// Whether `port` can be bound on `host`, as the gateway would
+pub fn is_free(host: IpAddr, port: u16) -> bool {
+    TcpListener::bind((host, port)).is_ok()
+}
+
+/// Whether the gateway could listen on a port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortAvailability {
+    Free,
+    InUse,
+    /// Privileged, which the app never uses, or refused by the OS like a
+    /// port Windows reserved
+    NotPermitted,
+}
+
+/// How `port` is on `host`, as the gateway would bind it
+pub fn availability(host: IpAddr, port: u16) -> PortAvailability {
+    if port < MIN_PORT {
+        return PortAvailability::NotPermitted;
+    }
+    match TcpListener::bind((host, port)) {
+        Ok(_) => PortAvailability::Free,
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => PortAvailability::NotPermitted,
+        Err(_) => PortAvailability::InUse,
+    }
+}
+
+/// The service `port` usually belongs to, when it is one of
+/// `KNOWN_SERVICES`
+pub fn known_service(port: u16) -> Option<&'static str> {
+    KNOWN_SERVICES
+        .iter()
+        .find(|(known, _)| *known == port)
+        .map(|(_, service)| *service)
+}
+
+/// What to say about choosing `port`, when it is a known service's
+pub fn conflict_warning(port: u16) -> Option<String> {
+    known_service(port).map(|service| {
+        format!(
+            "Port {} is commonly used by {}; while that runs the gateway can't start.",
+            port, service
+        )
+    })
+}
+
+/// A port free on `host` other than `avoid`, the one nearest
+/// `DEFAULT_PORT` and not a known service's. None when all within
+/// `SUGGEST_RANGE` are taken.
+pub fn suggest(host: IpAddr, avoid: u16) -> Option<u16> {
+    std::iter::once(DEFAULT_PORT)
+        .chain((1..=SUGGEST_RANGE).flat_map(|d| [DEFAULT_PORT + d, DEFAULT_PORT - d]))
+        .filter(|port| *port != avoid && known_service(*port).is_none())
+        .find(|port| availability(host, *port) == PortAvailability::Free)
+}
+
+/// The error for starting on `port`, which isn't permitted: with a port to
+/// move to, or `invalid_port` when there is none
+pub fn not_permitted(host: IpAddr, port: u16) -> AppError {
+    match suggest(host, port) {
+        Some(suggested) => AppError::PortNotPermitted {
+            port: port.to_string(),
+            suggested: suggested.to_string(),
+        },
+        None => AppError::InvalidPort {
+            port: port.to_string(),
+        },
+    }
+}
+
+/// `port` if the gateway can move to it on `host` right now
+pub fn check_usable(host: IpAddr, port: u16) -> Result<u16, AppError> {
+    match availability(host, port) {
+        PortAvailability::Free => Ok(port),
+        PortAvailability::InUse => Err(AppError::PortInUse {
+            port: port.to_string(),
+        }),
+        PortAvailability::NotPermitted => Err(AppError::InvalidPort {
+            port: port.to_string(),
+        }),
+    }
+}
+
+/// An ephemeral port on `host` that was free a moment ago
+pub fn pick_free(host: IpAddr) -> Option<u16> {
+    let listener = TcpListener::bind((host, 0)).ok()?;
+    listener.local_addr().ok().map(|address| address.port())
+}
+
+/// The port from a gateway startup line such as
+/// `[gateway] listening on ws://127.0.0.1:18789`
+pub fn parse_listening_port(line: &str) -> Option<u16> {
+    let rest = &line[line.find("listening on ")? + "listening on ".len()..];
+    let address = rest.split_whitespace().next()?;
+    let address = address.split_once("://").map_or(address, |(_, address)| address);
+    let host_port = address.split('/').next()?;
+    host_port.rsplit_once(':')?.1.parse().ok()
+}
@@ -0,0 +1,794 @@
+//! Gateway Process Abstraction
+//!
+//! `SidecarManager` spawns and supervises the gateway through the
+//! `ProcessLauncher` and `GatewayProcess` traits instead of using
+//! `std::process` directly, so its state machine can be exercised in tests
+//! with a mock launcher (spawn failures, `try_wait` errors, failing kills)
+//! without creating real processes.
+//!
+//! On Windows the gateway is started through an npm `.cmd` shim, so killing
+//! the direct child leaves the real node process running and holding the
+//! port. Every spawned gateway is therefore placed in a Job Object with
+//! kill-on-close set, which takes the whole tree down on `kill` and also
+//! when the app itself exits, crashes or is killed. The gateway is spawned
+//! suspended and only resumed once it is in the job, so nothing it starts
+//! can slip out. The handle lives as long as the manager holds the
+//! process. The manual test plan is in
+//! `apps/desktop/docs/windows-process-termination.md`.
+//!
+//! On Unix the gateway leads its own process group, and stopping it
+//! signals the whole group, so node's workers go with the CLI. A group
+//! whose process is dropped without being stopped, e.g. while a panic
+//! unwinds, is killed the same way, unless the process was already waited
+//! for: its group id may have been reused. What a hard crash of the app
+//! leaves behind is swept up on the next launch.
+//!
+//! A gateway can be paused to free the CPU while it isn't used: SIGSTOP to
+//! its process group, SIGCONT to resume it. A stopped process isn't an
+//! exited one, `try_wait` keeps returning None for it. Windows has no
+//! supported way to do the same, so there it is refused.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// Everything needed to spawn the gateway process
+#[derive(Debug, Clone)]
+pub struct LaunchSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Variables set on top of the inherited environment
+    pub env: Vec<(String, String)>,
+    /// Start from an empty environment, so `env` is all the process gets
+    pub clean_env: bool,
+    /// Run the process (and everything it spawns) at reduced CPU priority
+    pub low_priority: bool,
+    /// Working directory; inherited from the app when `None`
+    pub cwd: Option<PathBuf>,
+    /// Account to run the process as (Unix only). The spawn fails when the
+    /// switch isn't possible.
+    pub user: Option<ProcessUser>,
+}
+
+/// Numeric ids of the account a process runs as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessUser {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// How a process exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessExit {
+    /// Exit code, if the process exited normally
+    pub code: Option<i32>,
+    /// Terminating signal on Unix, if the process was killed by one
+    pub signal: Option<i32>,
+}
+
+impl ProcessExit {
+    pub fn from_code(code: i32) -> Self {
+        Self {
+            code: Some(code),
+            signal: None,
+        }
+    }
+}
+
+impl From<ExitStatus> for ProcessExit {
+    fn from(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.signal()
+        };
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self {
+            code: status.code(),
+            signal,
+        }
+    }
+}
+
+impl fmt::Display for ProcessExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit status: {}", code),
+            (None, Some(signal)) => write!(f, "signal: {}", signal),
+            (None, None) => write!(f, "unknown exit status"),
+        }
+    }
+}
+
+/// A running gateway process
+pub trait GatewayProcess: Send {
+    fn id(&self) -> u32;
+
+    /// Check whether the process has exited, without blocking
+    fn try_wait(&mut self) -> io::Result<Option<ProcessExit>>;
+
+    /// Terminate the process and everything it spawned, then reap it
+    fn kill(&mut self) -> io::Result<()>;
+
+    /// Ask the process and everything it spawned to shut down, kill them
+    /// if they haven't within `grace`, then reap the process
+    fn stop(&mut self, _grace: Duration) -> io::Result<StopKind> {
+        self.kill().map(|()| StopKind::Forced)
+    }
+
+    /// Block until the process exits
+    fn wait(&mut self) -> io::Result<ProcessExit>;
+
+    /// Keep the process and everything it spawned from running until
+    /// `resume`, without ending them
+    fn suspend(&mut self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "processes can't be suspended here",
+        ))
+    }
+
+    /// Let a suspended process run again
+    fn resume(&mut self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "processes can't be suspended here",
+        ))
+    }
+
+    /// Take the process's stdout stream, if it hasn't been taken already
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// Take the process's stderr stream, if it hasn't been taken already
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// Scheduling priority the process is actually running at
+    fn priority(&self) -> Option<ProcessPriority> {
+        None
+    }
+
+    /// Whether the app started it and so stops it, see `adopted`
+    fn managed(&self) -> bool {
+        true
+    }
+}
+
+/// Effective scheduling priority of a process
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessPriority {
+    /// Whether the process runs below normal priority
+    pub lowered: bool,
+    /// Platform detail, e.g. "nice 10" or "below normal"
+    pub detail: String,
+}
+
+/// How a gateway process was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopKind {
+    /// It exited on its own when asked to
+    Graceful,
+    /// It had to be killed
+    Forced,
+    /// It wasn't the app's to stop and still runs, see `adopted`
+    Detached,
+}
+
+/// How long `kill` gives a process to shut down before killing it
+const KILL_GRACE: Duration = Duration::from_millis(500);
+
+/// One step of stopping a gateway process tree on Windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowsStopStep {
+    /// Send CTRL_BREAK to the gateway's process group and give it a moment
+    /// to shut down gracefully
+    CtrlBreak,
+    /// Terminate every process in the gateway's job object
+    TerminateJob,
+    /// `taskkill /PID <pid> /T /F`
+    TaskKill,
+}
+
+/// Decide how to stop a gateway on Windows.
+///
+/// The graceful phase only works when we share a console with the child,
+/// which the release build (no console window) usually doesn't. The job
+/// object is preferred for the hard stop; processes without one (e.g. the
+/// job couldn't be created or assigned) fall back to `taskkill /T`.
+pub fn windows_stop_steps(has_job: bool, shares_console: bool) -> Vec<WindowsStopStep> {
+    let mut steps = Vec::new();
+    if shares_console {
+        steps.push(WindowsStopStep::CtrlBreak);
+    }
+    steps.push(if has_job {
+        WindowsStopStep::TerminateJob
+    } else {
+        WindowsStopStep::TaskKill
+    });
+    steps
+}
+
+/// Spawns gateway processes
+pub trait ProcessLauncher: Send + Sync {
+    fn spawn(&self, spec: &LaunchSpec) -> io::Result<Box<dyn GatewayProcess>>;
+}
+
+/// Launches real OS processes via `std::process`
+pub struct SystemLauncher;
+
+impl ProcessLauncher for SystemLauncher {
+    fn spawn(&self, spec: &LaunchSpec) -> io::Result<Box<dyn GatewayProcess>> {
+        let mut cmd = Command::new(&spec.program);
+        if spec.clean_env {
+            cmd.env_clear();
+        }
+        cmd.args(&spec.args)
+            .envs(spec.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(ref cwd) = spec.cwd {
+            cmd.current_dir(cwd);
+        }
+
+        // On Unix, create a new process group so we can kill all children
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+
+        // On Windows, give the gateway its own process group so CTRL_BREAK
+        // can be sent to it without hitting us, and keep it suspended until
+        // it is in its job
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            cmd.creation_flags(job::SPAWN_FLAGS);
+        }
+
+        // After the creation flags above, which this replaces on Windows
+        if spec.low_priority {
+            priority::lower_at_spawn(&mut cmd);
+        }
+
+        // Last, so the steps above still run with our own privileges
+        if let Some(user) = spec.user {
+            credentials::switch_at_spawn(&mut cmd, user)?;
+        }
+
+        let child = cmd.spawn()?;
+
+        #[cfg(windows)]
+        let (child, job) = job::contain(child)?;
+
+        Ok(Box::new(SystemProcess {
+            child,
+            reaped: false,
+            #[cfg(windows)]
+            job,
+        }))
+    }
+}
+
+struct SystemProcess {
+    child: Child,
+    /// Whether the process was waited for. Its pid, and the process group
+    /// with it, may be another process's since, so neither is signalled.
+    reaped: bool,
+    /// Job object holding the process tree. Dropping it kills the tree.
+    #[cfg(windows)]
+    job: Option<job::Job>,
+}
+
+/// Kill-on-drop for the process group, like the job object on Windows
+#[cfg(unix)]
+impl Drop for SystemProcess {
+    fn drop(&mut self) {
+        if !self.reaped {
+            self.force_kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+impl SystemProcess {
+    /// Ask the process tree to shut down: SIGTERM to its process group on
+    /// Unix, CTRL_BREAK on Windows when we share its console. False when
+    /// there was no way to ask.
+    fn request_shutdown(&self) -> bool {
+        let pid = self.child.id();
+
+        #[cfg(unix)]
+        {
+            unsafe { libc::kill(-(pid as i32), libc::SIGTERM) == 0 }
+        }
+
+        #[cfg(windows)]
+        {
+            windows_stop_steps(self.job.is_some(), job::has_console())
+                .contains(&WindowsStopStep::CtrlBreak)
+                && job::ctrl_break(pid)
+        }
+    }
+
+    /// Kill the process tree outright
+    fn force_kill(&self) {
+        let pid = self.child.id();
+
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGKILL);
+        }
+
+        #[cfg(windows)]
+        for step in windows_stop_steps(self.job.is_some(), job::has_console()) {
+            match step {
+                WindowsStopStep::CtrlBreak => {}
+                WindowsStopStep::TerminateJob => {
+                    let terminated = self.job.as_ref().is_some_and(|job| job.terminate());
+                    if !terminated {
+                        taskkill_tree(pid);
+                    }
+                }
+                WindowsStopStep::TaskKill => taskkill_tree(pid),
+            }
+        }
+    }
+
+    /// Send `signal` to the process group
+    #[cfg(unix)]
+    fn signal_group(&self, signal: i32) -> io::Result<()> {
+        if self.reaped {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "the process has exited"));
+        }
+        match unsafe { libc::kill(-(self.child.id() as i32), signal) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Whether the process exits on its own within `grace`
+    fn exits_within(&mut self, grace: Duration) -> bool {
+        let deadline = Instant::now() + grace;
+        loop {
+            if !matches!(self.try_wait(), Ok(None)) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl GatewayProcess for SystemProcess {
+    fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ProcessExit>> {
+        let exit = self.child.try_wait()?;
+        self.reaped |= exit.is_some();
+        Ok(exit.map(ProcessExit::from))
+    }
+
+    /// Kill the process and all its children, after a brief chance to
+    /// shut down
+    fn kill(&mut self) -> io::Result<()> {
+        self.stop(KILL_GRACE).map(|_| ())
+    }
+
+    fn stop(&mut self, grace: Duration) -> io::Result<StopKind> {
+        // It exited on its own before
+        if self.reaped {
+            return Ok(StopKind::Graceful);
+        }
+        let kind = if self.request_shutdown() && self.exits_within(grace) {
+            StopKind::Graceful
+        } else {
+            StopKind::Forced
+        };
+        // Also takes down anything the gateway left running. Once it was
+        // reaped above its pid, and the group with it, may be another
+        // process's on Unix, and the rest of the group had the same
+        // SIGTERM. On Windows the job and our handle to the process keep
+        // them ours.
+        if !self.reaped || cfg!(windows) {
+            self.force_kill();
+        }
+
+        // Also kill via the standard method. This fails harmlessly if the
+        // group kill above already took the process down.
+        let _ = self.child.kill();
+        self.wait()?;
+        Ok(kind)
+    }
+
+    fn wait(&mut self) -> io::Result<ProcessExit> {
+        let status = self.child.wait()?;
+        self.reaped = true;
+        Ok(status.into())
+    }
+
+    #[cfg(unix)]
+    fn suspend(&mut self) -> io::Result<()> {
+        self.signal_group(libc::SIGSTOP)
+    }
+
+    #[cfg(unix)]
+    fn resume(&mut self) -> io::Result<()> {
+        self.signal_group(libc::SIGCONT)
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child
+            .stdout
+            .take()
+            .map(|s| Box::new(s) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child
+            .stderr
+            .take()
+            .map(|s| Box::new(s) as Box<dyn Read + Send>)
+    }
+
+    fn priority(&self) -> Option<ProcessPriority> {
+        priority::query(self.child.id())
+    }
+}
+
+/// Niceness used for low-priority gateways on Unix
+pub const LOW_PRIORITY_NICE: i32 = 10;
+
+/// Niceness to run a low-priority child at, given our own. Never raises the
+/// priority: an unprivileged process can't lower its niceness.
+pub fn low_priority_nice(current: i32) -> i32 {
+    current.max(LOW_PRIORITY_NICE)
+}
+
+/// Lower the priority of an already running process, e.g. one we adopted
+/// rather than spawned. Fails without the permissions to do so.
+pub fn lower_process_priority(pid: u32) -> io::Result<()> {
+    priority::lower(pid)
+}
+
+/// Effective priority of any process
+pub fn process_priority(pid: u32) -> Option<ProcessPriority> {
+    priority::query(pid)
+}
+
+#[cfg(unix)]
+mod priority {
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    use super::{low_priority_nice, ProcessPriority};
+
+    /// Make `cmd` set its niceness between fork and exec, so the gateway and
+    /// everything it spawns inherit it. Errors are ignored in the child (it
+    /// can't report them); callers check the result with `query`.
+    pub fn lower_at_spawn(cmd: &mut Command) {
+        let nice = low_priority_nice(nice_of(0).unwrap_or(0));
+        // SAFETY: setpriority is a plain syscall and async-signal-safe; the
+        // closure doesn't allocate or touch any shared state.
+        unsafe {
+            cmd.pre_exec(move || {
+                libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                Ok(())
+            });
+        }
+    }
+
+    pub fn lower(pid: u32) -> io::Result<()> {
+        let nice = low_priority_nice(nice_of(pid)?);
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn query(pid: u32) -> Option<ProcessPriority> {
+        let nice = nice_of(pid).ok()?;
+        Some(ProcessPriority {
+            lowered: nice > 0,
+            detail: format!("nice {}", nice),
+        })
+    }
+
+    /// getpriority returns -1 both as a valid niceness and on error, so errno
+    /// has to be cleared first to tell them apart. PID 0 means ourselves.
+    fn nice_of(pid: u32) -> io::Result<i32> {
+        unsafe {
+            clear_errno();
+            let nice = libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t);
+            if nice == -1 {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error().unwrap_or(0) != 0 {
+                    return Err(err);
+                }
+            }
+            Ok(nice)
+        }
+    }
+
+    unsafe fn clear_errno() {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            *libc::__errno_location() = 0;
+        }
+        #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+        {
+            *libc::__error() = 0;
+        }
+    }
+}
+
+#[cfg(unix)]
+mod credentials {
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    use super::ProcessUser;
+
+    /// Make `cmd` drop to `user` between fork and exec: supplementary groups
+    /// first (down to the primary group), then the gid, then the uid, since
+    /// each needs the privileges the next one gives up. A failing step fails
+    /// the spawn, so the gateway never runs as us by accident.
+    pub fn switch_at_spawn(cmd: &mut Command, user: ProcessUser) -> io::Result<()> {
+        let groups = [user.gid as libc::gid_t];
+        // SAFETY: setgroups, setgid and setuid are async-signal-safe
+        // syscalls, and the closure only reads the ids copied into it;
+        // `last_os_error` reads errno without allocating.
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::setgroups(1, groups.as_ptr()) != 0
+                    || libc::setgid(user.gid as libc::gid_t) != 0
+                    || libc::setuid(user.uid as libc::uid_t) != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod credentials {
+    use std::io;
+    use std::process::Command;
+
+    use super::ProcessUser;
+
+    pub fn switch_at_spawn(_cmd: &mut Command, _user: ProcessUser) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "running as another user is only supported on Unix",
+        ))
+    }
+}
+
+#[cfg(windows)]
+mod priority {
+    use std::io;
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        GetPriorityClass, OpenProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS,
+        IDLE_PRIORITY_CLASS, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SET_INFORMATION,
+    };
+
+    use super::ProcessPriority;
+
+    /// Start the process in BELOW_NORMAL_PRIORITY_CLASS. Processes it
+    /// spawns inherit the class.
+    pub fn lower_at_spawn(cmd: &mut Command) {
+        // creation_flags replaces the flags, so keep the ones set at spawn
+        cmd.creation_flags(super::job::SPAWN_FLAGS | BELOW_NORMAL_PRIORITY_CLASS);
+    }
+
+    pub fn lower(pid: u32) -> io::Result<()> {
+        unsafe {
+            let process = OpenProcess(PROCESS_SET_INFORMATION, 0, pid);
+            if process.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let ok = SetPriorityClass(process, BELOW_NORMAL_PRIORITY_CLASS) != 0;
+            let err = io::Error::last_os_error();
+            CloseHandle(process);
+            if ok {
+                Ok(())
+            } else {
+                Err(err)
+            }
+        }
+    }
+
+    pub fn query(pid: u32) -> Option<ProcessPriority> {
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                return None;
+            }
+            let class = GetPriorityClass(process);
+            CloseHandle(process);
+            let (lowered, detail) = match class {
+                0 => return None,
+                BELOW_NORMAL_PRIORITY_CLASS => (true, "below normal"),
+                IDLE_PRIORITY_CLASS => (true, "idle"),
+                _ => (false, "normal or higher"),
+            };
+            Some(ProcessPriority {
+                lowered,
+                detail: detail.to_string(),
+            })
+        }
+    }
+}
+
+/// Kill a process and its children with `taskkill /T`
+#[cfg(windows)]
+fn taskkill_tree(pid: u32) {
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .output();
+}
+
+#[cfg(windows)]
+mod job {
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Console::{
+        GenerateConsoleCtrlEvent, GetConsoleWindow, CTRL_BREAK_EVENT,
+    };
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenThread, ResumeThread, CREATE_NEW_PROCESS_GROUP, CREATE_SUSPENDED,
+        PROCESS_CREATION_FLAGS, THREAD_SUSPEND_RESUME,
+    };
+
+    /// Creation flags every gateway is spawned with: its own process group,
+    /// and suspended so it can be put in its job before it runs
+    pub const SPAWN_FLAGS: PROCESS_CREATION_FLAGS = CREATE_NEW_PROCESS_GROUP | CREATE_SUSPENDED;
+
+    /// A kill-on-close job object owning a gateway process tree
+    pub struct Job(HANDLE);
+
+    // The handle is only used through &self/&mut self and closed once on drop
+    unsafe impl Send for Job {}
+
+    impl Job {
+        /// Create a kill-on-close job and assign `child` to it
+        pub fn for_child(child: &Child) -> io::Result<Self> {
+            unsafe {
+                let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if handle.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let job = Job(handle);
+
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                if SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const std::ffi::c_void,
+                    std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                ) == 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+
+                if AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                Ok(job)
+            }
+        }
+
+        /// Terminate every process in the job, returning whether it worked
+        pub fn terminate(&self) -> bool {
+            unsafe { TerminateJobObject(self.0, 1) != 0 }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    /// Put a child spawned with `SPAWN_FLAGS` in a new job, then let it
+    /// run. It hasn't executed anything yet, so nothing it spawns can
+    /// escape the job. Without a job the child still runs and stopping
+    /// falls back to taskkill; if it can't be resumed it is killed.
+    pub fn contain(mut child: Child) -> io::Result<(Child, Option<Job>)> {
+        let job = match Job::for_child(&child) {
+            Ok(job) => Some(job),
+            Err(e) => {
+                eprintln!("[openclaw] Failed to create job object, falling back to taskkill: {}", e);
+                None
+            }
+        };
+        if let Err(e) = resume(&child) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+        Ok((child, job))
+    }
+
+    /// Resume a child spawned with `SPAWN_FLAGS`. `std::process` doesn't
+    /// hand out the primary thread's handle, so its threads are found in a
+    /// ToolHelp snapshot; a suspended process only has the one.
+    fn resume(child: &Child) -> io::Result<()> {
+        let pid = child.id();
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut resumed = false;
+            let mut entry: THREADENTRY32 = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+            let mut more = Thread32First(snapshot, &mut entry) != 0;
+            while more {
+                if entry.th32OwnerProcessID == pid {
+                    let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                    if !thread.is_null() {
+                        resumed |= ResumeThread(thread) != u32::MAX;
+                        CloseHandle(thread);
+                    }
+                }
+                more = Thread32Next(snapshot, &mut entry) != 0;
+            }
+            CloseHandle(snapshot);
+
+            if resumed {
+                Ok(())
+            } else {
+                Err(io::Error::other("could not resume the gateway's main thread"))
+            }
+        }
+    }
+
+    /// Whether this process is attached to a console, which is required
+    /// for `GenerateConsoleCtrlEvent` to reach the gateway
+    pub fn has_console() -> bool {
+        unsafe { !GetConsoleWindow().is_null() }
+    }
+
+    /// Send CTRL_BREAK to the process group led by `pid`
+    pub fn ctrl_break(pid: u32) -> bool {
+        unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) != 0 }
+    }
+}
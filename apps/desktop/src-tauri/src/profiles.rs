@@ -0,0 +1,340 @@
+//! Gateway Profiles
+//!
+//! A profile is a named set of the settings people switch between, such
+//! as a personal and a work key: the API key, `gatewayPort` and
+//! `gatewayExtraArgs`. The active profile's settings are the usual
+//! top-level fields, so everything that reads them keeps working and a
+//! config from before profiles is simply the `default` profile. The others
+//! wait in `profiles`, with their keys in the keychain under
+//! `keychain::profile_key_account`.
+//!
+//! Switching swaps the two: the top-level settings are saved as the old
+//! profile and the new one's are moved in, key included. A running
+//! gateway is restarted with them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, State};
+use thiserror::Error;
+
+use crate::config::{self, save_config, Config, ProfileConfig};
+use crate::error::AppError;
+use crate::gateway_options;
+use crate::keychain::{self, KeychainError, SecretStore};
+use crate::metrics::AppMetrics;
+use crate::sidecar::{SidecarManager, StartResult};
+
+/// Name of the profile a config without `activeProfile` is in
+pub const DEFAULT_PROFILE: &str = "default";
+
+pub const MAX_NAME_LEN: usize = 64;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProfileError {
+    #[error("There is no profile named {0}")]
+    Unknown(String),
+    #[error("A profile named {0} already exists")]
+    Exists(String),
+    #[error("{0:?} is not a profile name; use letters, digits, - and _")]
+    InvalidName(String),
+    #[error("{0} is the active profile")]
+    Active(String),
+    #[error(transparent)]
+    Keychain(#[from] KeychainError),
+}
+
+/// A profile as `list_profiles` returns it, without its key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInfo {
+    pub name: String,
+    pub active: bool,
+    pub has_api_key: bool,
+    pub gateway_port: u16,
+    pub gateway_extra_args: Vec<String>,
+}
+
+/// Check `name` can be a profile name
+pub fn validate_name(name: &str) -> Result<(), ProfileError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ProfileError::InvalidName(name.to_string()))
+    }
+}
+
+/// Name of the profile in use
+pub fn active(config: &Config) -> &str {
+    config.active_profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+}
+
+/// Every profile, the active one included, by name
+pub fn list(config: &Config) -> Vec<ProfileInfo> {
+    let current = ProfileInfo {
+        name: active(config).to_string(),
+        active: true,
+        has_api_key: config.anthropic_api_key.is_some(),
+        gateway_port: config.gateway_port,
+        gateway_extra_args: config.gateway_extra_args.clone(),
+    };
+    let mut profiles: Vec<ProfileInfo> = config
+        .profiles
+        .iter()
+        .filter(|(name, _)| *name != &current.name)
+        .map(|(name, profile)| ProfileInfo {
+            name: name.clone(),
+            active: false,
+            has_api_key: profile.api_key_in_keychain || profile.api_key.is_some(),
+            gateway_port: profile.gateway_port,
+            gateway_extra_args: profile.gateway_extra_args.clone(),
+        })
+        .collect();
+    profiles.push(current);
+    profiles.sort_by(|a, b| a.name.cmp(&b.name));
+    profiles
+}
+
+/// Keep `key` for profile `name` in `store`, or in `profile` with
+/// `storeApiKeyInFile`
+fn store_key(
+    config: &Config,
+    name: &str,
+    profile: &mut ProfileConfig,
+    key: Option<String>,
+    store: &dyn SecretStore,
+) -> Result<(), KeychainError> {
+    match key {
+        Some(key) if !config.store_api_key_in_file => {
+            store.set(&keychain::profile_key_account(name), &key)?;
+            profile.api_key = None;
+            profile.api_key_in_keychain = true;
+        }
+        key => {
+            profile.api_key = key;
+            profile.api_key_in_keychain = false;
+        }
+    }
+    Ok(())
+}
+
+/// Add profile `name`, with the active profile's port and arguments
+/// unless given. Save afterwards.
+pub fn create(
+    config: &mut Config,
+    name: &str,
+    gateway_port: Option<u16>,
+    gateway_extra_args: Option<Vec<String>>,
+    api_key: Option<String>,
+    store: &dyn SecretStore,
+) -> Result<(), AppError> {
+    validate_name(name)?;
+    if name == active(config) || config.profiles.contains_key(name) {
+        return Err(ProfileError::Exists(name.to_string()).into());
+    }
+    let gateway_port = gateway_port.unwrap_or(config.gateway_port);
+    if gateway_port == 0 {
+        return Err(AppError::InvalidPort {
+            port: gateway_port.to_string(),
+        });
+    }
+    let gateway_extra_args =
+        gateway_extra_args.unwrap_or_else(|| config.gateway_extra_args.clone());
+    gateway_options::validate(&gateway_extra_args, &BTreeMap::new())?;
+
+    let mut profile = ProfileConfig {
+        api_key: None,
+        api_key_in_keychain: false,
+        gateway_port,
+        gateway_extra_args,
+    };
+    store_key(config, name, &mut profile, api_key, store).map_err(ProfileError::from)?;
+    config.profiles.insert(name.to_string(), profile);
+    Ok(())
+}
+
+/// Remove profile `name` and its key, which can't be the active one. Save
+/// afterwards.
+pub fn delete(
+    config: &mut Config,
+    name: &str,
+    store: &dyn SecretStore,
+) -> Result<(), ProfileError> {
+    if name == active(config) {
+        return Err(ProfileError::Active(name.to_string()));
+    }
+    let profile = config
+        .profiles
+        .remove(name)
+        .ok_or_else(|| ProfileError::Unknown(name.to_string()))?;
+    if profile.api_key_in_keychain {
+        if let Err(e) = store.delete(&keychain::profile_key_account(name)) {
+            config.profiles.insert(name.to_string(), profile);
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// Make `name` the active profile, keeping the current settings as the
+/// previous one. Nothing changes when it already is active. Save
+/// afterwards.
+pub fn switch(
+    config: &mut Config,
+    name: &str,
+    store: &dyn SecretStore,
+) -> Result<(), ProfileError> {
+    let previous = active(config).to_string();
+    if name == previous {
+        return Ok(());
+    }
+    let target = config
+        .profiles
+        .get(name)
+        .cloned()
+        .ok_or_else(|| ProfileError::Unknown(name.to_string()))?;
+    let target_account = keychain::profile_key_account(name);
+    let target_key = if target.api_key_in_keychain {
+        store.get(&target_account)?
+    } else {
+        target.api_key.clone()
+    };
+
+    // The previous profile's key goes to its own account first, so a
+    // failure leaves it where it was
+    let mut kept = ProfileConfig {
+        api_key: None,
+        api_key_in_keychain: false,
+        gateway_port: config.gateway_port,
+        gateway_extra_args: config.gateway_extra_args.clone(),
+    };
+    store_key(
+        config,
+        &previous,
+        &mut kept,
+        config.anthropic_api_key.clone(),
+        store,
+    )?;
+    config.set_api_key(target_key, store)?;
+    if target.api_key_in_keychain {
+        store.delete(&target_account)?;
+    }
+
+    config.profiles.remove(name);
+    config.profiles.insert(previous, kept);
+    config.gateway_port = target.gateway_port;
+    config.gateway_extra_args = target.gateway_extra_args;
+    config.active_profile = Some(name.to_string());
+    // The profile's key isn't one of the stored keys
+    config.active_api_key = None;
+    Ok(())
+}
+
+/// Give `config` profile `name`'s settings without making it active, for
+/// running it next to the active one. The keychain is left alone.
+pub fn apply(config: &mut Config, name: &str, store: &dyn SecretStore) -> Result<(), ProfileError> {
+    if name == active(config) {
+        return Ok(());
+    }
+    let profile = config
+        .profiles
+        .get(name)
+        .cloned()
+        .ok_or_else(|| ProfileError::Unknown(name.to_string()))?;
+    config.anthropic_api_key = if profile.api_key_in_keychain {
+        store.get(&keychain::profile_key_account(name))?
+    } else {
+        profile.api_key
+    };
+    config.gateway_port = profile.gateway_port;
+    config.gateway_extra_args = profile.gateway_extra_args;
+    Ok(())
+}
+
+// Tauri commands
+#[tauri::command]
+pub fn list_profiles() -> Result<Vec<ProfileInfo>, AppError> {
+    Ok(list(&Config::load()?))
+}
+
+/// Without a port or arguments the new profile gets the active one's; the
+/// key is trimmed, and empty means none
+#[tauri::command]
+pub fn create_profile(
+    name: String,
+    gateway_port: Option<u16>,
+    gateway_extra_args: Option<Vec<String>>,
+    api_key: Option<String>,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<Vec<ProfileInfo>, AppError> {
+    let mut config = Config::load()?;
+    let api_key = api_key
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty());
+    create(
+        &mut config,
+        name.trim(),
+        gateway_port,
+        gateway_extra_args,
+        api_key,
+        keychain::system(),
+    )?;
+    save_config(&config, &metrics)?;
+    Ok(list(&config))
+}
+
+#[tauri::command]
+pub fn delete_profile(
+    name: String,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<Vec<ProfileInfo>, AppError> {
+    let mut config = Config::load()?;
+    delete(&mut config, &name, keychain::system())?;
+    save_config(&config, &metrics)?;
+    Ok(list(&config))
+}
+
+/// Outcome of `switch_profile`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileSwitch {
+    pub profiles: Vec<ProfileInfo>,
+    /// The gateway restarted with the new profile, when one was running
+    pub started: Option<StartResult>,
+}
+
+/// Switch to profile `name`, restarting a running gateway with its
+/// settings. The restart is emitted as `gateway://started` like a start.
+#[tauri::command]
+pub async fn switch_profile(name: String, app: AppHandle) -> Result<ProfileSwitch, AppError> {
+    let (profiles, started) = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || -> Result<_, AppError> {
+            let mut config = Config::load()?;
+            let changed = name != active(&config);
+            switch(&mut config, &name, keychain::system())?;
+            save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+            println!("[profiles] Switched to profile {}", name);
+
+            let manager = app.state::<SidecarManager>();
+            let started = if changed && manager.gateway_pid().is_some() {
+                Some(manager.restart(&format!("Switched to profile {}", name))?)
+            } else {
+                None
+            };
+            Ok((list(&config), started))
+        }
+    })
+    .await??;
+
+    if let Some(ref started) = started {
+        let _ = app.emit("gateway://started", started);
+    }
+    config::emit_changed(&app);
+    Ok(ProfileSwitch { profiles, started })
+}
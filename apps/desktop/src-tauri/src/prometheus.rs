@@ -0,0 +1,239 @@
+//! Prometheus Exporter
+//!
+//! With `metricsExporter.enabled` the app serves `/metrics` in the
+//! Prometheus text exposition format, built from the `AppMetrics` counters
+//! and the gateway status. It listens on loopback unless `bindAddress` says
+//! otherwise, for as long as the app runs, whether or not the gateway does.
+//!
+//! Only counts and states are exported. Tokens, the API key, URLs and
+//! message content never are, so the endpoint needs no authentication.
+//! Token usage isn't exported because the app doesn't track it.
+
+use std::fmt::{Display, Write};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::config::MetricsExporter;
+use crate::gateway_logs;
+use crate::heartbeat::GatewayHealth;
+use crate::http::{self, HttpResponse};
+use crate::metrics::{AppMetrics, MetricsSnapshot};
+use crate::sidecar::{GatewayStatus, SidecarManager};
+
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Content type of the text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Render everything in the text exposition format. `now_ms` is the
+/// current Unix time, which the gateway uptime is measured against.
+pub fn render(metrics: &MetricsSnapshot, status: &GatewayStatus, now_ms: i64) -> String {
+    let mut out = Exposition::default();
+
+    out.family(
+        "simplestclaw_app_start_time_seconds",
+        "gauge",
+        "Unix time the app started.",
+    );
+    out.sample(
+        "simplestclaw_app_start_time_seconds",
+        &[],
+        seconds(metrics.started_at),
+    );
+    out.family(
+        "simplestclaw_app_uptime_seconds",
+        "gauge",
+        "Seconds since the app started.",
+    );
+    out.sample("simplestclaw_app_uptime_seconds", &[], metrics.uptime_secs);
+
+    out.family(
+        "simplestclaw_gateway_start_attempts_total",
+        "counter",
+        "Gateway starts attempted.",
+    );
+    out.sample(
+        "simplestclaw_gateway_start_attempts_total",
+        &[],
+        metrics.start_attempts,
+    );
+    out.family(
+        "simplestclaw_gateway_start_failures_total",
+        "counter",
+        "Gateway starts that failed, by reason.",
+    );
+    for (reason, count) in &metrics.start_failures_by_reason {
+        out.sample(
+            "simplestclaw_gateway_start_failures_total",
+            &[("reason", reason)],
+            count,
+        );
+    }
+    out.family(
+        "simplestclaw_gateway_crashes_total",
+        "counter",
+        "Gateways that exited on their own after starting.",
+    );
+    out.sample("simplestclaw_gateway_crashes_total", &[], metrics.crashes);
+    out.family(
+        "simplestclaw_gateway_restarts_total",
+        "counter",
+        "Automatic gateway restarts.",
+    );
+    out.sample(
+        "simplestclaw_gateway_restarts_total",
+        &[],
+        metrics.auto_restarts,
+    );
+    out.family(
+        "simplestclaw_config_save_failures_total",
+        "counter",
+        "Failed writes of config.json.",
+    );
+    out.sample(
+        "simplestclaw_config_save_failures_total",
+        &[],
+        metrics.config_save_failures,
+    );
+    out.family(
+        "simplestclaw_command_errors_total",
+        "counter",
+        "Commands that returned an error, by error code.",
+    );
+    for (code, count) in &metrics.command_errors {
+        out.sample(
+            "simplestclaw_command_errors_total",
+            &[("code", code)],
+            count,
+        );
+    }
+
+    out.family(
+        "simplestclaw_gateway_up",
+        "gauge",
+        "Whether the gateway is running.",
+    );
+    out.sample("simplestclaw_gateway_up", &[], u8::from(status.running));
+    out.family(
+        "simplestclaw_gateway_uptime_seconds",
+        "gauge",
+        "Seconds since the running gateway became ready.",
+    );
+    let uptime_ms = status
+        .started_at
+        .map_or(0, |started| (now_ms - started).max(0));
+    out.sample("simplestclaw_gateway_uptime_seconds", &[], uptime_ms / 1000);
+    out.family(
+        "simplestclaw_gateway_health",
+        "gauge",
+        "Gateway health, 1 for the current state.",
+    );
+    for health in GatewayHealth::ALL {
+        out.sample(
+            "simplestclaw_gateway_health",
+            &[("state", health.as_str())],
+            u8::from(status.health == health),
+        );
+    }
+    out.family(
+        "simplestclaw_gateway_port_mismatch",
+        "gauge",
+        "Whether the gateway serves on another port than configured.",
+    );
+    out.sample(
+        "simplestclaw_gateway_port_mismatch",
+        &[],
+        u8::from(status.port_mismatch.is_some()),
+    );
+    if let Some(last_response_at) = status.last_response_at {
+        out.family(
+            "simplestclaw_gateway_last_response_timestamp_seconds",
+            "gauge",
+            "Unix time of the last response the frontend got.",
+        );
+        out.sample(
+            "simplestclaw_gateway_last_response_timestamp_seconds",
+            &[],
+            seconds(last_response_at),
+        );
+    }
+
+    out.0
+}
+
+/// Start serving `/metrics` if the exporter is enabled. A port that's
+/// already taken only logs a warning; the app runs on without it.
+pub fn spawn_metrics_exporter(app: &AppHandle, exporter: &MetricsExporter) {
+    if !exporter.enabled {
+        return;
+    }
+    let listener = match http::bind(&exporter.bind_address, exporter.port) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!(
+                "[metrics] Warning: not exporting metrics, can't listen on {}:{}: {}",
+                exporter.bind_address, exporter.port, e
+            );
+            return;
+        }
+    };
+
+    let app = app.clone();
+    let handler = Arc::new(move |path: &str| {
+        if path != METRICS_PATH {
+            return HttpResponse::not_found();
+        }
+        let (Some(metrics), Some(manager)) = (
+            app.try_state::<Arc<AppMetrics>>(),
+            app.try_state::<SidecarManager>(),
+        ) else {
+            return HttpResponse::new(503, CONTENT_TYPE, "");
+        };
+        // Status first, it notices and counts crashes
+        let status = manager.status();
+        let body = render(&metrics.snapshot(), &status, gateway_logs::now_ms());
+        HttpResponse::new(200, CONTENT_TYPE, body)
+    });
+    match http::serve(listener, "metrics-exporter", handler) {
+        Ok(addr) => println!(
+            "[metrics] Exporting metrics at http://{}{}",
+            addr, METRICS_PATH
+        ),
+        Err(e) => eprintln!("[metrics] Warning: failed to start the exporter: {}", e),
+    }
+}
+
+/// Text exposition output
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.0.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                .collect();
+            let _ = write!(self.0, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.0, " {}", value);
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Milliseconds as fractional seconds, e.g. `1700000000.123`
+fn seconds(ms: i64) -> String {
+    format!("{}.{:03}", ms.div_euclid(1000), ms.rem_euclid(1000))
+}
@@ -0,0 +1,403 @@
+//! Gateway Protocol Client
+//!
+//! A small blocking client for the gateway's WebSocket protocol, so the app
+//! can talk to the gateway without the frontend, e.g. for the auth
+//! self-test after a start: the `connect` handshake with our token, then
+//! plain `req`/`res` requests. Events are skipped.
+//!
+//! It connects over TCP or the gateway's Unix socket alike (see
+//! `gateway_socket::Endpoint`).
+//!
+//! Frames are encoded here rather than by a WebSocket library. Text,
+//! continuation, ping/pong and close frames are handled, which is all the
+//! gateway sends.
+
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::connection::{encode_query_value, TOKEN_PARAM};
+use crate::gateway_socket::{Endpoint, GatewayStream};
+
+/// Gateway protocol version spoken by this client and the frontend's
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Largest message accepted from the gateway
+pub const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("Could not talk to the gateway: {0}")]
+    Io(#[from] io::Error),
+    #[error("The gateway refused the WebSocket upgrade: {0}")]
+    Upgrade(String),
+    #[error("The gateway rejected the token: {0}")]
+    Rejected(String),
+    #[error("{method} failed: {message}")]
+    Request { method: String, message: String },
+    #[error("The gateway closed the connection")]
+    Closed,
+    #[error("Unexpected message from the gateway: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close,
+}
+
+impl Frame {
+    fn parts(&self) -> (u8, &[u8]) {
+        match self {
+            Frame::Text(text) => (0x1, text.as_bytes()),
+            Frame::Binary(data) => (0x2, data),
+            Frame::Close => (0x8, &[]),
+            Frame::Ping(data) => (0x9, data),
+            Frame::Pong(data) => (0xA, data),
+        }
+    }
+}
+
+/// Write `frame` as a single final frame. Clients mask what they send,
+/// servers don't.
+pub fn write_frame(
+    writer: &mut impl Write,
+    frame: &Frame,
+    mask: Option<[u8; 4]>,
+) -> io::Result<()> {
+    let (opcode, payload) = frame.parts();
+    let mut out = Vec::with_capacity(payload.len() + 14);
+    out.push(0x80 | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => out.push(mask_bit | len as u8),
+        len if len <= u16::MAX as usize => {
+            out.push(mask_bit | 126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(mask_bit | 127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(key) => {
+            out.extend_from_slice(&key);
+            out.extend(
+                payload
+                    .iter()
+                    .enumerate()
+                    .map(|(i, byte)| byte ^ key[i % 4]),
+            );
+        }
+        None => out.extend_from_slice(payload),
+    }
+    writer.write_all(&out)?;
+    writer.flush()
+}
+
+/// Reads messages, putting fragmented ones back together. Control frames
+/// arriving between fragments are returned as they come, the fragments
+/// read so far are kept for the next call.
+#[derive(Default)]
+pub struct FrameReader {
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl FrameReader {
+    /// The next complete message or control frame. Masked frames (from a
+    /// client) are unmasked.
+    pub fn read(&mut self, reader: &mut impl Read) -> io::Result<Frame> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        loop {
+            let mut header = [0u8; 2];
+            reader.read_exact(&mut header)?;
+            let fin = header[0] & 0x80 != 0;
+            let opcode = header[0] & 0x0F;
+            let masked = header[1] & 0x80 != 0;
+            let len = match header[1] & 0x7F {
+                126 => {
+                    let mut len = [0u8; 2];
+                    reader.read_exact(&mut len)?;
+                    u16::from_be_bytes(len) as usize
+                }
+                127 => {
+                    let mut len = [0u8; 8];
+                    reader.read_exact(&mut len)?;
+                    usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX)
+                }
+                len => len as usize,
+            };
+            let buffered = self.partial.as_ref().map_or(0, |(_, data)| data.len());
+            if len.saturating_add(buffered) > MAX_MESSAGE_BYTES {
+                return Err(invalid("message too large"));
+            }
+            let mut key = [0u8; 4];
+            if masked {
+                reader.read_exact(&mut key)?;
+            }
+            let mut payload = vec![0u8; len];
+            reader.read_exact(&mut payload)?;
+            if masked {
+                for (i, byte) in payload.iter_mut().enumerate() {
+                    *byte ^= key[i % 4];
+                }
+            }
+
+            let (opcode, payload) = match opcode {
+                0x8 => return Ok(Frame::Close),
+                0x9 => return Ok(Frame::Ping(payload)),
+                0xA => return Ok(Frame::Pong(payload)),
+                0x0 => {
+                    let (opcode, mut data) = self
+                        .partial
+                        .take()
+                        .ok_or_else(|| invalid("stray continuation frame"))?;
+                    data.extend_from_slice(&payload);
+                    (opcode, data)
+                }
+                0x1 | 0x2 if self.partial.is_none() => (opcode, payload),
+                _ => return Err(invalid("unexpected frame")),
+            };
+            if !fin {
+                self.partial = Some((opcode, payload));
+                continue;
+            }
+            return Ok(if opcode == 0x1 {
+                Frame::Text(
+                    String::from_utf8(payload).map_err(|_| invalid("text frame is not UTF-8"))?,
+                )
+            } else {
+                Frame::Binary(payload)
+            });
+        }
+    }
+}
+
+/// Bytes that don't need to be unpredictable to anyone but a proxy
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    for chunk in bytes.chunks_mut(8) {
+        // Every RandomState is seeded differently
+        let value = RandomState::new().build_hasher().finish().to_le_bytes();
+        chunk.copy_from_slice(&value[..chunk.len()]);
+    }
+    bytes
+}
+
+/// Let reads on `stream` wait until `deadline`
+fn set_timeout(stream: &GatewayStream, deadline: Instant) -> io::Result<()> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+    }
+    stream.set_read_timeout(Some(remaining))
+}
+
+/// Ask for the WebSocket upgrade of `path` and read the response headers,
+/// byte by byte so nothing after them is consumed. Anything but `101` is
+/// `ProtocolError::Upgrade` with the status line.
+fn upgrade(
+    stream: &mut GatewayStream,
+    endpoint: &Endpoint,
+    path: &str,
+    deadline: Instant,
+) -> Result<(), ProtocolError> {
+    let key = base64::engine::general_purpose::STANDARD.encode(random_bytes::<16>());
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        path,
+        endpoint.host(),
+        key
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() > 8 * 1024 {
+            return Err(ProtocolError::Upgrade(
+                "response headers too long".to_string(),
+            ));
+        }
+        set_timeout(stream, deadline)?;
+        let mut byte = [0u8; 1];
+        if stream.read(&mut byte)? == 0 {
+            return Err(ProtocolError::Closed);
+        }
+        response.push(byte[0]);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status = response.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(ProtocolError::Upgrade(status.to_string()));
+    }
+    Ok(())
+}
+
+/// Only the WebSocket upgrade, with `token` as `?token=` the way clients
+/// that can't send it otherwise do. Proves the gateway takes WebSocket
+/// clients at `endpoint` without speaking its protocol.
+pub fn upgrade_handshake(
+    endpoint: impl Into<Endpoint>,
+    token: &str,
+    timeout: Duration,
+) -> Result<(), ProtocolError> {
+    let endpoint = endpoint.into();
+    let deadline = Instant::now() + timeout;
+    let mut stream = endpoint.connect(timeout)?;
+    let path = format!("/?{}={}", TOKEN_PARAM, encode_query_value(token));
+    upgrade(&mut stream, &endpoint, &path, deadline)?;
+    let _ = write_frame(&mut stream, &Frame::Close, Some(random_bytes::<4>()));
+    Ok(())
+}
+
+/// A connection that completed the `connect` handshake
+pub struct ProtocolClient {
+    stream: GatewayStream,
+    frames: FrameReader,
+    deadline: Instant,
+    next_id: u64,
+}
+
+impl ProtocolClient {
+    /// Connect to the gateway at `endpoint` and authenticate with `token`.
+    /// Everything, later requests included, has to finish within `timeout`.
+    pub fn connect(
+        endpoint: impl Into<Endpoint>,
+        token: &str,
+        timeout: Duration,
+    ) -> Result<Self, ProtocolError> {
+        let mut client = Self::open(endpoint.into(), timeout)?;
+        let params = json!({
+            "minProtocol": PROTOCOL_VERSION,
+            "maxProtocol": PROTOCOL_VERSION,
+            "client": {
+                "id": "gateway-client",
+                "version": env!("CARGO_PKG_VERSION"),
+                "platform": std::env::consts::OS,
+                "mode": "backend",
+            },
+            "role": "operator",
+            "scopes": ["operator.read"],
+            "userAgent": format!("simplestclaw-desktop/{}", env!("CARGO_PKG_VERSION")),
+            "auth": { "token": token },
+        });
+        match client.request("connect", params) {
+            Ok(_) => Ok(client),
+            Err(ProtocolError::Request { message, .. }) => Err(ProtocolError::Rejected(message)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Upgrade a connection to `endpoint` and wait for the challenge the
+    /// gateway opens with; only then does it take `connect`
+    fn open(endpoint: Endpoint, timeout: Duration) -> Result<Self, ProtocolError> {
+        let deadline = Instant::now() + timeout;
+        let stream = endpoint.connect(timeout)?;
+        let mut client = Self {
+            stream,
+            frames: FrameReader::default(),
+            deadline,
+            next_id: 0,
+        };
+        upgrade(&mut client.stream, &endpoint, "/", deadline)?;
+        loop {
+            let message = client.next_message()?;
+            if message["type"] == "event" && message["event"] == "connect.challenge" {
+                return Ok(client);
+            }
+        }
+    }
+
+    /// Send `method` and wait for its response payload
+    pub fn request(&mut self, method: &str, params: Value) -> Result<Value, ProtocolError> {
+        self.next_id += 1;
+        let id = format!("sc-{}", self.next_id);
+        let request = json!({ "type": "req", "id": id, "method": method, "params": params });
+        let mask = random_bytes::<4>();
+        write_frame(
+            &mut self.stream,
+            &Frame::Text(request.to_string()),
+            Some(mask),
+        )?;
+
+        loop {
+            let message = self.next_message()?;
+            if message["type"] != "res" || message["id"] != id.as_str() {
+                continue;
+            }
+            if message["ok"] == true {
+                return Ok(message.get("payload").cloned().unwrap_or(Value::Null));
+            }
+            let detail = message["error"]["message"]
+                .as_str()
+                .or_else(|| message["error"].as_str())
+                .unwrap_or("no reason given");
+            return Err(ProtocolError::Request {
+                method: method.to_string(),
+                message: detail.to_string(),
+            });
+        }
+    }
+
+    fn set_timeout(&self) -> io::Result<()> {
+        set_timeout(&self.stream, self.deadline)
+    }
+
+    /// The next JSON message, answering pings on the way
+    fn next_message(&mut self) -> Result<Value, ProtocolError> {
+        loop {
+            self.set_timeout()?;
+            match self.frames.read(&mut self.stream)? {
+                Frame::Text(text) => {
+                    return serde_json::from_str(&text)
+                        .map_err(|e| ProtocolError::Invalid(e.to_string()));
+                }
+                Frame::Ping(data) => {
+                    write_frame(
+                        &mut self.stream,
+                        &Frame::Pong(data),
+                        Some(random_bytes::<4>()),
+                    )?;
+                }
+                Frame::Close => return Err(ProtocolError::Closed),
+                Frame::Binary(_) | Frame::Pong(_) => {}
+            }
+        }
+    }
+}
+
+impl Drop for ProtocolClient {
+    fn drop(&mut self) {
+        let _ = write_frame(&mut self.stream, &Frame::Close, Some(random_bytes::<4>()));
+    }
+}
+
+/// Whether what listens at `endpoint` is an OpenClaw gateway, telling by
+/// the challenge it opens with, so without a token
+pub fn is_gateway(endpoint: impl Into<Endpoint>, timeout: Duration) -> bool {
+    ProtocolClient::open(endpoint.into(), timeout).is_ok()
+}
+
+/// Connect with `token` and make one trivial request, proving the gateway
+/// accepts the token we gave it. Returns how long that took.
+pub fn auth_self_test(
+    endpoint: impl Into<Endpoint>,
+    token: &str,
+    timeout: Duration,
+) -> Result<Duration, ProtocolError> {
+    let started = Instant::now();
+    let mut client = ProtocolClient::connect(endpoint, token, timeout)?;
+    client.request("health", json!({}))?;
+    Ok(started.elapsed())
+}
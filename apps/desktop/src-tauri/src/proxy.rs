@@ -0,0 +1,183 @@
+//! Gateway Proxy
+//!
+//! A gateway launched from the Dock or a desktop launcher gets none of the
+//! shell's `HTTP_PROXY` variables, and clean-environment mode drops them,
+//! so behind a corporate proxy it can't reach the provider's API. Every
+//! spawn gets `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`, plus their
+//! lower-case forms that curl-style tools prefer, from `proxy` in config,
+//! or from the app's own environment when that is unset, so launching the
+//! app from a terminal keeps working.
+//!
+//! The API key check in `api_key` goes through the same proxy, so a key
+//! that checks out is one the gateway can use.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::config::{Config, ProxyConfig};
+use crate::locale::ValueSource;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProxyError {
+    #[error("{0} is not a proxy URL like http://proxy.example.com:3128")]
+    InvalidUrl(String),
+}
+
+/// Schemes node and reqwest both understand
+const SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+/// Check `url` is a proxy URL with a host
+pub fn validate_url(url: &str) -> Result<(), ProxyError> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if SCHEMES.contains(&parsed.scheme()) && parsed.has_host() => Ok(()),
+        _ => Err(ProxyError::InvalidUrl(url.to_string())),
+    }
+}
+
+/// `config` trimmed and checked, with empty fields unset. None when
+/// nothing is left.
+pub fn normalize(config: ProxyConfig) -> Result<Option<ProxyConfig>, ProxyError> {
+    let field = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let config = ProxyConfig {
+        http_proxy: field(config.http_proxy),
+        https_proxy: field(config.https_proxy),
+        no_proxy: field(config.no_proxy),
+    };
+    validate(&config)?;
+    Ok((config != ProxyConfig::default()).then_some(config))
+}
+
+/// Check the proxy URLs in `config`
+pub fn validate(config: &ProxyConfig) -> Result<(), ProxyError> {
+    for url in [&config.http_proxy, &config.https_proxy]
+        .into_iter()
+        .flatten()
+    {
+        validate_url(url)?;
+    }
+    Ok(())
+}
+
+/// The proxy a gateway goes through
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayProxy {
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    /// Comma-separated hosts reached directly
+    pub no_proxy: Option<String>,
+    pub source: ValueSource,
+}
+
+impl GatewayProxy {
+    /// Variables to inject, in both cases
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        for (name, value) in [
+            ("HTTP_PROXY", &self.http_proxy),
+            ("HTTPS_PROXY", &self.https_proxy),
+            ("NO_PROXY", &self.no_proxy),
+        ] {
+            if let Some(value) = value {
+                env.push((name.to_string(), value.clone()));
+                env.push((name.to_ascii_lowercase(), value.clone()));
+            }
+        }
+        env
+    }
+
+    /// The proxies without any credentials in them
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ref url) = self.http_proxy {
+            parts.push(format!("HTTP via {}", without_credentials(url)));
+        }
+        if let Some(ref url) = self.https_proxy {
+            parts.push(format!("HTTPS via {}", without_credentials(url)));
+        }
+        if let Some(ref hosts) = self.no_proxy {
+            parts.push(format!("direct to {}", hosts));
+        }
+        let source = match self.source {
+            ValueSource::Configured => "configured",
+            ValueSource::Detected => "from the app's environment",
+        };
+        format!("{} ({})", parts.join(", "), source)
+    }
+}
+
+/// `url` with any `user:password@` removed
+pub fn without_credentials(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.to_string().trim_end_matches('/').to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// The configured proxy, or the one in the variables `var` reads when
+/// none is configured. None when there is neither.
+pub fn resolve(
+    config: Option<&ProxyConfig>,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Option<GatewayProxy>, ProxyError> {
+    let proxy = match config {
+        Some(config) => {
+            validate(config)?;
+            GatewayProxy {
+                http_proxy: config.http_proxy.clone(),
+                https_proxy: config.https_proxy.clone(),
+                no_proxy: config.no_proxy.clone(),
+                source: ValueSource::Configured,
+            }
+        }
+        None => {
+            let read = |name: &str| {
+                [name.to_string(), name.to_ascii_lowercase()]
+                    .iter()
+                    .filter_map(|name| var(name))
+                    .find(|value| !value.trim().is_empty())
+            };
+            GatewayProxy {
+                http_proxy: read("HTTP_PROXY"),
+                https_proxy: read("HTTPS_PROXY"),
+                no_proxy: read("NO_PROXY"),
+                source: ValueSource::Detected,
+            }
+        }
+    };
+    let empty = proxy.http_proxy.is_none() && proxy.https_proxy.is_none();
+    Ok((!empty).then_some(proxy))
+}
+
+/// An HTTP client going through `proxy`, like the gateway does
+pub fn client(proxy: Option<&GatewayProxy>, timeout: Duration) -> reqwest::Result<reqwest::Client> {
+    // Only what the gateway also gets, not the system's proxy settings
+    let mut builder = reqwest::Client::builder().timeout(timeout).no_proxy();
+    if let Some(proxy) = proxy {
+        let no_proxy = proxy
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string);
+        if let Some(ref url) = proxy.http_proxy {
+            builder = builder.proxy(reqwest::Proxy::http(url)?.no_proxy(no_proxy.clone()));
+        }
+        if let Some(ref url) = proxy.https_proxy {
+            builder = builder.proxy(reqwest::Proxy::https(url)?.no_proxy(no_proxy));
+        }
+    }
+    builder.build()
+}
+
+/// `resolve` with the app's environment
+pub fn plan(config: &Config) -> Result<Option<GatewayProxy>, ProxyError> {
+    resolve(config.proxy.as_ref(), |name| std::env::var(name).ok())
+}
@@ -0,0 +1,307 @@
+//! Removing All App State
+//!
+//! `purge_app_data` removes everything the app wrote: the config and data
+//! directories (API key, logs, crash reports, the managed Node.js runtime,
+//! the sandbox workspace, the gateway state), the cache, OpenClaw's own
+//! session store from before the gateway state dir and the openclaw entries
+//! in the npx cache. `keepConfig` and `keepSessions` leave those parts in
+//! place, and `dryRun` only lists what would go. A relocated
+//! `gatewayStateDir` is not removed.
+//!
+//! A real purge needs the nonce from a `prepare_purge` call made shortly
+//! before, and each nonce works once, so a single stray invoke can't wipe
+//! anything. The report lists every item removed and every item that
+//! couldn't be, with the reason.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::config;
+use crate::error::AppError;
+use crate::paths::AppPaths;
+use crate::sidecar::SidecarManager;
+
+/// How long a nonce from `prepare_purge` stays valid
+pub const NONCE_TTL: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PurgeOptions {
+    /// Keep config.json, including the API key
+    pub keep_config: bool,
+    /// Keep OpenClaw's sessions, memory and conversation history
+    pub keep_sessions: bool,
+    /// Only report what would be removed
+    pub dry_run: bool,
+    /// From `prepare_purge`; required unless `dry_run` is set
+    pub nonce: Option<String>,
+}
+
+/// Something the purge removes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeItem {
+    pub path: String,
+    /// `config`, `data`, `cache`, `npx-cache`, `sessions` or
+    /// `legacy-workspace`
+    pub kind: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeFailure {
+    pub item: PurgeItem,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    pub dry_run: bool,
+    /// Removed, or for a dry run, what would be removed
+    pub removed: Vec<PurgeItem>,
+    pub failed: Vec<PurgeFailure>,
+}
+
+/// Returned by `prepare_purge`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgePreparation {
+    pub nonce: String,
+    pub expires_in_secs: u64,
+    /// What a purge with the given options would remove
+    pub items: Vec<PurgeItem>,
+}
+
+/// Issues and checks single-use confirmation nonces. Takes the time as a
+/// parameter so expiry can be tested without waiting.
+#[derive(Default)]
+pub struct PurgeGuard {
+    issued: Mutex<Option<(String, Instant)>>,
+}
+
+impl PurgeGuard {
+    /// A new nonce, replacing any earlier one
+    pub fn issue(&self, now: Instant) -> String {
+        let nonce = generate_nonce();
+        if let Ok(mut issued) = self.issued.lock() {
+            *issued = Some((nonce.clone(), now));
+        }
+        nonce
+    }
+
+    /// Whether `nonce` is the current one and still valid. Consumes it
+    /// either way, so a wrong guess can't be retried against the same one.
+    pub fn confirm(&self, nonce: &str, now: Instant) -> bool {
+        let Ok(mut issued) = self.issued.lock() else {
+            return false;
+        };
+        match issued.take() {
+            Some((expected, at)) => expected == nonce && now.duration_since(at) <= NONCE_TTL,
+            None => false,
+        }
+    }
+}
+
+/// Everything a purge with `options` removes. `home` is where OpenClaw
+/// keeps its data, `npx_entries` the openclaw folders in the npx cache.
+/// Only existing paths are listed, each at most once.
+pub fn plan(
+    paths: &AppPaths,
+    home: Option<&Path>,
+    npx_entries: &[PathBuf],
+    options: &PurgeOptions,
+) -> Vec<PurgeItem> {
+    let mut kept: Vec<PathBuf> = Vec::new();
+    if options.keep_config {
+        kept.push(paths.config_dir.join("config.json"));
+        kept.push(paths.config_dir.join(config::SECRETS_FILE));
+    }
+    if options.keep_sessions {
+        kept.push(paths.gateway_state_dir.clone());
+    }
+    let mut targets: Vec<(PathBuf, &str)> = Vec::new();
+    for root in paths.roots() {
+        let kind = root_kind(paths, &root);
+        if !kept.iter().any(|path| path.starts_with(&root)) {
+            targets.push((root, kind));
+            continue;
+        }
+        // Everything next to what is kept
+        let Ok(entries) = fs::read_dir(&root) else {
+            continue;
+        };
+        let mut children: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| !kept.contains(path))
+            .collect();
+        children.sort();
+        targets.extend(children.into_iter().map(|path| (path, kind)));
+    }
+    for entry in npx_entries {
+        targets.push((entry.clone(), "npx-cache"));
+    }
+    if let (Some(home), false) = (home, options.keep_sessions) {
+        targets.extend([
+            (home.join(".openclaw"), "sessions"),
+            (home.join(".clawdbot"), "sessions"),
+            (home.join("clawd"), "legacy-workspace"),
+        ]);
+    }
+
+    let mut items: Vec<PurgeItem> = Vec::new();
+    for (path, kind) in targets {
+        let covered = items.iter().any(|item| path.starts_with(&item.path));
+        if covered || fs::symlink_metadata(&path).is_err() {
+            continue;
+        }
+        items.push(PurgeItem {
+            size_bytes: config::calculate_dir_size(&path).max(file_size(&path)),
+            path: path.display().to_string(),
+            kind: kind.to_string(),
+        });
+    }
+    items
+}
+
+fn root_kind(paths: &AppPaths, root: &Path) -> &'static str {
+    if root == paths.config_dir {
+        "config"
+    } else if root == paths.cache_dir {
+        "cache"
+    } else {
+        "data"
+    }
+}
+
+/// Remove `items`, or just report them for a dry run
+pub fn execute(items: Vec<PurgeItem>, dry_run: bool) -> PurgeReport {
+    let mut report = PurgeReport {
+        dry_run,
+        ..PurgeReport::default()
+    };
+    if dry_run {
+        report.removed = items;
+        return report;
+    }
+
+    for item in items {
+        match remove_path(Path::new(&item.path)) {
+            Ok(()) => {
+                println!("[purge] Removed {} ({})", item.path, item.kind);
+                report.removed.push(item);
+            }
+            Err(e) => {
+                eprintln!("[purge] Failed to remove {}: {}", item.path, e);
+                report.failed.push(PurgeFailure {
+                    item,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    report
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map_or(0, |metadata| metadata.len())
+}
+
+fn generate_nonce() -> String {
+    let mut hasher = Sha256::new();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.update(now.as_nanos().to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    // Distinct even when the clock hasn't moved
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+    // Stack address, which varies with ASLR
+    let marker = 0u8;
+    hasher.update((&marker as *const u8 as usize).to_le_bytes());
+    hex::encode(&hasher.finalize()[..16])
+}
+
+/// The purge plan for this machine
+fn plan_current(options: &PurgeOptions) -> Result<Vec<PurgeItem>, AppError> {
+    let paths = AppPaths::resolve().ok_or(AppError::DataDirUnavailable)?;
+    let npx_entries = config::npx_cache_dir()
+        .map(|dir| config::openclaw_npx_entries(&dir))
+        .unwrap_or_default();
+    Ok(plan(
+        &paths,
+        dirs::home_dir().as_deref(),
+        &npx_entries,
+        options,
+    ))
+}
+
+// Tauri commands
+
+/// Issue the nonce `purge_app_data` requires, with the list of what a
+/// purge with `options` would remove
+#[tauri::command]
+pub async fn prepare_purge(
+    app: AppHandle,
+    options: Option<PurgeOptions>,
+) -> Result<PurgePreparation, AppError> {
+    let options = options.unwrap_or_default();
+    let items = tokio::task::spawn_blocking(move || plan_current(&options)).await??;
+    Ok(PurgePreparation {
+        nonce: app.state::<PurgeGuard>().issue(Instant::now()),
+        expires_in_secs: NONCE_TTL.as_secs(),
+        items,
+    })
+}
+
+/// Stop the gateway and remove all app state, see the module docs
+#[tauri::command]
+pub async fn purge_app_data(
+    app: AppHandle,
+    options: PurgeOptions,
+) -> Result<PurgeReport, AppError> {
+    if !options.dry_run {
+        let confirmed = options
+            .nonce
+            .as_deref()
+            .is_some_and(|nonce| app.state::<PurgeGuard>().confirm(nonce, Instant::now()));
+        if !confirmed {
+            return Err(AppError::PurgeNotConfirmed);
+        }
+    }
+
+    tokio::task::spawn_blocking(move || {
+        if !options.dry_run {
+            println!("[purge] Stopping the gateway before removing app data");
+            if let Err(e) = app.state::<SidecarManager>().stop() {
+                eprintln!("[purge] Failed to stop the gateway: {}", e);
+            }
+            config::forget_gateway_token();
+        }
+        let items = plan_current(&options)?;
+        Ok(execute(items, options.dry_run))
+    })
+    .await?
+}
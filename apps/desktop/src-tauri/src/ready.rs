@@ -0,0 +1,141 @@
+//! Cold Start to Connected
+//!
+//! `ensure_ready` takes the app from launch to a gateway the frontend can
+//! connect to in one call, instead of the frontend sequencing config,
+//! credentials and start itself. The stages run in `STAGES` order and each
+//! is announced as a `ready://progress` event before it runs, so the splash
+//! screen can show where a slow start is. A failure names its stage along
+//! with the usual error code, so the frontend can route a missing API key
+//! to onboarding and show everything else as a start error.
+//!
+//! A gateway that is already running, or being started by another call, is
+//! reused rather than started again.
+
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::sidecar::{GatewayInfo, SidecarManager, StartResult, StartWarning};
+
+/// Event each stage is announced with
+pub const PROGRESS_EVENT: &str = "ready://progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadyStage {
+    /// Load and validate config
+    Config,
+    /// Check an API key is configured
+    Credentials,
+    /// Find the runtime or gateway binary
+    Binary,
+    /// Start the gateway, or join a running or starting one, and wait for
+    /// its port
+    Gateway,
+    /// Prove the gateway accepts the app's token
+    SelfTest,
+}
+
+pub const STAGES: &[ReadyStage] = &[
+    ReadyStage::Config,
+    ReadyStage::Credentials,
+    ReadyStage::Binary,
+    ReadyStage::Gateway,
+    ReadyStage::SelfTest,
+];
+
+/// Payload of `ready://progress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyProgress {
+    pub stage: ReadyStage,
+    /// 1-based position of `stage` in `STAGES`
+    pub step: usize,
+    pub steps: usize,
+}
+
+impl ReadyProgress {
+    pub fn new(stage: ReadyStage) -> Self {
+        Self {
+            stage,
+            step: STAGES.iter().position(|s| *s == stage).map_or(0, |i| i + 1),
+            steps: STAGES.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyResult {
+    pub info: GatewayInfo,
+    /// The gateway had no state directory yet, so there are no sessions to
+    /// restore
+    pub first_run: bool,
+    pub warnings: Vec<StartWarning>,
+}
+
+/// An `AppError` and the stage it stopped at, serialized as the error with
+/// a `stage` field added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadyError {
+    pub stage: ReadyStage,
+    pub error: AppError,
+}
+
+impl ReadyError {
+    pub fn new(stage: ReadyStage, error: impl Into<AppError>) -> Self {
+        Self {
+            stage,
+            error: error.into(),
+        }
+    }
+}
+
+impl fmt::Display for ReadyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for ReadyError {}
+
+impl Serialize for ReadyError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ReadyError", 4)?;
+        state.serialize_field("stage", &self.stage)?;
+        state.serialize_field("code", self.error.code())?;
+        state.serialize_field("data", &self.error.data())?;
+        state.serialize_field("message", &self.error.message())?;
+        state.end()
+    }
+}
+
+/// Get the gateway running and connectable, announcing every stage as a
+/// `ready://progress` event. A start it performs is also emitted as
+/// `gateway://started`, like one from `start_gateway`.
+#[tauri::command]
+pub async fn ensure_ready(app: AppHandle) -> Result<ReadyResult, ReadyError> {
+    let result = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || {
+            let manager = app.state::<SidecarManager>();
+            manager.ensure_ready(|progress| {
+                let _ = app.emit(PROGRESS_EVENT, progress);
+            })
+        }
+    })
+    .await
+    // Nothing tells which stage a panic was in
+    .map_err(|e| ReadyError::new(ReadyStage::Config, e))??;
+
+    let _ = app.emit(
+        "gateway://started",
+        StartResult {
+            info: result.info.clone(),
+            warnings: result.warnings.clone(),
+        },
+    );
+    Ok(result)
+}
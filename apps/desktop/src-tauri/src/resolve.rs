@@ -0,0 +1,487 @@
+//! Executable Resolution
+//!
+//! Finds the binaries the app launches, node and npx for the gateway, the
+//! same way for a start and for `doctor`. Apps started from the Dock or a
+//! desktop launcher get a minimal PATH, so `resolve` looks further and
+//! takes the first executable found, in this order:
+//!
+//! 1. The configured binary (`nodePath`). It is final: when it isn't an
+//!    executable file nothing else is tried.
+//! 2. The managed runtime downloaded by the app.
+//! 3. The app's own PATH.
+//! 4. PATH as the user's login shell sets it up (Unix), probed once per
+//!    run and only when needed.
+//! 5. Version manager, pnpm and yarn directories under home
+//!    (`VERSION_MANAGER_DIRS`), then the newest nvm or fnm install
+//!    (`VERSION_INSTALL_DIRS`).
+//! 6. Homebrew prefixes (`HOMEBREW_PREFIX`, then `BREW_PREFIXES`).
+//! 7. Standard system locations (`SYSTEM_DIRS`).
+//!
+//! PATH is searched here rather than by running `which`/`where.exe`, so it
+//! works the same on every platform and costs no subprocess. On Windows
+//! the extensions in PATHEXT are tried.
+//!
+//! npx is run as `node npx-cli.js` rather than through the `npx` shim,
+//! which on Windows is a `.cmd` node can't run. `find_npx_cli` looks for
+//! npm next to node, where each platform's installers put it, then under
+//! the global npm prefixes (`NPM_CONFIG_PREFIX`, and `%APPDATA%\npm` and
+//! `%LOCALAPPDATA%\npm` on Windows).
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::config::Config;
+use crate::runtime::RuntimeManager;
+use crate::shell_env;
+use crate::sidecar::ExecutableSource;
+
+/// Directories under home where version managers link the active
+/// version, or pnpm and yarn put global binaries, in the order they are
+/// tried
+#[cfg(not(windows))]
+pub const VERSION_MANAGER_DIRS: &[&str] = &[
+    ".nvm/current/bin",
+    ".volta/bin",
+    ".local/share/fnm/aliases/default/bin",
+    ".fnm/aliases/default/bin",
+    ".asdf/shims",
+    ".local/share/mise/shims",
+    "Library/pnpm",
+    ".local/share/pnpm",
+    ".yarn/bin",
+    ".config/yarn/global/node_modules/.bin",
+];
+#[cfg(windows)]
+pub const VERSION_MANAGER_DIRS: &[&str] = &[
+    "AppData/Local/Volta/bin",
+    "AppData/Roaming/fnm/aliases/default",
+    "AppData/Local/mise/shims",
+    "AppData/Local/pnpm",
+    "AppData/Local/Yarn/bin",
+];
+
+/// nvm installs under home, searched newest version first when none of
+/// `VERSION_MANAGER_DIRS` has the binary
+pub const NVM_VERSIONS_DIR: &str = ".nvm/versions/node";
+
+/// Directories under home holding one install per version, and where the
+/// binaries are inside each, searched after `VERSION_MANAGER_DIRS`
+#[cfg(not(windows))]
+pub const VERSION_INSTALL_DIRS: &[(&str, &str)] = &[
+    (NVM_VERSIONS_DIR, "bin"),
+    (".local/share/fnm/node-versions", "installation/bin"),
+    ("Library/Application Support/fnm/node-versions", "installation/bin"),
+];
+#[cfg(windows)]
+pub const VERSION_INSTALL_DIRS: &[(&str, &str)] = &[
+    ("AppData/Roaming/nvm", ""),
+    ("AppData/Roaming/fnm/node-versions", "installation"),
+];
+
+/// The directories under `home` searched for a version manager's
+/// binaries, in order and without repeats: `VERSION_MANAGER_DIRS`, then
+/// each of `VERSION_INSTALL_DIRS` newest version first
+pub fn version_manager_dirs(home: &Path) -> Vec<PathBuf> {
+    let linked = VERSION_MANAGER_DIRS.iter().map(|dir| home.join(dir));
+    let installed = VERSION_INSTALL_DIRS.iter().flat_map(|(versions, bin)| {
+        let mut installs: Vec<(Vec<u32>, PathBuf)> = std::fs::read_dir(home.join(versions))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| {
+                (
+                    version_key(&entry.file_name().to_string_lossy()),
+                    match *bin {
+                        "" => entry.path(),
+                        bin => entry.path().join(bin),
+                    },
+                )
+            })
+            .collect();
+        installs.sort();
+        installs.into_iter().rev().map(|(_, dir)| dir)
+    });
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in linked.chain(installed) {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Homebrew prefixes tried after `HOMEBREW_PREFIX`
+#[cfg(not(windows))]
+pub const BREW_PREFIXES: &[&str] = &["/opt/homebrew", "/home/linuxbrew/.linuxbrew"];
+#[cfg(windows)]
+pub const BREW_PREFIXES: &[&str] = &[];
+
+#[cfg(not(windows))]
+pub const SYSTEM_DIRS: &[&str] = &["/usr/local/bin", "/usr/bin"];
+#[cfg(windows)]
+pub const SYSTEM_DIRS: &[&str] = &["C:/Program Files/nodejs"];
+
+/// npx's entry point under a node install's or npm prefix's module dir
+pub const NPX_CLI: &str = "node_modules/npm/bin/npx-cli.js";
+
+/// Where a node install keeps npm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeLayout {
+    /// `bin/node` and `lib/node_modules`
+    Unix,
+    /// `node.exe` and `node_modules` side by side
+    Windows,
+}
+
+impl NodeLayout {
+    #[cfg(not(windows))]
+    pub const CURRENT: NodeLayout = NodeLayout::Unix;
+    #[cfg(windows)]
+    pub const CURRENT: NodeLayout = NodeLayout::Windows;
+
+    /// Directory holding `node_modules` under an install or npm prefix
+    fn modules_root(self, prefix: &Path) -> PathBuf {
+        match self {
+            NodeLayout::Unix => prefix.join("lib"),
+            NodeLayout::Windows => prefix.to_path_buf(),
+        }
+    }
+}
+
+/// Where npx-cli.js may be for `node`, in the order they are tried: the
+/// npm that came with it, then the one in each of `npm_prefixes`
+pub fn npx_cli_candidates(
+    node: &Path,
+    layout: NodeLayout,
+    npm_prefixes: &[PathBuf],
+) -> Vec<PathBuf> {
+    let install = match layout {
+        NodeLayout::Unix => node.parent().and_then(Path::parent),
+        NodeLayout::Windows => node.parent(),
+    };
+    let mut candidates: Vec<PathBuf> = install
+        .into_iter()
+        .chain(npm_prefixes.iter().map(PathBuf::as_path))
+        .map(|prefix| layout.modules_root(prefix).join(NPX_CLI))
+        .collect();
+    candidates.dedup();
+    candidates
+}
+
+/// The first of `npx_cli_candidates` that `exists`
+pub fn find_npx_cli(
+    node: &Path,
+    layout: NodeLayout,
+    npm_prefixes: &[PathBuf],
+    exists: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    npx_cli_candidates(node, layout, npm_prefixes)
+        .into_iter()
+        .find(|candidate| exists(candidate))
+}
+
+/// An executable and how it was found
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedBinary {
+    pub path: PathBuf,
+    pub source: ExecutableSource,
+    /// Set by `with_version`; resolving doesn't run anything
+    pub version: Option<String>,
+}
+
+impl ResolvedBinary {
+    fn new(path: PathBuf, source: ExecutableSource) -> Self {
+        Self {
+            path,
+            source,
+            version: None,
+        }
+    }
+
+    /// Fill in the version from `<binary> --version`, without a leading `v`
+    pub fn with_version(mut self) -> Self {
+        if self.version.is_none() {
+            self.version = Command::new(&self.path)
+                .arg("--version")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| {
+                    let version = String::from_utf8_lossy(&output.stdout);
+                    version.trim().trim_start_matches('v').to_string()
+                })
+                .filter(|version| !version.is_empty());
+        }
+        self
+    }
+}
+
+/// Where the login shell's PATH comes from
+#[derive(Debug, Clone)]
+pub enum LoginPath {
+    /// Ask the user's login shell, once per run
+    Probe,
+    Known(Option<OsString>),
+}
+
+/// Everything `resolve` looks at besides the filesystem, so the search can
+/// be pointed at a fixture
+#[derive(Debug, Clone)]
+pub struct SearchContext {
+    /// Configured binaries by name
+    pub overrides: HashMap<String, PathBuf>,
+    /// Directory of the managed runtime's binaries, when installed
+    pub managed_dir: Option<PathBuf>,
+    /// PATH of this process
+    pub path: Option<OsString>,
+    pub login_path: LoginPath,
+    pub home: Option<PathBuf>,
+    pub brew_prefixes: Vec<PathBuf>,
+    pub system_dirs: Vec<PathBuf>,
+    /// Extensions tried after a bare name (Windows)
+    pub extensions: Vec<String>,
+    /// Global npm prefixes searched for npx-cli.js
+    pub npm_prefixes: Vec<PathBuf>,
+}
+
+impl SearchContext {
+    /// Nothing to search; fill in what a test needs
+    pub fn empty() -> Self {
+        Self {
+            overrides: HashMap::new(),
+            managed_dir: None,
+            path: None,
+            login_path: LoginPath::Known(None),
+            home: None,
+            brew_prefixes: Vec::new(),
+            system_dirs: Vec::new(),
+            extensions: Vec::new(),
+            npm_prefixes: Vec::new(),
+        }
+    }
+
+    /// The search for this machine with `config`
+    pub fn current(config: &Config) -> Self {
+        let mut overrides = HashMap::new();
+        if let Some(ref node) = config.node_path {
+            overrides.insert("node".to_string(), node.clone());
+        }
+        let brew_prefixes = std::env::var_os("HOMEBREW_PREFIX")
+            .filter(|prefix| !prefix.is_empty())
+            .map(PathBuf::from)
+            .into_iter()
+            .chain(BREW_PREFIXES.iter().map(PathBuf::from))
+            .collect();
+        let extensions = if cfg!(windows) {
+            let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+            pathext
+                .split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(|ext| ext.to_lowercase())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let mut npm_prefixes: Vec<PathBuf> = std::env::var_os("NPM_CONFIG_PREFIX")
+            .filter(|prefix| !prefix.is_empty())
+            .map(PathBuf::from)
+            .into_iter()
+            .collect();
+        if cfg!(windows) {
+            npm_prefixes.extend(
+                ["APPDATA", "LOCALAPPDATA"]
+                    .iter()
+                    .filter_map(std::env::var_os)
+                    .map(|dir| PathBuf::from(dir).join("npm")),
+            );
+        }
+        Self {
+            overrides,
+            managed_dir: RuntimeManager::node_path()
+                .and_then(|node| node.parent().map(Path::to_path_buf)),
+            path: std::env::var_os("PATH"),
+            login_path: LoginPath::Probe,
+            home: dirs::home_dir(),
+            brew_prefixes,
+            system_dirs: SYSTEM_DIRS.iter().map(PathBuf::from).collect(),
+            extensions,
+            npm_prefixes,
+        }
+    }
+
+    /// The same search without the managed runtime
+    pub fn without_managed(&self) -> Self {
+        Self {
+            managed_dir: None,
+            ..self.clone()
+        }
+    }
+
+    /// Every directory `resolve` looks in, in order and without repeats,
+    /// to report where a binary was not found
+    pub fn searched_dirs(&self) -> Vec<PathBuf> {
+        let login_path = self.login_path();
+        let on_path = self
+            .path
+            .iter()
+            .chain(login_path.as_ref())
+            .flat_map(std::env::split_paths)
+            .filter(|dir| dir.is_absolute());
+        let version_managers = self
+            .home
+            .as_deref()
+            .map(version_manager_dirs)
+            .unwrap_or_default();
+        let candidates = self
+            .managed_dir
+            .iter()
+            .cloned()
+            .chain(on_path)
+            .chain(version_managers)
+            .chain(self.brew_prefixes.iter().map(|prefix| prefix.join("bin")))
+            .chain(self.system_dirs.iter().cloned());
+        let mut dirs = Vec::new();
+        for dir in candidates {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs
+    }
+
+    fn login_path(&self) -> Option<OsString> {
+        match self.login_path {
+            LoginPath::Known(ref path) => path.clone(),
+            LoginPath::Probe => probed_login_path().clone(),
+        }
+    }
+
+    /// `name` in `dir`, trying each extension when it has none
+    pub fn find_in(&self, dir: &Path, name: &str) -> Option<PathBuf> {
+        if self.extensions.is_empty() || Path::new(name).extension().is_some() {
+            return Some(dir.join(name)).filter(|path| is_executable(path));
+        }
+        self.extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", name, ext)))
+            .find(|path| is_executable(path))
+    }
+
+    fn find_on(&self, path: Option<&OsString>, name: &str) -> Option<PathBuf> {
+        std::env::split_paths(path?)
+            .filter(|dir| dir.is_absolute())
+            .find_map(|dir| self.find_in(&dir, name))
+    }
+
+    fn find_in_version_managers(&self, name: &str) -> Option<PathBuf> {
+        let home = self.home.as_ref()?;
+        version_manager_dirs(home)
+            .iter()
+            .find_map(|dir| self.find_in(dir, name))
+    }
+}
+
+/// `v20.11.0` as `[20, 11, 0]`, so `v10` sorts after `v9`
+fn version_key(name: &str) -> Vec<u32> {
+    name.trim_start_matches('v')
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn probed_login_path() -> &'static Option<OsString> {
+    static LOGIN_PATH: OnceLock<Option<OsString>> = OnceLock::new();
+    LOGIN_PATH.get_or_init(|| {
+        let shell = shell_env::user_shell()?;
+        match shell_env::probe_login_shell(&shell, &["PATH"], shell_env::PROBE_TIMEOUT) {
+            Ok(values) => values.into_iter().next().map(|(_, path)| path.into()),
+            Err(e) => {
+                eprintln!(
+                    "[openclaw] Could not read PATH from {}: {}",
+                    shell.display(),
+                    e
+                );
+                None
+            }
+        }
+    })
+}
+
+/// A regular file we may run
+pub fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// Find `name`, trying the places in the order documented above
+pub fn resolve(name: &str, context: &SearchContext) -> Option<ResolvedBinary> {
+    if let Some(path) = context.overrides.get(name) {
+        return is_executable(path)
+            .then(|| ResolvedBinary::new(path.clone(), ExecutableSource::Config));
+    }
+
+    let found = context
+        .managed_dir
+        .as_ref()
+        .and_then(|dir| context.find_in(dir, name))
+        .map(|path| (path, ExecutableSource::Managed))
+        .or_else(|| {
+            let path = context.find_on(context.path.as_ref(), name)?;
+            Some((path, ExecutableSource::Path))
+        })
+        .or_else(|| {
+            let path = context.find_on(context.login_path().as_ref(), name)?;
+            Some((path, ExecutableSource::LoginShell))
+        })
+        .or_else(|| {
+            let path = context.find_in_version_managers(name)?;
+            Some((path, ExecutableSource::VersionManager))
+        })
+        .or_else(|| {
+            let path = context
+                .brew_prefixes
+                .iter()
+                .find_map(|prefix| context.find_in(&prefix.join("bin"), name))?;
+            Some((path, ExecutableSource::Brew))
+        })
+        .or_else(|| {
+            let path = context
+                .system_dirs
+                .iter()
+                .find_map(|dir| context.find_in(dir, name))?;
+            Some((path, ExecutableSource::System))
+        });
+    found.map(|(path, source)| ResolvedBinary::new(path, source))
+}
+
+/// `name` from the directory `binary` is in, so npx matches its node,
+/// otherwise wherever `resolve` finds it
+pub fn resolve_beside(
+    name: &str,
+    binary: &ResolvedBinary,
+    context: &SearchContext,
+) -> Option<ResolvedBinary> {
+    binary
+        .path
+        .parent()
+        .and_then(|dir| context.find_in(dir, name))
+        .map(|path| ResolvedBinary::new(path, binary.source))
+        .or_else(|| resolve(name, context))
+}
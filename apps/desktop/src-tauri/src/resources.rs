@@ -0,0 +1,262 @@
+//! Gateway Resource Sampling
+//!
+//! The gateway is a small tree of processes (npx's node plus the node
+//! running openclaw), so memory is measured over the whole tree rooted at
+//! the process we spawned rather than just that one PID. The same goes for
+//! CPU time. Open connections are counted on the gateway's port, since the
+//! process tree doesn't tell which sockets are clients.
+
+/// One row of the system process table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessSample {
+    pub pid: u32,
+    pub ppid: u32,
+    pub rss_bytes: u64,
+    /// CPU time used so far, user and system
+    pub cpu_ms: u64,
+    /// Executable name (or path, depending on the platform)
+    pub name: String,
+}
+
+/// `root` and all its descendants
+fn process_tree(samples: &[ProcessSample], root: u32) -> Vec<&ProcessSample> {
+    let mut tree = Vec::new();
+    let mut pending = vec![root];
+    let mut seen = Vec::new();
+    while let Some(pid) = pending.pop() {
+        // Guard against PID reuse creating a cycle
+        if seen.contains(&pid) {
+            continue;
+        }
+        seen.push(pid);
+        for sample in samples {
+            if sample.pid == pid {
+                tree.push(sample);
+            } else if sample.ppid == pid {
+                pending.push(sample.pid);
+            }
+        }
+    }
+    tree
+}
+
+/// Total resident memory of `root` and all its descendants
+pub fn tree_rss_bytes(samples: &[ProcessSample], root: u32) -> u64 {
+    process_tree(samples, root)
+        .iter()
+        .map(|sample| sample.rss_bytes)
+        .sum()
+}
+
+/// Total CPU time of `root` and all its descendants
+pub fn tree_cpu_ms(samples: &[ProcessSample], root: u32) -> u64 {
+    process_tree(samples, root)
+        .iter()
+        .map(|sample| sample.cpu_ms)
+        .sum()
+}
+
+/// Resident memory of the process tree rooted at `pid`, or `None` if the
+/// process table can't be read or the process is gone
+pub fn process_tree_rss(pid: u32) -> Option<u64> {
+    let samples = sample_processes()?;
+    if !samples.iter().any(|sample| sample.pid == pid) {
+        return None;
+    }
+    Some(tree_rss_bytes(&samples, pid))
+}
+
+/// Resident memory and CPU time of the process tree rooted at `pid`, or
+/// `None` if the process table can't be read or the process is gone
+pub fn process_tree_usage(pid: u32) -> Option<(u64, u64)> {
+    let samples = sample_processes()?;
+    if !samples.iter().any(|sample| sample.pid == pid) {
+        return None;
+    }
+    Some((tree_rss_bytes(&samples, pid), tree_cpu_ms(&samples, pid)))
+}
+
+/// Snapshot the process table via `ps`
+#[cfg(unix)]
+pub fn sample_processes() -> Option<Vec<ProcessSample>> {
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,rss=,time=,comm="])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_ps_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `ps -o pid=,ppid=,rss=,time=,comm=` output (RSS in KiB). The
+/// command name comes last because it may contain spaces.
+pub fn parse_ps_output(output: &str) -> Vec<ProcessSample> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            let rss_kb: u64 = fields.next()?.parse().ok()?;
+            let cpu_ms = parse_cpu_time(fields.next()?)?;
+            let name = fields.collect::<Vec<_>>().join(" ");
+            Some(ProcessSample {
+                pid,
+                ppid,
+                rss_bytes: rss_kb * 1024,
+                cpu_ms,
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Parse the `time` column of `ps` into milliseconds: `[dd-]hh:mm:ss` on
+/// Linux, `mm:ss.hh` on macOS
+pub fn parse_cpu_time(time: &str) -> Option<u64> {
+    let (days, clock) = match time.split_once('-') {
+        Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+        None => (0, time),
+    };
+    let mut fields = clock.rsplit(':');
+    let seconds: f64 = fields.next()?.parse().ok()?;
+    let minutes: u64 = fields.next().map_or(Some(0), |field| field.parse().ok())?;
+    let hours: u64 = fields.next().map_or(Some(0), |field| field.parse().ok())?;
+    if fields.next().is_some() || !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    let whole_secs = days * 86_400 + hours * 3600 + minutes * 60;
+    Some(whole_secs * 1000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Established TCP connections to `port` on this machine, or `None` if
+/// they can't be listed
+pub fn open_connections(port: u16) -> Option<u32> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut count = None;
+        for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+            if let Ok(contents) = std::fs::read_to_string(table) {
+                *count.get_or_insert(0) += count_proc_net_tcp(&contents, port);
+            }
+        }
+        count
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let output = std::process::Command::new("netstat")
+            .args(["-an", "-p", "tcp"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(count_netstat(&String::from_utf8_lossy(&output.stdout), port))
+    }
+}
+
+/// Established connections with `port` as the local port in the contents
+/// of `/proc/net/tcp` or `/proc/net/tcp6`
+pub fn count_proc_net_tcp(contents: &str, port: u16) -> u32 {
+    // State 01 is ESTABLISHED
+    contents
+        .lines()
+        .skip(1)
+        .filter(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let local_port = fields
+                .next()
+                .and_then(|local| local.rsplit(':').next())
+                .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+            local_port == Some(port) && fields.nth(1) == Some("01")
+        })
+        .count() as u32
+}
+
+/// Established connections with `port` as the local port in `netstat -an`
+/// output, as macOS (`127.0.0.1.18789`) and Windows (`127.0.0.1:18789`)
+/// print it
+pub fn count_netstat(output: &str, port: u16) -> u32 {
+    let port = port.to_string();
+    output
+        .lines()
+        .filter(|line| line.contains("ESTABLISHED"))
+        .filter(|line| {
+            // The local address comes before the foreign one
+            line.split_whitespace()
+                .find(|field| field.contains(['.', ':']))
+                .and_then(|local| local.rsplit(['.', ':']).next())
+                == Some(port.as_str())
+        })
+        .count() as u32
+}
+
+/// Snapshot the process table via the ToolHelp API
+#[cfg(windows)]
+pub fn sample_processes() -> Option<Vec<ProcessSample>> {
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    // In 100 ns units
+    fn filetime_ms(time: &FILETIME) -> u64 {
+        ((time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64) / 10_000
+    }
+
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return None;
+        }
+
+        let mut samples = Vec::new();
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut more = Process32FirstW(snapshot, &mut entry) != 0;
+        while more {
+            let mut rss_bytes = 0;
+            let mut cpu_ms = 0;
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, entry.th32ProcessID);
+            if !process.is_null() {
+                let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+                let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+                if K32GetProcessMemoryInfo(process, &mut counters, size) != 0 {
+                    rss_bytes = counters.WorkingSetSize as u64;
+                }
+                let mut created: FILETIME = std::mem::zeroed();
+                let mut exited: FILETIME = std::mem::zeroed();
+                let mut kernel: FILETIME = std::mem::zeroed();
+                let mut user: FILETIME = std::mem::zeroed();
+                if GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user) != 0 {
+                    cpu_ms = filetime_ms(&kernel) + filetime_ms(&user);
+                }
+                CloseHandle(process);
+            }
+            let name_len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            samples.push(ProcessSample {
+                pid: entry.th32ProcessID,
+                ppid: entry.th32ParentProcessID,
+                rss_bytes,
+                cpu_ms,
+                name: String::from_utf16_lossy(&entry.szExeFile[..name_len]),
+            });
+            more = Process32NextW(snapshot, &mut entry) != 0;
+        }
+
+        CloseHandle(snapshot);
+        Some(samples)
+    }
+}
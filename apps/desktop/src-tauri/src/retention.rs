@@ -0,0 +1,173 @@
+//! Log Retention
+//!
+//! Sweeps the logs and crash report directories so long-lived installs
+//! don't grow without bound. A sweep runs at startup and then once a day,
+//! unless `logRetention.enabled` is turned off in config.json.
+//!
+//! A sweep:
+//! - deletes rotated log files older than `retentionDays`
+//! - keeps only the newest `MAX_CRASH_REPORTS` crash reports
+//! - deletes the oldest rotated logs until the logs directory fits in
+//!   `maxLogsMb`
+//!
+//! Files that are currently open for writing are passed in as `active` and
+//! are never touched.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::config::{Config, LogRetention};
+use crate::paths::AppPaths;
+
+/// Number of crash reports kept
+pub const MAX_CRASH_REPORTS: usize = 10;
+
+/// Time between sweeps after the startup one
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Files removed by a sweep
+#[derive(Debug, Default)]
+pub struct SweepReport {
+    pub removed: Vec<PathBuf>,
+    pub freed_bytes: u64,
+}
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Run one sweep over `paths` as of `now`, skipping the `active` files
+pub fn sweep(
+    paths: &AppPaths,
+    policy: &LogRetention,
+    active: &[PathBuf],
+    now: SystemTime,
+) -> io::Result<SweepReport> {
+    let mut report = SweepReport::default();
+
+    // Rotated logs past the retention period
+    let max_age = Duration::from_secs(u64::from(policy.retention_days) * 24 * 60 * 60);
+    let mut logs = Vec::new();
+    for entry in list_files(&paths.logs_dir)? {
+        let expired = now
+            .duration_since(entry.modified)
+            .is_ok_and(|age| age > max_age);
+        if is_rotated_log(&entry.path, active) && expired {
+            remove(entry, &mut report);
+        } else {
+            logs.push(entry);
+        }
+    }
+
+    // Crash reports beyond the newest few
+    let mut crashes = list_files(&paths.crash_dir)?;
+    crashes.sort_by_key(|entry| std::cmp::Reverse(entry.modified));
+    for entry in crashes.into_iter().skip(MAX_CRASH_REPORTS) {
+        if !active.contains(&entry.path) {
+            remove(entry, &mut report);
+        }
+    }
+
+    // Size budget for the logs directory, oldest rotated logs first
+    let budget = policy.max_logs_mb.saturating_mul(1024 * 1024);
+    let mut total: u64 = logs.iter().map(|entry| entry.size).sum();
+    logs.sort_by_key(|entry| entry.modified);
+    for entry in logs {
+        if total <= budget {
+            break;
+        }
+        if is_rotated_log(&entry.path, active) {
+            total -= entry.size;
+            remove(entry, &mut report);
+        }
+    }
+
+    Ok(report)
+}
+
+/// A log file that's no longer being written to
+fn is_rotated_log(path: &Path, active: &[PathBuf]) -> bool {
+    let is_log = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.contains(".log"));
+    is_log && !active.iter().any(|active| active == path)
+}
+
+/// Regular files directly inside `dir`. A missing directory has no files.
+fn list_files(dir: &Path) -> io::Result<Vec<Entry>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        files.push(Entry {
+            path: entry.path(),
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    Ok(files)
+}
+
+fn remove(entry: Entry, report: &mut SweepReport) {
+    match fs::remove_file(&entry.path) {
+        Ok(()) => {
+            println!("[retention] Removed {:?} ({} bytes)", entry.path, entry.size);
+            report.freed_bytes += entry.size;
+            report.removed.push(entry.path);
+        }
+        Err(e) => eprintln!("[retention] Failed to remove {:?}: {}", entry.path, e),
+    }
+}
+
+/// Run a sweep with the current config, unless retention is disabled
+pub fn run_scheduled_sweep() {
+    let policy = Config::load().map(|c| c.log_retention).unwrap_or_default();
+    if !policy.enabled {
+        println!("[retention] Log retention disabled in config, skipping sweep");
+        return;
+    }
+    let Some(paths) = AppPaths::resolve() else {
+        return;
+    };
+
+    // The activity log is rewritten in place and the gateway log is being
+    // appended to; both must survive any sweep
+    let active = vec![
+        paths.logs_dir.join("activity.json"),
+        paths.logs_dir.join(crate::gateway_logs::ACTIVE_FILE),
+    ];
+    match sweep(&paths, &policy, &active, SystemTime::now()) {
+        Ok(report) if !report.removed.is_empty() => println!(
+            "[retention] Removed {} files, freed {} bytes",
+            report.removed.len(),
+            report.freed_bytes
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("[retention] Sweep failed: {}", e),
+    }
+}
+
+/// Sweep at startup and then every `SWEEP_INTERVAL`
+pub fn spawn_retention_task() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            let _ = tokio::task::spawn_blocking(run_scheduled_sweep).await;
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,319 @@
+//! Running the Gateway as Another User
+//!
+//! With `runAsUser` set (Unix only) the gateway runs as that account, e.g. a
+//! dedicated `claw` user with no access to the operator's home directory.
+//! Switching users needs the app to run as root; without that the start
+//! fails instead of quietly running the gateway as the current user.
+//!
+//! The switch happens between fork and exec (see `process`): supplementary
+//! groups are dropped to the user's primary group, then the gid and uid are
+//! set. Before that the start checks the user can reach the gateway
+//! executable, its working directory (the sandbox workspace or the user's
+//! home) and the gateway state directory, and hands the workspace and the
+//! state directory over to the user. Only what the app created in its own
+//! data dir is handed over with everything in it; a `workspaceDir` or
+//! `gatewayStateDir` configured in there only has the directory itself
+//! handed over, and one configured anywhere else is refused, since it may
+//! be a home directory or a shared mount full of other people's files.
+//!
+//! Root is refused as a target, since the point is to have less privilege.
+
+#[cfg(unix)]
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::error::AppError;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RunAsError {
+    #[error("runAsUser is only supported on Unix")]
+    Unsupported,
+    #[error("No user named {0}")]
+    UnknownUser(String),
+    #[error("Running the gateway as root is not allowed; pick an unprivileged user")]
+    RootNotAllowed,
+    #[error("Running the gateway as {0} needs the app to run as root")]
+    InsufficientPrivileges(String),
+    #[error("{user} can't access {path}")]
+    NoAccess { user: String, path: String },
+    #[error("Could not hand {path} over to {user}: {detail}")]
+    Ownership {
+        user: String,
+        path: String,
+        detail: String,
+    },
+}
+
+/// An account from the user database
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunAsUser {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub home: PathBuf,
+}
+
+impl RunAsUser {
+    /// `HOME`, `USER` and `LOGNAME` for the gateway, so it keeps its state
+    /// in the user's home rather than the operator's
+    pub fn env(&self) -> Vec<(String, String)> {
+        vec![
+            ("HOME".to_string(), self.home.display().to_string()),
+            ("USER".to_string(), self.name.clone()),
+            ("LOGNAME".to_string(), self.name.clone()),
+        ]
+    }
+}
+
+/// Look up `name` in the user database
+#[cfg(unix)]
+pub fn resolve(name: &str) -> Result<RunAsUser, RunAsError> {
+    use std::ffi::{CStr, CString};
+
+    let unknown = || RunAsError::UnknownUser(name.to_string());
+    let c_name = CString::new(name).map_err(|_| unknown())?;
+    let mut buf: Vec<libc::c_char> = vec![0; 4096];
+    // SAFETY: getpwnam_r only writes into `pwd` and `buf`, which outlive
+    // the call, and strings in `pwd` point into `buf`.
+    unsafe {
+        let mut pwd: libc::passwd = std::mem::zeroed();
+        let mut result: *mut libc::passwd = std::ptr::null_mut();
+        loop {
+            let rc = libc::getpwnam_r(
+                c_name.as_ptr(),
+                &mut pwd,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut result,
+            );
+            if rc == libc::ERANGE && buf.len() < 1 << 20 {
+                buf.resize(buf.len() * 2, 0);
+                continue;
+            }
+            break;
+        }
+        if result.is_null() {
+            return Err(unknown());
+        }
+        Ok(RunAsUser {
+            name: name.to_string(),
+            uid: pwd.pw_uid,
+            gid: pwd.pw_gid,
+            home: PathBuf::from(CStr::from_ptr(pwd.pw_dir).to_string_lossy().into_owned()),
+        })
+    }
+}
+
+#[cfg(not(unix))]
+pub fn resolve(_name: &str) -> Result<RunAsUser, RunAsError> {
+    Err(RunAsError::Unsupported)
+}
+
+/// Our effective uid, None off Unix
+pub fn current_euid() -> Option<u32> {
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions and can't fail
+        Some(unsafe { libc::geteuid() })
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Whether an app running with `euid` can start processes as `user`.
+/// Running as the user already needs no switch.
+pub fn check_privileges(user: &RunAsUser, euid: u32) -> Result<(), RunAsError> {
+    if user.uid == 0 {
+        return Err(RunAsError::RootNotAllowed);
+    }
+    if euid != 0 && euid != user.uid {
+        return Err(RunAsError::InsufficientPrivileges(user.name.clone()));
+    }
+    Ok(())
+}
+
+/// Whether `user` can reach `path` through its parent directories and
+/// execute (or enter) it. Judged from the permission bits alone, ACLs are
+/// not considered.
+#[cfg(unix)]
+pub fn can_access(user: &RunAsUser, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let allows = |path: &Path, bits: u32| {
+        let Ok(meta) = fs::metadata(path) else {
+            return false;
+        };
+        let mode = meta.mode();
+        let granted = if meta.uid() == user.uid {
+            mode >> 6
+        } else if meta.gid() == user.gid {
+            mode >> 3
+        } else {
+            mode
+        };
+        granted & bits == bits
+    };
+
+    let reachable = path
+        .ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .all(|dir| allows(dir, 0o1));
+    reachable && allows(path, 0o1)
+}
+
+#[cfg(not(unix))]
+pub fn can_access(_user: &RunAsUser, _path: &Path) -> bool {
+    false
+}
+
+/// Everything `prepare` needs to run the gateway as `name`
+#[derive(Debug, Clone)]
+pub struct RunAsPlan {
+    pub user: RunAsUser,
+    /// Directory the gateway starts in, when no workspace is set
+    pub home_dir: Option<PathBuf>,
+    /// Directories given to the user at start, see `hand_over_scope`
+    pub hand_over: Vec<HandOver>,
+}
+
+/// A directory given to the run-as user at start
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandOver {
+    pub dir: PathBuf,
+    /// Everything in it too, only for a directory the app created
+    pub recursive: bool,
+}
+
+/// How much of `dir` may be handed over. `field` names the setting it was
+/// configured in, None for the app's default in `data_dir`: that one is
+/// handed over whole, a configured one in `data_dir` only itself, and a
+/// configured one anywhere else not at all.
+pub fn hand_over_scope(
+    dir: &Path,
+    field: Option<&str>,
+    data_dir: Option<&Path>,
+) -> Result<HandOver, AppError> {
+    let in_data_dir = data_dir.is_some_and(|data_dir| dir.starts_with(data_dir));
+    match field {
+        _ if !in_data_dir => Err(AppError::InvalidSetting {
+            field: field.unwrap_or("runAsUser").to_string(),
+            detail: format!(
+                "{} is outside the app data directory, so it can't be handed over to the \
+                 runAsUser account.",
+                dir.display()
+            ),
+        }),
+        Some(_) => Ok(HandOver {
+            dir: dir.to_path_buf(),
+            recursive: false,
+        }),
+        None => Ok(HandOver {
+            dir: dir.to_path_buf(),
+            recursive: true,
+        }),
+    }
+}
+
+/// Resolve `name` and check the gateway can run as it: we have the
+/// privileges, and it can run `program` and work in `workspace` (or its
+/// home without one).
+pub fn plan(
+    name: &str,
+    program: Option<&Path>,
+    workspace: Option<&Path>,
+    euid: Option<u32>,
+) -> Result<RunAsPlan, RunAsError> {
+    let euid = euid.ok_or(RunAsError::Unsupported)?;
+    let user = resolve(name)?;
+    check_privileges(&user, euid)?;
+
+    if let Some(program) = program {
+        if !can_access(&user, program) {
+            return Err(RunAsError::NoAccess {
+                user: user.name.clone(),
+                path: program.display().to_string(),
+            });
+        }
+    }
+    let home_dir = match workspace {
+        Some(workspace) => {
+            check_reachable(&user, workspace)?;
+            None
+        }
+        None => Some(user.home.clone()).filter(|home| home.is_dir()),
+    };
+    Ok(RunAsPlan {
+        user,
+        home_dir,
+        hand_over: Vec::new(),
+    })
+}
+
+/// Whether `user` can get to `dir`, which is created or handed over at
+/// start: the closest existing parent has to be reachable
+pub fn check_reachable(user: &RunAsUser, dir: &Path) -> Result<(), RunAsError> {
+    let parent = dir.parent().unwrap_or(dir);
+    let existing = parent
+        .ancestors()
+        .find(|dir| dir.exists())
+        .unwrap_or(parent);
+    if !can_access(user, existing) {
+        return Err(RunAsError::NoAccess {
+            user: user.name.clone(),
+            path: existing.display().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Make `dir`, and with `recursive` everything below it, owned by `user`,
+/// so a workspace created by the app (or by runs as another user) is
+/// usable. Symlinks themselves are re-owned but not followed.
+#[cfg(unix)]
+pub fn hand_over(dir: &Path, user: &RunAsUser, recursive: bool) -> Result<(), RunAsError> {
+    fn walk(path: &Path, uid: u32, gid: u32, recursive: bool) -> std::io::Result<()> {
+        use std::os::unix::fs::MetadataExt;
+
+        let meta = fs::symlink_metadata(path)?;
+        if meta.uid() != uid || meta.gid() != gid {
+            std::os::unix::fs::lchown(path, Some(uid), Some(gid))?;
+        }
+        if recursive && meta.is_dir() {
+            for entry in fs::read_dir(path)? {
+                walk(&entry?.path(), uid, gid, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    walk(dir, user.uid, user.gid, recursive).map_err(|e| RunAsError::Ownership {
+        user: user.name.clone(),
+        path: dir.display().to_string(),
+        detail: e.to_string(),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn hand_over(_dir: &Path, _user: &RunAsUser, _recursive: bool) -> Result<(), RunAsError> {
+    Err(RunAsError::Unsupported)
+}
+
+/// Effective uid of a running process, where the OS tells us
+pub fn process_euid(pid: u32) -> Option<u32> {
+    #[cfg(target_os = "linux")]
+    {
+        // "Uid:	real	effective	saved	filesystem"
+        let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+        let line = status.lines().find(|line| line.starts_with("Uid:"))?;
+        line.split_whitespace().nth(2)?.parse().ok()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
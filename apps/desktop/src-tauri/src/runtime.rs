@@ -14,10 +14,13 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+use crate::error::AppError;
+use crate::paths::AppPaths;
+
 /// Node.js version to bundle
 /// NOTE: Node 25+ is required to fix fetch timeout bugs in Node 22's undici implementation
 /// See: https://github.com/nodejs/undici/issues/3410
-const NODE_VERSION: &str = "25.6.0";
+pub const NODE_VERSION: &str = "25.6.0";
 
 /// Download URLs for different platforms
 fn get_node_url() -> Option<(&'static str, &'static str)> {
@@ -116,7 +119,7 @@ impl Default for RuntimeManager {
 impl RuntimeManager {
     /// Get the runtime directory
     pub fn runtime_dir() -> Option<PathBuf> {
-        dirs::data_local_dir().map(|d| d.join("simplestclaw").join("runtime"))
+        AppPaths::resolve().map(|paths| paths.runtime_dir)
     }
 
     /// Get path to bundled node binary
@@ -407,15 +410,18 @@ impl RuntimeManager {
 #[tauri::command]
 pub async fn get_runtime_status(
     manager: tauri::State<'_, RuntimeManager>,
-) -> Result<RuntimeStatus, String> {
+) -> Result<RuntimeStatus, AppError> {
     Ok(manager.status().await)
 }
 
 #[tauri::command]
 pub async fn install_runtime(
     manager: tauri::State<'_, RuntimeManager>,
-) -> Result<(), String> {
-    manager.install().await
+) -> Result<(), AppError> {
+    manager
+        .install()
+        .await
+        .map_err(|detail| AppError::RuntimeInstallFailed { detail })
 }
 
 #[tauri::command]
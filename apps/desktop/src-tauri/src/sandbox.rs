@@ -0,0 +1,169 @@
+//! Sandboxed Gateway Workspace
+//!
+//! With `sandboxWorkspace` enabled the gateway runs in a dedicated
+//! workspace directory: `workspaceDir` from config.json, or `workspace/`
+//! under the app data dir. The default workspace is app data and is
+//! removed with it; a configured one belongs to the user and is left alone.
+//!
+//! This is not a real jail. The gateway CLI has no option to restrict its
+//! file tools, so a prompt can still make them reach outside the workspace.
+//! `SandboxStatus` therefore lists exactly which measures are applied and
+//! which are not, and is shown in `GatewayStatus` so nobody mistakes a
+//! working directory for isolation.
+//!
+//! Without the sandbox the gateway still works in `workspaceDir` when one
+//! is set, which must exist and be writable, and otherwise in the home
+//! directory of the user it runs as, never in whatever directory the app
+//! was launched from.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+use crate::paths::AppPaths;
+
+/// What the sandbox actually does for the running gateway
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxStatus {
+    pub workspace: String,
+    /// Measures in force
+    pub applied: Vec<String>,
+    /// Restrictions that are not enforced, so users know the limits
+    pub not_enforced: Vec<String>,
+}
+
+/// Workspace setup for one gateway start, computed without side effects
+#[derive(Debug, Clone)]
+pub struct SandboxPlan {
+    /// Working directory of the gateway
+    pub workspace: PathBuf,
+    /// Private temp dir, set as `TMPDIR` (Linux only)
+    pub temp_dir: Option<PathBuf>,
+    pub status: SandboxStatus,
+}
+
+impl SandboxPlan {
+    /// Environment variables the sandbox adds
+    pub fn env(&self) -> Vec<(String, String)> {
+        match self.temp_dir {
+            Some(ref dir) => vec![("TMPDIR".to_string(), dir.display().to_string())],
+            None => Vec::new(),
+        }
+    }
+
+    /// Create the workspace (and temp dir) if they don't exist yet
+    pub fn create_dirs(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.workspace)?;
+        if let Some(ref dir) = self.temp_dir {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plan the sandbox for `config`, or `None` when it's disabled. `paths` is
+/// only needed when no `workspaceDir` is configured.
+pub fn plan(config: &Config, paths: Option<&AppPaths>) -> Result<Option<SandboxPlan>, String> {
+    if !config.sandbox_workspace {
+        return Ok(None);
+    }
+
+    let workspace = match (config.workspace_dir.as_ref(), paths) {
+        (Some(dir), _) => dir.clone(),
+        (None, Some(paths)) => paths.workspace_dir.clone(),
+        (None, None) => return Err("Could not determine the app data directory for the sandbox workspace.".to_string()),
+    };
+    validate_workspace(&workspace, paths, dirs::home_dir().as_deref())?;
+
+    let temp_dir = cfg!(target_os = "linux").then(|| workspace.join(".tmp"));
+
+    let mut applied = vec![format!("Working directory set to {}", workspace.display())];
+    if let Some(ref dir) = temp_dir {
+        applied.push(format!(
+            "Temporary files go to {} via TMPDIR (best effort, not enforced)",
+            dir.display()
+        ));
+    }
+    let not_enforced = vec![
+        "File access outside the workspace is not blocked; the gateway CLI has no option to restrict its tools".to_string(),
+        "Network access is not restricted".to_string(),
+    ];
+
+    Ok(Some(SandboxPlan {
+        status: SandboxStatus {
+            workspace: workspace.display().to_string(),
+            applied,
+            not_enforced,
+        },
+        workspace,
+        temp_dir,
+    }))
+}
+
+/// Reject workspaces that would expose more than intended: relative paths,
+/// filesystem roots, the home directory, anything containing the app's
+/// config (and with it the API key) and files.
+pub fn validate_workspace(
+    dir: &Path,
+    paths: Option<&AppPaths>,
+    home: Option<&Path>,
+) -> Result<(), String> {
+    if !dir.is_absolute() {
+        return Err(format!("Workspace {} must be an absolute path.", dir.display()));
+    }
+    if dir.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("Workspace {} must not contain '..'.", dir.display()));
+    }
+    if dir.parent().is_none() {
+        return Err(format!("Workspace {} is a filesystem root.", dir.display()));
+    }
+    if home.is_some_and(|home| home.starts_with(dir)) {
+        return Err(format!(
+            "Workspace {} would contain your home directory.",
+            dir.display()
+        ));
+    }
+    if paths.is_some_and(|paths| paths.config_dir.starts_with(dir)) {
+        return Err(format!(
+            "Workspace {} would contain the app config, including the API key.",
+            dir.display()
+        ));
+    }
+    if dir.exists() && !dir.is_dir() {
+        return Err(format!("Workspace {} is not a directory.", dir.display()));
+    }
+    Ok(())
+}
+
+/// Check `dir` can be the gateway's working directory without a sandbox:
+/// an existing directory the app may write to. Nothing is written.
+pub fn check_workspace_dir(dir: &Path) -> Result<(), String> {
+    if !dir.is_absolute() {
+        return Err(format!("Workspace {} must be an absolute path.", dir.display()));
+    }
+    if !dir.is_dir() {
+        return Err(format!("Workspace {} is not an existing directory.", dir.display()));
+    }
+    if !is_writable(dir) {
+        return Err(format!("Workspace {} is not writable.", dir.display()));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: `path` is a NUL-terminated string that outlives the call
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(dir: &Path) -> bool {
+    fs::metadata(dir).is_ok_and(|metadata| !metadata.permissions().readonly())
+}
@@ -0,0 +1,142 @@
+//! Scheduled Gateway Restarts
+//!
+//! The gateway leaks memory slowly, over days. With
+//! `autoRestartIntervalHours` set, a thread restarts a gateway that has
+//! run that long, keeping its token so clients only reconnect. It waits
+//! until the gateway is idle so an agent run is never cut off: no client
+//! connected (see `gateway_stats`), or no request answered for
+//! `autoRestartQuietMinutes`, since the app's own window stays connected.
+//!
+//! The time counts from the start of the running gateway process, so any
+//! new one, started for whatever reason, starts the wait over. A stopped
+//! or paused gateway isn't restarted, including a stop that comes between
+//! the decision and the restart (`restart_if_running_since`).
+//!
+//! `gateway://scheduled-restart` is emitted before the restart and again
+//! with its outcome, for the frontend to show.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::Config;
+use crate::gateway_logs;
+use crate::gateway_stats::GatewayStats;
+use crate::sidecar::{SidecarManager, StartResult};
+
+/// Event emitted before and after a scheduled restart
+pub const SCHEDULED_RESTART_EVENT: &str = "gateway://scheduled-restart";
+
+/// Time between checks whether a restart is due
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When a restart is due, from `autoRestartIntervalHours` and
+/// `autoRestartQuietMinutes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartSchedule {
+    pub interval: Duration,
+    pub quiet: Duration,
+}
+
+impl RestartSchedule {
+    /// None when scheduled restarts are off
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let hours = config.auto_restart_interval_hours.filter(|&hours| hours > 0)?;
+        Some(Self {
+            interval: Duration::from_secs(u64::from(hours) * 3600),
+            quiet: Duration::from_secs(u64::from(config.auto_restart_quiet_minutes) * 60),
+        })
+    }
+
+    /// Whether a gateway started at `started_at`, with `stats`, should be
+    /// restarted at `now` (all Unix ms)
+    pub fn is_due(&self, started_at: i64, stats: &GatewayStats, now: i64) -> bool {
+        let since = |at: i64| Duration::from_millis((now - at).max(0) as u64);
+        if since(started_at) < self.interval {
+            return false;
+        }
+        stats.clients_connected == 0
+            || since(stats.last_request_at.unwrap_or(started_at)) >= self.quiet
+    }
+}
+
+/// Where a scheduled restart is
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScheduledRestartPhase {
+    Restarting,
+    Restarted,
+    Failed,
+    /// The gateway was stopped or replaced before it could be restarted
+    Cancelled,
+}
+
+/// Payload of `gateway://scheduled-restart`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledRestartEvent {
+    pub phase: ScheduledRestartPhase,
+    /// How long the restarted gateway had run
+    pub uptime_secs: u64,
+    /// The new gateway, once restarted
+    pub result: Option<StartResult>,
+    pub error: Option<String>,
+}
+
+/// Start the thread that restarts the gateway on schedule
+pub fn spawn_scheduled_restarts(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+
+        let Some(schedule) = Config::load().ok().as_ref().and_then(RestartSchedule::from_config)
+        else {
+            continue;
+        };
+        let Some(manager) = app.try_state::<SidecarManager>() else {
+            continue;
+        };
+        let Some(started_at) = manager.running_since() else {
+            continue;
+        };
+        let now = gateway_logs::now_ms();
+        if manager.is_starting() || !schedule.is_due(started_at, &manager.stats(), now) {
+            continue;
+        }
+        restart(&app, &manager, started_at, ((now - started_at).max(0) / 1000) as u64);
+    });
+}
+
+/// Restart the gateway started at `started_at` and tell the frontend
+fn restart(app: &AppHandle, manager: &SidecarManager, started_at: i64, uptime_secs: u64) {
+    let event = |phase, result, error| ScheduledRestartEvent {
+        phase,
+        uptime_secs,
+        result,
+        error,
+    };
+    println!(
+        "[scheduled-restart] Gateway is idle after {} hours, restarting it",
+        uptime_secs / 3600
+    );
+    let _ = app.emit(
+        SCHEDULED_RESTART_EVENT,
+        event(ScheduledRestartPhase::Restarting, None, None),
+    );
+
+    let outcome = match manager.restart_if_running_since(started_at, "scheduled") {
+        Some(Ok(result)) => {
+            manager.metrics().record_auto_restart();
+            event(ScheduledRestartPhase::Restarted, Some(result), None)
+        }
+        Some(Err(e)) => {
+            eprintln!("[scheduled-restart] Failed to restart gateway: {}", e);
+            manager.metrics().record_auto_restart();
+            event(ScheduledRestartPhase::Failed, None, Some(e.to_string()))
+        }
+        None => {
+            println!("[scheduled-restart] Gateway was stopped or replaced, not restarting it");
+            event(ScheduledRestartPhase::Cancelled, None, None)
+        }
+    };
+    let _ = app.emit(SCHEDULED_RESTART_EVENT, outcome);
+}
@@ -0,0 +1,247 @@
+//! API Keys Sealed in secrets.json
+//!
+//! Without a keychain the API keys are kept in secrets.json next to
+//! config.json (see `keychain`), but not in plain text. They are sealed
+//! with XChaCha20-Poly1305 under a key derived with HKDF-SHA256 from the
+//! machine's ID (`/etc/machine-id` on Linux, the platform UUID on macOS,
+//! `MachineGuid` on Windows) and an app salt, and stored as
+//!
+//! ```json
+//! { "enc": "v1", "nonce": "…", "ciphertext": "…" }
+//! ```
+//!
+//! That keeps them out of backups and copies of the file, not from whoever
+//! can run code as the user on this machine. Keys are sealed on save, so
+//! plain ones written by older versions are sealed the next time the
+//! config is saved; loading takes both.
+//!
+//! A sealed key that doesn't open, most likely because secrets.json was
+//! copied from another machine, is left out of the loaded config and kept
+//! in `Config::unreadable_keys`, so starting asks for the key again with
+//! `api_key_unreadable`. Saves write it back as it was until it is
+//! replaced. Without a machine ID keys are written in plain text as before.
+
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// The only format so far
+pub const VERSION: &str = "v1";
+
+/// Mixed into the machine ID, so the key is this app's alone
+const SALT: &[u8] = b"simplestclaw config.json keys";
+
+/// What the derived key is for
+const INFO: &[u8] = b"sealed api keys v1";
+
+/// Field of the API key, as a JSON pointer
+pub const API_KEY_FIELD: &str = "/anthropicApiKey";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SealError {
+    #[error("sealed with {0}, which this version can't open")]
+    UnknownVersion(String),
+    #[error("sealed on another machine")]
+    WrongKey,
+    #[error("could not seal the key: {0}")]
+    Failed(String),
+}
+
+/// A key as secrets.json stores it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedKey {
+    pub enc: String,
+    /// Base64
+    pub nonce: String,
+    /// Base64, with the tag
+    pub ciphertext: String,
+}
+
+/// The key for `machine_id`
+pub fn derive_key(machine_id: &str) -> [u8; 32] {
+    let mut key = [0; 32];
+    Hkdf::<Sha256>::new(Some(SALT), machine_id.trim().as_bytes())
+        .expand(INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 length");
+    key
+}
+
+/// The key of this machine, None when it has no ID to derive one from
+pub fn machine_key() -> Option<&'static [u8; 32]> {
+    static KEY: OnceLock<Option<[u8; 32]>> = OnceLock::new();
+    KEY.get_or_init(|| machine_id().map(|id| derive_key(&id)))
+        .as_ref()
+}
+
+pub fn seal(key: &[u8; 32], secret: &str) -> Result<SealedKey, SealError> {
+    let mut nonce = [0; 24];
+    getrandom::getrandom(&mut nonce).map_err(|e| SealError::Failed(e.to_string()))?;
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(XNonce::from_slice(&nonce), secret.as_bytes())
+        .map_err(|e| SealError::Failed(e.to_string()))?;
+    let base64 = base64::engine::general_purpose::STANDARD;
+    Ok(SealedKey {
+        enc: VERSION.to_string(),
+        nonce: base64.encode(nonce),
+        ciphertext: base64.encode(ciphertext),
+    })
+}
+
+pub fn open(key: &[u8; 32], sealed: &SealedKey) -> Result<String, SealError> {
+    if sealed.enc != VERSION {
+        return Err(SealError::UnknownVersion(sealed.enc.clone()));
+    }
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let nonce = base64
+        .decode(&sealed.nonce)
+        .ok()
+        .filter(|nonce| nonce.len() == 24)
+        .ok_or(SealError::WrongKey)?;
+    let ciphertext = base64
+        .decode(&sealed.ciphertext)
+        .map_err(|_| SealError::WrongKey)?;
+    let secret = XChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| SealError::WrongKey)?;
+    String::from_utf8(secret).map_err(|_| SealError::WrongKey)
+}
+
+/// JSON pointers of the key fields in config.json `value`: the API key,
+/// the other providers' keys, those of the profiles not in use and the
+/// stored keys
+pub fn key_fields(value: &Value) -> Vec<String> {
+    let escape = |token: &str| token.replace('~', "~0").replace('/', "~1");
+    let mut fields = vec![API_KEY_FIELD.to_string()];
+    if let Some(keys) = value.get("providerKeys").and_then(Value::as_object) {
+        fields.extend(keys.keys().map(|provider| format!("/providerKeys/{}", escape(provider))));
+    }
+    if let Some(profiles) = value.get("profiles").and_then(Value::as_object) {
+        fields.extend(profiles.keys().map(|name| format!("/profiles/{}/apiKey", escape(name))));
+    }
+    if let Some(keys) = value.get("apiKeys").and_then(Value::as_array) {
+        fields.extend((0..keys.len()).map(|i| format!("/apiKeys/{}/key", i)));
+    }
+    fields
+}
+
+/// Seal the plain keys in config.json `value`
+pub fn seal_keys(value: &mut Value, key: &[u8; 32]) -> Result<(), SealError> {
+    for pointer in key_fields(value) {
+        let Some(field) = value.pointer_mut(&pointer) else {
+            continue;
+        };
+        if let Some(secret) = field.as_str() {
+            let sealed = seal(key, secret)?;
+            *field = serde_json::to_value(sealed).map_err(|e| SealError::Failed(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Open the sealed keys in config.json `value`, with `key` when there is
+/// one. Those that don't open are taken out and returned by pointer.
+pub fn open_keys(value: &mut Value, key: Option<&[u8; 32]>) -> BTreeMap<String, Value> {
+    let mut unreadable = BTreeMap::new();
+    for pointer in key_fields(value) {
+        let Some(field) = value.pointer_mut(&pointer) else {
+            continue;
+        };
+        if !field.is_object() {
+            continue;
+        }
+        let opened = serde_json::from_value::<SealedKey>(field.clone())
+            .map_err(|_| SealError::WrongKey)
+            .and_then(|sealed| open(key.ok_or(SealError::WrongKey)?, &sealed));
+        match opened {
+            Ok(secret) => *field = Value::String(secret),
+            Err(_) => {
+                if let Some(sealed) = remove(value, &pointer) {
+                    unreadable.insert(pointer, sealed);
+                }
+            }
+        }
+    }
+    unreadable
+}
+
+/// Put the keys `open_keys` couldn't open back where nothing replaced them
+pub fn restore_unreadable(value: &mut Value, unreadable: &BTreeMap<String, Value>) {
+    for (pointer, sealed) in unreadable {
+        if value.pointer(pointer).is_some_and(|field| !field.is_null()) {
+            continue;
+        }
+        let Some((parent, name)) = split(pointer) else {
+            continue;
+        };
+        if let Some(parent) = value.pointer_mut(parent).and_then(Value::as_object_mut) {
+            parent.insert(name, sealed.clone());
+        }
+    }
+}
+
+/// The parent pointer and the unescaped name of the field
+fn split(pointer: &str) -> Option<(&str, String)> {
+    let (parent, name) = pointer.rsplit_once('/')?;
+    Some((parent, name.replace("~1", "/").replace("~0", "~")))
+}
+
+fn remove(value: &mut Value, pointer: &str) -> Option<Value> {
+    let (parent, name) = split(pointer)?;
+    value.pointer_mut(parent)?.as_object_mut()?.remove(&name)
+}
+
+#[cfg(target_os = "linux")]
+fn machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .into_iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+}
+
+#[cfg(target_os = "macos")]
+fn machine_id() -> Option<String> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    // "IOPlatformUUID" = "0A1B2C3D-..."
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))
+        .and_then(|line| line.rsplit('"').nth(1))
+        .map(str::to_string)
+        .filter(|id| !id.is_empty())
+}
+
+#[cfg(windows)]
+fn machine_id() -> Option<String> {
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .output()
+        .ok()?;
+    // MachineGuid    REG_SZ    0a1b2c3d-...
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+        .filter(|id| id != "MachineGuid")
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn machine_id() -> Option<String> {
+    None
+}
@@ -0,0 +1,604 @@
+//! Settings Patch
+//!
+//! `set_config` changes any of the plain settings at once: every field
+//! given in the patch is checked, and only when all pass are they merged
+//! into config.json. A failure names the field (`invalid_setting` with
+//! `data.field`) so the settings UI can point at it.
+//!
+//! Keys, tokens, profiles, the proxy, TLS and the external gateway aren't part
+//! of the patch; they keep their own commands, which handle the keychain
+//! and what else they need. A patch naming them is refused as a whole.
+//!
+//! Nothing is restarted. When the gateway is running and a setting it was
+//! started with changed, the reply says `restartRequired`.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+use crate::blocked_tools;
+use crate::config::{
+    self, save_config, validate_openclaw_path, Config, ConfigView, Provider, Transport,
+    MAX_START_RETRIES,
+};
+use crate::error::AppError;
+use crate::gateway_options;
+use crate::gateway_socket;
+use crate::locale;
+use crate::log_level;
+use crate::metrics::AppMetrics;
+use crate::models;
+use crate::port;
+use crate::resolve;
+use crate::sandbox;
+use crate::sidecar::SidecarManager;
+use crate::spend;
+use crate::tray;
+
+/// Lowest port `set_config` accepts, keeping clear of the privileged ones
+pub const MIN_PORT: u16 = 1024;
+
+/// Settings the gateway reads at start; changing one while it runs needs
+/// a restart
+const START_SETTINGS: &[&str] = &[
+    "provider",
+    "gatewayPort",
+    "gatewayHost",
+    "transport",
+    "gatewaySocketPath",
+    "gatewayMaxMemoryMb",
+    "gatewayLowPriority",
+    "workspaceDir",
+    "sandboxWorkspace",
+    "nodePath",
+    "openclawPath",
+    "preferGlobalOpenclaw",
+    "skipOpenclawVersionCheck",
+    "locale",
+    "timezone",
+    "defaultModel",
+    "smallModel",
+    "gatewayLogLevel",
+    "gatewayExtraArgs",
+    "gatewayExtraEnv",
+    "cleanEnvironment",
+    "forwardDevEnv",
+    "blockedTools",
+    "spendLimitUsd",
+];
+
+/// The settings to change; missing fields stay as they are. For the
+/// nullable ones, `null` goes back to the default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ConfigPatch {
+    pub provider: Option<String>,
+    pub gateway_port: Option<u16>,
+    /// An IP address, or `localhost` for 127.0.0.1
+    pub gateway_host: Option<String>,
+    /// `tcp` or `unix`
+    pub transport: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub gateway_socket_path: Option<Option<PathBuf>>,
+    pub auto_select_port: Option<bool>,
+    pub auto_start_gateway: Option<bool>,
+    #[serde(alias = "minimizeToTray")]
+    pub keep_running_in_background: Option<bool>,
+    pub file_logging_enabled: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub gateway_max_memory_mb: Option<Option<u64>>,
+    pub gateway_low_priority: Option<bool>,
+    pub gateway_heartbeat_timeout_secs: Option<u64>,
+    pub gateway_startup_timeout_secs: Option<u64>,
+    pub start_max_retries: Option<u32>,
+    pub auto_restart_gateway: Option<bool>,
+    pub auto_restart_on_config_change: Option<bool>,
+    pub restart_on_wake: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub auto_restart_interval_hours: Option<Option<u32>>,
+    pub auto_restart_quiet_minutes: Option<u32>,
+    pub check_openclaw_updates: Option<bool>,
+    pub gateway_stop_grace_secs: Option<u64>,
+    pub sandbox_workspace: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub workspace_dir: Option<Option<PathBuf>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub node_path: Option<Option<PathBuf>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub openclaw_path: Option<Option<PathBuf>>,
+    pub prefer_global_openclaw: Option<bool>,
+    pub skip_openclaw_version_check: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub locale: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub timezone: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub default_model: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub small_model: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub gateway_log_level: Option<Option<String>>,
+    pub gateway_extra_args: Option<Vec<String>>,
+    pub gateway_extra_env: Option<BTreeMap<String, String>>,
+    pub clean_environment: Option<bool>,
+    pub forward_dev_env: Option<bool>,
+    pub token_in_url: Option<bool>,
+    pub allow_external_clients: Option<bool>,
+    pub self_test_on_start: Option<bool>,
+    pub blocked_tools: Option<Vec<String>>,
+    pub min_free_disk_mb: Option<u64>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub metrics_interval_secs: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub spend_limit_usd: Option<Option<f64>>,
+    /// Above 0 and up to 1
+    pub warn_at_fraction: Option<f64>,
+    pub hard_stop_on_limit: Option<bool>,
+}
+
+/// Tells `null` (Some(None)) apart from a missing field (None)
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// `error` as the failure of `field`
+fn invalid(field: &str, error: impl Into<AppError>) -> AppError {
+    AppError::InvalidSetting {
+        field: field.to_string(),
+        detail: error.into().message(),
+    }
+}
+
+fn invalid_detail(field: &str, detail: String) -> AppError {
+    AppError::InvalidSetting {
+        field: field.to_string(),
+        detail,
+    }
+}
+
+/// Trimmed, with empty as None
+fn optional_text(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn optional_path(value: Option<PathBuf>) -> Option<PathBuf> {
+    value.filter(|path| !path.as_os_str().is_empty())
+}
+
+fn check_port(port: u16) -> Result<u16, AppError> {
+    if port < MIN_PORT {
+        return Err(invalid_detail(
+            "gatewayPort",
+            format!("{} is not a port between {} and 65535.", port, MIN_PORT),
+        ));
+    }
+    Ok(port)
+}
+
+fn check_host(host: &str) -> Result<String, AppError> {
+    let host = host.trim();
+    if host.eq_ignore_ascii_case("localhost") {
+        return Ok("127.0.0.1".to_string());
+    }
+    host.parse::<IpAddr>()
+        .map(|ip| ip.to_string())
+        .map_err(|_| {
+            invalid(
+                "gatewayHost",
+                AppError::InvalidGatewayHost {
+                    host: host.to_string(),
+                },
+            )
+        })
+}
+
+fn check_positive(field: &str, value: u64) -> Result<u64, AppError> {
+    if value == 0 {
+        return Err(invalid_detail(field, "Must be more than 0.".to_string()));
+    }
+    Ok(value)
+}
+
+/// Set `target` to `value` if given, noting `field` when it differs
+fn set<T: PartialEq>(
+    changed: &mut Vec<&'static str>,
+    field: &'static str,
+    target: &mut T,
+    value: Option<T>,
+) {
+    if let Some(value) = value {
+        if *target != value {
+            *target = value;
+            changed.push(field);
+        }
+    }
+}
+
+/// Check `patch` and merge it into `config`. Returns the settings that
+/// changed; on a failure `config` is left as it was.
+pub fn apply(config: &mut Config, patch: ConfigPatch) -> Result<Vec<&'static str>, AppError> {
+    let provider = patch
+        .provider
+        .map(|provider| Provider::parse(&provider).map_err(|e| invalid("provider", e)))
+        .transpose()?;
+    let gateway_port = patch.gateway_port.map(check_port).transpose()?;
+    let gateway_host = patch.gateway_host.as_deref().map(check_host).transpose()?;
+    let transport = patch
+        .transport
+        .map(|transport| {
+            Transport::parse(&transport).ok_or_else(|| {
+                invalid_detail("transport", format!("{} is not tcp or unix.", transport))
+            })
+        })
+        .transpose()?;
+    let gateway_socket_path = patch.gateway_socket_path.map(optional_path);
+    // Checked once merged, as each depends on the other
+    let socket_field = match gateway_socket_path {
+        Some(_) => "gatewaySocketPath",
+        None => "transport",
+    };
+    let checks_socket = transport.is_some() || gateway_socket_path.is_some();
+    let gateway_max_memory_mb = patch
+        .gateway_max_memory_mb
+        .map(|mb| {
+            mb.map(|mb| check_positive("gatewayMaxMemoryMb", mb))
+                .transpose()
+        })
+        .transpose()?;
+    let auto_restart_interval_hours = patch
+        .auto_restart_interval_hours
+        .map(|hours| {
+            hours
+                .map(|hours| {
+                    check_positive("autoRestartIntervalHours", hours.into()).map(|_| hours)
+                })
+                .transpose()
+        })
+        .transpose()?;
+    let gateway_startup_timeout_secs = patch
+        .gateway_startup_timeout_secs
+        .map(|secs| config::check_startup_timeout("gatewayStartupTimeoutSecs", secs))
+        .transpose()?;
+    if let Some(retries) = patch.start_max_retries.filter(|&retries| retries > MAX_START_RETRIES) {
+        return Err(invalid_detail(
+            "startMaxRetries",
+            format!("{} is more than {} retries.", retries, MAX_START_RETRIES),
+        ));
+    }
+    let metrics_interval_secs = patch
+        .metrics_interval_secs
+        .map(|secs| {
+            secs.map(|secs| check_positive("metricsIntervalSecs", secs))
+                .transpose()
+        })
+        .transpose()?;
+    let spend_limit_usd = patch
+        .spend_limit_usd
+        .map(|usd| {
+            usd.map(|usd| spend::check_limit(usd).map_err(|e| invalid_detail("spendLimitUsd", e)))
+                .transpose()
+        })
+        .transpose()?;
+    let warn_at_fraction = patch
+        .warn_at_fraction
+        .map(|fraction| {
+            spend::check_warn_at(fraction).map_err(|e| invalid_detail("warnAtFraction", e))
+        })
+        .transpose()?;
+    let workspace_dir = patch.workspace_dir.map(optional_path);
+    if let Some(Some(ref dir)) = workspace_dir {
+        sandbox::check_workspace_dir(dir).map_err(|e| invalid_detail("workspaceDir", e))?;
+    }
+    let node_path = patch.node_path.map(optional_path);
+    if let Some(Some(ref path)) = node_path {
+        if !resolve::is_executable(path) {
+            return Err(invalid_detail(
+                "nodePath",
+                format!("{} is not an executable file.", path.display()),
+            ));
+        }
+    }
+    let openclaw_path = patch.openclaw_path.map(optional_path);
+    if let Some(Some(ref path)) = openclaw_path {
+        validate_openclaw_path(path).map_err(|e| invalid("openclawPath", e))?;
+    }
+    let locale = patch.locale.map(optional_text);
+    if let Some(Some(ref value)) = locale {
+        locale::validate_locale(value).map_err(|e| invalid("locale", e))?;
+    }
+    let timezone = patch.timezone.map(optional_text);
+    if let Some(Some(ref value)) = timezone {
+        locale::validate_timezone(value).map_err(|e| invalid("timezone", e))?;
+    }
+    let default_model = patch.default_model.map(optional_text);
+    let small_model = patch.small_model.map(optional_text);
+    for (field, model) in [("defaultModel", &default_model), ("smallModel", &small_model)] {
+        if let Some(Some(ref value)) = model {
+            models::validate(field, value).map_err(|e| invalid_detail(field, e.to_string()))?;
+        }
+    }
+    let gateway_log_level = patch
+        .gateway_log_level
+        .map(|level| optional_text(level).map(|level| level.to_ascii_lowercase()));
+    if let Some(Some(ref level)) = gateway_log_level {
+        log_level::validate(level).map_err(|e| invalid_detail("gatewayLogLevel", e.to_string()))?;
+    }
+    if patch.gateway_extra_args.is_some() || patch.gateway_extra_env.is_some() {
+        let args = patch
+            .gateway_extra_args
+            .as_ref()
+            .unwrap_or(&config.gateway_extra_args);
+        let env = patch
+            .gateway_extra_env
+            .as_ref()
+            .unwrap_or(&config.gateway_extra_env);
+        gateway_options::validate(args, env)
+            .map_err(|e| invalid_detail(e.field(), e.to_string()))?;
+    }
+    let blocked_tools = patch
+        .blocked_tools
+        .map(|tools| blocked_tools::validate(&tools).map_err(|e| invalid("blockedTools", e)))
+        .transpose()?;
+
+    let mut next = config.clone();
+    let mut changed = Vec::new();
+    let c = &mut changed;
+    set(c, "provider", &mut next.provider, provider);
+    set(c, "gatewayPort", &mut next.gateway_port, gateway_port);
+    set(c, "gatewayHost", &mut next.gateway_host, gateway_host);
+    set(c, "transport", &mut next.transport, transport);
+    set(
+        c,
+        "gatewaySocketPath",
+        &mut next.gateway_socket_path,
+        gateway_socket_path,
+    );
+    set(
+        c,
+        "autoSelectPort",
+        &mut next.auto_select_port,
+        patch.auto_select_port,
+    );
+    set(
+        c,
+        "autoStartGateway",
+        &mut next.auto_start_gateway,
+        patch.auto_start_gateway,
+    );
+    set(
+        c,
+        "keepRunningInBackground",
+        &mut next.keep_running_in_background,
+        patch.keep_running_in_background,
+    );
+    set(
+        c,
+        "fileLoggingEnabled",
+        &mut next.file_logging_enabled,
+        patch.file_logging_enabled,
+    );
+    set(
+        c,
+        "gatewayMaxMemoryMb",
+        &mut next.gateway_max_memory_mb,
+        gateway_max_memory_mb,
+    );
+    set(
+        c,
+        "gatewayLowPriority",
+        &mut next.gateway_low_priority,
+        patch.gateway_low_priority,
+    );
+    set(
+        c,
+        "gatewayHeartbeatTimeoutSecs",
+        &mut next.gateway_heartbeat_timeout_secs,
+        patch.gateway_heartbeat_timeout_secs,
+    );
+    set(
+        c,
+        "gatewayStartupTimeoutSecs",
+        &mut next.gateway_startup_timeout_secs,
+        gateway_startup_timeout_secs,
+    );
+    set(
+        c,
+        "startMaxRetries",
+        &mut next.start_max_retries,
+        patch.start_max_retries,
+    );
+    set(
+        c,
+        "autoRestartGateway",
+        &mut next.auto_restart_gateway,
+        patch.auto_restart_gateway,
+    );
+    set(
+        c,
+        "autoRestartOnConfigChange",
+        &mut next.auto_restart_on_config_change,
+        patch.auto_restart_on_config_change,
+    );
+    set(c, "restartOnWake", &mut next.restart_on_wake, patch.restart_on_wake);
+    set(
+        c,
+        "autoRestartIntervalHours",
+        &mut next.auto_restart_interval_hours,
+        auto_restart_interval_hours,
+    );
+    set(
+        c,
+        "autoRestartQuietMinutes",
+        &mut next.auto_restart_quiet_minutes,
+        patch.auto_restart_quiet_minutes,
+    );
+    set(
+        c,
+        "checkOpenclawUpdates",
+        &mut next.check_openclaw_updates,
+        patch.check_openclaw_updates,
+    );
+    set(
+        c,
+        "gatewayStopGraceSecs",
+        &mut next.gateway_stop_grace_secs,
+        patch.gateway_stop_grace_secs,
+    );
+    set(
+        c,
+        "sandboxWorkspace",
+        &mut next.sandbox_workspace,
+        patch.sandbox_workspace,
+    );
+    set(c, "workspaceDir", &mut next.workspace_dir, workspace_dir);
+    set(c, "nodePath", &mut next.node_path, node_path);
+    set(c, "openclawPath", &mut next.openclaw_path, openclaw_path);
+    set(
+        c,
+        "preferGlobalOpenclaw",
+        &mut next.prefer_global_openclaw,
+        patch.prefer_global_openclaw,
+    );
+    set(
+        c,
+        "skipOpenclawVersionCheck",
+        &mut next.skip_openclaw_version_check,
+        patch.skip_openclaw_version_check,
+    );
+    set(c, "locale", &mut next.locale, locale);
+    set(c, "timezone", &mut next.timezone, timezone);
+    set(c, "defaultModel", &mut next.default_model, default_model);
+    set(c, "smallModel", &mut next.small_model, small_model);
+    set(
+        c,
+        "gatewayLogLevel",
+        &mut next.gateway_log_level,
+        gateway_log_level,
+    );
+    set(
+        c,
+        "gatewayExtraArgs",
+        &mut next.gateway_extra_args,
+        patch.gateway_extra_args,
+    );
+    set(
+        c,
+        "gatewayExtraEnv",
+        &mut next.gateway_extra_env,
+        patch.gateway_extra_env,
+    );
+    set(
+        c,
+        "cleanEnvironment",
+        &mut next.clean_environment,
+        patch.clean_environment,
+    );
+    set(
+        c,
+        "forwardDevEnv",
+        &mut next.forward_dev_env,
+        patch.forward_dev_env,
+    );
+    set(c, "tokenInUrl", &mut next.token_in_url, patch.token_in_url);
+    set(
+        c,
+        "allowExternalClients",
+        &mut next.allow_external_clients,
+        patch.allow_external_clients,
+    );
+    set(
+        c,
+        "selfTestOnStart",
+        &mut next.self_test_on_start,
+        patch.self_test_on_start,
+    );
+    set(c, "blockedTools", &mut next.blocked_tools, blocked_tools);
+    set(
+        c,
+        "minFreeDiskMb",
+        &mut next.min_free_disk_mb,
+        patch.min_free_disk_mb,
+    );
+    set(
+        c,
+        "metricsIntervalSecs",
+        &mut next.metrics_interval_secs,
+        metrics_interval_secs,
+    );
+    set(c, "spendLimitUsd", &mut next.spend_limit_usd, spend_limit_usd);
+    set(c, "warnAtFraction", &mut next.warn_at_fraction, warn_at_fraction);
+    set(
+        c,
+        "hardStopOnLimit",
+        &mut next.hard_stop_on_limit,
+        patch.hard_stop_on_limit,
+    );
+    if checks_socket {
+        gateway_socket::plan(&next).map_err(|e| invalid(socket_field, e))?;
+    }
+    *config = next;
+    Ok(changed)
+}
+
+/// Whether changing `changed` only reaches a running gateway after a
+/// restart
+pub fn requires_restart(changed: &[&str]) -> bool {
+    changed.iter().any(|field| START_SETTINGS.contains(field))
+}
+
+/// Outcome of `set_config`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigUpdate {
+    /// The config as saved, like `get_config` returns it
+    pub config: ConfigView,
+    /// The settings that changed, as named in config.json
+    pub changed: Vec<&'static str>,
+    /// The running gateway was started with some of the old settings
+    pub restart_required: bool,
+    /// Worth telling about the new settings, like a port other software
+    /// commonly uses
+    pub warnings: Vec<String>,
+}
+
+/// Merge `patch` into config.json, refusing it with `invalid_setting`
+/// for the first field that doesn't pass
+#[tauri::command]
+pub async fn set_config(app: AppHandle, patch: ConfigPatch) -> Result<ConfigUpdate, AppError> {
+    // Asking the manager waits out a start, finding the program may ask a
+    // login shell
+    tokio::task::spawn_blocking(move || {
+        let mut config = Config::load()?;
+        let changed = apply(&mut config, patch)?;
+        if !changed.is_empty() {
+            save_config(&config, &app.state::<Arc<AppMetrics>>())?;
+            tray::config_changed(&app);
+            config::emit_changed(&app);
+        }
+        let manager = app.state::<SidecarManager>();
+        let restart_required = manager.gateway_pid().is_some() && requires_restart(&changed);
+        let effective_program = manager.find_program(&config).ok();
+        let warnings = changed
+            .contains(&"gatewayPort")
+            .then(|| port::conflict_warning(config.gateway_port))
+            .flatten()
+            .into_iter()
+            .collect();
+        Ok(ConfigUpdate {
+            config: ConfigView::new(config, effective_program),
+            changed,
+            restart_required,
+            warnings,
+        })
+    })
+    .await?
+}
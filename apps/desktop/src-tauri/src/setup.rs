@@ -0,0 +1,138 @@
+//! Setup Status
+//!
+//! `get_setup_status` answers everything the onboarding checklist needs in
+//! one call: is there an API key, is there an openclaw to run and is it
+//! new enough, is the port free, and is the gateway running. Each answer
+//! comes with a detail saying why when it isn't the one hoped for, such as
+//! the directories searched for node, so the same status also serves a
+//! diagnostics view.
+//!
+//! Nothing is spawned. The openclaw version is the one cached by the last
+//! start or `get_openclaw_version`, so it is unknown on a first run; that
+//! counts as supported, as it does for a start. The port is tried with a
+//! bind that is dropped straight away. `cliConfigDetected` says there is
+//! an openclaw CLI config `import_from_openclaw` could take settings from.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::cli_import;
+use crate::error::AppError;
+use crate::openclaw_version::{self, MIN_OPENCLAW_VERSION};
+use crate::port;
+use crate::sidecar::SidecarManager;
+
+/// What `get_setup_status` reports. Each `*_detail` explains the answer
+/// before it, None when there is nothing to add.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SetupStatus {
+    /// The selected provider has a key
+    pub has_api_key: bool,
+    pub api_key_detail: Option<String>,
+    /// There is an openclaw to run, directly or through npx
+    pub openclaw_found: bool,
+    /// What a start would run: openclaw itself, or node for npx
+    pub openclaw_path: Option<String>,
+    pub openclaw_detail: Option<String>,
+    pub openclaw_version: Option<String>,
+    /// False only for a known version that a start would refuse
+    pub version_supported: bool,
+    pub version_detail: Option<String>,
+    /// A start can listen on the configured port, or pick another
+    pub port_available: bool,
+    pub port_detail: Option<String>,
+    pub gateway_running: bool,
+    pub gateway_detail: Option<String>,
+    /// The openclaw CLI has a config to import settings from
+    pub cli_config_detected: bool,
+}
+
+/// The setup status for starts by `manager`
+pub fn status(manager: &SidecarManager) -> Result<SetupStatus, AppError> {
+    let config = manager.load_config()?;
+    let gateway = manager.status();
+
+    let has_api_key = config.provider_key(config.provider).is_some();
+    let api_key_detail = (!has_api_key)
+        .then(|| format!("No API key is configured for {}", config.provider.as_str()));
+
+    let program = manager.find_program(&config);
+    let openclaw_detail = match program {
+        Err(ref e) => Some(match e {
+            AppError::GatewayNotFound { detail, searched } if !searched.is_empty() => {
+                format!("{} Searched: {}", detail, searched.join(", "))
+            }
+            e => e.to_string(),
+        }),
+        Ok(ref node) if manager.uses_npx(&config) => {
+            Some(format!("openclaw runs through npx with node {}", node))
+        }
+        Ok(_) => None,
+    };
+    let openclaw_path = program.ok();
+
+    let version = openclaw_path.as_deref().and_then(openclaw_version::cached);
+    let check = openclaw_version::check(version, &config);
+    let version_detail = match (version, &check) {
+        (_, Err(e)) => Some(e.to_string()),
+        (Some(version), Ok(())) if version < MIN_OPENCLAW_VERSION => Some(format!(
+            "openclaw {} is older than {}; skipOpenclawVersionCheck is on",
+            version, MIN_OPENCLAW_VERSION
+        )),
+        (Some(_), Ok(())) => None,
+        (None, Ok(())) if openclaw_path.is_some() => {
+            Some("The version is read on the first start".to_string())
+        }
+        (None, Ok(())) => None,
+    };
+
+    let (port_available, port_detail) = match config.gateway_ip() {
+        Err(e) => (false, Some(e.to_string())),
+        Ok(_) if gateway.running => (true, None),
+        Ok(ip) if port::is_free(ip, config.gateway_port) => (true, None),
+        Ok(_) if config.auto_select_port => (
+            true,
+            Some(format!(
+                "Port {} is in use; a start will pick a free one",
+                config.gateway_port
+            )),
+        ),
+        Ok(_) => (
+            false,
+            Some(format!(
+                "Port {} is in use by another program",
+                config.gateway_port
+            )),
+        ),
+    };
+
+    let gateway_detail = match gateway.last_exit_code {
+        _ if gateway.running => None,
+        Some(code) => Some(format!("The gateway exited with code {}", code)),
+        None => Some("The gateway has not been started".to_string()),
+    };
+
+    Ok(SetupStatus {
+        has_api_key,
+        api_key_detail,
+        openclaw_found: openclaw_path.is_some(),
+        openclaw_path,
+        openclaw_detail,
+        openclaw_version: version.map(|version| version.to_string()),
+        version_supported: check.is_ok(),
+        version_detail,
+        port_available,
+        port_detail,
+        gateway_running: gateway.running,
+        gateway_detail,
+        cli_config_detected: cli_import::find().is_some(),
+    })
+}
+
+/// Everything onboarding checks, without starting or spawning anything
+#[tauri::command]
+pub async fn get_setup_status(app: AppHandle) -> Result<SetupStatus, AppError> {
+    // Finding openclaw may ask a login shell
+    tokio::task::spawn_blocking(move || status(&app.state::<SidecarManager>())).await?
+}
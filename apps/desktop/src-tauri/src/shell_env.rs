@@ -0,0 +1,139 @@
+//! Login Shell Environment
+//!
+//! Apps launched from the Dock or a desktop launcher don't see what the
+//! user's shell profile sets up, so the gateway's git and shell tools can
+//! miss `SSH_AUTH_SOCK`, `GIT_AUTHOR_*` or `GPG_TTY`. With `forwardDevEnv`
+//! enabled these are read from a login shell on every gateway start (agent
+//! sockets change between logins) and injected into the gateway's
+//! environment. In clean-environment mode this extends the allow-list.
+//!
+//! `probe_login_shell` is the general helper: it runs the shell with
+//! `-l -i -c`, prints the requested variables between markers so banners
+//! from rc files don't get in the way, and gives up after a timeout.
+
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::environment;
+
+/// Variables forwarded with `forwardDevEnv`
+pub const DEV_ENV_VARS: &[&str] = &[
+    "SSH_AUTH_SOCK",
+    "SSH_AGENT_PID",
+    "GPG_TTY",
+    "GPG_AGENT_INFO",
+    "GIT_AUTHOR_NAME",
+    "GIT_AUTHOR_EMAIL",
+    "GIT_COMMITTER_NAME",
+    "GIT_COMMITTER_EMAIL",
+    "GIT_CONFIG_GLOBAL",
+    "GIT_SSH_COMMAND",
+];
+
+/// How long a login shell may take to print its environment
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MARKER: &str = "__SIMPLESTCLAW_ENV__";
+
+/// The user's login shell
+pub fn user_shell() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return None;
+    }
+    match std::env::var_os("SHELL") {
+        Some(shell) if !shell.is_empty() => Some(PathBuf::from(shell)),
+        _ if cfg!(target_os = "macos") => Some(PathBuf::from("/bin/zsh")),
+        _ => Some(PathBuf::from("/bin/sh")),
+    }
+}
+
+/// Values of `names` as seen by a login shell. Unset and empty variables
+/// are left out.
+pub fn probe_login_shell(
+    shell: &Path,
+    names: &[&str],
+    timeout: Duration,
+) -> io::Result<Vec<(String, String)>> {
+    // Names are our own constants, so they can go into the script as is
+    let mut script = format!("printf '%s' '{}';", MARKER);
+    for name in names {
+        script.push_str(&format!(" printf '%s\\0' \"{}=${}\";", name, name));
+    }
+    script.push_str(&format!(" printf '%s' '{}'", MARKER));
+
+    let mut child = Command::new(shell)
+        .args(["-l", "-i", "-c", &script])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Read on a thread so a chatty rc file can't fill the pipe and stall
+    // the shell while we wait for it
+    let mut stdout = child.stdout.take();
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(ref mut stdout) = stdout {
+            let _ = stdout.read_to_string(&mut output);
+        }
+        output
+    });
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if child.try_wait()?.is_some() {
+            break;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} did not finish within {:?}", shell.display(), timeout),
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    let output = reader.join().unwrap_or_default();
+    parse_probe_output(&output).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "login shell printed no environment")
+    })
+}
+
+/// Extract `NAME=value` pairs printed between the markers, or `None` if
+/// the markers are missing
+pub fn parse_probe_output(output: &str) -> Option<Vec<(String, String)>> {
+    let start = output.find(MARKER)? + MARKER.len();
+    let end = start + output[start..].find(MARKER)?;
+    Some(
+        output[start..end]
+            .split('\0')
+            .filter_map(|entry| entry.split_once('='))
+            .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    )
+}
+
+/// Developer variables to inject into the gateway: the login shell's
+/// values, falling back to the app's own for anything the shell didn't
+/// provide (and on Windows, where there is no login shell to ask)
+pub fn dev_env() -> Vec<(String, String)> {
+    let mut vars = match user_shell() {
+        Some(shell) => probe_login_shell(&shell, DEV_ENV_VARS, PROBE_TIMEOUT).unwrap_or_else(|e| {
+            eprintln!("[shell-env] Failed to read the login shell environment: {}", e);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    for (name, value) in environment::app_env() {
+        let wanted = DEV_ENV_VARS.contains(&name.as_str());
+        if wanted && !value.is_empty() && !vars.iter().any(|(known, _)| *known == name) {
+            vars.push((name, value));
+        }
+    }
+    vars
+}
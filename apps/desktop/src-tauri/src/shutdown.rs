@@ -0,0 +1,72 @@
+//! Stopping the Gateway on Exit
+//!
+//! The gateway must not outlive the app. `shutdown` runs on
+//! `RunEvent::ExitRequested` and again on `RunEvent::Exit`, which is all a
+//! Cmd+Q with no window left gets, and on unix for SIGINT and SIGTERM,
+//! which Tauri doesn't handle. Whichever comes first stops the gateway
+//! and any other instances and releases the single-instance lock; the
+//! rest do nothing. The headless mode, which has no app, stops its gateway
+//! with `stop_gateway` the same way.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager};
+
+use crate::config_watch;
+use crate::instances;
+use crate::sidecar::SidecarManager;
+use crate::single_instance::InstanceLock;
+
+static SHUT_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Stop the gateway and flush its history, once per process
+pub fn shutdown(app: &AppHandle, reason: &str) {
+    if SHUT_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    println!("[app] {}, cleaning up...", reason);
+    config_watch::stop(app);
+    instances::stop_all(app);
+    if let Some(manager) = app.try_state::<SidecarManager>() {
+        stop_gateway(&manager);
+    }
+    // Last, so a new launch doesn't find this gateway still going
+    if let Some(lock) = app.try_state::<InstanceLock>() {
+        lock.release();
+    }
+}
+
+/// Stop `manager`'s gateway and flush its history, for `shutdown` and the
+/// headless mode
+pub fn stop_gateway(manager: &SidecarManager) {
+    if let Err(e) = manager.stop() {
+        eprintln!("[app] Failed to stop the gateway: {}", e);
+    }
+    manager.history().flush();
+}
+
+/// Shut down and exit on SIGINT or SIGTERM
+#[cfg(unix)]
+pub fn spawn_signal_handler(app: AppHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tauri::async_runtime::spawn(async move {
+        let (Ok(mut interrupt), Ok(mut terminate)) = (
+            signal(SignalKind::interrupt()),
+            signal(SignalKind::terminate()),
+        ) else {
+            eprintln!("[app] Failed to install the signal handler");
+            return;
+        };
+        let (name, number) = tokio::select! {
+            _ = interrupt.recv() => ("SIGINT", libc::SIGINT),
+            _ = terminate.recv() => ("SIGTERM", libc::SIGTERM),
+        };
+        shutdown(&app, &format!("Received {}", name));
+        app.exit(128 + number);
+    });
+}
+
+/// Windows has no SIGTERM; a gateway whose app is killed there goes with
+/// its job object instead
+#[cfg(not(unix))]
+pub fn spawn_signal_handler(_app: AppHandle) {}
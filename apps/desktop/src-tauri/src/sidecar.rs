@@ -1,6 +1,8 @@
 //! OpenClaw Gateway Management
 //!
-//! Uses the bundled Node.js runtime to run OpenClaw gateway.
+//! Runs the openclaw CLI bundled with the app when there is one (see
+//! `bundled`), and otherwise uses the bundled Node.js runtime to run the
+//! OpenClaw gateway through npx.
 //! No global Node.js installation required - works for everyone!
 //!
 //! The app automatically downloads a portable Node.js runtime on first launch,
@@ -10,34 +12,349 @@
 //! - OpenClaw gateway: https://docs.clawd.bot/cli/gateway
 
 use serde::{Deserialize, Serialize};
-use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
+use std::collections::VecDeque;
+use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, TryLockError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use tauri::AppHandle;
+use tauri::Emitter;
 use tauri::Manager;
 
-use crate::config::Config;
+use crate::adopted::{self, AdoptedProcess};
+use crate::anthropic_endpoint;
+use crate::blocked_tools::{self, BlockedToolsError, GatewayVersion};
+use crate::bundled;
+use crate::capabilities::{self, CapabilityError, FlagSupport, GatewayCapabilities};
+use crate::config::{self, Config, ConfigError};
+use crate::connection;
+use crate::environment::{self, EffectiveEnv, EnvSource, GatewayEnv};
+use crate::error::AppError;
+use crate::external::{self, ExternalGateway};
+use crate::gateway_logs::{self, GatewayLogs, LogLine};
+use crate::gateway_metrics::{self, CpuSample, GatewayMetrics};
+use crate::gateway_options;
+use crate::gateway_socket::{self, Endpoint};
+use crate::gateway_stats::GatewayStats;
+use crate::gateway_state;
+use crate::heartbeat::{self, ActivityReport, GatewayHealth, HeartbeatMonitor, PortProbe};
+use crate::instances::{self, Peers};
+use crate::keychain;
+use crate::limits::{self, ConcurrencyLimits, LimitEnforcement};
+use crate::locale::{self, GatewayLocale};
+use crate::log_level;
+use crate::models;
+use crate::log_search::{self, SearchOptions, SearchResult};
+use crate::metrics::AppMetrics;
+use crate::openclaw_version::{
+    self, VersionError, VersionInfo, MIN_OPENCLAW_VERSION, RECOMMENDED_OPENCLAW_VERSION,
+};
+use crate::process::{
+    GatewayProcess, LaunchSpec, ProcessExit, ProcessLauncher, ProcessPriority, ProcessUser,
+    StopKind, SystemLauncher,
+};
+use crate::paths::{self, AppPaths, DiskSpace, InsufficientSpace, SystemDiskSpace};
+use crate::pid_file::{self, GatewayRecord, Leftover, PidFile};
+use crate::port::{self, PortAvailability, PortMismatch, PortSource};
+use crate::profiles;
+use crate::protocol;
+use crate::proxy;
+use crate::ready::{ReadyError, ReadyProgress, ReadyResult, ReadyStage};
+use crate::resolve::{self, SearchContext};
+use crate::resources;
+use crate::run_as::{self, RunAsPlan};
 use crate::runtime::RuntimeManager;
+use crate::sandbox::{self, SandboxPlan, SandboxStatus};
+use crate::shell_env;
+use crate::spawn_failure::{self, SpawnFailure};
+use crate::spend::{self, UsageEstimate};
+use crate::status_events::{
+    GatewayPhase, ProgressListener, StartFailedEvent, StartPhase, StartPhaseEvent, StartProgress,
+    StartReadyEvent, StartRetryEvent, StatusChange, StatusListener,
+};
+use crate::status_history::{StatusHistory, StatusTransition, TransitionKind};
+use crate::supervisor::{Crash, CrashInfo, RestartBackoff, CRASH_STDERR_LINES};
+use crate::tls;
 
 /// Gateway connection info returned to the frontend
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GatewayInfo {
+    /// Includes `?token=` when `tokenInUrl` is enabled. `ws+unix://` and
+    /// the socket path for a gateway on a socket.
     pub url: String,
-    pub port: u16,
+    /// None for a gateway on a socket
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// The socket the gateway listens on with the `unix` transport (see
+    /// `gateway_socket`)
+    #[serde(default)]
+    pub socket_path: Option<String>,
     pub token: String,
+    /// Listening on more than loopback, reachable from other machines
+    #[serde(default)]
+    pub lan_exposed: bool,
+    /// Directory the gateway works in, None for an external gateway
+    #[serde(default)]
+    pub workspace: Option<String>,
+    /// The start that spawned this gateway (see `SidecarManager::generation`),
+    /// 0 for an external gateway
+    #[serde(default)]
+    pub generation: u64,
+}
+
+impl GatewayInfo {
+    /// Where the app connects to this gateway, on `probe_ip` when it
+    /// listens on a port
+    pub fn endpoint(&self, probe_ip: IpAddr) -> Endpoint {
+        match self.socket_path {
+            Some(ref path) => Endpoint::Unix(PathBuf::from(path)),
+            None => Endpoint::Tcp(SocketAddr::new(probe_ip, self.port.unwrap_or_default())),
+        }
+    }
+
+    /// "port 18789" or "socket /run/user/1000/simplestclaw/gateway.sock"
+    pub fn listen_address(&self) -> String {
+        match self.socket_path {
+            Some(ref path) => format!("socket {}", path),
+            None => format!("port {}", self.port.unwrap_or_default()),
+        }
+    }
+}
+
+/// A non-fatal condition noticed while starting the gateway.
+/// The start still succeeds; the frontend can show these in a dismissible banner.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartWarning {
+    pub code: String,
+    pub message: String,
+}
+
+impl StartWarning {
+    fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
 }
 
+/// Sets `SidecarManager::starting` until dropped, so a start that panics
+/// doesn't leave it set
+struct Starting<'a>(&'a AtomicBool);
+
+impl<'a> Starting<'a> {
+    fn new(flag: &'a AtomicBool) -> Self {
+        flag.store(true, Ordering::SeqCst);
+        Self(flag)
+    }
+}
+
+impl Drop for Starting<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A start that spawned or tried to, see `status_events`
+struct StartAttempt {
+    generation: u64,
+    began: Instant,
+    phase: Option<StartPhase>,
+    /// It failed for a reason that may pass, see `transient`
+    retryable: bool,
+}
+
+/// Token a restart hands on to the next gateway, see `start_with_token`
+#[derive(Debug, Clone)]
+struct KeptToken {
+    token: String,
+    /// Only handed on to a gateway on this port, None for any. One that
+    /// moved gets a new token, so `GatewayInfo` held from before the move
+    /// is of no use rather than half right.
+    port: Option<u16>,
+}
+
+/// Keeps the token of a started gateway, see `set_token_saver`
+pub type TokenSaver = Box<dyn Fn(&str) -> Result<(), AppError> + Send + Sync>;
+
+/// Success payload of `start_gateway`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct StartResult {
+    pub info: GatewayInfo,
+    pub warnings: Vec<StartWarning>,
+}
+
+/// How the executable used to launch the gateway was found
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExecutableSource {
+    /// The Node.js runtime downloaded and managed by the app
+    Managed,
+    /// Found on the app's PATH
+    Path,
+    /// Found on the PATH set up by the user's login shell
+    LoginShell,
+    /// Found in a node version manager directory (nvm, volta, fnm, asdf,
+    /// mise) or pnpm's or yarn's global bin
+    VersionManager,
+    /// Found in the Homebrew prefix
+    Brew,
+    /// Found in a standard system location
+    System,
+    /// Set explicitly when the manager was created
+    Override,
+    /// The openclaw CLI bundled with the app as a sidecar
+    Bundled,
+    /// `nodePath` or `openclawPath` from config
+    Config,
+}
+
+/// The executable actually running the gateway.
+/// Fields that couldn't be determined are null.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutableInfo {
+    pub path: Option<String>,
+    pub version: Option<String>,
+    pub source: Option<ExecutableSource>,
+}
+
+/// How the most recent gateway was launched. Only the names of the
+/// variables the app set are kept, and the token in `args` is redacted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchDescriptor {
+    /// The program spawned: openclaw itself, or node for npx
+    pub binary_path: String,
+    pub args: Vec<String>,
+    pub env_keys: Vec<String>,
+    /// Unix timestamp (ms) of the spawn
+    pub started_at: i64,
+    pub cwd: Option<String>,
+}
+
+/// The most recent spawn, with the info handed out once it was ready
+struct Launch {
+    descriptor: LaunchDescriptor,
+    pid: u32,
+    info: Option<GatewayInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct GatewayStatus {
     pub running: bool,
+    /// `externalGateway` is set and no gateway the app started runs, so
+    /// `running` means it can be reached
+    pub external: bool,
+    /// The app started the gateway and stops it on quit; false for an
+    /// adopted or external one, see `adopted`
+    pub managed: bool,
     pub info: Option<GatewayInfo>,
+    pub pid: Option<u32>,
+    pub executable: Option<ExecutableInfo>,
+    /// Scheduling priority the gateway is actually running at
+    pub priority: Option<ProcessPriority>,
+    /// Effective uid of the gateway process (Unix)
+    pub effective_uid: Option<u32>,
+    /// Workspace restrictions in force, when `sandboxWorkspace` is on
+    pub sandbox: Option<SandboxStatus>,
+    /// Tools the running gateway was started with disabled
+    pub blocked_tools: Vec<String>,
+    /// Concurrency limits of the running gateway and who enforces them
+    pub limits: ConcurrencyLimits,
+    /// `gatewayPort` from config.json, None if it can't be read
+    pub configured_port: Option<u16>,
+    /// Port the running gateway actually serves on
+    pub effective_port: Option<u16>,
+    /// Why the two ports differ, when they do
+    pub port_mismatch: Option<PortMismatch>,
+    /// Health from the port probe and the frontend's activity reports
+    pub health: GatewayHealth,
+    /// Whether the gateway's port accepted a connection, as of at most
+    /// `heartbeat::PROBE_INTERVAL` ago
+    pub accepting_connections: Option<bool>,
+    /// Unix timestamp (ms) of the last response the frontend got
+    pub last_response_at: Option<i64>,
+    /// Unix timestamp (ms) of when the running gateway became ready
+    pub started_at: Option<i64>,
+    /// Seconds since `started_at`; None in `gateway://status` events
+    pub uptime_secs: Option<u64>,
+    /// Exit code of the last gateway that exited on its own, None when it
+    /// was killed by a signal or none has
+    pub last_exit_code: Option<i32>,
+    /// Signal that killed it (Unix)
+    pub last_exit_signal: Option<i32>,
+    /// Unix timestamp (ms) of that exit
+    pub last_exit_at: Option<i64>,
+    /// The last crash and its stderr, until a start succeeds; a stop
+    /// doesn't set it
+    pub last_crash: Option<CrashInfo>,
+    /// Consecutive restarts after crashes, see `supervisor`
+    pub crash_restarts: u32,
+    /// The gateway is suspended by `pause`; it still counts as running
+    pub paused: bool,
+    /// A start is in progress. Until it finishes the other fields describe
+    /// no gateway, since the start holds the state.
+    pub starting: bool,
+    /// Spawn attempts so far; `info.generation` once the last one is ready
+    pub generation: u64,
     pub error: Option<String>,
 }
 
+/// Answer of `validate_gateway_info`; the default is the one when no
+/// gateway runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationResult {
+    /// The info held describes the running gateway
+    pub valid: bool,
+    /// Generation of the running gateway, None when none runs
+    pub generation: Option<u64>,
+    /// The running gateway's info to use instead, when the held one is
+    /// stale and a gateway runs
+    pub info: Option<GatewayInfo>,
+}
+
 pub struct SidecarState {
-    pub child: Option<Child>,
+    pub child: Option<Box<dyn GatewayProcess>>,
     pub info: Option<GatewayInfo>,
+    /// Warnings collected during the start of the current child
+    pub warnings: Vec<StartWarning>,
+    /// Executable resolved for the current child, re-resolved on every start
+    pub executable: Option<ExecutableInfo>,
+    pub sandbox: Option<SandboxStatus>,
+    pub blocked_tools: Vec<String>,
+    pub limits: ConcurrencyLimits,
+    /// uid the current child was started as, when `runAsUser` is set
+    pub run_as_uid: Option<u32>,
+    /// Where the port in `info` came from
+    pub port_source: Option<PortSource>,
+    /// Address the app reaches the current child at
+    pub probe_ip: Option<IpAddr>,
+    /// Unix timestamp (ms) of when the current child became ready
+    pub started_at: Option<i64>,
+    /// How long the current child gets to shut down when stopped
+    pub stop_grace: Duration,
+    /// How the last gateway that exited on its own did, and when (Unix ms).
+    /// Kept by `clear`, so it can still be reported after the child is gone.
+    pub last_exit: Option<(ProcessExit, i64)>,
+    /// CPU time of the current child at the last metrics sample, for the
+    /// next percentage (see `gateway_metrics`)
+    pub cpu_sample: Option<CpuSample>,
+    /// The current child is suspended, see `pause`
+    pub paused: bool,
+    /// Clients and requests of the current child, counted by its output
+    /// readers
+    pub stats: Arc<Mutex<GatewayStats>>,
+    /// Model usage of the current child, see `spend`
+    pub usage: Arc<Mutex<UsageEstimate>>,
 }
 
 impl Default for SidecarState {
@@ -45,528 +362,3709 @@ impl Default for SidecarState {
         Self {
             child: None,
             info: None,
+            warnings: Vec::new(),
+            executable: None,
+            sandbox: None,
+            blocked_tools: Vec::new(),
+            limits: ConcurrencyLimits::default(),
+            run_as_uid: None,
+            port_source: None,
+            probe_ip: None,
+            started_at: None,
+            stop_grace: Duration::from_secs(config::DEFAULT_STOP_GRACE_SECS),
+            last_exit: None,
+            cpu_sample: None,
+            paused: false,
+            stats: Arc::default(),
+            usage: Arc::default(),
+        }
+    }
+}
+
+impl SidecarState {
+    /// Forget the current child and everything recorded about it, except
+    /// how the last one exited
+    pub fn clear(&mut self) {
+        *self = Self {
+            last_exit: self.last_exit,
+            ..Self::default()
+        };
+    }
+}
+
+/// How long the auth self-test after a start may take
+const SELF_TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long after spawning the gateway must stay up to count as started,
+/// even once it is listening
+const STARTUP_GRACE: Duration = Duration::from_secs(1);
+
+/// `e` from suspending or resuming the gateway
+fn pause_error(e: std::io::Error) -> AppError {
+    match e.kind() {
+        std::io::ErrorKind::Unsupported => AppError::GatewayPauseUnsupported,
+        _ => AppError::GatewayPauseFailed {
+            detail: e.to_string(),
+        },
+    }
+}
+
+/// Lines of each stream a start that timed out reports
+const STARTUP_OUTPUT_LINES: usize = 20;
+
+/// Lines of stderr a start keeps for its errors, see `StderrTail`
+const STARTUP_STDERR_LINES: usize = 200;
+
+/// How long a failed start waits for the rest of the gateway's stderr
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The end of a starting gateway's stderr. The pipe is read from the
+/// moment the gateway is spawned, so one that prints a lot before it
+/// listens, like npx with its warnings, can't block on a full pipe.
+#[derive(Default)]
+struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl StderrTail {
+    /// Keep `line`, dropping the oldest past `STARTUP_STDERR_LINES`
+    fn push(lines: &Mutex<VecDeque<String>>, line: &str) {
+        let mut lines = lock(lines);
+        if lines.len() == STARTUP_STDERR_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
+    }
+
+    /// The lines kept, once the pipe is closed or after
+    /// `STDERR_DRAIN_TIMEOUT`, since a process the gateway started may
+    /// still hold it open
+    fn lines(&self) -> Vec<String> {
+        if let Some(ref reader) = self.reader {
+            let deadline = Instant::now() + STDERR_DRAIN_TIMEOUT;
+            while !reader.is_finished() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        lock(&self.lines).iter().cloned().collect()
+    }
+}
+
+/// Starts in a row a port has to fail before a free one is suggested, so
+/// a gateway that was just slow to exit doesn't move the port
+pub const PERSISTENT_CONFLICT_STARTS: u32 = 3;
+
+/// Wait before trying a failed start again, see `start_retrying`
+pub const START_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// `e` from the last of `attempts` tries of a start, saying how many
+/// there were
+fn after_attempts(e: AppError, attempts: u32) -> AppError {
+    if attempts < 2 {
+        return e;
+    }
+    let note = |detail: String| {
+        format!("Gave up after {} attempts. Last error: {}", attempts, detail)
+    };
+    match e {
+        AppError::GatewaySpawnFailed { detail } => AppError::GatewaySpawnFailed {
+            detail: note(detail),
+        },
+        AppError::GatewayStartFailed { detail } => AppError::GatewayStartFailed {
+            detail: note(detail),
+        },
+        e => e,
+    }
+}
+
+
+/// Lock `mutex` even after a panic while it was held, e.g. in a status
+/// listener. Nothing the manager keeps behind a lock is left half updated
+/// by a panic, so refusing the lock would only fail every command until the
+/// app restarts.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub struct SidecarManager {
+    pub state: Mutex<SidecarState>,
+    /// Held for a whole start, so a second one waits for the first and
+    /// then returns its outcome instead of spawning another
+    start_lock: Mutex<()>,
+    /// Set while a start holds `start_lock`
+    starting: AtomicBool,
+    /// Starts finished so far, and the outcome of the last, for the starts
+    /// that waited for it
+    start_rounds: AtomicU64,
+    last_start: Mutex<Option<Result<StartResult, AppError>>>,
+    /// Gateway binary to run directly instead of `npx openclaw`
+    binary_override: Option<PathBuf>,
+    /// Config to use instead of loading config.json
+    config_override: Option<Config>,
+    /// What config.json held when last loaded, for starting while it can't
+    /// be read
+    last_config: Mutex<Option<Config>>,
+    /// What `load_config` last returned, for status polls
+    loaded_config: Mutex<Option<Config>>,
+    /// Overrides `gateway_startup_timeout_secs`
+    startup_timeout: Option<Duration>,
+    start_retry_delay: Duration,
+    launcher: Box<dyn ProcessLauncher>,
+    metrics: Arc<AppMetrics>,
+    /// Kept apart from `state` so activity reports never wait for a start
+    heartbeat: Mutex<HeartbeatMonitor>,
+    port_probe: Mutex<PortProbe>,
+    /// Output of every gateway started by this manager
+    logs: Arc<GatewayLogs>,
+    disk: Box<dyn DiskSpace>,
+    /// Cleared before an npx start instead of the user's npx cache
+    npx_cache: Option<PathBuf>,
+    /// Why the last gateway crashed, when recognized; cleared by a start
+    crash_cause: Mutex<Option<SpawnFailure>>,
+    /// The last crash, until a start succeeds. Kept apart from `state` so
+    /// it can be reported during a start.
+    last_crash: Mutex<Option<CrashInfo>>,
+    /// Environment of the most recent spawn, redacted
+    last_env: Mutex<Option<EffectiveEnv>>,
+    /// The most recent spawn, kept after it exits until the next one.
+    /// Kept apart from `state` so it can be reported during a start.
+    last_launch: Mutex<Option<Launch>>,
+    history: Arc<StatusHistory>,
+    /// A crash the supervisor hasn't handled yet
+    crash: Mutex<Option<Crash>>,
+    backoff: Mutex<RestartBackoff>,
+    /// Calls to `stop`, so a pending restart can tell it was cancelled
+    stops: AtomicU64,
+    /// A port something other than a leftover gateway held, and the starts
+    /// in a row that failed on it
+    port_conflicts: Mutex<(u16, u32)>,
+    /// Token for the next start instead of a new one
+    next_token: Mutex<Option<KeptToken>>,
+    /// Startup timeout for the next start instead of the config's
+    next_startup_timeout: Mutex<Option<Duration>>,
+    /// Where the running gateway is recorded for the next launch
+    pid_file: Option<PidFile>,
+    /// Whether leftover gateways are killed to free the port. Off in
+    /// tests, where they may be the developer's own.
+    sweep_leftovers: bool,
+    /// Spawn attempts so far, see `status_events`
+    generation: AtomicU64,
+    status_listener: RwLock<Option<StatusListener>>,
+    progress_listener: RwLock<Option<ProgressListener>>,
+    /// The start in progress, for its `StartProgress`
+    attempt: Mutex<Option<StartAttempt>>,
+    token_saver: RwLock<Option<TokenSaver>>,
+    /// Profile this instance runs instead of the active one (see
+    /// `instances`)
+    profile: Option<String>,
+    /// Port of the running gateway once it started, 0 otherwise. Read by
+    /// other instances, so never behind `state`.
+    serving_port: AtomicU32,
+    peers: RwLock<Option<Peers>>,
+}
+
+impl Default for SidecarManager {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(SidecarState::default()),
+            start_lock: Mutex::new(()),
+            starting: AtomicBool::new(false),
+            start_rounds: AtomicU64::new(0),
+            last_start: Mutex::new(None),
+            binary_override: None,
+            config_override: None,
+            last_config: Mutex::new(None),
+            loaded_config: Mutex::new(None),
+            startup_timeout: None,
+            start_retry_delay: START_RETRY_DELAY,
+            launcher: Box::new(SystemLauncher),
+            metrics: Arc::new(AppMetrics::default()),
+            heartbeat: Mutex::new(HeartbeatMonitor::new(Duration::ZERO)),
+            port_probe: Mutex::new(PortProbe::new(heartbeat::PROBE_INTERVAL)),
+            logs: Arc::new(GatewayLogs::default()),
+            disk: Box::new(SystemDiskSpace),
+            npx_cache: None,
+            crash_cause: Mutex::new(None),
+            last_crash: Mutex::new(None),
+            last_env: Mutex::new(None),
+            last_launch: Mutex::new(None),
+            history: Arc::new(StatusHistory::default()),
+            crash: Mutex::new(None),
+            backoff: Mutex::new(RestartBackoff::default()),
+            stops: AtomicU64::new(0),
+            port_conflicts: Mutex::new((0, 0)),
+            next_token: Mutex::new(None),
+            next_startup_timeout: Mutex::new(None),
+            pid_file: None,
+            sweep_leftovers: true,
+            generation: AtomicU64::new(0),
+            status_listener: RwLock::new(None),
+            progress_listener: RwLock::new(None),
+            attempt: Mutex::new(None),
+            token_saver: RwLock::new(None),
+            profile: None,
+            serving_port: AtomicU32::new(0),
+            peers: RwLock::new(None),
+        }
+    }
+}
+
+impl SidecarManager {
+    /// Create a manager that runs `binary` directly as the openclaw CLI with
+    /// a fixed config, bypassing the bundled runtime and config.json.
+    ///
+    /// Used by the integration tests to drive a fake gateway.
+    pub fn with_binary(binary: impl Into<PathBuf>, config: Config) -> Self {
+        Self {
+            binary_override: Some(binary.into()),
+            config_override: Some(config),
+            ..Self::default()
+        }
+    }
+
+    /// Create a manager with a fixed config instead of config.json, finding
+    /// the gateway the way the app does
+    pub fn with_config(config: Config) -> Self {
+        Self {
+            config_override: Some(config),
+            ..Self::default()
+        }
+    }
+
+    /// Set how long `start` waits for the gateway to accept connections,
+    /// whatever the config says
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = Some(timeout);
+        self
+    }
+
+    /// Wait `delay` before trying a failed start again
+    pub fn with_start_retry_delay(mut self, delay: Duration) -> Self {
+        self.start_retry_delay = delay;
+        self
+    }
+
+    /// Spawn processes through `launcher` instead of the OS directly
+    pub fn with_launcher(mut self, launcher: Box<dyn ProcessLauncher>) -> Self {
+        self.launcher = launcher;
+        self
+    }
+
+    /// Record start failures and crashes in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<AppMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn metrics(&self) -> &Arc<AppMetrics> {
+        &self.metrics
+    }
+
+    /// Capture gateway output in `logs`
+    pub fn with_logs(mut self, logs: Arc<GatewayLogs>) -> Self {
+        self.logs = logs;
+        self
+    }
+
+    pub fn logs(&self) -> &Arc<GatewayLogs> {
+        &self.logs
+    }
+
+    /// Record status transitions in `history`
+    pub fn with_history(mut self, history: Arc<StatusHistory>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Record every spawned gateway in `pid_file` until it is stopped
+    pub fn with_pid_file(mut self, pid_file: PidFile) -> Self {
+        self.pid_file = Some(pid_file);
+        self
+    }
+
+    /// Leave leftover gateways running unless `sweep`
+    pub fn with_leftover_sweep(mut self, sweep: bool) -> Self {
+        self.sweep_leftovers = sweep;
+        self
+    }
+
+    pub fn history(&self) -> &Arc<StatusHistory> {
+        &self.history
+    }
+
+    /// Wait between crash restarts as `backoff` says
+    pub fn with_restart_backoff(mut self, backoff: RestartBackoff) -> Self {
+        self.backoff = Mutex::new(backoff);
+        self
+    }
+
+    /// Tell `listener` about every transition from now on
+    pub fn set_status_listener(&self, listener: StatusListener) {
+        if let Ok(mut slot) = self.status_listener.write() {
+            *slot = Some(listener);
+        }
+    }
+
+    /// Tell `listener` how every start from now on progresses
+    pub fn set_progress_listener(&self, listener: ProgressListener) {
+        if let Ok(mut slot) = self.progress_listener.write() {
+            *slot = Some(listener);
+        }
+    }
+
+    /// Hand every new token to `saver` while `persistGatewayToken` is on
+    pub fn set_token_saver(&self, saver: TokenSaver) {
+        if let Ok(mut slot) = self.token_saver.write() {
+            *slot = Some(saver);
+        }
+    }
+
+    /// Run profile `name` instead of the active one, with its own state
+    /// directory; see `instances`
+    pub fn with_profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Ask `peers` for the other instances' ports before starting
+    pub fn set_peers(&self, peers: Peers) {
+        if let Ok(mut slot) = self.peers.write() {
+            *slot = Some(peers);
+        }
+    }
+
+    /// Other running instances and their ports
+    fn peers(&self) -> Vec<(String, u16)> {
+        match self.peers.read() {
+            Ok(peers) => peers.as_ref().map(|peers| peers()).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Spawn attempts so far
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Whether `held` still describes the running gateway, and the info to
+    /// use instead when it doesn't
+    pub fn validate_info(&self, held: &GatewayInfo) -> ValidationResult {
+        let status = self.status();
+        let Some(info) = status.info.filter(|_| status.running) else {
+            return ValidationResult::default();
+        };
+        let valid = info.generation == held.generation
+            && info.url == held.url
+            && info.token == held.token;
+        ValidationResult {
+            valid,
+            generation: Some(info.generation),
+            info: (!valid).then_some(info),
+        }
+    }
+
+    /// Whether a start is in progress, without taking the state lock
+    pub fn is_starting(&self) -> bool {
+        self.starting.load(Ordering::SeqCst)
+    }
+
+    /// Port of the running gateway, without taking the state lock
+    pub fn serving_port(&self) -> Option<u16> {
+        match self.serving_port.load(Ordering::SeqCst) {
+            0 => None,
+            port => u16::try_from(port).ok(),
+        }
+    }
+
+    fn clear_state(&self, state: &mut SidecarState) {
+        state.clear();
+        self.serving_port.store(0, Ordering::SeqCst);
+    }
+
+    /// `port_in_use` for `port` on `host`, not held by a leftover of ours, or
+    /// `port_blocked` with a port to move to once that happened
+    /// `PERSISTENT_CONFLICT_STARTS` starts in a row
+    fn port_conflict(&self, host: IpAddr, port: u16) -> AppError {
+        let starts = {
+            let mut conflicts = lock(&self.port_conflicts);
+            let starts = if conflicts.0 == port { conflicts.1 + 1 } else { 1 };
+            *conflicts = (port, starts);
+            starts
+        };
+        if starts >= PERSISTENT_CONFLICT_STARTS {
+            if let Some(suggested) = port::suggest(host, port) {
+                return AppError::PortBlocked {
+                    port: port.to_string(),
+                    suggested: suggested.to_string(),
+                    starts: starts.to_string(),
+                };
+            }
+        }
+        AppError::PortInUse {
+            port: port.to_string(),
+        }
+    }
+
+    /// Put `state.info` back from the launch of the running gateway, should
+    /// it be missing. Without it nothing can connect, so a gateway whose
+    /// launch doesn't tell is stopped, leaving the way clear for a start
+    /// instead of a second gateway next to it.
+    fn restore_info(&self, state: &mut SidecarState) {
+        if state.info.is_some() {
+            return;
+        }
+        let Some(pid) = state.child.as_ref().map(|child| child.id()) else {
+            return;
+        };
+        let launched = lock(&self.last_launch)
+            .as_ref()
+            .filter(|launch| launch.pid == pid)
+            .and_then(|launch| launch.info.clone());
+        match launched {
+            Some(info) => {
+                println!("[openclaw] Restored the running gateway's info from its launch");
+                self.serving_port.store(info.port.map_or(0, u32::from), Ordering::SeqCst);
+                state.info = Some(info);
+            }
+            None => {
+                eprintln!("[openclaw] Gateway {} runs without its info, stopping it", pid);
+                if let Some(mut child) = state.child.take() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                self.clear_state(state);
+                if let Some(ref pid_file) = self.pid_file {
+                    let _ = pid_file.clear();
+                }
+            }
+        }
+    }
+
+    /// The running gateway's info, or the external gateway's while it can
+    /// be reached. Unlike `start` this never starts one, so it is what a
+    /// reloaded frontend asks first.
+    pub fn current_info(&self) -> Option<GatewayInfo> {
+        let status = self.status();
+        status.info.filter(|_| status.running)
+    }
+
+    /// Kill the gateway the pid file records as left running by an earlier
+    /// start, see `pid_file::reap`. Returns its record when it was killed.
+    fn reap_leftover(&self) -> Option<GatewayRecord> {
+        if !self.sweep_leftovers {
+            return None;
+        }
+        match pid_file::reap(self.pid_file.as_ref()?) {
+            Leftover::Killed(record) => Some(record),
+            _ => None,
+        }
+    }
+
+    /// Tell the status listener about `phase` of `generation`, with
+    /// `status` or the current one, unless a newer generation began
+    fn announce(
+        &self,
+        generation: u64,
+        phase: GatewayPhase,
+        exit_code: Option<i32>,
+        status: Option<GatewayStatus>,
+    ) {
+        if self.status_listener.read().map_or(true, |listener| listener.is_none()) {
+            return;
+        }
+        let status = status.unwrap_or_else(|| self.status());
+        if self.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        if let Ok(listener) = self.status_listener.read() {
+            if let Some(ref listener) = *listener {
+                listener(&StatusChange {
+                    generation,
+                    phase,
+                    exit_code,
+                    status,
+                });
+            }
+        }
+    }
+
+    fn report_progress(&self, progress: StartProgress) {
+        if let Ok(listener) = self.progress_listener.read() {
+            if let Some(ref listener) = *listener {
+                listener(&progress);
+            }
+        }
+    }
+
+    /// Report that the start in progress entered `phase`
+    fn enter_phase(&self, phase: StartPhase) {
+        let event = lock(&self.attempt).as_mut().map(|attempt| {
+            attempt.phase = Some(phase);
+            StartPhaseEvent {
+                generation: attempt.generation,
+                phase,
+                at: gateway_logs::now_ms(),
+                elapsed_ms: attempt.began.elapsed().as_millis() as u64,
+            }
+        });
+        if let Some(event) = event {
+            self.report_progress(StartProgress::Phase(event));
+        }
+    }
+
+    /// Report how the start in progress ended
+    fn finish_attempt(&self, result: &Result<StartResult, AppError>) {
+        let Some(attempt) = lock(&self.attempt).take() else {
+            return;
+        };
+        let at = gateway_logs::now_ms();
+        let elapsed_ms = attempt.began.elapsed().as_millis() as u64;
+        self.report_progress(match result {
+            Ok(result) => StartProgress::Ready(StartReadyEvent {
+                generation: attempt.generation,
+                at,
+                elapsed_ms,
+                port: result.info.port,
+            }),
+            Err(e) => StartProgress::Failed(StartFailedEvent {
+                generation: attempt.generation,
+                phase: attempt.phase,
+                at,
+                elapsed_ms,
+                error: e.clone(),
+            }),
+        });
+    }
+
+    /// The last crash, until a start succeeds
+    pub fn last_crash(&self) -> Option<CrashInfo> {
+        lock(&self.last_crash).clone()
+    }
+
+    /// The crash noticed since the last call, if the gateway wasn't
+    /// stopped since
+    pub fn take_crash(&self) -> Option<Crash> {
+        lock(&self.crash).take()
+    }
+
+    /// Hand back a crash whose restart failed, to be tried again
+    pub fn retry_crash(&self, crash: Crash) {
+        if crash.stops != self.stop_count() {
+            return;
+        }
+        lock(&self.crash).get_or_insert(crash);
+    }
+
+    pub fn stop_count(&self) -> u64 {
+        self.stops.load(Ordering::SeqCst)
+    }
+
+    /// Whether `autoRestartGateway` is on
+    pub fn auto_restart_enabled(&self) -> bool {
+        self.load_config()
+            .is_ok_and(|config| config.auto_restart_gateway)
+    }
+
+    /// How long to wait before restarting after a crash, counting the
+    /// restart
+    pub fn next_restart_delay(&self, uptime: Option<Duration>) -> Duration {
+        lock(&self.backoff).next_delay(uptime)
+    }
+
+    /// Consecutive crash restarts since the last stable run or stop
+    pub fn crash_restarts(&self) -> u32 {
+        lock(&self.backoff).consecutive()
+    }
+
+    /// Start the gateway again after `crash`, with the crashed one's token
+    /// when it gets the same port
+    pub fn restart_after_crash(&self, crash: &Crash) -> Result<StartResult, AppError> {
+        self.start_with_token(crash.token.clone().map(|token| KeptToken {
+            token,
+            port: crash.port,
+        }))
+    }
+
+    /// `start` with `token` instead of a new one, when given and the
+    /// gateway gets its port. Otherwise the new gateway gets a new token,
+    /// or the one kept with `persistGatewayToken`.
+    fn start_with_token(&self, token: Option<KeptToken>) -> Result<StartResult, AppError> {
+        *lock(&self.next_token) = token;
+        let result = self.start();
+        *lock(&self.next_token) = None;
+        result
+    }
+
+    /// `start`, waiting up to `timeout` for the gateway to accept
+    /// connections instead of `gatewayStartupTimeoutSecs`
+    pub fn start_with_timeout(&self, timeout: Duration) -> Result<StartResult, AppError> {
+        *lock(&self.next_startup_timeout) = Some(timeout);
+        let result = self.start();
+        *lock(&self.next_startup_timeout) = None;
+        result
+    }
+
+    /// Query free space through `disk` instead of the OS
+    pub fn with_disk_space(mut self, disk: Box<dyn DiskSpace>) -> Self {
+        self.disk = disk;
+        self
+    }
+
+    /// Clear `dir` before an npx start instead of the user's npx cache
+    pub fn with_npx_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.npx_cache = Some(dir.into());
+        self
+    }
+
+    /// Count a failed start under `reason` and pass the message through
+    fn start_failed(&self, reason: &str, message: impl Into<String>) -> String {
+        self.metrics.record_start_failure(reason);
+        message.into()
+    }
+
+    /// The error for a gateway that exited with `status` before it was
+    /// ready, explained from its stderr where possible
+    fn exit_error(
+        &self,
+        stderr: &StderrTail,
+        status: ProcessExit,
+        program: &str,
+        spawned_at: Instant,
+    ) -> AppError {
+        let stderr_output = stderr.lines().join("\n");
+
+        let exit_code = status.code.unwrap_or(-1);
+        println!("[openclaw] Process exited with code: {}", exit_code);
+        println!("[openclaw] stderr: {}", stderr_output);
+
+        // Killed right away, e.g. by Gatekeeper or endpoint protection
+        let lifetime = spawned_at.elapsed();
+        if let Some(failure) =
+            spawn_failure::diagnose(Path::new(program), None, Some(status), Some(lifetime))
+        {
+            return self.start_error(
+                failure.code(),
+                format!(
+                    "Gateway process exited with status: {}. {}",
+                    status,
+                    failure.describe()
+                ),
+            );
+        }
+
+        // Crashes node explains on stderr, like OpenSSL mismatches
+        if let Some(failure) = spawn_failure::classify_stderr(&stderr_output) {
+            return self.start_error(
+                failure.code(),
+                format!(
+                    "Gateway process exited with status: {}. {}",
+                    status,
+                    failure.describe()
+                ),
+            );
+        }
+
+        // Exit code 127 = command not found
+        if exit_code == 127 {
+            return self.start_error(
+                "command_not_found",
+                format!(
+                    "Gateway failed: command not found (exit code 127). \
+                     Node path: {}. This usually means the Node.js binary couldn't execute. \
+                     stderr: {}",
+                    program, stderr_output
+                ),
+            );
+        }
+
+        self.transient(self.start_error(
+            "exited",
+            format!(
+                "Gateway process exited unexpectedly with status: {}. \
+                 stderr: {}",
+                status, stderr_output
+            ),
+        ))
+    }
+
+    /// What a gateway stopped before it was ready printed: the end of its
+    /// stdout and `stderr` as captured since it was spawned. Empty when it
+    /// printed nothing.
+    fn startup_output(&self, stderr: &StderrTail) -> String {
+        let tail = |lines: Vec<String>| {
+            let skip = lines.len().saturating_sub(STARTUP_OUTPUT_LINES);
+            lines[skip..].join("\n")
+        };
+        let stdout: Vec<String> = {
+            let logs = self.logs.lock();
+            let mut lines: Vec<String> = logs
+                .lines()
+                .rev()
+                .take_while(|line| line.stream != gateway_logs::MARKER_STREAM)
+                .filter(|line| line.stream == "stdout")
+                .take(STARTUP_OUTPUT_LINES)
+                .map(|line| line.text.clone())
+                .collect();
+            lines.reverse();
+            lines
+        };
+        let stderr = stderr.lines();
+
+        let mut output = String::new();
+        if !stdout.is_empty() {
+            output.push_str(&format!("\n\nstdout:\n{}", tail(stdout)));
+        }
+        if !stderr.is_empty() {
+            output.push_str(&format!("\n\nstderr:\n{}", tail(stderr)));
+        }
+        output
+    }
+
+    /// Kill `child`, spawned by a start a stop came during
+    fn cancel_start(&self, child: &mut dyn GatewayProcess, state: &mut SidecarState) -> AppError {
+        println!("[openclaw] Start cancelled by a stop, killing the gateway");
+        self.abandon_start(child, state);
+        self.metrics.record_start_failure("cancelled");
+        AppError::GatewayStartCancelled
+    }
+
+    /// Kill the gateway a start gave up on, and forget it was started
+    fn abandon_start(&self, child: &mut dyn GatewayProcess, state: &mut SidecarState) {
+        match child.kill() {
+            // Gone before a retry spawns the next one on the same port
+            Ok(()) => {
+                let _ = child.wait();
+            }
+            Err(e) => eprintln!("[openclaw] Failed to kill the starting gateway: {}", e),
+        }
+        self.clear_state(state);
+        if let Some(ref pid_file) = self.pid_file {
+            let _ = pid_file.clear();
+        }
+    }
+
+    /// `e`, marking the start in progress as failed for a reason that may
+    /// pass - a spawn the OS refused for now, a gateway that exited or never
+    /// listened - so `start_retrying` tries again. Anything explained, like
+    /// a quarantined binary or an OpenSSL crash, stays failed.
+    fn transient(&self, e: AppError) -> AppError {
+        if let Some(attempt) = lock(&self.attempt).as_mut() {
+            attempt.retryable = true;
+        }
+        e
+    }
+
+    /// `start_failed` as a generic start error
+    fn start_error(&self, reason: &str, message: impl Into<String>) -> AppError {
+        AppError::GatewayStartFailed {
+            detail: self.start_failed(reason, message),
+        }
+    }
+
+    /// The config a start would use: the manager's own or config.json,
+    /// with its profile applied. When config.json can't be read, e.g. in a
+    /// network home that went away, what it held last time is used.
+    pub fn load_config(&self) -> Result<Config, AppError> {
+        let mut config = match self.config_override {
+            Some(ref config) => config.clone(),
+            None => match Config::load() {
+                Ok(config) => {
+                    *lock(&self.last_config) = Some(config.clone());
+                    config
+                }
+                Err(e @ ConfigError::Io(_)) => match lock(&self.last_config).clone() {
+                    Some(config) => {
+                        eprintln!(
+                            "[openclaw] Could not read config.json, using it as last read: {}",
+                            e
+                        );
+                        config
+                    }
+                    None => return Err(e.into()),
+                },
+                Err(e) => return Err(e.into()),
+            },
+        };
+        if let Some(ref profile) = self.profile {
+            profiles::apply(&mut config, profile, keychain::system())?;
+            instances::separate_state(&mut config, profile);
+            // An external gateway and a kept token are the default
+            // instance's only
+            config.external_gateway = None;
+            config.persist_gateway_token = false;
+        }
+        *lock(&self.loaded_config) = Some(config.clone());
+        Ok(config)
+    }
+
+    /// The config as `load_config` last returned it, loaded now only the
+    /// first time. For status polls, which shouldn't migrate config.json or
+    /// read the keychain each time; the config watcher loads it on changes.
+    fn config_snapshot(&self) -> Option<Config> {
+        let loaded = lock(&self.loaded_config).clone();
+        loaded.or_else(|| self.load_config().ok())
+    }
+
+    /// Start the OpenClaw gateway using bundled Node.js runtime
+    ///
+    /// Uses the bundled Node.js runtime so users don't need to install
+    /// anything. On first launch, the runtime is automatically downloaded.
+    /// A missing runtime, API key or binary, a port that stays in use or
+    /// another instance serves on, a failed spawn, a blocked tool that can't be disabled, a required flag
+    /// the gateway doesn't accept and a full disk each have their own error;
+    /// anything else is `GatewayStartFailed`.
+    /// An OpenClaw gateway someone started by hand on the port fails it with
+    /// `ExternalGatewayDetected` instead of being swept away, see `adopt`.
+    ///
+    /// A start called while another is in progress waits for it and returns
+    /// the same gateway or error, so concurrent calls spawn one gateway. A
+    /// `stop` meanwhile cancels the start, killing what it spawned, and it
+    /// fails with `GatewayStartCancelled`; a start called after the stop
+    /// starts afresh instead.
+    ///
+    /// A spawn that fails or a gateway that doesn't become ready for a
+    /// reason that may pass is tried again up to `startMaxRetries` times;
+    /// see `start_retrying`.
+    pub fn start(&self) -> Result<StartResult, AppError> {
+        let round = self.start_rounds.load(Ordering::SeqCst);
+        let stops = self.stop_count();
+        let _start = lock(&self.start_lock);
+        if self.start_rounds.load(Ordering::SeqCst) != round {
+            let last = lock(&self.last_start).clone();
+            match last {
+                Some(Err(AppError::GatewayStartCancelled)) if self.stop_count() == stops => {}
+                Some(result) => return result,
+                None => {}
+            }
+        }
+
+        let result = self.try_start();
+        if let Err(ref e) = result {
+            self.history
+                .record(StatusTransition::now(TransitionKind::StartFailed, e.to_string()));
+        }
+        *lock(&self.last_start) = Some(result.clone());
+        self.start_rounds.fetch_add(1, Ordering::SeqCst);
+        result
+    }
+
+    /// `start` once it holds `start_lock`
+    fn try_start(&self) -> Result<StartResult, AppError> {
+        let starting = Starting::new(&self.starting);
+        let generation = self.generation.load(Ordering::SeqCst);
+        let result = self.start_retrying();
+        drop(starting);
+
+        // Nothing to announce when the gateway was already running
+        let spawned = self.generation.load(Ordering::SeqCst);
+        if spawned != generation {
+            self.finish_attempt(&result);
+            let phase = match result {
+                Ok(_) => GatewayPhase::Running,
+                Err(_) => GatewayPhase::Stopped,
+            };
+            self.announce(spawned, phase, None, None);
+        }
+        result
+    }
+
+    /// `start_locked`, tried again `START_RETRY_DELAY` after a failure that
+    /// may pass (see `transient`), up to `startMaxRetries` times. Anything
+    /// else, like a missing openclaw or API key or a port another process
+    /// holds, fails at once. After more than one try the error says how
+    /// many there were.
+    fn start_retrying(&self) -> Result<StartResult, AppError> {
+        let retries = self
+            .load_config()
+            .map_or(0, |config| config.start_max_retries.min(config::MAX_START_RETRIES));
+        let stops = self.stop_count();
+        let mut attempts = 1;
+        loop {
+            let e = match self.start_locked() {
+                Ok(result) => return Ok(result),
+                Err(e) => e,
+            };
+            // Taken, so a try failing before it begins isn't judged by the last
+            let (generation, retryable) = lock(&self.attempt)
+                .as_mut()
+                .map_or((0, false), |attempt| {
+                    (attempt.generation, std::mem::take(&mut attempt.retryable))
+                });
+            if !retryable || attempts > retries {
+                return Err(after_attempts(e, attempts));
+            }
+
+            println!(
+                "[openclaw] Start attempt {} of {} failed, trying again in {} ms: {}",
+                attempts,
+                retries + 1,
+                self.start_retry_delay.as_millis(),
+                e
+            );
+            self.report_progress(StartProgress::Retrying(StartRetryEvent {
+                generation,
+                attempt: attempts,
+                max_attempts: retries + 1,
+                retry_in_ms: self.start_retry_delay.as_millis() as u64,
+                at: gateway_logs::now_ms(),
+                error: e,
+            }));
+            let deadline = Instant::now() + self.start_retry_delay;
+            while Instant::now() < deadline {
+                if self.stop_count() != stops {
+                    println!("[openclaw] Start cancelled by a stop before trying again");
+                    return Err(AppError::GatewayStartCancelled);
+                }
+                let left = deadline.saturating_duration_since(Instant::now());
+                std::thread::sleep(left.min(Duration::from_millis(50)));
+            }
+            attempts += 1;
+        }
+    }
+
+    /// `start_retrying`'s single try, with the state held
+    fn start_locked(&self) -> Result<StartResult, AppError> {
+        let mut state = lock(&self.state);
+
+        let config = self.load_config()?;
+        if let Some(ref external) = config.external_gateway {
+            // One the app started before switching must not keep running
+            let managed = state.child.is_some();
+            drop(state);
+            if managed {
+                self.stop_as(TransitionKind::Stopped, "Switched to an external gateway")?;
+            }
+            let heartbeat_timeout = Duration::from_secs(config.gateway_heartbeat_timeout_secs);
+            return self.connect_external(external, heartbeat_timeout);
+        }
+
+        // Check if already running and healthy
+        if let Some(result) = self.already_running(&mut state)? {
+            return Ok(result);
+        }
+        // Not held while preparing, which runs openclaw and node, so status
+        // polls and stops don't wait on them
+        drop(state);
+
+        self.metrics.record_start_attempt();
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *lock(&self.attempt) = Some(StartAttempt {
+            generation,
+            began: Instant::now(),
+            phase: None,
+            retryable: false,
+        });
+        self.announce(generation, GatewayPhase::Starting, None, None);
+        let stops = self.stop_count();
+        self.enter_phase(StartPhase::LocatingBinary);
+        let mut plan = self.prepare().inspect_err(|_| {
+            self.metrics.record_start_failure("config");
+        })?;
+        if let Some(e) = plan.version_error.take() {
+            self.metrics.record_start_failure("openclaw_version");
+            return Err(e.into());
+        }
+        if let Some(e) = plan.blocked_tools_error.take() {
+            self.metrics.record_start_failure("blocked_tools");
+            return Err(e.into());
+        }
+        if let Some(e) = plan.capability_error.take() {
+            self.metrics.record_start_failure("capabilities");
+            return Err(e.into());
+        }
+        if let Some(e) = plan.disk_full.take() {
+            self.metrics.record_start_failure("disk_space");
+            return Err(e.into());
+        }
+        if !plan.errors.is_empty() {
+            let reason = plan
+                .checks
+                .iter()
+                .find(|check| !check.passed)
+                .map_or("preflight", |check| check.name.as_str());
+            self.metrics.record_start_failure(reason);
+            return Err(plan.errors.swap_remove(0));
+        }
+
+        self.enter_phase(StartPhase::CheckingPort);
+        // Held by another instance rather than left over, so not ours to stop
+        if plan.port_in_use {
+            let peers = self.peers();
+            if let Some((instance, _)) = peers.into_iter().find(|(_, port)| *port == plan.port) {
+                self.metrics.record_start_failure("port_in_use");
+                return Err(AppError::PortInUseByInstance {
+                    port: plan.port.to_string(),
+                    instance,
+                });
+            }
+        }
+
+        // A gateway may have been adopted in the meantime
+        let mut state = lock(&self.state);
+        if let Some(result) = self.already_running(&mut state)? {
+            return Ok(result);
+        }
+        let result = self.execute(state, plan, stops, generation)?;
+        if config.persist_gateway_token
+            && config.gateway_token.as_deref() != Some(result.info.token.as_str())
+        {
+            self.save_token(&result.info.token);
+        }
+        Ok(result)
+    }
+
+    /// What a start returns for the gateway in `state` while it runs. One
+    /// that exited is recorded as a crash and cleared.
+    fn already_running(&self, state: &mut SidecarState) -> Result<Option<StartResult>, AppError> {
+        if let Some(ref mut child) = state.child {
+            match child.try_wait() {
+                Ok(Some(exit)) => {
+                    // Process exited, clear state
+                    println!("[openclaw] Previous gateway process has exited, clearing state");
+                    self.record_crash(state, exit);
+                    self.clear_state(state);
+                    let generation = self.generation.load(Ordering::SeqCst);
+                    self.announce(generation, GatewayPhase::Exited, exit.code, None);
+                }
+                Ok(None) => {
+                    // Still running, return existing info
+                    if state.paused {
+                        self.resume_locked(state)?;
+                    }
+                    self.restore_info(state);
+                    if let Some(ref info) = state.info {
+                        println!("[openclaw] Gateway already running, returning existing connection");
+                        return Ok(Some(StartResult {
+                            info: info.clone(),
+                            warnings: state.warnings.clone(),
+                        }));
+                    }
+                }
+                Err(_) => {
+                    self.clear_state(state);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn save_token(&self, token: &str) {
+        let Ok(saver) = self.token_saver.read() else {
+            return;
+        };
+        if let Some(ref saver) = *saver {
+            match saver(token) {
+                Ok(()) => println!("[openclaw] Kept the gateway token for the next start"),
+                Err(e) => eprintln!("[openclaw] Failed to keep the gateway token: {}", e),
+            }
+        }
+    }
+
+    /// Hand out `external` once it accepts connections
+    fn connect_external(
+        &self,
+        external: &ExternalGateway,
+        heartbeat_timeout: Duration,
+    ) -> Result<StartResult, AppError> {
+        self.metrics.record_start_attempt();
+        let info = external::info(external)?;
+        if let Err(e) = external::probe(external, heartbeat::PROBE_TIMEOUT) {
+            self.metrics.record_start_failure("external_gateway");
+            return Err(e.into());
+        }
+        self.reset_heartbeat(heartbeat_timeout);
+        println!("[openclaw] Using the external gateway at {}", info.url);
+        Ok(StartResult {
+            info,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Status of `external`, which is running if it can be reached
+    fn external_status(&self, external: &ExternalGateway) -> GatewayStatus {
+        let mut status = self.idle_status(None);
+        status.external = true;
+        status.managed = false;
+        let info = match external::info(external) {
+            Ok(info) => info,
+            Err(e) => {
+                status.error = Some(AppError::from(e).code().to_string());
+                return status;
+            }
+        };
+        let reachable = lock(&self.port_probe).check(Instant::now(), || {
+            external::probe(external, heartbeat::PROBE_TIMEOUT).is_ok()
+        });
+        status.configured_port = info.port;
+        status.accepting_connections = Some(reachable);
+        if reachable {
+            status.running = true;
+            status.effective_port = info.port;
+            status.info = Some(info);
+            let heartbeat = lock(&self.heartbeat);
+            status.health = heartbeat.health(Instant::now());
+            status.last_response_at =
+                heartbeat.last_report().and_then(|report| report.last_success_at);
+        }
+        self.history.observe_health(status.health);
+        status
+    }
+
+    /// Take on the gateway a start found with `external_gateway_detected`
+    /// as the running one, once it accepts `token` (see `adopted`). Fails
+    /// with `gateway_not_running` when no gateway serves the port any more
+    /// and `auth_self_test_failed` for a token it refuses. With a gateway
+    /// already running, returns that one.
+    pub fn adopt(&self, token: &str) -> Result<StartResult, AppError> {
+        let config = self.load_config()?;
+        let mut state = lock(&self.state);
+        let running = state
+            .child
+            .as_mut()
+            .is_some_and(|child| matches!(child.try_wait(), Ok(None)));
+        if let Some(info) = state.info.as_ref().filter(|_| running) {
+            return Ok(StartResult {
+                info: info.clone(),
+                warnings: state.warnings.clone(),
+            });
+        }
+
+        let token = token.trim();
+        let bind_ip = config.gateway_ip()?;
+        let port = config.gateway_port;
+        let addr = SocketAddr::new(connection::probe_ip(bind_ip), port);
+        if !adopted::detect(addr) {
+            return Err(AppError::GatewayNotRunning);
+        }
+        if let Err(e) = protocol::auth_self_test(addr, token, SELF_TEST_TIMEOUT) {
+            eprintln!("[openclaw] The gateway on port {} refused the token: {}", port, e);
+            return Err(AppError::AuthSelfTestFailed {
+                detail: e.to_string(),
+            });
+        }
+
+        let pid = adopted::listener_pid(port);
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let lan_ip = if bind_ip.is_unspecified() {
+            connection::primary_lan_ip(bind_ip)
+        } else {
+            None
+        };
+        let info = GatewayInfo {
+            url: connection::gateway_url(
+                &connection::url_host(bind_ip, lan_ip),
+                port,
+                false,
+                config.token_in_url.then_some(token),
+            ),
+            port: Some(port),
+            socket_path: None,
+            token: token.to_string(),
+            lan_exposed: connection::is_lan_exposed(bind_ip),
+            workspace: None,
+            generation,
+        };
+        self.clear_state(&mut state);
+        state.child = Some(Box::new(AdoptedProcess::adopt(addr, pid)));
+        state.info = Some(info.clone());
+        state.port_source = Some(PortSource::Adopted);
+        state.probe_ip = Some(addr.ip());
+        state.started_at = Some(gateway_logs::now_ms());
+        self.serving_port.store(u32::from(port), Ordering::SeqCst);
+        *lock(&self.port_conflicts) = (0, 0);
+        *lock(&self.crash_cause) = None;
+        self.reset_heartbeat(Duration::from_secs(config.gateway_heartbeat_timeout_secs));
+        self.history.record(StatusTransition::now(
+            TransitionKind::Started,
+            format!("Adopted the gateway on port {}", port),
+        ));
+        drop(state);
+
+        println!("[openclaw] Adopted the gateway on port {}, it isn't managed", port);
+        self.announce(generation, GatewayPhase::Running, None, None);
+        Ok(StartResult {
+            info,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// The installed openclaw's version, read as a start reads it. Fails
+    /// like a start when there is no openclaw to ask.
+    pub fn openclaw_version(&self) -> Result<VersionInfo, AppError> {
+        let config = self.load_config()?;
+        self.find_program(&config)?;
+        let plan = self.prepare()?;
+        Ok(VersionInfo {
+            version: plan.gateway_version.map(|version| version.to_string()),
+            path: plan.program,
+            uses_npx: plan.uses_npx,
+            minimum: MIN_OPENCLAW_VERSION.to_string(),
+            supported: plan.gateway_version.map(|version| version >= MIN_OPENCLAW_VERSION),
+            check_skipped: config.skip_openclaw_version_check,
+        })
+    }
+
+    /// Run the preparation pipeline without spawning anything
+    pub fn preview(&self) -> StartPreview {
+        let running = self.status().running;
+
+        match self.prepare() {
+            Ok(plan) => {
+                let mut preview = plan.preview();
+                if running {
+                    preview.checks.insert(
+                        0,
+                        PreflightCheck::new(
+                            "gateway_running",
+                            false,
+                            "A gateway is already running; start would return its existing connection.",
+                        ),
+                    );
+                }
+                preview
+            }
+            Err(e) => StartPreview {
+                errors: vec![e.to_string()],
+                ..StartPreview::default()
+            },
+        }
+    }
+
+    /// What the app set in the environment of the most recent spawn, or
+    /// would set for the next start when nothing was spawned yet. None when
+    /// the config can't be loaded.
+    pub fn effective_env(&self) -> Option<EffectiveEnv> {
+        if let Some(env) = lock(&self.last_env).clone() {
+            return Some(env);
+        }
+        let plan = self.prepare().ok()?;
+        Some(EffectiveEnv {
+            spawned: false,
+            clean_environment: plan.clean_env,
+            vars: plan.env.report(),
+        })
+    }
+
+    /// The program, arguments and environment names the most recent gateway
+    /// was spawned with, also after it exited. None before the first spawn.
+    pub fn launch_info(&self) -> Option<LaunchDescriptor> {
+        lock(&self.last_launch)
+            .as_ref()
+            .map(|launch| launch.descriptor.clone())
+    }
+
+    /// Take the gateway from nothing to connectable (see `ready`), calling
+    /// `progress` as each stage begins
+    pub fn ensure_ready(
+        &self,
+        mut progress: impl FnMut(ReadyProgress),
+    ) -> Result<ReadyResult, ReadyError> {
+        let mut stage = |stage: ReadyStage| {
+            progress(ReadyProgress::new(stage));
+            move |e: AppError| ReadyError::new(stage, e)
+        };
+
+        let failed = stage(ReadyStage::Config);
+        let config = self.load_config().map_err(failed)?;
+        config.validate().map_err(failed)?;
+
+        // An external gateway has its own key and nothing to find
+        let external = config.external_gateway.as_ref();
+        let failed = stage(ReadyStage::Credentials);
+        if external.is_none() && config.anthropic_api_key.as_deref().is_none_or(str::is_empty) {
+            return Err(failed(config.missing_api_key_error()));
+        }
+
+        let failed = stage(ReadyStage::Binary);
+        if external.is_none() && !self.status().running {
+            self.find_program(&config).map_err(failed)?;
+        }
+        let paths = AppPaths::resolve();
+        let first_run = gateway_state::state_dir(&config, paths.as_ref())
+            .is_ok_and(|dir| !dir.is_dir());
+
+        let failed = stage(ReadyStage::Gateway);
+        // A start runs its own self-test, which rejects the token the same way
+        let result = self.start().map_err(|e| match e {
+            AppError::AuthSelfTestFailed { .. } => stage(ReadyStage::SelfTest)(e),
+            e => failed(e),
+        })?;
+
+        // Also covers a gateway that was already running when called
+        let failed = stage(ReadyStage::SelfTest);
+        // The protocol client doesn't speak TLS, so wss:// goes untested
+        let tested = match external {
+            Some(external) => external.url.starts_with("ws://"),
+            None => config.tls.is_none(),
+        };
+        if config.self_test_on_start && tested {
+            let started = Instant::now();
+            let endpoint = match external {
+                Some(external) => external::probe(external, heartbeat::PROBE_TIMEOUT)
+                    .map_err(|e| failed(e.into()))?
+                    .into(),
+                None => result.info.endpoint(self.probe_ip()),
+            };
+            match protocol::auth_self_test(endpoint, &result.info.token, SELF_TEST_TIMEOUT) {
+                Ok(elapsed) => self.metrics.record_auth_self_test(true, elapsed),
+                Err(e) => {
+                    self.metrics.record_auth_self_test(false, started.elapsed());
+                    eprintln!("[openclaw] Auth self-test failed: {}", e);
+                    let _ = self.stop();
+                    return Err(failed(AppError::AuthSelfTestFailed {
+                        detail: e.to_string(),
+                    }));
+                }
+            }
+        }
+
+        Ok(ReadyResult {
+            info: result.info,
+            first_run,
+            warnings: result.warnings,
+        })
+    }
+
+    /// Address of the running gateway for the app's own connections
+    fn probe_ip(&self) -> IpAddr {
+        lock(&self.state)
+            .probe_ip
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    /// Whether a start with `config` runs openclaw through npx
+    pub fn uses_npx(&self, config: &Config) -> bool {
+        self.direct_binary(config).is_none()
+    }
+
+    /// The openclaw CLI to run without node, when there is one: the
+    /// manager's own binary, otherwise `openclawPath`, otherwise the
+    /// bundled one
+    fn direct_binary(&self, config: &Config) -> Option<(PathBuf, ExecutableSource)> {
+        if let Some(ref binary) = self.binary_override {
+            return Some((binary.clone(), ExecutableSource::Override));
+        }
+        if let Some(ref path) = config.openclaw_path {
+            return Some((path.clone(), ExecutableSource::Config));
+        }
+        bundled::for_config(config)
+            .ok()
+            .map(|binary| (binary, ExecutableSource::Bundled))
+    }
+
+    /// The executable a start would spawn, or why there is none
+    pub fn find_program(&self, config: &Config) -> Result<String, AppError> {
+        match self.direct_binary(config) {
+            Some((binary, ExecutableSource::Config)) if !resolve::is_executable(&binary) => {
+                return Err(AppError::InvalidOpenclawPath {
+                    path: binary.display().to_string(),
+                });
+            }
+            Some((binary, _)) if !binary.is_file() => {
+                return Err(AppError::GatewayNotFound {
+                    detail: format!("{} does not exist.", binary.display()),
+                    searched: vec![binary.display().to_string()],
+                });
+            }
+            Some((binary, _)) => return Ok(binary.to_string_lossy().to_string()),
+            None => {}
+        }
+        if !RuntimeManager::is_installed() {
+            return Err(AppError::RuntimeNotInstalled);
+        }
+        let context = SearchContext::current(config);
+        match find_node_and_npx(&context) {
+            Some(node) => Ok(node.node),
+            None => Err(node_not_found(config, &context)),
+        }
+    }
+
+    /// Count a crash of a running gateway, explaining it when its last
+    /// stderr output is recognized, and leave it for the supervisor
+    fn record_crash(&self, state: &mut SidecarState, exit: ProcessExit) {
+        // An adopted gateway going away is its owner's doing
+        if state.child.as_ref().is_some_and(|child| !child.managed()) {
+            println!("[openclaw] The adopted gateway went away");
+            return;
+        }
+        self.metrics.record_crash();
+        let at = gateway_logs::now_ms();
+        state.last_exit = Some((exit, at));
+        // Only this gateway's, not what an earlier one left before the marker
+        let stderr_tail: Vec<String> = {
+            let logs = self.logs.lock();
+            let mut lines: Vec<String> = logs
+                .lines()
+                .rev()
+                .take_while(|line| line.stream != gateway_logs::MARKER_STREAM)
+                .filter(|line| line.stream == "stderr")
+                .take(CRASH_STDERR_LINES)
+                .map(|line| line.text.clone())
+                .collect();
+            lines.reverse();
+            lines
+        };
+        let info = CrashInfo {
+            exit_code: exit.code,
+            signal: exit.signal,
+            stderr_tail,
+            at,
+        };
+        let cause = spawn_failure::classify_stderr(&info.stderr_tail.join("\n"));
+        *lock(&self.last_crash) = Some(info.clone());
+        *lock(&self.crash) = Some(Crash {
+            exit,
+            info,
+            token: state.info.as_ref().map(|info| info.token.clone()),
+            port: state.info.as_ref().and_then(|info| info.port),
+            generation: state.info.as_ref().map_or(0, |info| info.generation),
+            uptime: state.started_at.map(|started_at| {
+                Duration::from_millis((at - started_at).max(0) as u64)
+            }),
+            stops: self.stop_count(),
+            launch: self.launch_info(),
+        });
+        if let Some(ref failure) = cause {
+            eprintln!("[openclaw] Gateway crashed ({}): {}", failure.code(), failure.describe());
+        }
+        let summary = match cause {
+            Some(ref failure) => format!("{} ({})", exit, failure.code()),
+            None => exit.to_string(),
+        };
+        self.history.record(
+            StatusTransition::now(TransitionKind::Crashed, summary).with_exit_code(exit.code),
+        );
+        *lock(&self.crash_cause) = cause;
+    }
+
+    /// Spawn the gateway described by a prepared plan and wait for it to be ready
+    fn execute<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, SidecarState>,
+        plan: StartPlan,
+        stops: u64,
+        generation: u64,
+    ) -> Result<StartResult, AppError> {
+        let StartPlan {
+            port,
+            port_source,
+            port_in_use,
+            bind_ip,
+            socket,
+            token,
+            program,
+            uses_npx,
+            executable,
+            args,
+            env,
+            clean_env,
+            low_priority,
+            heartbeat_timeout,
+            startup_timeout,
+            stop_grace,
+            token_in_url,
+            self_test,
+            tls,
+            sandbox,
+            workspace,
+            state_dir,
+            locale,
+            blocked_tools,
+            capabilities,
+            limits,
+            run_as,
+            mut warnings,
+            ..
+        } = plan;
+        let program = program.ok_or_else(|| {
+            self.metrics.record_start_failure("runtime");
+            AppError::RuntimeNotInstalled
+        })?;
+
+        if port_in_use {
+            // One the user started is theirs to stop, or for us to adopt
+            if adopted::detect(SocketAddr::new(connection::probe_ip(bind_ip), port)) {
+                self.metrics.record_start_failure("external_gateway_detected");
+                let pid = adopted::listener_pid(port);
+                println!(
+                    "[openclaw] Port {} is held by a gateway the app didn't start (pid {})",
+                    port,
+                    pid.map_or("unknown".to_string(), |pid| pid.to_string())
+                );
+                return Err(AppError::ExternalGatewayDetected {
+                    port: port.to_string(),
+                    pid: pid.map(|pid| pid.to_string()).unwrap_or_default(),
+                });
+            }
+            // Anything else is only stopped when the pid file vouches for
+            // it as a gateway of ours left running
+            println!("[openclaw] Port {} already in use, looking for a leftover gateway...", port);
+            drop(state); // Release lock before cleanup
+            let reclaimed = self.reap_leftover().is_some_and(|record| record.port == port);
+            let deadline = Instant::now() + Duration::from_millis(1500);
+            while reclaimed && !port::is_free(bind_ip, port) && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            state = lock(&self.state);
+
+            // Check again
+            if !reclaimed || !port::is_free(bind_ip, port) {
+                self.metrics.record_start_failure("port_in_use");
+                return Err(self.port_conflict(bind_ip, port));
+            }
+
+            warnings.push(StartWarning::new(
+                "port_reclaimed",
+                format!(
+                    "Port {} was held by a leftover gateway process, which was stopped.",
+                    port
+                ),
+            ));
+        }
+
+        // A socket a crashed gateway left is removed, one a leftover
+        // gateway still listens on is taken back like a port
+        if let Some(ref socket) = socket {
+            let prepared = match gateway_socket::prepare(socket) {
+                Err(AppError::SocketInUse { .. }) => {
+                    println!(
+                        "[openclaw] Socket {} already in use, cleaning up...",
+                        socket.display()
+                    );
+                    drop(state);
+                    if self.reap_leftover().is_some() {
+                        std::thread::sleep(std::time::Duration::from_millis(1500));
+                    }
+                    state = lock(&self.state);
+                    gateway_socket::prepare(socket)
+                }
+                prepared => prepared,
+            };
+            let removed = prepared.inspect_err(|e| {
+                self.metrics.record_start_failure(e.code());
+            })?;
+            if removed {
+                println!("[openclaw] Removed the leftover socket {}", socket.display());
+            }
+        }
+
+        self.enter_phase(StartPhase::Spawning);
+        if uses_npx {
+            println!("[openclaw] Starting gateway via bundled Node.js...");
+            println!("[openclaw] Using node at: {}", program);
+            if let Some(npx_cli) = args.first() {
+                println!("[openclaw] Using npx-cli at: {}", npx_cli);
+            }
+        } else {
+            println!("[openclaw] Starting gateway binary at: {}", program);
+        }
+        println!(
+            "[openclaw] Command line: {} {}",
+            program,
+            redact_token(&args, &token).join(" ")
+        );
+
+        // Clear npx cache to prevent corrupted package issues
+        // The npx cache at ~/.npm/_npx can become corrupted and cause
+        // "Cannot find package" errors with dependencies like axios
+        if uses_npx {
+            if let Err(e) = clear_npx_cache(self.npx_cache.as_deref()) {
+                warnings.push(StartWarning::new(
+                    "npx_cache_not_cleared",
+                    format!(
+                        "Could not clear the npx cache ({}). A stale cached package may be used.",
+                        e
+                    ),
+                ));
+            }
+        }
+
+        let env_names = env.names();
+        println!(
+            "[openclaw] Environment: {} ({})",
+            if clean_env { "clean" } else { "inherited plus" },
+            env_names.join(", ")
+        );
+        if let Some(path_env) = env.get("PATH") {
+            println!("[openclaw] PATH: {}", path_env.chars().take(200).collect::<String>());
+        }
+
+        if let Some(ref sandbox) = sandbox {
+            sandbox.create_dirs().map_err(|e| {
+                self.start_error(
+                    "sandbox",
+                    format!("Failed to create the sandbox workspace: {}", e),
+                )
+            })?;
+            println!("[openclaw] Sandbox workspace: {}", sandbox.workspace.display());
+        } else if let Some(ref dir) = workspace {
+            println!("[openclaw] Working directory: {}", dir.display());
+        }
+        if let Some(ref dir) = state_dir {
+            gateway_state::ensure(dir).map_err(|e| {
+                self.start_error(
+                    "gateway_state",
+                    format!("Failed to create the gateway state directory: {}", e),
+                )
+            })?;
+            println!("[openclaw] Gateway state: {}", dir.display());
+        }
+        if let Some(ref locale) = locale {
+            println!("[openclaw] {}", locale.describe());
+        }
+        println!("[openclaw] {}", capabilities.describe());
+        if let Some(ref run_as) = run_as {
+            for owned in &run_as.hand_over {
+                run_as::hand_over(&owned.dir, &run_as.user, owned.recursive)
+                    .map_err(|e| self.start_error("run_as", e.to_string()))?;
+            }
+            println!(
+                "[openclaw] Running as {} (uid {})",
+                run_as.user.name, run_as.user.uid
+            );
+        }
+
+        // Only the redacted report is kept
+        *lock(&self.last_env) = Some(EffectiveEnv {
+            spawned: true,
+            clean_environment: clean_env,
+            vars: env.report(),
+        });
+
+        let launch_args = redact_token(&args, &token);
+
+        // Spawn node directly with npx-cli.js to avoid shebang issues
+        // This ensures we use our bundled node, not whatever is in /usr/bin/env
+        let spec = LaunchSpec {
+            program: program.clone(),
+            args,
+            env: env.vars(),
+            clean_env,
+            low_priority,
+            cwd: workspace.clone(),
+            user: run_as.as_ref().map(|run_as| ProcessUser {
+                uid: run_as.user.uid,
+                gid: run_as.user.gid,
+            }),
+        };
+        if self.stop_count() != stops {
+            self.metrics.record_start_failure("cancelled");
+            println!("[openclaw] Start cancelled by a stop before spawning");
+            return Err(AppError::GatewayStartCancelled);
+        }
+        let spawned_at = Instant::now();
+        let mut child = match self.launcher.spawn(&spec) {
+            Ok(child) => child,
+            Err(e) => {
+                let failure = spawn_failure::diagnose(Path::new(&program), Some(&e), None, None);
+                let (reason, detail) = match failure {
+                    Some(ref failure) => (failure.code(), format!("{}. {}", e, failure.describe())),
+                    None => ("spawn", e.to_string()),
+                };
+                self.metrics.record_start_failure(reason);
+                let error = AppError::GatewaySpawnFailed { detail };
+                // A missing executable won't turn up by trying again
+                if failure.is_some() || e.kind() == std::io::ErrorKind::NotFound {
+                    return Err(error);
+                }
+                return Err(self.transient(error));
+            }
+        };
+        self.logs.mark_restart();
+        *lock(&self.last_launch) = Some(Launch {
+            descriptor: LaunchDescriptor {
+                binary_path: program.clone(),
+                args: launch_args,
+                env_keys: env_names,
+                started_at: gateway_logs::now_ms(),
+                cwd: workspace.as_ref().map(|dir| dir.display().to_string()),
+            },
+            pid: child.id(),
+            info: None,
+        });
+        if let Some(ref pid_file) = self.pid_file {
+            let record = GatewayRecord {
+                pid: child.id(),
+                port,
+                token: token.clone(),
+                started_at: gateway_logs::now_ms(),
+            };
+            if let Err(e) = pid_file.write(&record) {
+                eprintln!("[openclaw] Failed to record the gateway pid: {}", e);
+            }
+        }
+
+        // Capture stdout, which also keeps the gateway from blocking on a
+        // full pipe, and pick up the port from its startup line in case its
+        // own config overrides the one we passed
+        let stats = Arc::new(Mutex::new(GatewayStats::default()));
+        let usage = Arc::new(Mutex::new(UsageEstimate::default()));
+        let announced_port = child.take_stdout().map(|stdout| {
+            let parses_port = socket.is_none();
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let stats = stats.clone();
+            let usage = usage.clone();
+            self.logs.capture("stdout", stdout, move |line| {
+                if let Some(port) = port::parse_listening_port(line).filter(|_| parses_port) {
+                    println!("[openclaw] {}", line.trim());
+                    let _ = sender.send(port);
+                }
+                lock(&stats).observe(line, gateway_logs::now_ms());
+                lock(&usage).observe(line, gateway_logs::now_ms());
+            });
+            receiver
+        });
+        // Read from the start too, keeping its end for a failed start's error
+        let stderr = match child.take_stderr() {
+            Some(pipe) => {
+                let tail = StderrTail::default();
+                let lines = tail.lines.clone();
+                let stats = stats.clone();
+                let usage = usage.clone();
+                let reader = self.logs.capture("stderr", pipe, move |line| {
+                    StderrTail::push(&lines, line);
+                    lock(&stats).observe(line, gateway_logs::now_ms());
+                    lock(&usage).observe(line, gateway_logs::now_ms());
+                });
+                StderrTail {
+                    reader: Some(reader),
+                    ..tail
+                }
+            }
+            None => StderrTail::default(),
+        };
+        let mut listen_port = port;
+        let mut port_source = port_source;
+        let probe_ip = connection::probe_ip(bind_ip);
+        let endpoint_on = |port: u16| match socket {
+            Some(ref socket) => Endpoint::Unix(socket.clone()),
+            None => Endpoint::Tcp(SocketAddr::new(probe_ip, port)),
+        };
+        let listening_on = |port: u16| match socket {
+            Some(ref socket) => format!("socket {}", socket.display()),
+            None => format!("port {}", port),
+        };
+
+        println!("[openclaw] Gateway process started (PID: {:?}), waiting for it to be ready...", child.id());
+        self.enter_phase(StartPhase::WaitingReady);
+
+        // Lowering the priority is best effort, so only warn when it didn't stick
+        if low_priority {
+            match child.priority() {
+                Some(ref priority) if priority.lowered => {
+                    println!("[openclaw] Gateway running at reduced priority ({})", priority.detail);
+                }
+                Some(priority) => warnings.push(StartWarning::new(
+                    "priority_not_lowered",
+                    format!(
+                        "Could not lower the gateway's CPU priority; it is running at {}.",
+                        priority.detail
+                    ),
+                )),
+                None => warnings.push(StartWarning::new(
+                    "priority_not_lowered",
+                    "Could not confirm that the gateway runs at reduced CPU priority.",
+                )),
+            }
+        }
+
+        // Wait for gateway to be ready (check if port is listening)
+        let deadline = Instant::now() + startup_timeout;
+        let mut ready = false;
+        let mut attempt = 0;
+        while Instant::now() < deadline {
+            attempt += 1;
+            if self.stop_count() != stops {
+                return Err(self.cancel_start(child.as_mut(), &mut state));
+            }
+            // Check if process is still running
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    self.abandon_start(child.as_mut(), &mut state);
+                    return Err(self.exit_error(&stderr, status, &program, spawned_at));
+                }
+                Ok(None) => {} // Still running, good
+                Err(e) => {
+                    self.abandon_start(child.as_mut(), &mut state);
+                    return Err(self.start_error(
+                        "status_check",
+                        format!("Failed to check gateway status: {}", e),
+                    ));
+                }
+            }
+
+            if let Some(reported) = announced_port.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                if reported != listen_port {
+                    println!(
+                        "[openclaw] Gateway reports port {} instead of {}",
+                        reported, port
+                    );
+                    listen_port = reported;
+                    port_source = PortSource::GatewayOverride;
+                }
+            }
+
+            // Try to connect to the port
+            if endpoint_on(listen_port).accepts() {
+                ready = true;
+                println!("[openclaw] Gateway ready after {} attempts", attempt);
+                break;
+            }
+
+            // Wait 500ms before retrying
+            std::thread::sleep(std::time::Duration::from_millis(500));
+        }
+
+        if !ready {
+            // Kill the process if it never became ready
+            self.abandon_start(child.as_mut(), &mut state);
+            return Err(self.transient(self.start_error(
+                "timeout",
+                format!(
+                    "Gateway failed to start within {} seconds: nothing was listening on {}, \
+                     so it was stopped. Please check your internet connection and try again.{}",
+                    startup_timeout.as_secs(),
+                    listening_on(listen_port),
+                    self.startup_output(&stderr)
+                ),
+            )));
+        }
+
+        // A gateway that can't use its config, e.g. one whose API key or
+        // flags are rejected, may listen briefly and then exit
+        while spawned_at.elapsed() < STARTUP_GRACE {
+            if self.stop_count() != stops {
+                return Err(self.cancel_start(child.as_mut(), &mut state));
+            }
+            if let Ok(Some(status)) = child.try_wait() {
+                self.abandon_start(child.as_mut(), &mut state);
+                return Err(self.exit_error(&stderr, status, &program, spawned_at));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+
+        // Made with the gateway's umask; the private directory covers the
+        // moment in between
+        if let Some(ref socket) = socket {
+            if let Err(e) = gateway_socket::restrict(socket) {
+                self.abandon_start(child.as_mut(), &mut state);
+                return Err(self.start_error(
+                    "socket",
+                    format!("Could not make the socket {} private: {}", socket.display(), e),
+                ));
+            }
+        }
+
+        // The protocol client doesn't speak TLS, so only the handshake is
+        // checked, trusting the certificate whoever signed it
+        if self_test && tls {
+            let address = SocketAddr::new(probe_ip, listen_port);
+            match tls::handshake(address, SELF_TEST_TIMEOUT) {
+                Ok(elapsed) => {
+                    println!("[openclaw] TLS handshake passed in {} ms", elapsed.as_millis());
+                }
+                Err(e) => {
+                    self.abandon_start(child.as_mut(), &mut state);
+                    eprintln!("[openclaw] TLS check failed: {}", e);
+                    return Err(self.start_error(
+                        "tls_handshake",
+                        format!("The gateway is listening on port {} but {}.", listen_port, e),
+                    ));
+                }
+            }
+        }
+
+        // A gateway that rejects our own token is no use to the frontend
+        if self_test && !tls {
+            match protocol::auth_self_test(endpoint_on(listen_port), &token, SELF_TEST_TIMEOUT) {
+                Ok(elapsed) => {
+                    println!("[openclaw] Auth self-test passed in {} ms", elapsed.as_millis());
+                    self.metrics.record_auth_self_test(true, elapsed);
+                }
+                Err(e) => {
+                    self.metrics.record_auth_self_test(false, spawned_at.elapsed());
+                    self.abandon_start(child.as_mut(), &mut state);
+                    self.metrics.record_start_failure("auth_self_test");
+                    eprintln!("[openclaw] Auth self-test failed: {}", e);
+                    return Err(AppError::AuthSelfTestFailed {
+                        detail: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        let lan_ip = if bind_ip.is_unspecified() {
+            connection::primary_lan_ip(bind_ip)
+        } else {
+            None
+        };
+        let url_token = token_in_url.then_some(token.as_str());
+        let info = GatewayInfo {
+            url: match socket {
+                Some(ref socket) => connection::socket_url(socket, url_token),
+                None => connection::gateway_url(
+                    &connection::url_host(bind_ip, lan_ip),
+                    listen_port,
+                    tls,
+                    url_token,
+                ),
+            },
+            port: socket.is_none().then_some(listen_port),
+            socket_path: socket.as_ref().map(|socket| socket.display().to_string()),
+            token,
+            lan_exposed: socket.is_none() && connection::is_lan_exposed(bind_ip),
+            workspace: workspace.map(|dir| dir.display().to_string()),
+            generation,
+        };
+        if port_source == PortSource::GatewayOverride {
+            warnings.push(StartWarning::new(
+                "port_overridden",
+                format!(
+                    "The gateway is listening on port {} instead of the configured port {}.",
+                    listen_port, port
+                ),
+            ));
+        }
+
+        if let Some(launch) = lock(&self.last_launch).as_mut() {
+            launch.info = Some(info.clone());
+        }
+        *lock(&self.port_conflicts) = (0, 0);
+        state.child = Some(child);
+        state.info = Some(info.clone());
+        self.serving_port.store(info.port.map_or(0, u32::from), Ordering::SeqCst);
+        state.warnings = warnings.clone();
+        state.executable = Some(executable);
+        state.sandbox = sandbox.map(|sandbox| sandbox.status);
+        state.blocked_tools = blocked_tools;
+        state.limits = limits;
+        state.run_as_uid = run_as.map(|run_as| run_as.user.uid);
+        state.port_source = Some(port_source);
+        state.probe_ip = Some(probe_ip);
+        state.started_at = Some(gateway_logs::now_ms());
+        state.stop_grace = stop_grace;
+        state.stats = stats;
+        state.usage = usage;
+        *lock(&self.crash_cause) = None;
+        *lock(&self.last_crash) = None;
+        self.metrics.record_start_success();
+        self.reset_heartbeat(heartbeat_timeout);
+        self.history.record(StatusTransition::now(
+            TransitionKind::Started,
+            format!("Listening on {}", info.listen_address()),
+        ));
+
+        println!("[openclaw] Gateway running at {}", connection::redact_tokens(&info.url));
+        for warning in &warnings {
+            println!("[openclaw] Warning ({}): {}", warning.code, warning.message);
+        }
+        Ok(StartResult { info, warnings })
+    }
+
+    /// Stop the gateway, giving it `gatewayStopGraceSecs` to shut down
+    /// before it is killed. None when it wasn't running.
+    pub fn stop(&self) -> Result<Option<StopKind>, AppError> {
+        self.stop_as(TransitionKind::Stopped, "Stop requested")
+    }
+
+    /// `stop`, recorded in the history as `kind` because of `cause`
+    fn stop_as(&self, kind: TransitionKind, cause: &str) -> Result<Option<StopKind>, AppError> {
+        // Counted first, so a start holding the state sees it and gives up
+        self.stops.fetch_add(1, Ordering::SeqCst);
+        let mut state = lock(&self.state);
+
+        // Not a crash, and nothing to restart after
+        *lock(&self.crash) = None;
+        lock(&self.backoff).reset();
+
+        let grace = state.stop_grace;
+        let paused = state.paused;
+        let generation = self.generation.load(Ordering::SeqCst);
+        let mut stopped = None;
+        if let Some(ref mut child) = state.child {
+            println!("[openclaw] Stopping gateway...");
+            self.history.record(StatusTransition::now(kind, cause));
+
+            // A stopped process can't handle SIGTERM, it would only be
+            // killed once the grace period is over
+            if paused {
+                match child.resume() {
+                    Ok(()) => println!("[openclaw] Resumed the paused gateway to stop it"),
+                    Err(e) => eprintln!("[openclaw] Failed to resume the paused gateway: {}", e),
+                }
+            }
+
+            // Stop the process and all its children
+            stopped = Some(match child.stop(grace) {
+                Ok(StopKind::Graceful) => {
+                    println!("[openclaw] Gateway stopped");
+                    StopKind::Graceful
+                }
+                Ok(StopKind::Forced) => {
+                    println!(
+                        "[openclaw] Gateway killed after not stopping within {} seconds",
+                        grace.as_secs()
+                    );
+                    StopKind::Forced
+                }
+                Ok(StopKind::Detached) => {
+                    println!("[openclaw] Let go of the adopted gateway, which keeps running");
+                    StopKind::Detached
+                }
+                Err(e) => {
+                    eprintln!("[openclaw] Failed to kill gateway: {}", e);
+                    StopKind::Forced
+                }
+            });
+        }
+        self.clear_state(&mut state);
+        if let Some(ref pid_file) = self.pid_file {
+            if let Err(e) = pid_file.clear() {
+                eprintln!("[openclaw] Failed to remove the gateway pid file: {}", e);
+            }
+        }
+
+        drop(state);
+
+        if stopped.is_some() {
+            let phase = match kind {
+                TransitionKind::Restarting => GatewayPhase::Restarting,
+                _ => GatewayPhase::Stopped,
+            };
+            self.announce(generation, phase, None, None);
+        }
+        Ok(stopped)
+    }
+
+    /// Stop the gateway and start a fresh one
+    pub fn restart(&self, reason: &str) -> Result<StartResult, AppError> {
+        println!("[openclaw] Restarting gateway ({})", reason);
+        self.stop_as(TransitionKind::Restarting, reason)?;
+        self.start()
+    }
+
+    /// Stop the gateway and start one with the current config and the same
+    /// token, so connected clients only need to reconnect. The port is the
+    /// same unless `gatewayPort` changed or it is taken now; on another
+    /// port the gateway gets a new token. Starts one when none is running.
+    pub fn restart_keeping_token(&self, reason: &str) -> Result<StartResult, AppError> {
+        let token = lock(&self.state).info.as_ref().map(|info| KeptToken {
+            token: info.token.clone(),
+            port: info.port,
+        });
+        println!("[openclaw] Restarting gateway ({})", reason);
+        self.stop_as(TransitionKind::Restarting, reason)?;
+        self.start_with_token(token)
+    }
+
+    /// When the running gateway was started (Unix ms). None when there is
+    /// none or it is paused.
+    pub fn running_since(&self) -> Option<i64> {
+        let state = lock(&self.state);
+        if state.child.is_none() || state.paused {
+            return None;
+        }
+        state.started_at
+    }
+
+    /// `restart_keeping_token`, if the gateway started at `started_at` (see
+    /// `running_since`) still runs. None when it was stopped, paused or
+    /// replaced meanwhile, so a stop cancels a restart decided before it.
+    pub fn restart_if_running_since(
+        &self,
+        started_at: i64,
+        reason: &str,
+    ) -> Option<Result<StartResult, AppError>> {
+        if self.is_starting() || self.running_since() != Some(started_at) {
+            return None;
+        }
+        Some(self.restart_keeping_token(reason))
+    }
+
+    /// Restart the gateway with a new token, so the old one stops working.
+    /// Starts one when none is running. With `persistGatewayToken` the new
+    /// token is the one kept from then on.
+    pub fn regenerate_token(&self) -> Result<StartResult, AppError> {
+        let token = generate_token().map_err(|e| AppError::GatewayStartFailed {
+            detail: format!("Could not generate a gateway token: {}", e),
+        })?;
+        println!("[openclaw] Restarting gateway (token regenerated)");
+        self.stop_as(TransitionKind::Restarting, "token regenerated")?;
+        self.start_with_token(Some(KeptToken { token, port: None }))
+    }
+
+    /// Suspend the gateway so it uses no CPU until `resume`; it keeps its
+    /// memory and port. Fails with `gateway_not_running` when there is no
+    /// gateway and `gateway_pause_unsupported` on Windows.
+    pub fn pause(&self) -> Result<(), AppError> {
+        let mut state = lock(&self.state);
+        if state.paused {
+            return Ok(());
+        }
+        let child = state.child.as_mut().ok_or(AppError::GatewayNotRunning)?;
+        child.suspend().map_err(pause_error)?;
+        state.paused = true;
+        drop(state);
+        println!("[openclaw] Gateway paused");
+        Ok(())
+    }
+
+    /// Let a gateway `pause` suspended run again. Does nothing when it
+    /// isn't paused.
+    pub fn resume(&self) -> Result<(), AppError> {
+        let mut state = lock(&self.state);
+        self.resume_locked(&mut state)
+    }
+
+    fn resume_locked(&self, state: &mut SidecarState) -> Result<(), AppError> {
+        if !state.paused {
+            return Ok(());
+        }
+        let child = state.child.as_mut().ok_or(AppError::GatewayNotRunning)?;
+        child.resume().map_err(pause_error)?;
+        state.paused = false;
+        // Nothing was answered while it was paused
+        let timeout = lock(&self.heartbeat).timeout();
+        self.reset_heartbeat(timeout);
+        println!("[openclaw] Gateway resumed");
+        Ok(())
+    }
+
+    /// Record request activity reported by the frontend
+    pub fn report_activity(&self, report: ActivityReport) {
+        lock(&self.heartbeat).report(report, Instant::now());
+    }
+
+    /// Health of the gateway from the reported activity. Cheap, so the
+    /// watchdog can poll it without touching the process.
+    pub fn health(&self) -> GatewayHealth {
+        let (running, paused) = {
+            let state = lock(&self.state);
+            (state.child.is_some(), state.paused)
+        };
+        if !running {
+            return GatewayHealth::Stopped;
+        }
+        // Unanswered on purpose, not for the watchdog to restart
+        if paused {
+            return GatewayHealth::Idle;
+        }
+        lock(&self.heartbeat).health(Instant::now())
+    }
+
+    /// Forget the activity of the previous gateway
+    fn reset_heartbeat(&self, timeout: Duration) {
+        *lock(&self.heartbeat) = HeartbeatMonitor::new(timeout);
+        lock(&self.port_probe).reset();
+    }
+
+    /// Whether the running gateway accepts connections at `endpoint`, from
+    /// the cached probe when it is recent
+    fn accepts_connections(&self, endpoint: &Endpoint) -> Option<bool> {
+        Some(lock(&self.port_probe).check(Instant::now(), || {
+            endpoint.connect(heartbeat::PROBE_TIMEOUT).is_ok()
+        }))
+    }
+
+    /// Whether the running gateway answers a `health` request within
+    /// `timeout`, asked now rather than taken from the cached port probe.
+    /// Over TLS, which the protocol client doesn't speak, only whether it
+    /// accepts connections. None when there is no gateway or it is paused.
+    pub fn check_responsive(&self, timeout: Duration) -> Option<Result<(), String>> {
+        let (endpoint, token, tls) = {
+            let state = lock(&self.state);
+            if state.child.is_none() || state.paused {
+                return None;
+            }
+            let info = state.info.as_ref()?;
+            let probe_ip = state.probe_ip.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+            (
+                info.endpoint(probe_ip),
+                info.token.clone(),
+                info.url.starts_with("wss://"),
+            )
+        };
+        // What it saw may be from before
+        lock(&self.port_probe).reset();
+        let result = if tls {
+            endpoint
+                .connect(timeout)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        } else {
+            protocol::auth_self_test(endpoint, &token, timeout)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        Some(result)
+    }
+
+    /// Whether switching to `port` only takes effect after restarting the
+    /// running gateway. One on a socket doesn't use the port.
+    pub fn port_change_requires_restart(&self, port: u16) -> bool {
+        lock(&self.state)
+            .info
+            .as_ref()
+            .and_then(|info| info.port)
+            .is_some_and(|serving| serving != port)
+    }
+
+    /// Whether blocking `tools` only takes effect after restarting the
+    /// running gateway
+    pub fn blocked_tools_change_requires_restart(&self, tools: &[String]) -> bool {
+        let state = lock(&self.state);
+        state.child.is_some() && state.blocked_tools != tools
+    }
+
+    /// Clients and requests of the running gateway, see `gateway_stats`
+    pub fn stats(&self) -> GatewayStats {
+        lock(&lock(&self.state).stats).clone()
+    }
+
+    /// Model usage of the running gateway, see `spend`
+    pub fn usage(&self) -> UsageEstimate {
+        lock(&lock(&self.state).usage).clone()
+    }
+
+    /// PID of the running gateway process, if any
+    pub fn gateway_pid(&self) -> Option<u32> {
+        lock(&self.state).child.as_ref().map(|child| child.id())
+    }
+
+    /// What the running gateway uses, see `gateway_metrics`. Fails with
+    /// `gateway_not_running` when there is no gateway.
+    pub fn gateway_metrics(&self) -> Result<GatewayMetrics, AppError> {
+        let (pid, port, started_at) = {
+            let state = lock(&self.state);
+            match (state.child.as_ref(), state.info.as_ref()) {
+                (Some(child), Some(info)) => (child.id(), info.port, state.started_at),
+                _ => return Err(AppError::GatewayNotRunning),
+            }
+        };
+
+        // Without the lock, since listing processes takes a moment
+        let (rss_bytes, cpu_ms) =
+            resources::process_tree_usage(pid).ok_or(AppError::GatewayNotRunning)?;
+        let open_connections = port.and_then(resources::open_connections);
+        let sample = CpuSample {
+            pid,
+            cpu_ms,
+            at: Instant::now(),
+        };
+
+        let mut state = lock(&self.state);
+        // Stopped or replaced meanwhile
+        if state.child.as_ref().map(|child| child.id()) != Some(pid) {
+            return Err(AppError::GatewayNotRunning);
+        }
+        let cpu_percent = state
+            .cpu_sample
+            .and_then(|previous| gateway_metrics::cpu_percent(&previous, &sample));
+        state.cpu_sample = Some(sample);
+        let now = gateway_logs::now_ms();
+        Ok(GatewayMetrics {
+            pid,
+            rss_bytes,
+            cpu_percent,
+            uptime_secs: started_at
+                .map_or(0, |started_at| ((now - started_at).max(0) / 1000) as u64),
+            open_connections,
+            sampled_at: now,
+        })
+    }
+
+    /// Get gateway status
+    pub fn status(&self) -> GatewayStatus {
+        // A start holds the state until the gateway is ready, which can
+        // take seconds, so don't wait for it
+        let mut state = match self.state.try_lock() {
+            Ok(state) => state,
+            Err(TryLockError::WouldBlock) if self.starting.load(Ordering::SeqCst) => {
+                return self.idle_status(None);
+            }
+            Err(TryLockError::WouldBlock) => lock(&self.state),
+            // Consistent all the same, see `lock`
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        };
+        let config = self.config_snapshot();
+
+        // A gateway the app started still counts until it is stopped
+        if state.child.is_none() {
+            let external = config.as_ref().and_then(|config| config.external_gateway.as_ref());
+            if let Some(external) = external {
+                drop(state);
+                return self.external_status(external);
+            }
+        }
+
+        // Check if runtime is installed, unless the gateway runs without it
+        if !RuntimeManager::is_installed()
+            && !config
+                .as_ref()
+                .is_some_and(|config| self.direct_binary(config).is_some())
+        {
+            return self.idle_status(Some("runtime_not_installed".to_string()));
+        }
+
+        let mut exited = None;
+        if let Some(ref mut child) = state.child {
+            // A paused gateway is stopped, not exited: without WUNTRACED
+            // waitpid doesn't report stopped children, so this is None
+            match child.try_wait() {
+                Ok(Some(exit)) => {
+                    self.record_crash(&mut state, exit);
+                    self.clear_state(&mut state);
+                    exited = Some(exit);
+                }
+                Ok(None) => self.restore_info(&mut state),
+                Err(_) => {
+                    self.clear_state(&mut state);
+                }
+            }
+        }
+
+        let running = state.child.is_some();
+        let paused = running && state.paused;
+        let accepting_connections = match (running && !paused, state.info.as_ref()) {
+            (true, Some(info)) => {
+                let probe_ip = state.probe_ip.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+                self.accepts_connections(&info.endpoint(probe_ip))
+            }
+            _ => None,
+        };
+        let (mut health, last_response_at) = if running {
+            let heartbeat = lock(&self.heartbeat);
+            (
+                if paused {
+                    GatewayHealth::Idle
+                } else {
+                    heartbeat.health(Instant::now())
+                },
+                heartbeat.last_report().and_then(|report| report.last_success_at),
+            )
+        } else {
+            (GatewayHealth::Stopped, None)
+        };
+        // Still running, but nothing can reach it
+        if accepting_connections == Some(false) {
+            health = GatewayHealth::Unresponsive;
+        }
+        self.history.observe_health(health);
+
+        let configured_port = config.as_ref().map(|config| config.gateway_port);
+        let effective_port = state.info.as_ref().and_then(|info| info.port);
+        let port_mismatch = match (configured_port, effective_port) {
+            (Some(configured), Some(effective)) => port::port_mismatch(
+                configured,
+                effective,
+                state.port_source.unwrap_or(PortSource::Configured),
+            ),
+            _ => None,
+        };
+
+        // An adopted gateway's pid may not be known
+        let pid = state.child.as_ref().map(|child| child.id()).filter(|pid| *pid != 0);
+        let status = GatewayStatus {
+            running,
+            external: false,
+            managed: state.child.as_ref().is_none_or(|child| child.managed()),
+            info: state.info.clone(),
+            pid,
+            executable: state.executable.clone(),
+            priority: state.child.as_ref().and_then(|child| child.priority()),
+            effective_uid: pid.and_then(|pid| {
+                run_as::process_euid(pid)
+                    .or(state.run_as_uid)
+                    .or_else(run_as::current_euid)
+            }),
+            sandbox: state.sandbox.clone(),
+            blocked_tools: state.blocked_tools.clone(),
+            limits: state.limits.clone(),
+            configured_port,
+            effective_port,
+            port_mismatch,
+            health,
+            accepting_connections,
+            last_response_at,
+            started_at: state.started_at,
+            uptime_secs: state
+                .started_at
+                .map(|started_at| ((gateway_logs::now_ms() - started_at).max(0) / 1000) as u64),
+            last_exit_code: state.last_exit.and_then(|(exit, _)| exit.code),
+            last_exit_signal: state.last_exit.and_then(|(exit, _)| exit.signal),
+            last_exit_at: state.last_exit.map(|(_, at)| at),
+            last_crash: self.last_crash(),
+            crash_restarts: self.crash_restarts(),
+            paused,
+            starting: self.starting.load(Ordering::SeqCst),
+            generation: self.generation(),
+            // Code of a recognized crash cause, see `spawn_failure`
+            error: if running {
+                None
+            } else {
+                lock(&self.crash_cause)
+                    .as_ref()
+                    .map(|failure| failure.code().to_string())
+            },
+        };
+        drop(state);
+
+        if let Some(exit) = exited {
+            let generation = self.generation.load(Ordering::SeqCst);
+            self.announce(generation, GatewayPhase::Exited, exit.code, Some(status.clone()));
+        }
+        status
+    }
+
+    /// Status without a gateway to describe
+    fn idle_status(&self, error: Option<String>) -> GatewayStatus {
+        GatewayStatus {
+            running: false,
+            external: false,
+            managed: true,
+            info: None,
+            pid: None,
+            executable: None,
+            priority: None,
+            effective_uid: None,
+            sandbox: None,
+            blocked_tools: Vec::new(),
+            limits: ConcurrencyLimits::default(),
+            configured_port: None,
+            effective_port: None,
+            port_mismatch: None,
+            health: if self.starting.load(Ordering::SeqCst) {
+                GatewayHealth::Starting
+            } else {
+                GatewayHealth::Stopped
+            },
+            accepting_connections: None,
+            last_response_at: None,
+            started_at: None,
+            uptime_secs: None,
+            last_exit_code: None,
+            last_exit_signal: None,
+            last_exit_at: None,
+            last_crash: self.last_crash(),
+            crash_restarts: self.crash_restarts(),
+            paused: false,
+            starting: self.starting.load(Ordering::SeqCst),
+            generation: self.generation(),
+            error,
+        }
+    }
+}
+
+/// Outcome of a single preflight check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl PreflightCheck {
+    fn new(name: &str, passed: bool, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// What `start_gateway` would do, as returned by `preview_gateway_start`.
+/// Secrets are never included: the token is redacted from `args` and only
+/// environment variable names are listed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartPreview {
+    pub program: Option<String>,
+    pub args: Vec<String>,
+    /// Whether the gateway gets only `env_names` (clean mode) or inherits
+    /// the app's environment with `env_names` set on top
+    pub clean_environment: bool,
+    pub env_names: Vec<String>,
+    pub port: Option<u16>,
+    /// The socket of the `unix` transport, which takes the place of `port`
+    pub socket_path: Option<String>,
+    pub executable: Option<ExecutableInfo>,
+    /// Every flag the app may pass and whether the gateway accepts it
+    pub capabilities: Vec<FlagSupport>,
+    pub checks: Vec<PreflightCheck>,
+    /// Side effects a real start would perform before spawning
+    pub side_effects: Vec<String>,
+    pub warnings: Vec<StartWarning>,
+    pub errors: Vec<String>,
+}
+
+/// Everything needed to spawn the gateway, computed by `prepare`
+struct StartPlan {
+    port: u16,
+    /// Configured or, with `autoSelectPort`, picked because it was taken
+    port_source: PortSource,
+    /// The configured port is taken and leftover gateways will be stopped
+    port_in_use: bool,
+    /// `gatewayHost`
+    bind_ip: IpAddr,
+    /// Listen on this socket instead of `port` (see `gateway_socket`)
+    socket: Option<PathBuf>,
+    token: String,
+    /// Executable to spawn; None when nothing suitable was found
+    program: Option<String>,
+    /// Whether the gateway is launched through `npx openclaw`
+    uses_npx: bool,
+    executable: ExecutableInfo,
+    args: Vec<String>,
+    env: GatewayEnv,
+    /// `env` is the complete environment rather than additions
+    clean_env: bool,
+    low_priority: bool,
+    heartbeat_timeout: Duration,
+    /// How long to wait for the gateway to accept connections
+    startup_timeout: Duration,
+    /// How long the gateway gets to shut down when stopped
+    stop_grace: Duration,
+    /// Embed the token in `GatewayInfo.url`
+    token_in_url: bool,
+    /// Prove the gateway accepts `token` once it is ready, or with `tls`
+    /// that it completes a handshake
+    self_test: bool,
+    /// Serves `wss://` with the certificate in `args`
+    tls: bool,
+    sandbox: Option<SandboxPlan>,
+    /// Working directory: the sandbox workspace, `workspaceDir`, or the
+    /// home of the user the gateway runs as
+    workspace: Option<PathBuf>,
+    /// Passed as `OPENCLAW_STATE_DIR`, created at start if missing
+    state_dir: Option<PathBuf>,
+    /// Passed as `LANG`, `LC_ALL` and `TZ`
+    locale: Option<GatewayLocale>,
+    /// Tools disabled by the `--deny-tool` flags in `args`
+    blocked_tools: Vec<String>,
+    /// Why the blocked tools can't be disabled, also listed in `errors`
+    blocked_tools_error: Option<BlockedToolsError>,
+    /// What `--version` said
+    gateway_version: Option<GatewayVersion>,
+    /// It is older than `MIN_OPENCLAW_VERSION`, also listed in `errors`
+    version_error: Option<VersionError>,
+    /// Flags the installed gateway accepts
+    capabilities: GatewayCapabilities,
+    /// A required flag it doesn't, also listed in `errors`
+    capability_error: Option<CapabilityError>,
+    limits: ConcurrencyLimits,
+    run_as: Option<RunAsPlan>,
+    /// A volume below `HARD_MIN_FREE_BYTES`, also listed in `errors`
+    disk_full: Option<InsufficientSpace>,
+    checks: Vec<PreflightCheck>,
+    warnings: Vec<StartWarning>,
+    errors: Vec<AppError>,
+}
+
+impl StartPlan {
+    fn preview(&self) -> StartPreview {
+        let mut side_effects = Vec::new();
+        if self.port_in_use {
+            side_effects.push(format!(
+                "Stop leftover gateway processes listening on port {}",
+                self.port
+            ));
+        }
+        if let Some(ref socket) = self.socket {
+            if socket.symlink_metadata().is_ok() {
+                side_effects.push(format!(
+                    "Remove the leftover socket {} unless a gateway still listens on it",
+                    socket.display()
+                ));
+            }
+        }
+        if self.uses_npx {
+            side_effects.push("Clear the npx package cache".to_string());
+        }
+        if let Some(ref sandbox) = self.sandbox {
+            side_effects.push(format!(
+                "Create the sandbox workspace {} if missing",
+                sandbox.workspace.display()
+            ));
+            if let Some(ref run_as) = self.run_as {
+                side_effects.push(format!(
+                    "Give {} ownership of {}",
+                    run_as.user.name,
+                    sandbox.workspace.display()
+                ));
+            }
+        }
+        if let Some(ref dir) = self.state_dir {
+            if !dir.is_dir() {
+                side_effects.push(format!("Create the gateway state directory {}", dir.display()));
+            }
+            if let Some(ref run_as) = self.run_as {
+                side_effects.push(format!(
+                    "Give {} ownership of {}",
+                    run_as.user.name,
+                    dir.display()
+                ));
+            }
+        }
+
+        StartPreview {
+            program: self.program.clone(),
+            args: redact_token(&self.args, &self.token),
+            clean_environment: self.clean_env,
+            env_names: self.env.names(),
+            port: self.socket.is_none().then_some(self.port),
+            socket_path: self.socket.as_ref().map(|path| path.display().to_string()),
+            executable: Some(self.executable.clone()),
+            capabilities: self.capabilities.table(),
+            checks: self.checks.clone(),
+            side_effects,
+            warnings: self.warnings.clone(),
+            errors: self.errors.iter().map(AppError::to_string).collect(),
+        }
+    }
+}
+
+impl SidecarManager {
+    /// Prepare a gateway start: load config, check credentials, resolve the
+    /// gateway executable, probe the port and assemble the arguments and
+    /// environment.
+    ///
+    /// This has no side effects - nothing is killed or deleted and only a
+    /// read-only login shell (for `forwardDevEnv`) and the gateway's
+    /// `--version` and `gateway --help` may be spawned - so it
+    /// backs both `start` and `preview_gateway_start`. Problems that would
+    /// stop the start are collected in `errors` rather than returned early,
+    /// so a preview can report all of them at once. Only a config that can't
+    /// be loaded at all is returned as `Err`.
+    fn prepare(&self) -> Result<StartPlan, AppError> {
+        let config = self.load_config()?;
+
+        let mut checks = Vec::new();
+        let mut warnings = Vec::new();
+        let mut errors = Vec::new();
+
+        // Check if port is already in use (another instance might be running,
+        // or something else entirely)
+        let bind_ip = config.gateway_ip().unwrap_or_else(|e| {
+            errors.push(e);
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        });
+        let socket = gateway_socket::plan(&config).unwrap_or_else(|e| {
+            errors.push(e);
+            None
+        });
+        let configured_port = config.gateway_port;
+        // A gateway on a socket leaves the port alone
+        let availability = match socket {
+            Some(_) => PortAvailability::Free,
+            None => port::availability(bind_ip, configured_port),
+        };
+        let taken = availability == PortAvailability::InUse;
+        let not_permitted = availability == PortAvailability::NotPermitted;
+        let picked = if (taken || not_permitted) && config.auto_select_port {
+            port::pick_free(bind_ip)
+        } else {
+            None
+        };
+        let (port, port_source) = match picked {
+            Some(free) => (free, PortSource::AutoSelected),
+            None => (configured_port, PortSource::Configured),
+        };
+        let port_in_use = taken && picked.is_none();
+        // No sweep frees a port like this, so the start fails right away
+        if not_permitted && picked.is_none() {
+            errors.push(port::not_permitted(bind_ip, configured_port));
+        }
+        match socket {
+            Some(ref socket) => checks.push(PreflightCheck::new(
+                "socket",
+                true,
+                format!("The gateway will listen on the socket {}", socket.display()),
+            )),
+            None => checks.push(PreflightCheck::new(
+                "port",
+                !port_in_use && (picked.is_some() || !not_permitted),
+                match picked {
+                    _ if availability == PortAvailability::Free => format!("Port {} is free", port),
+                    Some(free) if not_permitted => format!(
+                        "Port {} is not permitted; the gateway will use the free port {}",
+                        configured_port, free
+                    ),
+                    None if not_permitted => format!("Port {} is not permitted", port),
+                    Some(free) => format!(
+                        "Port {} is in use; the gateway will use the free port {}",
+                        configured_port, free
+                    ),
+                    None => format!(
+                        "Port {} is in use; leftover gateway processes will be stopped",
+                        port
+                    ),
+                },
+            )),
+        }
+        if let Some(free) = picked {
+            println!("[openclaw] Port {} is in use, using port {}", configured_port, free);
+            warnings.push(StartWarning::new(
+                "port_auto_selected",
+                format!(
+                    "Port {} was in use, so the gateway was started on port {} instead.",
+                    configured_port, free
+                ),
+            ));
+        }
+
+        // Check if runtime is installed (not needed when running a binary directly)
+        if self.direct_binary(&config).is_none() {
+            let runtime_installed = RuntimeManager::is_installed();
+            checks.push(PreflightCheck::new(
+                "runtime",
+                runtime_installed,
+                if runtime_installed {
+                    "Node.js runtime is installed"
+                } else {
+                    "Node.js runtime is not installed"
+                },
+            ));
+            if !runtime_installed {
+                errors.push(AppError::RuntimeNotInstalled);
+            }
+        }
+
+        checks.push(PreflightCheck::new(
+            "credentials",
+            config.anthropic_api_key.is_some(),
+            if config.anthropic_api_key.is_some() {
+                "API key is configured"
+            } else {
+                "No API key is configured"
+            },
+        ));
+        if config.anthropic_api_key.is_none() {
+            errors.push(config.missing_api_key_error());
+        }
+
+        let next_token = lock(&self.next_token)
+            .clone()
+            .filter(|kept| socket.is_some() || kept.port.is_none_or(|kept_port| kept_port == port))
+            .map(|kept| kept.token);
+        let token = match next_token.or_else(|| config.persisted_gateway_token()) {
+            Some(token) => token,
+            None => generate_token().map_err(|e| AppError::GatewayStartFailed {
+                detail: format!("Could not generate a gateway token: {}", e),
+            })?,
+        };
+        let listen_flag = match socket {
+            Some(_) => capabilities::SOCKET_FLAG,
+            None => capabilities::PORT_FLAG,
+        };
+        let listen_on = match socket {
+            Some(ref socket) => socket.display().to_string(),
+            None => port.to_string(),
+        };
+        let mut gateway_args = vec![
+            "gateway".to_string(),
+            listen_flag.to_string(),
+            listen_on,
+            capabilities::TOKEN_FLAG.to_string(),
+            token.clone(),
+        ];
+        // Left out for loopback, so gateways without it keep working
+        let passes_host = socket.is_none() && bind_ip != IpAddr::V4(Ipv4Addr::LOCALHOST);
+        if passes_host {
+            gateway_args.push(capabilities::HOST_FLAG.to_string());
+            gateway_args.push(bind_ip.to_string());
+        }
+
+        // Anyone on the network can connect, so only with a token
+        if socket.is_none() && connection::is_lan_exposed(bind_ip) {
+            let has_token = !token.trim().is_empty();
+            checks.push(PreflightCheck::new(
+                "lan",
+                has_token,
+                if has_token {
+                    format!("The gateway listens on {} and requires its token", bind_ip)
+                } else {
+                    format!("The gateway would listen on {} without a token", bind_ip)
+                },
+            ));
+            if has_token {
+                warnings.push(StartWarning::new(
+                    "lan_exposed",
+                    format!(
+                        "The gateway listens on {}, so other devices on the network can connect to it with its token.",
+                        bind_ip
+                    ),
+                ));
+            } else {
+                errors.push(AppError::LanTokenRequired);
+            }
+        }
+
+        // Unreadable files are refused here, as the gateway would only
+        // exit saying it couldn't load them
+        let tls = match config.tls {
+            Some(ref tls_config) => match tls::validate(tls_config) {
+                Ok(()) => {
+                    checks.push(PreflightCheck::new(
+                        "tls",
+                        true,
+                        format!("Serving TLS with {}", tls_config.cert_path.display()),
+                    ));
+                    gateway_args.extend(tls::args(tls_config));
+                    Some(tls_config.clone())
+                }
+                Err(e) => {
+                    checks.push(PreflightCheck::new("tls", false, e.to_string()));
+                    errors.push(e.into());
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let gateway_args_len = gateway_args.len();
+        let mut env = GatewayEnv::new();
+        let (program, uses_npx, executable, mut args) = match self.direct_binary(&config) {
+            Some((binary, ExecutableSource::Config)) if !resolve::is_executable(&binary) => {
+                errors.push(AppError::InvalidOpenclawPath {
+                    path: binary.display().to_string(),
+                });
+                let executable = ExecutableInfo {
+                    path: None,
+                    version: None,
+                    source: None,
+                };
+                (None, false, executable, Vec::new())
+            }
+            Some((binary, source)) => {
+                let path = binary.to_string_lossy().to_string();
+                match source {
+                    ExecutableSource::Config => println!("[openclaw] Using openclawPath {}", path),
+                    ExecutableSource::Bundled => println!("[openclaw] Using the bundled openclaw {}", path),
+                    _ => {}
+                }
+                let executable = ExecutableInfo {
+                    path: Some(path.clone()),
+                    version: None,
+                    source: Some(source),
+                };
+                (Some(path), false, executable, gateway_args)
+            }
+            None => {
+                if let Err(reason) = bundled::for_config(&config) {
+                    println!("[openclaw] Not using a bundled openclaw ({}), running it through npx", reason);
+                    // Asked for with preferGlobalOpenclaw, nothing to warn about
+                    if !config.prefer_global_openclaw {
+                        warnings.push(StartWarning::new(
+                            "npx_fallback",
+                            format!(
+                                "No bundled openclaw could be used ({}), so it is run through npx, which downloads it on first use.",
+                                reason
+                            ),
+                        ));
+                    }
+                }
+                // Get bundled node path (prioritize bundled over system)
+                let context = SearchContext::current(&config);
+                match find_node_and_npx(&context) {
+                    Some(node) => {
+                        println!(
+                            "[openclaw] Using node {} ({:?}) with {}",
+                            node.node, node.source, node.npx_cli
+                        );
+                        if node.source != ExecutableSource::Managed {
+                            warnings.push(StartWarning::new(
+                                "system_node",
+                                format!(
+                                    "The bundled Node.js runtime could not be used; falling back to the system installation at {}.",
+                                    node.node
+                                ),
+                            ));
+                        }
+
+                        // Get the bin directory for PATH
+                        let node_path = std::path::Path::new(&node.node);
+                        let bin_dir = node_path.parent().map(|p| p.to_string_lossy().to_string());
+
+                        // Build PATH with node bin directory first
+                        let existing_path = std::env::var("PATH").unwrap_or_default();
+                        let path_env = bin_dir
+                            .and_then(|bin| {
+                                let dirs = std::iter::once(PathBuf::from(bin))
+                                    .chain(std::env::split_paths(&existing_path));
+                                std::env::join_paths(dirs).ok()
+                            })
+                            .map(|path| path.to_string_lossy().to_string())
+                            .unwrap_or(existing_path);
+                        env.set("PATH", path_env, EnvSource::Runtime);
+
+                        let mut args = vec![
+                            node.npx_cli.clone(),
+                            "--yes".to_string(), // Auto-confirm package installation
+                            "openclaw".to_string(),
+                        ];
+                        args.extend(gateway_args);
+
+                        let executable = ExecutableInfo {
+                            path: Some(node.node.clone()),
+                            version: node.version(),
+                            source: Some(node.source),
+                        };
+                        (Some(node.node), true, executable, args)
+                    }
+                    None => {
+                        errors.push(node_not_found(&config, &context));
+                        let executable = ExecutableInfo {
+                            path: None,
+                            version: None,
+                            source: None,
+                        };
+                        (None, true, executable, Vec::new())
+                    }
+                }
+            }
+        };
+        checks.push(PreflightCheck::new(
+            "executable",
+            program.is_some(),
+            match executable.path {
+                Some(ref path) => format!(
+                    "Using {} ({})",
+                    path,
+                    executable.version.as_deref().unwrap_or("unknown version")
+                ),
+                None => "No Node.js binary found".to_string(),
+            },
+        ));
+
+        // Ask the installed gateway for its version and the flags it
+        // accepts, since an unknown flag makes it exit straight away.
+        // Without an executable there's nothing to ask, and that is an
+        // error already.
+        let startup_timeout = lock(&self.next_startup_timeout)
+            .or(self.startup_timeout)
+            .unwrap_or(Duration::from_secs(config.gateway_startup_timeout_secs.max(1)));
+        let (gateway_version, capabilities) = match program {
+            Some(ref program) => {
+                let cli_args = &args[..args.len() - gateway_args_len];
+                let version =
+                    openclaw_version::query(program, cli_args, &env.vars(), startup_timeout);
+                let capabilities =
+                    capabilities::probe(program, cli_args, &env.vars(), version, startup_timeout);
+                (version, capabilities)
+            }
+            None => (None, GatewayCapabilities::unknown()),
+        };
+        // A release too old for the app's flags exits without saying why
+        let mut version_error = None;
+        if program.is_some() {
+            let checked = openclaw_version::check(gateway_version, &config);
+            let detail = match (&checked, gateway_version) {
+                (Err(e), _) => e.to_string(),
+                (Ok(()), Some(version)) if version < MIN_OPENCLAW_VERSION => format!(
+                    "openclaw {} is older than {}, starting it as skipOpenclawVersionCheck is on",
+                    version, MIN_OPENCLAW_VERSION
+                ),
+                (Ok(()), Some(version)) => format!("openclaw {}", version),
+                (Ok(()), None) => "The openclaw version is unknown".to_string(),
+            };
+            checks.push(PreflightCheck::new("openclaw_version", checked.is_ok(), detail));
+            if let Err(e) = checked {
+                errors.push(e.clone().into());
+                version_error = Some(e);
+            }
+            let outdated = MIN_OPENCLAW_VERSION..RECOMMENDED_OPENCLAW_VERSION;
+            if let Some(version) = gateway_version.filter(|version| outdated.contains(version)) {
+                warnings.push(StartWarning::new(
+                    "openclaw_outdated",
+                    format!(
+                        "openclaw {} is older than {}, the release the app is tested with; some features may not work until it is updated.",
+                        version, RECOMMENDED_OPENCLAW_VERSION
+                    ),
+                ));
+            }
         }
-    }
-}
 
-pub struct SidecarManager {
-    pub state: Mutex<SidecarState>,
-}
+        let mut capability_error = None;
+        if program.is_some() {
+            let host_flag = passes_host.then_some(capabilities::HOST_FLAG);
+            let tls_flags = tls.is_some().then_some([tls::CERT_FLAG, tls::KEY_FLAG]);
+            capability_error = [listen_flag, capabilities::TOKEN_FLAG]
+                .into_iter()
+                .chain(host_flag)
+                .chain(tls_flags.into_iter().flatten())
+                .find_map(|flag| capabilities.require(flag, gateway_version).err());
+            checks.push(PreflightCheck::new(
+                "capabilities",
+                capability_error.is_none(),
+                capabilities.describe(),
+            ));
+            if let Some(ref e) = capability_error {
+                errors.push(e.clone().into());
+            }
 
-impl Default for SidecarManager {
-    fn default() -> Self {
-        Self {
-            state: Mutex::new(SidecarState::default()),
+            // Lets the gateway start without a config file of its own
+            if capabilities.allows(capabilities::ALLOW_UNCONFIGURED_FLAG) {
+                args.push(capabilities::ALLOW_UNCONFIGURED_FLAG.to_string());
+            } else {
+                warnings.push(StartWarning::new(
+                    "flag_unsupported",
+                    format!(
+                        "The installed gateway doesn't accept {}, so it is started without it.",
+                        capabilities::ALLOW_UNCONFIGURED_FLAG
+                    ),
+                ));
+            }
         }
-    }
-}
 
-impl SidecarManager {
-    /// Start the OpenClaw gateway using bundled Node.js runtime
-    ///
-    /// Uses the bundled Node.js runtime so users don't need to install
-    /// anything. On first launch, the runtime is automatically downloaded.
-    pub fn start(&self, _app: &AppHandle) -> Result<GatewayInfo, String> {
-        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        // A blocked tool the gateway can't disable stops the start
+        let mut blocked_tools = Vec::new();
+        let mut blocked_tools_error = None;
+        let blocked = blocked_tools::validate(&config.blocked_tools).and_then(|tools| {
+            if tools.is_empty() || program.is_none() {
+                return Ok(Vec::new());
+            }
+            if !capabilities.allows(blocked_tools::DENY_FLAG) {
+                return Err(BlockedToolsError::Unsupported {
+                    tool: tools[0].clone(),
+                    version: gateway_version.map_or("unknown version".to_string(), |v| v.to_string()),
+                });
+            }
+            let flags = blocked_tools::gateway_args(&tools, gateway_version)?;
+            checks.push(PreflightCheck::new(
+                "blocked_tools",
+                true,
+                format!(
+                    "Disabling {} (gateway {})",
+                    tools.join(", "),
+                    gateway_version.map(|v| v.to_string()).unwrap_or_default()
+                ),
+            ));
+            blocked_tools = tools;
+            Ok(flags)
+        });
+        match blocked {
+            Ok(flags) => args.extend(flags),
+            Err(e) => {
+                checks.push(PreflightCheck::new("blocked_tools", false, e.to_string()));
+                errors.push(e.clone().into());
+                blocked_tools_error = Some(e);
+            }
+        }
 
-        // Check if already running and healthy
-        if let Some(ref mut child) = state.child {
-            match child.try_wait() {
-                Ok(Some(_)) => {
-                    // Process exited, clear state
-                    println!("[openclaw] Previous gateway process has exited, clearing state");
-                    state.child = None;
-                    state.info = None;
-                }
-                Ok(None) => {
-                    // Still running, return existing info
-                    if let Some(ref info) = state.info {
-                        println!("[openclaw] Gateway already running, returning existing connection");
-                        return Ok(info.clone());
+        // Limits the gateway can't enforce fall back to the app, or to nothing
+        let limits = match limits::plan(&config, gateway_version, &capabilities) {
+            Ok(plan) => {
+                if let Some(limit) = plan.limits.sessions {
+                    if limit.enforced_by == LimitEnforcement::App {
+                        warnings.push(StartWarning::new(
+                            "session_limit_app_only",
+                            format!(
+                                "The gateway can't limit sessions itself; only sessions started \
+                                 from the app count toward the limit of {}.",
+                                limit.max
+                            ),
+                        ));
                     }
                 }
-                Err(_) => {
-                    state.child = None;
-                    state.info = None;
+                if let Some(limit) = plan.limits.requests {
+                    if limit.enforced_by == LimitEnforcement::NotEnforced {
+                        warnings.push(StartWarning::new(
+                            "request_limit_not_enforced",
+                            format!(
+                                "The gateway can't limit concurrent requests, so the limit of {} \
+                                 is not enforced.",
+                                limit.max
+                            ),
+                        ));
+                    }
                 }
+                args.extend(plan.args);
+                plan.limits
             }
-        }
+            Err(e) => {
+                checks.push(PreflightCheck::new("limits", false, e.clone()));
+                errors.push(AppError::GatewayStartFailed { detail: e });
+                ConcurrencyLimits::default()
+            }
+        };
 
-        // Check if port is already in use (another instance might be running)
-        let config = Config::load().map_err(|e| format!("Failed to load config: {}", e))?;
-        let port = config.gateway_port;
-        
-        if std::net::TcpStream::connect(format!("127.0.0.1:{}", port)).is_ok() {
-            println!("[openclaw] Port {} already in use, cleaning up...", port);
-            // Port is in use, kill orphaned processes
-            drop(state); // Release lock before cleanup
-            kill_orphaned_gateway_processes();
-            std::thread::sleep(std::time::Duration::from_millis(1500));
-            state = self.state.lock().map_err(|e| e.to_string())?;
-            
-            // Check again
-            if std::net::TcpStream::connect(format!("127.0.0.1:{}", port)).is_ok() {
-                return Err(format!(
-                    "Port {} is still in use. Another gateway may be running. \
-                     Please close all simplestclaw windows and try again.",
-                    port
+        // The gateway's own verbosity, refused before the spawn when unknown
+        match log_level::plan(&config, &capabilities) {
+            Ok(Some(plan)) => {
+                checks.push(PreflightCheck::new(
+                    "log_level",
+                    true,
+                    format!("Gateway log level {}", plan.level),
                 ));
+                env.extend(plan.env(), EnvSource::LogLevel);
+                args.extend(plan.args);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                checks.push(PreflightCheck::new("log_level", false, e.to_string()));
+                errors.push(e.into());
             }
         }
 
-        // Check if runtime is installed
-        if !RuntimeManager::is_installed() {
-            return Err(
-                "Node.js runtime not installed. Please wait for the download to complete, \
-                 or click 'Install Runtime' in Settings.".to_string()
-            );
+        if config.token_in_url {
+            warnings.push(StartWarning::new(
+                "token_in_url",
+                "The gateway URL includes the access token. URLs are easily copied into logs or \
+                 screenshots; only enable this for clients that can't send the token separately.",
+            ));
         }
 
-        // Get API key from config (already loaded above)
-        let api_key = config
-            .anthropic_api_key
-            .ok_or("No API key configured. Please enter your Anthropic API key in Settings.")?;
-
-        let token = generate_token();
-
-        // Get bundled node path (prioritize bundled over system)
-        let (node_cmd, npx_cli_path) = find_node_and_npx().ok_or(
-            "Node.js runtime not found. Please click 'Install Runtime' in Settings."
-        )?;
+        if cfg!(debug_assertions) {
+            warnings.push(StartWarning::new(
+                "dev_build",
+                "This is a development build of simplestclaw.",
+            ));
+        }
 
-        println!("[openclaw] Starting gateway via bundled Node.js...");
-        println!("[openclaw] Using node at: {}", node_cmd);
-        println!("[openclaw] Using npx-cli at: {}", npx_cli_path);
+        env.set(gateway_options::TOKEN_ENV, token.clone(), EnvSource::Credentials);
 
-        // Clear npx cache to prevent corrupted package issues
-        // The npx cache at ~/.npm/_npx can become corrupted and cause
-        // "Cannot find package" errors with dependencies like axios
-        clear_npx_cache();
-
-        // Get the bin directory for PATH
-        let node_path = std::path::Path::new(&node_cmd);
-        let bin_dir = node_path.parent().map(|p| p.to_string_lossy().to_string());
-        
-        // Build PATH with node bin directory first
-        let path_env = if let Some(ref bin) = bin_dir {
-            let existing_path = std::env::var("PATH").unwrap_or_default();
-            format!("{}:{}", bin, existing_path)
+        // Cap the V8 heap. A --max-old-space-size already in the user's
+        // NODE_OPTIONS wins, unless clean mode drops theirs anyway; the
+        // watchdog enforces the limit either way.
+        let existing = if config.clean_environment {
+            String::new()
         } else {
-            std::env::var("PATH").unwrap_or_default()
+            std::env::var("NODE_OPTIONS").unwrap_or_default()
         };
+        let mut node_options = existing.clone();
+        if let Some(limit_mb) = config.gateway_max_memory_mb {
+            if let Some(with_limit) = node_options_with_heap_limit(&node_options, limit_mb) {
+                node_options = with_limit;
+            }
+        }
+        // Only ever on request: it re-enables algorithms OpenSSL 3 retired
+        if config.openssl_legacy_provider {
+            warnings.push(StartWarning::new(
+                "openssl_legacy_provider",
+                "The gateway runs with --openssl-legacy-provider, which re-enables outdated OpenSSL algorithms.",
+            ));
+            if let Some(with_flag) =
+                node_options_with_flag(&node_options, spawn_failure::OPENSSL_LEGACY_PROVIDER_FLAG)
+            {
+                node_options = with_flag;
+            }
+        }
+        if node_options != existing {
+            env.set("NODE_OPTIONS", node_options, EnvSource::NodeOptions);
+        }
 
-        println!("[openclaw] PATH: {}", path_env.chars().take(200).collect::<String>());
-
-        // Spawn node directly with npx-cli.js to avoid shebang issues
-        // This ensures we use our bundled node, not whatever is in /usr/bin/env
-        let mut cmd = Command::new(&node_cmd);
-        cmd.args([
-                &npx_cli_path,
-                "--yes",  // Auto-confirm package installation
-                "openclaw",
-                "gateway",
-                "--port",
-                &port.to_string(),
-                "--token",
-                &token,
-                "--allow-unconfigured",
-            ])
-            .env("PATH", &path_env)
-            .env("OPENCLAW_GATEWAY_TOKEN", &token);
-        
-        // Set the appropriate API key environment variable based on provider
-        use crate::config::Provider;
-        match config.provider {
-            Provider::Anthropic => { cmd.env("ANTHROPIC_API_KEY", &api_key); }
-            Provider::Openai => { cmd.env("OPENAI_API_KEY", &api_key); }
-            Provider::Google => { cmd.env("GOOGLE_API_KEY", &api_key); }
-            Provider::Openrouter => { cmd.env("OPENROUTER_API_KEY", &api_key); }
-        }
-        
-        cmd
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-        
-        // On Unix, create a new process group so we can kill all children
-        #[cfg(unix)]
-        {
-            use std::os::unix::process::CommandExt;
-            cmd.process_group(0);
+        // Every provider's key that is set, under the variable it is read from
+        for provider in config::Provider::ALL {
+            if let Some(key) = config.provider_key(provider) {
+                env.set(provider.env_var(), key.to_string(), EnvSource::Credentials);
+            }
         }
-        
-        let mut child = cmd.spawn()
-            .map_err(|e| format!("Failed to start gateway: {}", e))?;
 
-        let info = GatewayInfo {
-            url: format!("ws://localhost:{}", port),
-            port,
-            token: token.clone(),
+        // Run in a dedicated workspace, reporting exactly what that restricts
+        let sandbox = match sandbox::plan(&config, AppPaths::resolve().as_ref()) {
+            Ok(Some(plan)) => {
+                checks.push(PreflightCheck::new(
+                    "sandbox",
+                    true,
+                    format!("Workspace {}", plan.workspace.display()),
+                ));
+                env.extend(plan.env(), EnvSource::Sandbox);
+                Some(plan)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                checks.push(PreflightCheck::new("sandbox", false, e.clone()));
+                errors.push(AppError::GatewayStartFailed { detail: e });
+                None
+            }
         };
 
-        println!("[openclaw] Gateway process started (PID: {:?}), waiting for it to be ready...", child.id());
+        // Sessions and memory go to a directory of our own, so they
+        // survive restarts
+        let state_dir = match gateway_state::state_dir(&config, AppPaths::resolve().as_ref()) {
+            Ok(dir) => {
+                checks.push(PreflightCheck::new(
+                    "gateway_state",
+                    true,
+                    format!("Gateway state in {}", dir.display()),
+                ));
+                let legacy = dirs::home_dir().map(|home| home.join(".openclaw"));
+                if !dir.exists() && legacy.as_ref().is_some_and(|legacy| legacy.is_dir()) {
+                    warnings.push(StartWarning::new(
+                        "gateway_state_moved",
+                        format!(
+                            "The gateway now keeps its sessions in {}. Sessions in ~/.openclaw are not carried over.",
+                            dir.display()
+                        ),
+                    ));
+                }
+                env.extend([gateway_state::env(&dir)], EnvSource::GatewayState);
+                Some(dir)
+            }
+            Err(e) => {
+                checks.push(PreflightCheck::new("gateway_state", false, e.clone()));
+                errors.push(AppError::GatewayStartFailed { detail: e });
+                None
+            }
+        };
 
-        // Wait for gateway to be ready (check if port is listening)
-        // Give it up to 30 seconds to start
-        let mut ready = false;
-        for attempt in 0..60 {
-            // Check if process is still running
-            match child.try_wait() {
-                Ok(Some(status)) => {
-                    // Try to read stderr for more info
-                    let mut stderr_output = String::new();
-                    if let Some(ref mut stderr) = child.stderr {
-                        use std::io::Read;
-                        let _ = stderr.read_to_string(&mut stderr_output);
-                    }
-                    
-                    let exit_code = status.code().unwrap_or(-1);
-                    println!("[openclaw] Process exited with code: {}", exit_code);
-                    println!("[openclaw] stderr: {}", stderr_output);
-                    
-                    // Exit code 127 = command not found
-                    if exit_code == 127 {
-                        return Err(format!(
-                            "Gateway failed: command not found (exit code 127). \
-                             Node path: {}. This usually means the Node.js binary couldn't execute. \
-                             stderr: {}",
-                            node_cmd, stderr_output
-                        ));
-                    }
-                    
-                    return Err(format!(
-                        "Gateway process exited unexpectedly with status: {}. \
-                         stderr: {}",
-                        status, stderr_output
+        // Without a sandbox the gateway still works in `workspaceDir`
+        let workspace_dir = match (sandbox.as_ref(), config.workspace_dir.as_ref()) {
+            (Some(sandbox), _) => Some(sandbox.workspace.clone()),
+            (None, Some(dir)) => match sandbox::check_workspace_dir(dir) {
+                Ok(()) => {
+                    checks.push(PreflightCheck::new(
+                        "workspace",
+                        true,
+                        format!("Workspace {}", dir.display()),
                     ));
+                    Some(dir.clone())
                 }
-                Ok(None) => {} // Still running, good
                 Err(e) => {
-                    return Err(format!("Failed to check gateway status: {}", e));
+                    checks.push(PreflightCheck::new("workspace", false, e.clone()));
+                    errors.push(AppError::GatewayStartFailed { detail: e });
+                    None
                 }
-            }
+            },
+            (None, None) => None,
+        };
 
-            // Try to connect to the port
-            if std::net::TcpStream::connect(format!("127.0.0.1:{}", port)).is_ok() {
-                ready = true;
-                println!("[openclaw] Gateway ready after {} attempts", attempt + 1);
-                break;
+        // Another user's gateway gets that user's home, and resolving it
+        // checks we can switch and the user can reach what it needs
+        let run_as = match config.run_as_user {
+            Some(ref name) => {
+                let planned = run_as::plan(
+                    name,
+                    program.as_deref().map(Path::new),
+                    workspace_dir.as_deref(),
+                    run_as::current_euid(),
+                )
+                .and_then(|plan| match state_dir {
+                    Some(ref dir) => run_as::check_reachable(&plan.user, dir).map(|()| plan),
+                    None => Ok(plan),
+                });
+                match planned {
+                    Ok(plan) => {
+                        // The workspace and state directory become the
+                        // user's, as far as that is safe
+                        let data_dir = AppPaths::resolve().map(|paths| paths.data_dir);
+                        let workspace = sandbox.as_ref().map(|sandbox| {
+                            let field = config.workspace_dir.is_some().then_some("workspaceDir");
+                            (&sandbox.workspace, field)
+                        });
+                        let state = state_dir.as_ref().map(|dir| {
+                            let configured = config.gateway_state_dir.is_some();
+                            (dir, configured.then_some("gatewayStateDir"))
+                        });
+                        let owned: Result<Vec<_>, AppError> = workspace
+                            .into_iter()
+                            .chain(state)
+                            .map(|(dir, field)| {
+                                run_as::hand_over_scope(dir, field, data_dir.as_deref())
+                            })
+                            .collect();
+                        match owned {
+                            Ok(hand_over) => {
+                                checks.push(PreflightCheck::new(
+                                    "run_as",
+                                    true,
+                                    format!(
+                                        "Running as {} (uid {})",
+                                        plan.user.name, plan.user.uid
+                                    ),
+                                ));
+                                env.extend(plan.user.env(), EnvSource::RunAs);
+                                Some(RunAsPlan { hand_over, ..plan })
+                            }
+                            Err(e) => {
+                                checks.push(PreflightCheck::new("run_as", false, e.to_string()));
+                                errors.push(e);
+                                None
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        checks.push(PreflightCheck::new("run_as", false, e.to_string()));
+                        errors.push(AppError::GatewayStartFailed {
+                            detail: e.to_string(),
+                        });
+                        None
+                    }
+                }
             }
+            None => None,
+        };
 
-            // Wait 500ms before retrying
-            std::thread::sleep(std::time::Duration::from_millis(500));
-        }
+        // Never the app's own working directory, often `/` from the dock
+        let workspace = workspace_dir
+            .clone()
+            .or_else(|| run_as.as_ref().and_then(|run_as| run_as.home_dir.clone()))
+            .or_else(dirs::home_dir);
 
-        if !ready {
-            // Kill the process if it never became ready
-            let _ = child.kill();
-            return Err(
-                "Gateway failed to start within 30 seconds. \
-                 Please check your internet connection and try again.".to_string()
+        // A full disk truncates state and logs mid-session, so check every
+        // volume the gateway writes to
+        let data_dir = AppPaths::resolve().map(|paths| paths.data_dir);
+        let dirs: Vec<&Path> = data_dir
+            .iter()
+            .chain(state_dir.iter())
+            .chain(workspace_dir.iter())
+            .map(PathBuf::as_path)
+            .collect();
+        let warn_below = config.min_free_disk_mb.saturating_mul(1024 * 1024);
+        let mut disk_full = None;
+        for volume in paths::volume_space(self.disk.as_ref(), &dirs) {
+            let Some(available) = volume.available_bytes else {
+                checks.push(PreflightCheck::new(
+                    "disk_space",
+                    true,
+                    format!("Free space at {} is unknown", volume.path.display()),
+                ));
+                continue;
+            };
+            let free = format!(
+                "{} free at {}",
+                config::format_bytes(available),
+                volume.path.display()
             );
+            if available < paths::HARD_MIN_FREE_BYTES {
+                let e = InsufficientSpace {
+                    path: volume.path,
+                    available,
+                    required: paths::HARD_MIN_FREE_BYTES,
+                };
+                checks.push(PreflightCheck::new("disk_space", false, free));
+                errors.push(e.clone().into());
+                disk_full.get_or_insert(e);
+                continue;
+            }
+            if available < warn_below {
+                warnings.push(StartWarning::new(
+                    "low_disk_space",
+                    format!(
+                        "Only {} is free at {}. The gateway may fail to save sessions and logs.",
+                        config::format_bytes(available),
+                        volume.path.display()
+                    ),
+                ));
+            }
+            checks.push(PreflightCheck::new("disk_space", true, free));
         }
 
-        state.child = Some(child);
-        state.info = Some(info.clone());
-
-        println!("[openclaw] Gateway running at {}", info.url);
-        Ok(info)
-    }
+        // The operator's SSH agent and identity are exactly what another
+        // user shouldn't get
+        if config.forward_dev_env && config.run_as_user.is_some() {
+            warnings.push(StartWarning::new(
+                "dev_env_not_forwarded",
+                "The developer environment is not forwarded to a gateway running as another user.",
+            ));
+        }
 
-    /// Stop the gateway
-    pub fn stop(&self) -> Result<(), String> {
-        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        // GUI launches and clean mode would otherwise leave the gateway
+        // in en-US and UTC
+        let locale = match locale::plan(&config) {
+            Ok(locale) => {
+                checks.push(PreflightCheck::new("locale", true, locale.describe()));
+                env.extend(locale.env(), EnvSource::Locale);
+                Some(locale)
+            }
+            Err(e) => {
+                checks.push(PreflightCheck::new("locale", false, e.to_string()));
+                errors.push(e.into());
+                None
+            }
+        };
 
-        if let Some(ref mut child) = state.child {
-            println!("[openclaw] Stopping gateway...");
-            
-            // Kill the process and all its children
-            kill_process_tree(child);
-            
-            println!("[openclaw] Gateway stopped");
+        // Pinned models, otherwise openclaw's own choice
+        match models::plan(&config) {
+            Ok(Some(models)) => {
+                checks.push(PreflightCheck::new("models", true, models.describe()));
+                env.extend(models.env(), EnvSource::Model);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                checks.push(PreflightCheck::new("models", false, e.to_string()));
+                errors.push(e.into());
+            }
         }
-        state.child = None;
-        state.info = None;
 
-        // Also kill any orphaned openclaw processes
-        kill_orphaned_gateway_processes();
+        // Enforced by the gateway where it can, estimated here either way
+        if let Some((name, value)) = spend::env(&config) {
+            checks.push(PreflightCheck::new(
+                "spend_limit",
+                true,
+                format!("Spend limit ${}", value),
+            ));
+            env.set(name, value, EnvSource::SpendLimit);
+        }
 
-        Ok(())
-    }
+        // Launched from the Dock, or in clean mode, the gateway would get
+        // no proxy and fail to reach the API behind one
+        match proxy::plan(&config) {
+            Ok(Some(proxy)) => {
+                checks.push(PreflightCheck::new("proxy", true, proxy.describe()));
+                env.extend(proxy.env(), EnvSource::Proxy);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                checks.push(PreflightCheck::new("proxy", false, e.to_string()));
+                errors.push(e.into());
+            }
+        }
 
-    /// Get gateway status
-    pub fn status(&self) -> GatewayStatus {
-        let mut state = match self.state.lock() {
-            Ok(s) => s,
-            Err(_) => return GatewayStatus { 
-                running: false, 
-                info: None,
-                error: Some("Lock error".to_string()),
-            },
-        };
+        // A company LLM proxy in place of Anthropic's API
+        match anthropic_endpoint::plan(&config) {
+            Ok(Some(endpoint)) => {
+                checks.push(PreflightCheck::new("anthropic_endpoint", true, endpoint.describe()));
+                let key = config.provider_key(config::Provider::Anthropic);
+                env.extend(endpoint.env(key), EnvSource::AnthropicEndpoint);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                checks.push(PreflightCheck::new("anthropic_endpoint", false, e.to_string()));
+                errors.push(e.into());
+            }
+        }
 
-        // Check if runtime is installed
-        if !RuntimeManager::is_installed() {
-            return GatewayStatus {
-                running: false,
-                info: None,
-                error: Some("runtime_not_installed".to_string()),
-            };
+        // SSH agent, git identity etc. from the login shell, re-read on
+        // every start because agent sockets change between logins
+        if config.forward_dev_env && config.run_as_user.is_none() {
+            let dev_env = shell_env::dev_env();
+            let names: Vec<&str> = dev_env.iter().map(|(name, _)| name.as_str()).collect();
+            checks.push(PreflightCheck::new(
+                "dev_env",
+                true,
+                if names.is_empty() {
+                    "No developer environment variables found to forward".to_string()
+                } else {
+                    format!("Forwarding {}", names.join(", "))
+                },
+            ));
+            env.extend(dev_env, EnvSource::DevEnv);
         }
 
-        if let Some(ref mut child) = state.child {
-            match child.try_wait() {
-                Ok(Some(_)) => {
-                    state.child = None;
-                    state.info = None;
-                }
-                Ok(None) => {}
-                Err(_) => {
-                    state.child = None;
-                    state.info = None;
+        // Last, so the gateway sees them after ours and they win over the
+        // variables set above
+        match gateway_options::validate(&config.gateway_extra_args, &config.gateway_extra_env) {
+            Ok(()) => {
+                if !config.gateway_extra_args.is_empty() || !config.gateway_extra_env.is_empty() {
+                    checks.push(PreflightCheck::new(
+                        "gateway_options",
+                        true,
+                        format!(
+                            "{} extra arguments, {} extra variables",
+                            config.gateway_extra_args.len(),
+                            config.gateway_extra_env.len()
+                        ),
+                    ));
                 }
+                args.extend(config.gateway_extra_args.iter().cloned());
+                env.extend(config.gateway_extra_env.clone(), EnvSource::ExtraEnv);
+            }
+            Err(e) => {
+                checks.push(PreflightCheck::new("gateway_options", false, e.to_string()));
+                errors.push(e.into());
             }
         }
 
-        GatewayStatus {
-            running: state.child.is_some(),
-            info: state.info.clone(),
-            error: None,
-        }
-    }
-}
-
-/// Find node and npx-cli.js paths - prioritizes bundled runtime over system
-/// 
-/// Returns (node_path, npx_cli_path) tuple
-/// 
-/// Order of preference:
-/// 1. Bundled Node.js runtime (for normal users)
-/// 2. System Node.js (for developers who prefer their own)
-fn find_node_and_npx() -> Option<(String, String)> {
-    // First, try the bundled runtime (preferred for normal users)
-    if let Some(node_path) = RuntimeManager::node_path() {
-        let node_str = node_path.to_string_lossy().to_string();
-        
-        // npx-cli.js is at ../lib/node_modules/npm/bin/npx-cli.js relative to node binary
-        let npx_cli = node_path
-            .parent()? // bin/
-            .parent()? // node-vX.X.X-platform/
-            .join("lib/node_modules/npm/bin/npx-cli.js");
-        
-        if npx_cli.exists() {
-            return Some((node_str, npx_cli.to_string_lossy().to_string()));
-        }
-    }
-
-    // Fall back to system Node.js for developers
-    find_system_node_and_npx()
-}
-
-/// Find system-installed node and npx (fallback for developers)
-fn find_system_node_and_npx() -> Option<(String, String)> {
-    // Try to find system node
-    let node_path = find_system_command("node")?;
-    let npx_path = find_system_command("npx")?;
-    
-    // For system npx, we can just run it directly since it's properly installed
-    // Return node path and npx path (we'll handle this specially)
-    Some((node_path, npx_path))
-}
-
-/// Find a system command by name
-fn find_system_command(cmd: &str) -> Option<String> {
-    #[cfg(target_os = "windows")]
-    {
-        let output = Command::new("where.exe")
-            .arg(cmd)
-            .output()
-            .ok()?;
-
-        if output.status.success() {
-            let path = String::from_utf8_lossy(&output.stdout)
-                .lines()
-                .next()
-                .unwrap_or("")
-                .trim()
-                .to_string();
-            if !path.is_empty() && std::path::Path::new(&path).exists() {
-                return Some(path);
-            }
-        }
-        None
-    }
-
-    #[cfg(not(target_os = "windows"))]
-    {
-        // On Unix, try which first
-        let output = Command::new("which")
-            .arg(cmd)
-            .output()
-            .ok()?;
-
-        if output.status.success() {
-            let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !path.is_empty() && std::path::Path::new(&path).exists() {
-                return Some(path);
-            }
-        }
-
-        // Try common Unix locations (GUI apps often don't have full PATH)
-        let home = std::env::var("HOME").ok()?;
-        let locations = [
-            // nvm (most common)
-            format!("{}/.nvm/current/bin/{}", home, cmd),
-            // volta
-            format!("{}/.volta/bin/{}", home, cmd),
-            // fnm
-            format!("{}/.local/share/fnm/aliases/default/bin/{}", home, cmd),
-            format!("{}/.fnm/aliases/default/bin/{}", home, cmd),
-            // asdf
-            format!("{}/.asdf/shims/{}", home, cmd),
-            // mise (formerly rtx)
-            format!("{}/.local/share/mise/shims/{}", home, cmd),
-            // System locations
-            format!("/usr/local/bin/{}", cmd),
-            format!("/opt/homebrew/bin/{}", cmd), // Homebrew on Apple Silicon
-            format!("/usr/bin/{}", cmd),
-        ];
+        // Only the allow-list and what was injected above
+        if config.clean_environment {
+            env = env.into_clean(environment::app_env());
+        }
+
+        Ok(StartPlan {
+            port,
+            port_source,
+            port_in_use,
+            bind_ip,
+            socket,
+            token,
+            program,
+            uses_npx,
+            executable,
+            args,
+            env,
+            clean_env: config.clean_environment,
+            low_priority: config.gateway_low_priority,
+            heartbeat_timeout: Duration::from_secs(config.gateway_heartbeat_timeout_secs),
+            startup_timeout,
+            stop_grace: Duration::from_secs(config.gateway_stop_grace_secs),
+            token_in_url: config.token_in_url,
+            self_test: config.self_test_on_start,
+            tls: tls.is_some(),
+            sandbox,
+            workspace,
+            state_dir,
+            locale,
+            blocked_tools,
+            blocked_tools_error,
+            gateway_version,
+            version_error,
+            capabilities,
+            capability_error,
+            limits,
+            run_as,
+            disk_full,
+            checks,
+            warnings,
+            errors,
+        })
+    }
+}
 
-        for loc in locations {
-            if std::path::Path::new(&loc).exists() {
-                return Some(loc);
+/// `args` with `token` replaced by `[redacted]`
+fn redact_token(args: &[String], token: &str) -> Vec<String> {
+    args.iter()
+        .map(|arg| {
+            if arg == token {
+                "[redacted]".to_string()
+            } else {
+                arg.clone()
             }
+        })
+        .collect()
+}
+
+/// `NODE_OPTIONS` with a heap limit appended, or `None` when `existing`
+/// already sets one
+pub fn node_options_with_heap_limit(existing: &str, limit_mb: u64) -> Option<String> {
+    if existing.contains("--max-old-space-size") {
+        return None;
+    }
+    node_options_with_flag(existing, &format!("--max-old-space-size={}", limit_mb))
+}
+
+/// `NODE_OPTIONS` with `flag` appended, or `None` when `existing` already
+/// has it
+pub fn node_options_with_flag(existing: &str, flag: &str) -> Option<String> {
+    if existing.split_whitespace().any(|option| option == flag) {
+        return None;
+    }
+    Some(if existing.trim().is_empty() {
+        flag.to_string()
+    } else {
+        format!("{} {}", existing.trim(), flag)
+    })
+}
+
+/// The node binary and npx entry point used to launch the gateway
+pub(crate) struct NodeCommand {
+    pub(crate) node: String,
+    pub(crate) npx_cli: String,
+    pub(crate) source: ExecutableSource,
+}
+
+impl NodeCommand {
+    /// Version of the node binary, without the leading `v`
+    fn version(&self) -> Option<String> {
+        if self.source == ExecutableSource::Managed {
+            return Some(crate::runtime::NODE_VERSION.to_string());
         }
 
-        // Last resort: try to find the newest Node version in nvm
-        let nvm_dir = format!("{}/.nvm/versions/node", home);
-        if let Ok(entries) = std::fs::read_dir(&nvm_dir) {
-            let mut versions: Vec<_> = entries
-                .filter_map(|e| e.ok())
-                .map(|e| e.path())
-                .collect();
-            versions.sort();
-            if let Some(newest) = versions.last() {
-                let cmd_path = newest.join("bin").join(cmd);
-                if cmd_path.exists() {
-                    return Some(cmd_path.to_string_lossy().to_string());
-                }
-            }
-        }
-
-        None
-    }
-}
-
-/// Kill a process and all its children
-fn kill_process_tree(child: &mut Child) {
-    let pid = child.id();
-    
-    #[cfg(unix)]
-    {
-        // On Unix, kill the process group
-        // First try SIGTERM for graceful shutdown
-        unsafe {
-            libc::kill(-(pid as i32), libc::SIGTERM);
-        }
-        
-        // Give it a moment to shut down
-        std::thread::sleep(std::time::Duration::from_millis(500));
-        
-        // Then SIGKILL to make sure it's dead
-        unsafe {
-            libc::kill(-(pid as i32), libc::SIGKILL);
-        }
-    }
-    
-    #[cfg(windows)]
-    {
-        // On Windows, use taskkill with /T to kill child processes
-        let _ = Command::new("taskkill")
-            .args(["/F", "/T", "/PID", &pid.to_string()])
-            .output();
-    }
-    
-    // Also kill via the standard method
-    let _ = child.kill();
-    let _ = child.wait();
-}
-
-/// Kill any orphaned openclaw gateway processes from previous runs
-pub fn kill_orphaned_gateway_processes() {
-    #[cfg(unix)]
-    {
-        // Find and kill processes listening on our gateway port
-        let output = Command::new("lsof")
-            .args(["-ti", ":18789"])
-            .output();
-        
-        if let Ok(output) = output {
-            if output.status.success() {
-                let pids = String::from_utf8_lossy(&output.stdout);
-                for pid in pids.lines() {
-                    if let Ok(pid_num) = pid.trim().parse::<i32>() {
-                        println!("[openclaw] Killing orphaned process: {}", pid_num);
-                        unsafe {
-                            libc::kill(pid_num, libc::SIGKILL);
-                        }
-                    }
-                }
-            }
+        let output = Command::new(&self.node).arg("--version").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let version = version.trim_start_matches('v');
+        if version.is_empty() {
+            None
+        } else {
+            Some(version.to_string())
         }
-        
-        // Also kill any openclaw-gateway processes
-        let _ = Command::new("pkill")
-            .args(["-9", "-f", "openclaw-gateway"])
-            .output();
-        
-        let _ = Command::new("pkill")
-            .args(["-9", "-f", "openclaw gateway"])
-            .output();
     }
-    
-    #[cfg(windows)]
-    {
-        // On Windows, kill by process name
-        let _ = Command::new("taskkill")
-            .args(["/F", "/IM", "openclaw*"])
-            .output();
+}
+
+/// The error for `find_node_and_npx` finding nothing with `context`
+fn node_not_found(config: &Config, context: &SearchContext) -> AppError {
+    match config.node_path {
+        Some(ref node) => AppError::GatewayNotFound {
+            detail: format!("nodePath {} is not an executable file.", node.display()),
+            searched: vec![node.display().to_string()],
+        },
+        None => AppError::GatewayNotFound {
+            detail: "Node.js was not found.".to_string(),
+            searched: context
+                .searched_dirs()
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect(),
+        },
     }
 }
 
+/// Find node and the npx to run the gateway with (see `resolve` for the
+/// order). The managed runtime is only used when its npx-cli.js is there.
+pub(crate) fn find_node_and_npx(context: &SearchContext) -> Option<NodeCommand> {
+    let node = resolve::resolve("node", context)?;
+    let layout = resolve::NodeLayout::CURRENT;
+    let npx_cli = if node.source == ExecutableSource::Managed {
+        // Only the runtime's own npm, so it never runs a global one
+        match resolve::find_npx_cli(&node.path, layout, &[], Path::exists) {
+            Some(npx_cli) => npx_cli,
+            None => return find_node_and_npx(&context.without_managed()),
+        }
+    } else {
+        match resolve::find_npx_cli(&node.path, layout, &context.npm_prefixes, Path::exists) {
+            Some(npx_cli) => npx_cli,
+            // The npx shim is a script node can run, except Windows' npx.cmd
+            None if layout == resolve::NodeLayout::Unix => {
+                resolve::resolve_beside("npx", &node, context)?.path
+            }
+            None => return None,
+        }
+    };
+    Some(NodeCommand {
+        node: node.path.to_string_lossy().to_string(),
+        npx_cli: npx_cli.to_string_lossy().to_string(),
+        source: node.source,
+    })
+}
+
 /// Clear the npx cache to prevent corrupted package issues.
 /// The npx cache at ~/.npm/_npx can become corrupted and cause
 /// "Cannot find package" errors (e.g., with axios dependency).
 /// This is a known npm issue - the cache doesn't auto-update.
-fn clear_npx_cache() {
-    if let Some(home) = dirs::home_dir() {
-        // Primary location: ~/.npm/_npx
-        let npx_cache = home.join(".npm").join("_npx");
+/// `cache` replaces the user's, for tests.
+fn clear_npx_cache(cache: Option<&Path>) -> std::io::Result<()> {
+    let home = dirs::home_dir();
+    // Primary location: ~/.npm/_npx
+    let npx_cache = cache
+        .map(Path::to_path_buf)
+        .or_else(|| home.as_ref().map(|home| home.join(".npm").join("_npx")));
+    if let Some(npx_cache) = npx_cache {
         if npx_cache.exists() {
             println!("[openclaw] Clearing npx cache at {:?}", npx_cache);
             if let Err(e) = std::fs::remove_dir_all(&npx_cache) {
                 println!("[openclaw] Warning: Failed to clear npx cache: {}", e);
-            } else {
-                println!("[openclaw] npx cache cleared successfully");
+                return Err(e);
             }
+            println!("[openclaw] npx cache cleared successfully");
         }
-        
-        // Secondary location: ~/.npx (older npm versions)
+    }
+
+    // Secondary location: ~/.npx (older npm versions)
+    if let Some(home) = home.filter(|_| cache.is_none()) {
         let npx_alt = home.join(".npx");
         if npx_alt.exists() {
             println!("[openclaw] Clearing alternate npx cache at {:?}", npx_alt);
             let _ = std::fs::remove_dir_all(&npx_alt);
         }
     }
+    Ok(())
 }
 
-/// Generate auth token
-fn generate_token() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let duration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
-    format!("sclw-{:x}{:x}", duration.as_secs(), duration.subsec_nanos())
+/// Random bytes in a gateway token
+pub const TOKEN_BYTES: usize = 32;
+
+/// A new gateway auth token: `sclw-` and `TOKEN_BYTES` from the OS's
+/// CSPRNG, hex encoded
+pub fn generate_token() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(format!("sclw-{}", hex::encode(bytes)))
 }
 
 // Tauri Commands
@@ -574,26 +4072,268 @@ fn generate_token() -> String {
 /// Start gateway in a background thread to avoid blocking the UI.
 /// The startup process involves waiting for the gateway to be ready,
 /// which can take several seconds.
+///
+/// On success the result is also emitted as a `gateway://started` event.
+/// Fails with one of the codes listed for `SidecarManager::start`, e.g.
+/// `port_in_use` with the `port`, or `gateway_not_found` with the
+/// directories `searched`, one per line.
+///
+/// `instance` starts another profile next to the active one instead, see
+/// `instances`; those starts emit nothing. `startup_timeout_secs` replaces
+/// `gatewayStartupTimeoutSecs` for this start, e.g. to give onboarding's
+/// first run longer, and must be in the same range.
 #[tauri::command]
-pub async fn start_gateway(app: AppHandle) -> Result<GatewayInfo, String> {
+pub async fn start_gateway(
+    app: AppHandle,
+    instance: Option<String>,
+    startup_timeout_secs: Option<u64>,
+) -> Result<StartResult, AppError> {
+    let startup_timeout = startup_timeout_secs
+        .map(|secs| config::check_startup_timeout("startupTimeoutSecs", secs))
+        .transpose()?
+        .map(Duration::from_secs);
     // Run the blocking startup in a separate thread
     // We clone the AppHandle which is cheap (Arc internally)
+    let is_default = instance
+        .as_deref()
+        .is_none_or(|id| id == instances::DEFAULT_INSTANCE);
+    let result = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || {
+            instances::with_manager(&app, instance.as_deref(), |manager| match startup_timeout {
+                Some(timeout) => manager.start_with_timeout(timeout),
+                None => manager.start(),
+            })
+        }
+    })
+    .await??;
+
+    if is_default {
+        let _ = app.emit("gateway://started", &result);
+    }
+    Ok(result)
+}
+
+/// Restart the gateway to pick up config changes, keeping its token; see
+/// `restart_keeping_token`. Emitted as `gateway://started` like a start.
+#[tauri::command]
+pub async fn restart_gateway(app: AppHandle) -> Result<StartResult, AppError> {
+    let result = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || {
+            let manager = app.state::<SidecarManager>();
+            manager.restart_keeping_token("Restart requested")
+        }
+    })
+    .await??;
+
+    let _ = app.emit("gateway://started", &result);
+    Ok(result)
+}
+
+/// Restart the gateway with a new token; see `regenerate_token`. Emitted
+/// as `gateway://started` like a start.
+#[tauri::command]
+pub async fn regenerate_token(app: AppHandle) -> Result<StartResult, AppError> {
+    let result = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || app.state::<SidecarManager>().regenerate_token()
+    })
+    .await??;
+
+    let _ = app.emit("gateway://started", &result);
+    Ok(result)
+}
+
+/// Preview what `start_gateway` would do without spawning anything:
+/// the binary, arguments, environment variable names, port and the
+/// outcome of every preflight check.
+#[tauri::command]
+pub async fn preview_gateway_start(app: AppHandle) -> Result<StartPreview, AppError> {
+    Ok(tokio::task::spawn_blocking(move || app.state::<SidecarManager>().preview()).await?)
+}
+
+/// The environment variables the app gave the most recent gateway, or
+/// would give the next one, with values redacted
+#[tauri::command]
+pub async fn get_effective_gateway_env(app: AppHandle) -> Result<Option<EffectiveEnv>, AppError> {
+    Ok(tokio::task::spawn_blocking(move || app.state::<SidecarManager>().effective_env()).await?)
+}
+
+/// Fails only with `gateway_stop_failed`. Off the main thread, since the
+/// gateway gets `gatewayStopGraceSecs` to exit and a start in progress is
+/// waited for. An `instance` that was never started is left as it is.
+#[tauri::command]
+pub async fn stop_gateway(
+    app: AppHandle,
+    instance: Option<String>,
+) -> Result<Option<StopKind>, AppError> {
+    let stopped = tokio::task::spawn_blocking(move || {
+        instances::with_existing(&app, instance.as_deref(), |manager| manager.stop())
+    })
+    .await??;
+    Ok(stopped.flatten())
+}
+
+/// `get_gateway_status` for one instance, or for all of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GatewayStatusReply {
+    One(Box<GatewayStatus>),
+    All(Vec<instances::InstanceStatus>),
+}
+
+/// The current status, for hydrating before `gateway://status` events and
+/// after missing some. During a start only `starting` is set.
+///
+/// With `instance` the status of that one; without it, every instance's,
+/// the default one first.
+#[tauri::command]
+pub async fn get_gateway_status(
+    app: AppHandle,
+    instance: Option<String>,
+) -> Result<GatewayStatusReply, AppError> {
+    tokio::task::spawn_blocking(move || match instance {
+        Some(id) => instances::with_manager(&app, Some(&id), |manager| Ok(manager.status()))
+            .map(|status| GatewayStatusReply::One(Box::new(status))),
+        None => Ok(GatewayStatusReply::All(instances::statuses(&app))),
+    })
+    .await?
+}
+
+/// The running gateway's info, without starting one: fails with
+/// `gateway_not_running` when `start_gateway` is needed
+#[tauri::command]
+pub async fn get_gateway_info(
+    app: AppHandle,
+    instance: Option<String>,
+) -> Result<GatewayInfo, AppError> {
+    tokio::task::spawn_blocking(move || {
+        instances::with_existing(&app, instance.as_deref(), |manager| {
+            Ok(manager.current_info())
+        })?
+        .flatten()
+        .ok_or(AppError::GatewayNotRunning)
+    })
+    .await?
+}
+
+/// Adopt the gateway `external_gateway_detected` reported, started by the
+/// user with `token`; see `SidecarManager::adopt`. Emitted as
+/// `gateway://started` like a start.
+#[tauri::command]
+pub async fn adopt_external_gateway(
+    app: AppHandle,
+    token: String,
+    instance: Option<String>,
+) -> Result<StartResult, AppError> {
+    let is_default = instance
+        .as_deref()
+        .is_none_or(|id| id == instances::DEFAULT_INSTANCE);
+    let result = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || {
+            instances::with_manager(&app, instance.as_deref(), |manager| manager.adopt(&token))
+        }
+    })
+    .await??;
+
+    if is_default {
+        let _ = app.emit("gateway://started", &result);
+    }
+    Ok(result)
+}
+
+/// Whether the `GatewayInfo` the frontend holds is the running gateway's.
+/// After a restart moved the gateway it isn't, and the reply carries the
+/// current one.
+#[tauri::command]
+pub async fn validate_gateway_info(
+    app: AppHandle,
+    info: GatewayInfo,
+    instance: Option<String>,
+) -> Result<ValidationResult, AppError> {
+    tokio::task::spawn_blocking(move || {
+        instances::with_existing(&app, instance.as_deref(), |manager| {
+            Ok(manager.validate_info(&info))
+        })
+        .map(Option::unwrap_or_default)
+    })
+    .await?
+}
+
+/// Suspend the gateway of `instance`, the default one without it, until
+/// `resume_gateway`. Fails with `gateway_not_running`,
+/// `gateway_pause_unsupported` on Windows and `gateway_pause_failed`.
+#[tauri::command]
+pub async fn pause_gateway(app: AppHandle, instance: Option<String>) -> Result<(), AppError> {
+    tokio::task::spawn_blocking(move || {
+        instances::with_existing(&app, instance.as_deref(), |manager| manager.pause())?
+            .ok_or(AppError::GatewayNotRunning)
+    })
+    .await?
+}
+
+/// Let the paused gateway of `instance` run again
+#[tauri::command]
+pub async fn resume_gateway(app: AppHandle, instance: Option<String>) -> Result<(), AppError> {
     tokio::task::spawn_blocking(move || {
+        instances::with_existing(&app, instance.as_deref(), |manager| manager.resume()).map(|_| ())
+    })
+    .await?
+}
+
+/// The last crash of the gateway with the end of its stderr, until a start
+/// succeeds
+#[tauri::command]
+pub fn get_last_crash(app: AppHandle) -> Option<CrashInfo> {
+    app.state::<SidecarManager>().last_crash()
+}
+
+/// How the most recent gateway was launched, see `LaunchDescriptor`
+#[tauri::command]
+pub fn get_launch_info(app: AppHandle) -> Option<LaunchDescriptor> {
+    app.state::<SidecarManager>().launch_info()
+}
+
+/// Search the captured gateway output, newest first
+#[tauri::command]
+pub async fn search_gateway_logs(
+    app: AppHandle,
+    pattern: String,
+    options: Option<SearchOptions>,
+) -> Result<SearchResult, AppError> {
+    let options = options.unwrap_or_default();
+    let result = tokio::task::spawn_blocking(move || {
         let manager = app.state::<SidecarManager>();
-        manager.start(&app)
+        log_search::search(manager.logs(), &pattern, &options)
     })
-    .await
-    .map_err(|e| format!("Task failed: {}", e))?
+    .await??;
+    Ok(result)
 }
 
+/// The newest `limit` captured lines of gateway output, or all of them,
+/// oldest first
 #[tauri::command]
-pub fn stop_gateway(app: AppHandle) -> Result<(), String> {
+pub fn get_gateway_logs(limit: Option<usize>, app: AppHandle) -> Vec<LogLine> {
     let manager = app.state::<SidecarManager>();
-    manager.stop()
+    manager.logs().recent(limit)
 }
 
+/// Delete the gateway log files, returning how many were removed. The
+/// lines in memory stay.
 #[tauri::command]
-pub fn get_gateway_status(app: AppHandle) -> GatewayStatus {
+pub fn clear_gateway_logs(app: AppHandle) -> Result<usize, AppError> {
     let manager = app.state::<SidecarManager>();
-    manager.status()
+    manager.logs().clear_files().map_err(|e| AppError::DeleteFailed {
+        path: gateway_logs::ACTIVE_FILE.to_string(),
+        detail: e.to_string(),
+    })
+}
+
+/// Called periodically by the frontend with its request counters, so a
+/// gateway that accepts connections but no longer answers can be detected
+#[tauri::command]
+pub fn report_gateway_activity(app: AppHandle, report: ActivityReport) {
+    app.state::<SidecarManager>().report_activity(report);
 }
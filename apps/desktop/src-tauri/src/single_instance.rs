@@ -0,0 +1,216 @@
+//! Single Instance
+//!
+//! Launching the app twice would start two gateways from the same config,
+//! the second failing on the port or picking another, and worse, the
+//! second launch would reap the first one's gateway as left by a crash. So
+//! before anything else `run` takes `app.lock.json` in the config dir -
+//! pid, executable and start time - created only if it doesn't exist yet.
+//! A launch that finds it held asks the running app to show its window over
+//! the control socket, or to open the `simplestclaw://` link it was launched
+//! with (see `deep_link`), and exits.
+//!
+//! The lock is released when the app shuts down. One left behind by a
+//! crash is reclaimed: it only counts as held while its pid is alive and
+//! that process's command line names the recorded executable, so a pid
+//! reused by something else doesn't keep the app from starting.
+//!
+//! Without a control socket, as on Windows, the running window can't be
+//! asked to come forward; the second launch still exits.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::control::{self, ControlRequest};
+use crate::gateway_logs;
+use crate::paths::AppPaths;
+use crate::pid_file;
+
+/// Name of the file in the config dir
+pub const FILE_NAME: &str = "app.lock.json";
+
+/// The app holding the lock
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockRecord {
+    pub pid: u32,
+    /// Path of the running executable
+    pub exe: String,
+    /// Unix timestamp (ms)
+    pub started_at: i64,
+}
+
+impl LockRecord {
+    /// A record for this process
+    pub fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            exe: std::env::current_exe()
+                .map(|exe| exe.display().to_string())
+                .unwrap_or_default(),
+            started_at: gateway_logs::now_ms(),
+        }
+    }
+}
+
+/// The lock held by this process, released on `release` or drop
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+    record: LockRecord,
+}
+
+impl InstanceLock {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self) -> &LockRecord {
+        &self.record
+    }
+
+    /// Remove the lock file if it is still ours. Safe to call again.
+    pub fn release(&self) {
+        if read(&self.path).is_some_and(|record| record.pid != self.record.pid) {
+            return;
+        }
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                eprintln!("[startup] Failed to remove {}: {}", self.path.display(), e);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// What `acquire` found
+#[derive(Debug)]
+pub enum Acquired {
+    /// This process now holds the lock
+    Locked(InstanceLock),
+    /// Another running app holds it
+    Running(LockRecord),
+}
+
+/// None when there is no lock file or it can't be read
+pub fn read(path: &Path) -> Option<LockRecord> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Take the lock at `path` for `record`, unless a running app holds it
+pub fn acquire(path: &Path, record: LockRecord) -> io::Result<Acquired> {
+    acquire_with(path, record, is_running)
+}
+
+/// `acquire`, asking `running` whether the app of an existing lock is
+pub fn acquire_with(
+    path: &Path,
+    record: LockRecord,
+    running: impl Fn(&LockRecord) -> bool,
+) -> io::Result<Acquired> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Twice at most: once more after removing a stale lock
+    for _ in 0..2 {
+        match create(path, &record) {
+            Ok(()) => {
+                return Ok(Acquired::Locked(InstanceLock {
+                    path: path.to_path_buf(),
+                    record,
+                }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+        match read(path) {
+            Some(holder) if holder.pid != record.pid && running(&holder) => {
+                return Ok(Acquired::Running(holder));
+            }
+            Some(holder) => println!(
+                "[startup] Reclaiming the lock of app {} that is no longer running",
+                holder.pid
+            ),
+            // Unreadable, e.g. cut short by a crash mid-write
+            None => println!("[startup] Reclaiming an unreadable {}", path.display()),
+        }
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} keeps being recreated", path.display()),
+    ))
+}
+
+/// Create `path` with `record`, failing if it exists
+fn create(path: &Path, record: &LockRecord) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(serde_json::to_string(record)?.as_bytes())
+}
+
+/// Whether `record.pid` is alive and still the recorded executable
+pub fn is_running(record: &LockRecord) -> bool {
+    let Some(name) = Path::new(&record.exe).file_name() else {
+        return false;
+    };
+    let name = name.to_string_lossy();
+    pid_file::command_line(record.pid).is_some_and(|line| line.contains(name.as_ref()))
+}
+
+/// Take the app's lock in the config dir, or the record of the running app
+/// holding it. Ok(None) when there is no lock to take, and the app runs
+/// unguarded rather than not at all.
+pub fn lock_app() -> Result<Option<InstanceLock>, LockRecord> {
+    let path = match AppPaths::resolve().map(|paths| paths.instance_lock_file()) {
+        Some(Ok(path)) => path,
+        Some(Err(e)) => {
+            eprintln!("[startup] Warning: no single-instance lock: {}", e);
+            return Ok(None);
+        }
+        None => return Ok(None),
+    };
+    match acquire(&path, LockRecord::current()) {
+        Ok(Acquired::Locked(lock)) => Ok(Some(lock)),
+        Ok(Acquired::Running(holder)) => Err(holder),
+        Err(e) => {
+            eprintln!("[startup] Warning: no single-instance lock: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Ask the running app `holder` to show its window, or to open `link`
+pub fn hand_over(holder: &LockRecord, link: Option<String>) {
+    println!(
+        "[startup] simplestclaw is already running as pid {}, showing its window",
+        holder.pid
+    );
+    let Some(paths) = AppPaths::resolve() else {
+        return;
+    };
+    let request = match link {
+        Some(url) => ControlRequest::OpenUrl { url },
+        None => ControlRequest::Focus,
+    };
+    if let Err(e) = control::send(&control::socket_path(&paths), &request) {
+        eprintln!("[startup] Could not reach the running app: {}", e);
+    }
+}
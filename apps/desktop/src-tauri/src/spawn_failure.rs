@@ -0,0 +1,392 @@
+//! Spawn Failure Classification
+//!
+//! On managed Macs and locked-down Windows machines, the gateway can fail
+//! to spawn (EPERM, access denied) or get killed right after spawning by
+//! Gatekeeper or endpoint protection. A generic "failed to start" message
+//! sends users down the wrong path, so failures are classified from the
+//! evidence we can collect (the io error, how and how quickly the process
+//! died, quarantine attributes, security software in the process table)
+//! and reported with targeted remediation.
+//!
+//! `classify` is pure logic over `SpawnEvidence`; `collect_evidence` does
+//! the platform-specific gathering.
+//!
+//! A gateway that does start can still die on something node prints to
+//! stderr, like OpenSSL 3 refusing a legacy algorithm. `classify_stderr`
+//! matches that output against `STDERR_SIGNATURES`.
+
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::process::ProcessExit;
+use crate::resources;
+
+/// A process dying this soon after spawn is treated as killed at launch
+pub const KILLED_AT_LAUNCH_WINDOW: Duration = Duration::from_secs(3);
+
+const SIGKILL: i32 = 9;
+
+/// Windows: "Operation did not complete successfully because the file
+/// contains a virus or potentially unwanted software"
+const ERROR_VIRUS_INFECTED: i32 = 225;
+/// Windows: "...the file contains a virus... it was deleted"
+const ERROR_VIRUS_DELETED: i32 = 226;
+/// Windows: "Access is denied"
+const ERROR_ACCESS_DENIED: i32 = 5;
+/// Windows: "This program is blocked by group policy"
+const ERROR_ACCESS_DISABLED_BY_POLICY: i32 = 1260;
+/// Windows NTSTATUS STATUS_ACCESS_DENIED as a process exit code
+const STATUS_ACCESS_DENIED: i32 = 0xC000_0022_u32 as i32;
+
+/// Process names of common endpoint protection products, lowercase, with
+/// the product name shown to the user
+const SECURITY_PRODUCTS: &[(&str, &str)] = &[
+    ("falcond", "CrowdStrike Falcon"),
+    ("falcon-sensor", "CrowdStrike Falcon"),
+    ("csfalconservice.exe", "CrowdStrike Falcon"),
+    ("sentinelagent", "SentinelOne"),
+    ("sentineld", "SentinelOne"),
+    ("sentinelagent.exe", "SentinelOne"),
+    ("cbdefense", "Carbon Black"),
+    ("repmgr.exe", "Carbon Black"),
+    ("sophosscand", "Sophos"),
+    ("sophosfs", "Sophos"),
+    ("sophoshealth.exe", "Sophos"),
+    ("jamfprotect", "Jamf Protect"),
+    ("masvc", "McAfee"),
+    ("mfetp.exe", "McAfee"),
+    ("ccsvchst.exe", "Symantec"),
+    ("ekrn", "ESET"),
+    ("ekrn.exe", "ESET"),
+    ("avp", "Kaspersky"),
+    ("avp.exe", "Kaspersky"),
+    ("msmpeng.exe", "Microsoft Defender"),
+    ("wdavdaemon", "Microsoft Defender"),
+];
+
+/// Node flag re-enabling the algorithms OpenSSL 3 moved to its legacy
+/// provider
+pub const OPENSSL_LEGACY_PROVIDER_FLAG: &str = "--openssl-legacy-provider";
+
+/// Lowercase stderr fragments and what they mean, checked in order. The
+/// legacy provider failures come first since their output can also contain
+/// the generic OpenSSL errors.
+pub const STDERR_SIGNATURES: &[(&str, SpawnFailure)] = &[
+    // Node built without the flag, or too old to know it
+    (
+        "--openssl-legacy-provider is not allowed in node_options",
+        SpawnFailure::OpensslLegacyProviderFailed,
+    ),
+    ("bad option: --openssl-legacy-provider", SpawnFailure::OpensslLegacyProviderFailed),
+    // The system OpenSSL has no legacy provider module to load
+    (
+        "dso support routines::could not load the shared library",
+        SpawnFailure::OpensslLegacyProviderFailed,
+    ),
+    (
+        "error:0308010c:digital envelope routines::unsupported",
+        SpawnFailure::OpensslUnsupported,
+    ),
+    ("err_ossl_evp_unsupported", SpawnFailure::OpensslUnsupported),
+    (
+        "digital envelope routines::initialization error",
+        SpawnFailure::OpensslUnsupported,
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    MacOs,
+    Windows,
+    Linux,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Linux
+        }
+    }
+}
+
+/// Everything known about a failed spawn
+#[derive(Debug, Clone)]
+pub struct SpawnEvidence {
+    pub platform: Platform,
+    /// Error returned by spawn itself
+    pub spawn_error: Option<(io::ErrorKind, Option<i32>)>,
+    /// How the process ended, if it was spawned and then died
+    pub exit: Option<ProcessExit>,
+    /// Time between spawn and exit
+    pub lifetime: Option<Duration>,
+    /// macOS: the binary carries a `com.apple.quarantine` attribute
+    pub quarantined: bool,
+    /// Endpoint protection products found in the process table
+    pub security_products: Vec<String>,
+}
+
+impl SpawnEvidence {
+    pub fn new(platform: Platform) -> Self {
+        Self {
+            platform,
+            spawn_error: None,
+            exit: None,
+            lifetime: None,
+            quarantined: false,
+            security_products: Vec::new(),
+        }
+    }
+}
+
+/// Recognized causes of a failed spawn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpawnFailure {
+    /// macOS Gatekeeper quarantine on the binary
+    Quarantined,
+    /// macOS refused with EPERM: SIP, Gatekeeper or an MDM policy
+    NotPermitted,
+    /// Windows Defender/AV flagged or removed the binary
+    FlaggedByAntivirus,
+    /// Blocked by Windows group policy / AppLocker
+    BlockedByPolicy,
+    /// Killed right after launch while security software is running
+    KilledBySecuritySoftware { product: String },
+    /// Killed right after launch with no specific culprit found
+    KilledAtLaunch,
+    /// Plain permission problem, e.g. missing execute bit
+    PermissionDenied,
+    /// OpenSSL 3 in node refused an algorithm the gateway uses
+    OpensslUnsupported,
+    /// Node could not enable the OpenSSL legacy provider it was asked for
+    OpensslLegacyProviderFailed,
+}
+
+impl SpawnFailure {
+    /// Stable identifier for metrics and error codes
+    pub fn code(&self) -> &'static str {
+        match self {
+            SpawnFailure::Quarantined => "spawn_quarantined",
+            SpawnFailure::NotPermitted => "spawn_not_permitted",
+            SpawnFailure::FlaggedByAntivirus => "spawn_flagged_by_antivirus",
+            SpawnFailure::BlockedByPolicy => "spawn_blocked_by_policy",
+            SpawnFailure::KilledBySecuritySoftware { .. } => "spawn_killed_by_security_software",
+            SpawnFailure::KilledAtLaunch => "spawn_killed_at_launch",
+            SpawnFailure::PermissionDenied => "spawn_permission_denied",
+            SpawnFailure::OpensslUnsupported => "node_openssl_unsupported",
+            SpawnFailure::OpensslLegacyProviderFailed => "node_openssl_legacy_provider_failed",
+        }
+    }
+
+    /// What happened, in user terms
+    pub fn message(&self) -> String {
+        match self {
+            SpawnFailure::Quarantined => {
+                "macOS blocked the gateway because the file is quarantined.".to_string()
+            }
+            SpawnFailure::NotPermitted => {
+                "macOS did not permit the gateway to run.".to_string()
+            }
+            SpawnFailure::FlaggedByAntivirus => {
+                "Antivirus software flagged the gateway and blocked it.".to_string()
+            }
+            SpawnFailure::BlockedByPolicy => {
+                "A Windows policy on this computer blocks the gateway.".to_string()
+            }
+            SpawnFailure::KilledBySecuritySoftware { product } => format!(
+                "The gateway was stopped right after it started, most likely by {}.",
+                product
+            ),
+            SpawnFailure::KilledAtLaunch => {
+                "The gateway was killed by the system right after it started.".to_string()
+            }
+            SpawnFailure::PermissionDenied => {
+                "The gateway could not be run because of file permissions.".to_string()
+            }
+            SpawnFailure::OpensslUnsupported => "The gateway crashed because the OpenSSL \
+                version in this Node.js no longer supports an algorithm it uses."
+                .to_string(),
+            SpawnFailure::OpensslLegacyProviderFailed => {
+                "Node.js could not enable the OpenSSL legacy provider.".to_string()
+            }
+        }
+    }
+
+    /// What the user can do about it
+    pub fn remediation(&self) -> String {
+        match self {
+            SpawnFailure::Quarantined => "Remove the quarantine attribute with \
+                `xattr -d com.apple.quarantine <path>`, or open the app once from Finder \
+                and confirm the prompt."
+                .to_string(),
+            SpawnFailure::NotPermitted => "Check System Settings > Privacy & Security for \
+                a blocked item. On a managed Mac, ask IT to allow simplestclaw and Node.js."
+                .to_string(),
+            SpawnFailure::FlaggedByAntivirus => "Restore the file from your antivirus \
+                quarantine and add an exclusion for the simplestclaw data folder, or ask IT \
+                to allow-list it."
+                .to_string(),
+            SpawnFailure::BlockedByPolicy => {
+                "Ask IT to allow-list simplestclaw and its Node.js runtime.".to_string()
+            }
+            SpawnFailure::KilledBySecuritySoftware { product } => format!(
+                "Ask IT to allow-list simplestclaw and its Node.js runtime in {}.",
+                product
+            ),
+            SpawnFailure::KilledAtLaunch => "If this is a managed computer, ask IT whether \
+                security software is blocking Node.js. Reinstalling the runtime from \
+                Settings can also help."
+                .to_string(),
+            SpawnFailure::PermissionDenied => "Reinstall the runtime from Settings so the \
+                files get the right permissions."
+                .to_string(),
+            SpawnFailure::OpensslUnsupported => "This happens with some distribution builds \
+                of Node.js linked against OpenSSL 3. Use the bundled runtime (Install Runtime \
+                in Settings) or an official Node.js LTS build. As a workaround, turn on \
+                opensslLegacyProvider to start the gateway with \
+                NODE_OPTIONS=--openssl-legacy-provider."
+                .to_string(),
+            SpawnFailure::OpensslLegacyProviderFailed => "This Node.js or its OpenSSL has no \
+                legacy provider. Turn off opensslLegacyProvider, and remove \
+                --openssl-legacy-provider from NODE_OPTIONS if you set it yourself."
+                .to_string(),
+        }
+    }
+
+    /// Full user-facing text
+    pub fn describe(&self) -> String {
+        format!("{} {}", self.message(), self.remediation())
+    }
+}
+
+/// Map collected evidence to a specific cause, if one is recognized
+pub fn classify(evidence: &SpawnEvidence) -> Option<SpawnFailure> {
+    let killed_by_security = || {
+        evidence
+            .security_products
+            .first()
+            .map(|product| SpawnFailure::KilledBySecuritySoftware {
+                product: product.clone(),
+            })
+    };
+
+    if let Some((kind, raw)) = evidence.spawn_error {
+        if evidence.quarantined {
+            return Some(SpawnFailure::Quarantined);
+        }
+        return match (evidence.platform, raw) {
+            (Platform::Windows, Some(ERROR_VIRUS_INFECTED | ERROR_VIRUS_DELETED)) => {
+                Some(SpawnFailure::FlaggedByAntivirus)
+            }
+            (Platform::Windows, Some(ERROR_ACCESS_DISABLED_BY_POLICY)) => {
+                Some(SpawnFailure::BlockedByPolicy)
+            }
+            (Platform::Windows, Some(ERROR_ACCESS_DENIED)) => {
+                killed_by_security().or(Some(SpawnFailure::PermissionDenied))
+            }
+            // EPERM, as opposed to the EACCES of a missing execute bit
+            (Platform::MacOs, Some(1)) => Some(SpawnFailure::NotPermitted),
+            _ if kind == io::ErrorKind::PermissionDenied => Some(SpawnFailure::PermissionDenied),
+            _ => None,
+        };
+    }
+
+    let exit = evidence.exit?;
+    let died_at_launch = evidence
+        .lifetime
+        .is_some_and(|lifetime| lifetime <= KILLED_AT_LAUNCH_WINDOW);
+    if !died_at_launch {
+        return None;
+    }
+
+    let blocked = match evidence.platform {
+        Platform::Windows => exit.code == Some(STATUS_ACCESS_DENIED),
+        _ => exit.signal == Some(SIGKILL),
+    };
+    if !blocked {
+        return None;
+    }
+    if evidence.quarantined {
+        return Some(SpawnFailure::Quarantined);
+    }
+    killed_by_security().or(Some(SpawnFailure::KilledAtLaunch))
+}
+
+/// The failure `stderr` from the gateway shows, if it matches one of
+/// `STDERR_SIGNATURES`
+pub fn classify_stderr(stderr: &str) -> Option<SpawnFailure> {
+    let stderr = stderr.to_lowercase();
+    STDERR_SIGNATURES
+        .iter()
+        .find(|(signature, _)| stderr.contains(signature))
+        .map(|(_, failure)| failure.clone())
+}
+
+/// Security products whose processes appear in `names`
+pub fn find_security_products<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for name in names {
+        // ps may report a full path
+        let base = name.rsplit(['/', '\\']).next().unwrap_or(name).to_lowercase();
+        for (process, product) in SECURITY_PRODUCTS {
+            if base == *process && !found.iter().any(|p| p == product) {
+                found.push(product.to_string());
+            }
+        }
+    }
+    found
+}
+
+/// Whether `path` has a macOS quarantine attribute
+fn is_quarantined(path: &Path) -> bool {
+    if !cfg!(target_os = "macos") {
+        return false;
+    }
+    std::process::Command::new("xattr")
+        .args(["-p", "com.apple.quarantine"])
+        .arg(path)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Gather evidence about a failed spawn of `program`
+pub fn collect_evidence(
+    program: &Path,
+    spawn_error: Option<&io::Error>,
+    exit: Option<ProcessExit>,
+    lifetime: Option<Duration>,
+) -> SpawnEvidence {
+    let mut evidence = SpawnEvidence::new(Platform::current());
+    evidence.spawn_error = spawn_error.map(|e| (e.kind(), e.raw_os_error()));
+    evidence.exit = exit;
+    evidence.lifetime = lifetime;
+    evidence.quarantined = is_quarantined(program);
+    if let Some(samples) = resources::sample_processes() {
+        evidence.security_products =
+            find_security_products(samples.iter().map(|sample| sample.name.as_str()));
+    }
+    evidence
+}
+
+/// Classify a failed spawn of `program`. Evidence that needs the process
+/// table or extra commands is only collected when the failure could be one
+/// of the recognized kinds.
+pub fn diagnose(
+    program: &Path,
+    spawn_error: Option<&io::Error>,
+    exit: Option<ProcessExit>,
+    lifetime: Option<Duration>,
+) -> Option<SpawnFailure> {
+    if spawn_error.is_none() {
+        let mut basic = SpawnEvidence::new(Platform::current());
+        basic.exit = exit;
+        basic.lifetime = lifetime;
+        classify(&basic)?;
+    }
+    classify(&collect_evidence(program, spawn_error, exit, lifetime))
+}
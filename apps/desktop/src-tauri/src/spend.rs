@@ -0,0 +1,295 @@
+//! Spend Limit
+//!
+//! An agent left running can use up API credit unattended. `spendLimitUsd`
+//! is passed to the gateway as `OPENCLAW_SPEND_LIMIT_USD`, for releases
+//! that enforce it; older ones ignore the variable. Either way the app
+//! keeps its own estimate from the gateway's output, the way
+//! `gateway_stats` counts requests: `parse_usage` picks token counts and a
+//! cost out of lines that report them, in any order and wording, and a
+//! line it can't read leaves the estimate as it was. Usage reported
+//! without a cost is priced at the `FALLBACK_*` rates, so the estimate is
+//! only as good as the gateway's reports.
+//!
+//! The estimate belongs to one gateway process, like the stats, and
+//! `get_usage_estimate` returns it. Once it reaches `warnAtFraction` of
+//! the limit `gateway://spend-warning` is emitted, and at the limit
+//! `gateway://spend-limit-reached`, each once per gateway. With
+//! `hardStopOnLimit` the gateway is then stopped as well.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::sidecar::SidecarManager;
+
+/// Variable the limit is passed in
+pub const SPEND_LIMIT_ENV: &str = "OPENCLAW_SPEND_LIMIT_USD";
+
+pub const SPEND_WARNING_EVENT: &str = "gateway://spend-warning";
+
+pub const SPEND_LIMIT_REACHED_EVENT: &str = "gateway://spend-limit-reached";
+
+/// Time between looks at the estimate
+pub const TICK: Duration = Duration::from_secs(1);
+
+/// Dollars per million tokens for usage reported without a cost, what a
+/// mid-range model charges
+pub const FALLBACK_INPUT_USD_PER_MTOK: f64 = 3.0;
+pub const FALLBACK_OUTPUT_USD_PER_MTOK: f64 = 15.0;
+
+/// Lowercase names the counts go by, longest first where one contains
+/// another
+const INPUT_KEYS: &[&str] = &[
+    "input_tokens",
+    "inputtokens",
+    "input tokens",
+    "prompt_tokens",
+    "prompttokens",
+];
+const OUTPUT_KEYS: &[&str] = &[
+    "output_tokens",
+    "outputtokens",
+    "output tokens",
+    "completion_tokens",
+    "completiontokens",
+];
+const COST_KEYS: &[&str] = &["cost_usd", "costusd", "cost"];
+
+/// `spendLimitUsd` must be a positive amount
+pub fn check_limit(usd: f64) -> Result<f64, String> {
+    if !usd.is_finite() || usd <= 0.0 {
+        return Err(format!("{} is not an amount of dollars above 0.", usd));
+    }
+    Ok(usd)
+}
+
+/// `warnAtFraction` must be a share of the limit
+pub fn check_warn_at(fraction: f64) -> Result<f64, String> {
+    if fraction.is_nan() || fraction <= 0.0 || fraction > 1.0 {
+        return Err(format!("{} is not a fraction above 0 and up to 1.", fraction));
+    }
+    Ok(fraction)
+}
+
+/// The limit to pass to the gateway, None without one
+pub fn env(config: &Config) -> Option<(String, String)> {
+    let usd = config.spend_limit_usd?;
+    Some((SPEND_LIMIT_ENV.to_string(), usd.to_string()))
+}
+
+/// What a line of gateway output reports about model usage
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageSample {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// What the gateway said it cost, when it did
+    pub cost_usd: Option<f64>,
+}
+
+impl UsageSample {
+    /// The reported cost, or the usage at the fallback rates
+    pub fn usd(&self) -> f64 {
+        self.cost_usd.unwrap_or_else(|| {
+            (self.input_tokens as f64 * FALLBACK_INPUT_USD_PER_MTOK
+                + self.output_tokens as f64 * FALLBACK_OUTPUT_USD_PER_MTOK)
+                / 1_000_000.0
+        })
+    }
+}
+
+/// The usage `line` reports, if any. It has to name a token count; a cost
+/// alone is too easily something else.
+pub fn parse_usage(line: &str) -> Option<UsageSample> {
+    let line = line.to_lowercase();
+    let input = number_after(&line, INPUT_KEYS);
+    let output = number_after(&line, OUTPUT_KEYS);
+    if input.is_none() && output.is_none() {
+        return None;
+    }
+    Some(UsageSample {
+        input_tokens: input.map_or(0, |tokens| tokens as u64),
+        output_tokens: output.map_or(0, |tokens| tokens as u64),
+        cost_usd: number_after(&line, COST_KEYS),
+    })
+}
+
+/// The number after the first of `keys` standing as a word in `line`, past
+/// `:`, `=`, quotes, spaces and `$`
+fn number_after(line: &str, keys: &[&str]) -> Option<f64> {
+    keys.iter().find_map(|key| {
+        line.match_indices(key).find_map(|(at, _)| {
+            let before = line[..at].chars().next_back();
+            if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                return None;
+            }
+            let rest = line[at + key.len()..]
+                .trim_start_matches(|c: char| matches!(c, ' ' | ':' | '=' | '"' | '\'' | '$'));
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            rest[..end]
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite() && *number >= 0.0)
+        })
+    })
+}
+
+/// Model usage of the running gateway, from its output
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEstimate {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// The costs the gateway reported, plus its other usage at the
+    /// fallback rates
+    pub estimated_usd: f64,
+    /// Lines that reported usage
+    pub reports: u64,
+    /// Unix timestamp (ms) of the last one
+    pub last_report_at: Option<i64>,
+}
+
+impl UsageEstimate {
+    /// Add the usage `line`, printed at `now` (Unix ms), reports
+    pub fn observe(&mut self, line: &str, now: i64) -> Option<UsageSample> {
+        let sample = parse_usage(line)?;
+        self.input_tokens += sample.input_tokens;
+        self.output_tokens += sample.output_tokens;
+        self.estimated_usd += sample.usd();
+        self.reports += 1;
+        self.last_report_at = Some(now);
+        Some(sample)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendAlert {
+    Warning,
+    LimitReached,
+}
+
+/// Decides when the spend of a gateway is worth an event, once for each
+/// alert and gateway
+#[derive(Debug, Clone, Default)]
+pub struct SpendAlarm {
+    generation: u64,
+    warned: bool,
+    reached: bool,
+}
+
+impl SpendAlarm {
+    /// Record that the gateway of `generation` spent `spent_usd`, with a
+    /// limit of `limit_usd` to warn at `warn_at` of
+    pub fn observe(
+        &mut self,
+        generation: u64,
+        spent_usd: f64,
+        limit_usd: f64,
+        warn_at: f64,
+    ) -> Option<SpendAlert> {
+        if generation != self.generation {
+            *self = Self {
+                generation,
+                ..Self::default()
+            };
+        }
+        if !self.reached && spent_usd >= limit_usd {
+            self.reached = true;
+            self.warned = true;
+            return Some(SpendAlert::LimitReached);
+        }
+        if !self.warned && spent_usd >= limit_usd * warn_at {
+            self.warned = true;
+            return Some(SpendAlert::Warning);
+        }
+        None
+    }
+}
+
+/// Payload of `gateway://spend-warning` and `gateway://spend-limit-reached`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendEvent {
+    /// Of the gateway, see `GatewayInfo.generation`
+    pub generation: u64,
+    pub spent_usd: f64,
+    pub limit_usd: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// The gateway was stopped, with `hardStopOnLimit`
+    pub stopped: bool,
+}
+
+/// Start the thread that watches the estimate against `spendLimitUsd`
+pub fn spawn_spend_monitor(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut alarm = SpendAlarm::default();
+        // config.json is only read again once there is new usage
+        let mut seen = (0, 0);
+        loop {
+            std::thread::sleep(TICK);
+            let Some(manager) = app.try_state::<SidecarManager>() else {
+                continue;
+            };
+            let usage = manager.usage();
+            let generation = manager.generation();
+            if usage.reports == 0 || (generation, usage.reports) == seen {
+                continue;
+            }
+            seen = (generation, usage.reports);
+            let Ok(config) = Config::load() else {
+                continue;
+            };
+            let Some(limit_usd) = config.spend_limit_usd else {
+                continue;
+            };
+            let alert = alarm.observe(
+                generation,
+                usage.estimated_usd,
+                limit_usd,
+                config.warn_at_fraction,
+            );
+            let Some(alert) = alert else {
+                continue;
+            };
+            let stopped = alert == SpendAlert::LimitReached
+                && config.hard_stop_on_limit
+                && match manager.stop() {
+                    Ok(_) => true,
+                    Err(e) => {
+                        eprintln!("[spend] Failed to stop the gateway at the limit: {}", e);
+                        false
+                    }
+                };
+            println!(
+                "[spend] Gateway spent about ${:.2} of ${:.2}{}",
+                usage.estimated_usd,
+                limit_usd,
+                if stopped { ", stopped it" } else { "" }
+            );
+            let event = SpendEvent {
+                generation,
+                spent_usd: usage.estimated_usd,
+                limit_usd,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                stopped,
+            };
+            let _ = match alert {
+                SpendAlert::Warning => app.emit(SPEND_WARNING_EVENT, &event),
+                SpendAlert::LimitReached => app.emit(SPEND_LIMIT_REACHED_EVENT, &event),
+            };
+        }
+    });
+}
+
+// Tauri commands
+/// Tokens and estimated dollars of the running gateway; all zero when
+/// none runs
+#[tauri::command]
+pub async fn get_usage_estimate(app: AppHandle) -> Result<UsageEstimate, AppError> {
+    Ok(tokio::task::spawn_blocking(move || app.state::<SidecarManager>().usage()).await?)
+}
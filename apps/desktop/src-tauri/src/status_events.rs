@@ -0,0 +1,313 @@
+//! Gateway Status Events
+//!
+//! A background thread snapshots `GatewayStatus` every `TICK` and emits the
+//! full snapshot as `gateway://status` whenever it changed. Changes within
+//! `COALESCE_WINDOW` of the first one are coalesced into a single event, so
+//! a start or stop that touches several fields produces one update.
+//!
+//! `uptimeSecs` is left out of the events, since it changes every second;
+//! `startedAt` gives the same.
+//!
+//! Every event carries a sequence number that increases by exactly one.
+//! A frontend that sees a gap (e.g. after a webview reload) re-hydrates
+//! with `get_gateway_status`.
+//!
+//! The same snapshots feed the `UnhealthyAlarm`, which emits
+//! `gateway://unhealthy` once the gateway has been unresponsive for
+//! `heartbeat::UNHEALTHY_AFTER`.
+//!
+//! Transitions don't wait for a snapshot: `SidecarManager` announces each
+//! one as it happens - starting, running, exited, restarting, stopped - as
+//! `gateway://status-changed`. A gateway exiting on its own is noticed by
+//! the next `status()`, which this thread calls every `TICK`. Every spawn
+//! attempt is a new generation, and an announcement is dropped when a newer
+//! generation began while it was being put together, so an old gateway
+//! can't be reported after its replacement.
+//!
+//! The generation a gateway was spawned in is also its
+//! `GatewayInfo.generation`, and `GatewayStatus.generation` is the latest.
+//! A frontend holding info from an older generation, e.g. from before a
+//! restart that moved the gateway to another port, asks
+//! `validate_gateway_info` for the current one instead of reconnecting to
+//! a port nothing listens on. A restart hands the token on only to a
+//! gateway on the same port, unless `persistGatewayToken` keeps it anyway.
+//!
+//! A start also reports what it is doing, since waiting for the port can
+//! take a while: `gateway://starting` as it enters each `StartPhase`, then
+//! `gateway://ready` or `gateway://start-failed` with the phase it failed
+//! in. The phases are entered where the start actually does that work, so
+//! a start that hangs shows where. All three carry the generation, so the
+//! events of overlapping restarts can be told apart. `autostart` emits
+//! `gateway://start-failed` too, with just the error, when the start on
+//! launch failed before its first phase.
+//!
+//! A start whose spawn failed or whose gateway didn't become ready for a
+//! reason that may pass is tried again, up to `startMaxRetries` times.
+//! Each retry is announced as `gateway://start-retrying` with the error of
+//! the failed try; the retry is a new generation with its own phases, and
+//! only the last try ends in `gateway://ready` or `gateway://start-failed`.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::heartbeat::{self, UnhealthyAlarm, UnhealthyEvent};
+use crate::sidecar::{GatewayStatus, SidecarManager};
+use crate::tray;
+
+/// Changes are collected this long before being emitted
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(250);
+
+/// Time between snapshots
+pub const TICK: Duration = Duration::from_millis(100);
+
+/// Payload of `gateway://status`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEvent<T> {
+    /// Starts at 1 and increases by one per event
+    pub seq: u64,
+    pub status: T,
+}
+
+/// Event every transition is announced with
+pub const STATUS_CHANGED_EVENT: &str = "gateway://status-changed";
+
+/// Transition announced by `gateway://status-changed`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GatewayPhase {
+    Starting,
+    Running,
+    /// Exited on its own
+    Exited,
+    /// Stopped to be started again
+    Restarting,
+    /// Stopped on request, or a start failed
+    Stopped,
+}
+
+/// Payload of `gateway://status-changed`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusChange {
+    /// The spawn attempt the transition belongs to, increasing by one per
+    /// attempt
+    pub generation: u64,
+    pub phase: GatewayPhase,
+    /// Exit code for `exited`, None when killed by a signal
+    pub exit_code: Option<i32>,
+    pub status: GatewayStatus,
+}
+
+/// Receives every `StatusChange` of a manager
+pub type StatusListener = Box<dyn Fn(&StatusChange) + Send + Sync>;
+
+pub const STARTING_EVENT: &str = "gateway://starting";
+
+pub const READY_EVENT: &str = "gateway://ready";
+
+pub const START_FAILED_EVENT: &str = "gateway://start-failed";
+
+pub const START_RETRYING_EVENT: &str = "gateway://start-retrying";
+
+/// What a start is doing, in order
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartPhase {
+    /// Checking the settings, finding the executable and asking it for its
+    /// version and flags
+    LocatingBinary,
+    /// Making sure the port is free, stopping leftover gateways on it
+    CheckingPort,
+    /// Setting up the workspace and environment and spawning
+    Spawning,
+    /// Waiting for the gateway to listen and pass its self-test
+    WaitingReady,
+}
+
+/// Payload of `gateway://starting`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartPhaseEvent {
+    /// As in `StatusChange`
+    pub generation: u64,
+    pub phase: StartPhase,
+    /// Unix timestamp (ms) the phase began
+    pub at: i64,
+    /// Since the start began
+    pub elapsed_ms: u64,
+}
+
+/// Payload of `gateway://ready`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartReadyEvent {
+    pub generation: u64,
+    pub at: i64,
+    pub elapsed_ms: u64,
+    /// None for a gateway on a socket
+    pub port: Option<u16>,
+}
+
+/// Payload of `gateway://start-failed`: the error as commands return it,
+/// with where and when the start failed
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartFailedEvent {
+    pub generation: u64,
+    /// None when it failed before the first phase
+    pub phase: Option<StartPhase>,
+    pub at: i64,
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub error: AppError,
+}
+
+/// Payload of `gateway://start-retrying`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StartRetryEvent {
+    /// Of the try that failed
+    pub generation: u64,
+    /// The try that failed, from 1
+    pub attempt: u32,
+    /// Tries there will be at most, the first included
+    pub max_attempts: u32,
+    pub retry_in_ms: u64,
+    pub at: i64,
+    pub error: AppError,
+}
+
+/// Progress of a start, see the module docs
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartProgress {
+    Phase(StartPhaseEvent),
+    Ready(StartReadyEvent),
+    Retrying(StartRetryEvent),
+    Failed(StartFailedEvent),
+}
+
+/// Receives the `StartProgress` of every start of a manager
+pub type ProgressListener = Box<dyn Fn(&StartProgress) + Send + Sync>;
+
+/// Decides which snapshots become events. Takes the time as a parameter
+/// so the ordering and coalescing can be tested without waiting.
+#[derive(Debug, Clone)]
+pub struct StatusCoalescer<T> {
+    window: Duration,
+    /// Last snapshot emitted (or the initial one, which isn't)
+    sent: Option<T>,
+    /// Newest unsent snapshot and when it first differed from `sent`
+    pending: Option<(T, Instant)>,
+    seq: u64,
+}
+
+impl<T: Clone + PartialEq> StatusCoalescer<T> {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            sent: None,
+            pending: None,
+            seq: 0,
+        }
+    }
+
+    /// Sequence number of the last event, 0 before the first
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Record the snapshot taken at `now`. The first snapshot is the
+    /// baseline a hydrating frontend already has, so it's never emitted.
+    pub fn observe(&mut self, status: T, now: Instant) {
+        let Some(ref sent) = self.sent else {
+            self.sent = Some(status);
+            return;
+        };
+        if *sent == status {
+            // Changed and changed back before anything was emitted
+            self.pending = None;
+            return;
+        }
+        let since = self.pending.as_ref().map_or(now, |(_, since)| *since);
+        self.pending = Some((status, since));
+    }
+
+    /// The event due at `now`, if any: the newest snapshot once the
+    /// window since the first unsent change has passed
+    pub fn poll(&mut self, now: Instant) -> Option<StatusEvent<T>> {
+        let due = self
+            .pending
+            .as_ref()
+            .is_some_and(|(_, since)| now.duration_since(*since) >= self.window);
+        if !due {
+            return None;
+        }
+        let (status, _) = self.pending.take()?;
+        self.seq += 1;
+        self.sent = Some(status.clone());
+        Some(StatusEvent {
+            seq: self.seq,
+            status,
+        })
+    }
+}
+
+/// Emit the manager's transitions as `gateway://status-changed` and the
+/// progress of its starts as `gateway://starting`, `gateway://ready`,
+/// `gateway://start-retrying` and `gateway://start-failed`
+pub fn install_change_emitter(app: &AppHandle) {
+    let manager = app.state::<SidecarManager>();
+    let handle = app.clone();
+    manager.set_status_listener(Box::new(move |change| {
+        let _ = handle.emit(STATUS_CHANGED_EVENT, change);
+    }));
+    let handle = app.clone();
+    manager.set_progress_listener(Box::new(move |progress| {
+        let _ = match progress {
+            StartProgress::Phase(event) => handle.emit(STARTING_EVENT, event),
+            StartProgress::Ready(event) => handle.emit(READY_EVENT, event),
+            StartProgress::Retrying(event) => handle.emit(START_RETRYING_EVENT, event),
+            StartProgress::Failed(event) => handle.emit(START_FAILED_EVENT, event),
+        };
+    }));
+}
+
+/// Start the thread that emits `gateway://status` and
+/// `gateway://unhealthy`
+pub fn spawn_status_publisher(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut coalescer = StatusCoalescer::<GatewayStatus>::new(COALESCE_WINDOW);
+        let mut alarm = UnhealthyAlarm::new(heartbeat::UNHEALTHY_AFTER);
+        loop {
+            if let Some(manager) = app.try_state::<SidecarManager>() {
+                let now = Instant::now();
+                // Left out, or every second would be a change
+                let status = GatewayStatus {
+                    uptime_secs: None,
+                    ..manager.status()
+                };
+                if let Some(unresponsive_for) = alarm.observe(status.health, now) {
+                    eprintln!(
+                        "[openclaw] Gateway unresponsive for {} seconds",
+                        unresponsive_for.as_secs()
+                    );
+                    let event = UnhealthyEvent {
+                        generation: status.generation,
+                        unresponsive_for_ms: unresponsive_for.as_millis() as u64,
+                        accepting_connections: status.accepting_connections != Some(false),
+                    };
+                    let _ = app.emit(heartbeat::UNHEALTHY_EVENT, &event);
+                }
+                coalescer.observe(status, now);
+                if let Some(event) = coalescer.poll(now) {
+                    tray::gateway_changed(&app, event.status.running, event.status.effective_port);
+                    let _ = app.emit("gateway://status", &event);
+                }
+            }
+            std::thread::sleep(TICK);
+        }
+    });
+}
@@ -0,0 +1,295 @@
+//! Gateway Status History
+//!
+//! A timeline of the gateway's status transitions - starts, failed starts,
+//! stops, restarts and their reasons, crashes and health changes - for
+//! working out what happened since the morning rather than only what the
+//! status is now. The newest `MAX_ENTRIES` are kept in memory and, when a
+//! file is configured, appended to it as JSON lines so they survive an app
+//! restart.
+//!
+//! Recording only pushes to memory and hands the entry to a writer thread,
+//! so it never waits on the disk. The file is pruned back to the newest
+//! `MAX_ENTRIES` once it holds twice that many lines, so appends stay cheap
+//! and the file small.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, MutexGuard};
+use tauri::State;
+
+use crate::gateway_logs;
+use crate::heartbeat::GatewayHealth;
+use crate::sidecar::SidecarManager;
+
+/// Entries kept in memory and on disk
+pub const MAX_ENTRIES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionKind {
+    Started,
+    StartFailed,
+    Stopped,
+    /// Stopped to be started again, e.g. by the watchdog
+    Restarting,
+    /// Exited on its own
+    Crashed,
+    /// Health changed while running
+    Health,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusTransition {
+    /// Unix timestamp (ms)
+    pub timestamp: i64,
+    pub kind: TransitionKind,
+    pub cause: String,
+    /// For a crash, when the gateway exited with one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+impl StatusTransition {
+    /// A transition happening now
+    pub fn now(kind: TransitionKind, cause: impl Into<String>) -> Self {
+        Self {
+            timestamp: gateway_logs::now_ms(),
+            kind,
+            cause: cause.into(),
+            exit_code: None,
+        }
+    }
+
+    pub fn with_exit_code(mut self, code: Option<i32>) -> Self {
+        self.exit_code = code;
+        self
+    }
+}
+
+/// The history file, appended to one line per entry
+pub struct HistoryFile {
+    path: PathBuf,
+    file: File,
+    capacity: usize,
+    /// Lines in the file, pruned at twice `capacity`
+    lines: usize,
+    /// The newest `capacity` entries, oldest first, kept for pruning
+    recent: VecDeque<StatusTransition>,
+}
+
+impl HistoryFile {
+    /// Open `path`, creating it if missing. Lines that don't parse are
+    /// dropped.
+    pub fn open(path: impl Into<PathBuf>, capacity: usize) -> io::Result<Self> {
+        let path = path.into();
+        let capacity = capacity.max(1);
+        let mut recent = VecDeque::new();
+        let mut lines = 0;
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                lines += 1;
+                if let Ok(entry) = serde_json::from_str(&line) {
+                    push_bounded(&mut recent, entry, capacity);
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut history = Self {
+            path,
+            file,
+            capacity,
+            lines,
+            recent,
+        };
+        if history.lines > history.recent.len() {
+            history.rewrite()?;
+        }
+        Ok(history)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The newest entries in the file, oldest first
+    pub fn entries(&self) -> Vec<StatusTransition> {
+        self.recent.iter().cloned().collect()
+    }
+
+    /// Append `entry`, first pruning the file to the newest entries when it
+    /// has grown to twice the capacity
+    pub fn append(&mut self, entry: StatusTransition) -> io::Result<()> {
+        let line = serde_json::to_string(&entry)?;
+        push_bounded(&mut self.recent, entry, self.capacity);
+        if self.lines >= self.capacity * 2 {
+            return self.rewrite();
+        }
+        writeln!(self.file, "{}", line)?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Replace the file with `recent`, through a temporary file so a crash
+    /// never leaves it half written
+    fn rewrite(&mut self) -> io::Result<()> {
+        let temp = self.path.with_extension("jsonl.tmp");
+        {
+            let mut out = io::BufWriter::new(File::create(&temp)?);
+            for entry in &self.recent {
+                writeln!(out, "{}", serde_json::to_string(entry)?)?;
+            }
+            out.flush()?;
+        }
+        fs::rename(&temp, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.lines = self.recent.len();
+        Ok(())
+    }
+}
+
+fn push_bounded(
+    entries: &mut VecDeque<StatusTransition>,
+    entry: StatusTransition,
+    capacity: usize,
+) {
+    entries.push_back(entry);
+    while entries.len() > capacity {
+        entries.pop_front();
+    }
+}
+
+enum WriterMessage {
+    Append(StatusTransition),
+    Flush(Sender<()>),
+}
+
+/// The transition history, shared by the manager and the commands
+pub struct StatusHistory {
+    entries: Mutex<VecDeque<StatusTransition>>,
+    capacity: usize,
+    /// Health last seen while running, to record only changes
+    health: Mutex<Option<GatewayHealth>>,
+    writer: Option<Sender<WriterMessage>>,
+}
+
+impl Default for StatusHistory {
+    /// In-memory only
+    fn default() -> Self {
+        Self::new(MAX_ENTRIES)
+    }
+}
+
+impl StatusHistory {
+    /// In-memory only, keeping `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+            health: Mutex::new(None),
+            writer: None,
+        }
+    }
+
+    /// Keep the history in `path` as well, starting from what it holds
+    pub fn open(path: impl Into<PathBuf>, capacity: usize) -> io::Result<Self> {
+        let mut file = HistoryFile::open(path, capacity)?;
+        let history = Self::new(capacity);
+        history.lock().extend(file.entries());
+
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for message in rx {
+                match message {
+                    WriterMessage::Append(entry) => {
+                        if let Err(e) = file.append(entry) {
+                            eprintln!("[history] Failed to write {}: {}", file.path().display(), e);
+                        }
+                    }
+                    WriterMessage::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            writer: Some(tx),
+            ..history
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<StatusTransition>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, entry: StatusTransition) {
+        if let Some(ref writer) = self.writer {
+            let _ = writer.send(WriterMessage::Append(entry.clone()));
+        }
+        push_bounded(&mut self.lock(), entry, self.capacity);
+    }
+
+    pub fn record(&self, entry: StatusTransition) {
+        // The next health change is measured from the new gateway
+        if let Ok(mut health) = self.health.lock() {
+            *health = None;
+        }
+        self.push(entry);
+    }
+
+    /// Record a change of `health` since the last call. Starts and stops
+    /// are recorded as their own transitions, so `Stopped` only resets.
+    pub fn observe_health(&self, health: GatewayHealth) {
+        let previous = match self.health.lock() {
+            Ok(mut last) => last.replace(health),
+            Err(_) => return,
+        };
+        match previous {
+            Some(previous)
+                if previous != health
+                    && previous != GatewayHealth::Stopped
+                    && health != GatewayHealth::Stopped =>
+            {
+                let cause = format!("{} to {}", previous.as_str(), health.as_str());
+                self.push(StatusTransition::now(TransitionKind::Health, cause));
+            }
+            _ => {}
+        }
+    }
+
+    /// Up to `limit` entries at or after `since` (unix ms), newest first
+    pub fn query(&self, limit: usize, since: Option<i64>) -> Vec<StatusTransition> {
+        self.lock()
+            .iter()
+            .rev()
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Wait until everything recorded so far is written
+    pub fn flush(&self) {
+        let Some(ref writer) = self.writer else {
+            return;
+        };
+        let (done, wait) = mpsc::channel();
+        if writer.send(WriterMessage::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
+
+/// The newest transitions, newest first, at or after `since` (unix ms)
+#[tauri::command]
+pub fn get_status_history(
+    limit: usize,
+    since: Option<i64>,
+    manager: State<'_, SidecarManager>,
+) -> Vec<StatusTransition> {
+    manager.history().query(limit, since)
+}
@@ -0,0 +1,268 @@
+//! Stored API Keys
+//!
+//! People who rotate between Anthropic keys (personal, team, trial) keep
+//! each one under a label in `apiKeys` instead of overwriting the one key.
+//! Every stored key has its own keychain account
+//! (`keychain::stored_key_account`), or sits in the file with
+//! `storeApiKeyInFile`; config.json keeps the label, when it was added and
+//! a hint like `sk-ant-…abcd` so `list_api_keys` never reads a secret.
+//!
+//! The key in use stays the usual `anthropicApiKey`, so starts and
+//! everything else reading it keep working. Making a stored key active
+//! copies it there and records its label in `activeApiKey`; a running
+//! gateway is restarted with it. `set_api_key` stores what it is given as
+//! `default` and makes that active, and a key set before there were stored
+//! keys becomes `default` the first time they are used. Switching profile
+//! brings the profile's own key, so no stored key is active after it.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use thiserror::Error;
+
+use crate::config::{self, redact_api_key, save_config, Config, NamedKey, Provider};
+use crate::error::AppError;
+use crate::keychain::{self, KeychainError, SecretStore};
+use crate::metrics::AppMetrics;
+use crate::sidecar::{SidecarManager, StartResult};
+
+/// Label `set_api_key` stores its key under
+pub const DEFAULT_LABEL: &str = "default";
+
+pub const MAX_LABEL_LEN: usize = 64;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum StoredKeyError {
+    #[error("There is no API key labelled {0}")]
+    Unknown(String),
+    #[error("{0:?} is not a key label; use letters, digits, - and _")]
+    InvalidLabel(String),
+    #[error("{0} is the API key in use")]
+    Active(String),
+    #[error(transparent)]
+    Keychain(#[from] KeychainError),
+}
+
+/// A stored key as `list_api_keys` returns it, without the key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeySummary {
+    pub label: String,
+    /// Unix timestamp (ms)
+    pub created_at: i64,
+    /// Like `sk-ant-…abcd`
+    pub hint: String,
+    pub active: bool,
+}
+
+/// Check `label` can label a key
+pub fn validate_label(label: &str) -> Result<(), StoredKeyError> {
+    let valid = !label.is_empty()
+        && label.len() <= MAX_LABEL_LEN
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if valid {
+        Ok(())
+    } else {
+        Err(StoredKeyError::InvalidLabel(label.to_string()))
+    }
+}
+
+/// The stored keys, by label
+pub fn list(config: &Config) -> Vec<KeySummary> {
+    let mut keys: Vec<KeySummary> = config
+        .api_keys
+        .iter()
+        .map(|named| KeySummary {
+            label: named.label.clone(),
+            created_at: named.created_at,
+            hint: named.hint.clone(),
+            active: config.active_api_key.as_deref() == Some(named.label.as_str()),
+        })
+        .collect();
+    keys.sort_by(|a, b| a.label.cmp(&b.label));
+    keys
+}
+
+/// Store the key in use as `default` when nothing is stored yet, as set
+/// before there were stored keys. Returns whether it did; save afterwards.
+pub fn adopt_current(config: &mut Config, store: &dyn SecretStore) -> Result<bool, StoredKeyError> {
+    if !config.api_keys.is_empty() {
+        return Ok(false);
+    }
+    let Some(key) = config.provider_key(Provider::Anthropic).map(str::to_string) else {
+        return Ok(false);
+    };
+    add(config, DEFAULT_LABEL, key, store)?;
+    config.active_api_key = Some(DEFAULT_LABEL.to_string());
+    Ok(true)
+}
+
+/// Store `key` as `label`, replacing the key of that label; when that one
+/// is in use, so is the new key. Save afterwards.
+pub fn add(
+    config: &mut Config,
+    label: &str,
+    key: String,
+    store: &dyn SecretStore,
+) -> Result<(), StoredKeyError> {
+    validate_label(label)?;
+    let mut named = NamedKey {
+        label: label.to_string(),
+        created_at: now_ms(),
+        hint: redact_api_key(&key),
+        key: None,
+        key_in_keychain: false,
+    };
+    if config.store_api_key_in_file {
+        named.key = Some(key.clone());
+    } else {
+        store.set(&keychain::stored_key_account(label), &key)?;
+        named.key_in_keychain = true;
+    }
+    if is_active(config, label) {
+        config.set_provider_key(Provider::Anthropic, Some(key), store)?;
+    }
+    match config.api_keys.iter_mut().find(|k| k.label == label) {
+        Some(existing) => *existing = named,
+        None => config.api_keys.push(named),
+    }
+    Ok(())
+}
+
+/// Remove stored key `label`, which can't be the one in use. Save
+/// afterwards.
+pub fn remove(config: &mut Config, label: &str, store: &dyn SecretStore) -> Result<(), StoredKeyError> {
+    if is_active(config, label) {
+        return Err(StoredKeyError::Active(label.to_string()));
+    }
+    let index = config
+        .api_keys
+        .iter()
+        .position(|k| k.label == label)
+        .ok_or_else(|| StoredKeyError::Unknown(label.to_string()))?;
+    if config.api_keys[index].key_in_keychain {
+        store.delete(&keychain::stored_key_account(label))?;
+    }
+    config.api_keys.remove(index);
+    Ok(())
+}
+
+/// Make stored key `label` the one in use. Save afterwards.
+pub fn activate(config: &mut Config, label: &str, store: &dyn SecretStore) -> Result<(), StoredKeyError> {
+    let named = config
+        .api_keys
+        .iter()
+        .find(|k| k.label == label)
+        .cloned()
+        .ok_or_else(|| StoredKeyError::Unknown(label.to_string()))?;
+    let key = if named.key_in_keychain {
+        store.get(&keychain::stored_key_account(label))?
+    } else {
+        named.key
+    };
+    // Gone from the keychain behind our back
+    let key = key.ok_or_else(|| StoredKeyError::Unknown(label.to_string()))?;
+    config.set_provider_key(Provider::Anthropic, Some(key), store)?;
+    config.active_api_key = Some(label.to_string());
+    Ok(())
+}
+
+fn is_active(config: &Config, label: &str) -> bool {
+    config.active_api_key.as_deref() == Some(label)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// `config` from disk, with a key set before there were stored keys
+/// adopted and saved
+fn load(metrics: &AppMetrics) -> Result<Config, AppError> {
+    let mut config = Config::load()?;
+    if adopt_current(&mut config, keychain::system())? {
+        save_config(&config, metrics)?;
+    }
+    Ok(config)
+}
+
+// Tauri commands
+#[tauri::command]
+pub fn list_api_keys(metrics: State<'_, Arc<AppMetrics>>) -> Result<Vec<KeySummary>, AppError> {
+    Ok(list(&load(&metrics)?))
+}
+
+/// Store `key`, trimmed, as `label`, replacing a key of that label
+#[tauri::command]
+pub fn add_api_key(
+    label: String,
+    key: String,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<Vec<KeySummary>, AppError> {
+    let key = key.trim().to_string();
+    if key.is_empty() {
+        return Err(AppError::ApiKeyMissing);
+    }
+    let mut config = load(&metrics)?;
+    add(&mut config, label.trim(), key, keychain::system())?;
+    save_config(&config, &metrics)?;
+    Ok(list(&config))
+}
+
+/// Remove stored key `label`; refused for the one in use
+#[tauri::command]
+pub fn remove_api_key(
+    label: String,
+    metrics: State<'_, Arc<AppMetrics>>,
+) -> Result<Vec<KeySummary>, AppError> {
+    let mut config = load(&metrics)?;
+    remove(&mut config, &label, keychain::system())?;
+    save_config(&config, &metrics)?;
+    Ok(list(&config))
+}
+
+/// Outcome of `set_active_api_key`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeySwitch {
+    pub keys: Vec<KeySummary>,
+    /// The gateway restarted with the key, when one was running
+    pub started: Option<StartResult>,
+}
+
+/// Use stored key `label`, restarting a running gateway with it. The
+/// restart is emitted as `gateway://started` like a start.
+#[tauri::command]
+pub async fn set_active_api_key(label: String, app: AppHandle) -> Result<KeySwitch, AppError> {
+    let (keys, started) = tokio::task::spawn_blocking({
+        let app = app.clone();
+        move || -> Result<_, AppError> {
+            let metrics = app.state::<Arc<AppMetrics>>();
+            let mut config = load(&metrics)?;
+            let changed = config.active_api_key.as_deref() != Some(label.as_str());
+            activate(&mut config, &label, keychain::system())?;
+            save_config(&config, &metrics)?;
+            println!("[keys] Using API key {}", label);
+
+            let manager = app.state::<SidecarManager>();
+            let started = if changed && manager.gateway_pid().is_some() {
+                Some(manager.restart(&format!("Switched to API key {}", label))?)
+            } else {
+                None
+            };
+            Ok((list(&config), started))
+        }
+    })
+    .await??;
+
+    if let Some(ref started) = started {
+        let _ = app.emit("gateway://started", started);
+    }
+    config::emit_changed(&app);
+    Ok(KeySwitch { keys, started })
+}
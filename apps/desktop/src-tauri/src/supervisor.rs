@@ -0,0 +1,234 @@
+//! Gateway Crash Recovery
+//!
+//! With `autoRestartGateway` on (the default) a gateway that exits on its
+//! own is started again after a backoff: `INITIAL_BACKOFF`, doubling with
+//! every consecutive crash up to `MAX_BACKOFF`. A gateway that was up for
+//! `STABLE_UPTIME` before crashing starts the count over. Every crash is
+//! emitted as `gateway://crashed`, and every restart as
+//! `gateway://restarted` like the watchdog's. The new gateway gets the
+//! crashed one's token when it gets the same port, so the frontend's
+//! stored credentials stay valid.
+//! A restart that fails is tried again after the next delay.
+//!
+//! What a crash left behind - exit code, signal and the last
+//! `CRASH_STDERR_LINES` of stderr - is kept as `CrashInfo` until the next
+//! successful start, so a gateway that crashed overnight can still be
+//! explained in the morning: in `GatewayStatus.lastCrash`, from
+//! `get_last_crash` and in the `gateway://crashed` event, which also says
+//! how the crashed gateway was launched.
+//!
+//! Only exits the manager notices on its own are crashes. A stop - from
+//! the frontend, the tray or the watchdog - forgets a pending crash and
+//! cancels a restart still waiting out its backoff.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::process::ProcessExit;
+use crate::sidecar::{LaunchDescriptor, SidecarManager};
+use crate::watchdog::RestartEvent;
+
+/// Event every crash is announced with
+pub const CRASHED_EVENT: &str = "gateway://crashed";
+
+/// Time between checks for a crash
+pub const TICK: Duration = Duration::from_millis(500);
+
+pub const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Uptime after which a crash no longer counts as consecutive
+pub const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+/// Delays between a crash and the restart. Fed the uptime of each crashed
+/// gateway so it can be tested without waiting.
+#[derive(Debug, Clone)]
+pub struct RestartBackoff {
+    initial: Duration,
+    max: Duration,
+    stable_after: Duration,
+    consecutive: u32,
+}
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        Self::new(INITIAL_BACKOFF, MAX_BACKOFF, STABLE_UPTIME)
+    }
+}
+
+impl RestartBackoff {
+    pub fn new(initial: Duration, max: Duration, stable_after: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            stable_after,
+            consecutive: 0,
+        }
+    }
+
+    /// Restarts since the last stable run or stop
+    pub fn consecutive(&self) -> u32 {
+        self.consecutive
+    }
+
+    /// How long to wait before restarting a gateway that crashed after
+    /// running for `uptime`, counting the restart
+    pub fn next_delay(&mut self, uptime: Option<Duration>) -> Duration {
+        if uptime.is_some_and(|uptime| uptime >= self.stable_after) {
+            self.consecutive = 0;
+        }
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.consecutive))
+            .min(self.max);
+        self.consecutive = self.consecutive.saturating_add(1);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.consecutive = 0;
+    }
+}
+
+/// Lines of stderr kept of a crash
+pub const CRASH_STDERR_LINES: usize = 100;
+
+/// The last crash as reported to the frontend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashInfo {
+    /// None when killed by a signal
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    /// The crashed gateway's last lines of stderr, oldest first
+    pub stderr_tail: Vec<String>,
+    /// Unix timestamp (ms) the crash was noticed
+    pub at: i64,
+}
+
+/// A gateway exit the manager noticed without being asked to stop it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crash {
+    pub exit: ProcessExit,
+    pub info: CrashInfo,
+    /// Token of the crashed gateway, for the restarted one
+    pub token: Option<String>,
+    /// Port of the crashed gateway; a restart elsewhere gets a new token
+    pub port: Option<u16>,
+    /// `GatewayInfo.generation` of the crashed gateway
+    pub generation: u64,
+    /// How long it had been ready
+    pub uptime: Option<Duration>,
+    /// `SidecarManager::stop_count` when it crashed
+    pub stops: u64,
+    /// How the crashed gateway was launched
+    pub launch: Option<LaunchDescriptor>,
+}
+
+/// Payload of `gateway://crashed`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashEvent {
+    /// Of the crashed gateway, see `GatewayInfo.generation`
+    pub generation: u64,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    /// Consecutive restarts, counting the one this crash gets
+    pub restarts: u32,
+    /// Delay before the restart, None when `autoRestartGateway` is off or
+    /// the gateway is already running again
+    pub restart_in_ms: Option<u64>,
+    /// As in `CrashInfo`
+    pub stderr_tail: Vec<String>,
+    pub at: i64,
+    /// How the crashed gateway was launched, see `get_launch_info`
+    pub launch: Option<LaunchDescriptor>,
+}
+
+/// What `supervise` saw happen
+#[derive(Debug, Clone)]
+pub enum Notice {
+    Crashed(CrashEvent),
+    Restarted(RestartEvent),
+}
+
+/// Handle a crash `manager` noticed, if any: report it to `notify` and,
+/// when auto restart is on, start the gateway again after the backoff
+/// unless it was stopped or started in the meantime
+pub fn supervise(manager: &SidecarManager, mut notify: impl FnMut(Notice)) {
+    // Notices an exit that nothing else has looked for yet
+    let _ = manager.status();
+    let Some(crash) = manager.take_crash() else {
+        return;
+    };
+
+    // Already started again, e.g. by the start that noticed the exit
+    let restart = manager.auto_restart_enabled() && manager.gateway_pid().is_none();
+    let delay = restart.then(|| manager.next_restart_delay(crash.uptime));
+    notify(Notice::Crashed(CrashEvent {
+        generation: crash.generation,
+        exit_code: crash.exit.code,
+        signal: crash.exit.signal,
+        restarts: manager.crash_restarts(),
+        restart_in_ms: delay.map(|delay| delay.as_millis() as u64),
+        stderr_tail: crash.info.stderr_tail.clone(),
+        at: crash.info.at,
+        launch: crash.launch.clone(),
+    }));
+    let Some(delay) = delay else {
+        return;
+    };
+
+    println!(
+        "[supervisor] Gateway crashed ({}), restarting in {} ms",
+        crash.exit,
+        delay.as_millis()
+    );
+    std::thread::sleep(delay);
+    if manager.stop_count() != crash.stops || manager.gateway_pid().is_some() {
+        println!("[supervisor] Gateway was stopped or started meanwhile, not restarting");
+        return;
+    }
+
+    manager.metrics().record_auto_restart();
+    let message = format!("Gateway exited unexpectedly ({}).", crash.exit);
+    let event = match manager.restart_after_crash(&crash) {
+        Ok(result) => RestartEvent {
+            reason: "crashed".to_string(),
+            message,
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => {
+            eprintln!("[supervisor] Failed to restart gateway: {}", e);
+            manager.retry_crash(Crash {
+                uptime: None,
+                ..crash
+            });
+            RestartEvent {
+                reason: "crashed".to_string(),
+                message,
+                result: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    notify(Notice::Restarted(event));
+}
+
+/// Start the supervisor thread
+pub fn spawn_supervisor(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(TICK);
+        let Some(manager) = app.try_state::<SidecarManager>() else {
+            continue;
+        };
+        supervise(&manager, |notice| {
+            let _ = match notice {
+                Notice::Crashed(event) => app.emit(CRASHED_EVENT, &event),
+                Notice::Restarted(event) => app.emit("gateway://restarted", &event),
+            };
+        });
+    });
+}
@@ -0,0 +1,99 @@
+//! Gateway TLS
+//!
+//! With `tls` set the gateway serves `wss://` with the configured
+//! certificate and key, passed as `--tls-cert` and `--tls-key`, so clients
+//! on other machines don't send the token in the clear. Both files must
+//! exist and be readable before the spawn; otherwise the start is refused
+//! with `tls_config_invalid` naming the file, instead of the gateway
+//! exiting with an error about it that doesn't say which.
+//!
+//! Readiness is still a plain TCP connect. The auth self-test doesn't
+//! speak TLS, so for a TLS gateway it is replaced by a handshake check
+//! that accepts any certificate, self-signed ones included: it proves the
+//! gateway could load its certificate, not that clients will trust it.
+
+use std::fs::File;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::config::TlsConfig;
+
+/// Passed with the certificate file
+pub const CERT_FLAG: &str = "--tls-cert";
+
+/// Passed with the private key file
+pub const KEY_FLAG: &str = "--tls-key";
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{} can't be used: {detail}", path.display())]
+pub struct TlsError {
+    pub path: PathBuf,
+    pub detail: String,
+}
+
+fn check_file(path: &Path, what: &str) -> Result<(), TlsError> {
+    let fail = |detail: String| TlsError {
+        path: path.to_path_buf(),
+        detail,
+    };
+    if !path.is_absolute() {
+        return Err(fail(format!("the {} path must be absolute", what)));
+    }
+    if !path.is_file() {
+        return Err(fail(format!("the {} file doesn't exist", what)));
+    }
+    File::open(path)
+        .map(drop)
+        .map_err(|e| fail(format!("the {} file can't be read: {}", what, e)))
+}
+
+/// Check the certificate and key of `tls` exist and can be read
+pub fn validate(tls: &TlsConfig) -> Result<(), TlsError> {
+    check_file(&tls.cert_path, "certificate")?;
+    check_file(&tls.key_path, "key")
+}
+
+/// The flags that start a gateway with `tls`
+pub fn args(tls: &TlsConfig) -> Vec<String> {
+    vec![
+        CERT_FLAG.to_string(),
+        tls.cert_path.display().to_string(),
+        KEY_FLAG.to_string(),
+        tls.key_path.display().to_string(),
+    ]
+}
+
+/// Complete a TLS handshake with the gateway at `address`, trusting any
+/// certificate. Any HTTP answer, an error status included, means the
+/// handshake succeeded. Returns how long it took, or why it failed in a
+/// lowercase phrase.
+pub fn handshake(address: SocketAddr, timeout: Duration) -> Result<Duration, String> {
+    let started = Instant::now();
+    let url = format!("https://{}/", address);
+    // A runtime of its own on a thread of its own, as starts run both on
+    // blocking tasks and on plain threads
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("the TLS handshake can't be tried: {}", e))?;
+        runtime.block_on(async {
+            let client = reqwest::Client::builder()
+                .danger_accept_invalid_certs(true)
+                .timeout(timeout)
+                .no_proxy()
+                .build()
+                .map_err(|e| format!("the TLS handshake can't be tried: {}", e))?;
+            client
+                .get(url)
+                .send()
+                .await
+                .map(|_| started.elapsed())
+                .map_err(|e| format!("the TLS handshake failed: {}", e))
+        })
+    })
+    .join()
+    .map_err(|_| "the TLS handshake check panicked".to_string())?
+}
@@ -0,0 +1,308 @@
+//! Tray Menu
+//!
+//! The tray menu shows the gateway state, with its port while it runs, and
+//! has Start/Stop/Restart items for it, so the app can host the gateway
+//! without a window. Below are checkboxes for the two settings people flip
+//! most, `autoStartGateway` and `keepRunningInBackground` (which hides the
+//! window to the tray on close), and Show/Quit items. Quit goes through
+//! the same `shutdown` as any other exit, which stops the gateway.
+//!
+//! Everything that changes the menu goes through `TrayMenu::apply`: the
+//! checkboxes themselves, config changes from the settings UI, and gateway
+//! starts and stops from the status publisher. It computes the new state
+//! with `reduce` and updates the native items under one lock, so updates
+//! from different threads can't interleave and leave stale checkmarks.
+//!
+//! The native updates run on the main thread, so `apply` must be called off
+//! it (except during setup); the main thread waiting for the lock while its
+//! holder waits for the main thread would deadlock. Menu events hand off to
+//! a thread for that reason.
+
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::config::{self, Config};
+use crate::sidecar::SidecarManager;
+
+pub const AUTO_START_ID: &str = "auto-start-gateway";
+pub const KEEP_RUNNING_ID: &str = "keep-running-in-background";
+pub const GATEWAY_ID: &str = "gateway-state";
+pub const START_ID: &str = "start-gateway";
+pub const STOP_ID: &str = "stop-gateway";
+pub const RESTART_ID: &str = "restart-gateway";
+pub const SHOW_ID: &str = "show-window";
+pub const QUIT_ID: &str = "quit";
+
+/// What the menu shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrayState {
+    pub auto_start: bool,
+    pub keep_running: bool,
+    pub gateway_running: bool,
+    /// The port the running gateway listens on
+    pub gateway_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayToggle {
+    AutoStart,
+    KeepRunning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayUpdate {
+    /// The settings as saved in config.json
+    Config {
+        auto_start: bool,
+        keep_running: bool,
+    },
+    /// A checkbox was clicked, before the change is saved
+    Toggled(TrayToggle),
+    /// The gateway started or stopped, or moved to another port
+    Gateway { running: bool, port: Option<u16> },
+}
+
+impl TrayUpdate {
+    pub fn from_config(config: &Config) -> Self {
+        TrayUpdate::Config {
+            auto_start: config.auto_start_gateway,
+            keep_running: config.keep_running_in_background,
+        }
+    }
+}
+
+/// The state after `update`. Each update only touches its own fields.
+pub fn reduce(state: TrayState, update: TrayUpdate) -> TrayState {
+    match update {
+        TrayUpdate::Config {
+            auto_start,
+            keep_running,
+        } => TrayState {
+            auto_start,
+            keep_running,
+            ..state
+        },
+        TrayUpdate::Toggled(TrayToggle::AutoStart) => TrayState {
+            auto_start: !state.auto_start,
+            ..state
+        },
+        TrayUpdate::Toggled(TrayToggle::KeepRunning) => TrayState {
+            keep_running: !state.keep_running,
+            ..state
+        },
+        TrayUpdate::Gateway { running, port } => TrayState {
+            gateway_running: running,
+            gateway_port: port.filter(|_| running),
+            ..state
+        },
+    }
+}
+
+pub fn gateway_label(running: bool, port: Option<u16>) -> String {
+    match (running, port) {
+        (true, Some(port)) => format!("Gateway: running on port {}", port),
+        (true, None) => "Gateway: running".to_string(),
+        (false, _) => "Gateway: stopped".to_string(),
+    }
+}
+
+struct TrayItems {
+    auto_start: CheckMenuItem<Wry>,
+    keep_running: CheckMenuItem<Wry>,
+    gateway: MenuItem<Wry>,
+    start: MenuItem<Wry>,
+    stop: MenuItem<Wry>,
+    restart: MenuItem<Wry>,
+}
+
+/// Menu state and the native items showing it. Managed by the app.
+#[derive(Default)]
+pub struct TrayMenu {
+    inner: Mutex<(TrayState, Option<TrayItems>)>,
+}
+
+impl TrayMenu {
+    /// Apply `update` and bring the native items in line. Returns the new
+    /// state.
+    pub fn apply(&self, update: TrayUpdate) -> TrayState {
+        let Ok(mut inner) = self.inner.lock() else {
+            return TrayState::default();
+        };
+        let (state, items) = &mut *inner;
+        let next = reduce(*state, update);
+        if let Some(items) = items {
+            // The native checkbox flips itself on click, so always set it
+            let _ = items.auto_start.set_checked(next.auto_start);
+            let _ = items.keep_running.set_checked(next.keep_running);
+            if (next.gateway_running, next.gateway_port)
+                != (state.gateway_running, state.gateway_port)
+            {
+                let _ = items
+                    .gateway
+                    .set_text(gateway_label(next.gateway_running, next.gateway_port));
+                let _ = items.start.set_enabled(!next.gateway_running);
+                let _ = items.stop.set_enabled(next.gateway_running);
+                let _ = items.restart.set_enabled(next.gateway_running);
+            }
+        }
+        *state = next;
+        next
+    }
+
+    pub fn state(&self) -> TrayState {
+        self.inner.lock().map(|inner| inner.0).unwrap_or_default()
+    }
+}
+
+/// Create the tray icon and menu, in the state from config.json
+pub fn create_tray(app: &AppHandle) -> tauri::Result<()> {
+    let config = Config::load().unwrap_or_default();
+    let auto_start = CheckMenuItem::with_id(
+        app,
+        AUTO_START_ID,
+        "Start gateway automatically",
+        true,
+        config.auto_start_gateway,
+        None::<&str>,
+    )?;
+    let keep_running = CheckMenuItem::with_id(
+        app,
+        KEEP_RUNNING_ID,
+        "Keep running when the window is closed",
+        true,
+        config.keep_running_in_background,
+        None::<&str>,
+    )?;
+    let gateway = MenuItem::with_id(
+        app,
+        GATEWAY_ID,
+        gateway_label(false, None),
+        false,
+        None::<&str>,
+    )?;
+    let start = MenuItem::with_id(app, START_ID, "Start gateway", true, None::<&str>)?;
+    let stop = MenuItem::with_id(app, STOP_ID, "Stop gateway", false, None::<&str>)?;
+    let restart = MenuItem::with_id(app, RESTART_ID, "Restart", false, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &gateway,
+            &start,
+            &stop,
+            &restart,
+            &PredefinedMenuItem::separator(app)?,
+            &auto_start,
+            &keep_running,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, SHOW_ID, "Show SimplestClaw", true, None::<&str>)?,
+            &MenuItem::with_id(app, QUIT_ID, "Quit", true, None::<&str>)?,
+        ],
+    )?;
+
+    let tray = app.state::<TrayMenu>();
+    if let Ok(mut inner) = tray.inner.lock() {
+        inner.1 = Some(TrayItems {
+            auto_start,
+            keep_running,
+            gateway,
+            start,
+            stop,
+            restart,
+        });
+    }
+    tray.apply(TrayUpdate::from_config(&config));
+
+    let mut builder = TrayIconBuilder::with_id("main")
+        .tooltip("SimplestClaw")
+        .menu(&menu)
+        .on_menu_event(|app, event| handle_menu_event(app, event.id.as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Bring the main window forward, showing it if it was hidden
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn handle_menu_event(app: &AppHandle, id: &str) {
+    let toggle = match id {
+        AUTO_START_ID => TrayToggle::AutoStart,
+        KEEP_RUNNING_ID => TrayToggle::KeepRunning,
+        SHOW_ID => {
+            show_main_window(app);
+            return;
+        }
+        START_ID | STOP_ID | RESTART_ID => {
+            // Starts block until the gateway is up, see the module docs
+            let app = app.clone();
+            let id = id.to_string();
+            std::thread::spawn(move || control_gateway(&app, &id));
+            return;
+        }
+        QUIT_ID => {
+            // Exiting runs `shutdown`, which stops the gateway
+            app.exit(0);
+            return;
+        }
+        _ => return,
+    };
+
+    // Menu events arrive on the main thread, see the module docs
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = app.state::<TrayMenu>().apply(TrayUpdate::Toggled(toggle));
+        let result = config::update_tray_setting(&app, |config| match toggle {
+            TrayToggle::AutoStart => config.auto_start_gateway = state.auto_start,
+            TrayToggle::KeepRunning => config.keep_running_in_background = state.keep_running,
+        });
+        if let Err(e) = result {
+            eprintln!("[tray] Failed to save setting: {}", e);
+        }
+    });
+}
+
+/// Start, stop or restart the default gateway for the menu item `id`.
+/// Starts are announced like `start_gateway`'s; the menu itself follows
+/// from the status publisher.
+fn control_gateway(app: &AppHandle, id: &str) {
+    let manager = app.state::<SidecarManager>();
+    let result = match id {
+        START_ID => manager.start().map(Some),
+        RESTART_ID => manager
+            .restart_keeping_token("Restart from the tray")
+            .map(Some),
+        _ => manager.stop().map(|_| None),
+    };
+    match result {
+        Ok(Some(started)) => {
+            let _ = app.emit("gateway://started", &started);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("[tray] Failed to {}: {}", id.replace('-', " "), e),
+    }
+}
+
+/// Re-read config.json into the menu, after anything changed it
+pub fn config_changed(app: &AppHandle) {
+    let (Some(tray), Ok(config)) = (app.try_state::<TrayMenu>(), Config::load()) else {
+        return;
+    };
+    tray.apply(TrayUpdate::from_config(&config));
+}
+
+/// Show a gateway start or stop in the menu
+pub fn gateway_changed(app: &AppHandle, running: bool, port: Option<u16>) {
+    if let Some(tray) = app.try_state::<TrayMenu>() {
+        tray.apply(TrayUpdate::Gateway { running, port });
+    }
+}
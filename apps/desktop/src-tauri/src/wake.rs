@@ -0,0 +1,149 @@
+//! Sleep and Wake
+//!
+//! After the machine sleeps the gateway process is still there, so
+//! `try_wait` has nothing to report, but its upstream connections are gone
+//! and it can stay wedged until restarted.
+//!
+//! Tauri reports no power events, so a thread notices wakes from the
+//! clocks instead: on macOS and Linux the monotonic clock stops while the
+//! machine sleeps and the wall clock doesn't, and where both keep running
+//! the thread's next tick simply comes late. Either way a tick that missed
+//! more than `WAKE_GAP` was a sleep. A wall clock set forward looks the
+//! same, which only costs a check.
+//!
+//! After a wake, with `restartOnWake` (the default), the running gateway is
+//! asked for `health` over its protocol (`check_responsive`). One that
+//! doesn't answer within `WAKE_PROBE_TIMEOUT` is restarted and
+//! `gateway://restarted-after-wake` is emitted. Laptops can report several
+//! wakes in a row, so wakes within `WAKE_DEBOUNCE` of the last one are
+//! ignored. A start or restart already in progress is left to finish, and
+//! a gateway replaced during the check isn't restarted again.
+
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::Config;
+use crate::sidecar::SidecarManager;
+use crate::watchdog::RestartEvent;
+
+/// Event a restart after a wake is emitted as
+pub const RESTARTED_AFTER_WAKE_EVENT: &str = "gateway://restarted-after-wake";
+
+/// Time between ticks of the wake monitor
+pub const WAKE_TICK: Duration = Duration::from_secs(5);
+
+/// Time a tick must have missed to count as a sleep
+pub const WAKE_GAP: Duration = Duration::from_secs(30);
+
+/// Wakes this soon after the last one are the same wake
+pub const WAKE_DEBOUNCE: Duration = Duration::from_secs(60);
+
+/// How long the gateway gets to answer after a wake
+pub const WAKE_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tells wakes from the clocks, one tick at a time, so it can be tested
+/// without sleeping the machine
+#[derive(Debug, Clone)]
+pub struct WakeDetector {
+    tick: Duration,
+    last: Option<(SystemTime, Instant)>,
+    last_wake: Option<Instant>,
+}
+
+impl WakeDetector {
+    pub fn new(tick: Duration) -> Self {
+        Self {
+            tick,
+            last: None,
+            last_wake: None,
+        }
+    }
+
+    /// Record a tick at `wall` and `now`. Returns about how long the machine
+    /// slept when this is the first tick after a wake.
+    pub fn observe(&mut self, wall: SystemTime, now: Instant) -> Option<Duration> {
+        let (last_wall, last_now) = self.last.replace((wall, now))?;
+        let elapsed = now.saturating_duration_since(last_now);
+        let wall_elapsed = wall.duration_since(last_wall).unwrap_or_default();
+        // Unseen by the monotonic clock, or a tick that came late
+        let missed = wall_elapsed
+            .saturating_sub(elapsed)
+            .max(elapsed.saturating_sub(self.tick));
+        if missed < WAKE_GAP {
+            return None;
+        }
+        if self
+            .last_wake
+            .is_some_and(|at| now.saturating_duration_since(at) < WAKE_DEBOUNCE)
+        {
+            return None;
+        }
+        self.last_wake = Some(now);
+        Some(missed)
+    }
+}
+
+/// Start the thread that checks the gateway after the machine wakes
+pub fn spawn_wake_monitor(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut detector = WakeDetector::new(WAKE_TICK);
+        loop {
+            std::thread::sleep(WAKE_TICK);
+            if let Some(slept) = detector.observe(SystemTime::now(), Instant::now()) {
+                println!("[wake] Woke after about {} seconds asleep", slept.as_secs());
+                after_wake(&app);
+            }
+        }
+    });
+}
+
+/// Restart the gateway if it stopped answering during the sleep
+fn after_wake(app: &AppHandle) {
+    if !Config::load().is_ok_and(|config| config.restart_on_wake) {
+        return;
+    }
+    let Some(manager) = app.try_state::<SidecarManager>() else {
+        return;
+    };
+    // A start in progress brings up a fresh gateway anyway
+    if manager.is_starting() {
+        return;
+    }
+    let Some(pid) = manager.gateway_pid() else {
+        return;
+    };
+    let detail = match manager.check_responsive(WAKE_PROBE_TIMEOUT) {
+        Some(Err(detail)) => detail,
+        Some(Ok(())) => {
+            println!("[wake] Gateway still answers");
+            return;
+        }
+        None => return,
+    };
+    // Restarted or stopped by someone else while it was checked
+    if manager.is_starting() || manager.gateway_pid() != Some(pid) {
+        return;
+    }
+
+    let message = format!("Gateway stopped answering after the machine slept: {}", detail);
+    println!("[wake] {} Restarting gateway", message);
+    manager.metrics().record_auto_restart();
+    let event = match manager.restart("woke from sleep") {
+        Ok(result) => RestartEvent {
+            reason: "wake".to_string(),
+            message,
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => {
+            eprintln!("[wake] Failed to restart gateway: {}", e);
+            RestartEvent {
+                reason: "wake".to_string(),
+                message,
+                result: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    let _ = app.emit(RESTARTED_AFTER_WAKE_EVENT, &event);
+}
@@ -0,0 +1,167 @@
+//! Gateway Watchdog
+//!
+//! A background thread that periodically samples the running gateway and
+//! restarts it when it misbehaves:
+//!
+//! - `gatewayMaxMemoryMb`: when the gateway's process tree stays above the
+//!   limit for `BREACH_SAMPLES` consecutive samples it is restarted with
+//!   reason `memory-limit`. This works independently of the
+//!   `--max-old-space-size` passed via `NODE_OPTIONS`, which only covers the
+//!   V8 heap and can be overridden by the user's environment.
+//! - heartbeats: when the frontend's requests have gone unanswered for
+//!   `gatewayHeartbeatTimeoutSecs` (see `heartbeat`) it is restarted with
+//!   reason `unresponsive`.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::config::Config;
+use crate::heartbeat::GatewayHealth;
+use crate::resources;
+use crate::sidecar::{SidecarManager, StartResult};
+
+/// Time between samples
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Consecutive samples over the limit before the gateway is restarted
+pub const BREACH_SAMPLES: u32 = 3;
+
+/// Decides when a memory limit has been exceeded for long enough.
+/// Fed one RSS sample at a time so it can be tested without a process.
+#[derive(Debug, Clone)]
+pub struct MemoryLimitMonitor {
+    limit_bytes: u64,
+    required: u32,
+    breaches: u32,
+}
+
+impl MemoryLimitMonitor {
+    pub fn new(limit_mb: u64, required: u32) -> Self {
+        Self {
+            limit_bytes: limit_mb.saturating_mul(1024 * 1024),
+            required: required.max(1),
+            breaches: 0,
+        }
+    }
+
+    pub fn limit_bytes(&self) -> u64 {
+        self.limit_bytes
+    }
+
+    /// Record a sample, returning true once the limit has been exceeded for
+    /// `required` samples in a row. The count resets after triggering.
+    pub fn observe(&mut self, rss_bytes: u64) -> bool {
+        if rss_bytes <= self.limit_bytes {
+            self.breaches = 0;
+            return false;
+        }
+        self.breaches += 1;
+        if self.breaches >= self.required {
+            self.breaches = 0;
+            return true;
+        }
+        false
+    }
+
+    pub fn reset(&mut self) {
+        self.breaches = 0;
+    }
+}
+
+/// Emitted as `gateway://restarted` after the watchdog restarts the gateway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartEvent {
+    pub reason: String,
+    pub message: String,
+    /// The new gateway, or `None` if it failed to come back up
+    pub result: Option<StartResult>,
+    pub error: Option<String>,
+}
+
+/// Start the watchdog thread
+pub fn spawn_watchdog(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut memory: Option<MemoryLimitMonitor> = None;
+        let mut watched_pid = None;
+
+        loop {
+            std::thread::sleep(SAMPLE_INTERVAL);
+
+            let Some(manager) = app.try_state::<SidecarManager>() else {
+                continue;
+            };
+            let Some(pid) = manager.gateway_pid() else {
+                watched_pid = None;
+                continue;
+            };
+
+            // A new gateway starts with a clean slate
+            if watched_pid != Some(pid) {
+                watched_pid = Some(pid);
+                if let Some(ref mut monitor) = memory {
+                    monitor.reset();
+                }
+            }
+
+            if manager.health() == GatewayHealth::Unresponsive {
+                let message = "Gateway accepts connections but stopped answering requests.".to_string();
+                restart(&app, &manager, "unresponsive", message);
+                watched_pid = manager.gateway_pid();
+                continue;
+            }
+
+            let limit_mb = Config::load().ok().and_then(|c| c.gateway_max_memory_mb);
+            let Some(limit_mb) = limit_mb else {
+                memory = None;
+                continue;
+            };
+            let monitor = match memory {
+                Some(ref mut monitor) if monitor.limit_bytes() == limit_mb.saturating_mul(1024 * 1024) => {
+                    monitor
+                }
+                _ => memory.insert(MemoryLimitMonitor::new(limit_mb, BREACH_SAMPLES)),
+            };
+
+            let Some(rss) = resources::process_tree_rss(pid) else {
+                continue;
+            };
+            if monitor.observe(rss) {
+                let message = format!(
+                    "Gateway used {} MB, over the {} MB limit, for {} samples in a row.",
+                    rss / (1024 * 1024),
+                    limit_mb,
+                    BREACH_SAMPLES
+                );
+                restart(&app, &manager, "memory-limit", message);
+                watched_pid = manager.gateway_pid();
+            }
+        }
+    });
+}
+
+/// Restart the gateway, count it and tell the frontend
+fn restart(app: &AppHandle, manager: &SidecarManager, reason: &str, message: String) {
+    println!("[watchdog] {} Restarting gateway ({})", message, reason);
+    manager.metrics().record_auto_restart();
+
+    let event = match manager.restart(reason) {
+        Ok(result) => RestartEvent {
+            reason: reason.to_string(),
+            message,
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => {
+            eprintln!("[watchdog] Failed to restart gateway: {}", e);
+            RestartEvent {
+                reason: reason.to_string(),
+                message,
+                result: None,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    let _ = app.emit("gateway://restarted", &event);
+}
@@ -0,0 +1,129 @@
+//! Finding a gateway the user started on the port, and adopting it without
+//! managing it.
+
+mod support;
+
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+
+use simplestclaw_desktop::adopted;
+use simplestclaw_desktop::process::StopKind;
+use support::{free_port, port_is_listening, FakeGateway};
+
+#[test]
+fn only_a_gateway_is_detected() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    assert!(!adopted::detect(listener.local_addr().unwrap()));
+    let nothing = SocketAddr::from((Ipv4Addr::LOCALHOST, free_port()));
+    assert!(!adopted::detect(nothing));
+
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let owner = fake.manager(port);
+    owner.start().expect("start failed");
+    assert!(adopted::detect(SocketAddr::from((Ipv4Addr::LOCALHOST, port))));
+    owner.stop().unwrap();
+}
+
+#[test]
+fn a_gateway_started_by_hand_is_reported_and_can_be_adopted() {
+    // Stands in for `openclaw gateway` run in a terminal
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let user = fake.manager(port);
+    let token = user.start().expect("start failed").info.token;
+
+    let manager = fake.manager(port);
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "external_gateway_detected");
+    assert_eq!(err.data()["port"], port.to_string());
+    assert!(port_is_listening(port), "the user's gateway was swept");
+
+    let err = manager.adopt("wrong").expect_err("a wrong token was taken");
+    assert_eq!(err.code(), "auth_self_test_failed");
+    assert!(!manager.status().running);
+
+    let info = manager.adopt(&token).expect("adopt failed").info;
+    assert_eq!(info.port, Some(port));
+    assert_eq!(info.token, token);
+    let status = manager.status();
+    assert!(status.running);
+    assert!(!status.managed);
+    assert!(!status.external);
+    // Starting again hands out the adopted gateway
+    assert_eq!(manager.start().expect("start failed").info, info);
+    assert_eq!(manager.current_info(), Some(info));
+
+    // A stop only lets go of it
+    assert_eq!(manager.stop().unwrap(), Some(StopKind::Detached));
+    assert!(port_is_listening(port));
+    assert!(!manager.status().running);
+    user.stop().unwrap();
+}
+
+#[test]
+fn an_adopted_gateway_that_goes_away_is_not_a_crash() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let user = fake.manager(port);
+    let token = user.start().expect("start failed").info.token;
+    let manager = fake.manager(port);
+    manager.adopt(&token).expect("adopt failed");
+
+    user.stop().unwrap();
+    let status = manager.status();
+    assert!(!status.running);
+    assert!(status.managed);
+    assert_eq!(status.last_crash, None);
+    assert_eq!(manager.adopt(&token).unwrap_err().code(), "gateway_not_running");
+    // With the port free again the app starts its own
+    let info = manager.start().expect("start failed").info;
+    assert_eq!(info.port, Some(port));
+    assert!(manager.status().managed);
+    manager.stop().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn a_gateway_started_by_hand_is_never_taken_for_a_leftover() {
+    use simplestclaw_desktop::pid_file::{self, GatewayRecord, PidFile};
+    use std::process::{Command, Stdio};
+
+    // Named and run like `openclaw gateway` from a terminal
+    let fake = FakeGateway::serving();
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("openclaw-gateway");
+    std::fs::copy(fake.binary(), &binary).unwrap();
+    std::fs::copy(fake.binary().with_extension("conf"), binary.with_extension("conf")).unwrap();
+    let port = free_port();
+    let mut user = Command::new(&binary)
+        .args(["gateway", "--port", &port.to_string(), "--token", "users-own"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    while !port_is_listening(port) {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    // With a record of the app's own last gateway, long gone
+    let pid_file = PidFile::new(dir.path().join(pid_file::FILE_NAME));
+    let record = GatewayRecord {
+        pid: std::process::id(),
+        port,
+        token: "sclw-from-a-previous-run".to_string(),
+        started_at: 0,
+    };
+    pid_file.write(&record).unwrap();
+    let manager = fake
+        .manager(port)
+        .with_pid_file(pid_file.clone())
+        .with_leftover_sweep(true);
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "external_gateway_detected");
+    manager.stop().unwrap();
+    pid_file::reap(&pid_file);
+
+    assert!(matches!(user.try_wait(), Ok(None)), "the user's gateway was killed");
+    assert!(port_is_listening(port));
+    user.kill().unwrap();
+    let _ = user.wait();
+}
@@ -0,0 +1,190 @@
+//! Pointing the gateway and the API key check at an Anthropic-compatible
+//! proxy instead of Anthropic's API.
+
+mod support;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+
+use simplestclaw_desktop::anthropic_endpoint::{
+    plan, AnthropicEndpoint, EndpointError, AUTH_TOKEN_ENV, BASE_URL_ENV, CUSTOM_HEADERS_ENV,
+};
+use simplestclaw_desktop::api_key::{check_api_key, ApiKeyCheck, ApiKeyErrorKind};
+use simplestclaw_desktop::config::{Config, ConfigView};
+use simplestclaw_desktop::environment::EnvSource;
+use support::{free_port, FakeGateway};
+
+fn endpoint(base_url: &str, auth_header: &str) -> Result<AnthropicEndpoint, EndpointError> {
+    AnthropicEndpoint::new(Some(base_url.to_string()), Some(auth_header.to_string()))
+}
+
+#[test]
+fn the_base_url_must_be_http_and_the_header_a_header_name() {
+    let proxy = endpoint(" https://llm.corp.example/anthropic/ ", " X-Proxy-Key ").unwrap();
+    assert_eq!(
+        proxy.base_url.as_deref(),
+        Some("https://llm.corp.example/anthropic")
+    );
+    assert_eq!(proxy.auth_header.as_deref(), Some("X-Proxy-Key"));
+
+    for url in [
+        "llm.corp.example",
+        "ftp://llm.corp.example",
+        "https://user:pw@llm.corp",
+    ] {
+        let err = endpoint(url, "").unwrap_err();
+        assert_eq!(err, EndpointError::InvalidBaseUrl(url.to_string()));
+        assert_eq!(err.field(), "anthropicBaseUrl");
+    }
+    let err = endpoint("http://localhost:4000", "X Proxy Key").unwrap_err();
+    assert_eq!(err.field(), "anthropicAuthHeader");
+
+    // A key can't be checked against it, which is no fault of the key's
+    let check = ApiKeyCheck::invalid_endpoint(&err);
+    assert_eq!(check.error_kind, Some(ApiKeyErrorKind::Settings));
+    assert!(!ApiKeyErrorKind::Settings.is_rejection());
+    assert!(check.message.unwrap().contains("anthropicAuthHeader"));
+
+    // Empty is cleared, back to Anthropic
+    let cleared = endpoint(" ", "").unwrap();
+    assert!(cleared.is_default());
+    assert_eq!(cleared.base_url(), "https://api.anthropic.com");
+    assert_eq!(
+        cleared.auth("k"),
+        ("x-api-key".to_string(), "k".to_string())
+    );
+}
+
+#[test]
+fn the_key_goes_in_the_header_the_proxy_wants() {
+    let env = |auth_header| {
+        endpoint("https://llm.corp", auth_header)
+            .unwrap()
+            .env(Some("key"))
+    };
+    let pair = |name: &str, value: &str| (name.to_string(), value.to_string());
+    assert_eq!(env(""), [pair(BASE_URL_ENV, "https://llm.corp")]);
+    assert_eq!(
+        env("authorization"),
+        [
+            pair(BASE_URL_ENV, "https://llm.corp"),
+            pair(AUTH_TOKEN_ENV, "key")
+        ]
+    );
+    assert_eq!(
+        env("X-Proxy-Key"),
+        [
+            pair(BASE_URL_ENV, "https://llm.corp"),
+            pair(CUSTOM_HEADERS_ENV, "X-Proxy-Key: key")
+        ]
+    );
+    let bearer = endpoint("https://llm.corp", "Authorization").unwrap();
+    assert_eq!(
+        bearer.auth("key"),
+        ("Authorization".to_string(), "Bearer key".to_string())
+    );
+}
+
+#[test]
+fn the_base_url_shows_in_get_config_and_is_checked_on_load() {
+    let config = Config {
+        anthropic_base_url: Some("https://llm.corp".to_string()),
+        ..Config::default()
+    };
+    let view = serde_json::to_value(ConfigView::new(config, None)).unwrap();
+    assert_eq!(view["anthropicBaseUrl"], "https://llm.corp");
+    assert_eq!(view["anthropicAuthHeader"], serde_json::Value::Null);
+
+    let config = Config {
+        anthropic_base_url: Some("llm.corp".to_string()),
+        ..Config::default()
+    };
+    assert_eq!(config.validate().unwrap_err().code(), "invalid_setting");
+    assert_eq!(plan(&Config::default()), Ok(None));
+}
+
+#[test]
+fn the_gateway_is_pointed_at_the_proxy_until_it_is_cleared() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.anthropic_base_url = Some("http://localhost:4000".to_string());
+        config.anthropic_auth_header = Some("Authorization".to_string());
+    });
+    let effective = manager.effective_env().expect("no environment");
+    let set: Vec<&str> = effective
+        .vars
+        .iter()
+        .filter(|var| var.source == EnvSource::AnthropicEndpoint)
+        .map(|var| var.name.as_str())
+        .collect();
+    assert_eq!(set, [BASE_URL_ENV, AUTH_TOKEN_ENV]);
+    manager.start().expect("start failed");
+    let env = fake.env();
+    assert_eq!(
+        env.get(BASE_URL_ENV).map(String::as_str),
+        Some("http://localhost:4000")
+    );
+    assert!(env.contains_key(AUTH_TOKEN_ENV));
+    manager.stop().unwrap();
+
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    let env = fake.env();
+    assert!(!env.contains_key(BASE_URL_ENV));
+    assert!(!env.contains_key(AUTH_TOKEN_ENV));
+    manager.stop().unwrap();
+}
+
+#[test]
+fn a_key_only_the_proxy_knows_checks_out() {
+    // A proxy that takes any key in X-Proxy-Key
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}/anthropic", listener.local_addr().unwrap());
+    let (seen, requests) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut lines = Vec::new();
+            let mut reader = BufReader::new(&stream);
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                lines.push(line.trim_end().to_ascii_lowercase());
+            }
+            let status = if lines.iter().any(|line| line.starts_with("x-proxy-key: ")) {
+                "200 OK"
+            } else {
+                "401 Unauthorized"
+            };
+            seen.send(lines).unwrap();
+            let reply = format!("HTTP/1.1 {}\r\ncontent-length: 2\r\n\r\n{{}}", status);
+            let _ = stream.write_all(reply.as_bytes());
+        }
+    });
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let proxy = endpoint(&base_url, "X-Proxy-Key").unwrap();
+    let check = runtime.block_on(check_api_key(&proxy, "corp-team-key", None));
+    assert!(check.valid, "{:?}", check);
+    let request = requests.recv().unwrap();
+    assert!(
+        request[0].starts_with("get /anthropic/v1/models"),
+        "{:?}",
+        request
+    );
+    assert!(request.contains(&"x-proxy-key: corp-team-key".to_string()));
+    assert!(!request.iter().any(|line| line.starts_with("x-api-key")));
+
+    let direct = endpoint(&base_url, "").unwrap();
+    let check = runtime.block_on(check_api_key(&direct, "corp-team-key", None));
+    assert_eq!(check.error_kind, Some(ApiKeyErrorKind::Unauthorized));
+    assert_eq!(
+        check.message.as_deref(),
+        Some(format!("{} did not accept this key.", base_url).as_str())
+    );
+    let check = runtime.block_on(check_api_key(&direct, "corp team key", None));
+    assert_eq!(check.error_kind, Some(ApiKeyErrorKind::Malformed));
+}
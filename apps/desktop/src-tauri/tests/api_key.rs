@@ -0,0 +1,35 @@
+//! What checking an API key before it is saved makes of the key and of
+//! the API's answer.
+
+use simplestclaw_desktop::api_key::{check_format, classify_status, ApiKeyErrorKind};
+
+const KEY: &str = "sk-ant-REDACTED";
+
+#[test]
+fn keys_that_cannot_be_anthropic_ones_are_malformed() {
+    assert!(check_format(KEY).is_ok());
+    assert!(check_format("sk-proj-0123456789abcdefghijklmnopqrstuvwxyz").is_err());
+    assert!(check_format("sk-ant-short").is_err());
+    assert!(check_format("sk-ant-api03-0123456789 abcdefghijklmnopqrstuvwxyz").is_err());
+
+    assert_eq!(classify_status(200), None);
+    assert_eq!(classify_status(401), Some(ApiKeyErrorKind::Unauthorized));
+    assert_eq!(classify_status(429), Some(ApiKeyErrorKind::RateLimited));
+    assert_eq!(classify_status(529), Some(ApiKeyErrorKind::Unexpected));
+
+    // Only these stop a key from being saved
+    let rejections: Vec<ApiKeyErrorKind> = [
+        ApiKeyErrorKind::Malformed,
+        ApiKeyErrorKind::Unauthorized,
+        ApiKeyErrorKind::Network,
+        ApiKeyErrorKind::RateLimited,
+        ApiKeyErrorKind::Unexpected,
+    ]
+    .into_iter()
+    .filter(|kind| kind.is_rejection())
+    .collect();
+    assert_eq!(
+        rejections,
+        [ApiKeyErrorKind::Malformed, ApiKeyErrorKind::Unauthorized]
+    );
+}
@@ -0,0 +1,58 @@
+//! Starting the gateway on launch: when it is skipped, and that a failed
+//! start leaves the manager ready for the next one.
+
+mod support;
+
+use std::net::TcpListener;
+
+use simplestclaw_desktop::autostart::{auto_start, AutoStart};
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::error::AppError;
+use support::{free_port, port_is_listening, FakeGateway, TEST_API_KEY};
+
+fn launch_config(auto_start_gateway: bool, api_key: Option<&str>) -> Config {
+    Config {
+        auto_start_gateway,
+        anthropic_api_key: api_key.map(str::to_string),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn nothing_is_started_when_off_or_without_an_api_key() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+
+    let outcome = auto_start(&manager, &launch_config(false, Some(TEST_API_KEY)));
+    assert!(matches!(outcome, AutoStart::Disabled), "{:?}", outcome);
+    let outcome = auto_start(&manager, &launch_config(true, Some("")));
+    assert!(matches!(outcome, AutoStart::NoApiKey), "{:?}", outcome);
+    assert!(!manager.status().running);
+}
+
+#[test]
+fn a_failed_auto_start_can_be_retried() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(port);
+    let config = launch_config(true, Some(TEST_API_KEY));
+
+    match auto_start(&manager, &config) {
+        AutoStart::Failed(e) => assert_eq!(
+            e,
+            AppError::PortInUse {
+                port: port.to_string()
+            }
+        ),
+        outcome => panic!("expected a failed start, got {:?}", outcome),
+    }
+    assert!(!manager.status().running);
+
+    drop(listener);
+    match auto_start(&manager, &config) {
+        AutoStart::Started(result) => assert!(port_is_listening(result.info.port.unwrap())),
+        outcome => panic!("expected a start, got {:?}", outcome),
+    }
+    manager.stop().unwrap();
+}
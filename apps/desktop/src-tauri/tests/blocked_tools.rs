@@ -0,0 +1,115 @@
+//! Blocked tools: validation, flag translation and refusing gateways that
+//! can't disable them.
+
+mod support;
+
+use simplestclaw_desktop::blocked_tools::{
+    gateway_args, validate, BlockedToolsError, GatewayVersion, DENY_FLAG,
+};
+use simplestclaw_desktop::error::AppError;
+use support::{free_port, port_is_listening, FakeGateway};
+
+fn tools(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn tools_are_normalized_and_unknown_ones_rejected() {
+    assert_eq!(
+        validate(&tools(&[" Exec", "process", "exec"])),
+        Ok(tools(&["exec", "process"]))
+    );
+    assert_eq!(
+        validate(&tools(&["exec", "shell"])),
+        Err(BlockedToolsError::UnknownTool("shell".to_string()))
+    );
+}
+
+#[test]
+fn versions_are_read_from_cli_output() {
+    assert_eq!(
+        GatewayVersion::parse("openclaw 2026.1.29\n"),
+        Some(GatewayVersion(2026, 1, 29))
+    );
+    assert_eq!(
+        GatewayVersion::parse("v2026.2.1-beta.3"),
+        Some(GatewayVersion(2026, 2, 1))
+    );
+    assert_eq!(GatewayVersion::parse("openclaw dev"), None);
+}
+
+#[test]
+fn each_tool_becomes_a_deny_flag() {
+    let args = gateway_args(
+        &tools(&["browser", "exec"]),
+        Some(GatewayVersion(2026, 2, 1)),
+    );
+    assert_eq!(args, Ok(tools(&[DENY_FLAG, "browser", DENY_FLAG, "exec"])));
+    assert_eq!(gateway_args(&[], None), Ok(Vec::new()));
+}
+
+#[test]
+fn tools_the_gateway_cannot_disable_are_rejected() {
+    let old = Some(GatewayVersion(2026, 1, 25));
+    assert!(gateway_args(&tools(&["exec"]), old).is_ok());
+    assert_eq!(
+        gateway_args(&tools(&["exec", "browser"]), old),
+        Err(BlockedToolsError::Unsupported {
+            tool: "browser".to_string(),
+            version: "2026.1.25".to_string(),
+        })
+    );
+    // No release can disable it yet
+    assert!(gateway_args(&tools(&["gateway"]), Some(GatewayVersion(2099, 1, 1))).is_err());
+    // Nor can a gateway that won't say which version it is
+    assert!(matches!(
+        gateway_args(&tools(&["exec"]), None),
+        Err(BlockedToolsError::Unsupported { .. })
+    ));
+}
+
+#[test]
+fn blocked_tools_reach_the_gateway_and_its_status() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.blocked_tools = tools(&["exec"]);
+    });
+
+    let preview = manager.preview();
+    assert!(preview
+        .args
+        .windows(2)
+        .any(|pair| pair == [DENY_FLAG, "exec"]));
+
+    manager.start().expect("start failed");
+    assert_eq!(manager.status().blocked_tools, tools(&["exec"]));
+    assert!(!manager.blocked_tools_change_requires_restart(&tools(&["exec"])));
+    assert!(manager.blocked_tools_change_requires_restart(&[]));
+    manager.stop().unwrap();
+}
+
+#[test]
+fn an_old_gateway_is_not_started() {
+    let fake = FakeGateway::new(&[("version", "openclaw 2026.1.25")]);
+    let port = free_port();
+    let manager = fake.manager_with(port, |config| {
+        config.blocked_tools = tools(&["browser"]);
+    });
+
+    let err = manager
+        .start()
+        .expect_err("start should be refused");
+    assert_eq!(
+        err,
+        AppError::ToolBlockUnsupported {
+            tool: "browser".to_string(),
+            version: "2026.1.25".to_string(),
+        }
+    );
+    assert!(!port_is_listening(port));
+    assert!(!manager.status().running);
+    assert_eq!(
+        manager.metrics().snapshot().last_failure.unwrap().code,
+        "blocked_tools"
+    );
+}
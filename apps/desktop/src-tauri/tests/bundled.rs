@@ -0,0 +1,59 @@
+//! Finding the openclaw bundled with the app.
+
+use std::fs;
+
+use simplestclaw_desktop::bundled::{self, BINARY_NAME, TARGET_TRIPLE};
+use simplestclaw_desktop::config::Config;
+
+fn make_executable(path: &std::path::Path, executable: bool) {
+    fs::write(path, "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if executable { 0o755 } else { 0o644 };
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+    #[cfg(not(unix))]
+    let _ = executable;
+}
+
+#[test]
+fn the_bundled_binary_is_found_by_either_name() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(bundled::find_in(dir.path()).is_err());
+
+    let suffixed = dir.path().join(format!(
+        "{}-{}{}",
+        BINARY_NAME,
+        TARGET_TRIPLE,
+        std::env::consts::EXE_SUFFIX
+    ));
+    make_executable(&suffixed, true);
+    assert_eq!(bundled::find_in(dir.path()).unwrap(), suffixed);
+
+    // The plain name comes first
+    let plain = dir
+        .path()
+        .join(format!("{}{}", BINARY_NAME, std::env::consts::EXE_SUFFIX));
+    make_executable(&plain, true);
+    assert_eq!(bundled::find_in(dir.path()).unwrap(), plain);
+}
+
+#[test]
+fn an_unusable_bundle_gives_the_reason() {
+    let config = Config {
+        prefer_global_openclaw: true,
+        ..Config::default()
+    };
+    assert!(bundled::for_config(&config)
+        .unwrap_err()
+        .contains("preferGlobalOpenclaw"));
+
+    #[cfg(unix)]
+    {
+        let dir = tempfile::tempdir().unwrap();
+        make_executable(&dir.path().join(BINARY_NAME), false);
+        let reason = bundled::find_in(dir.path()).unwrap_err();
+        assert!(reason.contains("not executable"), "{}", reason);
+    }
+}
@@ -0,0 +1,129 @@
+//! Reading the flags an installed gateway accepts, and starting gateways
+//! that lack some of them.
+
+mod support;
+
+use std::fs;
+use std::time::Duration;
+
+use simplestclaw_desktop::blocked_tools::{GatewayVersion, DENY_FLAG};
+use simplestclaw_desktop::capabilities::{
+    probe, FlagSupport, GatewayCapabilities, ALLOW_UNCONFIGURED_FLAG, PORT_FLAG, TOKEN_FLAG,
+};
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::limits::SESSIONS_FLAG;
+use support::{free_port, FakeGateway};
+
+#[test]
+fn flags_are_found_in_differently_formatted_help() {
+    let commander = "Usage: openclaw gateway [options]\n\nOptions:\n  \
+        --port <port>          Port to listen on\n  \
+        --token <token>        Auth token\n  \
+        --allow-unconfigured   Start without a config\n";
+    let capabilities = GatewayCapabilities::from_help(commander);
+    assert_eq!(capabilities.supports(PORT_FLAG), Some(true));
+    assert_eq!(capabilities.supports(ALLOW_UNCONFIGURED_FLAG), Some(true));
+    assert_eq!(capabilities.supports(DENY_FLAG), Some(false));
+
+    let reflowed = "-p, --port=PORT | --token=TOKEN, --deny-tool=ID (repeatable)";
+    let capabilities = GatewayCapabilities::from_help(reflowed);
+    assert_eq!(capabilities.supports(TOKEN_FLAG), Some(true));
+    assert_eq!(capabilities.supports(DENY_FLAG), Some(true));
+
+    // Longer flags that merely contain ours don't count
+    let lookalikes = "--port <n>\n--tokens-file <path>\n--no-allow-unconfigured";
+    let capabilities = GatewayCapabilities::from_help(lookalikes);
+    assert_eq!(capabilities.supports(TOKEN_FLAG), Some(false));
+    assert_eq!(capabilities.supports(ALLOW_UNCONFIGURED_FLAG), Some(false));
+}
+
+#[test]
+fn help_without_any_known_flag_tells_nothing() {
+    let capabilities = GatewayCapabilities::from_help("error: unknown command 'gateway'");
+    assert!(!capabilities.is_known());
+    assert!(capabilities.allows(PORT_FLAG));
+    assert!(capabilities.require(PORT_FLAG, None).is_ok());
+    assert!(capabilities
+        .table()
+        .iter()
+        .all(|row| row.supported.is_none()));
+}
+
+#[test]
+fn probes_are_cached_per_binary_and_version() {
+    let fake = FakeGateway::new(&[("flags", "--port --token")]);
+    let program = fake.binary().to_string_lossy().to_string();
+    let timeout = Duration::from_secs(10);
+    let version = Some(GatewayVersion(2026, 2, 1));
+    let first = probe(&program, &[], &[], version, timeout);
+    assert_eq!(first.supports(ALLOW_UNCONFIGURED_FLAG), Some(false));
+
+    // The same binary and version isn't asked again
+    fs::write(fake.binary().with_extension("conf"), "").unwrap();
+    assert_eq!(probe(&program, &[], &[], version, timeout), first);
+
+    // Another version is, and so is a binary without one
+    let newer = probe(
+        &program,
+        &[],
+        &[],
+        Some(GatewayVersion(2026, 3, 1)),
+        timeout,
+    );
+    assert_eq!(newer.supports(ALLOW_UNCONFIGURED_FLAG), Some(true));
+    assert_eq!(
+        probe(&program, &[], &[], None, timeout).supports(SESSIONS_FLAG),
+        Some(true)
+    );
+}
+
+#[test]
+fn an_optional_flag_is_dropped_with_a_warning() {
+    let fake = FakeGateway::new(&[("flags", "--port --token")]);
+    let manager = fake.manager(free_port());
+
+    let preview = manager.preview();
+    assert!(!preview
+        .args
+        .iter()
+        .any(|arg| arg == ALLOW_UNCONFIGURED_FLAG));
+    assert!(preview.capabilities.contains(&FlagSupport {
+        flag: ALLOW_UNCONFIGURED_FLAG.to_string(),
+        required: false,
+        supported: Some(false),
+    }));
+
+    // The fake exits on flags it doesn't list, so this only starts
+    // because the flag was left out
+    let result = manager.start().expect("start failed");
+    assert!(result
+        .warnings
+        .iter()
+        .any(|warning| warning.code == "flag_unsupported"));
+    assert!(manager.status().running);
+    manager.stop().unwrap();
+}
+
+#[test]
+fn a_missing_required_flag_refuses_the_start() {
+    let fake = FakeGateway::new(&[("flags", "--token --allow-unconfigured")]);
+    let manager = fake.manager(free_port());
+    assert_eq!(
+        manager.start().err(),
+        Some(AppError::GatewayFlagUnsupported {
+            flag: PORT_FLAG.to_string(),
+            version: "2026.2.1".to_string(),
+        })
+    );
+
+    // Blocked tools need their flag too
+    let fake = FakeGateway::new(&[("flags", "--port --token --allow-unconfigured")]);
+    let manager = fake.manager_with(free_port(), |config| {
+        config.blocked_tools = vec!["exec".to_string()];
+    });
+    assert!(matches!(
+        manager.start(),
+        Err(AppError::ToolBlockUnsupported { .. })
+    ));
+    assert_eq!(manager.metrics().snapshot().start_failures, 1);
+}
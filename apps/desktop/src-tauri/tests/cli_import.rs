@@ -0,0 +1,168 @@
+//! Taking the key, model and port from an openclaw CLI config.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use simplestclaw_desktop::cli_import::{self, CliSettings};
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::keychain::{KeychainError, SecretStore, API_KEY_ACCOUNT};
+
+#[derive(Default)]
+struct MemoryStore(Mutex<HashMap<String, String>>);
+
+impl SecretStore for MemoryStore {
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+        Ok(self.0.lock().unwrap().get(account).cloned())
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<(), KeychainError> {
+        self.0.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+fn no_env(_: &str) -> Option<String> {
+    None
+}
+
+fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn the_cli_config_is_found_in_its_known_places() {
+    let home = tempfile::tempdir().unwrap();
+    assert_eq!(cli_import::find_in(Some(home.path()), &no_env), None);
+
+    let legacy = home.path().join(".clawdbot/clawdbot.json");
+    write(&legacy, "{}");
+    assert_eq!(cli_import::find_in(Some(home.path()), &no_env), Some(legacy));
+
+    let current = home.path().join(".openclaw/openclaw.json");
+    write(&current, "{}");
+    assert_eq!(cli_import::find_in(Some(home.path()), &no_env), Some(current));
+
+    let custom = home.path().join("elsewhere.json");
+    write(&custom, "{}");
+    let env = |name: &str| (name == "OPENCLAW_CONFIG_PATH").then(|| custom.display().to_string());
+    assert_eq!(cli_import::find_in(Some(home.path()), &env), Some(custom.clone()));
+}
+
+#[test]
+fn key_model_and_port_are_read_from_json5() {
+    let home = tempfile::tempdir().unwrap();
+    let path = home.path().join(".openclaw/openclaw.json");
+    let original = r#"{
+        // written by `openclaw onboard`
+        agents: { defaults: { model: { primary: "anthropic/claude-sonnet-4-5" } } },
+        gateway: { port: 19001, },
+        env: { ANTHROPIC_API_KEY: "sk-ant-from-cli" },
+    }"#;
+    write(&path, original);
+
+    let cli = cli_import::read(&path).unwrap();
+    assert_eq!(cli.api_key.as_deref(), Some("sk-ant-from-cli"));
+    assert_eq!(cli.default_model.as_deref(), Some("anthropic/claude-sonnet-4-5"));
+    assert_eq!(cli.gateway_port, Some(19001));
+    // Only read
+    assert_eq!(fs::read_to_string(&path).unwrap(), original);
+}
+
+#[test]
+fn the_key_can_come_from_the_agents_auth_profiles() {
+    let home = tempfile::tempdir().unwrap();
+    let path = home.path().join(".openclaw/openclaw.json");
+    write(&path, "{ gateway: { mode: 'local' } }");
+    write(
+        &home.path().join(".openclaw/agents/main/agent/auth-profiles.json"),
+        r#"{ "profiles": {
+            "openai:default": { "type": "api_key", "provider": "openai", "key": "sk-openai" },
+            "anthropic:oauth": { "type": "token", "provider": "anthropic", "token": "t" },
+            "anthropic:default": { "type": "api_key", "provider": "anthropic", "key": "sk-ant-profile" }
+        } }"#,
+    );
+
+    let cli = cli_import::read(&path).unwrap();
+    assert_eq!(cli.api_key.as_deref(), Some("sk-ant-profile"));
+    assert_eq!(cli.default_model, None);
+    assert_eq!(cli.gateway_port, None);
+}
+
+#[test]
+fn an_unparsable_cli_config_is_reported() {
+    let home = tempfile::tempdir().unwrap();
+    let path = home.path().join(".openclaw/openclaw.json");
+    write(&path, "{ gateway: ");
+    let error = cli_import::read(&path).unwrap_err();
+    assert_eq!(
+        simplestclaw_desktop::error::AppError::from(error).code(),
+        "cli_config_unreadable"
+    );
+}
+
+#[test]
+fn only_unset_fields_are_imported_and_the_key_goes_to_the_keychain() {
+    let store = MemoryStore::default();
+    let cli = CliSettings {
+        source: "/home/me/.openclaw/openclaw.json".into(),
+        api_key: Some("sk-ant-from-cli".to_string()),
+        default_model: Some("anthropic/claude-sonnet-4-5".to_string()),
+        gateway_port: Some(19001),
+    };
+
+    let mut config = Config {
+        anthropic_api_key: None,
+        ..Config::default()
+    };
+    let summary = cli_import::apply(&mut config, &cli, &store).unwrap();
+    assert_eq!(summary.imported, ["anthropicApiKey", "defaultModel", "gatewayPort"]);
+    assert!(summary.skipped.is_empty());
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-from-cli"));
+    assert!(config.api_key_in_keychain);
+    assert_eq!(store.get(API_KEY_ACCOUNT).unwrap().as_deref(), Some("sk-ant-from-cli"));
+    assert_eq!(config.gateway_port, 19001);
+
+    // Nothing set is overwritten
+    let mut config = Config {
+        anthropic_api_key: Some("sk-ant-mine".to_string()),
+        default_model: Some("anthropic/claude-opus-4-1".to_string()),
+        gateway_port: 18800,
+        ..Config::default()
+    };
+    let summary = cli_import::apply(&mut config, &cli, &store).unwrap();
+    assert!(summary.imported.is_empty());
+    let skipped: Vec<&str> = summary.skipped.iter().map(|s| s.field.as_str()).collect();
+    assert_eq!(skipped, ["anthropicApiKey", "defaultModel", "gatewayPort"]);
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-mine"));
+    assert_eq!(config.gateway_port, 18800);
+}
+
+#[test]
+fn values_the_app_cant_use_are_skipped() {
+    let cli = CliSettings {
+        source: "openclaw.json".into(),
+        api_key: Some("${ANTHROPIC_API_KEY}".to_string()),
+        default_model: Some("not a model".to_string()),
+        gateway_port: Some(80),
+    };
+    let mut config = Config {
+        anthropic_api_key: None,
+        ..Config::default()
+    };
+    let summary = cli_import::apply(&mut config, &cli, &MemoryStore::default()).unwrap();
+    assert!(summary.imported.is_empty());
+    assert_eq!(summary.skipped.len(), 3);
+    assert_eq!(config.anthropic_api_key, None);
+    assert_eq!(config.default_model, None);
+}
@@ -0,0 +1,353 @@
+//! Writing config.json so it survives a crash halfway, getting past one
+//! that is unreadable anyway, keeping the secrets in secrets.json, and
+//! config dirs that are missing or can't be written.
+
+use std::fs;
+use std::path::Path;
+
+use simplestclaw_desktop::config::{
+    backup_path, restrict_permissions, secrets_path, Config, ConfigError, NamedKey, ProfileConfig,
+    Provider,
+};
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::keychain::{KeychainError, SecretStore};
+use simplestclaw_desktop::sealed_keys;
+
+/// Keys stay in the file
+struct NoStore;
+
+impl SecretStore for NoStore {
+    fn get(&self, _account: &str) -> Result<Option<String>, KeychainError> {
+        Ok(None)
+    }
+
+    fn set(&self, _account: &str, _secret: &str) -> Result<(), KeychainError> {
+        Err(KeychainError::Unavailable("no keychain".to_string()))
+    }
+
+    fn delete(&self, _account: &str) -> Result<(), KeychainError> {
+        Ok(())
+    }
+}
+
+fn saved_with_port(path: &Path, port: u16) {
+    let config = Config {
+        gateway_port: port,
+        ..Config::default()
+    };
+    config.save_to(path).unwrap();
+}
+
+fn corrupt_files(dir: &Path) -> Vec<String> {
+    fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("config.json.corrupt-"))
+        .collect()
+}
+
+#[test]
+fn a_truncated_file_is_replaced_by_the_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    saved_with_port(&path, 18800);
+    saved_with_port(&path, 18801);
+    assert!(fs::read_to_string(backup_path(&path))
+        .unwrap()
+        .contains("18800"));
+    assert!(!dir.path().join("config.json.tmp").exists());
+
+    // Cut off mid-write
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::write(&path, &contents[..contents.len() / 2]).unwrap();
+
+    let (config, recovery) = Config::load_recovering(&path, &NoStore).unwrap();
+    let recovery = recovery.expect("no recovery reported");
+    assert!(recovery.from_backup);
+    assert_eq!(config.gateway_port, 18800);
+    assert!(recovery.moved_to.exists());
+    assert_eq!(corrupt_files(dir.path()).len(), 1);
+
+    // The restored file loads as is from now on
+    let (config, recovery) = Config::load_recovering(&path, &NoStore).unwrap();
+    assert_eq!(config.gateway_port, 18800);
+    assert_eq!(recovery, None);
+}
+
+#[test]
+fn defaults_are_used_when_the_backup_is_unreadable_too() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    fs::write(&path, "{ \"gatewayPort\": 18800,, }").unwrap();
+    fs::write(backup_path(&path), "not json").unwrap();
+
+    let config = Config::load_from(&path, &NoStore).expect("load failed");
+    assert_eq!(config.gateway_port, Config::default().gateway_port);
+    assert!(!path.exists());
+    assert_eq!(corrupt_files(dir.path()).len(), 1);
+
+    let recovery = Config::take_recovery().expect("no recovery recorded");
+    assert!(!recovery.from_backup);
+    assert_eq!(
+        fs::read_to_string(&recovery.moved_to).unwrap(),
+        "{ \"gatewayPort\": 18800,, }"
+    );
+    assert_eq!(Config::take_recovery(), None);
+}
+
+#[cfg(unix)]
+fn mode(path: &Path) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn a_file_others_can_read_is_made_private_on_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    saved_with_port(&path, 18800);
+    set_mode(&path, 0o644);
+
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.gateway_port, 18800);
+    assert_eq!(mode(&path), 0o600);
+
+    // Reported with the mode it had, and only while it needs fixing
+    set_mode(&path, 0o644);
+    let fix = restrict_permissions(&path).unwrap().expect("no fix reported");
+    assert_eq!(fix.previous_mode, "644");
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(restrict_permissions(&path).unwrap(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn saves_are_private_to_the_owner() {
+    let dir = tempfile::tempdir().unwrap();
+    set_mode(dir.path(), 0o755);
+    let path = dir.path().join("config.json");
+    // Left by a crash before the modes were set
+    fs::write(dir.path().join("config.json.tmp"), "{").unwrap();
+    set_mode(&dir.path().join("config.json.tmp"), 0o644);
+
+    saved_with_port(&path, 18800);
+    saved_with_port(&path, 18801);
+    assert_eq!(mode(dir.path()), 0o700);
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(mode(&backup_path(&path)), 0o600);
+}
+
+#[test]
+fn a_key_sealed_on_another_machine_has_to_be_entered_again() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let sealed = sealed_keys::seal(&sealed_keys::derive_key("another machine"), "sk-ant-elsewhere")
+        .unwrap();
+    let file = serde_json::json!({
+        "storeApiKeyInFile": true,
+        "anthropicApiKey": sealed,
+        "gatewayPort": 18800
+    });
+    fs::write(&path, file.to_string()).unwrap();
+
+    let mut config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.anthropic_api_key, None);
+    assert_eq!(config.gateway_port, 18800);
+    assert_eq!(config.missing_api_key_error().code(), "api_key_unreadable");
+
+    // Other saves keep it as it was, in secrets.json
+    config.gateway_port = 18801;
+    config.save_to(&path).unwrap();
+    let secrets = fs::read(secrets_path(&path)).unwrap();
+    let saved: serde_json::Value = serde_json::from_slice(&secrets).unwrap();
+    assert_eq!(saved["anthropicApiKey"], serde_json::to_value(&sealed).unwrap());
+
+    // Until it is entered again
+    config.set_api_key(Some("sk-ant-here".to_string()), &NoStore).unwrap();
+    assert_eq!(config.missing_api_key_error().code(), "api_key_missing");
+    config.save_to(&path).unwrap();
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-here"));
+    assert!(config.unreadable_keys.is_empty());
+}
+
+/// Every key in the file: the API key, the gateway token, another
+/// provider's, a stored key's and a profile's
+const SECRETS: [&str; 5] = [
+    "sk-ant-in-file",
+    "sclw-0123456789abcdef",
+    "sk-openai-in-file",
+    "sk-ant-stored-in-file",
+    "sk-ant-profile-in-file",
+];
+
+fn in_file_with_secrets() -> Config {
+    Config {
+        store_api_key_in_file: true,
+        anthropic_api_key: Some(SECRETS[0].to_string()),
+        persist_gateway_token: true,
+        gateway_token: Some(SECRETS[1].to_string()),
+        provider_keys: [(Provider::Openai, SECRETS[2].to_string())].into(),
+        api_keys: vec![NamedKey {
+            label: "work".to_string(),
+            created_at: 0,
+            hint: String::new(),
+            key: Some(SECRETS[3].to_string()),
+            key_in_keychain: false,
+        }],
+        profiles: [(
+            "staging".to_string(),
+            ProfileConfig {
+                api_key: Some(SECRETS[4].to_string()),
+                api_key_in_keychain: false,
+                gateway_port: 18800,
+                gateway_extra_args: Vec::new(),
+            },
+        )]
+        .into(),
+        ..Config::default()
+    }
+}
+
+/// Check that `config` has every key of `in_file_with_secrets`
+fn has_every_secret(config: &Config) {
+    assert_eq!(config.anthropic_api_key.as_deref(), Some(SECRETS[0]));
+    assert_eq!(config.persisted_gateway_token().as_deref(), Some(SECRETS[1]));
+    assert_eq!(config.provider_keys[&Provider::Openai], SECRETS[2]);
+    assert_eq!(config.api_keys[0].label, "work");
+    assert_eq!(config.api_keys[0].key.as_deref(), Some(SECRETS[3]));
+    assert_eq!(config.profiles["staging"].gateway_port, 18800);
+    assert_eq!(config.profiles["staging"].api_key.as_deref(), Some(SECRETS[4]));
+}
+
+#[test]
+fn secrets_are_saved_apart_from_the_settings() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    in_file_with_secrets().save_to(&path).unwrap();
+
+    let settings: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    assert!(settings.get("anthropicApiKey").is_none(), "{}", settings);
+    assert!(settings.get("gatewayToken").is_none(), "{}", settings);
+    assert!(settings.get("providerKeys").is_none(), "{}", settings);
+    assert!(settings["apiKeys"][0].get("key").is_none(), "{}", settings);
+    assert!(settings["profiles"]["staging"].get("apiKey").is_none(), "{}", settings);
+    // What isn't a key stays
+    assert_eq!(settings["storeApiKeyInFile"], true);
+    assert_eq!(settings["apiKeys"][0]["label"], "work");
+    assert_eq!(settings["profiles"]["staging"]["gatewayPort"], 18800);
+    let secrets: serde_json::Value =
+        serde_json::from_slice(&fs::read(secrets_path(&path)).unwrap()).unwrap();
+    assert!(secrets.get("anthropicApiKey").is_some());
+    assert_eq!(secrets["gatewayToken"], "sclw-0123456789abcdef");
+    assert!(secrets["providerKeys"].get("openai").is_some(), "{}", secrets);
+    assert!(secrets["apiKeys"].get("work").is_some(), "{}", secrets);
+    assert!(secrets["profiles"].get("staging").is_some(), "{}", secrets);
+    #[cfg(unix)]
+    assert_eq!(mode(&secrets_path(&path)), 0o600);
+
+    let mut config = Config::load_from(&path, &NoStore).unwrap();
+    has_every_secret(&config);
+
+    // Without secrets there is no secrets.json
+    config.set_api_key(None, &NoStore).unwrap();
+    config.set_gateway_token(None, &NoStore).unwrap();
+    config.provider_keys.clear();
+    config.api_keys.clear();
+    config.profiles.clear();
+    config.save_to(&path).unwrap();
+    assert!(!secrets_path(&path).exists());
+}
+
+#[test]
+fn secrets_in_config_json_are_moved_to_secrets_json_on_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    // As written before secrets.json, which doesn't exist yet
+    let legacy = serde_json::to_string(&in_file_with_secrets()).unwrap();
+    fs::write(&path, &legacy).unwrap();
+    assert!(!secrets_path(&path).exists());
+
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    has_every_secret(&config);
+    // Neither config.json nor its backup keeps them
+    for file in [path.clone(), backup_path(&path)] {
+        let contents = fs::read_to_string(&file).unwrap();
+        for secret in SECRETS {
+            assert!(!contents.contains(secret), "{} in {}", secret, contents);
+        }
+    }
+    assert!(secrets_path(&path).exists());
+
+    // Only once
+    let written = fs::read_to_string(&path).unwrap();
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    has_every_secret(&config);
+    assert_eq!(fs::read_to_string(&path).unwrap(), written);
+}
+
+#[test]
+fn a_missing_config_dir_reads_as_the_defaults_until_saved() {
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("simplestclaw");
+    let path = dir.join("config.json");
+
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.gateway_port, Config::default().gateway_port);
+    assert!(!dir.exists());
+
+    saved_with_port(&path, 18800);
+    assert_eq!(Config::load_from(&path, &NoStore).unwrap().gateway_port, 18800);
+
+    // In place of a dir there is a file, like an unmounted network home
+    let blocked = root.path().join("home");
+    fs::write(&blocked, "").unwrap();
+    let err = Config::default()
+        .save_to(&blocked.join("simplestclaw").join("config.json"))
+        .unwrap_err();
+    assert!(matches!(err, ConfigError::Unavailable { ref path, .. } if path.starts_with(&blocked)));
+    assert_eq!(AppError::from(err).code(), "config_unavailable");
+}
+
+#[cfg(unix)]
+#[test]
+fn a_read_only_config_dir_is_read_and_saving_can_be_retried() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    // Loading has to save this, to move the secrets out
+    let legacy = serde_json::to_string(&in_file_with_secrets()).unwrap();
+    fs::write(&path, &legacy).unwrap();
+    set_mode(dir.path(), 0o500);
+    if fs::write(dir.path().join("probe"), "").is_ok() {
+        // Running as root, which can write anyway
+        set_mode(dir.path(), 0o700);
+        return;
+    }
+
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-in-file"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), legacy);
+
+    let changed = Config {
+        gateway_port: 18800,
+        ..config
+    };
+    let err = changed.save_to(&path).unwrap_err();
+    assert!(matches!(err, ConfigError::ReadOnly { ref path } if path == dir.path()));
+    assert_eq!(fs::read_to_string(&path).unwrap(), legacy);
+    assert!(!dir.path().join("config.json.tmp").exists());
+
+    // Once it is writable again the same save goes through
+    set_mode(dir.path(), 0o700);
+    changed.save_to(&path).unwrap();
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.gateway_port, 18800);
+    assert!(!fs::read_to_string(&path).unwrap().contains("sk-ant-in-file"));
+}
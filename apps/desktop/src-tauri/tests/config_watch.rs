@@ -0,0 +1,24 @@
+//! Telling which settings an outside edit of config.json changed.
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::config_watch::changed_fields;
+use simplestclaw_desktop::settings::requires_restart;
+
+#[test]
+fn only_the_edited_settings_count_as_changed() {
+    let before = Config::default();
+    assert!(changed_fields(&before, &before.clone()).is_empty());
+
+    let after = Config {
+        gateway_port: 18800,
+        keep_running_in_background: !before.keep_running_in_background,
+        ..before.clone()
+    };
+    let changed = changed_fields(&before, &after);
+    assert_eq!(changed, ["gatewayPort", "keepRunningInBackground"]);
+
+    // Only the port needs the running gateway restarted
+    let changed: Vec<&str> = changed.iter().map(String::as_str).collect();
+    assert!(requires_restart(&changed));
+    assert!(!requires_restart(&changed[1..]));
+}
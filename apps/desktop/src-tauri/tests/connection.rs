@@ -0,0 +1,80 @@
+//! Gateway URLs with an embedded token, and their redaction.
+
+use std::net::IpAddr;
+
+use simplestclaw_desktop::connection::{
+    connection_string, encode_query_value, gateway_url, is_lan_exposed, probe_ip, redact_tokens,
+    url_host,
+};
+
+fn ip(text: &str) -> IpAddr {
+    text.parse().unwrap()
+}
+
+#[test]
+fn token_is_url_encoded_into_the_query() {
+    assert_eq!(gateway_url("localhost", 18789, false, None), "ws://localhost:18789");
+    assert_eq!(
+        gateway_url("localhost", 18789, false, Some("sclw-abc123")),
+        "ws://localhost:18789?token=sclw-abc123"
+    );
+    assert_eq!(
+        gateway_url("gateway.lan", 18789, true, Some("sclw-abc123")),
+        "wss://gateway.lan:18789?token=sclw-abc123"
+    );
+    assert_eq!(encode_query_value("a b&c=d/é"), "a%20b%26c%3Dd%2F%C3%A9");
+    assert_eq!(encode_query_value("-._~"), "-._~");
+}
+
+#[test]
+fn the_connection_string_carries_the_token_once() {
+    assert_eq!(
+        connection_string("ws://localhost:18789", "sclw-abc123"),
+        "ws://localhost:18789?token=sclw-abc123"
+    );
+    // Already in the URL with `tokenInUrl`
+    assert_eq!(
+        connection_string("wss://192.168.1.20:18789?token=sclw-abc123", "sclw-abc123"),
+        "wss://192.168.1.20:18789?token=sclw-abc123"
+    );
+    assert_eq!(
+        connection_string("ws://localhost:18789", "a b"),
+        "ws://localhost:18789?token=a%20b"
+    );
+}
+
+#[test]
+fn tokens_in_urls_are_redacted() {
+    assert_eq!(
+        redact_tokens("Gateway running at ws://localhost:18789?token=sclw-abc123"),
+        "Gateway running at ws://localhost:18789?token=[redacted]"
+    );
+    assert_eq!(
+        redact_tokens("open http://localhost:1/?mode=x&token=a%20b#chat and ws://h?token=c"),
+        "open http://localhost:1/?mode=x&token=[redacted]#chat and ws://h?token=[redacted]"
+    );
+    // Not a query parameter, and nothing to redact
+    assert_eq!(redact_tokens("set sessiontoken=abc"), "set sessiontoken=abc");
+    assert_eq!(redact_tokens("ws://h?token="), "ws://h?token=");
+    assert_eq!(redact_tokens("no url here"), "no url here");
+}
+
+#[test]
+fn lan_hosts_are_named_by_an_address_other_devices_can_use() {
+    let lan = Some(ip("192.168.1.20"));
+    assert_eq!(url_host(ip("127.0.0.1"), lan), "localhost");
+    assert_eq!(url_host(ip("::1"), lan), "localhost");
+    assert_eq!(url_host(ip("0.0.0.0"), lan), "192.168.1.20");
+    assert_eq!(url_host(ip("0.0.0.0"), None), "localhost");
+    assert_eq!(url_host(ip("10.0.0.5"), lan), "10.0.0.5");
+    assert_eq!(url_host(ip("fd00::5"), None), "[fd00::5]");
+
+    assert!(!is_lan_exposed(ip("127.0.0.1")));
+    assert!(is_lan_exposed(ip("0.0.0.0")));
+    assert!(is_lan_exposed(ip("10.0.0.5")));
+
+    // The app itself stays on loopback where it can
+    assert_eq!(probe_ip(ip("0.0.0.0")), ip("127.0.0.1"));
+    assert_eq!(probe_ip(ip("::")), ip("::1"));
+    assert_eq!(probe_ip(ip("10.0.0.5")), ip("10.0.0.5"));
+}
@@ -0,0 +1,96 @@
+//! Checking clients can reach the gateway, stage by stage.
+
+mod support;
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use simplestclaw_desktop::connectivity::{self, ConnectivityStage, ConnectivityTarget};
+use support::{free_port, FakeGateway};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+const LOOPBACK: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+fn target(port: u16, token: &str) -> ConnectivityTarget {
+    ConnectivityTarget {
+        port,
+        token: token.to_string(),
+        tls: false,
+        local_ip: LOOPBACK,
+        lan_exposed: false,
+        lan_ip: None,
+    }
+}
+
+fn stages(report: &connectivity::ConnectivityReport) -> Vec<ConnectivityStage> {
+    report.stages.iter().map(|stage| stage.stage).collect()
+}
+
+#[test]
+fn a_running_gateway_passes_on_loopback() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    let err = connectivity::run(&manager).unwrap_err();
+    assert_eq!(err.code(), "gateway_not_running");
+
+    manager.start().expect("start failed");
+    let report = connectivity::run(&manager).unwrap();
+    manager.stop().unwrap();
+    assert!(report.passed, "{:?}", report);
+    // Not exposed to the LAN, so not tried there
+    assert_eq!(
+        stages(&report),
+        [ConnectivityStage::Loopback, ConnectivityStage::Handshake]
+    );
+    assert!(report.stages.iter().all(|stage| stage.hint.is_none()));
+}
+
+#[test]
+fn a_refused_upgrade_is_a_token_mismatch() {
+    let fake = FakeGateway::new(&[("upgrade", "token")]);
+    let manager = fake.manager_with(free_port(), |config| config.self_test_on_start = false);
+    let info = manager.start().expect("start failed").info;
+    let port = info.port.expect("no port");
+
+    assert!(connectivity::check(&target(port, &info.token), TIMEOUT).passed);
+    let report = connectivity::check(&target(port, "wrong"), TIMEOUT);
+    manager.stop().unwrap();
+    assert!(!report.passed);
+    let handshake = &report.stages[1];
+    assert_eq!(handshake.stage, ConnectivityStage::Handshake);
+    assert_eq!(handshake.detail, "upgrade rejected: token mismatch");
+    assert!(handshake.hint.is_some());
+}
+
+#[test]
+fn nothing_listening_fails_the_connect_and_skips_the_handshake() {
+    let report = connectivity::check(&target(free_port(), "token"), TIMEOUT);
+    assert!(!report.passed);
+    assert_eq!(stages(&report), [ConnectivityStage::Loopback]);
+    assert!(report.stages[0].detail.contains("refused"), "{:?}", report);
+    assert!(report.stages[0].hint.is_some());
+}
+
+#[test]
+fn a_lan_exposed_gateway_is_also_tried_on_the_lan_address() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    let info = manager.start().expect("start failed").info;
+    let port = info.port.expect("no port");
+
+    let mut exposed = target(port, &info.token);
+    exposed.lan_exposed = true;
+    // Without a network there's nothing to try
+    let report = connectivity::check(&exposed, TIMEOUT);
+    let lan = report.stages.last().unwrap();
+    assert_eq!(lan.stage, ConnectivityStage::Lan);
+    assert!(!lan.passed);
+    assert_eq!(lan.detail, "no LAN address found");
+
+    // Where it listens, it passes
+    exposed.lan_ip = Some(LOOPBACK);
+    let report = connectivity::check(&exposed, TIMEOUT);
+    manager.stop().unwrap();
+    assert!(report.passed, "{:?}", report);
+    assert_eq!(report.stages.len(), 3);
+}
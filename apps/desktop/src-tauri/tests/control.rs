@@ -0,0 +1,171 @@
+//! The control socket protocol and the CLI verbs on top of it.
+#![cfg(unix)]
+
+mod support;
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use simplestclaw_desktop::cli::{self, Invocation};
+use simplestclaw_desktop::control::{self, ControlRequest, ControlResponse, MAX_REQUEST_BYTES};
+use simplestclaw_desktop::error::AppError;
+use support::{free_port, FakeGateway};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn echo(request: &ControlRequest) -> Result<Value, AppError> {
+    match request {
+        ControlRequest::Stop => Err(AppError::GatewayStopFailed {
+            detail: "no gateway".to_string(),
+        }),
+        request => Ok(serde_json::to_value(request).unwrap()),
+    }
+}
+
+/// Write `input` to a connection served by `echo` and collect the answers
+fn exchange(input: &[u8]) -> Vec<ControlResponse> {
+    let (mut client, server) = UnixStream::pair().unwrap();
+    let server = std::thread::spawn(move || control::handle_connection(server, echo));
+    client.write_all(input).unwrap();
+    client.shutdown(std::net::Shutdown::Write).unwrap();
+    let responses = BufReader::new(client)
+        .lines()
+        .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+        .collect();
+    server.join().unwrap().unwrap();
+    responses
+}
+
+#[test]
+fn verbs_parse_into_requests() {
+    assert_eq!(cli::parse_args(&[]), Ok(None));
+    // Launcher options still open the window
+    assert_eq!(cli::parse_args(&args(&["-psn_0_42"])), Ok(None));
+    // So does a link the OS opens the app with
+    assert_eq!(
+        cli::parse_args(&args(&["simplestclaw://set-key?key=sk-ant-abc"])),
+        Ok(None)
+    );
+    assert_eq!(
+        cli::parse_args(&args(&["logs", "--tail", "5", "--pretty"])),
+        Ok(Some(Invocation {
+            request: ControlRequest::Logs { tail: Some(5) },
+            pretty: true,
+        }))
+    );
+    assert_eq!(
+        cli::parse_args(&args(&["start-gateway"])),
+        Ok(Some(Invocation {
+            request: ControlRequest::Start,
+            pretty: false,
+        }))
+    );
+    assert_eq!(cli::parse_args(&args(&["--help"])), Err(String::new()));
+    assert!(cli::parse_args(&args(&["status", "--tail", "5"])).is_err());
+    assert!(cli::parse_args(&args(&["logs", "--tail", "many"])).is_err());
+    assert!(cli::parse_args(&args(&["restart"])).is_err());
+}
+
+#[test]
+fn headless_runs_unless_it_asks_for_the_status() {
+    // Run by the app itself, not sent anywhere
+    assert_eq!(cli::parse_args(&args(&["--headless"])), Ok(None));
+    assert!(cli::is_headless(&args(&["--headless"])));
+    assert!(!cli::is_headless(&args(&["status"])));
+    assert_eq!(
+        cli::parse_args(&args(&["--headless", "--status", "--pretty"])),
+        Ok(Some(Invocation {
+            request: ControlRequest::Status,
+            pretty: true,
+        }))
+    );
+    assert!(cli::parse_args(&args(&["--headless", "--pretty"])).is_err());
+    assert!(cli::parse_args(&args(&["--headless", "--port", "1"])).is_err());
+}
+
+#[test]
+fn each_request_line_gets_one_response_line() {
+    let responses = exchange(
+        b"{\"command\":\"status\"}\n\n{\"command\":\"stop\"}\nnot json\n{\"command\":\"logs\"}",
+    );
+    assert_eq!(responses.len(), 4);
+    assert_eq!(
+        responses[0],
+        ControlResponse::success(json!({ "command": "status" }))
+    );
+    assert!(!responses[1].ok);
+    assert_eq!(
+        responses[1].error.as_ref().unwrap()["code"],
+        "gateway_stop_failed"
+    );
+    assert_eq!(responses[2].error.as_ref().unwrap()["code"], "bad_request");
+    // The last line doesn't need a newline
+    assert_eq!(
+        responses[3].result,
+        Some(json!({ "command": "logs", "tail": null }))
+    );
+}
+
+#[test]
+fn an_oversized_request_ends_the_connection() {
+    let mut input = vec![b' '; MAX_REQUEST_BYTES + 10];
+    input.extend_from_slice(b"\n{\"command\":\"status\"}\n");
+    let responses = exchange(&input);
+    assert_eq!(responses.len(), 1);
+    assert_eq!(
+        responses[0].error.as_ref().unwrap()["message"],
+        "request too long"
+    );
+}
+
+#[test]
+fn the_socket_is_private_and_owned_by_one_instance() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(control::SOCKET_NAME);
+
+    // Left behind by a crashed run
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    control::listen(&path, echo).expect("stale socket not replaced");
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let response = control::send(&path, &ControlRequest::Doctor).unwrap();
+    assert_eq!(response.result, Some(json!({ "command": "doctor" })));
+
+    let err = control::listen(&path, echo).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+}
+
+#[test]
+fn dispatch_drives_the_manager() {
+    let fake = FakeGateway::serving();
+    let manager = Arc::new(fake.manager(free_port()));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(control::SOCKET_NAME);
+    let handler = manager.clone();
+    control::listen(&path, move |request: &ControlRequest| {
+        control::dispatch(&handler, request)
+    })
+    .unwrap();
+
+    let started = control::send(&path, &ControlRequest::Start).unwrap();
+    assert!(started.ok);
+    let status = control::send(&path, &ControlRequest::Status).unwrap();
+    assert_eq!(status.result.as_ref().unwrap()["running"], true);
+    assert!(cli::render_pretty(&ControlRequest::Status, &status).starts_with("Gateway: running"));
+
+    let logs = control::send(&path, &ControlRequest::Logs { tail: Some(1) }).unwrap();
+    assert!(logs.result.unwrap().as_array().unwrap().len() <= 1);
+
+    assert!(control::send(&path, &ControlRequest::Stop).unwrap().ok);
+    let status = control::send(&path, &ControlRequest::Status).unwrap();
+    assert_eq!(
+        cli::render_pretty(&ControlRequest::Status, &status),
+        "Gateway: stopped"
+    );
+}
@@ -0,0 +1,66 @@
+//! Parsing simplestclaw:// links.
+
+use simplestclaw_desktop::deep_link::{self, DeepLink, DeepLinkError, MAX_LINK_LEN};
+
+const KEY: &str = "sk-ant-REDACTED";
+
+#[test]
+fn a_set_key_link_carries_its_key() {
+    let set_key = DeepLink::SetKey {
+        key: KEY.to_string(),
+    };
+    for link in [
+        format!("simplestclaw://set-key?key={}", KEY),
+        format!("simplestclaw://set-key/?key={}&from=onboarding", KEY),
+        format!("SimplestClaw://set-key?key={}#team", KEY),
+        format!("simplestclaw:set-key?key=%20{}", KEY.replace('-', "%2D")),
+    ] {
+        assert_eq!(deep_link::parse(&link), Ok(set_key.clone()), "{}", link);
+    }
+}
+
+#[test]
+fn other_links_and_keys_are_refused() {
+    let refused = |link: &str| deep_link::parse(link).unwrap_err();
+    assert_eq!(refused("https://example.com/set-key?key=x"), DeepLinkError::NotOurs);
+    assert_eq!(refused("simplestclaw"), DeepLinkError::NotOurs);
+    assert_eq!(
+        refused("simplestclaw://delete-everything"),
+        DeepLinkError::UnknownAction("delete-everything".to_string())
+    );
+    assert_eq!(refused("simplestclaw://set-key"), DeepLinkError::MissingKey);
+    assert_eq!(refused("simplestclaw://set-key?key="), DeepLinkError::MissingKey);
+    assert!(matches!(
+        refused("simplestclaw://set-key?key=sk-openai-123"),
+        DeepLinkError::InvalidKey(_)
+    ));
+    assert!(matches!(
+        refused(&format!("simplestclaw://set-key?key={}&key={}", KEY, KEY)),
+        DeepLinkError::Malformed(_)
+    ));
+    assert!(matches!(
+        refused("simplestclaw://set-key?key=sk-ant-%E"),
+        DeepLinkError::Malformed(_)
+    ));
+    assert!(matches!(
+        refused("simplestclaw://set-key?key=%FF%FE"),
+        DeepLinkError::Malformed(_)
+    ));
+    let long = format!("simplestclaw://set-key?key={}", "a".repeat(MAX_LINK_LEN));
+    assert_eq!(refused(&long), DeepLinkError::TooLong);
+    // Cut in the middle of a character, without panicking
+    assert_eq!(refused("simplestcla€"), DeepLinkError::NotOurs);
+}
+
+#[test]
+fn the_link_is_found_among_the_launch_arguments() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let link = format!("simplestclaw://set-key?key={}", KEY);
+    assert_eq!(
+        deep_link::link_from_args(args(&["/usr/bin/simplestclaw", &link])),
+        Some(link.clone())
+    );
+    assert_eq!(deep_link::link_from_args(args(&["/usr/bin/simplestclaw"])), None);
+    // Not the program itself
+    assert_eq!(deep_link::link_from_args(args(&["simplestclaw:x"])), None);
+}
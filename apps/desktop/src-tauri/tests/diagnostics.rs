@@ -0,0 +1,66 @@
+//! Exporting a diagnostics bundle without leaking secrets.
+
+mod support;
+
+use std::fs::File;
+use std::io::Read;
+
+use simplestclaw_desktop::diagnostics;
+use support::{free_port, FakeGateway, TEST_API_KEY};
+
+/// Every file in the zip at `path`, by name
+fn entries(path: &std::path::Path) -> Vec<(String, String)> {
+    let mut archive = zip::ZipArchive::new(File::open(path).unwrap()).unwrap();
+    (0..archive.len())
+        .map(|index| {
+            let mut file = archive.by_index(index).unwrap();
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).unwrap();
+            (file.name().to_string(), contents)
+        })
+        .collect()
+}
+
+#[test]
+fn the_bundle_holds_no_api_key_or_token() {
+    let fake = FakeGateway::new(&[("version", "openclaw 2026.2.3")]);
+    let manager = fake.manager(free_port());
+    let started = manager.start().unwrap().info;
+    let token = started.token.clone();
+    // Secrets that slipped into the gateway's output
+    manager
+        .logs()
+        .push("stdout", &format!("using key {}", TEST_API_KEY));
+    manager.logs().push("stdout", &format!("auth {}", token));
+    manager
+        .logs()
+        .push("stdout", &format!("open {}", started.url));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = diagnostics::export(&manager, "1.2.3", Some(dir.path())).unwrap();
+    manager.stop().unwrap();
+    assert!(path.starts_with(dir.path()));
+
+    let entries = entries(&path);
+    let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["diagnostics.json", "config.json", "gateway.log"]);
+    for (name, contents) in &entries {
+        assert!(
+            !contents.contains(TEST_API_KEY),
+            "{} holds the API key",
+            name
+        );
+        assert!(
+            !contents.contains(&token),
+            "{} holds the gateway token",
+            name
+        );
+    }
+
+    let info: serde_json::Value = serde_json::from_str(&entries[0].1).unwrap();
+    assert_eq!(info["appVersion"], "1.2.3");
+    assert_eq!(info["os"], std::env::consts::OS);
+    assert_eq!(info["openclaw"]["version"]["version"], "2026.2.3");
+    assert_eq!(info["status"]["running"], true);
+    assert!(entries[2].1.contains("using key [redacted]"));
+}
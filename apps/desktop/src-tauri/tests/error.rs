@@ -0,0 +1,141 @@
+//! Error codes and the message catalog.
+
+use std::collections::{BTreeMap, HashSet};
+
+use simplestclaw_desktop::config::ConfigError;
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::messages::{self, CATALOG};
+
+#[test]
+fn every_variant_has_a_unique_code_with_a_catalog_entry() {
+    let mut seen = HashSet::new();
+    for error in AppError::examples() {
+        let code = error.code();
+        assert!(seen.insert(code), "duplicate code {}", code);
+        assert!(messages::template(code).is_some(), "no catalog entry for {}", code);
+    }
+}
+
+#[test]
+fn catalog_has_no_stale_entries() {
+    let codes: HashSet<&str> = AppError::examples().iter().map(|e| e.code()).collect();
+    for (code, _) in CATALOG {
+        assert!(codes.contains(code), "catalog entry {} has no variant", code);
+    }
+}
+
+#[test]
+fn every_placeholder_is_filled_from_the_error_data() {
+    for error in AppError::examples() {
+        let message = error.message();
+        assert!(!message.contains('{'), "{}: {}", error.code(), message);
+    }
+}
+
+#[test]
+fn rendering_without_parameters_never_panics() {
+    let empty = BTreeMap::new();
+    for (code, template) in CATALOG {
+        assert_eq!(messages::render(code, &empty), *template);
+    }
+    assert_eq!(messages::render("no_such_code", &empty), "Unexpected error (no_such_code)");
+}
+
+#[test]
+fn interpolation_handles_partial_and_malformed_templates() {
+    let params = BTreeMap::from([("path".to_string(), "/tmp/x".to_string())]);
+    assert_eq!(
+        messages::interpolate("{path} {detail} {unterminated", &params),
+        "/tmp/x {detail} {unterminated"
+    );
+    assert_eq!(messages::interpolate("{}", &params), "{}");
+}
+
+#[test]
+fn serialized_error_carries_code_data_and_message() {
+    let error = AppError::UnknownProvider {
+        provider: "foo".to_string(),
+    };
+    let json = serde_json::to_value(&error).unwrap();
+    assert_eq!(json["code"], "unknown_provider");
+    assert_eq!(json["data"]["provider"], "foo");
+    assert_eq!(json["message"], "Unknown provider: foo");
+    assert_eq!(error.to_string(), "Unknown provider: foo");
+}
+
+#[test]
+fn start_errors_serialize_their_parameters() {
+    let error = AppError::GatewayNotFound {
+        detail: "Node.js was not found.".to_string(),
+        searched: vec!["/usr/local/bin".to_string(), "/usr/bin".to_string()],
+    };
+    let json = serde_json::to_value(&error).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "code": "gateway_not_found",
+            "message": "The gateway could not be found. Node.js was not found.",
+            "detail": "Node.js was not found.",
+            "data": { "detail": "Node.js was not found.", "searched": "/usr/local/bin\n/usr/bin" },
+        })
+    );
+
+    let json = serde_json::to_value(AppError::PortInUse {
+        port: "18789".to_string(),
+    })
+    .unwrap();
+    assert_eq!(json["code"], "port_in_use");
+    assert_eq!(json["data"], serde_json::json!({ "port": "18789" }));
+    let json = serde_json::to_value(AppError::RuntimeNotInstalled).unwrap();
+    assert_eq!(json["data"], serde_json::json!({}));
+    assert_eq!(json["detail"], serde_json::Value::Null);
+}
+
+#[test]
+fn the_error_catalog_describes_every_code() {
+    let catalog = messages::error_catalog();
+    assert_eq!(catalog.len(), AppError::examples().len());
+    for error in AppError::examples() {
+        let entry = catalog
+            .iter()
+            .find(|entry| entry.code == error.code())
+            .unwrap_or_else(|| panic!("{} is not in the catalog", error.code()));
+        let params: Vec<String> = error.data().into_keys().collect();
+        assert_eq!(entry.params, params, "{}", entry.code);
+        assert_eq!(entry.has_detail, params.iter().any(|param| param == "detail"));
+        // Every placeholder is a parameter
+        let filled = params.iter().map(|param| (param.clone(), String::new())).collect();
+        let rendered = messages::interpolate(&entry.fallback, &filled);
+        assert!(!rendered.contains('{'), "{}: {}", entry.code, entry.fallback);
+    }
+
+    let io = catalog.iter().find(|entry| entry.code == "config_io").unwrap();
+    assert_eq!(io.params, ["detail"]);
+    assert!(io.has_detail);
+}
+
+#[test]
+fn config_errors_map_to_codes() {
+    assert_eq!(AppError::from(ConfigError::NoConfigDir).code(), "config_dir_unavailable");
+
+    let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+    assert_eq!(
+        AppError::from(ConfigError::Io(io)),
+        AppError::ConfigIo {
+            detail: "denied".to_string()
+        }
+    );
+
+    let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    assert_eq!(AppError::from(ConfigError::Json(json)).code(), "config_invalid");
+
+    let read_only = ConfigError::ReadOnly {
+        path: "/home/me/.config/simplestclaw".into(),
+    };
+    assert_eq!(
+        AppError::from(read_only),
+        AppError::ConfigReadOnly {
+            path: "/home/me/.config/simplestclaw".to_string()
+        }
+    );
+}
@@ -0,0 +1,73 @@
+//! Connecting to a gateway managed outside the app.
+
+mod support;
+
+use std::net::{TcpListener, TcpStream};
+
+use simplestclaw_desktop::external::{parse_url, ExternalGateway, ExternalGatewayError};
+use support::{free_port, FakeGateway};
+
+fn external(port: u16) -> ExternalGateway {
+    ExternalGateway {
+        url: format!("ws://127.0.0.1:{}", port),
+        token: "systemd-token".to_string(),
+    }
+}
+
+#[test]
+fn an_external_gateway_is_used_and_left_running() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.anthropic_api_key = None;
+        config.external_gateway = Some(external(port));
+    });
+
+    let started = manager.start().expect("external gateway not used");
+    assert_eq!(started.info.url, format!("ws://127.0.0.1:{}", port));
+    assert_eq!(started.info.token, "systemd-token");
+    assert_eq!(manager.gateway_pid(), None);
+
+    let status = manager.status();
+    assert!(status.running && status.external);
+    assert_eq!(status.effective_port, Some(port));
+
+    // Nothing to stop, and the external gateway keeps serving
+    assert_eq!(manager.stop().unwrap(), None);
+    assert!(TcpStream::connect(("127.0.0.1", port)).is_ok());
+    drop(listener);
+}
+
+#[test]
+fn an_unreachable_external_gateway_fails_the_start() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.external_gateway = Some(external(free_port()));
+    });
+
+    let err = manager.start().expect_err("started without a gateway");
+    assert_eq!(err.code(), "external_gateway_unreachable");
+    let status = manager.status();
+    assert!(status.external);
+    assert!(!status.running);
+    assert_eq!(status.accepting_connections, Some(false));
+}
+
+#[test]
+fn external_urls_must_be_websocket_urls() {
+    assert_eq!(
+        parse_url("wss://gateway.example.com"),
+        Ok(("gateway.example.com".to_string(), 443))
+    );
+    assert_eq!(
+        parse_url("ws://[::1]:18789"),
+        Ok(("::1".to_string(), 18789))
+    );
+    assert_eq!(
+        parse_url("http://localhost:18789"),
+        Err(ExternalGatewayError::InvalidUrl(
+            "http://localhost:18789".to_string()
+        ))
+    );
+}
@@ -0,0 +1,57 @@
+//! Gateway output on disk: clearing the files, and carrying on in memory
+//! when they can't be written.
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use simplestclaw_desktop::gateway_logs::{log_files, GatewayLogs, ACTIVE_FILE};
+
+#[test]
+fn clearing_removes_every_file_and_starts_over() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = GatewayLogs::open(dir.path()).unwrap().with_rotation(256, 3);
+    for i in 0..50 {
+        logs.push("stdout", &format!("line {}", i));
+    }
+    assert_eq!(logs.lock().files().len(), 4);
+
+    assert_eq!(logs.clear_files().unwrap(), 4);
+    assert_eq!(log_files(dir.path()), [dir.path().join(ACTIVE_FILE)]);
+    assert_eq!(logs.lock().lines_on_disk(), 0);
+    assert_eq!(logs.recent(None).len(), 50);
+
+    logs.push("stdout", "after clearing");
+    assert_eq!(logs.lock().lines_on_disk(), 1);
+    let files = logs.lock().files();
+    let contents = fs::read_to_string(&files[0]).unwrap();
+    assert!(
+        contents.ends_with("\tstdout\tafter clearing\n"),
+        "{:?}",
+        contents
+    );
+}
+
+#[test]
+fn a_failed_write_turns_file_logging_off_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs_dir = dir.path().join("logs");
+    let logs = GatewayLogs::open(&logs_dir).unwrap().with_rotation(64, 2);
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let seen = errors.clone();
+    logs.set_file_error_listener(Box::new(move |disabled| {
+        seen.lock().unwrap().push(disabled.clone());
+    }));
+
+    // Rotating into a directory that is gone fails
+    fs::remove_dir_all(&logs_dir).unwrap();
+    for i in 0..20 {
+        logs.push("stdout", &format!("line {}", i));
+    }
+
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].dir, logs_dir.display().to_string());
+    assert_eq!(logs.recent(None).len(), 20);
+    assert!(logs.lock().files().is_empty());
+    assert_eq!(logs.clear_files().unwrap(), 0);
+}
@@ -0,0 +1,95 @@
+//! CPU, memory and connection accounting behind get_gateway_metrics.
+
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::gateway_metrics::{self, CpuSample};
+use simplestclaw_desktop::resources::{self, parse_ps_output, tree_cpu_ms};
+use simplestclaw_desktop::sidecar::SidecarManager;
+
+#[test]
+fn cpu_time_parses_linux_and_macos_formats() {
+    // Linux: [dd-]hh:mm:ss
+    assert_eq!(resources::parse_cpu_time("00:00:01"), Some(1_000));
+    assert_eq!(resources::parse_cpu_time("01:02:03"), Some(3_723_000));
+    assert_eq!(resources::parse_cpu_time("2-00:00:00"), Some(172_800_000));
+    // macOS: mm:ss.hh
+    assert_eq!(resources::parse_cpu_time("0:00.51"), Some(510));
+    assert_eq!(resources::parse_cpu_time("12:34.56"), Some(754_560));
+
+    assert_eq!(resources::parse_cpu_time("node"), None);
+    assert_eq!(resources::parse_cpu_time("1:2:3:4"), None);
+}
+
+#[test]
+fn cpu_time_is_summed_over_the_process_tree() {
+    let samples = parse_ps_output(
+        "  100     1    100 00:00:01 node\n\
+         \x20 101   100    200 0:02.50 node\n\
+         \x20 200     1   5000 00:10:00 other\n",
+    );
+    assert_eq!(tree_cpu_ms(&samples, 100), 3_500);
+    assert_eq!(tree_cpu_ms(&samples, 200), 600_000);
+}
+
+#[test]
+fn cpu_percent_is_of_one_core_since_the_previous_sample() {
+    let at = Instant::now();
+    let previous = CpuSample { pid: 7, cpu_ms: 1_000, at };
+    let sample = |pid, cpu_ms, secs| CpuSample {
+        pid,
+        cpu_ms,
+        at: at + Duration::from_secs(secs),
+    };
+
+    assert_eq!(gateway_metrics::cpu_percent(&previous, &sample(7, 1_500, 1)), Some(50.0));
+    // Two busy cores
+    assert_eq!(gateway_metrics::cpu_percent(&previous, &sample(7, 5_000, 2)), Some(200.0));
+    // A child exited and took its time with it
+    assert_eq!(gateway_metrics::cpu_percent(&previous, &sample(7, 900, 1)), Some(0.0));
+    // Another gateway, or no time passed
+    assert_eq!(gateway_metrics::cpu_percent(&previous, &sample(8, 1_500, 1)), None);
+    assert_eq!(gateway_metrics::cpu_percent(&previous, &previous), None);
+}
+
+#[test]
+fn only_established_connections_on_the_port_count() {
+    // Port 18789 is 0x4965
+    let proc_net_tcp = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:4965 00000000:0000 0A 00000000:00000000 00:00000000 00000000   501        0 1 1
+   1: 0100007F:4965 0100007F:C350 01 00000000:00000000 00:00000000 00000000   501        0 2 1
+   2: 0100007F:4965 0100007F:C351 01 00000000:00000000 00:00000000 00000000   501        0 3 1
+   3: 0100007F:C350 0100007F:4965 01 00000000:00000000 00:00000000 00000000   501        0 4 1
+   4: 0100007F:4965 0100007F:C352 06 00000000:00000000 00:00000000 00000000   501        0 5 1
+";
+    assert_eq!(resources::count_proc_net_tcp(proc_net_tcp, 18789), 2);
+    assert_eq!(resources::count_proc_net_tcp(proc_net_tcp, 80), 0);
+
+    let macos = "\
+Active Internet connections (including servers)
+Proto Recv-Q Send-Q  Local Address          Foreign Address        (state)
+tcp4       0      0  127.0.0.1.18789        127.0.0.1.52344        ESTABLISHED
+tcp4       0      0  127.0.0.1.52344        127.0.0.1.18789        ESTABLISHED
+tcp6       0      0  ::1.18789              ::1.52345              ESTABLISHED
+tcp4       0      0  *.18789                *.*                    LISTEN
+";
+    assert_eq!(resources::count_netstat(macos, 18789), 2);
+
+    let windows = "\
+Active Connections
+
+  Proto  Local Address          Foreign Address        State
+  TCP    0.0.0.0:18789          0.0.0.0:0              LISTENING
+  TCP    127.0.0.1:18789        127.0.0.1:52344        ESTABLISHED
+  TCP    127.0.0.1:52344        127.0.0.1:18789        ESTABLISHED
+  TCP    [::1]:18789            [::1]:52345            TIME_WAIT
+";
+    assert_eq!(resources::count_netstat(windows, 18789), 1);
+}
+
+#[test]
+fn metrics_need_a_running_gateway() {
+    let manager = SidecarManager::default();
+    let error = manager.gateway_metrics().unwrap_err();
+    assert_eq!(error.code(), "gateway_not_running");
+}
@@ -0,0 +1,95 @@
+//! Extra gateway arguments and variables from the config, and refusing
+//! the ones the app manages.
+
+mod support;
+
+use std::collections::BTreeMap;
+
+use simplestclaw_desktop::environment::EnvSource;
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::gateway_options::{validate, GatewayOptionsError};
+use support::{free_port, FakeGateway};
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn env(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn managed_flags_and_variables_are_refused() {
+    assert_eq!(
+        validate(
+            &args(&["--verbose", "--proxy=http://proxy:3128"]),
+            &env(&[("HTTPS_PROXY", "x")])
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        validate(&args(&["--verbose", "--port=1"]), &BTreeMap::new()),
+        Err(GatewayOptionsError::ManagedFlag("--port".to_string()))
+    );
+    assert_eq!(
+        validate(&args(&["--token", "mine"]), &BTreeMap::new()),
+        Err(GatewayOptionsError::ManagedFlag("--token".to_string()))
+    );
+    assert_eq!(
+        validate(&[], &env(&[("anthropic_api_key", "sk-ant-mine")])),
+        Err(GatewayOptionsError::ManagedEnv(
+            "anthropic_api_key".to_string()
+        ))
+    );
+    assert_eq!(
+        validate(&[], &env(&[("OPENCLAW_GATEWAY_TOKEN", "mine")])),
+        Err(GatewayOptionsError::ManagedEnv(
+            "OPENCLAW_GATEWAY_TOKEN".to_string()
+        ))
+    );
+    assert_eq!(
+        validate(&[], &env(&[("A=B", "c")])),
+        Err(GatewayOptionsError::InvalidEnvName("A=B".to_string()))
+    );
+
+    let e = AppError::from(GatewayOptionsError::ManagedFlag("--port".to_string()));
+    assert_eq!(e.code(), "invalid_gateway_option");
+    assert_eq!(e.data()["field"], "gatewayExtraArgs");
+}
+
+#[test]
+fn extra_options_come_last_and_survive_clean_mode() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.clean_environment = true;
+        config.gateway_extra_args = args(&["--verbose"]);
+        config.gateway_extra_env = env(&[("HTTPS_PROXY", "http://proxy:3128")]);
+    });
+
+    let preview = manager.preview();
+    assert!(preview.errors.is_empty(), "{:?}", preview.errors);
+    assert_eq!(preview.args.last().map(String::as_str), Some("--verbose"));
+
+    let effective = manager.effective_env().expect("no environment");
+    let proxy = effective
+        .vars
+        .iter()
+        .find(|var| var.name == "HTTPS_PROXY")
+        .expect("HTTPS_PROXY not set");
+    assert_eq!(proxy.source, EnvSource::ExtraEnv);
+}
+
+#[test]
+fn a_gateway_with_managed_options_is_not_started() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.gateway_extra_args = args(&["--host", "0.0.0.0"]);
+    });
+
+    let err = manager.start().expect_err("started anyway");
+    assert_eq!(err.code(), "invalid_gateway_option");
+    assert_eq!(manager.gateway_pid(), None);
+}
@@ -0,0 +1,144 @@
+//! Running the gateway on a Unix domain socket instead of a port.
+
+mod support;
+
+use std::path::PathBuf;
+
+use simplestclaw_desktop::config::{Config, Transport};
+use simplestclaw_desktop::gateway_socket;
+use simplestclaw_desktop::sidecar::GatewayInfo;
+
+fn unix_config(path: PathBuf) -> Config {
+    Config {
+        transport: Transport::Unix,
+        gateway_socket_path: Some(path),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn tcp_has_no_socket() {
+    assert_eq!(gateway_socket::plan(&Config::default()).unwrap(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn a_socket_needs_a_short_absolute_path_and_no_tls() {
+    use simplestclaw_desktop::config::TlsConfig;
+
+    let path = PathBuf::from("/run/user/1000/gw.sock");
+    assert_eq!(
+        gateway_socket::plan(&unix_config(path.clone())).unwrap(),
+        Some(path.clone())
+    );
+
+    let mut config = unix_config(path);
+    config.tls = Some(TlsConfig {
+        cert_path: "/etc/gateway/cert.pem".into(),
+        key_path: "/etc/gateway/key.pem".into(),
+    });
+    let err = gateway_socket::plan(&config).unwrap_err();
+    assert_eq!(err.code(), "transport_unsupported");
+    assert_eq!(err.data()["transport"], "unix");
+
+    for path in ["gw.sock".to_string(), format!("/tmp/{}.sock", "x".repeat(100))] {
+        let err = gateway_socket::plan(&unix_config(path.into())).unwrap_err();
+        assert_eq!(err.code(), "transport_unsupported");
+    }
+}
+
+#[cfg(not(unix))]
+#[test]
+fn there_is_no_socket_transport_without_unix_sockets() {
+    let err = gateway_socket::plan(&unix_config("C:\\gw.sock".into())).unwrap_err();
+    assert_eq!(err.code(), "transport_unsupported");
+}
+
+#[test]
+fn gateway_info_from_before_sockets_still_has_its_port() {
+    let json = r#"{"url":"ws://localhost:18789","port":18789,"token":"t"}"#;
+    let info: GatewayInfo = serde_json::from_str(json).unwrap();
+    assert_eq!(info.port, Some(18789));
+    assert_eq!(info.socket_path, None);
+    assert_eq!(info.listen_address(), "port 18789");
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+
+    use super::support::{free_port, port_is_listening, FakeGateway};
+    use super::*;
+
+    #[test]
+    fn a_gateway_on_a_socket_is_private_and_has_no_port() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("run").join("gw.sock");
+        let fake = FakeGateway::serving();
+        let port = free_port();
+        let manager = fake.manager_with(port, |config| {
+            config.transport = Transport::Unix;
+            config.gateway_socket_path = Some(socket.clone());
+        });
+
+        let info = manager.start().expect("start failed").info;
+        assert_eq!(info.port, None);
+        assert_eq!(info.socket_path.as_deref(), socket.to_str());
+        assert!(info.url.starts_with("ws+unix://"), "{}", info.url);
+        assert!(!port_is_listening(port));
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let dir_mode = std::fs::metadata(socket.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(dir_mode & 0o777, 0o700);
+
+        let status = manager.status();
+        assert!(status.running);
+        assert_eq!(status.accepting_connections, Some(true));
+        manager.stop().unwrap();
+    }
+
+    #[test]
+    fn a_socket_left_by_a_crashed_gateway_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("gw.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+
+        let fake = FakeGateway::serving();
+        let manager = fake.manager_with(free_port(), |config| {
+            config.transport = Transport::Unix;
+            config.gateway_socket_path = Some(socket.clone());
+        });
+        let info = manager.start().expect("start failed").info;
+        assert_eq!(info.socket_path.as_deref(), socket.to_str());
+        manager.stop().unwrap();
+    }
+
+    #[test]
+    fn a_socket_still_in_use_is_not_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("gw.sock");
+        let _listener = UnixListener::bind(&socket).unwrap();
+
+        let err = gateway_socket::prepare(&socket).unwrap_err();
+        assert_eq!(err.code(), "socket_in_use");
+        assert!(socket.exists());
+    }
+
+    #[test]
+    fn a_file_that_isnt_a_socket_is_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gw.sock");
+        std::fs::write(&path, "notes").unwrap();
+
+        let err = gateway_socket::prepare(&path).unwrap_err();
+        assert_eq!(err.code(), "gateway_start_failed");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "notes");
+        // Nothing there yet is nothing to clean up
+        assert!(!gateway_socket::prepare(&dir.path().join("new.sock")).unwrap());
+    }
+}
@@ -0,0 +1,146 @@
+//! The gateway state directory, session checks after restarts and what
+//! diagnostics may take from the directory.
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::gateway_state::{
+    classify, diagnostics_files, state_dir, SessionCheck, SessionTracker, StateFileKind,
+    STATE_DIR_ENV,
+};
+use simplestclaw_desktop::paths::AppPaths;
+use support::{free_port, FakeGateway};
+
+fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+#[test]
+fn the_state_dir_defaults_to_app_data_and_can_move() {
+    let paths = AppPaths::from_root("/tmp/sclw-test");
+    assert_eq!(
+        state_dir(&Config::default(), Some(&paths)).unwrap(),
+        PathBuf::from("/tmp/sclw-test/data/gateway-state")
+    );
+
+    let moved = Config {
+        gateway_state_dir: Some("/srv/claw/state".into()),
+        ..Config::default()
+    };
+    assert_eq!(
+        state_dir(&moved, None).unwrap(),
+        PathBuf::from("/srv/claw/state")
+    );
+
+    for bad in ["relative/state", "/srv/../state", "/"] {
+        let config = Config {
+            gateway_state_dir: Some(bad.into()),
+            ..Config::default()
+        };
+        assert!(
+            state_dir(&config, Some(&paths)).is_err(),
+            "{} accepted",
+            bad
+        );
+    }
+}
+
+#[test]
+fn every_spawn_gets_the_state_dir() {
+    let fake = FakeGateway::serving();
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("first/run");
+    let manager = fake.manager_with(free_port(), |config| {
+        config.gateway_state_dir = Some(dir.clone());
+    });
+
+    manager.start().expect("start failed");
+    assert_eq!(
+        fake.env().get(STATE_DIR_ENV),
+        Some(&dir.display().to_string())
+    );
+    assert!(dir.is_dir());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+    manager.stop().unwrap();
+}
+
+#[test]
+fn sessions_are_checked_once_per_new_gateway() {
+    let tracker = SessionTracker::default();
+    // Nothing known before the first gateway
+    assert_eq!(tracker.report(1, ids(&["a", "b"])), None);
+    // The same gateway again
+    assert_eq!(tracker.report(1, ids(&["a", "b", "c"])), None);
+
+    let check = tracker.report(2, ids(&["c", "b", "a"])).unwrap();
+    assert_eq!(check.lost, Vec::<String>::new());
+    assert_eq!(check.event(), "gateway://sessions-restored");
+    assert_eq!(tracker.report(2, ids(&["c"])), None);
+
+    let check = tracker.report(3, ids(&["new"])).unwrap();
+    assert_eq!(
+        check,
+        SessionCheck {
+            restored: Vec::new(),
+            lost: ids(&["c"]),
+        }
+    );
+    assert_eq!(check.event(), "gateway://sessions-lost");
+}
+
+#[test]
+fn diagnostics_leave_out_secrets_and_by_default_conversations() {
+    let root = tempfile::tempdir().unwrap();
+    let write = |relative: &str| {
+        let path = root.path().join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{}").unwrap();
+    };
+    let metadata = ["openclaw.json", "agents/main/sessions/sessions.json"];
+    for file in metadata {
+        write(file);
+    }
+    write("agents/main/sessions/abc.jsonl");
+    write("credentials/oauth.json");
+    write("agents/main/agent/auth-profiles.json");
+    write(".env");
+
+    assert_eq!(
+        classify(Path::new("agents/main/sessions/abc.jsonl")),
+        StateFileKind::Conversation
+    );
+    assert_eq!(
+        classify(Path::new("credentials/oauth.json")),
+        StateFileKind::Secret
+    );
+
+    let relative = |files: Vec<PathBuf>| -> Vec<String> {
+        files
+            .iter()
+            .map(|file| {
+                let file = file.strip_prefix(root.path()).unwrap();
+                file.to_string_lossy().replace('\\', "/")
+            })
+            .collect()
+    };
+    assert_eq!(
+        relative(diagnostics_files(root.path(), false)),
+        ["agents/main/sessions/sessions.json", "openclaw.json"]
+    );
+    assert_eq!(
+        relative(diagnostics_files(root.path(), true)),
+        [
+            "agents/main/sessions/abc.jsonl",
+            "agents/main/sessions/sessions.json",
+            "openclaw.json"
+        ]
+    );
+}
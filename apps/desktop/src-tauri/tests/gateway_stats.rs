@@ -0,0 +1,81 @@
+//! Client and request counts from the gateway's output.
+
+mod support;
+
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::gateway_stats::{classify, GatewayStats, StatsEvent};
+use support::{free_port, FakeGateway};
+
+#[test]
+fn connects_disconnects_and_answered_requests_are_recognized() {
+    let lines = [
+        ("[ws] client connected conn=7f3a remote=127.0.0.1", Some(StatsEvent::ClientConnected)),
+        ("2026-10-14T09:12:03Z INFO WebSocket connected", Some(StatsEvent::ClientConnected)),
+        ("[ws] Client disconnected conn=7f3a code=1000", Some(StatsEvent::ClientDisconnected)),
+        ("[gateway] connection closed (idle)", Some(StatsEvent::ClientDisconnected)),
+        ("[ws] ⇄ res ✓ chat.send 412ms conn=7f3a", Some(StatsEvent::RequestCompleted)),
+        ("[ws] ⇄ res ✗ agent.run 9ms errorCode=INVALID", Some(StatsEvent::RequestCompleted)),
+        ("request completed method=sessions.list", Some(StatsEvent::RequestCompleted)),
+        ("[gateway] listening on ws://127.0.0.1:18789", None),
+        ("[ws] ⇄ req chat.send conn=7f3a", None),
+        ("", None),
+    ];
+    for (line, expected) in lines {
+        assert_eq!(classify(line), expected, "{:?}", line);
+    }
+}
+
+#[test]
+fn counts_follow_the_lines() {
+    let mut stats = GatewayStats::default();
+    // Connected before anything was counted
+    stats.observe("[ws] client disconnected", 1_000);
+    assert_eq!(stats.clients_connected, 0);
+
+    stats.observe("[ws] client connected", 1_000);
+    stats.observe("[ws] client connected", 1_100);
+    stats.observe("[ws] res ✓ chat.send 40ms", 1_200);
+    stats.observe("[agent] thinking...", 1_250);
+    stats.observe("[ws] res ✓ chat.history 3ms", 1_300);
+    stats.observe("[ws] client disconnected", 1_400);
+    assert_eq!(
+        stats,
+        GatewayStats {
+            clients_connected: 1,
+            total_requests: 2,
+            last_request_at: Some(1_300),
+        }
+    );
+}
+
+#[test]
+fn a_gateway_is_counted_from_its_output_until_it_stops() {
+    let fake = FakeGateway::new(&[
+        ("mode", "serve"),
+        (
+            "stdout_tail",
+            "[ws] client connected\\n[ws] odd line\\n[ws] res ✓ chat.send 40ms\\n",
+        ),
+    ]);
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while manager.stats().total_requests == 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let stats = manager.stats();
+    assert_eq!(stats.clients_connected, 1);
+    assert_eq!(stats.total_requests, 1);
+    assert!(stats.last_request_at.is_some());
+    // Left in the log as printed
+    assert!(manager
+        .logs()
+        .recent(None)
+        .iter()
+        .any(|line| line.text == "[ws] odd line"));
+
+    manager.stop().unwrap();
+    assert_eq!(manager.stats(), GatewayStats::default());
+}
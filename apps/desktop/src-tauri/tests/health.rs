@@ -0,0 +1,108 @@
+//! The health endpoint on an ephemeral port, backed by a real manager
+//! running the fake gateway.
+
+mod support;
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use simplestclaw_desktop::health::{self, HEALTH_PATH, READY_PATH};
+use simplestclaw_desktop::http;
+use simplestclaw_desktop::sidecar::SidecarManager;
+use support::{free_port, FakeGateway};
+
+/// Serve the endpoint for `manager`, returning where it listens
+fn serve(manager: Arc<SidecarManager>) -> SocketAddr {
+    let listener = http::bind("127.0.0.1", 0).unwrap();
+    let source = Arc::new(move || (manager.status(), manager.metrics().snapshot()));
+    http::serve(listener, "test-health", health::handler(source)).unwrap()
+}
+
+/// Status code and raw body of `GET path`
+fn get(addr: SocketAddr, path: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+fn get_json(addr: SocketAddr, path: &str) -> (u16, Value) {
+    let (status, body) = get(addr, path);
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+#[test]
+fn health_follows_the_gateway_through_start_and_crash() {
+    let fake = FakeGateway::new(&[("exit_after_ms", "3000"), ("exit_code", "137")]);
+    let manager = Arc::new(fake.manager(free_port()));
+    let addr = serve(manager.clone());
+
+    let (status, body) = get_json(addr, HEALTH_PATH);
+    assert_eq!(status, 200);
+    assert_eq!(body["ok"], true);
+    assert_eq!(body["gateway"]["phase"], "stopped");
+    assert_eq!(body["lastErrorCode"], Value::Null);
+    assert_eq!(get(addr, READY_PATH).0, 503);
+
+    let info = manager.start().expect("start failed");
+    let (status, body) = get(addr, HEALTH_PATH);
+    assert_eq!(status, 200);
+    let summary: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(summary["gateway"]["phase"], "running");
+    assert_eq!(summary["gateway"]["ready"], true);
+    assert!(!body.contains(&info.info.token));
+    assert_eq!(
+        get_json(addr, READY_PATH),
+        (200, serde_json::json!({ "ready": true }))
+    );
+
+    std::thread::sleep(Duration::from_millis(4000));
+    let (status, body) = get_json(addr, HEALTH_PATH);
+    assert_eq!(status, 503);
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["gateway"]["phase"], "failed");
+    assert_eq!(body["lastErrorCode"], "crashed");
+    assert_eq!(get(addr, READY_PATH).0, 503);
+}
+
+#[test]
+fn a_failed_start_reports_its_reason_until_the_next_success() {
+    let fake = FakeGateway::new(&[("mode", "hang")]);
+    let manager = Arc::new(
+        fake.manager(free_port())
+            .with_startup_timeout(Duration::from_secs(1)),
+    );
+    let addr = serve(manager.clone());
+
+    manager.start().expect_err("start should time out");
+    let (status, body) = get_json(addr, HEALTH_PATH);
+    assert_eq!(status, 503);
+    assert_eq!(body["gateway"]["phase"], "failed");
+    assert!(body["lastErrorCode"].is_string());
+
+    let fake = FakeGateway::serving();
+    let manager = Arc::new(
+        fake.manager(free_port())
+            .with_metrics(manager.metrics().clone()),
+    );
+    let addr = serve(manager.clone());
+    manager.start().expect("start failed");
+    let (status, body) = get_json(addr, HEALTH_PATH);
+    assert_eq!(status, 200);
+    assert_eq!(body["lastErrorCode"], Value::Null);
+    manager.stop().unwrap();
+}
+
+#[test]
+fn other_paths_are_not_found() {
+    let fake = FakeGateway::serving();
+    let addr = serve(Arc::new(fake.manager(free_port())));
+    assert_eq!(get(addr, "/").0, 404);
+    assert_eq!(get(addr, "/healthz?verbose=1").0, 200);
+}
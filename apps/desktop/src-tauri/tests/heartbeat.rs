@@ -0,0 +1,131 @@
+//! Hung gateway detection from reported request activity.
+
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::heartbeat::{
+    ActivityReport, GatewayHealth, HeartbeatMonitor, PortProbe, UnhealthyAlarm,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn report(attempts: u64, successes: u64) -> ActivityReport {
+    ActivityReport {
+        attempts,
+        successes,
+        last_success_at: None,
+    }
+}
+
+#[test]
+fn no_requests_is_idle_not_unresponsive() {
+    let start = Instant::now();
+    let mut monitor = HeartbeatMonitor::new(TIMEOUT);
+    assert_eq!(monitor.health(start), GatewayHealth::Idle);
+
+    monitor.report(report(0, 0), start);
+    assert_eq!(monitor.health(start + TIMEOUT * 10), GatewayHealth::Idle);
+}
+
+#[test]
+fn answered_requests_are_healthy() {
+    let start = Instant::now();
+    let mut monitor = HeartbeatMonitor::new(TIMEOUT);
+    monitor.report(report(3, 3), start);
+    assert_eq!(monitor.health(start + TIMEOUT * 2), GatewayHealth::Healthy);
+}
+
+#[test]
+fn unanswered_requests_become_unresponsive_after_the_timeout() {
+    let start = Instant::now();
+    let mut monitor = HeartbeatMonitor::new(TIMEOUT);
+    monitor.report(report(2, 2), start);
+    monitor.report(report(3, 2), start + Duration::from_secs(10));
+    monitor.report(report(5, 2), start + Duration::from_secs(20));
+
+    // Measured from when the wait was first seen, not from the latest request
+    let first_wait = start + Duration::from_secs(10);
+    assert_eq!(monitor.health(first_wait + TIMEOUT - Duration::from_secs(1)), GatewayHealth::Healthy);
+    assert_eq!(monitor.health(first_wait + TIMEOUT), GatewayHealth::Unresponsive);
+}
+
+#[test]
+fn a_response_clears_the_wait() {
+    let start = Instant::now();
+    let mut monitor = HeartbeatMonitor::new(TIMEOUT);
+    monitor.report(report(1, 0), start);
+    monitor.report(report(1, 1), start + Duration::from_secs(30));
+    assert_eq!(monitor.health(start + TIMEOUT * 2), GatewayHealth::Healthy);
+}
+
+#[test]
+fn requests_that_timed_out_before_a_response_do_not_count() {
+    let start = Instant::now();
+    let mut monitor = HeartbeatMonitor::new(TIMEOUT);
+    // Two requests never answered, then the gateway recovers
+    monitor.report(report(2, 0), start);
+    monitor.report(report(3, 1), start + Duration::from_secs(30));
+    monitor.report(report(3, 1), start + Duration::from_secs(40));
+    assert_eq!(monitor.health(start + TIMEOUT * 5), GatewayHealth::Healthy);
+}
+
+#[test]
+fn counters_going_backwards_start_over() {
+    let start = Instant::now();
+    let mut monitor = HeartbeatMonitor::new(TIMEOUT);
+    monitor.report(report(4, 4), start);
+    monitor.report(report(10, 4), start);
+    assert_eq!(monitor.health(start + TIMEOUT), GatewayHealth::Unresponsive);
+
+    // A new client after a reload
+    let reload = start + TIMEOUT;
+    monitor.report(report(1, 0), reload);
+    assert_eq!(monitor.health(reload + Duration::from_secs(1)), GatewayHealth::Healthy);
+    assert_eq!(monitor.health(reload + TIMEOUT), GatewayHealth::Unresponsive);
+}
+
+#[test]
+fn zero_timeout_disables_the_check() {
+    let start = Instant::now();
+    let mut monitor = HeartbeatMonitor::new(Duration::ZERO);
+    monitor.report(report(5, 0), start);
+    assert_eq!(monitor.health(start + TIMEOUT * 100), GatewayHealth::Healthy);
+}
+
+#[test]
+fn the_port_is_probed_at_most_once_per_interval() {
+    let start = Instant::now();
+    let mut probe = PortProbe::new(Duration::from_secs(1));
+    let mut probes = 0;
+    let mut check = |probe: &mut PortProbe, at: Instant, accepting: bool| {
+        probe.check(at, || {
+            probes += 1;
+            accepting
+        })
+    };
+
+    assert!(check(&mut probe, start, true));
+    // Cached, whatever the port would say now
+    assert!(check(&mut probe, start + Duration::from_millis(900), false));
+    assert!(!check(&mut probe, start + Duration::from_secs(1), false));
+    probe.reset();
+    assert!(check(&mut probe, start + Duration::from_millis(1100), true));
+    assert_eq!(probes, 3);
+}
+
+#[test]
+fn unhealthy_is_raised_once_per_stretch_past_the_threshold() {
+    let start = Instant::now();
+    let after = Duration::from_secs(5);
+    let mut alarm = UnhealthyAlarm::new(after);
+
+    assert_eq!(alarm.observe(GatewayHealth::Unresponsive, start), None);
+    assert_eq!(alarm.observe(GatewayHealth::Unresponsive, start + Duration::from_secs(4)), None);
+    assert_eq!(alarm.observe(GatewayHealth::Unresponsive, start + after), Some(after));
+    assert_eq!(alarm.observe(GatewayHealth::Unresponsive, start + after * 2), None);
+
+    // Recovering starts a new stretch
+    let recovered = start + after * 3;
+    assert_eq!(alarm.observe(GatewayHealth::Healthy, recovered), None);
+    assert_eq!(alarm.observe(GatewayHealth::Unresponsive, recovered), None);
+    assert_eq!(alarm.observe(GatewayHealth::Unresponsive, recovered + after), Some(after));
+}
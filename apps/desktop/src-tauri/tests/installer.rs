@@ -0,0 +1,77 @@
+//! Telling failed openclaw installs apart.
+
+use std::path::PathBuf;
+
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::installer::{self, InstallError, Installer, PackageManager};
+
+#[test]
+fn install_failures_are_classified_from_npm_output() {
+    let eacces = "\
+npm error code EACCES
+npm error syscall mkdir
+npm error path /usr/local/lib/node_modules/openclaw
+npm error errno -13
+npm error Error: EACCES: permission denied, mkdir '/usr/local/lib/node_modules/openclaw'
+";
+    let err = AppError::from(installer::classify(eacces));
+    assert_eq!(err.code(), "install_permission_denied");
+    assert_eq!(err.data()["path"], "/usr/local/lib/node_modules/openclaw");
+
+    let offline = "\
+npm ERR! code ENOTFOUND
+npm ERR! syscall getaddrinfo
+npm ERR! errno ENOTFOUND
+npm ERR! network request to https://registry.npmjs.org/openclaw failed, reason: getaddrinfo ENOTFOUND registry.npmjs.org
+";
+    assert_eq!(
+        installer::classify(offline),
+        InstallError::Network(
+            "network request to https://registry.npmjs.org/openclaw failed, reason: getaddrinfo ENOTFOUND registry.npmjs.org"
+                .to_string()
+        )
+    );
+
+    let other =
+        "npm error code E404\nnpm error 404 Not Found - GET https://registry.npmjs.org/openclaw\n";
+    assert_eq!(
+        installer::classify(other),
+        InstallError::Failed("404 Not Found - GET https://registry.npmjs.org/openclaw".to_string())
+    );
+    assert_eq!(
+        AppError::from(InstallError::NoInstaller).code(),
+        "installer_not_found"
+    );
+}
+
+#[test]
+fn npm_runs_through_node_and_the_others_directly() {
+    let npm = Installer {
+        manager: PackageManager::Npm,
+        program: PathBuf::from("/runtime/bin/node"),
+        args: vec!["/runtime/lib/node_modules/npm/bin/npm-cli.js".to_string()],
+        bin_dir: Some(PathBuf::from("/runtime/bin")),
+    };
+    assert_eq!(
+        npm.install_args("openclaw@latest"),
+        [
+            "/runtime/lib/node_modules/npm/bin/npm-cli.js",
+            "install",
+            "--global",
+            "--no-fund",
+            "--no-audit",
+            "openclaw@latest"
+        ]
+    );
+
+    let pnpm = Installer {
+        manager: PackageManager::Pnpm,
+        program: PathBuf::from("/home/me/.local/share/pnpm/pnpm"),
+        args: Vec::new(),
+        bin_dir: None,
+    };
+    assert_eq!(
+        pnpm.install_args("openclaw"),
+        ["add", "--global", "openclaw"]
+    );
+}
@@ -0,0 +1,68 @@
+//! Several gateways at once, each on its own port.
+
+mod support;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use simplestclaw_desktop::instances::{socket_path_for, state_dir_for};
+use simplestclaw_desktop::sidecar::SidecarManager;
+use support::{free_port, port_is_listening, FakeGateway};
+
+/// Tell `manager` that `others` are running under these names
+fn peers_of(manager: &SidecarManager, others: Vec<(&str, Arc<SidecarManager>)>) {
+    let others: Vec<(String, Arc<SidecarManager>)> = others
+        .into_iter()
+        .map(|(name, other)| (name.to_string(), other))
+        .collect();
+    manager.set_peers(Box::new(move || {
+        others
+            .iter()
+            .filter_map(|(name, other)| Some((name.clone(), other.serving_port()?)))
+            .collect()
+    }));
+}
+
+#[test]
+fn instances_run_side_by_side_and_stop_alone() {
+    let fake = FakeGateway::serving();
+    let (default_port, work_port) = (free_port(), free_port());
+    let default = Arc::new(fake.manager(default_port));
+    let work = Arc::new(fake.manager(work_port));
+    peers_of(&default, vec![("work", work.clone())]);
+    peers_of(&work, vec![("default", default.clone())]);
+
+    let first = default.start().expect("default did not start");
+    let second = work.start().expect("work did not start");
+    assert_ne!(first.info.token, second.info.token);
+    assert_eq!(work.serving_port(), Some(work_port));
+
+    // A third on the default's port is refused rather than stopping it
+    let clash = fake.manager(default_port);
+    peers_of(
+        &clash,
+        vec![("default", default.clone()), ("work", work.clone())],
+    );
+    let err = clash.start().expect_err("started on a taken port");
+    assert_eq!(err.code(), "port_in_use_by_instance");
+    assert_eq!(err.data()["instance"], "default");
+    assert!(default.gateway_pid().is_some());
+
+    default.stop().unwrap();
+    assert_eq!(default.serving_port(), None);
+    assert!(work.gateway_pid().is_some());
+    assert!(port_is_listening(work_port));
+    work.stop().unwrap();
+}
+
+#[test]
+fn each_instance_keeps_its_own_state() {
+    assert_eq!(
+        state_dir_for(Path::new("/data/gateway-state"), "work"),
+        Path::new("/data/gateway-state-work")
+    );
+    assert_eq!(
+        socket_path_for(Path::new("/run/user/1000/simplestclaw/gateway.sock"), "work"),
+        Path::new("/run/user/1000/simplestclaw/gateway-work.sock")
+    );
+}
@@ -0,0 +1,227 @@
+//! Where the API key is kept: moving it out of config.json into the
+//! keychain, the file fallback where there is none, and what the webview
+//! gets to see of it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use simplestclaw_desktop::config::{
+    backup_path, redact_api_key, secrets_path, Config, ConfigView, Provider,
+};
+use simplestclaw_desktop::keychain::{
+    provider_key_account, KeychainError, SecretStore, API_KEY_ACCOUNT, GATEWAY_TOKEN_ACCOUNT,
+};
+
+#[derive(Default)]
+struct MemoryStore(Mutex<HashMap<String, String>>);
+
+impl SecretStore for MemoryStore {
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+        Ok(self.0.lock().unwrap().get(account).cloned())
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<(), KeychainError> {
+        self.0.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+/// Headless Linux without a Secret Service
+struct NoStore;
+
+impl SecretStore for NoStore {
+    fn get(&self, _account: &str) -> Result<Option<String>, KeychainError> {
+        Err(KeychainError::Unavailable("none".to_string()))
+    }
+
+    fn set(&self, _account: &str, _secret: &str) -> Result<(), KeychainError> {
+        Err(KeychainError::Unavailable("none".to_string()))
+    }
+
+    fn delete(&self, _account: &str) -> Result<(), KeychainError> {
+        Err(KeychainError::Unavailable("none".to_string()))
+    }
+}
+
+fn with_key(key: &str) -> Config {
+    Config {
+        anthropic_api_key: Some(key.to_string()),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn a_key_in_the_file_is_moved_to_the_keychain_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    fs::write(&path, serde_json::to_string(&with_key("sk-old")).unwrap()).unwrap();
+    let store = MemoryStore::default();
+
+    let config = Config::load_from(&path, &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-old"));
+    assert!(config.api_key_in_keychain);
+    assert_eq!(
+        store.get(API_KEY_ACCOUNT).unwrap().as_deref(),
+        Some("sk-old")
+    );
+    let file = fs::read_to_string(&path).unwrap();
+    assert!(!file.contains("sk-old"), "{}", file);
+    assert!(!fs::read_to_string(backup_path(&path)).unwrap().contains("sk-old"));
+    assert!(!secrets_path(&path).exists());
+
+    // Saving something else keeps it out of the file
+    let mut config = Config::load_from(&path, &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-old"));
+    config.gateway_port = 20000;
+    config.save_to(&path).unwrap();
+    assert!(!fs::read_to_string(&path).unwrap().contains("sk-old"));
+}
+
+#[test]
+fn setting_and_clearing_the_key_goes_through_the_keychain() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let store = MemoryStore::default();
+
+    let mut config = Config::default();
+    config
+        .set_api_key(Some("sk-new".to_string()), &store)
+        .unwrap();
+    config.save_to(&path).unwrap();
+    assert!(!fs::read_to_string(&path).unwrap().contains("sk-new"));
+    let loaded = Config::load_from(&path, &store).unwrap();
+    assert_eq!(loaded.anthropic_api_key.as_deref(), Some("sk-new"));
+
+    config.set_api_key(None, &store).unwrap();
+    config.save_to(&path).unwrap();
+    assert_eq!(store.get(API_KEY_ACCOUNT).unwrap(), None);
+    let loaded = Config::load_from(&path, &store).unwrap();
+    assert_eq!(loaded.anthropic_api_key, None);
+    assert!(!loaded.api_key_in_keychain);
+}
+
+#[test]
+fn without_a_keychain_the_key_stays_in_the_file_when_allowed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    fs::write(&path, serde_json::to_string(&with_key("sk-old")).unwrap()).unwrap();
+
+    // Still usable, just not moved to the keychain
+    let mut config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-old"));
+    assert!(!config.api_key_in_keychain);
+    assert!(!fs::read_to_string(&path).unwrap().contains("sk-old"));
+    assert!(secrets_path(&path).exists());
+
+    // A new key isn't silently written to the file
+    let err = config
+        .set_api_key(Some("sk-new".to_string()), &NoStore)
+        .unwrap_err();
+    assert!(matches!(err, KeychainError::Unavailable(_)));
+
+    config.store_api_key_in_file = true;
+    config
+        .set_api_key(Some("sk-new".to_string()), &NoStore)
+        .unwrap();
+    config.save_to(&path).unwrap();
+    let loaded = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(loaded.anthropic_api_key.as_deref(), Some("sk-new"));
+}
+
+#[test]
+fn other_providers_keys_are_kept_like_the_api_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let store = MemoryStore::default();
+    let key = "sk-or-v1-0123456789abcd";
+
+    let mut config = with_key("sk-ant");
+    config
+        .set_provider_key(Provider::Openrouter, Some(key.to_string()), &store)
+        .unwrap();
+    config.save_to(&path).unwrap();
+    assert!(!fs::read_to_string(&path).unwrap().contains(key));
+    assert_eq!(
+        store
+            .get(&provider_key_account("openrouter"))
+            .unwrap()
+            .as_deref(),
+        Some(key)
+    );
+
+    let loaded = Config::load_from(&path, &store).unwrap();
+    assert_eq!(loaded.provider_key(Provider::Openrouter), Some(key));
+    assert_eq!(loaded.provider_key(Provider::Anthropic), Some("sk-ant"));
+    assert_eq!(loaded.provider_key(Provider::Openai), None);
+    let json = serde_json::to_value(ConfigView::new(loaded, None)).unwrap();
+    assert_eq!(json["providerKeys"]["openrouter"], "sk-or-…abcd");
+
+    // The selected provider's key is the API key
+    config
+        .set_provider_key(Provider::Anthropic, None, &store)
+        .unwrap();
+    assert_eq!(config.anthropic_api_key, None);
+    config
+        .set_provider_key(Provider::Openrouter, None, &store)
+        .unwrap();
+    assert_eq!(
+        store.get(&provider_key_account("openrouter")).unwrap(),
+        None
+    );
+    assert!(config.provider_keys_in_keychain.is_empty());
+}
+
+#[test]
+fn a_kept_gateway_token_is_stored_like_the_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let token = "sclw-0123456789abcdef";
+    let config = Config {
+        persist_gateway_token: true,
+        gateway_token: Some(token.to_string()),
+        ..Config::default()
+    };
+    fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
+    let store = MemoryStore::default();
+
+    let mut config = Config::load_from(&path, &store).unwrap();
+    assert_eq!(config.persisted_gateway_token().as_deref(), Some(token));
+    assert_eq!(
+        store.get(GATEWAY_TOKEN_ACCOUNT).unwrap().as_deref(),
+        Some(token)
+    );
+    assert!(!fs::read_to_string(&path).unwrap().contains(token));
+    let json = serde_json::to_string(&ConfigView::new(config.clone(), None)).unwrap();
+    assert!(!json.contains(token), "{}", json);
+
+    config.set_gateway_token(None, &store).unwrap();
+    assert_eq!(store.get(GATEWAY_TOKEN_ACCOUNT).unwrap(), None);
+}
+
+#[test]
+fn the_webview_only_sees_a_redacted_key() {
+    assert_eq!(
+        redact_api_key("sk-ant-REDACTED"),
+        "sk-ant-…abcd"
+    );
+    assert_eq!(redact_api_key("sk-proj-0123456789wxyz"), "sk-proj-…wxyz");
+    assert_eq!(redact_api_key("AIzaSyA0123456789-wxyz"), "…wxyz");
+    assert_eq!(redact_api_key("short"), "…");
+
+    let key = "sk-ant-REDACTED";
+    let view = ConfigView::new(with_key(key), None);
+    let json = serde_json::to_string(&view).unwrap();
+    assert!(!json.contains(key), "{}", json);
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json["anthropicApiKey"], "sk-ant-…abcd");
+    assert_eq!(json["effectiveProgram"], serde_json::Value::Null);
+}
@@ -0,0 +1,457 @@
+//! `SidecarManager` state handling against a mock process launcher.
+
+mod support;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::heartbeat::{self, ActivityReport, GatewayHealth};
+use simplestclaw_desktop::process::ProcessExit;
+use simplestclaw_desktop::status_events::StartProgress;
+use support::mock::MockLauncher;
+use support::{free_port, port_is_listening, FakeDisk};
+
+#[test]
+fn spawn_failure_is_reported() {
+    let launcher = MockLauncher::new();
+    launcher.control().spawn_error = Some(io::ErrorKind::PermissionDenied);
+    let manager = launcher.manager(free_port());
+
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "gateway_spawn_failed");
+    assert!(err.to_string().contains("permissions"), "{}", err);
+    assert!(!manager.status().running);
+}
+
+#[test]
+fn a_spawn_refused_for_now_is_tried_again() {
+    let launcher = MockLauncher::new();
+    launcher.control().spawn_error = Some(io::ErrorKind::WouldBlock);
+    let manager = launcher
+        .manager_with(free_port(), |config| config.start_max_retries = 2)
+        .with_start_retry_delay(Duration::from_millis(10));
+    let retries = Arc::new(Mutex::new(Vec::new()));
+    let seen = retries.clone();
+    let control = launcher.control.clone();
+    manager.set_progress_listener(Box::new(move |progress| {
+        if let StartProgress::Retrying(event) = progress {
+            seen.lock().unwrap().push((event.attempt, event.max_attempts));
+            // Works the second time
+            control.lock().unwrap().spawn_error = None;
+        }
+    }));
+
+    manager.start().expect("start failed");
+    assert_eq!(*retries.lock().unwrap(), [(1, 3)]);
+    assert_eq!(launcher.control().spawned, 1);
+    assert_eq!(manager.generation(), 2);
+    manager.stop().unwrap();
+}
+
+#[test]
+fn a_start_gives_up_after_its_retries_and_says_so() {
+    let launcher = MockLauncher::new();
+    launcher.control().spawn_error = Some(io::ErrorKind::WouldBlock);
+    let manager = launcher
+        .manager_with(free_port(), |config| config.start_max_retries = 2)
+        .with_start_retry_delay(Duration::from_millis(10));
+
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "gateway_spawn_failed");
+    assert!(err.to_string().contains("after 3 attempts"), "{}", err);
+    assert_eq!(manager.generation(), 3);
+
+    // Failures that won't pass fail at once
+    launcher.control().spawn_error = Some(io::ErrorKind::NotFound);
+    let err = manager.start().expect_err("start should fail");
+    assert!(!err.to_string().contains("attempts"), "{}", err);
+    assert_eq!(manager.generation(), 4);
+    launcher.control().spawn_error = Some(io::ErrorKind::PermissionDenied);
+    manager.start().expect_err("start should fail");
+    assert_eq!(manager.generation(), 5);
+
+    let manager = launcher.manager_with(free_port(), |config| {
+        config.anthropic_api_key = None;
+        config.start_max_retries = 2;
+    });
+    assert_eq!(manager.start().unwrap_err().code(), "api_key_missing");
+    assert_eq!(manager.generation(), 1);
+}
+
+#[test]
+fn starts_waiting_for_a_failing_one_share_its_error() {
+    let launcher = MockLauncher::new();
+    // Never listens, so the start runs until it times out
+    launcher.control().closed = true;
+    let manager = Arc::new(
+        launcher
+            .manager(free_port())
+            .with_startup_timeout(Duration::from_secs(1)),
+    );
+    let first = {
+        let manager = Arc::clone(&manager);
+        std::thread::spawn(move || manager.start())
+    };
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while launcher.control().spawned == 0 {
+        assert!(Instant::now() < deadline, "never spawned");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let waiting: Vec<_> = (0..8)
+        .map(|_| {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || manager.start())
+        })
+        .collect();
+    let expected = first.join().unwrap().expect_err("start should fail");
+    for handle in waiting {
+        assert_eq!(handle.join().unwrap().expect_err("start should fail"), expected);
+    }
+    assert_eq!(launcher.control().spawned, 1);
+    assert_eq!(manager.generation(), 1);
+}
+
+#[test]
+fn a_panic_while_the_state_was_locked_doesnt_stop_the_manager() {
+    let launcher = MockLauncher::new();
+    let manager = Arc::new(launcher.manager(free_port()));
+    manager.start().expect("start failed");
+
+    let poisoner = {
+        let manager = Arc::clone(&manager);
+        std::thread::spawn(move || {
+            let _state = manager.state.lock().unwrap();
+            panic!("poisoning the state");
+        })
+    };
+    assert!(poisoner.join().is_err());
+    assert!(manager.state.is_poisoned());
+
+    // Reported as it is, not as stopped
+    let status = manager.status();
+    assert!(status.running);
+    assert_eq!(status.pid, manager.gateway_pid());
+    assert!(manager.stop().unwrap().is_some());
+    assert!(!manager.status().running);
+    manager.start().expect("start after the panic failed");
+    assert!(manager.status().running);
+    manager.stop().unwrap();
+}
+
+#[test]
+fn exit_during_startup_reports_stderr() {
+    let launcher = MockLauncher::new();
+    {
+        let mut control = launcher.control();
+        control.exit_on_spawn = Some(ProcessExit::from_code(2));
+        control.stderr = "boom".to_string();
+    }
+    let manager = launcher.manager(free_port());
+
+    let err = manager.start().expect_err("start should fail").to_string();
+    assert!(err.contains("boom"), "{}", err);
+    assert!(!manager.status().running);
+}
+
+#[test]
+fn try_wait_error_clears_state() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+    manager.start().expect("start failed");
+
+    launcher.control().try_wait_error = true;
+    let status = manager.status();
+    assert!(!status.running);
+    assert!(status.info.is_none());
+}
+
+#[test]
+fn exited_child_is_replaced_on_start() {
+    let launcher = MockLauncher::new();
+    let port = free_port();
+    let manager = launcher.manager(port);
+    let first = manager.start().expect("first start failed");
+
+    launcher.control().exit = Some(ProcessExit::from_code(1));
+    let second = manager.start().expect("second start failed");
+
+    assert_eq!(launcher.control().spawned, 2);
+    assert_ne!(first.info.token, second.info.token);
+    assert!(manager.status().running);
+    assert!(port_is_listening(port));
+}
+
+#[test]
+fn running_child_is_reused_on_start() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+    let first = manager.start().expect("first start failed");
+    let second = manager.start().expect("second start failed");
+
+    assert_eq!(launcher.control().spawned, 1);
+    assert_eq!(first.info.token, second.info.token);
+}
+
+#[test]
+fn stop_clears_state_when_kill_fails() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+    manager.start().expect("start failed");
+
+    launcher.control().kill_error = true;
+    manager.stop().expect("stop failed");
+
+    assert_eq!(launcher.control().killed, 1);
+    assert!(!manager.status().running);
+}
+
+#[test]
+fn a_paused_gateway_is_idle_and_resumed_to_stop_or_start() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager_with(free_port(), |config| {
+        config.gateway_heartbeat_timeout_secs = 1;
+    });
+    assert_eq!(manager.pause().unwrap_err().code(), "gateway_not_running");
+
+    manager.start().expect("start");
+    manager.report_activity(ActivityReport {
+        attempts: 1,
+        successes: 0,
+        last_success_at: None,
+    });
+    manager.pause().expect("pause");
+    assert!(launcher.control().suspended);
+    std::thread::sleep(Duration::from_millis(1100));
+    // Not answering while paused isn't a hang
+    assert_eq!(manager.health(), GatewayHealth::Idle);
+    let status = manager.status();
+    assert!(status.running && status.paused);
+    assert_eq!(status.health, GatewayHealth::Idle);
+
+    // Starting it again means using it
+    manager.start().expect("start");
+    assert!(!launcher.control().suspended);
+    assert!(!manager.status().paused);
+    assert_eq!(launcher.control().spawned, 1);
+
+    manager.pause().expect("pause");
+    manager.stop().expect("stop");
+    assert_eq!(launcher.control().resumed, 2);
+    assert!(!manager.status().paused);
+    // Nothing to resume any more
+    manager.resume().expect("resume");
+}
+
+#[test]
+fn process_handle_is_held_while_running_and_released_once() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+    let dropped = || launcher.control().dropped;
+
+    manager.start().expect("start failed");
+    manager.status();
+    assert_eq!(dropped(), 0);
+
+    manager.stop().expect("stop failed");
+    assert_eq!(dropped(), 1);
+    manager.stop().expect("second stop failed");
+    assert_eq!(dropped(), 1);
+
+    // A crash noticed by status releases it too
+    manager.start().expect("start failed");
+    launcher.control().exit = Some(ProcessExit::from_code(1));
+    assert!(!manager.status().running);
+    assert_eq!(dropped(), 2);
+
+    // A restart swaps the handle for the new process's
+    manager.start().expect("start failed");
+    manager.restart("test").expect("restart failed");
+    assert_eq!(dropped(), 3);
+    assert!(manager.status().running);
+
+    // A process that dies during startup isn't kept either
+    manager.stop().expect("stop failed");
+    launcher.control().exit_on_spawn = Some(ProcessExit::from_code(2));
+    manager.start().expect_err("start should fail");
+    assert_eq!(dropped(), 5);
+    assert_eq!(launcher.control().spawned, 5);
+}
+
+#[test]
+fn failures_and_crashes_are_counted() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+
+    launcher.control().spawn_error = Some(io::ErrorKind::NotFound);
+    manager.start().expect_err("start should fail");
+    launcher.control().spawn_error = None;
+    manager.start().expect("start failed");
+    launcher.control().exit = Some(ProcessExit::from_code(1));
+    assert!(!manager.status().running);
+
+    let metrics = manager.metrics().snapshot();
+    assert_eq!(metrics.start_attempts, 2);
+    assert_eq!(metrics.start_failures, 1);
+    assert_eq!(metrics.start_failures_by_reason.get("spawn"), Some(&1));
+    assert_eq!(metrics.crashes, 1);
+}
+
+#[test]
+fn killed_at_launch_gets_remediation() {
+    let launcher = MockLauncher::new();
+    launcher.control().exit_on_spawn = Some(ProcessExit {
+        code: None,
+        signal: Some(9),
+    });
+    let manager = launcher.manager(free_port());
+
+    let err = manager.start().expect_err("start should fail").to_string();
+    assert!(err.contains("right after it started"), "{}", err);
+    assert!(err.contains("ask IT"), "{}", err);
+
+    let failures = manager.metrics().snapshot().start_failures_by_reason;
+    assert_eq!(failures.len(), 1);
+    assert!(failures.keys().all(|reason| reason.starts_with("spawn_killed")));
+}
+
+#[test]
+fn reported_activity_drives_health() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager_with(free_port(), |config| {
+        config.gateway_heartbeat_timeout_secs = 1;
+    });
+    assert_eq!(manager.status().health, GatewayHealth::Stopped);
+
+    manager.start().expect("start");
+    assert_eq!(manager.status().health, GatewayHealth::Idle);
+
+    manager.report_activity(ActivityReport {
+        attempts: 2,
+        successes: 2,
+        last_success_at: Some(1_700_000_000_000),
+    });
+    let status = manager.status();
+    assert_eq!(status.health, GatewayHealth::Healthy);
+    assert_eq!(status.last_response_at, Some(1_700_000_000_000));
+
+    manager.report_activity(ActivityReport {
+        attempts: 3,
+        successes: 2,
+        last_success_at: Some(1_700_000_000_000),
+    });
+    std::thread::sleep(Duration::from_millis(1100));
+    assert_eq!(manager.health(), GatewayHealth::Unresponsive);
+
+    // A fresh gateway starts with a clean slate
+    manager.restart("unresponsive").expect("restart");
+    assert_eq!(manager.health(), GatewayHealth::Idle);
+}
+
+#[test]
+fn a_gateway_refusing_connections_is_unresponsive() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+    manager.start().expect("start");
+    let status = manager.status();
+    assert_eq!(status.health, GatewayHealth::Idle);
+    assert_eq!(status.accepting_connections, Some(true));
+
+    launcher.control().closed = true;
+    std::thread::sleep(heartbeat::PROBE_INTERVAL);
+    let status = manager.status();
+    assert!(status.running);
+    assert_eq!(status.health, GatewayHealth::Unresponsive);
+    assert_eq!(status.accepting_connections, Some(false));
+    manager.stop().unwrap();
+    assert_eq!(manager.status().accepting_connections, None);
+}
+
+#[test]
+fn sandboxed_gateway_runs_in_its_workspace() {
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = dir.path().join("workspace");
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager_with(free_port(), |config| {
+        config.sandbox_workspace = true;
+        config.workspace_dir = Some(workspace.clone());
+    });
+
+    manager.start().expect("start");
+    assert!(workspace.is_dir());
+    assert_eq!(launcher.control().last_spec.as_ref().unwrap().cwd, Some(workspace.clone()));
+
+    let sandbox = manager.status().sandbox.expect("sandbox status");
+    assert_eq!(sandbox.workspace, workspace.display().to_string());
+    assert!(!sandbox.not_enforced.is_empty());
+
+    manager.stop().unwrap();
+    assert!(manager.status().sandbox.is_none());
+}
+
+#[test]
+fn gateway_works_in_workspace_dir_or_home() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+    let info = manager.start().expect("start").info;
+    let home = dirs::home_dir();
+    assert_eq!(launcher.control().last_spec.as_ref().unwrap().cwd, home);
+    assert_eq!(info.workspace, home.map(|home| home.display().to_string()));
+    manager.stop().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let workspace = dir.path().to_path_buf();
+    let manager = launcher.manager_with(free_port(), |config| {
+        config.workspace_dir = Some(workspace.clone());
+    });
+    let info = manager.start().expect("start").info;
+    assert_eq!(launcher.control().last_spec.as_ref().unwrap().cwd, Some(workspace.clone()));
+    assert_eq!(info.workspace, Some(workspace.display().to_string()));
+    manager.stop().unwrap();
+
+    let missing = launcher.manager_with(free_port(), |config| {
+        config.workspace_dir = Some(workspace.join("missing"));
+    });
+    let err = missing.start().expect_err("start should fail").to_string();
+    assert!(err.contains("not an existing directory"), "{}", err);
+}
+
+#[test]
+fn invalid_workspace_fails_the_start() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager_with(free_port(), |config| {
+        config.sandbox_workspace = true;
+        config.workspace_dir = Some("relative".into());
+    });
+
+    let err = manager.start().expect_err("start should fail").to_string();
+    assert!(err.contains("absolute"), "{}", err);
+    assert_eq!(launcher.control().spawned, 0);
+    assert_eq!(manager.metrics().snapshot().start_failures_by_reason.get("sandbox"), Some(&1));
+}
+
+#[test]
+fn low_disk_space_warns_and_a_full_disk_refuses_the_start() {
+    let launcher = MockLauncher::new();
+    let manager = launcher
+        .manager_with(free_port(), |config| config.min_free_disk_mb = 2048)
+        .with_disk_space(Box::new(FakeDisk::everywhere(1 << 30)));
+    let result = manager.start().expect("start");
+    assert!(result.warnings.iter().any(|w| w.code == "low_disk_space"));
+    manager.stop().unwrap();
+
+    let full = launcher
+        .manager(free_port())
+        .with_disk_space(Box::new(FakeDisk::everywhere(10 << 20)));
+    let err = full.start().expect_err("start should fail");
+    assert_eq!(err.code(), "disk_full");
+    assert_eq!(err.data()["required"], "100.0 MB");
+    assert_eq!(launcher.control().spawned, 1);
+    assert_eq!(
+        full.metrics().snapshot().start_failures_by_reason.get("disk_space"),
+        Some(&1)
+    );
+    let check = full.preview().checks.into_iter().find(|c| c.name == "disk_space");
+    assert!(check.is_some_and(|check| !check.passed && check.detail.contains("10.0 MB free")));
+}
@@ -0,0 +1,125 @@
+//! Concurrency limits: where each one is enforced and the app-side
+//! session check.
+
+mod support;
+
+use simplestclaw_desktop::blocked_tools::GatewayVersion;
+use simplestclaw_desktop::capabilities::GatewayCapabilities;
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::limits::{
+    ensure_session_allowed, plan, ConcurrencyLimit, LimitEnforcement, REQUESTS_FLAG, SESSIONS_FLAG,
+};
+use support::{free_port, FakeGateway};
+
+fn limited(sessions: Option<u32>, requests: Option<u32>) -> Config {
+    Config {
+        max_concurrent_sessions: sessions,
+        max_concurrent_requests: requests,
+        ..Config::default()
+    }
+}
+
+fn unknown() -> GatewayCapabilities {
+    GatewayCapabilities::unknown()
+}
+
+#[test]
+fn a_gateway_with_the_flags_enforces_both_limits() {
+    let plan = plan(&limited(Some(2), Some(4)), Some(GatewayVersion(2026, 2, 1)), &unknown()).unwrap();
+    assert_eq!(plan.args, [SESSIONS_FLAG, "2", REQUESTS_FLAG, "4"]);
+    assert_eq!(
+        plan.limits.sessions,
+        Some(ConcurrencyLimit {
+            max: 2,
+            enforced_by: LimitEnforcement::Gateway,
+        })
+    );
+    assert_eq!(
+        plan.limits.requests.unwrap().enforced_by,
+        LimitEnforcement::Gateway
+    );
+}
+
+#[test]
+fn older_gateways_fall_back_to_the_app_or_nothing() {
+    let plan = plan(&limited(Some(2), Some(4)), Some(GatewayVersion(2026, 1, 5)), &unknown()).unwrap();
+    assert!(plan.args.is_empty());
+    assert_eq!(
+        plan.limits.sessions.unwrap().enforced_by,
+        LimitEnforcement::App
+    );
+    assert_eq!(
+        plan.limits.requests.unwrap().enforced_by,
+        LimitEnforcement::NotEnforced
+    );
+}
+
+#[test]
+fn a_flag_missing_from_the_help_is_not_passed() {
+    let capabilities = GatewayCapabilities::from_help(&format!("--port <port>\n{} <n>", REQUESTS_FLAG));
+    let plan = plan(
+        &limited(Some(2), Some(4)),
+        Some(GatewayVersion(2026, 2, 1)),
+        &capabilities,
+    )
+    .unwrap();
+    assert_eq!(plan.args, [REQUESTS_FLAG, "4"]);
+    assert_eq!(
+        plan.limits.sessions.unwrap().enforced_by,
+        LimitEnforcement::App
+    );
+}
+
+#[test]
+fn no_limits_means_no_flags_and_zero_is_rejected() {
+    let unlimited = plan(&limited(None, None), None, &unknown()).unwrap();
+    assert!(unlimited.args.is_empty());
+    assert!(unlimited.limits.is_empty());
+
+    let version = Some(GatewayVersion(2026, 2, 1));
+    assert!(plan(&limited(Some(0), None), version, &unknown()).is_err());
+    assert!(plan(&limited(None, Some(0)), version, &unknown()).is_err());
+}
+
+#[test]
+fn the_app_refuses_sessions_past_its_own_limit() {
+    let app = plan(&limited(Some(2), None), None, &unknown()).unwrap().limits;
+    assert!(ensure_session_allowed(&app, 1).is_ok());
+    assert_eq!(
+        ensure_session_allowed(&app, 2),
+        Err(AppError::SessionLimitReached {
+            limit: "2".to_string()
+        })
+    );
+
+    // The gateway answers for itself
+    let gateway = plan(&limited(Some(2), None), Some(GatewayVersion(2026, 2, 1)), &unknown())
+        .unwrap()
+        .limits;
+    assert!(ensure_session_allowed(&gateway, 5).is_ok());
+}
+
+#[test]
+fn status_reports_who_enforces_the_limits() {
+    let fake = FakeGateway::new(&[("version", "openclaw 2026.1.25")]);
+    let manager = fake.manager_with(free_port(), |config| {
+        config.max_concurrent_sessions = Some(2);
+        config.max_concurrent_requests = Some(3);
+    });
+
+    let result = manager.start().expect("start failed");
+    assert!(result
+        .warnings
+        .iter()
+        .any(|warning| warning.code == "session_limit_app_only"));
+
+    let limits = manager.status().limits;
+    assert_eq!(limits.sessions.unwrap().enforced_by, LimitEnforcement::App);
+    assert_eq!(
+        limits.requests.unwrap().enforced_by,
+        LimitEnforcement::Gateway
+    );
+    manager.stop().unwrap();
+    assert!(manager.status().limits.is_empty());
+}
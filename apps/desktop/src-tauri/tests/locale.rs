@@ -0,0 +1,162 @@
+//! Locale and timezone detection, validation and what the gateway gets.
+
+mod support;
+
+use std::collections::HashMap;
+use std::fs;
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::locale::{
+    locale_from_vars, parse_apple_locale, resolve, timezone_from_root, timezone_from_vars,
+    validate_locale, validate_timezone, ValueSource,
+};
+use support::{free_port, FakeGateway};
+
+fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn locales_are_validated_and_made_posix() {
+    for (input, expected) in [
+        ("en-GB", "en_GB.UTF-8"),
+        ("de_DE.UTF-8", "de_DE.UTF-8"),
+        ("pt-br", "pt_BR.UTF-8"),
+        ("fr", "fr.UTF-8"),
+        ("zh-Hant-TW", "zh_TW.UTF-8"),
+        ("es-419", "es_419.UTF-8"),
+        ("sr_RS@latin", "sr_RS.UTF-8@latin"),
+        ("C.UTF-8", "C.UTF-8"),
+    ] {
+        assert_eq!(validate_locale(input).as_deref(), Ok(expected), "{}", input);
+    }
+    for bad in [
+        "",
+        "english",
+        "en-GB-London",
+        "e",
+        "en_GB.",
+        "en;rm -rf",
+        "en-GB-US",
+    ] {
+        assert!(validate_locale(bad).is_err(), "{} accepted", bad);
+    }
+}
+
+#[test]
+fn timezones_must_be_iana_names() {
+    for good in [
+        "UTC",
+        "Europe/Berlin",
+        "America/Argentina/Buenos_Aires",
+        "Etc/GMT+5",
+    ] {
+        assert!(validate_timezone(good).is_ok(), "{} rejected", good);
+    }
+    for bad in [
+        "",
+        "/etc/passwd",
+        "Europe/../Berlin",
+        "Europe//Berlin",
+        "CET-1CEST,M3.5.0",
+        "Europe/Berlin ",
+    ] {
+        assert!(validate_timezone(bad).is_err(), "{} accepted", bad);
+    }
+}
+
+#[test]
+fn detection_from_the_environment_and_the_system() {
+    assert_eq!(
+        locale_from_vars(vars(&[("LANG", "de_DE.UTF-8")])).as_deref(),
+        Some("de_DE.UTF-8")
+    );
+    assert_eq!(
+        locale_from_vars(vars(&[("LC_ALL", "fr_FR.UTF-8"), ("LANG", "de_DE.UTF-8")])).as_deref(),
+        Some("fr_FR.UTF-8")
+    );
+    // What a GUI session gets isn't a choice
+    assert_eq!(locale_from_vars(vars(&[("LANG", "C")])), None);
+    assert_eq!(
+        parse_apple_locale("en_GB@rg=dezzzz\n").as_deref(),
+        Some("en_GB.UTF-8")
+    );
+
+    assert_eq!(
+        timezone_from_vars(vars(&[("TZ", ":Europe/Berlin")])).as_deref(),
+        Some("Europe/Berlin")
+    );
+    // A POSIX rule instead of a name
+    assert_eq!(
+        timezone_from_vars(vars(&[("TZ", "CET-1CEST,M3.5.0,M10.5.0/3")])),
+        None
+    );
+
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir_all(root.path().join("etc")).unwrap();
+    fs::write(root.path().join("etc/timezone"), "Asia/Tokyo\n").unwrap();
+    assert_eq!(
+        timezone_from_root(root.path()).as_deref(),
+        Some("Asia/Tokyo")
+    );
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(
+            "/var/db/timezone/zoneinfo/posix/America/New_York",
+            root.path().join("etc/localtime"),
+        )
+        .unwrap();
+        assert_eq!(
+            timezone_from_root(root.path()).as_deref(),
+            Some("America/New_York")
+        );
+    }
+}
+
+#[test]
+fn configured_values_win_and_reach_the_gateway() {
+    let detected = (Some("en_US.UTF-8".to_string()), Some("UTC".to_string()));
+    let planned = resolve(&Config::default(), detected.clone()).unwrap();
+    assert_eq!(planned.locale_source, Some(ValueSource::Detected));
+    assert_eq!(
+        planned.describe(),
+        "Locale en_US.UTF-8 (detected), timezone UTC (detected)"
+    );
+    let invalid = Config {
+        timezone: Some("Mars/Olympus Mons".into()),
+        ..Config::default()
+    };
+    assert!(resolve(&invalid, detected).is_err());
+
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.clean_environment = true;
+        config.locale = Some("de-DE".into());
+        config.timezone = Some("Europe/Berlin".into());
+    });
+    let preview = manager.preview();
+    let check = preview
+        .checks
+        .iter()
+        .find(|check| check.name == "locale")
+        .unwrap();
+    assert!(
+        check.detail.contains("de_DE.UTF-8 (configured)"),
+        "{}",
+        check.detail
+    );
+
+    manager.start().expect("start failed");
+    let env = fake.env();
+    manager.stop().unwrap();
+    assert_eq!(env.get("LANG").map(String::as_str), Some("de_DE.UTF-8"));
+    assert_eq!(env.get("LC_ALL").map(String::as_str), Some("de_DE.UTF-8"));
+    assert_eq!(env.get("TZ").map(String::as_str), Some("Europe/Berlin"));
+
+    let broken = fake.manager_with(free_port(), |config| config.locale = Some("klingon".into()));
+    assert!(broken.start().is_err());
+}
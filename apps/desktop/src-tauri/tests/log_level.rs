@@ -0,0 +1,49 @@
+//! Passing the configured log level to the gateway.
+
+mod support;
+
+use simplestclaw_desktop::log_level::{self, LOG_LEVEL_ENV, VERBOSE_FLAG};
+use support::{free_port, FakeGateway};
+
+#[test]
+fn only_known_levels_are_accepted() {
+    for level in log_level::LEVELS {
+        assert_eq!(log_level::validate(level), Ok(()));
+    }
+    for level in ["", "verbose", "DEBUG", "info "] {
+        assert!(log_level::validate(level).is_err(), "{:?}", level);
+    }
+}
+
+#[test]
+fn debug_is_passed_as_env_and_verbose_when_supported() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.gateway_log_level = Some("debug".to_string());
+    });
+    assert!(manager.preview().args.iter().any(|arg| arg == VERBOSE_FLAG));
+    manager.start().unwrap();
+    let env = fake.env();
+    manager.stop().unwrap();
+    assert_eq!(env.get(LOG_LEVEL_ENV).map(String::as_str), Some("debug"));
+
+    // A gateway without the flag still gets the variable
+    let old = FakeGateway::new(&[("flags", "--port --token --host")]);
+    let manager = old.manager_with(free_port(), |config| {
+        config.gateway_log_level = Some("trace".to_string());
+    });
+    assert!(!manager.preview().args.iter().any(|arg| arg == VERBOSE_FLAG));
+    manager.start().unwrap();
+    let env = old.env();
+    manager.stop().unwrap();
+    assert_eq!(env.get(LOG_LEVEL_ENV).map(String::as_str), Some("trace"));
+
+    let invalid = fake.manager_with(free_port(), |config| {
+        config.gateway_log_level = Some("loud".to_string());
+    });
+    let err = invalid.start().expect_err("start should be refused");
+    assert_eq!(
+        err.data().get("field").map(String::as_str),
+        Some("gatewayLogLevel")
+    );
+}
@@ -0,0 +1,143 @@
+//! Searching captured gateway output across memory and rotated log files.
+
+use std::fs;
+
+use simplestclaw_desktop::gateway_logs::{log_files, GatewayLogs, RING_CAPACITY};
+use simplestclaw_desktop::log_search::{search, SearchError, SearchOptions, MEMORY_SOURCE};
+
+/// Logs in `dir` rotating every ~1 KB, so a few hundred lines span files
+fn rotating_logs(dir: &std::path::Path) -> GatewayLogs {
+    GatewayLogs::open(dir).unwrap().with_rotation(1024, 10)
+}
+
+#[test]
+fn matches_span_rotated_files_newest_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = rotating_logs(dir.path());
+    for i in 0..200 {
+        logs.push("stdout", &format!("request {} ok", i));
+        if i % 50 == 0 {
+            logs.push("stderr", &format!("ERROR request {} failed", i));
+        }
+    }
+    assert!(log_files(dir.path()).len() > 3, "fixture did not rotate");
+
+    let result = search(&logs, r"ERROR request \d+", &SearchOptions::default()).unwrap();
+    let texts: Vec<&str> = result.matches.iter().map(|m| m.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "ERROR request 150 failed",
+            "ERROR request 100 failed",
+            "ERROR request 50 failed",
+            "ERROR request 0 failed",
+        ]
+    );
+    assert!(!result.truncated);
+    assert!(result.matches.iter().all(|m| m.stream == "stderr"));
+    assert_eq!(result.matches[1].before, ["request 99 ok", "request 100 ok"]);
+
+    // Everything is still in memory, so each line is only reported once
+    assert!(result.matches.iter().all(|m| m.source == MEMORY_SOURCE));
+}
+
+#[test]
+fn lines_evicted_from_memory_are_found_on_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = GatewayLogs::open(dir.path())
+        .unwrap()
+        .with_rotation(64 * 1024, 10);
+    logs.push("stderr", "ERROR oldest");
+    for i in 0..RING_CAPACITY {
+        logs.push("stdout", &format!("line {}", i));
+    }
+    logs.push("stderr", "ERROR newest");
+
+    let result = search(&logs, "ERROR", &SearchOptions::default()).unwrap();
+    let found: Vec<(&str, &str)> = result
+        .matches
+        .iter()
+        .map(|m| (m.text.as_str(), m.source.as_str()))
+        .collect();
+    assert_eq!(found.len(), 2, "{:?}", found);
+    assert_eq!(found[0], ("ERROR newest", MEMORY_SOURCE));
+    assert_eq!(found[1].0, "ERROR oldest");
+    assert!(found[1].1.starts_with("gateway.log"));
+    assert_eq!(result.matches[1].after, ["line 0", "line 1"]);
+}
+
+#[test]
+fn results_are_capped_and_flagged() {
+    let logs = GatewayLogs::default();
+    for i in 0..20 {
+        logs.push("stdout", &format!("tick {}", i));
+    }
+    let options = SearchOptions {
+        limit: 5,
+        context: 0,
+        ..SearchOptions::default()
+    };
+    let result = search(&logs, "tick", &options).unwrap();
+    assert_eq!(result.matches.len(), 5);
+    assert!(result.truncated);
+    assert_eq!(result.matches[0].text, "tick 19");
+    assert!(result.matches[0].before.is_empty());
+
+    let exact = SearchOptions { limit: 20, ..options };
+    assert!(!search(&logs, "tick", &exact).unwrap().truncated);
+}
+
+#[test]
+fn case_insensitive_and_literal_modes() {
+    let logs = GatewayLogs::default();
+    logs.push("stdout", "Connected to api.anthropic.com");
+    logs.push("stdout", "connected to apiXanthropicXcom");
+
+    let regex = search(&logs, "api.anthropic.com", &SearchOptions::default()).unwrap();
+    assert_eq!(regex.matches.len(), 2);
+
+    let literal = SearchOptions {
+        literal: true,
+        ..SearchOptions::default()
+    };
+    let plain = search(&logs, "api.anthropic.com", &literal).unwrap();
+    assert_eq!(plain.matches.len(), 1);
+    assert_eq!(search(&logs, "(", &literal).unwrap().matches.len(), 0);
+
+    assert_eq!(search(&logs, "CONNECTED", &SearchOptions::default()).unwrap().matches.len(), 0);
+    let insensitive = SearchOptions {
+        case_insensitive: true,
+        ..SearchOptions::default()
+    };
+    assert_eq!(search(&logs, "CONNECTED", &insensitive).unwrap().matches.len(), 2);
+}
+
+#[test]
+fn pathological_patterns_are_rejected() {
+    let logs = GatewayLogs::default();
+    let options = SearchOptions::default();
+
+    let huge = search(&logs, r"((\w{100}){100}){100}", &options).unwrap_err();
+    assert!(matches!(huge, SearchError::TooComplex), "{:?}", huge);
+
+    let nested = format!("{}a{}", "(".repeat(100), ")".repeat(100));
+    let nested = search(&logs, &nested, &options).unwrap_err();
+    assert!(matches!(nested, SearchError::TooComplex), "{:?}", nested);
+
+    let long = "a".repeat(5000);
+    assert!(matches!(search(&logs, &long, &options), Err(SearchError::TooLong)));
+    assert!(matches!(search(&logs, "(", &options), Err(SearchError::Invalid(_))));
+    assert!(matches!(search(&logs, "", &options), Err(SearchError::Empty)));
+}
+
+#[test]
+fn lines_from_older_versions_are_searchable() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("gateway.log.1"), "plain line without fields\n").unwrap();
+    let logs = GatewayLogs::open(dir.path()).unwrap();
+
+    let result = search(&logs, "without", &SearchOptions::default()).unwrap();
+    assert_eq!(result.matches.len(), 1);
+    assert_eq!(result.matches[0].source, "gateway.log.1");
+    assert_eq!(result.matches[0].timestamp, None);
+}
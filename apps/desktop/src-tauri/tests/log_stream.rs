@@ -0,0 +1,350 @@
+//! Filtered log fan-out to subscribed windows, and keeping whatever the
+//! gateway prints bounded.
+
+use std::io::{Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::gateway_logs::{
+    self, GatewayLogs, LogLine, MAX_LINE_BYTES, RING_CAPACITY, RING_MAX_BYTES,
+};
+use simplestclaw_desktop::log_stream::{
+    event_name, CompiledFilter, GatewayLogEvent, LogEntry, LogFilter, LogLevel, LogRateLimiter,
+    BATCH_INTERVAL, LINES_PER_INTERVAL, MAX_BATCH_LINES,
+};
+
+type Delivered = Arc<Mutex<Vec<(String, String, String)>>>;
+
+/// Logs whose subscribers record `(window, event, text)` instead of emitting
+fn recording_logs() -> (GatewayLogs, Delivered) {
+    let logs = GatewayLogs::default();
+    let delivered: Delivered = Arc::default();
+    let sink = delivered.clone();
+    logs.subscribers().set_emitter(Box::new(move |window, event, entry| {
+        sink.lock()
+            .unwrap()
+            .push((window.to_string(), event.to_string(), entry.text.clone()));
+    }));
+    (logs, delivered)
+}
+
+fn entry(stream: &str, text: &str) -> LogEntry {
+    LogEntry::from_line(&LogLine {
+        seq: None,
+        timestamp: Some(0),
+        stream: stream.to_string(),
+        text: text.to_string(),
+    })
+}
+
+fn filter(filter: LogFilter) -> CompiledFilter {
+    CompiledFilter::new(&filter).unwrap()
+}
+
+#[test]
+fn lines_are_classified_by_component_and_level() {
+    let line = entry("stdout", "[gateway] ERROR: upstream closed");
+    assert_eq!(line.component.as_deref(), Some("gateway"));
+    assert_eq!(line.level, LogLevel::Error);
+
+    assert_eq!(entry("stdout", "[ws] warn - slow client").level, LogLevel::Warn);
+    assert_eq!(entry("stdout", "debug: tick").level, LogLevel::Debug);
+    assert_eq!(entry("stdout", "listening").level, LogLevel::Info);
+    assert_eq!(entry("stderr", "(node:42) something odd").level, LogLevel::Warn);
+    assert_eq!(entry("stdout", "no tag").component, None);
+}
+
+#[test]
+fn filters_combine_level_stream_and_component() {
+    let everything = filter(LogFilter::default());
+    assert!(everything.matches(&entry("stdout", "debug: anything")));
+
+    let warnings = filter(LogFilter {
+        min_level: Some(LogLevel::Warn),
+        ..LogFilter::default()
+    });
+    assert!(warnings.matches(&entry("stdout", "ERROR boom")));
+    assert!(warnings.matches(&entry("stdout", "warning: careful")));
+    assert!(!warnings.matches(&entry("stdout", "info: fine")));
+
+    let stderr = filter(LogFilter {
+        stream: Some("stderr".to_string()),
+        ..LogFilter::default()
+    });
+    assert!(stderr.matches(&entry("stderr", "x")));
+    assert!(!stderr.matches(&entry("stdout", "x")));
+
+    let agents = filter(LogFilter {
+        component: Some("^agent(/|$)".to_string()),
+        min_level: Some(LogLevel::Info),
+        ..LogFilter::default()
+    });
+    assert!(agents.matches(&entry("stdout", "[agent/tools] info: ran bash")));
+    assert!(!agents.matches(&entry("stdout", "[agent/tools] debug: ran bash")));
+    assert!(!agents.matches(&entry("stdout", "[agents] info: loaded")));
+    assert!(!agents.matches(&entry("stdout", "untagged info line")));
+
+    let invalid = LogFilter {
+        component: Some("(".to_string()),
+        ..LogFilter::default()
+    };
+    assert!(CompiledFilter::new(&invalid).is_err());
+}
+
+#[test]
+fn entries_go_only_to_matching_subscriptions() {
+    let (logs, delivered) = recording_logs();
+    let subscribers = logs.subscribers();
+    let errors = subscribers.subscribe(
+        "main",
+        filter(LogFilter {
+            min_level: Some(LogLevel::Error),
+            ..LogFilter::default()
+        }),
+    );
+    let all = subscribers.subscribe("logs", filter(LogFilter::default()));
+
+    logs.push("stdout", "[gateway] info: ready");
+    logs.push("stdout", "[gateway] error: crashed");
+
+    let mut delivered = delivered.lock().unwrap().clone();
+    delivered.sort();
+    let mut expected = vec![
+        ("logs".to_string(), event_name(all), "[gateway] info: ready".to_string()),
+        ("logs".to_string(), event_name(all), "[gateway] error: crashed".to_string()),
+        ("main".to_string(), event_name(errors), "[gateway] error: crashed".to_string()),
+    ];
+    expected.sort();
+    assert_eq!(delivered, expected);
+}
+
+#[test]
+fn unsubscribing_and_closing_windows_clean_up() {
+    let (logs, delivered) = recording_logs();
+    let subscribers = logs.subscribers();
+    let first = subscribers.subscribe("main", filter(LogFilter::default()));
+    subscribers.subscribe("main", filter(LogFilter::default()));
+    subscribers.subscribe("logs", filter(LogFilter::default()));
+    assert_eq!(subscribers.len(), 3);
+
+    assert!(subscribers.unsubscribe(first));
+    assert!(!subscribers.unsubscribe(first));
+    assert_eq!(subscribers.len(), 2);
+
+    // The window-destroyed handler removes everything that window had
+    assert_eq!(subscribers.remove_window("main"), 1);
+    assert_eq!(subscribers.remove_window("main"), 0);
+    logs.push("stdout", "after close");
+    assert_eq!(
+        *delivered.lock().unwrap(),
+        [("logs".to_string(), "logs://3".to_string(), "after close".to_string())]
+    );
+
+    assert_eq!(subscribers.remove_window("logs"), 1);
+    assert!(subscribers.is_empty());
+    logs.push("stdout", "nobody listening");
+    assert_eq!(delivered.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn every_line_is_broadcast_without_subscribing() {
+    let logs = GatewayLogs::default();
+    let broadcast: Arc<Mutex<Vec<GatewayLogEvent>>> = Arc::default();
+    let sink = broadcast.clone();
+    logs.subscribers()
+        .set_broadcaster(Box::new(move |event| sink.lock().unwrap().push(event.clone())));
+
+    logs.push("stdout", "ready");
+    logs.push("stderr", "warn: slow");
+    let broadcast = broadcast.lock().unwrap();
+    let lines: Vec<(&str, &str)> = broadcast
+        .iter()
+        .map(|event| (event.stream.as_str(), event.line.as_str()))
+        .collect();
+    assert_eq!(lines, [("stdout", "ready"), ("stderr", "warn: slow")]);
+    assert!(broadcast.iter().all(|event| event.timestamp > 0));
+}
+
+#[test]
+fn pipes_are_read_to_the_end_whatever_they_contain() {
+    let output: &[u8] = b"one\r\ntwo\n\xffbad\n\nlast";
+    let mut lines = Vec::new();
+    gateway_logs::read_lines(output, |line| lines.push(line.to_string()));
+    assert_eq!(lines, ["one", "two", "\u{fffd}bad", "", "last"]);
+}
+
+#[test]
+fn recent_lines_are_numbered_and_bounded() {
+    let logs = GatewayLogs::default();
+    for n in 0..RING_CAPACITY + 5 {
+        logs.push("stdout", &n.to_string());
+    }
+    let all = logs.recent(None);
+    assert_eq!(all.len(), RING_CAPACITY);
+    assert_eq!(all[0].seq, Some(5));
+
+    let newest = logs.recent(Some(2));
+    let seqs: Vec<Option<u64>> = newest.iter().map(|line| line.seq).collect();
+    let last = RING_CAPACITY as u64 + 4;
+    assert_eq!(seqs, [Some(last - 1), Some(last)]);
+    assert_eq!(newest[1].text, last.to_string());
+
+    let json = serde_json::to_value(&newest[1]).unwrap();
+    assert_eq!(json["seq"], last);
+    assert_eq!(json["stream"], "stdout");
+}
+
+/// Bytes that look random, the same every run
+fn noise(len: usize) -> Vec<u8> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn line(text: &str) -> LogLine {
+    LogLine {
+        seq: None,
+        timestamp: Some(0),
+        stream: "stdout".to_string(),
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn a_huge_line_is_cut_as_it_is_read() {
+    // 64 MB without a newline, never in memory at once
+    let huge = std::io::repeat(b'x').take(64 * 1024 * 1024);
+    let output = huge.chain(&b"\nnext\n"[..]);
+    let mut lines = Vec::new();
+    gateway_logs::read_lines(output, |line| lines.push(line.to_string()));
+
+    assert_eq!(lines.len(), 2);
+    let cut = 64 * 1024 * 1024 - MAX_LINE_BYTES;
+    assert_eq!(
+        lines[0],
+        format!("{}{}", "x".repeat(MAX_LINE_BYTES), gateway_logs::truncated_marker(cut))
+    );
+    assert_eq!(lines[1], "next");
+}
+
+#[test]
+fn binary_output_is_read_to_the_end_as_clean_lines() {
+    let logs = Arc::new(GatewayLogs::default());
+    let seen = Arc::new(Mutex::new(0));
+    let counter = seen.clone();
+    logs.capture("stdout", Cursor::new(noise(4 * 1024 * 1024)), move |_| {
+        *counter.lock().unwrap() += 1;
+    })
+    .join()
+    .unwrap();
+
+    let lines = logs.recent(None);
+    assert!(*seen.lock().unwrap() > 0);
+    let longest = MAX_LINE_BYTES * 3 + gateway_logs::truncated_marker(usize::MAX).len();
+    for line in &lines {
+        // Invalid bytes become U+FFFD, up to three bytes for one
+        assert!(line.text.len() <= longest, "{}", line.text.len());
+        assert!(!line.text.chars().any(|c| c.is_control() && c != '\t'));
+    }
+    assert_eq!(logs.active_readers(), 0);
+}
+
+#[test]
+fn escape_sequences_and_control_characters_are_stripped() {
+    let cases = [
+        ("\x1b[1;31mERROR\x1b[0m boom", "ERROR boom"),
+        ("\x1b]0;openclaw\x07ready", "ready"),
+        ("\x1b]8;;https://x.test\x1b\\link\x1b]8;;\x1b\\", "link"),
+        ("\x1b(Bplain", "plain"),
+        ("10%\r50%\r100%", "10%50%100%"),
+        ("\u{9b}2Kcleared", "cleared"),
+        ("keep\ttabs", "keep\ttabs"),
+        // A sequence cut off at the end of the line
+        ("partial \x1b[38;5", "partial "),
+    ];
+    for (raw, clean) in cases {
+        assert_eq!(gateway_logs::strip_control(raw), clean, "{:?}", raw);
+    }
+    assert!(matches!(
+        gateway_logs::strip_control("[ws] ⇄ res ✓"),
+        std::borrow::Cow::Borrowed(_)
+    ));
+
+    let logs = GatewayLogs::default();
+    logs.push("stdout", "\x1b[32m[gateway]\x1b[39m listening");
+    assert_eq!(logs.recent(None)[0].text, "[gateway] listening");
+}
+
+#[test]
+fn long_lines_drop_the_oldest_to_stay_within_the_memory_budget() {
+    let logs = GatewayLogs::default();
+    let long = "y".repeat(MAX_LINE_BYTES);
+    for n in 0..RING_CAPACITY {
+        logs.push("stdout", &format!("{} {}", n, long));
+    }
+    let lines = logs.recent(None);
+    let bytes: usize = lines.iter().map(|line| line.text.len()).sum();
+    assert!(bytes <= RING_MAX_BYTES, "{}", bytes);
+    assert!(lines.len() < RING_CAPACITY);
+    let last = lines.last().unwrap();
+    assert!(last.text.starts_with(&format!("{} ", RING_CAPACITY - 1)));
+}
+
+#[test]
+fn a_flood_is_held_for_one_batch_per_interval() {
+    let mut limiter = LogRateLimiter::default();
+    let start = Instant::now();
+    for n in 0..LINES_PER_INTERVAL {
+        assert!(limiter.offer(&line(&n.to_string()), start), "line {}", n);
+    }
+    for n in 0..MAX_BATCH_LINES + 10 {
+        assert!(!limiter.offer(&line(&format!("held {}", n)), start));
+    }
+    // Not before the interval is over
+    assert_eq!(limiter.take_held(start + BATCH_INTERVAL / 2), None);
+
+    let held = limiter.take_held(start + BATCH_INTERVAL).unwrap();
+    assert_eq!(held.lines.len(), MAX_BATCH_LINES);
+    assert_eq!(held.dropped, 10);
+    assert_eq!(held.lines[0].text, "held 10");
+    assert_eq!(limiter.take_held(start + BATCH_INTERVAL * 2), None);
+
+    // Quiet again, lines go out on their own
+    let later = start + BATCH_INTERVAL * 3;
+    assert!(limiter.offer(&line("calm"), later));
+}
+
+#[test]
+fn every_line_of_a_flood_is_sent_in_far_fewer_events() {
+    let logs = GatewayLogs::default();
+    let singles = Arc::new(Mutex::new(0usize));
+    let batched = Arc::new(Mutex::new((0usize, 0u64, 0usize)));
+    let sink = singles.clone();
+    logs.subscribers()
+        .set_broadcaster(Box::new(move |_| *sink.lock().unwrap() += 1));
+    let sink = batched.clone();
+    logs.subscribers().set_batch_broadcaster(Box::new(move |batch| {
+        let mut batched = sink.lock().unwrap();
+        batched.0 += batch.lines.len();
+        batched.1 += batch.dropped;
+        batched.2 += 1;
+    }));
+
+    let flood = 20_000;
+    for n in 0..flood {
+        logs.push("stdout", &format!("flood {}", n));
+    }
+    std::thread::sleep(BATCH_INTERVAL + Duration::from_millis(50));
+    logs.subscribers().flush();
+
+    let singles = *singles.lock().unwrap();
+    let (lines, dropped, batches) = *batched.lock().unwrap();
+    assert_eq!(singles + lines + dropped as usize, flood);
+    assert!(singles + batches < flood / 10, "{} events", singles + batches);
+}
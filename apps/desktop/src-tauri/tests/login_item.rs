@@ -0,0 +1,43 @@
+//! The entries that register the app to start at login.
+
+use std::fs;
+use std::path::Path;
+
+use simplestclaw_desktop::login_item::{self, LABEL};
+
+#[test]
+fn an_autostart_entry_runs_the_app_until_switched_off() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("autostart").join("simplestclaw.desktop");
+
+    let entry = login_item::desktop_entry(Path::new("/opt/Simplest Claw/$bin/simplestclaw"));
+    assert!(entry.contains("Exec=\"/opt/Simplest Claw/\\$bin/simplestclaw\"\n"));
+    login_item::write_entry(&path, Some(&entry)).unwrap();
+    assert!(login_item::desktop_entry_enabled(
+        &fs::read_to_string(&path).unwrap()
+    ));
+
+    // As GNOME's startup settings switch it off
+    let switched_off = entry.replace(
+        "X-GNOME-Autostart-enabled=true",
+        "X-GNOME-Autostart-enabled=false",
+    );
+    assert!(!login_item::desktop_entry_enabled(&switched_off));
+    assert!(!login_item::desktop_entry_enabled(&format!(
+        "{}Hidden = true\n",
+        entry
+    )));
+
+    login_item::write_entry(&path, None).unwrap();
+    assert!(!path.exists());
+    // Already gone is fine
+    login_item::write_entry(&path, None).unwrap();
+}
+
+#[test]
+fn a_launch_agent_escapes_the_program() {
+    let agent = login_item::launch_agent(Path::new("/Applications/R&D <test>.app"));
+    assert!(agent.contains(&format!("<string>{}</string>", LABEL)));
+    assert!(agent.contains("<string>/Applications/R&amp;D &lt;test&gt;.app</string>"));
+    assert!(agent.contains("<key>RunAtLoad</key>\n    <true/>"));
+}
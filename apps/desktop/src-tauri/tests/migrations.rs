@@ -0,0 +1,78 @@
+//! Upgrading config.json from older versions, one fixture per step, and
+//! leaving one from a newer app alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::keychain::{KeychainError, SecretStore};
+use simplestclaw_desktop::migrations::{version_of, CONFIG_VERSION};
+
+/// Keys stay in the file
+struct NoStore;
+
+impl SecretStore for NoStore {
+    fn get(&self, _account: &str) -> Result<Option<String>, KeychainError> {
+        Ok(None)
+    }
+
+    fn set(&self, _account: &str, _secret: &str) -> Result<(), KeychainError> {
+        Err(KeychainError::Unavailable("no keychain".to_string()))
+    }
+
+    fn delete(&self, _account: &str) -> Result<(), KeychainError> {
+        Ok(())
+    }
+}
+
+/// `fixtures/config/<name>` copied to a config.json in `dir`
+fn fixture(dir: &Path, name: &str) -> PathBuf {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/config")
+        .join(name);
+    let path = dir.join("config.json");
+    fs::copy(fixture, &path).unwrap();
+    path
+}
+
+fn version_on_disk(path: &Path) -> u32 {
+    version_of(&serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap())
+}
+
+#[test]
+fn version_0_keeps_its_settings_and_is_saved_as_1() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(dir.path(), "v0.json");
+    assert_eq!(version_on_disk(&path), 0);
+
+    let config = Config::load_from(&path, &NoStore).unwrap();
+    assert_eq!(config.config_version, CONFIG_VERSION);
+    assert_eq!(
+        config.anthropic_api_key.as_deref(),
+        Some("sk-ant-api03-fixture")
+    );
+    assert_eq!(config.gateway_port, 18800);
+    assert!(!config.auto_start_gateway);
+    assert!(config.keep_running_in_background);
+    assert_eq!(config.gateway_extra_args, ["--verbose"]);
+    assert_eq!(config.blocked_tools, ["exec"]);
+    assert_eq!(version_on_disk(&path), 1);
+}
+
+#[test]
+fn a_config_from_a_newer_app_is_refused_and_left_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(dir.path(), "newer.json");
+    let written = fs::read_to_string(&path).unwrap();
+
+    let err = AppError::from(Config::load_from(&path, &NoStore).unwrap_err());
+    assert_eq!(err.code(), "config_too_new");
+    assert_eq!(err.data()["version"], "999");
+    assert!(err.message().contains("newer version"));
+
+    // Not taken for a corrupt file, nor replaced by a save
+    assert!(Config::default().save_to(&path).is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), written);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
@@ -0,0 +1,49 @@
+//! Pinning the gateway's models from config.
+
+mod support;
+
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::models::{self, MODEL_ENV, SMALL_MODEL_ENV};
+use support::{free_port, FakeGateway};
+
+#[test]
+fn model_names_are_checked_by_shape_only() {
+    for model in [
+        "anthropic/claude-sonnet-4-5",
+        "openrouter/meta-llama/llama-3:free",
+        "gpt-5.1",
+        "some-model-released-tomorrow",
+    ] {
+        assert_eq!(models::validate("defaultModel", model), Ok(()), "{}", model);
+    }
+    for model in ["", "claude sonnet", "model\n", "rm;-rf", &"a".repeat(201)] {
+        let err = models::validate("smallModel", model).expect_err(model);
+        assert_eq!(err.field, "smallModel");
+    }
+}
+
+#[test]
+fn configured_models_reach_the_gateway() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.default_model = Some("anthropic/claude-haiku-4-5".to_string());
+    });
+    manager.start().unwrap();
+    let env = fake.env();
+    manager.stop().unwrap();
+    assert_eq!(
+        env.get(MODEL_ENV).map(String::as_str),
+        Some("anthropic/claude-haiku-4-5")
+    );
+    assert!(!env.contains_key(SMALL_MODEL_ENV));
+
+    let invalid = fake.manager_with(free_port(), |config| {
+        config.small_model = Some("not a model".to_string());
+    });
+    let err = invalid.start().expect_err("start should be refused");
+    assert!(
+        matches!(err, AppError::InvalidSetting { ref field, .. } if field == "smallModel"),
+        "{:?}",
+        err
+    );
+}
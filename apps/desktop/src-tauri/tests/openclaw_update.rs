@@ -0,0 +1,32 @@
+//! Noticing a newer openclaw release in the npm registry.
+
+use simplestclaw_desktop::blocked_tools::GatewayVersion;
+use simplestclaw_desktop::openclaw_update::{is_check_due, is_newer, parse_latest, CHECK_INTERVAL};
+
+#[test]
+fn a_check_is_due_once_a_day() {
+    let day = CHECK_INTERVAL.as_millis() as i64;
+    let last = 1_700_000_000_000;
+    assert!(is_check_due(None, last));
+    assert!(!is_check_due(Some(last), last + day - 1));
+    assert!(is_check_due(Some(last), last + day));
+    // A clock set back doesn't put the next check off
+    assert!(is_check_due(Some(last), last - 1));
+}
+
+#[test]
+fn the_latest_version_is_read_from_the_registry_document() {
+    let body = r#"{"name":"openclaw","version":"2026.3.2","dist":{"tarball":"..."}}"#;
+    assert_eq!(parse_latest(body), Some(GatewayVersion(2026, 3, 2)));
+    assert_eq!(parse_latest(r#"{"name":"openclaw"}"#), None);
+    assert_eq!(parse_latest("<html>rate limited</html>"), None);
+}
+
+#[test]
+fn only_a_newer_release_than_the_installed_one_counts() {
+    let latest = GatewayVersion(2026, 3, 2);
+    assert!(is_newer(Some(GatewayVersion(2026, 2, 1)), latest));
+    assert!(!is_newer(Some(latest), latest));
+    assert!(!is_newer(Some(GatewayVersion(2026, 4, 0)), latest));
+    assert!(!is_newer(None, latest));
+}
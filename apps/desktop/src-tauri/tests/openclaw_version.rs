@@ -0,0 +1,70 @@
+//! Refusing openclaw releases older than the app supports.
+
+mod support;
+
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::openclaw_version::{MIN_OPENCLAW_VERSION, RECOMMENDED_OPENCLAW_VERSION};
+use support::{free_port, port_is_listening, FakeGateway};
+
+#[test]
+fn a_too_old_openclaw_is_not_started() {
+    let fake = FakeGateway::new(&[("version", "openclaw 2025.12.1")]);
+    let port = free_port();
+    let manager = fake.manager(port);
+
+    let info = manager.openclaw_version().unwrap();
+    assert_eq!(info.version.as_deref(), Some("2025.12.1"));
+    assert_eq!(info.path.as_deref(), fake.binary().to_str());
+    assert_eq!(info.minimum, MIN_OPENCLAW_VERSION.to_string());
+    assert_eq!(info.supported, Some(false));
+
+    let err = manager.start().expect_err("start should be refused");
+    assert_eq!(
+        err,
+        AppError::OpenclawTooOld {
+            version: "2025.12.1".to_string(),
+            required: MIN_OPENCLAW_VERSION.to_string(),
+        }
+    );
+    assert!(!port_is_listening(port));
+    assert_eq!(
+        manager.metrics().snapshot().last_failure.unwrap().code,
+        "openclaw_version"
+    );
+}
+
+#[test]
+fn forks_can_skip_the_check() {
+    let fake = FakeGateway::new(&[("version", "myclaw 1.4.0")]);
+    let port = free_port();
+    let manager = fake.manager_with(port, |config| {
+        config.skip_openclaw_version_check = true;
+    });
+
+    let info = manager.openclaw_version().unwrap();
+    assert_eq!(info.supported, Some(false));
+    assert!(info.check_skipped);
+    manager.start().unwrap();
+    assert!(port_is_listening(port));
+    manager.stop().unwrap();
+}
+
+#[test]
+fn an_openclaw_older_than_recommended_starts_with_a_warning() {
+    let outdated = |version: &str, skip_check: bool| {
+        let fake = FakeGateway::new(&[("version", version)]);
+        let manager = fake.manager_with(free_port(), |config| {
+            config.skip_openclaw_version_check = skip_check;
+        });
+        let result = manager.start().expect("start failed");
+        manager.stop().unwrap();
+        result.warnings.into_iter().find(|w| w.code == "openclaw_outdated")
+    };
+
+    let warning = outdated("openclaw 2026.1.25", false).expect("no openclaw_outdated");
+    assert!(warning.message.contains(&RECOMMENDED_OPENCLAW_VERSION.to_string()));
+    assert!(outdated(&format!("openclaw {}", MIN_OPENCLAW_VERSION), false).is_some());
+    assert!(outdated(&format!("openclaw {}", RECOMMENDED_OPENCLAW_VERSION), false).is_none());
+    // Below the minimum it is the version check that says so
+    assert!(outdated("openclaw 2025.12.1", true).is_none());
+}
@@ -0,0 +1,106 @@
+//! Forcing settings from the environment without them reaching
+//! config.json.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::config::{Config, ConfigView};
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::overrides::{self, AUTO_START_ENV, GATEWAY_PORT_ENV, OPENCLAW_PATH_ENV};
+use simplestclaw_desktop::paths::CONFIG_DIR_ENV;
+
+fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |name| {
+        vars.iter()
+            .find(|(var, _)| *var == name)
+            .map(|(_, value)| value.to_string())
+    }
+}
+
+#[test]
+fn overrides_apply_but_are_not_saved() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let mut config = Config {
+        gateway_port: 18800,
+        ..Config::default()
+    };
+    config.save_to(&path).unwrap();
+
+    overrides::apply_from(
+        &mut config,
+        env(&[
+            (GATEWAY_PORT_ENV, "19000"),
+            (AUTO_START_ENV, "off"),
+            (OPENCLAW_PATH_ENV, "/opt/openclaw/bin/openclaw"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(config.gateway_port, 19000);
+    assert!(!config.auto_start_gateway);
+    assert_eq!(
+        config.openclaw_path.as_deref(),
+        Some(Path::new("/opt/openclaw/bin/openclaw"))
+    );
+    let view = serde_json::to_value(ConfigView::new(config.clone(), None)).unwrap();
+    assert_eq!(
+        view["overridden"],
+        serde_json::json!(["gatewayPort", "autoStartGateway", "openclawPath"])
+    );
+
+    // Changed by hand since, so saved
+    config.auto_start_gateway = true;
+    config.locale = Some("de-DE".to_string());
+    config.save_to(&path).unwrap();
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved["gatewayPort"], 18800);
+    assert_eq!(saved["autoStartGateway"], true);
+    assert_eq!(saved["openclawPath"], serde_json::Value::Null);
+    assert_eq!(saved["locale"], "de-DE");
+}
+
+#[test]
+fn invalid_overrides_fail_the_load() {
+    for (variable, value) in [
+        (GATEWAY_PORT_ENV, "0"),
+        (GATEWAY_PORT_ENV, "http"),
+        (GATEWAY_PORT_ENV, "70000"),
+        (AUTO_START_ENV, "maybe"),
+    ] {
+        let mut config = Config::default();
+        let err = overrides::apply_from(&mut config, env(&[(variable, value)])).unwrap_err();
+        let err = AppError::from(err);
+        assert_eq!(err.code(), "invalid_config_override");
+        assert_eq!(err.data()["variable"], variable);
+        assert_eq!(err.data()["value"], value);
+    }
+
+    // Empty is unset
+    let mut config = Config::default();
+    overrides::apply_from(&mut config, env(&[(GATEWAY_PORT_ENV, "")])).unwrap();
+    assert!(config.overrides.fields().is_empty());
+}
+
+#[test]
+fn the_config_dir_can_be_moved() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_dir: PathBuf = dir.path().join("kiosk");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("config.json"),
+        r#"{ "gatewayPort": 18800, "autoStartGateway": true }"#,
+    )
+    .unwrap();
+
+    // The only test here that touches the process environment
+    std::env::set_var(CONFIG_DIR_ENV, &config_dir);
+    std::env::set_var(AUTO_START_ENV, "false");
+    let config = Config::load().unwrap();
+    std::env::remove_var(CONFIG_DIR_ENV);
+    std::env::remove_var(AUTO_START_ENV);
+
+    assert_eq!(config.gateway_port, 18800);
+    assert!(!config.auto_start_gateway);
+    assert_eq!(config.overrides.fields(), ["autoStartGateway"]);
+}
@@ -0,0 +1,134 @@
+//! App directory layout and legacy migration over a temporary root.
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::paths::{
+    available_space, ensure_space, estimate_write_size, volume_space, AppPaths,
+    InsufficientSpace, VolumeSpace,
+};
+use support::FakeDisk;
+
+#[test]
+fn layout_under_root_is_created_lazily() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+
+    assert!(paths.logs_dir.starts_with(&paths.data_dir));
+    assert!(paths.runtime_dir.starts_with(&paths.data_dir));
+    assert!(!paths.config_dir.exists());
+
+    // Only saving config.json creates its dir
+    assert_eq!(paths.config_file(), paths.config_dir.join("config.json"));
+    assert!(!paths.config_dir.exists());
+    paths.gateway_pid_file().unwrap();
+    assert!(paths.config_dir.is_dir());
+    assert!(!paths.logs_dir.exists());
+}
+
+#[test]
+fn roots_skip_nested_and_duplicate_dirs() {
+    let root = tempfile::tempdir().unwrap();
+    let mut paths = AppPaths::from_root(root.path());
+    assert_eq!(
+        paths.roots(),
+        vec![paths.config_dir.clone(), paths.data_dir.clone(), paths.cache_dir.clone()]
+    );
+
+    // Config and data share a directory on macOS
+    paths.config_dir = paths.data_dir.clone();
+    assert_eq!(paths.roots(), vec![paths.data_dir.clone(), paths.cache_dir.clone()]);
+}
+
+#[test]
+fn legacy_activity_log_is_moved_into_logs_dir() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+    fs::create_dir_all(&paths.data_dir).unwrap();
+    let legacy = paths.data_dir.join("activity.json");
+    fs::write(&legacy, r#"{"entries":[]}"#).unwrap();
+
+    let moved = paths.migrate_legacy_layout().unwrap();
+    let target = paths.logs_dir.join("activity.json");
+    assert_eq!(moved, vec![(legacy.clone(), target.clone())]);
+    assert!(!legacy.exists());
+    assert_eq!(fs::read_to_string(&target).unwrap(), r#"{"entries":[]}"#);
+
+    // Running again is a no-op
+    assert!(paths.migrate_legacy_layout().unwrap().is_empty());
+}
+
+#[test]
+fn migration_never_overwrites_existing_files() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+    fs::create_dir_all(&paths.logs_dir).unwrap();
+    fs::write(paths.data_dir.join("activity.json"), "old").unwrap();
+    fs::write(paths.logs_dir.join("activity.json"), "new").unwrap();
+
+    assert!(paths.migrate_legacy_layout().unwrap().is_empty());
+    assert_eq!(fs::read_to_string(paths.logs_dir.join("activity.json")).unwrap(), "new");
+    assert!(paths.data_dir.join("activity.json").exists());
+}
+
+#[test]
+fn free_space_comes_from_the_closest_existing_parent() {
+    let root = tempfile::tempdir().unwrap();
+    let mounted = root.path().join("mnt");
+    fs::create_dir_all(&mounted).unwrap();
+    let disk = FakeDisk(vec![
+        (root.path().to_path_buf(), 5 << 30),
+        (mounted.clone(), 20 << 20),
+    ]);
+
+    let not_yet = root.path().join("data/gateway-state");
+    assert_eq!(available_space(&disk, &not_yet).unwrap(), 5 << 30);
+    assert_eq!(available_space(&disk, &mounted.join("a/b")).unwrap(), 20 << 20);
+    assert!(available_space(&FakeDisk(Vec::new()), &not_yet).is_err());
+
+    // Nested directories count once, unknown space is reported as such
+    let volumes = volume_space(&disk, &[root.path(), &not_yet, Path::new("/elsewhere")]);
+    assert_eq!(
+        volumes,
+        [
+            VolumeSpace {
+                path: root.path().to_path_buf(),
+                available_bytes: Some(5 << 30),
+            },
+            VolumeSpace {
+                path: PathBuf::from("/elsewhere"),
+                available_bytes: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn writes_are_checked_against_their_estimated_size() {
+    let root = tempfile::tempdir().unwrap();
+    let source = root.path().join("session");
+    fs::create_dir_all(source.join("nested")).unwrap();
+    fs::write(source.join("a.jsonl"), vec![0u8; 4000]).unwrap();
+    fs::write(source.join("nested/b.json"), vec![0u8; 6000]).unwrap();
+    let loose = root.path().join("loose.json");
+    fs::write(&loose, vec![0u8; 10_000]).unwrap();
+
+    let estimate = estimate_write_size(&[source, loose, root.path().join("missing")]);
+    assert_eq!(estimate, 20_000 + 2_000 + 1024 * 1024);
+
+    let dest = root.path().join("export.zip");
+    let disk = FakeDisk(vec![(root.path().to_path_buf(), estimate - 1)]);
+    assert_eq!(
+        ensure_space(&disk, &dest, estimate),
+        Err(InsufficientSpace {
+            path: dest.clone(),
+            available: estimate - 1,
+            required: estimate,
+        })
+    );
+    assert!(ensure_space(&disk, &dest, estimate - 1).is_ok());
+    // Unknown free space doesn't block the write
+    assert!(ensure_space(&FakeDisk(Vec::new()), &dest, estimate).is_ok());
+}
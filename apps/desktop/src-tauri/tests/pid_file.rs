@@ -0,0 +1,81 @@
+//! The record of the running gateway and what the next launch does with a
+//! gateway a crashed app left behind.
+
+mod support;
+
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::pid_file::{self, GatewayRecord, Leftover, PidFile};
+use support::{free_port, port_is_listening, FakeGateway};
+
+fn closes(port: u16) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while port_is_listening(port) {
+        if Instant::now() > deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    true
+}
+
+#[test]
+fn a_gateway_left_running_is_killed_at_the_next_launch() {
+    let dir = tempfile::tempdir().unwrap();
+    let pid_file = PidFile::new(dir.path().join(pid_file::FILE_NAME));
+    let fake = FakeGateway::serving();
+    let port = free_port();
+
+    // A clean stop leaves nothing behind
+    let manager = fake.manager(port).with_pid_file(pid_file.clone());
+    manager.start().expect("start failed");
+    manager.stop().unwrap();
+    assert_eq!(pid_file.read(), None);
+
+    let manager = fake.manager(port).with_pid_file(pid_file.clone());
+    let result = manager.start().expect("start failed");
+    let record = pid_file.read().expect("no record");
+    assert_eq!(Some(record.pid), manager.gateway_pid());
+    assert_eq!(record.port, port);
+    assert_eq!(record.token, result.info.token);
+
+    // The app goes away without stopping it
+    std::mem::forget(manager);
+    assert!(port_is_listening(port));
+    assert_eq!(pid_file::reap(&pid_file), Leftover::Killed(record));
+    assert!(closes(port));
+    assert!(!pid_file.path().exists());
+    assert_eq!(pid_file::reap(&pid_file), Leftover::None);
+}
+
+#[test]
+fn a_reused_pid_is_left_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let pid_file = PidFile::new(dir.path().join(pid_file::FILE_NAME));
+    // This test now has the pid, and never had the token
+    let record = GatewayRecord {
+        pid: std::process::id(),
+        port: free_port(),
+        token: "sclw-from-a-previous-run".to_string(),
+        started_at: 0,
+    };
+    pid_file.write(&record).unwrap();
+
+    assert_eq!(pid_file::reap(&pid_file), Leftover::Gone(record));
+    assert!(!pid_file.path().exists());
+}
+
+#[test]
+fn a_gateway_the_start_gave_up_on_leaves_no_record() {
+    let dir = tempfile::tempdir().unwrap();
+    let pid_file = PidFile::new(dir.path().join(pid_file::FILE_NAME));
+    // Listening with a token it then rejects, and exiting at once
+    for conf in [("auth", "reject"), ("mode", "exit")] {
+        let fake = FakeGateway::new(&[conf]);
+        let manager = fake.manager(free_port()).with_pid_file(pid_file.clone());
+        manager.start().expect_err("start should fail");
+        assert_eq!(pid_file.read(), None, "{:?}", conf);
+        assert!(!manager.status().running);
+        assert_eq!(manager.gateway_pid(), None);
+    }
+}
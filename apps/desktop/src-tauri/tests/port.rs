@@ -0,0 +1,110 @@
+//! Explanations for a gateway port that differs from config.json, and
+//! ports to move to when the configured one can't be used.
+
+use std::net::{IpAddr, Ipv4Addr, TcpListener};
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::port::{
+    self, parse_listening_port, port_mismatch, PortAvailability, PortSource, DEFAULT_PORT,
+    SUGGEST_RANGE,
+};
+
+const LOOPBACK: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+fn assert_suggestion_is_usable(suggested: u16, avoid: u16) {
+    assert_ne!(suggested, avoid);
+    assert!(suggested.abs_diff(DEFAULT_PORT) <= SUGGEST_RANGE, "{}", suggested);
+    assert_eq!(port::known_service(suggested), None);
+    assert_eq!(port::availability(LOOPBACK, suggested), PortAvailability::Free);
+}
+
+#[test]
+fn matching_ports_are_not_a_mismatch() {
+    for source in [
+        PortSource::Configured,
+        PortSource::AutoSelected,
+        PortSource::GatewayOverride,
+        PortSource::Adopted,
+    ] {
+        assert_eq!(port_mismatch(18789, 18789, source), None);
+    }
+}
+
+#[test]
+fn each_source_explains_the_difference() {
+    let changed = port_mismatch(19000, 18789, PortSource::Configured).unwrap();
+    assert!(changed.message.contains("takes effect after a restart"));
+    assert!(changed.restart_resolves);
+
+    let auto = port_mismatch(18789, 18790, PortSource::AutoSelected).unwrap();
+    assert!(auto.message.contains("Port 18789 was in use"));
+    assert!(!auto.restart_resolves);
+
+    let overridden = port_mismatch(18789, 20000, PortSource::GatewayOverride).unwrap();
+    assert!(overridden.message.contains("own configuration overrides"));
+    assert!(!overridden.restart_resolves);
+
+    let adopted = port_mismatch(18789, 18800, PortSource::Adopted).unwrap();
+    assert!(adopted.message.contains("already running gateway on port 18800"));
+    assert!(adopted.restart_resolves);
+
+    for mismatch in [changed, auto, overridden, adopted] {
+        assert!(mismatch.message.contains("18789"), "{}", mismatch.message);
+    }
+}
+
+#[test]
+fn listening_port_is_parsed_from_startup_lines() {
+    assert_eq!(
+        parse_listening_port("[gateway] listening on ws://127.0.0.1:18789"),
+        Some(18789)
+    );
+    assert_eq!(
+        parse_listening_port("[gateway] listening on ws://[::1]:20000/ (PID 42)"),
+        Some(20000)
+    );
+    assert_eq!(parse_listening_port("gateway listening on localhost:3000"), Some(3000));
+    assert_eq!(parse_listening_port("[gateway] starting"), None);
+    assert_eq!(parse_listening_port("[gateway] listening on ws://localhost"), None);
+}
+
+#[test]
+fn a_privileged_port_is_not_permitted_and_a_free_one_is_suggested() {
+    // Whoever runs the tests, root included
+    assert_eq!(port::availability(LOOPBACK, 443), PortAvailability::NotPermitted);
+    assert_eq!(port::check_usable(LOOPBACK, 443).unwrap_err().code(), "invalid_port");
+
+    let err = port::not_permitted(LOOPBACK, 443);
+    assert_eq!(err.code(), "port_not_permitted");
+    assert_eq!(err.data()["port"], "443");
+    assert_suggestion_is_usable(err.data()["suggested"].parse().unwrap(), 443);
+
+    // A config.json saved with one is caught with the suggestion too
+    let config = Config {
+        gateway_port: 443,
+        ..Config::default()
+    };
+    assert_eq!(config.validate().unwrap_err().code(), "port_not_permitted");
+}
+
+#[test]
+fn suggestions_skip_the_port_to_avoid_and_taken_ports() {
+    let suggested = port::suggest(LOOPBACK, DEFAULT_PORT).expect("no free port");
+    assert_suggestion_is_usable(suggested, DEFAULT_PORT);
+
+    let held = TcpListener::bind((LOOPBACK, suggested)).unwrap();
+    assert_eq!(port::availability(LOOPBACK, suggested), PortAvailability::InUse);
+    assert_eq!(port::check_usable(LOOPBACK, suggested).unwrap_err().code(), "port_in_use");
+    let next = port::suggest(LOOPBACK, DEFAULT_PORT).expect("no free port");
+    assert_ne!(next, suggested);
+    assert_suggestion_is_usable(next, DEFAULT_PORT);
+    drop(held);
+}
+
+#[test]
+fn ports_of_common_services_are_flagged() {
+    let warning = port::conflict_warning(5432).expect("no warning");
+    assert!(warning.contains("PostgreSQL"), "{}", warning);
+    assert_eq!(port::conflict_warning(DEFAULT_PORT), None);
+    assert_eq!(port::conflict_warning(18800), None);
+}
@@ -0,0 +1,171 @@
+//! Process launcher helpers: the Windows stop-path decision logic,
+//! reduced-priority spawning and taking down the Unix process group. The Win32 calls themselves are covered by the
+//! manual tests in `apps/desktop/docs/windows-process-termination.md`.
+
+use simplestclaw_desktop::process::{windows_stop_steps, WindowsStopStep};
+
+#[test]
+fn job_is_used_when_available() {
+    assert_eq!(windows_stop_steps(true, false), vec![WindowsStopStep::TerminateJob]);
+}
+
+#[test]
+fn taskkill_is_used_without_a_job() {
+    assert_eq!(windows_stop_steps(false, false), vec![WindowsStopStep::TaskKill]);
+}
+
+#[test]
+fn ctrl_break_comes_first_when_sharing_a_console() {
+    assert_eq!(
+        windows_stop_steps(true, true),
+        vec![WindowsStopStep::CtrlBreak, WindowsStopStep::TerminateJob]
+    );
+    assert_eq!(
+        windows_stop_steps(false, true),
+        vec![WindowsStopStep::CtrlBreak, WindowsStopStep::TaskKill]
+    );
+}
+
+#[test]
+fn low_priority_never_raises_priority() {
+    use simplestclaw_desktop::process::{low_priority_nice, LOW_PRIORITY_NICE};
+
+    assert_eq!(low_priority_nice(0), LOW_PRIORITY_NICE);
+    assert_eq!(low_priority_nice(-5), LOW_PRIORITY_NICE);
+    assert_eq!(low_priority_nice(15), 15);
+}
+
+#[cfg(unix)]
+mod spawn_priority {
+    use simplestclaw_desktop::process::{
+        process_priority, LaunchSpec, ProcessLauncher, SystemLauncher,
+    };
+
+    fn spawn_sleep(low_priority: bool) -> Box<dyn simplestclaw_desktop::process::GatewayProcess> {
+        let spec = LaunchSpec {
+            program: "sleep".to_string(),
+            args: vec!["5".to_string()],
+            env: Vec::new(),
+            clean_env: false,
+            low_priority,
+            cwd: None,
+            user: None,
+        };
+        SystemLauncher.spawn(&spec).expect("failed to spawn sleep")
+    }
+
+    #[test]
+    fn low_priority_child_is_niced() {
+        let mut child = spawn_sleep(true);
+        let priority = child.priority().expect("priority unavailable");
+        child.kill().unwrap();
+
+        assert!(priority.lowered, "{:?}", priority);
+    }
+
+    #[test]
+    fn normal_child_inherits_our_priority() {
+        let mut child = spawn_sleep(false);
+        let priority = child.priority().expect("priority unavailable");
+        child.kill().unwrap();
+
+        assert_eq!(Some(priority), process_priority(std::process::id()));
+    }
+}
+
+#[cfg(unix)]
+mod process_group {
+    use std::io::{BufRead, BufReader};
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    use simplestclaw_desktop::process::{
+        GatewayProcess, LaunchSpec, ProcessLauncher, StopKind, SystemLauncher,
+    };
+
+    /// A shell that forks a long sleep, like openclaw its workers, and the
+    /// sleep's pid. It waits for the sleep unless told to `exit`.
+    fn spawn_with_grandchild(exit: bool) -> (Box<dyn GatewayProcess>, u32) {
+        let script = if exit { "sleep 60 & echo $!" } else { "sleep 60 & echo $!; wait" };
+        spawn_printing_pid(script)
+    }
+
+    /// `sh -c script`, and the pid it prints first
+    fn spawn_printing_pid(script: &str) -> (Box<dyn GatewayProcess>, u32) {
+        let spec = LaunchSpec {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: Vec::new(),
+            clean_env: false,
+            low_priority: false,
+            cwd: None,
+            user: None,
+        };
+        let mut child = SystemLauncher.spawn(&spec).expect("failed to spawn sh");
+        let mut line = String::new();
+        BufReader::new(child.take_stdout().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        (child, line.trim().parse().expect("no grandchild pid"))
+    }
+
+    /// Whether `pid` runs; a zombie waiting for init to reap it doesn't
+    fn running(pid: u32) -> bool {
+        let output = Command::new("ps")
+            .args(["-o", "stat=", "-p", &pid.to_string()])
+            .output()
+            .unwrap();
+        let stat = String::from_utf8_lossy(&output.stdout);
+        !(stat.trim().is_empty() || stat.trim().starts_with('Z'))
+    }
+
+    /// Whether `pid` is gone within five seconds
+    fn exits(pid: u32) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if !running(pid) {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        false
+    }
+
+    #[test]
+    fn stop_takes_down_the_grandchildren() {
+        let (mut child, grandchild) = spawn_with_grandchild(false);
+        child.stop(Duration::from_secs(1)).unwrap();
+        assert!(exits(grandchild), "grandchild {} still running", grandchild);
+    }
+
+    #[test]
+    fn a_dropped_process_takes_its_group_with_it() {
+        let (child, grandchild) = spawn_with_grandchild(false);
+        drop(child);
+        assert!(exits(grandchild), "grandchild {} still running", grandchild);
+    }
+
+    #[test]
+    fn a_reaped_process_no_longer_signals_its_group() {
+        let (mut child, grandchild) = spawn_with_grandchild(true);
+        while child.try_wait().unwrap().is_none() {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        // The group id may be another's by now, so the drop leaves it be
+        drop(child);
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(running(grandchild), "grandchild {} was killed", grandchild);
+        let _ = Command::new("kill").args(["-9", &grandchild.to_string()]).status();
+    }
+
+    #[test]
+    fn a_process_reaped_during_a_stop_no_longer_signals_its_group() {
+        // The shell exits on SIGTERM, the worker it forked ignores it
+        let (mut child, worker) = spawn_printing_pid("(trap '' TERM; sleep 60) & echo $!; wait");
+        let kind = child.stop(Duration::from_secs(2)).unwrap();
+        assert_eq!(kind, StopKind::Graceful);
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(running(worker), "worker {} was killed", worker);
+        let _ = Command::new("kill").args(["-9", &worker.to_string()]).status();
+    }
+}
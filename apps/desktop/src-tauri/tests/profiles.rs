@@ -0,0 +1,129 @@
+//! Switching between named sets of key, port and arguments, starting from
+//! a config written before there were profiles.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::keychain::{
+    profile_key_account, KeychainError, SecretStore, API_KEY_ACCOUNT,
+};
+use simplestclaw_desktop::profiles::{self, ProfileError, DEFAULT_PROFILE};
+
+#[derive(Default)]
+struct MemoryStore(Mutex<HashMap<String, String>>);
+
+impl SecretStore for MemoryStore {
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+        Ok(self.0.lock().unwrap().get(account).cloned())
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<(), KeychainError> {
+        self.0.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+impl MemoryStore {
+    fn key(&self, account: &str) -> Option<String> {
+        self.get(account).unwrap()
+    }
+}
+
+#[test]
+fn switching_swaps_key_port_and_arguments() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let store = MemoryStore::default();
+    // As written before profiles existed
+    fs::write(
+        &path,
+        r#"{ "anthropicApiKey": "sk-ant-personal", "gatewayPort": 18789 }"#,
+    )
+    .unwrap();
+
+    let mut config = Config::load_from(&path, &store).unwrap();
+    let names: Vec<String> = profiles::list(&config)
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(names, [DEFAULT_PROFILE]);
+
+    profiles::create(
+        &mut config,
+        "work",
+        Some(18800),
+        Some(vec!["--verbose".to_string()]),
+        Some("sk-ant-work".to_string()),
+        &store,
+    )
+    .unwrap();
+    assert_eq!(
+        store.key(&profile_key_account("work")).as_deref(),
+        Some("sk-ant-work")
+    );
+
+    profiles::switch(&mut config, "work", &store).unwrap();
+    config.save_to(&path).unwrap();
+    let config = Config::load_from(&path, &store).unwrap();
+    assert_eq!(config.active_profile.as_deref(), Some("work"));
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-work"));
+    assert_eq!(config.gateway_port, 18800);
+    assert_eq!(config.gateway_extra_args, ["--verbose"]);
+    assert_eq!(store.key(API_KEY_ACCOUNT).as_deref(), Some("sk-ant-work"));
+    assert_eq!(
+        store.key(&profile_key_account(DEFAULT_PROFILE)).as_deref(),
+        Some("sk-ant-personal")
+    );
+    assert_eq!(store.key(&profile_key_account("work")), None);
+    assert!(!fs::read_to_string(&path).unwrap().contains("sk-ant-"));
+
+    let mut config = config;
+    profiles::switch(&mut config, DEFAULT_PROFILE, &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-personal"));
+    assert_eq!(config.gateway_port, 18789);
+    assert!(config.gateway_extra_args.is_empty());
+    let listed: Vec<(String, bool, bool)> = profiles::list(&config)
+        .into_iter()
+        .map(|p| (p.name, p.active, p.has_api_key))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            (DEFAULT_PROFILE.to_string(), true, true),
+            ("work".to_string(), false, true),
+        ]
+    );
+}
+
+#[test]
+fn the_active_profile_stays() {
+    let store = MemoryStore::default();
+    let mut config = Config::default();
+    profiles::create(&mut config, "work", None, None, None, &store).unwrap();
+
+    assert_eq!(
+        profiles::delete(&mut config, DEFAULT_PROFILE, &store),
+        Err(ProfileError::Active(DEFAULT_PROFILE.to_string()))
+    );
+    assert_eq!(
+        profiles::switch(&mut config, "home", &store),
+        Err(ProfileError::Unknown("home".to_string()))
+    );
+    let exists = profiles::create(&mut config, DEFAULT_PROFILE, None, None, None, &store);
+    assert_eq!(exists.unwrap_err().code(), "profile_exists");
+    let invalid = profiles::create(&mut config, "my work", None, None, None, &store);
+    assert_eq!(invalid.unwrap_err().code(), "invalid_profile_name");
+
+    profiles::delete(&mut config, "work", &store).unwrap();
+    assert!(config.profiles.is_empty());
+}
@@ -0,0 +1,184 @@
+//! Prometheus text rendering, and serving it over the HTTP listener.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use simplestclaw_desktop::heartbeat::GatewayHealth;
+use simplestclaw_desktop::http::{self, HttpResponse};
+use simplestclaw_desktop::metrics::MetricsSnapshot;
+use simplestclaw_desktop::prometheus::{render, CONTENT_TYPE, METRICS_PATH};
+use simplestclaw_desktop::sidecar::{GatewayInfo, GatewayStatus};
+
+const NOW_MS: i64 = 1_700_000_100_000;
+
+fn metrics() -> MetricsSnapshot {
+    MetricsSnapshot {
+        started_at: 1_700_000_000_250,
+        uptime_secs: 99,
+        start_attempts: 3,
+        start_failures: 2,
+        start_failures_by_reason: BTreeMap::from([
+            ("port_in_use".to_string(), 1),
+            ("timeout".to_string(), 1),
+        ]),
+        crashes: 1,
+        auto_restarts: 1,
+        config_save_failures: 0,
+        command_errors: BTreeMap::from([("invalid_port".to_string(), 4)]),
+        last_failure: None,
+        last_auth_self_test: None,
+    }
+}
+
+fn status(running: bool) -> GatewayStatus {
+    GatewayStatus {
+        running,
+        external: false,
+        managed: true,
+        info: running.then(|| GatewayInfo {
+            url: "ws://localhost:18789?token=secret".to_string(),
+            port: Some(18789),
+            socket_path: None,
+            token: "secret".to_string(),
+            lan_exposed: false,
+            workspace: None,
+            generation: 1,
+        }),
+        pid: running.then_some(4242),
+        executable: None,
+        priority: None,
+        effective_uid: None,
+        sandbox: None,
+        blocked_tools: Vec::new(),
+        limits: Default::default(),
+        configured_port: Some(18789),
+        effective_port: running.then_some(18789),
+        port_mismatch: None,
+        health: if running {
+            GatewayHealth::Healthy
+        } else {
+            GatewayHealth::Stopped
+        },
+        accepting_connections: running.then_some(true),
+        last_response_at: running.then_some(1_700_000_099_500),
+        started_at: running.then_some(1_700_000_040_000),
+        uptime_secs: running.then_some(59),
+        last_exit_code: None,
+        last_exit_signal: None,
+        last_exit_at: None,
+        last_crash: None,
+        crash_restarts: 0,
+        paused: false,
+        starting: false,
+        generation: 1,
+        error: None,
+    }
+}
+
+#[test]
+fn a_running_gateway_renders_every_family() {
+    let expected = "\
+# HELP simplestclaw_app_start_time_seconds Unix time the app started.
+# TYPE simplestclaw_app_start_time_seconds gauge
+simplestclaw_app_start_time_seconds 1700000000.250
+# HELP simplestclaw_app_uptime_seconds Seconds since the app started.
+# TYPE simplestclaw_app_uptime_seconds gauge
+simplestclaw_app_uptime_seconds 99
+# HELP simplestclaw_gateway_start_attempts_total Gateway starts attempted.
+# TYPE simplestclaw_gateway_start_attempts_total counter
+simplestclaw_gateway_start_attempts_total 3
+# HELP simplestclaw_gateway_start_failures_total Gateway starts that failed, by reason.
+# TYPE simplestclaw_gateway_start_failures_total counter
+simplestclaw_gateway_start_failures_total{reason=\"port_in_use\"} 1
+simplestclaw_gateway_start_failures_total{reason=\"timeout\"} 1
+# HELP simplestclaw_gateway_crashes_total Gateways that exited on their own after starting.
+# TYPE simplestclaw_gateway_crashes_total counter
+simplestclaw_gateway_crashes_total 1
+# HELP simplestclaw_gateway_restarts_total Automatic gateway restarts.
+# TYPE simplestclaw_gateway_restarts_total counter
+simplestclaw_gateway_restarts_total 1
+# HELP simplestclaw_config_save_failures_total Failed writes of config.json.
+# TYPE simplestclaw_config_save_failures_total counter
+simplestclaw_config_save_failures_total 0
+# HELP simplestclaw_command_errors_total Commands that returned an error, by error code.
+# TYPE simplestclaw_command_errors_total counter
+simplestclaw_command_errors_total{code=\"invalid_port\"} 4
+# HELP simplestclaw_gateway_up Whether the gateway is running.
+# TYPE simplestclaw_gateway_up gauge
+simplestclaw_gateway_up 1
+# HELP simplestclaw_gateway_uptime_seconds Seconds since the running gateway became ready.
+# TYPE simplestclaw_gateway_uptime_seconds gauge
+simplestclaw_gateway_uptime_seconds 60
+# HELP simplestclaw_gateway_health Gateway health, 1 for the current state.
+# TYPE simplestclaw_gateway_health gauge
+simplestclaw_gateway_health{state=\"stopped\"} 0
+simplestclaw_gateway_health{state=\"starting\"} 0
+simplestclaw_gateway_health{state=\"idle\"} 0
+simplestclaw_gateway_health{state=\"healthy\"} 1
+simplestclaw_gateway_health{state=\"unresponsive\"} 0
+# HELP simplestclaw_gateway_port_mismatch Whether the gateway serves on another port than configured.
+# TYPE simplestclaw_gateway_port_mismatch gauge
+simplestclaw_gateway_port_mismatch 0
+# HELP simplestclaw_gateway_last_response_timestamp_seconds Unix time of the last response the frontend got.
+# TYPE simplestclaw_gateway_last_response_timestamp_seconds gauge
+simplestclaw_gateway_last_response_timestamp_seconds 1700000099.500
+";
+    assert_eq!(render(&metrics(), &status(true), NOW_MS), expected);
+}
+
+#[test]
+fn a_stopped_gateway_and_label_escaping() {
+    let mut metrics = metrics();
+    metrics.start_failures_by_reason = BTreeMap::new();
+    metrics.command_errors = BTreeMap::from([("odd\"code\\\n".to_string(), 1)]);
+    let text = render(&metrics, &status(false), NOW_MS);
+
+    assert!(text.contains("simplestclaw_gateway_up 0\n"));
+    assert!(text.contains("simplestclaw_gateway_uptime_seconds 0\n"));
+    assert!(text.contains("simplestclaw_gateway_health{state=\"stopped\"} 1\n"));
+    assert!(!text.contains("last_response_timestamp"));
+    // The family is still declared without samples
+    assert!(text.contains("# TYPE simplestclaw_gateway_start_failures_total counter\n# HELP"));
+    assert!(text.contains("simplestclaw_command_errors_total{code=\"odd\\\"code\\\\\\n\"} 1\n"));
+}
+
+#[test]
+fn secrets_are_never_exported() {
+    let text = render(&metrics(), &status(true), NOW_MS);
+    assert!(!text.contains("secret"));
+    assert!(!text.contains("ws://"));
+}
+
+fn get(addr: std::net::SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn metrics_are_served_over_http() {
+    let listener = http::bind("127.0.0.1", 0).unwrap();
+    let handler = Arc::new(|path: &str| match path {
+        METRICS_PATH => {
+            HttpResponse::new(200, CONTENT_TYPE, render(&metrics(), &status(true), NOW_MS))
+        }
+        _ => HttpResponse::not_found(),
+    });
+    let addr = http::serve(listener, "test-exporter", handler).unwrap();
+
+    let response = get(addr, "GET /metrics?x=1 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains(&format!("Content-Type: {}\r\n", CONTENT_TYPE)));
+    assert!(
+        response.ends_with("simplestclaw_gateway_last_response_timestamp_seconds 1700000099.500\n")
+    );
+
+    assert!(get(addr, "GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404"));
+    assert!(get(addr, "POST /metrics HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 405"));
+    // A second listener on the same port fails instead of sharing it
+    assert!(http::bind("127.0.0.1", addr.port()).is_err());
+}
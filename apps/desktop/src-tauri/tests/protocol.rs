@@ -0,0 +1,98 @@
+//! WebSocket framing and the auth self-test after a start.
+
+mod support;
+
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use simplestclaw_desktop::protocol::{self, write_frame, Frame, FrameReader, ProtocolError};
+use support::{free_port, port_is_listening, FakeGateway};
+
+#[test]
+fn frames_round_trip_masked_and_unmasked() {
+    let long = "x".repeat(70_000);
+    for frame in [
+        Frame::Text("{\"type\":\"req\"}".to_string()),
+        Frame::Text("y".repeat(300)),
+        Frame::Text(long),
+        Frame::Ping(b"hi".to_vec()),
+        Frame::Close,
+    ] {
+        for mask in [None, Some([1, 2, 3, 4])] {
+            let mut wire = Vec::new();
+            write_frame(&mut wire, &frame, mask).unwrap();
+            assert_eq!(wire[1] & 0x80 != 0, mask.is_some());
+            let read = FrameReader::default().read(&mut Cursor::new(wire)).unwrap();
+            assert_eq!(read, frame);
+        }
+    }
+}
+
+#[test]
+fn fragments_are_reassembled_around_control_frames() {
+    // "hel", a ping, then "lo"
+    let wire = [
+        &[0x01, 0x03][..],
+        b"hel",
+        &[0x89, 0x00],
+        &[0x80, 0x02],
+        b"lo",
+    ]
+    .concat();
+    let mut reader = Cursor::new(wire);
+    let mut frames = FrameReader::default();
+    assert_eq!(frames.read(&mut reader).unwrap(), Frame::Ping(Vec::new()));
+    assert_eq!(
+        frames.read(&mut reader).unwrap(),
+        Frame::Text("hello".to_string())
+    );
+
+    let stray = vec![0x80, 0x01, b'x'];
+    assert!(FrameReader::default()
+        .read(&mut Cursor::new(stray))
+        .is_err());
+}
+
+#[test]
+fn the_self_test_authenticates_with_our_token() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = fake.manager(port);
+    let result = manager.start().expect("start failed");
+
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    assert!(protocol::auth_self_test(address, &result.info.token, Duration::from_secs(5)).is_ok());
+    let err = protocol::auth_self_test(address, "wrong", Duration::from_secs(5)).unwrap_err();
+    assert!(matches!(err, ProtocolError::Rejected(ref detail) if detail.contains("mismatch")));
+
+    let last = manager.metrics().snapshot().last_auth_self_test.unwrap();
+    assert!(last.passed);
+    manager.stop().unwrap();
+}
+
+#[test]
+fn a_rejected_token_fails_the_start_and_stops_the_gateway() {
+    let fake = FakeGateway::new(&[("auth", "reject")]);
+    let port = free_port();
+    let manager = fake.manager(port);
+
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "auth_self_test_failed");
+    assert!(err.data()["detail"].contains("token mismatch"), "{}", err);
+    assert!(!manager.status().running);
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(!port_is_listening(port));
+
+    let metrics = manager.metrics().snapshot();
+    assert_eq!(
+        metrics.start_failures_by_reason.get("auth_self_test"),
+        Some(&1)
+    );
+    assert!(!metrics.last_auth_self_test.unwrap().passed);
+
+    // The check can be turned off
+    let unchecked = fake.manager_with(free_port(), |config| config.self_test_on_start = false);
+    unchecked.start().expect("start failed");
+    unchecked.stop().unwrap();
+}
@@ -0,0 +1,174 @@
+//! Purge planning and removal over a synthetic app layout and home dir.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::paths::AppPaths;
+use simplestclaw_desktop::purge::{execute, plan, PurgeGuard, PurgeOptions, NONCE_TTL};
+
+fn write(path: &Path, contents: &str) -> PathBuf {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+    path.to_path_buf()
+}
+
+/// An app layout with some state in every directory, and a home dir with
+/// OpenClaw sessions
+fn populated(root: &Path) -> (AppPaths, PathBuf) {
+    let paths = AppPaths::from_root(root.join("app"));
+    write(&paths.config_dir.join("config.json"), "{}");
+    write(&paths.logs_dir.join("gateway.log"), "line");
+    write(&paths.crash_dir.join("crash-1.json"), "{}");
+    write(&paths.runtime_dir.join("node/bin/node"), "");
+    write(&paths.cache_dir.join("blob"), "cached");
+    let home = root.join("home");
+    write(&home.join(".openclaw/sessions/main.jsonl"), "{}");
+    (paths, home)
+}
+
+fn planned(paths: &AppPaths, home: &Path, options: PurgeOptions) -> Vec<(String, String)> {
+    plan(paths, Some(home), &[], &options)
+        .into_iter()
+        .map(|item| (item.kind, item.path))
+        .collect()
+}
+
+#[test]
+fn everything_is_planned_by_default() {
+    let root = tempfile::tempdir().unwrap();
+    let (paths, home) = populated(root.path());
+    let npx = write(
+        &root
+            .path()
+            .join("npx/abc/node_modules/openclaw/package.json"),
+        "{}",
+    );
+    let npx_entry = npx.ancestors().nth(3).unwrap().to_path_buf();
+
+    let items = plan(
+        &paths,
+        Some(&home),
+        std::slice::from_ref(&npx_entry),
+        &PurgeOptions::default(),
+    );
+    let listed: Vec<(&str, PathBuf)> = items
+        .iter()
+        .map(|item| (item.kind.as_str(), PathBuf::from(&item.path)))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("config", paths.config_dir.clone()),
+            ("data", paths.data_dir.clone()),
+            ("cache", paths.cache_dir.clone()),
+            ("npx-cache", npx_entry),
+            ("sessions", home.join(".openclaw")),
+        ]
+    );
+    assert_eq!(items[1].size_bytes, 6);
+}
+
+#[test]
+fn keep_flags_leave_config_and_sessions() {
+    let root = tempfile::tempdir().unwrap();
+    let (paths, home) = populated(root.path());
+    write(&paths.config_dir.join("other.json"), "{}");
+    let state = write(&paths.gateway_state_dir.join("sessions.json"), "{}");
+
+    let kept = planned(
+        &paths,
+        &home,
+        PurgeOptions {
+            keep_config: true,
+            keep_sessions: true,
+            ..PurgeOptions::default()
+        },
+    );
+    let config_items: Vec<_> = kept.iter().filter(|(kind, _)| kind == "config").collect();
+    assert_eq!(
+        config_items,
+        [&(
+            "config".to_string(),
+            paths.config_dir.join("other.json").display().to_string()
+        )]
+    );
+    assert!(!kept.iter().any(|(kind, _)| kind == "sessions"));
+
+    let report = execute(
+        plan(
+            &paths,
+            Some(&home),
+            &[],
+            &PurgeOptions {
+                keep_config: true,
+                keep_sessions: true,
+                ..PurgeOptions::default()
+            },
+        ),
+        false,
+    );
+    assert!(report.failed.is_empty());
+    assert!(paths.config_dir.join("config.json").exists());
+    assert!(home.join(".openclaw/sessions/main.jsonl").exists());
+    assert!(state.exists());
+    assert!(!paths.logs_dir.exists());
+    assert!(!paths.cache_dir.exists());
+}
+
+#[test]
+fn a_dry_run_removes_nothing() {
+    let root = tempfile::tempdir().unwrap();
+    let (paths, home) = populated(root.path());
+
+    let items = plan(&paths, Some(&home), &[], &PurgeOptions::default());
+    let report = execute(items.clone(), true);
+    assert!(report.dry_run);
+    assert_eq!(report.removed, items);
+    assert!(paths.config_dir.join("config.json").exists());
+    assert!(paths.logs_dir.join("gateway.log").exists());
+    assert!(home.join(".openclaw").exists());
+}
+
+#[test]
+fn items_that_cannot_be_removed_are_reported() {
+    let root = tempfile::tempdir().unwrap();
+    let (paths, home) = populated(root.path());
+
+    let mut items = plan(&paths, Some(&home), &[], &PurgeOptions::default());
+    // Gone by the time the purge runs
+    fs::remove_dir_all(&paths.cache_dir).unwrap();
+
+    let report = execute(items.clone(), false);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].item.kind, "cache");
+    assert!(!report.failed[0].reason.is_empty());
+    items.retain(|item| item.kind != "cache");
+    assert_eq!(report.removed, items);
+    assert!(!paths.config_dir.exists());
+    assert!(!home.join(".openclaw").exists());
+}
+
+#[test]
+fn nonces_are_single_use_and_expire() {
+    let guard = PurgeGuard::default();
+    let now = Instant::now();
+    assert!(!guard.confirm("anything", now));
+
+    let nonce = guard.issue(now);
+    assert!(!guard.confirm("wrong", now));
+    // The wrong guess spent it
+    assert!(!guard.confirm(&nonce, now));
+
+    let nonce = guard.issue(now);
+    assert!(guard.confirm(&nonce, now + Duration::from_secs(1)));
+    assert!(!guard.confirm(&nonce, now + Duration::from_secs(1)));
+
+    let nonce = guard.issue(now);
+    assert!(!guard.confirm(&nonce, now + NONCE_TTL + Duration::from_secs(1)));
+
+    let first = guard.issue(now);
+    let second = guard.issue(now);
+    assert_ne!(first, second);
+    assert!(!guard.confirm(&first, now));
+}
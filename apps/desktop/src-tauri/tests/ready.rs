@@ -0,0 +1,130 @@
+//! `ensure_ready` against the fake gateway, one test per way it can stop.
+
+mod support;
+
+use std::sync::Arc;
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::ready::{ReadyError, ReadyResult, ReadyStage, STAGES};
+use simplestclaw_desktop::sidecar::SidecarManager;
+use support::{free_port, FakeGateway, TEST_API_KEY};
+
+/// `ensure_ready` and the stages it announced
+fn ensure_ready(manager: &SidecarManager) -> (Result<ReadyResult, ReadyError>, Vec<ReadyStage>) {
+    let mut stages = Vec::new();
+    let result = manager.ensure_ready(|progress| stages.push(progress.stage));
+    (result, stages)
+}
+
+/// The stage and code `manager` fails with, checking it stopped there
+fn failure(manager: &SidecarManager) -> (ReadyStage, &'static str) {
+    let (result, stages) = ensure_ready(manager);
+    let err = result.expect_err("ensure_ready should fail");
+    assert_eq!(stages.last(), Some(&err.stage));
+    assert!(!manager.status().running);
+    (err.stage, err.error.code())
+}
+
+#[test]
+fn every_stage_runs_in_order_and_a_running_gateway_is_reused() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+
+    let (result, stages) = ensure_ready(&manager);
+    let first = result.expect("ensure_ready failed");
+    assert_eq!(stages, STAGES);
+    assert!(first.first_run);
+    assert_eq!(
+        fake.env().get("OPENCLAW_GATEWAY_TOKEN"),
+        Some(&first.info.token)
+    );
+
+    let (result, _) = ensure_ready(&manager);
+    let again = result.expect("ensure_ready failed");
+    assert_eq!(again.info, first.info);
+    assert!(!again.first_run);
+
+    manager.stop().unwrap();
+}
+
+#[test]
+fn concurrent_calls_share_one_start() {
+    let fake = FakeGateway::serving();
+    let manager = Arc::new(fake.manager(free_port()));
+
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || manager.ensure_ready(|_| {}))
+        })
+        .collect();
+    let tokens: Vec<String> = handles
+        .into_iter()
+        .map(|h| h.join().unwrap().expect("ensure_ready failed").info.token)
+        .collect();
+    assert_eq!(tokens[0], tokens[1]);
+
+    manager.stop().unwrap();
+}
+
+#[test]
+fn an_invalid_config_stops_at_config() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(0, |_| {});
+    assert_eq!(failure(&manager), (ReadyStage::Config, "invalid_port"));
+
+    let manager = fake.manager_with(free_port(), |config| {
+        config.timezone = Some("not a zone".to_string())
+    });
+    assert_eq!(failure(&manager), (ReadyStage::Config, "invalid_timezone"));
+}
+
+#[test]
+fn a_missing_api_key_stops_at_credentials() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| config.anthropic_api_key = None);
+    assert_eq!(
+        failure(&manager),
+        (ReadyStage::Credentials, "api_key_missing")
+    );
+
+    // Serialized like any other error, with the stage added
+    let (result, _) = ensure_ready(&manager);
+    let json = serde_json::to_value(result.unwrap_err()).unwrap();
+    assert_eq!(json["stage"], "credentials");
+    assert_eq!(json["code"], "api_key_missing");
+    assert!(json["message"].as_str().unwrap().contains("API key"));
+}
+
+#[test]
+fn a_missing_binary_stops_at_binary() {
+    let fake = FakeGateway::serving();
+    let config = Config {
+        anthropic_api_key: Some(TEST_API_KEY.to_string()),
+        gateway_port: free_port(),
+        ..Config::default()
+    };
+    let manager = SidecarManager::with_binary(fake.binary().with_file_name("missing"), config)
+        .with_leftover_sweep(false);
+    assert_eq!(failure(&manager), (ReadyStage::Binary, "gateway_not_found"));
+}
+
+#[test]
+fn a_gateway_that_exits_stops_at_gateway() {
+    let fake = FakeGateway::new(&[("mode", "exit"), ("exit_code", "1")]);
+    let manager = fake.manager(free_port());
+    assert_eq!(
+        failure(&manager),
+        (ReadyStage::Gateway, "gateway_start_failed")
+    );
+}
+
+#[test]
+fn a_rejected_token_stops_at_self_test() {
+    let fake = FakeGateway::new(&[("auth", "reject")]);
+    let manager = fake.manager(free_port());
+    assert_eq!(
+        failure(&manager),
+        (ReadyStage::SelfTest, "auth_self_test_failed")
+    );
+}
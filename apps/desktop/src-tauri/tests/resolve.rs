@@ -0,0 +1,286 @@
+//! The order executables are found in, against a fixture filesystem.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::resolve::{
+    find_npx_cli, npx_cli_candidates, resolve, resolve_beside, version_manager_dirs, LoginPath,
+    NodeLayout, SearchContext, NVM_VERSIONS_DIR, VERSION_INSTALL_DIRS, VERSION_MANAGER_DIRS,
+};
+use simplestclaw_desktop::sidecar::ExecutableSource;
+
+fn executable(path: &Path) -> PathBuf {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    path.to_path_buf()
+}
+
+fn path_of(dirs: &[PathBuf]) -> Option<OsString> {
+    Some(std::env::join_paths(dirs).unwrap())
+}
+
+/// Every place `resolve` looks, each with its own node
+struct Fixture {
+    _root: tempfile::TempDir,
+    context: SearchContext,
+    config: PathBuf,
+    managed: PathBuf,
+    path: PathBuf,
+    login: PathBuf,
+    version_manager: PathBuf,
+    brew: PathBuf,
+    system: PathBuf,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let root = tempfile::tempdir().unwrap();
+        let at = |relative: &str| root.path().join(relative);
+        let home = at("home");
+        let fixture = Fixture {
+            config: executable(&at("custom/node")),
+            managed: executable(&at("runtime/bin/node")),
+            path: executable(&at("path/node")),
+            login: executable(&at("login/node")),
+            version_manager: executable(&home.join(VERSION_MANAGER_DIRS[1]).join("node")),
+            brew: executable(&at("brew/bin/node")),
+            system: executable(&at("usr/bin/node")),
+            context: SearchContext {
+                path: path_of(&[at("empty"), at("path")]),
+                login_path: LoginPath::Known(path_of(&[at("login")])),
+                home: Some(home),
+                brew_prefixes: vec![at("brew")],
+                system_dirs: vec![at("usr/local/bin"), at("usr/bin")],
+                ..SearchContext::empty()
+            },
+            _root: root,
+        };
+        let mut context = fixture.context.clone();
+        context
+            .overrides
+            .insert("node".into(), fixture.config.clone());
+        context.managed_dir = fixture.managed.parent().map(Path::to_path_buf);
+        Fixture { context, ..fixture }
+    }
+
+    fn found(&self) -> Option<(PathBuf, ExecutableSource)> {
+        resolve("node", &self.context).map(|found| (found.path, found.source))
+    }
+}
+
+#[test]
+fn each_place_is_tried_in_order() {
+    let mut fixture = Fixture::new();
+    let expected = [
+        (fixture.config.clone(), ExecutableSource::Config),
+        (fixture.managed.clone(), ExecutableSource::Managed),
+        (fixture.path.clone(), ExecutableSource::Path),
+        (fixture.login.clone(), ExecutableSource::LoginShell),
+        (
+            fixture.version_manager.clone(),
+            ExecutableSource::VersionManager,
+        ),
+        (fixture.brew.clone(), ExecutableSource::Brew),
+        (fixture.system.clone(), ExecutableSource::System),
+    ];
+    for (path, source) in expected {
+        assert_eq!(fixture.found(), Some((path.clone(), source)));
+        // Take the winner away (the override from config instead)
+        if source == ExecutableSource::Config {
+            fixture.context.overrides.clear();
+        } else {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+    assert_eq!(fixture.found(), None);
+}
+
+#[test]
+fn a_configured_binary_that_is_missing_is_not_replaced() {
+    let mut fixture = Fixture::new();
+    fs::remove_file(&fixture.config).unwrap();
+    assert_eq!(fixture.found(), None);
+
+    fixture.context.overrides.clear();
+    assert_eq!(
+        fixture.found().map(|(_, source)| source),
+        Some(ExecutableSource::Managed)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn directories_and_unexecutable_files_are_skipped() {
+    let root = tempfile::tempdir().unwrap();
+    fs::create_dir_all(root.path().join("a/node")).unwrap();
+    // Not executable on Unix
+    fs::create_dir_all(root.path().join("b")).unwrap();
+    fs::write(root.path().join("b/node"), "").unwrap();
+    let node = executable(&root.path().join("c/node"));
+    let context = SearchContext {
+        path: path_of(&[
+            PathBuf::from("relative"),
+            root.path().join("a"),
+            root.path().join("b"),
+            root.path().join("c"),
+        ]),
+        ..SearchContext::empty()
+    };
+    assert_eq!(resolve("node", &context).unwrap().path, node);
+}
+
+#[test]
+fn extensions_newest_nvm_and_npx_beside_node() {
+    let root = tempfile::tempdir().unwrap();
+    let home = root.path().join("home");
+    let versions = home.join(NVM_VERSIONS_DIR);
+    executable(&versions.join("v9.11.2/bin/node"));
+    let newest = executable(&versions.join("v20.11.0/bin/node"));
+    let context = SearchContext {
+        home: Some(home),
+        ..SearchContext::empty()
+    };
+    let node = resolve("node", &context).unwrap();
+    assert_eq!(node.path, newest);
+    assert_eq!(node.source, ExecutableSource::VersionManager);
+
+    // npx next to that node wins over one on PATH
+    let on_path = executable(&root.path().join("path/npx"));
+    let context = SearchContext {
+        path: path_of(&[root.path().join("path")]),
+        ..context
+    };
+    assert_eq!(resolve("npx", &context).unwrap().path, on_path);
+    let beside = executable(&versions.join("v20.11.0/bin/npx"));
+    assert_eq!(resolve_beside("npx", &node, &context).unwrap().path, beside);
+
+    // Windows style: the bare name is never taken
+    let windows = root.path().join("windows");
+    executable(&windows.join("node"));
+    let exe = executable(&windows.join("node.exe"));
+    let context = SearchContext {
+        path: path_of(&[windows]),
+        extensions: vec![".com".into(), ".exe".into()],
+        ..SearchContext::empty()
+    };
+    assert_eq!(resolve("node", &context).unwrap().path, exe);
+}
+
+#[test]
+fn npx_cli_is_found_beside_node_then_under_the_npm_prefixes() {
+    let prefixes = [PathBuf::from("/roaming/npm"), PathBuf::from("/local/npm")];
+
+    let unix = npx_cli_candidates(Path::new("/opt/node/bin/node"), NodeLayout::Unix, &prefixes);
+    assert_eq!(
+        unix,
+        [
+            PathBuf::from("/opt/node/lib/node_modules/npm/bin/npx-cli.js"),
+            PathBuf::from("/roaming/npm/lib/node_modules/npm/bin/npx-cli.js"),
+            PathBuf::from("/local/npm/lib/node_modules/npm/bin/npx-cli.js"),
+        ]
+    );
+
+    // Windows installs keep node_modules next to node.exe, and so do prefixes
+    let node = Path::new("/nodejs/node.exe");
+    let windows = npx_cli_candidates(node, NodeLayout::Windows, &prefixes);
+    assert_eq!(
+        windows,
+        [
+            PathBuf::from("/nodejs/node_modules/npm/bin/npx-cli.js"),
+            PathBuf::from("/roaming/npm/node_modules/npm/bin/npx-cli.js"),
+            PathBuf::from("/local/npm/node_modules/npm/bin/npx-cli.js"),
+        ]
+    );
+
+    let only = |existing: &'static str| move |path: &Path| path == Path::new(existing);
+    assert_eq!(
+        find_npx_cli(
+            node,
+            NodeLayout::Windows,
+            &prefixes,
+            only("/local/npm/node_modules/npm/bin/npx-cli.js")
+        ),
+        Some(windows[2].clone())
+    );
+    assert_eq!(
+        find_npx_cli(node, NodeLayout::Windows, &prefixes, |_| true),
+        Some(windows[0].clone())
+    );
+    assert_eq!(
+        find_npx_cli(node, NodeLayout::Windows, &prefixes, |_| false),
+        None
+    );
+}
+
+#[test]
+fn version_manager_dirs_are_linked_first_then_newest_install_without_repeats() {
+    let home = tempfile::tempdir().unwrap();
+    let (nvm, nvm_bin) = VERSION_INSTALL_DIRS[0];
+    let (fnm, fnm_bin) = VERSION_INSTALL_DIRS[1];
+    for version in ["v9.11.2", "v22.1.0", "v18.20.0"] {
+        fs::create_dir_all(home.path().join(nvm).join(version)).unwrap();
+    }
+    fs::create_dir_all(home.path().join(fnm).join("v20.0.0")).unwrap();
+    // Not an install
+    fs::write(home.path().join(nvm).join("settings.txt"), "").unwrap();
+
+    let dirs = version_manager_dirs(home.path());
+    let linked: Vec<PathBuf> = VERSION_MANAGER_DIRS
+        .iter()
+        .map(|dir| home.path().join(dir))
+        .collect();
+    assert_eq!(dirs[..linked.len()], linked[..]);
+    let install =
+        |root: &str, version: &str, bin: &str| home.path().join(root).join(version).join(bin);
+    assert_eq!(
+        dirs[linked.len()..],
+        [
+            install(nvm, "v22.1.0", nvm_bin),
+            install(nvm, "v18.20.0", nvm_bin),
+            install(nvm, "v9.11.2", nvm_bin),
+            install(fnm, "v20.0.0", fnm_bin),
+        ]
+    );
+    let mut unique = dirs.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), dirs.len());
+}
+
+#[test]
+fn searched_dirs_follow_the_search_order_once_each() {
+    let mut fixture = Fixture::new();
+    let root = fixture.managed.ancestors().nth(3).unwrap().to_path_buf();
+    // On PATH and in the login shell's PATH
+    fixture.context.login_path =
+        LoginPath::Known(path_of(&[root.join("login"), root.join("path")]));
+
+    let dirs = fixture.context.searched_dirs();
+    assert_eq!(
+        dirs[..4],
+        [
+            root.join("runtime/bin"),
+            root.join("empty"),
+            root.join("path"),
+            root.join("login")
+        ]
+    );
+    assert!(dirs.contains(&fixture.version_manager.parent().unwrap().to_path_buf()));
+    assert_eq!(
+        dirs[dirs.len() - 3..],
+        [
+            root.join("brew/bin"),
+            root.join("usr/local/bin"),
+            root.join("usr/bin")
+        ]
+    );
+    let unique: HashSet<&PathBuf> = dirs.iter().collect();
+    assert_eq!(unique.len(), dirs.len());
+}
@@ -0,0 +1,113 @@
+//! Retention sweeps over a synthetic directory tree with controlled mtimes.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use simplestclaw_desktop::config::LogRetention;
+use simplestclaw_desktop::paths::AppPaths;
+use simplestclaw_desktop::retention::{sweep, MAX_CRASH_REPORTS};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Write `size` bytes to `path` and backdate it by `age`
+fn file(path: &Path, size: usize, age: Duration, now: SystemTime) -> PathBuf {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, vec![b'x'; size]).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(now - age)
+        .unwrap();
+    path.to_path_buf()
+}
+
+fn policy(retention_days: u32, max_logs_mb: u64) -> LogRetention {
+    LogRetention {
+        enabled: true,
+        retention_days,
+        max_logs_mb,
+    }
+}
+
+#[test]
+fn old_rotated_logs_are_deleted() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+    let now = SystemTime::now();
+
+    let old = file(&paths.logs_dir.join("gateway.log.1"), 10, 20 * DAY, now);
+    let recent = file(&paths.logs_dir.join("gateway.log.2"), 10, DAY, now);
+    let other = file(&paths.logs_dir.join("activity.json"), 10, 30 * DAY, now);
+
+    let report = sweep(&paths, &policy(14, 100), &[], now).unwrap();
+
+    assert_eq!(report.removed, vec![old.clone()]);
+    assert_eq!(report.freed_bytes, 10);
+    assert!(!old.exists());
+    assert!(recent.exists());
+    assert!(other.exists(), "non-log files are left alone");
+}
+
+#[test]
+fn active_files_are_never_deleted() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+    let now = SystemTime::now();
+
+    let active = file(&paths.logs_dir.join("gateway.log"), 2 * 1024 * 1024, 60 * DAY, now);
+
+    let report = sweep(&paths, &policy(14, 1), std::slice::from_ref(&active), now).unwrap();
+
+    assert!(report.removed.is_empty());
+    assert!(active.exists());
+}
+
+#[test]
+fn only_newest_crash_reports_are_kept() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+    let now = SystemTime::now();
+
+    let reports: Vec<PathBuf> = (0..MAX_CRASH_REPORTS + 3)
+        .map(|i| {
+            let age = Duration::from_secs(60 * i as u64);
+            file(&paths.crash_dir.join(format!("crash-{}.json", i)), 1, age, now)
+        })
+        .collect();
+
+    let report = sweep(&paths, &policy(14, 100), &[], now).unwrap();
+
+    assert_eq!(report.removed.len(), 3);
+    for (i, path) in reports.iter().enumerate() {
+        assert_eq!(path.exists(), i < MAX_CRASH_REPORTS, "{}", path.display());
+    }
+}
+
+#[test]
+fn size_budget_deletes_oldest_logs_first() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+    let now = SystemTime::now();
+    let mb = 1024 * 1024;
+
+    let oldest = file(&paths.logs_dir.join("app.log.3"), mb, 3 * DAY, now);
+    let middle = file(&paths.logs_dir.join("app.log.2"), mb, 2 * DAY, now);
+    let newest = file(&paths.logs_dir.join("app.log.1"), mb, DAY, now);
+
+    let report = sweep(&paths, &policy(14, 2), &[], now).unwrap();
+
+    assert_eq!(report.removed, vec![oldest.clone()]);
+    assert!(middle.exists());
+    assert!(newest.exists());
+}
+
+#[test]
+fn missing_directories_are_fine() {
+    let root = tempfile::tempdir().unwrap();
+    let paths = AppPaths::from_root(root.path());
+
+    let report = sweep(&paths, &policy(14, 100), &[], SystemTime::now()).unwrap();
+    assert!(report.removed.is_empty());
+}
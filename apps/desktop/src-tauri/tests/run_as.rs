@@ -0,0 +1,175 @@
+//! Running the gateway as another user: account lookup, privilege and
+//! access checks, and the uid switch at spawn.
+#![cfg(unix)]
+
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::process::{LaunchSpec, ProcessLauncher, ProcessUser, SystemLauncher};
+use simplestclaw_desktop::run_as::{
+    can_access, check_privileges, current_euid, hand_over, hand_over_scope, plan, resolve,
+    RunAsError, RunAsUser,
+};
+
+fn claw() -> RunAsUser {
+    RunAsUser {
+        name: "claw".to_string(),
+        uid: 64_999,
+        gid: 64_999,
+        home: PathBuf::from("/home/claw"),
+    }
+}
+
+fn chmod(path: &Path, mode: u32) {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[test]
+fn accounts_are_looked_up_by_name() {
+    let root = resolve("root").unwrap();
+    assert_eq!((root.uid, root.gid), (0, 0));
+    assert_eq!(
+        resolve("no-such-user-sclw"),
+        Err(RunAsError::UnknownUser("no-such-user-sclw".to_string()))
+    );
+    assert!(matches!(
+        resolve("bad\0name"),
+        Err(RunAsError::UnknownUser(_))
+    ));
+
+    let env = claw().env();
+    assert!(env.contains(&("HOME".to_string(), "/home/claw".to_string())));
+    assert!(env.contains(&("USER".to_string(), "claw".to_string())));
+}
+
+#[test]
+fn switching_needs_root_and_a_less_privileged_target() {
+    let root = RunAsUser {
+        uid: 0,
+        gid: 0,
+        ..claw()
+    };
+    assert_eq!(check_privileges(&root, 0), Err(RunAsError::RootNotAllowed));
+    assert_eq!(check_privileges(&claw(), 0), Ok(()));
+    // Already running as the user, nothing to switch
+    assert_eq!(check_privileges(&claw(), 64_999), Ok(()));
+    assert_eq!(
+        check_privileges(&claw(), 501),
+        Err(RunAsError::InsufficientPrivileges("claw".to_string()))
+    );
+    assert_eq!(
+        plan("root", None, None, None).unwrap_err(),
+        RunAsError::Unsupported
+    );
+}
+
+#[test]
+fn only_what_the_app_created_is_handed_over_whole() {
+    let data_dir = Path::new("/var/lib/simplestclaw");
+    let scope = |dir: &str, field| hand_over_scope(Path::new(dir), field, Some(data_dir));
+
+    let default = scope("/var/lib/simplestclaw/gateway-state", None).unwrap();
+    assert!(default.recursive);
+    let configured = scope("/var/lib/simplestclaw/claw-state", Some("gatewayStateDir")).unwrap();
+    assert_eq!(configured.dir, Path::new("/var/lib/simplestclaw/claw-state"));
+    assert!(!configured.recursive);
+
+    // Someone's home or a shared mount is never given away
+    for dir in ["/home/alice", "/srv", "/var/lib/simplestclaw-other"] {
+        let err = scope(dir, Some("gatewayStateDir")).unwrap_err();
+        assert_eq!(err.code(), "invalid_setting");
+        assert_eq!(err.data()["field"], "gatewayStateDir");
+    }
+    let err = hand_over_scope(Path::new("/srv/ws"), Some("workspaceDir"), None).unwrap_err();
+    assert_eq!(err.data()["field"], "workspaceDir");
+}
+
+#[test]
+fn access_follows_the_permission_bits_of_every_parent() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("node");
+    fs::write(&program, "").unwrap();
+    chmod(&program, 0o755);
+
+    chmod(dir.path(), 0o700);
+    assert!(!can_access(&claw(), &program));
+    chmod(dir.path(), 0o755);
+    assert!(can_access(&claw(), &program));
+    chmod(&program, 0o700);
+    assert!(!can_access(&claw(), &program));
+    assert!(!can_access(&claw(), &dir.path().join("missing")));
+}
+
+#[test]
+fn a_workspace_the_user_cannot_reach_is_rejected() {
+    let euid = current_euid();
+    if euid != Some(0) {
+        // Without root the privilege check fails first
+        assert!(matches!(
+            plan("root", None, None, euid),
+            Err(RunAsError::RootNotAllowed)
+        ));
+        return;
+    }
+    let Ok(nobody) = resolve("nobody") else {
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    chmod(dir.path(), 0o700);
+    let workspace = dir.path().join("private").join("workspace");
+    assert_eq!(
+        plan("nobody", None, Some(&workspace), euid).unwrap_err(),
+        RunAsError::NoAccess {
+            user: nobody.name.clone(),
+            path: dir.path().display().to_string(),
+        }
+    );
+
+    chmod(dir.path(), 0o755);
+    let planned = plan("nobody", None, Some(&workspace), euid).unwrap();
+    assert_eq!(planned.user, nobody);
+    assert_eq!(planned.home_dir, None);
+}
+
+#[test]
+fn the_child_runs_as_the_user_or_does_not_run() {
+    let Ok(nobody) = resolve("nobody") else {
+        return;
+    };
+    let spec = LaunchSpec {
+        program: "id".to_string(),
+        args: vec!["-u".to_string()],
+        env: Vec::new(),
+        clean_env: false,
+        low_priority: false,
+        cwd: Some(PathBuf::from("/")),
+        user: Some(ProcessUser {
+            uid: nobody.uid,
+            gid: nobody.gid,
+        }),
+    };
+
+    if current_euid() != Some(0) {
+        assert!(SystemLauncher.spawn(&spec).is_err());
+        return;
+    }
+    let mut child = SystemLauncher.spawn(&spec).expect("spawn failed");
+    let mut output = String::new();
+    child
+        .take_stdout()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    child.wait().unwrap();
+    assert_eq!(output.trim(), nobody.uid.to_string());
+
+    // And the workspace is handed over along with it
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join(".tmp")).unwrap();
+    fs::write(dir.path().join(".tmp").join("file"), "").unwrap();
+    hand_over(dir.path(), &nobody, true).unwrap();
+    let file = fs::metadata(dir.path().join(".tmp").join("file")).unwrap();
+    assert_eq!((file.uid(), file.gid()), (nobody.uid, nobody.gid));
+}
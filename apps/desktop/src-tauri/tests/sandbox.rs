@@ -0,0 +1,77 @@
+//! Sandbox workspace planning and validation.
+
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::paths::AppPaths;
+use simplestclaw_desktop::sandbox::{plan, validate_workspace};
+
+fn sandboxed(workspace_dir: Option<PathBuf>) -> Config {
+    Config {
+        sandbox_workspace: true,
+        workspace_dir,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn disabled_sandbox_has_no_plan() {
+    let paths = AppPaths::from_root("/tmp/sclw-test");
+    assert!(plan(&Config::default(), Some(&paths)).unwrap().is_none());
+}
+
+#[test]
+fn default_workspace_is_under_app_data() {
+    let paths = AppPaths::from_root("/tmp/sclw-test");
+    let plan = plan(&sandboxed(None), Some(&paths)).unwrap().unwrap();
+    assert_eq!(plan.workspace, paths.workspace_dir);
+    assert!(plan.workspace.starts_with(&paths.data_dir));
+}
+
+#[test]
+fn configured_workspace_wins_and_limits_are_reported() {
+    let paths = AppPaths::from_root("/tmp/sclw-test");
+    let dir = PathBuf::from("/tmp/sclw-elsewhere/workspace");
+    let plan = plan(&sandboxed(Some(dir.clone())), Some(&paths)).unwrap().unwrap();
+
+    assert_eq!(plan.workspace, dir);
+    assert_eq!(plan.status.workspace, dir.display().to_string());
+    assert!(plan.status.applied[0].contains("Working directory"));
+    assert!(plan.status.not_enforced.iter().any(|item| item.contains("not blocked")));
+
+    let tmpdir = plan.env().into_iter().find(|(name, _)| name == "TMPDIR");
+    if cfg!(target_os = "linux") {
+        assert_eq!(tmpdir.unwrap().1, dir.join(".tmp").display().to_string());
+    } else {
+        assert!(tmpdir.is_none());
+    }
+}
+
+#[test]
+fn unsafe_workspaces_are_rejected() {
+    let paths = AppPaths::from_root("/tmp/sclw-test");
+    let home = Path::new("/home/someone");
+    let rejected = [
+        "relative/workspace",
+        "/tmp/../etc",
+        "/",
+        "/home",
+        "/home/someone",
+        "/tmp/sclw-test",
+    ];
+    for dir in rejected {
+        assert!(
+            validate_workspace(Path::new(dir), Some(&paths), Some(home)).is_err(),
+            "{} should be rejected",
+            dir
+        );
+    }
+    assert!(validate_workspace(Path::new("/home/someone/agent"), Some(&paths), Some(home)).is_ok());
+}
+
+#[test]
+fn a_file_is_not_a_workspace() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let err = validate_workspace(file.path(), None, None).unwrap_err();
+    assert!(err.contains("not a directory"), "{}", err);
+}
@@ -0,0 +1,90 @@
+//! Restarting the gateway on schedule once it is idle.
+
+mod support;
+
+use std::time::Duration;
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::gateway_stats::GatewayStats;
+use simplestclaw_desktop::scheduled_restart::RestartSchedule;
+use support::{free_port, FakeGateway};
+
+const HOUR_MS: i64 = 3_600_000;
+const MINUTE_MS: i64 = 60_000;
+
+#[test]
+fn the_schedule_comes_from_the_config() {
+    let mut config = Config::default();
+    assert_eq!(RestartSchedule::from_config(&config), None);
+    config.auto_restart_interval_hours = Some(0);
+    assert_eq!(RestartSchedule::from_config(&config), None);
+
+    config.auto_restart_interval_hours = Some(48);
+    config.auto_restart_quiet_minutes = 5;
+    assert_eq!(
+        RestartSchedule::from_config(&config),
+        Some(RestartSchedule {
+            interval: Duration::from_secs(48 * 3600),
+            quiet: Duration::from_secs(5 * 60),
+        })
+    );
+}
+
+#[test]
+fn a_restart_waits_for_the_interval_and_for_quiet() {
+    let schedule = RestartSchedule {
+        interval: Duration::from_secs(24 * 3600),
+        quiet: Duration::from_secs(15 * 60),
+    };
+    let started_at = 1_000_000;
+    let idle = GatewayStats::default();
+    assert!(!schedule.is_due(started_at, &idle, started_at + 23 * HOUR_MS));
+    assert!(schedule.is_due(started_at, &idle, started_at + 24 * HOUR_MS));
+
+    // A client in the middle of a run
+    let busy = GatewayStats {
+        clients_connected: 1,
+        total_requests: 40,
+        last_request_at: Some(started_at + 24 * HOUR_MS - MINUTE_MS),
+    };
+    assert!(!schedule.is_due(started_at, &busy, started_at + 24 * HOUR_MS));
+    // Still connected, but quiet for long enough
+    assert!(schedule.is_due(started_at, &busy, started_at + 24 * HOUR_MS + 14 * MINUTE_MS));
+
+    // Connected and never asked anything
+    let silent = GatewayStats {
+        clients_connected: 1,
+        ..GatewayStats::default()
+    };
+    assert!(schedule.is_due(started_at, &silent, started_at + 24 * HOUR_MS));
+}
+
+#[test]
+fn a_stop_or_a_new_gateway_cancels_a_restart_already_decided() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    assert_eq!(manager.running_since(), None);
+
+    manager.start().expect("start failed");
+    let started_at = manager.running_since().expect("no start time");
+    manager.stop().unwrap();
+    assert!(manager.restart_if_running_since(started_at, "scheduled").is_none());
+    assert_eq!(manager.gateway_pid(), None);
+
+    // Any new gateway starts the wait over
+    let second = manager.start().expect("start failed");
+    let restarted_at = manager.running_since().expect("no start time");
+    assert_ne!(restarted_at, started_at);
+    assert!(manager.restart_if_running_since(started_at, "scheduled").is_none());
+
+    let pid = manager.gateway_pid();
+    let result = manager
+        .restart_if_running_since(restarted_at, "scheduled")
+        .expect("not restarted")
+        .expect("restart failed");
+    assert_ne!(manager.gateway_pid(), pid);
+    assert_ne!(manager.running_since(), Some(restarted_at));
+    // Clients only need to reconnect
+    assert_eq!(result.info.token, second.info.token);
+    manager.stop().unwrap();
+}
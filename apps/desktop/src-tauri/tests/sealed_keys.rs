@@ -0,0 +1,91 @@
+//! API keys sealed in config.json with a key bound to the machine.
+
+use serde_json::json;
+
+use simplestclaw_desktop::sealed_keys::{self, derive_key, SealError, SealedKey};
+
+#[test]
+fn a_sealed_key_opens_with_the_same_machine_key() {
+    let key = derive_key("machine-a");
+    let sealed = sealed_keys::seal(&key, "sk-ant-secret").unwrap();
+
+    assert_eq!(sealed.enc, "v1");
+    assert!(!sealed.ciphertext.contains("sk-ant"));
+    assert_eq!(sealed_keys::open(&key, &sealed).unwrap(), "sk-ant-secret");
+    // A fresh nonce each time
+    assert_ne!(sealed_keys::seal(&key, "sk-ant-secret").unwrap(), sealed);
+}
+
+#[test]
+fn another_machine_cannot_open_it() {
+    let sealed = sealed_keys::seal(&derive_key("machine-a"), "sk-ant-secret").unwrap();
+    assert_eq!(
+        sealed_keys::open(&derive_key("machine-b"), &sealed),
+        Err(SealError::WrongKey)
+    );
+
+    let newer = SealedKey {
+        enc: "v2".to_string(),
+        ..sealed
+    };
+    assert_eq!(
+        sealed_keys::open(&derive_key("machine-a"), &newer),
+        Err(SealError::UnknownVersion("v2".to_string()))
+    );
+}
+
+#[test]
+fn every_key_in_the_file_is_sealed_and_opened() {
+    let key = derive_key("machine-a");
+    let plain = json!({
+        "anthropicApiKey": "sk-ant-1",
+        "providerKeys": { "openai": "sk-openai" },
+        "profiles": { "work": { "apiKey": "sk-ant-work", "provider": "anthropic" } },
+        "gatewayPort": 18789
+    });
+    let mut value = plain.clone();
+
+    sealed_keys::seal_keys(&mut value, &key).unwrap();
+    let text = value.to_string();
+    assert!(!text.contains("sk-ant-1") && !text.contains("sk-openai") && !text.contains("sk-ant-work"));
+    assert_eq!(value["anthropicApiKey"]["enc"], "v1");
+    assert_eq!(value["gatewayPort"], 18789);
+
+    let unreadable = sealed_keys::open_keys(&mut value, Some(&key));
+    assert!(unreadable.is_empty());
+    assert_eq!(value, plain);
+}
+
+#[test]
+fn plain_keys_from_older_versions_are_left_as_they_are() {
+    let mut value = json!({ "anthropicApiKey": "sk-ant-1", "providerKeys": { "google": "g-1" } });
+    let unreadable = sealed_keys::open_keys(&mut value, Some(&derive_key("machine-a")));
+    assert!(unreadable.is_empty());
+    assert_eq!(value["anthropicApiKey"], "sk-ant-1");
+    assert_eq!(value["providerKeys"]["google"], "g-1");
+}
+
+#[test]
+fn keys_that_dont_open_are_taken_out_and_can_be_put_back() {
+    let mut value = json!({ "anthropicApiKey": "sk-ant-1", "providerKeys": { "openai": "sk-openai" } });
+    sealed_keys::seal_keys(&mut value, &derive_key("machine-a")).unwrap();
+    let sealed = value.clone();
+
+    let unreadable = sealed_keys::open_keys(&mut value, Some(&derive_key("machine-b")));
+    assert_eq!(
+        unreadable.keys().collect::<Vec<_>>(),
+        ["/anthropicApiKey", "/providerKeys/openai"]
+    );
+    assert!(value.get("anthropicApiKey").is_none());
+    assert!(value["providerKeys"].get("openai").is_none());
+
+    // Without a machine key none open either
+    let mut without = sealed.clone();
+    assert_eq!(sealed_keys::open_keys(&mut without, None).len(), 2);
+
+    // Saved back as they were, unless replaced meanwhile
+    let mut saved = json!({ "anthropicApiKey": null, "providerKeys": { "openai": "sk-new" } });
+    sealed_keys::restore_unreadable(&mut saved, &unreadable);
+    assert_eq!(saved["anthropicApiKey"], sealed["anthropicApiKey"]);
+    assert_eq!(saved["providerKeys"]["openai"], "sk-new");
+}
@@ -0,0 +1,106 @@
+//! Changing several settings at once, and pointing at the one that fails.
+
+use simplestclaw_desktop::config::{Config, Provider};
+use simplestclaw_desktop::settings::{self, ConfigPatch};
+
+fn patch(json: serde_json::Value) -> ConfigPatch {
+    serde_json::from_value(json).unwrap()
+}
+
+#[test]
+fn a_patch_changes_only_what_it_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = Config {
+        locale: Some("en-GB".to_string()),
+        ..Config::default()
+    };
+
+    let unchanged = config.auto_start_gateway;
+    let changed = settings::apply(
+        &mut config,
+        patch(serde_json::json!({
+            "provider": "openai",
+            "gatewayPort": 18800,
+            "gatewayHost": "::",
+            "autoStartGateway": unchanged,
+            "workspaceDir": dir.path(),
+            "locale": null,
+            "blockedTools": ["EXEC", "exec"],
+        })),
+    )
+    .unwrap();
+    assert_eq!(
+        changed,
+        [
+            "provider",
+            "gatewayPort",
+            "gatewayHost",
+            "workspaceDir",
+            "locale",
+            "blockedTools"
+        ]
+    );
+    assert!(settings::requires_restart(&changed));
+    assert_eq!(config.provider, Provider::Openai);
+    assert_eq!(config.gateway_port, 18800);
+    assert_eq!(config.gateway_host, "::");
+    assert_eq!(config.workspace_dir.as_deref(), Some(dir.path()));
+    assert_eq!(config.locale, None);
+    assert_eq!(config.blocked_tools, ["exec"]);
+
+    let changed = settings::apply(
+        &mut config,
+        patch(serde_json::json!({ "gatewayHost": "localhost", "tokenInUrl": true })),
+    );
+    assert_eq!(changed.unwrap(), ["gatewayHost", "tokenInUrl"]);
+    assert_eq!(config.gateway_host, "127.0.0.1");
+    assert!(!settings::requires_restart(&["tokenInUrl"]));
+}
+
+#[test]
+fn a_failing_field_is_named_and_nothing_changes() {
+    let mut config = Config::default();
+    for (json, field) in [
+        (serde_json::json!({ "gatewayPort": 80 }), "gatewayPort"),
+        (
+            serde_json::json!({ "gatewayHost": "gateway.lan" }),
+            "gatewayHost",
+        ),
+        (
+            serde_json::json!({ "workspaceDir": "/no/such/dir" }),
+            "workspaceDir",
+        ),
+        (
+            serde_json::json!({ "gatewayExtraArgs": ["--port=1"] }),
+            "gatewayExtraArgs",
+        ),
+        (
+            serde_json::json!({ "timezone": "Europe/Berlin 2" }),
+            "timezone",
+        ),
+        (
+            serde_json::json!({ "gatewayStartupTimeoutSecs": 2 }),
+            "gatewayStartupTimeoutSecs",
+        ),
+        (
+            serde_json::json!({ "gatewayStartupTimeoutSecs": 121 }),
+            "gatewayStartupTimeoutSecs",
+        ),
+        (serde_json::json!({ "transport": "udp" }), "transport"),
+        (
+            serde_json::json!({ "transport": "unix", "gatewaySocketPath": "gw.sock" }),
+            "gatewaySocketPath",
+        ),
+    ] {
+        let mut json = json;
+        json["autoSelectPort"] = true.into();
+        let err = settings::apply(&mut config, patch(json)).unwrap_err();
+        assert_eq!(err.code(), "invalid_setting");
+        assert_eq!(err.data()["field"], field);
+        assert!(!config.auto_select_port, "{} applied partly", field);
+    }
+
+    // Secrets have their own commands
+    let secret = serde_json::json!({ "anthropicApiKey": "sk-ant-x" });
+    assert!(serde_json::from_value::<ConfigPatch>(secret).is_err());
+}
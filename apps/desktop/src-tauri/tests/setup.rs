@@ -0,0 +1,46 @@
+//! The onboarding checklist, computed without starting anything.
+
+mod support;
+
+use simplestclaw_desktop::setup;
+use support::{free_port, port_is_listening, FakeGateway};
+
+#[test]
+fn status_reports_what_is_known_without_spawning() {
+    let fake = FakeGateway::new(&[("version", "openclaw 2026.2.3")]);
+    let port = free_port();
+    let manager = fake.manager(port);
+
+    let status = setup::status(&manager).unwrap();
+    assert!(status.has_api_key);
+    assert!(status.openclaw_found);
+    assert_eq!(status.openclaw_path.as_deref(), fake.binary().to_str());
+    // Nothing has asked openclaw yet
+    assert_eq!(status.openclaw_version, None);
+    assert!(status.version_supported);
+    assert!(status.version_detail.is_some());
+    assert!(status.port_available);
+    assert!(!status.gateway_running);
+    assert!(!port_is_listening(port));
+
+    manager.openclaw_version().unwrap();
+    let status = setup::status(&manager).unwrap();
+    assert_eq!(status.openclaw_version.as_deref(), Some("2026.2.3"));
+    assert_eq!(status.version_detail, None);
+}
+
+#[test]
+fn a_missing_key_and_a_taken_port_are_explained() {
+    let fake = FakeGateway::new(&[]);
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let manager = fake.manager_with(port, |config| {
+        config.anthropic_api_key = None;
+    });
+
+    let status = setup::status(&manager).unwrap();
+    assert!(!status.has_api_key);
+    assert!(status.api_key_detail.unwrap().contains("anthropic"));
+    assert!(!status.port_available);
+    assert!(status.port_detail.unwrap().contains(&port.to_string()));
+}
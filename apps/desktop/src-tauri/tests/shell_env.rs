@@ -0,0 +1,61 @@
+//! Login shell probing against fake shells.
+
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::shell_env::{parse_probe_output, probe_login_shell};
+use tempfile::TempDir;
+
+/// A "login shell" that prints a banner like a chatty rc file, then runs
+/// the `-c` script with `exports` set
+fn fake_shell(dir: &TempDir, exports: &str) -> PathBuf {
+    let path = dir.path().join("fake-shell");
+    let script = format!(
+        "#!/bin/sh\necho 'Welcome back!'\nwhile [ $# -gt 0 ]; do\n  if [ \"$1\" = -c ]; then {} exec /bin/sh -c \"$2\"; fi\n  shift\ndone\nexit 1\n",
+        exports
+    );
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn variables_are_read_past_rc_file_noise() {
+    let dir = tempfile::tempdir().unwrap();
+    let shell = fake_shell(&dir, "SSH_AUTH_SOCK=/tmp/agent.sock GIT_AUTHOR_NAME='Ada Lovelace' GPG_TTY=");
+    let vars = probe_login_shell(
+        &shell,
+        &["SSH_AUTH_SOCK", "GIT_AUTHOR_NAME", "GPG_TTY", "GIT_AUTHOR_EMAIL"],
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    assert_eq!(
+        vars,
+        [
+            ("SSH_AUTH_SOCK".to_string(), "/tmp/agent.sock".to_string()),
+            ("GIT_AUTHOR_NAME".to_string(), "Ada Lovelace".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn a_hanging_shell_times_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let shell = dir.path().join("hanging-shell");
+    fs::write(&shell, "#!/bin/sh\nexec sleep 30\n").unwrap();
+    fs::set_permissions(&shell, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let started = Instant::now();
+    let err = probe_login_shell(&shell, &["SSH_AUTH_SOCK"], Duration::from_millis(300)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn output_without_markers_is_rejected() {
+    assert!(parse_probe_output("zsh: command not found: printf").is_none());
+}
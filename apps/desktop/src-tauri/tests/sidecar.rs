@@ -0,0 +1,1026 @@
+//! Integration tests for `SidecarManager` against the fake gateway.
+
+mod support;
+
+use std::collections::HashSet;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Barrier, Mutex};
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::error::AppError;
+use simplestclaw_desktop::gateway_logs::{MARKER_STREAM, RESTART_MARKER};
+use simplestclaw_desktop::port::PortSource;
+use simplestclaw_desktop::process::StopKind;
+use simplestclaw_desktop::sidecar::{
+    generate_token, ExecutableSource, GatewayInfo, SidecarManager, PERSISTENT_CONFLICT_STARTS,
+    TOKEN_BYTES,
+};
+use simplestclaw_desktop::status_history::TransitionKind;
+use support::{free_port, port_is_listening, FakeGateway, TEST_API_KEY};
+
+#[test]
+fn start_status_stop() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = fake.manager(port);
+
+    let result = manager.start().expect("start failed");
+    assert_eq!(result.info.port, Some(port));
+    assert_eq!(result.info.url, format!("ws://localhost:{}", port));
+    assert!(port_is_listening(port));
+
+    let status = manager.status();
+    assert!(status.running);
+    assert_eq!(status.info.map(|i| i.token), Some(result.info.token.clone()));
+
+    let env = fake.env();
+    assert_eq!(env.get("OPENCLAW_GATEWAY_TOKEN"), Some(&result.info.token));
+    assert_eq!(env.get("ANTHROPIC_API_KEY").map(String::as_str), Some(TEST_API_KEY));
+
+    manager.stop().expect("stop failed");
+    assert!(!manager.status().running);
+    assert!(!port_is_listening(port));
+}
+
+#[test]
+fn exit_during_startup_reports_stderr() {
+    let fake = FakeGateway::new(&[
+        ("mode", "exit"),
+        ("exit_code", "3"),
+        ("stderr", "invalid x-api-key"),
+    ]);
+    let manager = fake.manager(free_port());
+
+    let err = manager.start().expect_err("start should fail").to_string();
+    assert!(err.contains("invalid x-api-key"), "unexpected error: {}", err);
+    assert!(!manager.status().running);
+}
+
+#[test]
+fn exit_soon_after_listening_fails_the_start() {
+    let fake = FakeGateway::new(&[
+        ("exit_after_ms", "200"),
+        ("exit_code", "1"),
+        ("stderr", "error: unknown option '--frobnicate'"),
+    ]);
+    let manager = fake.manager(free_port());
+
+    let err = manager.start().expect_err("start should fail").to_string();
+    assert!(err.contains("unknown option '--frobnicate'"), "unexpected error: {}", err);
+    assert!(!manager.status().running);
+    // Nothing is left behind to make the next start think it's running
+    let err = manager.start().expect_err("start should fail").to_string();
+    assert!(err.contains("unknown option"), "unexpected error: {}", err);
+}
+
+#[test]
+fn crash_after_start_is_detected() {
+    let fake = FakeGateway::new(&[("exit_after_ms", "2500"), ("exit_code", "137")]);
+    let manager = fake.manager(free_port());
+
+    manager.start().expect("start failed");
+    let running = serde_json::to_value(manager.status()).unwrap();
+    assert!(running["pid"].is_u64(), "{}", running);
+    assert!(running["startedAt"].is_i64(), "{}", running);
+    assert!(running["uptimeSecs"].is_u64(), "{}", running);
+    assert_eq!(running["lastExitCode"], serde_json::Value::Null);
+    assert_eq!(running["lastExitAt"], serde_json::Value::Null);
+    std::thread::sleep(Duration::from_millis(3500));
+
+    let status = manager.status();
+    assert!(!status.running);
+    // Still known on the next call, after the state was cleared
+    let exited = serde_json::to_value(manager.status()).unwrap();
+    assert_eq!(exited["pid"], serde_json::Value::Null, "{}", exited);
+    assert_eq!(exited["uptimeSecs"], serde_json::Value::Null);
+    assert_eq!(exited["lastExitCode"], 137);
+    assert!(exited["lastExitAt"].as_i64().unwrap() > running["startedAt"].as_i64().unwrap());
+
+    // A new gateway keeps reporting how the last one ended
+    manager.start().expect("start failed");
+    assert_eq!(manager.status().last_exit_code, Some(137));
+    manager.stop().unwrap();
+}
+
+#[test]
+fn startup_timeout_kills_the_child() {
+    let fake = FakeGateway::new(&[("mode", "hang")]);
+    let manager = fake
+        .manager(free_port())
+        .with_startup_timeout(Duration::from_secs(2));
+
+    let err = manager.start().expect_err("start should time out").to_string();
+    assert!(err.contains("failed to start within 2 seconds"), "unexpected error: {}", err);
+    assert!(!manager.status().running);
+}
+
+#[test]
+fn startup_timeout_comes_from_the_config() {
+    let fake = FakeGateway::new(&[("mode", "hang")]);
+    let manager = fake.manager_with(free_port(), |config| {
+        config.gateway_startup_timeout_secs = 1
+    });
+
+    let started = Instant::now();
+    let err = manager.start().expect_err("start should time out").to_string();
+    assert!(err.contains("failed to start within 1 seconds"), "unexpected error: {}", err);
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert!(!manager.status().running);
+}
+
+#[test]
+fn a_start_can_wait_longer_and_a_timeout_reports_what_the_gateway_printed() {
+    let fake = FakeGateway::new(&[
+        ("mode", "hang"),
+        ("stdout", "Loading 212 plugins"),
+        ("stderr", "warning: scanning node_modules"),
+    ]);
+    let manager = fake.manager_with(free_port(), |config| {
+        config.gateway_startup_timeout_secs = 1
+    });
+
+    let started = Instant::now();
+    let err = manager
+        .start_with_timeout(Duration::from_secs(3))
+        .expect_err("start should time out")
+        .to_string();
+    assert!(started.elapsed() >= Duration::from_secs(3));
+    assert!(err.contains("failed to start within 3 seconds"), "unexpected error: {}", err);
+    assert!(err.contains("stdout:\nLoading 212 plugins"), "{}", err);
+    assert!(err.contains("stderr:\nwarning: scanning node_modules"), "{}", err);
+    let status = manager.status();
+    assert!(!status.running);
+    assert_eq!(status.pid, None);
+
+    // Only that start
+    let err = manager.start().expect_err("start should time out").to_string();
+    assert!(err.contains("failed to start within 1 seconds"), "unexpected error: {}", err);
+}
+
+#[test]
+fn a_gateway_printing_more_stderr_than_a_pipe_holds_still_starts() {
+    // Far more than any pipe buffer, before it listens
+    let fake = FakeGateway::new(&[("stderr_bytes", "1048576")]);
+    let manager = fake.manager_with(free_port(), |config| {
+        config.gateway_startup_timeout_secs = 10
+    });
+    manager.start().expect("start failed");
+    manager.stop().unwrap();
+
+    // A failed start reports only the end of it
+    let fake = FakeGateway::new(&[("mode", "exit"), ("stderr_bytes", "1048576")]);
+    let err = fake
+        .manager(free_port())
+        .start()
+        .expect_err("start should fail")
+        .to_string();
+    assert!(err.contains("npm warn deprecated"), "{}", err);
+    assert!(err.len() < 64 * 1024, "{} bytes", err.len());
+}
+
+#[test]
+fn port_held_by_foreign_process_fails() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(port);
+
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err, AppError::PortInUse { port: port.to_string() });
+    assert!(!manager.status().running);
+    drop(listener);
+}
+
+#[test]
+fn a_privileged_port_fails_the_start_with_a_free_port_to_use() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(443);
+
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "port_not_permitted");
+    let suggested: u16 = err.data()["suggested"].parse().unwrap();
+    assert!(!port_is_listening(suggested));
+    assert_eq!(manager.launch_info(), None);
+}
+
+#[test]
+fn a_port_held_start_after_start_gets_a_free_port_suggested() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(port);
+
+    // Until then it may be a gateway slow to exit
+    for _ in 1..PERSISTENT_CONFLICT_STARTS {
+        let err = manager.start().expect_err("start should fail");
+        assert_eq!(err, AppError::PortInUse { port: port.to_string() });
+    }
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "port_blocked");
+    assert_eq!(err.data()["port"], port.to_string());
+    assert_eq!(err.data()["starts"], PERSISTENT_CONFLICT_STARTS.to_string());
+    let suggested: u16 = err.data()["suggested"].parse().unwrap();
+    assert_ne!(suggested, port);
+    drop(listener);
+}
+
+#[test]
+fn a_taken_port_is_replaced_when_auto_select_is_on() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(port, |config| config.auto_select_port = true);
+
+    let result = manager.start().expect("start failed");
+    let serving = result.info.port.expect("no port");
+    assert_ne!(serving, port);
+    assert!(port_is_listening(serving));
+    assert!(result.warnings.iter().any(|w| w.code == "port_auto_selected"));
+
+    let status = manager.status();
+    assert_eq!(status.info.and_then(|info| info.port), Some(serving));
+    assert_eq!(status.configured_port, Some(port));
+    assert_eq!(status.effective_port, Some(serving));
+    assert_eq!(status.port_mismatch.unwrap().source, PortSource::AutoSelected);
+    manager.stop().unwrap();
+    drop(listener);
+}
+
+#[test]
+fn a_gateway_on_all_interfaces_is_flagged_as_lan_exposed() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = fake.manager_with(port, |config| config.gateway_host = "0.0.0.0".to_string());
+
+    let result = manager.start().expect("start failed");
+    assert!(result.info.lan_exposed);
+    assert!(!result.info.url.contains("0.0.0.0"), "{}", result.info.url);
+    assert!(result.warnings.iter().any(|w| w.code == "lan_exposed"));
+    assert!(manager.status().info.unwrap().lan_exposed);
+    manager.stop().unwrap();
+
+    let manager = fake.manager_with(port, |config| config.gateway_host = "lan".to_string());
+    assert_eq!(
+        manager.start().unwrap_err(),
+        AppError::InvalidGatewayHost {
+            host: "lan".to_string()
+        }
+    );
+}
+
+#[test]
+fn concurrent_starts_share_one_gateway() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = Arc::new(fake.manager(port));
+
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || manager.start())
+        })
+        .collect();
+    let tokens: Vec<String> = handles
+        .into_iter()
+        .map(|h| h.join().unwrap().expect("start failed").info.token)
+        .collect();
+
+    assert_eq!(tokens[0], tokens[1]);
+    assert_eq!(fake.env().get("OPENCLAW_GATEWAY_TOKEN"), Some(&tokens[0]));
+
+    manager.stop().unwrap();
+}
+
+#[test]
+fn gateway_info_never_starts_a_gateway() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    assert_eq!(manager.current_info(), None);
+    assert_eq!(manager.generation(), 0);
+
+    let info = manager.start().expect("start failed").info;
+    assert_eq!(manager.current_info(), Some(info));
+    manager.stop().unwrap();
+    assert_eq!(manager.current_info(), None);
+}
+
+#[test]
+fn a_running_gateway_that_lost_its_info_is_not_started_twice() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = fake.manager(port);
+    let info = manager.start().expect("start failed").info;
+    let launch = manager.launch_info();
+
+    manager.state.lock().unwrap().info = None;
+    assert_eq!(manager.current_info(), Some(info.clone()));
+
+    manager.state.lock().unwrap().info = None;
+    let again = manager.start().expect("second start failed").info;
+    assert_eq!(again, info);
+    assert_eq!(manager.generation(), info.generation);
+    assert_eq!(manager.launch_info(), launch);
+    assert_eq!(fake.env().get("OPENCLAW_GATEWAY_TOKEN"), Some(&info.token));
+    assert!(port_is_listening(port));
+
+    manager.stop().unwrap();
+    assert!(!port_is_listening(port));
+}
+
+#[test]
+fn many_simultaneous_starts_spawn_one_gateway_with_one_token() {
+    let fake = FakeGateway::serving();
+    let manager = Arc::new(fake.manager(free_port()));
+    let barrier = Arc::new(Barrier::new(16));
+
+    let handles: Vec<_> = (0..16)
+        .map(|_| {
+            let manager = Arc::clone(&manager);
+            let barrier = Arc::clone(&barrier);
+            std::thread::spawn(move || {
+                barrier.wait();
+                manager.start()
+            })
+        })
+        .collect();
+    let tokens: HashSet<String> = handles
+        .into_iter()
+        .map(|h| h.join().unwrap().expect("start failed").info.token)
+        .collect();
+
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(manager.generation(), 1);
+    let started = manager
+        .history()
+        .query(100, None)
+        .into_iter()
+        .filter(|entry| entry.kind == TransitionKind::Started)
+        .count();
+    assert_eq!(started, 1);
+    assert_eq!(
+        fake.env().get("OPENCLAW_GATEWAY_TOKEN"),
+        tokens.iter().next()
+    );
+
+    manager.stop().unwrap();
+}
+
+#[test]
+fn a_stop_cancels_a_start_in_progress() {
+    let fake = FakeGateway::new(&[("mode", "hang")]);
+    let port = free_port();
+    let manager = Arc::new(
+        fake.manager(port)
+            .with_startup_timeout(Duration::from_secs(30)),
+    );
+    let start = {
+        let manager = Arc::clone(&manager);
+        std::thread::spawn(move || manager.start())
+    };
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while manager.generation() == 0 || !manager.status().starting {
+        assert!(Instant::now() < deadline, "the start never showed");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let stopping = Instant::now();
+    manager.stop().unwrap();
+    let err = start.join().unwrap().unwrap_err();
+    assert_eq!(err, AppError::GatewayStartCancelled);
+    assert!(stopping.elapsed() < Duration::from_secs(5));
+    let status = manager.status();
+    assert!(!status.running && !status.starting);
+    assert_eq!(status.pid, None);
+}
+
+#[test]
+fn starts_stops_and_status_from_many_threads_never_run_two_gateways() {
+    let fake = FakeGateway::serving();
+    let manager = Arc::new(fake.manager(free_port()));
+
+    let handles: Vec<_> = (0..6)
+        .map(|i| {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || {
+                for _ in 0..2 {
+                    match i % 3 {
+                        0 => drop(manager.start()),
+                        1 => drop(manager.stop()),
+                        _ => drop(manager.status()),
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    manager.stop().unwrap();
+
+    // Every gateway was stopped before the next one started
+    let mut entries = manager.history().query(100, None);
+    entries.reverse();
+    let mut running = false;
+    for entry in entries {
+        match entry.kind {
+            TransitionKind::Started => {
+                assert!(!running, "a second gateway started");
+                running = true;
+            }
+            TransitionKind::Stopped | TransitionKind::Crashed => running = false,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn status_answers_while_a_start_waits_for_the_gateway() {
+    let fake = FakeGateway::new(&[("mode", "hang")]);
+    let manager = Arc::new(
+        fake.manager(free_port())
+            .with_startup_timeout(Duration::from_secs(3)),
+    );
+    let start = {
+        let manager = Arc::clone(&manager);
+        std::thread::spawn(move || manager.start())
+    };
+
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while !manager.status().starting {
+        assert!(Instant::now() < deadline, "the start never showed");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    std::thread::sleep(Duration::from_millis(500));
+    let asked = Instant::now();
+    let status = manager.status();
+    assert!(asked.elapsed() < Duration::from_millis(500));
+    assert!(status.starting && !status.running);
+
+    start.join().unwrap().unwrap_err();
+    assert!(!manager.status().starting);
+}
+
+#[test]
+fn clean_environment_passes_only_the_allow_list_and_injections() {
+    let fake = FakeGateway::serving();
+
+    // Cargo sets CARGO_* for the test process, so they show what leaks through
+    let inherited = fake.manager(free_port());
+    inherited.start().expect("start failed");
+    assert!(fake.env().contains_key("CARGO_MANIFEST_DIR"));
+    inherited.stop().expect("stop failed");
+
+    let clean = fake.manager_with(free_port(), |config| {
+        config.clean_environment = true;
+        config.gateway_max_memory_mb = Some(512);
+    });
+    let result = clean.start().expect("start failed");
+    let env = fake.env();
+    clean.stop().expect("stop failed");
+
+    assert_eq!(env.get("OPENCLAW_GATEWAY_TOKEN"), Some(&result.info.token));
+    assert_eq!(env.get("ANTHROPIC_API_KEY").map(String::as_str), Some(TEST_API_KEY));
+    assert_eq!(
+        env.get("NODE_OPTIONS").map(String::as_str),
+        Some("--max-old-space-size=512")
+    );
+    let unexpected: Vec<&String> = env
+        .keys()
+        .filter(|name| {
+            !simplestclaw_desktop::environment::is_allowed(name)
+                && ![
+                    "OPENCLAW_GATEWAY_TOKEN",
+                    "OPENCLAW_STATE_DIR",
+                    "TZ",
+                    "ANTHROPIC_API_KEY",
+                    "NODE_OPTIONS",
+                ]
+                .contains(&name.as_str())
+        })
+        .collect();
+    assert!(unexpected.is_empty(), "leaked: {:?}", unexpected);
+
+    let preview = clean.preview();
+    assert!(preview.clean_environment);
+    assert!(preview.env_names.iter().any(|name| name == "OPENCLAW_GATEWAY_TOKEN"));
+}
+
+#[test]
+fn port_override_from_the_startup_line_is_reported() {
+    let actual = free_port();
+    let fake = FakeGateway::new(&[("port_override", &actual.to_string())]);
+    let configured = free_port();
+    let manager = fake.manager(configured).with_startup_timeout(Duration::from_secs(10));
+
+    let result = manager.start().expect("start failed");
+    assert_eq!(result.info.port, Some(actual));
+    assert!(result.warnings.iter().any(|w| w.code == "port_overridden"));
+
+    let status = manager.status();
+    assert_eq!(status.configured_port, Some(configured));
+    assert_eq!(status.effective_port, Some(actual));
+    let mismatch = status.port_mismatch.expect("no port mismatch");
+    assert_eq!(mismatch.source, PortSource::GatewayOverride);
+    assert!(!mismatch.restart_resolves);
+    assert!(manager.port_change_requires_restart(configured));
+    assert!(!manager.port_change_requires_restart(actual));
+
+    manager.stop().expect("stop failed");
+    assert_eq!(manager.status().effective_port, None);
+}
+
+#[test]
+fn token_in_url_keeps_the_separate_token() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = fake.manager_with(port, |config| config.token_in_url = true);
+
+    let result = manager.start().expect("start failed");
+    assert_eq!(
+        result.info.url,
+        format!("ws://localhost:{}?token={}", port, result.info.token)
+    );
+    assert!(!result.info.token.is_empty());
+    assert!(result.warnings.iter().any(|w| w.code == "token_in_url"));
+
+    manager.stop().expect("stop failed");
+}
+
+#[test]
+fn a_crash_keeps_the_end_of_stderr_until_the_next_start() {
+    let fake = FakeGateway::new(&[
+        ("stderr", "FATAL: out of memory"),
+        ("exit_after_ms", "2000"),
+        ("exit_code", "134"),
+    ]);
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    assert_eq!(manager.last_crash(), None);
+    std::thread::sleep(Duration::from_millis(3000));
+
+    let status = manager.status();
+    assert!(!status.running);
+    let crash = status.last_crash.expect("crash not kept");
+    assert_eq!(crash.exit_code, Some(134));
+    assert_eq!(crash.stderr_tail, ["FATAL: out of memory"]);
+    assert_eq!(Some(crash.at), status.last_exit_at);
+    // A stop after the crash leaves it alone
+    manager.stop().unwrap();
+    assert_eq!(manager.last_crash(), Some(crash));
+
+    manager.start().expect("start failed");
+    assert_eq!(manager.status().last_crash, None);
+    manager.stop().unwrap();
+
+    // Nor is a stop a crash
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    manager.stop().unwrap();
+    assert_eq!(manager.status().last_crash, None);
+}
+
+#[test]
+fn openssl_crashes_are_explained_and_the_workaround_is_opt_in() {
+    let signature = "Error: error:0308010C:digital envelope routines::unsupported";
+
+    let at_start = FakeGateway::new(&[("mode", "exit"), ("exit_code", "1"), ("stderr", signature)]);
+    let manager = at_start.manager(free_port());
+    let err = manager.start().expect_err("start should fail").to_string();
+    assert!(err.contains("opensslLegacyProvider"), "{}", err);
+    let failures = manager.metrics().snapshot().start_failures_by_reason;
+    assert_eq!(failures.get("node_openssl_unsupported"), Some(&1));
+
+    let later = FakeGateway::new(&[("stderr", signature), ("exit_after_ms", "2000")]);
+    let manager = later.manager(free_port());
+    manager.start().expect("start failed");
+    assert_eq!(manager.status().error, None);
+    std::thread::sleep(Duration::from_millis(3000));
+    let status = manager.status();
+    assert!(!status.running);
+    assert_eq!(status.error.as_deref(), Some("node_openssl_unsupported"));
+
+    // Never set unless asked for
+    assert!(!later.env().get("NODE_OPTIONS").is_some_and(|o| o.contains("legacy")));
+    let fake = FakeGateway::serving();
+    let manager = fake.manager_with(free_port(), |config| {
+        config.clean_environment = true;
+        config.openssl_legacy_provider = true;
+    });
+    let result = manager.start().expect("start failed");
+    manager.stop().unwrap();
+    assert_eq!(
+        fake.env().get("NODE_OPTIONS").map(String::as_str),
+        Some("--openssl-legacy-provider")
+    );
+    assert!(result.warnings.iter().any(|w| w.code == "openssl_legacy_provider"));
+}
+
+/// Wait up to five seconds for the manager's pipe readers to be down to
+/// `count`
+fn readers_settle(manager: &SidecarManager, count: usize) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while manager.logs().active_readers() != count {
+        if Instant::now() > deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    true
+}
+
+#[test]
+fn pipe_readers_end_with_the_gateway_and_flush_its_last_line() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    assert_eq!(manager.logs().active_readers(), 2);
+    // The old gateway's readers end rather than piling up
+    manager.restart("test").expect("restart failed");
+    assert!(readers_settle(&manager, 2));
+    manager.stop().unwrap();
+    assert!(readers_settle(&manager, 0));
+
+    let fake = FakeGateway::new(&[
+        ("stdout_tail", "one\\n~bad\\nno newline"),
+        ("exit_after_ms", "2000"),
+    ]);
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    assert!(readers_settle(&manager, 0));
+    let logs = manager.logs().lock();
+    let stdout: Vec<&str> = logs
+        .lines()
+        .filter(|line| line.stream == "stdout")
+        .map(|line| line.text.as_str())
+        .collect();
+    assert_eq!(stdout[1..], ["one", "\u{fffd}bad", "no newline"]);
+}
+
+#[test]
+fn gateway_output_is_kept_across_restarts_with_a_marker() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    manager.stop().unwrap();
+    manager.start().expect("start failed");
+    manager.stop().unwrap();
+
+    let lines = manager.logs().recent(None);
+    let streams: Vec<&str> = lines.iter().map(|line| line.stream.as_str()).collect();
+    // The first start has nothing to tell apart from
+    assert_eq!(streams, ["stdout", MARKER_STREAM, "stdout"]);
+    assert_eq!(lines[1].text, RESTART_MARKER);
+    assert!(lines.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+}
+
+#[test]
+fn stop_asks_first_and_kills_only_when_ignored() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    assert_eq!(manager.stop().unwrap(), Some(StopKind::Graceful));
+    assert_eq!(manager.stop().unwrap(), None);
+
+    let stubborn = FakeGateway::new(&[("sigterm", "ignore")]);
+    let port = free_port();
+    let manager = stubborn.manager_with(port, |config| config.gateway_stop_grace_secs = 1);
+    manager.start().expect("start failed");
+    let stopping = Instant::now();
+    let stopped = manager.stop().unwrap();
+    if cfg!(unix) {
+        assert_eq!(stopped, Some(StopKind::Forced));
+        assert!(stopping.elapsed() >= Duration::from_secs(1));
+    }
+    assert!(!port_is_listening(port));
+    assert!(!manager.status().running);
+}
+
+#[cfg(unix)]
+#[test]
+fn a_paused_gateway_is_stopped_not_exited_and_still_stops_gracefully() {
+    let state = |pid: u32| {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "state=", "-p", &pid.to_string()])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    manager.start().expect("start failed");
+    let pid = manager.gateway_pid().unwrap();
+
+    manager.pause().expect("pause failed");
+    assert!(state(pid).starts_with('T'), "{}", state(pid));
+    let status = manager.status();
+    assert!(status.running && status.paused);
+
+    manager.resume().expect("resume failed");
+    assert!(!state(pid).starts_with('T'), "{}", state(pid));
+    assert!(!manager.status().paused);
+
+    manager.pause().expect("pause failed");
+    assert_eq!(manager.stop().unwrap(), Some(StopKind::Graceful));
+}
+
+/// Exit can be requested, signalled and fired for one shutdown
+#[test]
+fn stopping_again_does_nothing() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = Arc::new(fake.manager(port));
+    assert_eq!(manager.stop().unwrap(), None);
+    manager.start().expect("start failed");
+
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            let manager = Arc::clone(&manager);
+            std::thread::spawn(move || manager.stop().unwrap())
+        })
+        .collect();
+    let stopped: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(stopped.iter().filter(|stop| stop.is_some()).count(), 1);
+    assert_eq!(manager.stop().unwrap(), None);
+    assert!(!port_is_listening(port));
+
+    let stops = manager
+        .history()
+        .query(10, None)
+        .into_iter()
+        .filter(|entry| entry.kind == TransitionKind::Stopped)
+        .count();
+    assert_eq!(stops, 1);
+}
+
+#[test]
+fn restart_keeps_the_token_and_port() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = fake.manager(port);
+
+    // Nothing running yet: just a start
+    let first = manager.restart_keeping_token("test").expect("restart failed");
+    assert_eq!(first.info.port, Some(port));
+    let pid = manager.gateway_pid();
+
+    let second = manager.restart_keeping_token("test").expect("restart failed");
+    // The same connection details, from a new generation
+    assert_eq!(second.info.generation, first.info.generation + 1);
+    assert_eq!(
+        GatewayInfo {
+            generation: first.info.generation,
+            ..second.info.clone()
+        },
+        first.info
+    );
+    assert_ne!(manager.gateway_pid(), pid);
+    assert_eq!(fake.env().get("OPENCLAW_GATEWAY_TOKEN"), Some(&first.info.token));
+
+    // Later starts get a new token again
+    manager.stop().unwrap();
+    let third = manager.start().expect("start failed");
+    assert_ne!(third.info.token, first.info.token);
+    manager.stop().unwrap();
+}
+
+#[test]
+fn tokens_are_random_and_carry_32_bytes() {
+    let tokens: HashSet<String> = (0..1000).map(|_| generate_token().unwrap()).collect();
+    assert_eq!(tokens.len(), 1000);
+    for token in &tokens {
+        let hex = token.strip_prefix("sclw-").expect("missing prefix");
+        assert_eq!(hex.len(), TOKEN_BYTES * 2);
+        assert!(hex.bytes().all(|b| b.is_ascii_hexdigit()), "{}", token);
+    }
+}
+
+#[test]
+fn regenerating_the_token_restarts_with_a_new_one() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+
+    let first = manager.start().expect("start failed");
+    let second = manager.regenerate_token().expect("regenerate failed");
+    assert_ne!(second.info.token, first.info.token);
+    assert_eq!(second.info.port, first.info.port);
+    assert_eq!(fake.env().get("OPENCLAW_GATEWAY_TOKEN"), Some(&second.info.token));
+    assert_eq!(manager.status().info.map(|info| info.token), Some(second.info.token));
+    manager.stop().unwrap();
+}
+
+#[test]
+fn a_persisted_token_is_reused_until_regenerated() {
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let saved = Arc::new(Mutex::new(Vec::new()));
+    let saver = |manager: &SidecarManager| {
+        let saved = saved.clone();
+        manager.set_token_saver(Box::new(move |token| {
+            saved.lock().unwrap().push(token.to_string());
+            Ok(())
+        }));
+    };
+
+    // Minted on the first start and handed over to be kept
+    let first = fake.manager_with(port, |config| config.persist_gateway_token = true);
+    saver(&first);
+    let minted = first.start().expect("start failed").info.token;
+    first.stop().unwrap();
+    assert_eq!(*saved.lock().unwrap(), [minted.as_str()]);
+
+    let second = fake.manager_with(port, |config| {
+        config.persist_gateway_token = true;
+        config.gateway_token = Some(minted.clone());
+    });
+    saver(&second);
+    assert_eq!(second.start().expect("start failed").info.token, minted);
+    let regenerated = second.regenerate_token().expect("regenerate failed").info.token;
+    assert_ne!(regenerated, minted);
+    second.stop().unwrap();
+    assert_eq!(*saved.lock().unwrap(), [minted.clone(), regenerated]);
+
+    // Without persistence a kept token is ignored
+    let third = fake.manager_with(port, |config| config.gateway_token = Some(minted.clone()));
+    saver(&third);
+    assert_ne!(third.start().expect("start failed").info.token, minted);
+    third.stop().unwrap();
+    assert_eq!(saved.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn a_configured_openclaw_path_is_run_directly_and_checked() {
+    let fake = FakeGateway::serving();
+    let state = tempfile::tempdir().unwrap();
+    let config = |openclaw_path: PathBuf| Config {
+        anthropic_api_key: Some(TEST_API_KEY.to_string()),
+        gateway_port: free_port(),
+        gateway_state_dir: Some(state.path().to_path_buf()),
+        openclaw_path: Some(openclaw_path),
+        ..Config::default()
+    };
+
+    let manager =
+        SidecarManager::with_config(config(fake.binary().to_path_buf())).with_leftover_sweep(false);
+    manager.start().expect("start failed");
+    let executable = manager.status().executable.unwrap();
+    assert_eq!(executable.source, Some(ExecutableSource::Config));
+    assert_eq!(executable.path.as_deref(), fake.binary().to_str());
+    manager.stop().unwrap();
+
+    // Said plainly instead of falling back to the search
+    let missing = fake.binary().with_file_name("missing");
+    let manager = SidecarManager::with_config(config(missing.clone())).with_leftover_sweep(false);
+    let err = manager.start().unwrap_err();
+    assert_eq!(
+        err,
+        AppError::InvalidOpenclawPath {
+            path: missing.display().to_string()
+        }
+    );
+    let err = manager.ensure_ready(|_| {}).unwrap_err();
+    assert_eq!(err.error.code(), "invalid_openclaw_path");
+}
+
+#[test]
+fn a_plain_start_has_none_of_the_situational_warnings() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    let result = manager.start().expect("start failed");
+    let has = |code: &str| result.warnings.iter().any(|w| w.code == code);
+    for code in [
+        "port_reclaimed",
+        "npx_cache_not_cleared",
+        "system_node",
+        "npx_fallback",
+        "openclaw_outdated",
+    ] {
+        assert!(!has(code), "unexpected {}", code);
+    }
+    // Exactly in builds with debug assertions, like these tests
+    assert_eq!(has("dev_build"), cfg!(debug_assertions));
+    manager.stop().unwrap();
+}
+
+#[cfg(unix)]
+mod situational_warnings {
+    use std::fs;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    use simplestclaw_desktop::pid_file::{self, GatewayRecord, PidFile};
+
+    use super::*;
+
+    /// `fake` copied to `dir/name` with its `.conf` plus `extra`
+    fn copy_as(fake: &FakeGateway, dir: &Path, name: &str, extra: &str) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let copy = dir.join(name);
+        fs::copy(fake.binary(), &copy).unwrap();
+        let conf = fs::read_to_string(fake.binary().with_extension("conf")).unwrap();
+        fs::write(copy.with_extension("conf"), conf + extra).unwrap();
+        copy
+    }
+
+    #[test]
+    fn a_port_freed_from_a_leftover_gateway_is_reported() {
+        let fake = FakeGateway::serving();
+        let dir = tempfile::tempdir().unwrap();
+        // Not a gateway to the protocol probe, so not the user's to adopt
+        let leftover = copy_as(&fake, dir.path(), "leftover-gateway", "upgrade=token\n");
+        let port = free_port();
+        let mut child = Command::new(&leftover)
+            .args(["gateway", "--port", &port.to_string(), "--token", "sclw-leftover"])
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !port_is_listening(port) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(port_is_listening(port));
+        let pid_file = PidFile::new(dir.path().join(pid_file::FILE_NAME));
+        let manager = || {
+            fake.manager(port)
+                .with_pid_file(pid_file.clone())
+                .with_leftover_sweep(true)
+        };
+
+        // Unrecorded, it could be anyone's
+        let err = manager().start().expect_err("start should fail");
+        assert_eq!(err, AppError::PortInUse { port: port.to_string() });
+        assert!(matches!(child.try_wait(), Ok(None)));
+
+        let record = GatewayRecord {
+            pid: child.id(),
+            port,
+            token: "sclw-leftover".to_string(),
+            started_at: 0,
+        };
+        pid_file.write(&record).unwrap();
+        let manager = manager();
+        let result = manager.start().expect("start failed");
+        assert!(result.warnings.iter().any(|w| w.code == "port_reclaimed"));
+        let _ = child.wait();
+        manager.stop().unwrap();
+    }
+
+    /// A manager starting through npx with a node outside the app's
+    /// runtime, npm's npx beside it, and its npx cache at `dir/_npx`
+    fn npx_manager(fake: &FakeGateway, dir: &Path, prefer_global: bool) -> SidecarManager {
+        let node = copy_as(fake, &dir.join("node/bin"), "node", "");
+        let npx_cli = dir.join("node/lib/node_modules/npm/bin/npx-cli.js");
+        fs::create_dir_all(npx_cli.parent().unwrap()).unwrap();
+        fs::write(&npx_cli, "").unwrap();
+        SidecarManager::with_config(Config {
+            anthropic_api_key: Some(TEST_API_KEY.to_string()),
+            gateway_port: free_port(),
+            gateway_state_dir: Some(dir.join("gateway-state")),
+            node_path: Some(node),
+            prefer_global_openclaw: prefer_global,
+            start_max_retries: 0,
+            ..Config::default()
+        })
+        .with_npx_cache(dir.join("_npx"))
+        .with_leftover_sweep(false)
+    }
+
+    #[test]
+    fn a_system_node_is_reported_and_so_is_an_npx_cache_that_stays() {
+        let fake = FakeGateway::serving();
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("_npx");
+        fs::create_dir_all(cache.join("0a1b2c")).unwrap();
+        let manager = npx_manager(&fake, dir.path(), true);
+
+        let result = manager.start().expect("start failed");
+        let codes: Vec<&str> = result.warnings.iter().map(|w| w.code.as_str()).collect();
+        assert!(codes.contains(&"system_node"), "{:?}", codes);
+        assert!(!codes.contains(&"npx_cache_not_cleared"), "{:?}", codes);
+        // npx was asked for with preferGlobalOpenclaw
+        assert!(!codes.contains(&"npx_fallback"), "{:?}", codes);
+        assert!(!cache.exists());
+        manager.stop().unwrap();
+
+        // A file where the cache goes isn't removed as a directory
+        fs::write(&cache, "").unwrap();
+        let result = manager.start().expect("start failed");
+        assert!(result.warnings.iter().any(|w| w.code == "npx_cache_not_cleared"));
+        manager.stop().unwrap();
+    }
+
+    #[test]
+    fn falling_back_to_npx_without_a_bundled_openclaw_is_reported() {
+        // There is no openclaw beside the test binary
+        let fake = FakeGateway::serving();
+        let dir = tempfile::tempdir().unwrap();
+        let manager = npx_manager(&fake, dir.path(), false);
+
+        let result = manager.start().expect("start failed");
+        let warning = result.warnings.iter().find(|w| w.code == "npx_fallback");
+        assert!(warning.unwrap().message.contains("there is no openclaw"));
+        manager.stop().unwrap();
+    }
+}
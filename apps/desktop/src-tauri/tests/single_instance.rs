@@ -0,0 +1,84 @@
+//! The lock that keeps a second launch from starting a second gateway.
+
+use simplestclaw_desktop::single_instance::{self, Acquired, LockRecord};
+
+fn record(pid: u32) -> LockRecord {
+    LockRecord {
+        pid,
+        exe: "/Applications/simplestclaw.app/Contents/MacOS/simplestclaw".to_string(),
+        started_at: 0,
+    }
+}
+
+#[test]
+fn a_running_app_keeps_the_lock_until_it_releases_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(single_instance::FILE_NAME);
+
+    let Acquired::Locked(lock) = single_instance::acquire_with(&path, record(1), |_| true).unwrap()
+    else {
+        panic!("the first launch should get the lock");
+    };
+    assert_eq!(single_instance::read(&path), Some(record(1)));
+
+    match single_instance::acquire_with(&path, record(2), |_| true).unwrap() {
+        Acquired::Running(holder) => assert_eq!(holder, record(1)),
+        Acquired::Locked(_) => panic!("the second launch got the lock"),
+    }
+    assert_eq!(single_instance::read(&path), Some(record(1)));
+
+    lock.release();
+    assert!(!path.exists());
+    assert!(matches!(
+        single_instance::acquire_with(&path, record(2), |_| true).unwrap(),
+        Acquired::Locked(_)
+    ));
+}
+
+#[test]
+fn a_crashed_apps_lock_is_reclaimed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(single_instance::FILE_NAME);
+
+    // Left behind, its pid gone or now another program's
+    let stale = single_instance::acquire_with(&path, record(1), |_| true).unwrap();
+    std::mem::forget(stale);
+    let lock = match single_instance::acquire_with(&path, record(2), |_| false).unwrap() {
+        Acquired::Locked(lock) => lock,
+        Acquired::Running(_) => panic!("a stale lock was honoured"),
+    };
+    assert_eq!(single_instance::read(&path), Some(record(2)));
+    drop(lock);
+    assert!(!path.exists());
+
+    // Cut short mid-write
+    std::fs::write(&path, "{\"pid\":").unwrap();
+    assert!(matches!(
+        single_instance::acquire_with(&path, record(3), |_| true).unwrap(),
+        Acquired::Locked(_)
+    ));
+}
+
+#[test]
+fn only_a_live_process_of_the_recorded_executable_holds_the_lock() {
+    let exe = std::env::current_exe().unwrap();
+    let this = LockRecord {
+        pid: std::process::id(),
+        exe: exe.display().to_string(),
+        started_at: 0,
+    };
+    assert!(single_instance::is_running(&this));
+
+    // The same pid, but not the executable that took the lock
+    let reused = LockRecord {
+        exe: "/usr/bin/not-simplestclaw-at-all".to_string(),
+        ..this.clone()
+    };
+    assert!(!single_instance::is_running(&reused));
+
+    let exited = LockRecord {
+        pid: u32::MAX - 1,
+        ..this
+    };
+    assert!(!single_instance::is_running(&exited));
+}
@@ -0,0 +1,187 @@
+//! Spawn failure classification over synthetic evidence.
+
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use simplestclaw_desktop::process::ProcessExit;
+use simplestclaw_desktop::spawn_failure::{
+    classify, classify_stderr, find_security_products, Platform, SpawnEvidence, SpawnFailure,
+};
+
+fn spawn_error(platform: Platform, kind: ErrorKind, raw: Option<i32>) -> SpawnEvidence {
+    let mut evidence = SpawnEvidence::new(platform);
+    evidence.spawn_error = Some((kind, raw));
+    evidence
+}
+
+fn killed_after(platform: Platform, exit: ProcessExit, lifetime: Duration) -> SpawnEvidence {
+    let mut evidence = SpawnEvidence::new(platform);
+    evidence.exit = Some(exit);
+    evidence.lifetime = Some(lifetime);
+    evidence
+}
+
+const SIGKILL: ProcessExit = ProcessExit {
+    code: None,
+    signal: Some(9),
+};
+
+#[test]
+fn quarantined_binary_wins_over_the_raw_error() {
+    let mut evidence = spawn_error(Platform::MacOs, ErrorKind::PermissionDenied, Some(1));
+    evidence.quarantined = true;
+    assert_eq!(classify(&evidence), Some(SpawnFailure::Quarantined));
+}
+
+#[test]
+fn macos_eperm_is_not_permitted() {
+    let evidence = spawn_error(Platform::MacOs, ErrorKind::PermissionDenied, Some(1));
+    assert_eq!(classify(&evidence), Some(SpawnFailure::NotPermitted));
+}
+
+#[test]
+fn eacces_is_a_plain_permission_problem() {
+    let evidence = spawn_error(Platform::MacOs, ErrorKind::PermissionDenied, Some(13));
+    assert_eq!(classify(&evidence), Some(SpawnFailure::PermissionDenied));
+}
+
+#[test]
+fn windows_virus_errors_are_flagged_by_antivirus() {
+    for raw in [225, 226] {
+        let evidence = spawn_error(Platform::Windows, ErrorKind::Other, Some(raw));
+        assert_eq!(classify(&evidence), Some(SpawnFailure::FlaggedByAntivirus));
+    }
+}
+
+#[test]
+fn windows_policy_block_is_recognized() {
+    let evidence = spawn_error(Platform::Windows, ErrorKind::Other, Some(1260));
+    assert_eq!(classify(&evidence), Some(SpawnFailure::BlockedByPolicy));
+}
+
+#[test]
+fn windows_access_denied_blames_security_software_when_present() {
+    let mut evidence = spawn_error(Platform::Windows, ErrorKind::PermissionDenied, Some(5));
+    assert_eq!(classify(&evidence), Some(SpawnFailure::PermissionDenied));
+
+    evidence.security_products = vec!["CrowdStrike Falcon".to_string()];
+    assert_eq!(
+        classify(&evidence),
+        Some(SpawnFailure::KilledBySecuritySoftware {
+            product: "CrowdStrike Falcon".to_string()
+        })
+    );
+}
+
+#[test]
+fn unrelated_spawn_errors_are_not_classified() {
+    let evidence = spawn_error(Platform::Linux, ErrorKind::NotFound, Some(2));
+    assert_eq!(classify(&evidence), None);
+}
+
+#[test]
+fn sigkill_right_after_launch_is_killed_at_launch() {
+    let evidence = killed_after(Platform::MacOs, SIGKILL, Duration::from_millis(200));
+    assert_eq!(classify(&evidence), Some(SpawnFailure::KilledAtLaunch));
+}
+
+#[test]
+fn sigkill_with_security_software_names_the_product() {
+    let mut evidence = killed_after(Platform::Linux, SIGKILL, Duration::from_millis(200));
+    evidence.security_products = vec!["SentinelOne".to_string()];
+    assert_eq!(
+        classify(&evidence),
+        Some(SpawnFailure::KilledBySecuritySoftware {
+            product: "SentinelOne".to_string()
+        })
+    );
+}
+
+#[test]
+fn late_or_ordinary_exits_are_not_classified() {
+    let late = killed_after(Platform::MacOs, SIGKILL, Duration::from_secs(20));
+    assert_eq!(classify(&late), None);
+
+    let exit_code = killed_after(Platform::MacOs, ProcessExit::from_code(1), Duration::ZERO);
+    assert_eq!(classify(&exit_code), None);
+}
+
+#[test]
+fn windows_status_access_denied_exit_is_recognized() {
+    let exit = ProcessExit::from_code(0xC000_0022_u32 as i32);
+    let evidence = killed_after(Platform::Windows, exit, Duration::from_millis(100));
+    assert_eq!(classify(&evidence), Some(SpawnFailure::KilledAtLaunch));
+}
+
+#[test]
+fn security_products_are_found_by_process_name() {
+    let names = [
+        "/Library/CS/falcond",
+        "C:\\Program Files\\ESET\\ekrn.exe",
+        "MsMpEng.exe",
+        "falcond",
+        "node",
+    ];
+    assert_eq!(
+        find_security_products(names),
+        vec!["CrowdStrike Falcon", "ESET", "Microsoft Defender"]
+    );
+}
+
+/// Node 18 on a distro OpenSSL 3, hashing with md4
+const OPENSSL_UNSUPPORTED: &str = r#"node:internal/crypto/hash:71
+  this[kHandle] = new _Hash(algorithm, xofLen);
+                  ^
+
+Error: error:0308010C:digital envelope routines::unsupported
+    at new Hash (node:internal/crypto/hash:71:19)
+    at Object.createHash (node:crypto:133:10)
+    at module.exports (/home/me/.npm/_npx/2f1b/node_modules/loader-runner/lib/createHash.js:90:53) {
+  opensslErrorStack: [ 'error:03000086:digital envelope routines::initialization error' ],
+  library: 'digital envelope routines',
+  reason: 'unsupported',
+  code: 'ERR_OSSL_EVP_UNSUPPORTED'
+}
+
+Node.js v18.19.1"#;
+
+/// Node 16 given the flag through NODE_OPTIONS
+const LEGACY_NOT_ALLOWED: &str = "/usr/bin/node: --openssl-legacy-provider is not allowed in NODE_OPTIONS";
+
+/// A distro node whose OpenSSL ships without the legacy module
+const LEGACY_NOT_LOADED: &str = r#"node:internal/crypto/hash:69
+  this[kHandle] = new _Hash(algorithm, xofLen);
+                  ^
+
+Error: error:12800067:DSO support routines::could not load the shared library
+    at new Hash (node:internal/crypto/hash:69:19)
+    at Object.createHash (node:crypto:133:10) {
+  opensslErrorStack: [
+    'error:12800067:DSO support routines::could not load the shared library',
+    'error:07880025:common libcrypto routines::reason(37)',
+    'error:0308010C:digital envelope routines::unsupported'
+  ],
+  library: 'DSO support routines',
+  reason: 'could not load the shared library',
+  code: 'ERR_OSSL_DSO_COULD_NOT_LOAD_THE_SHARED_LIBRARY'
+}"#;
+
+#[test]
+fn openssl_failures_are_recognized_in_stderr() {
+    assert_eq!(
+        classify_stderr(OPENSSL_UNSUPPORTED),
+        Some(SpawnFailure::OpensslUnsupported)
+    );
+    for stderr in [LEGACY_NOT_ALLOWED, LEGACY_NOT_LOADED] {
+        assert_eq!(
+            classify_stderr(stderr),
+            Some(SpawnFailure::OpensslLegacyProviderFailed)
+        );
+    }
+    assert!(SpawnFailure::OpensslUnsupported
+        .describe()
+        .contains("NODE_OPTIONS=--openssl-legacy-provider"));
+
+    let unrelated = "Error: listen EADDRINUSE: address already in use 127.0.0.1:18789";
+    assert_eq!(classify_stderr(unrelated), None);
+}
@@ -0,0 +1,109 @@
+//! Estimating what the gateway spends from its output, and the alerts
+//! against the spend limit.
+
+mod support;
+
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::spend::{
+    check_limit, check_warn_at, parse_usage, SpendAlarm, SpendAlert, UsageEstimate, UsageSample,
+    SPEND_LIMIT_ENV,
+};
+use support::{free_port, FakeGateway};
+
+#[test]
+fn usage_is_read_whatever_the_wording() {
+    let sample = |input, output, cost| {
+        Some(UsageSample {
+            input_tokens: input,
+            output_tokens: output,
+            cost_usd: cost,
+        })
+    };
+    let lines = [
+        (
+            "[agent] usage input_tokens=1200 output_tokens=350 cost=$0.0089",
+            sample(1200, 350, Some(0.0089)),
+        ),
+        (
+            r#"{"usage":{"inputTokens":40,"outputTokens":8,"costUsd":0.0003}}"#,
+            sample(40, 8, Some(0.0003)),
+        ),
+        (
+            // The cache counts are not the input count
+            r#"{"cache_read_input_tokens":9000,"input_tokens":12,"output_tokens":3}"#,
+            sample(12, 3, None),
+        ),
+        // A wording it doesn't know is no usage
+        ("[llm] tokens in: 500, tokens out: 20", None),
+        ("[llm] prompt_tokens: 500 completion_tokens: 20", sample(500, 20, None)),
+        ("[ws] ⇄ res ✓ chat.send 412ms", None),
+        ("[billing] cost=$4.20", None),
+        ("input_tokens=many", None),
+        ("", None),
+    ];
+    for (line, expected) in lines {
+        assert_eq!(parse_usage(line), expected, "{:?}", line);
+    }
+}
+
+#[test]
+fn usage_without_a_cost_is_priced_at_the_fallback_rates() {
+    let mut usage = UsageEstimate::default();
+    usage.observe("usage input_tokens=1000000 output_tokens=0", 1_000);
+    usage.observe("[agent] thinking...", 1_100);
+    usage.observe("usage input_tokens=10 output_tokens=1000000 cost=0.5", 1_200);
+    assert_eq!(usage.input_tokens, 1_000_010);
+    assert_eq!(usage.output_tokens, 1_000_000);
+    assert!((usage.estimated_usd - 3.5).abs() < 1e-9, "{}", usage.estimated_usd);
+    assert_eq!(usage.reports, 2);
+    assert_eq!(usage.last_report_at, Some(1_200));
+}
+
+#[test]
+fn each_alert_comes_once_per_gateway() {
+    let mut alarm = SpendAlarm::default();
+    assert_eq!(alarm.observe(1, 5.0, 10.0, 0.8), None);
+    assert_eq!(alarm.observe(1, 8.0, 10.0, 0.8), Some(SpendAlert::Warning));
+    assert_eq!(alarm.observe(1, 9.0, 10.0, 0.8), None);
+    assert_eq!(alarm.observe(1, 10.0, 10.0, 0.8), Some(SpendAlert::LimitReached));
+    assert_eq!(alarm.observe(1, 12.0, 10.0, 0.8), None);
+
+    // The next gateway starts from nothing, and can go straight past both
+    assert_eq!(alarm.observe(2, 1.0, 10.0, 0.8), None);
+    assert_eq!(alarm.observe(2, 11.0, 10.0, 0.8), Some(SpendAlert::LimitReached));
+    assert_eq!(alarm.observe(2, 11.0, 10.0, 0.8), None);
+}
+
+#[test]
+fn the_limit_and_warning_share_must_make_sense() {
+    assert_eq!(check_limit(25.0), Ok(25.0));
+    assert!(check_limit(0.0).is_err());
+    assert!(check_limit(-1.0).is_err());
+    assert!(check_limit(f64::NAN).is_err());
+    assert_eq!(check_warn_at(1.0), Ok(1.0));
+    assert!(check_warn_at(0.0).is_err());
+    assert!(check_warn_at(1.5).is_err());
+}
+
+#[test]
+fn a_gateway_gets_the_limit_and_its_usage_is_estimated() {
+    let fake = FakeGateway::new(&[(
+        "stdout_tail",
+        "[agent] usage input_tokens=2000 output_tokens=100 cost=$0.25\\n[ws] odd line\\n",
+    )]);
+    let manager = fake.manager_with(free_port(), |config| config.spend_limit_usd = Some(5.0));
+    manager.start().expect("start failed");
+    assert_eq!(fake.env().get(SPEND_LIMIT_ENV).map(String::as_str), Some("5"));
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while manager.usage().reports == 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let usage = manager.usage();
+    assert_eq!((usage.input_tokens, usage.output_tokens), (2000, 100));
+    assert_eq!(usage.estimated_usd, 0.25);
+
+    manager.stop().unwrap();
+    assert_eq!(manager.usage(), UsageEstimate::default());
+}
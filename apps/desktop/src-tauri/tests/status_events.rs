@@ -0,0 +1,214 @@
+//! Ordering and coalescing of `gateway://status` events, on a fake clock,
+//! the transitions announced as `gateway://status-changed`, and telling
+//! stale `GatewayInfo` from an older generation.
+
+mod support;
+
+use std::io;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::process::ProcessExit;
+use simplestclaw_desktop::sidecar::ValidationResult;
+use simplestclaw_desktop::status_events::{
+    GatewayPhase, StartPhase, StartProgress, StatusCoalescer, StatusEvent,
+};
+use support::free_port;
+use support::mock::MockLauncher;
+
+const WINDOW: Duration = Duration::from_millis(250);
+
+fn ms(start: Instant, ms: u64) -> Instant {
+    start + Duration::from_millis(ms)
+}
+
+#[test]
+fn the_baseline_is_not_emitted() {
+    let start = Instant::now();
+    let mut coalescer = StatusCoalescer::new(WINDOW);
+    coalescer.observe("stopped", start);
+    coalescer.observe("stopped", ms(start, 100));
+    assert_eq!(coalescer.poll(ms(start, 1000)), None);
+    assert_eq!(coalescer.seq(), 0);
+}
+
+#[test]
+fn changes_within_the_window_become_one_event() {
+    let start = Instant::now();
+    let mut coalescer = StatusCoalescer::new(WINDOW);
+    coalescer.observe("stopped", start);
+
+    coalescer.observe("starting", ms(start, 100));
+    assert_eq!(coalescer.poll(ms(start, 100)), None);
+    coalescer.observe("running", ms(start, 200));
+    assert_eq!(coalescer.poll(ms(start, 300)), None);
+
+    // Due 250ms after the first change, with the newest snapshot
+    assert_eq!(
+        coalescer.poll(ms(start, 350)),
+        Some(StatusEvent {
+            seq: 1,
+            status: "running"
+        })
+    );
+    assert_eq!(coalescer.poll(ms(start, 600)), None);
+}
+
+#[test]
+fn every_net_change_gets_the_next_sequence_number() {
+    let start = Instant::now();
+    let mut coalescer = StatusCoalescer::new(WINDOW);
+    coalescer.observe(0, start);
+
+    let mut events = Vec::new();
+    for (i, status) in [1, 1, 2, 2, 2, 3].into_iter().enumerate() {
+        let now = ms(start, 300 * (i as u64 + 1));
+        coalescer.observe(status, now);
+        events.extend(coalescer.poll(now));
+        events.extend(coalescer.poll(now + WINDOW));
+    }
+    let seen: Vec<(u64, i32)> = events.iter().map(|e| (e.seq, e.status)).collect();
+    assert_eq!(seen, [(1, 1), (2, 2), (3, 3)]);
+}
+
+#[test]
+fn a_change_reverted_before_emitting_is_dropped() {
+    let start = Instant::now();
+    let mut coalescer = StatusCoalescer::new(WINDOW);
+    coalescer.observe("healthy", start);
+    coalescer.observe("unresponsive", ms(start, 100));
+    coalescer.observe("healthy", ms(start, 200));
+    assert_eq!(coalescer.poll(ms(start, 500)), None);
+
+    // A later change starts a fresh window
+    coalescer.observe("stopped", ms(start, 600));
+    assert_eq!(coalescer.poll(ms(start, 800)), None);
+    assert_eq!(coalescer.poll(ms(start, 850)).map(|e| e.seq), Some(1));
+}
+
+#[test]
+fn every_transition_is_announced_with_its_generation() {
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager(free_port());
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let seen = changes.clone();
+    manager.set_status_listener(Box::new(move |change| {
+        let change = (
+            change.generation,
+            change.phase,
+            change.exit_code,
+            change.status.running,
+        );
+        seen.lock().unwrap().push(change);
+    }));
+
+    manager.start().expect("start");
+    manager.start().expect("start");
+    launcher.control().exit = Some(ProcessExit::from_code(3));
+    assert!(!manager.status().running);
+    manager.start().expect("start");
+    manager.restart("user").expect("restart");
+    manager.stop().unwrap();
+    manager.stop().unwrap();
+
+    use GatewayPhase::*;
+    assert_eq!(
+        *changes.lock().unwrap(),
+        [
+            (1, Starting, None, false),
+            (1, Running, None, true),
+            (1, Exited, Some(3), false),
+            (2, Starting, None, false),
+            (2, Running, None, true),
+            (2, Restarting, None, false),
+            (3, Starting, None, false),
+            (3, Running, None, true),
+            (3, Stopped, None, false),
+        ]
+    );
+}
+
+#[test]
+fn a_start_reports_each_phase_it_enters_and_how_it_ended() {
+    let launcher = MockLauncher::new();
+    let port = free_port();
+    let manager = launcher.manager(port);
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let seen = reported.clone();
+    manager.set_progress_listener(Box::new(move |progress| {
+        seen.lock().unwrap().push(progress.clone());
+    }));
+
+    manager.start().expect("start");
+    // Already running, so no attempt
+    manager.start().expect("start");
+    manager.stop().unwrap();
+    launcher.control().spawn_error = Some(io::ErrorKind::PermissionDenied);
+    manager.start().unwrap_err();
+
+    use StartPhase::*;
+    let reported = reported.lock().unwrap();
+    let summary: Vec<(u64, Option<StartPhase>, &str)> = reported
+        .iter()
+        .map(|progress| match progress {
+            StartProgress::Phase(e) => (e.generation, Some(e.phase), "phase"),
+            StartProgress::Ready(e) => (e.generation, None, "ready"),
+            StartProgress::Retrying(e) => (e.generation, None, "retrying"),
+            StartProgress::Failed(e) => (e.generation, e.phase, e.error.code()),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, Some(LocatingBinary), "phase"),
+            (1, Some(CheckingPort), "phase"),
+            (1, Some(Spawning), "phase"),
+            (1, Some(WaitingReady), "phase"),
+            (1, None, "ready"),
+            (2, Some(LocatingBinary), "phase"),
+            (2, Some(CheckingPort), "phase"),
+            (2, Some(Spawning), "phase"),
+            (2, Some(Spawning), "gateway_spawn_failed"),
+        ]
+    );
+    let StartProgress::Ready(ref ready) = reported[4] else {
+        panic!("not ready: {:?}", reported[4]);
+    };
+    assert_eq!(ready.port, Some(port));
+    let StartProgress::Phase(ref first) = reported[0] else {
+        panic!("not a phase: {:?}", reported[0]);
+    };
+    assert!(first.at > 0);
+    assert!(ready.elapsed_ms >= first.elapsed_ms);
+}
+
+#[test]
+fn info_from_before_a_restart_to_another_port_is_stale() {
+    let launcher = MockLauncher::new();
+    let port = free_port();
+    let manager = launcher.manager_with(port, |config| config.auto_select_port = true);
+    let first = manager.start().expect("start").info;
+    assert_eq!(first.generation, 1);
+    let validation = manager.validate_info(&first);
+    assert!(validation.valid);
+    assert_eq!((validation.generation, validation.info), (Some(1), None));
+
+    // Crashes, and its port is taken before the restart
+    launcher.control().exit = Some(ProcessExit::from_code(3));
+    assert!(!manager.status().running);
+    let crash = manager.take_crash().expect("no crash");
+    let _taken = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    let second = manager.restart_after_crash(&crash).expect("restart").info;
+    assert_ne!(second.port, first.port);
+    assert_ne!(second.token, first.token);
+    assert_eq!(manager.status().generation, 2);
+
+    let validation = manager.validate_info(&first);
+    assert!(!validation.valid);
+    assert_eq!(validation.generation, Some(2));
+    assert_eq!(validation.info, Some(second.clone()));
+    assert!(manager.validate_info(&second).valid);
+    manager.stop().unwrap();
+    assert_eq!(manager.validate_info(&second), ValidationResult::default());
+}
@@ -0,0 +1,150 @@
+//! The status transition history: pruning, persistence and what the
+//! manager records.
+
+mod support;
+
+use std::fs;
+use std::time::Duration;
+
+use simplestclaw_desktop::heartbeat::GatewayHealth;
+use simplestclaw_desktop::status_history::{
+    HistoryFile, StatusHistory, StatusTransition, TransitionKind,
+};
+use support::{free_port, FakeGateway};
+
+fn at(timestamp: i64) -> StatusTransition {
+    StatusTransition {
+        timestamp,
+        kind: TransitionKind::Started,
+        cause: format!("start {}", timestamp),
+        exit_code: None,
+    }
+}
+
+fn timestamps(entries: &[StatusTransition]) -> Vec<i64> {
+    entries.iter().map(|entry| entry.timestamp).collect()
+}
+
+#[test]
+fn only_the_newest_entries_are_kept_and_queried_newest_first() {
+    let history = StatusHistory::new(3);
+    for timestamp in 1..=5 {
+        history.record(at(timestamp));
+    }
+    assert_eq!(timestamps(&history.query(10, None)), [5, 4, 3]);
+    assert_eq!(timestamps(&history.query(2, None)), [5, 4]);
+    assert_eq!(timestamps(&history.query(10, Some(4))), [5, 4]);
+}
+
+#[test]
+fn only_health_changes_while_running_are_recorded() {
+    let history = StatusHistory::new(10);
+    for health in [
+        GatewayHealth::Stopped,
+        GatewayHealth::Idle,
+        GatewayHealth::Idle,
+        GatewayHealth::Healthy,
+        GatewayHealth::Unresponsive,
+        GatewayHealth::Stopped,
+    ] {
+        history.observe_health(health);
+    }
+    // A new gateway's first health isn't a change from the old one's
+    history.record(StatusTransition::now(TransitionKind::Started, "again"));
+    history.observe_health(GatewayHealth::Idle);
+
+    let causes: Vec<String> = history
+        .query(10, None)
+        .into_iter()
+        .filter(|entry| entry.kind == TransitionKind::Health)
+        .map(|entry| entry.cause)
+        .collect();
+    assert_eq!(causes, ["healthy to unresponsive", "idle to healthy"]);
+}
+
+#[test]
+fn the_file_is_appended_to_and_pruned_at_twice_the_capacity() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+    let lines = || fs::read_to_string(&path).unwrap().lines().count();
+
+    let mut file = HistoryFile::open(&path, 3).unwrap();
+    for timestamp in 1..=6 {
+        file.append(at(timestamp)).unwrap();
+    }
+    assert_eq!(lines(), 6);
+    file.append(at(7)).unwrap();
+    assert_eq!(lines(), 3);
+    file.append(at(8)).unwrap();
+    assert_eq!(lines(), 4);
+
+    let reopened = HistoryFile::open(&path, 3).unwrap();
+    assert_eq!(timestamps(&reopened.entries()), [6, 7, 8]);
+    // Opening prunes too, to what it keeps
+    assert_eq!(lines(), 3);
+}
+
+#[test]
+fn unreadable_lines_are_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+    let entry = serde_json::to_string(&at(1)).unwrap();
+    fs::write(&path, format!("{}\nnot json\n{{\"half\":\n", entry)).unwrap();
+
+    let file = HistoryFile::open(&path, 10).unwrap();
+    assert_eq!(timestamps(&file.entries()), [1]);
+    assert_eq!(fs::read_to_string(&path).unwrap(), format!("{}\n", entry));
+}
+
+#[test]
+fn the_history_survives_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.jsonl");
+
+    let history = StatusHistory::open(&path, 3).unwrap();
+    for timestamp in 1..=4 {
+        history.record(at(timestamp));
+    }
+    history.flush();
+    drop(history);
+
+    let history = StatusHistory::open(&path, 3).unwrap();
+    assert_eq!(timestamps(&history.query(10, None)), [4, 3, 2]);
+}
+
+#[test]
+fn the_manager_records_starts_crashes_restarts_and_stops() {
+    let fake = FakeGateway::new(&[("exit_after_ms", "3000"), ("exit_code", "3")]);
+    let manager = fake.manager(free_port());
+
+    manager.start().expect("start failed");
+    manager.restart("unresponsive").expect("restart failed");
+    std::thread::sleep(Duration::from_millis(4000));
+    assert!(!manager.status().running);
+    manager.start().expect("start failed");
+    manager.stop().unwrap();
+
+    let mut entries = manager.history().query(10, None);
+    entries.reverse();
+    let kinds: Vec<TransitionKind> = entries.iter().map(|entry| entry.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TransitionKind::Started,
+            TransitionKind::Restarting,
+            TransitionKind::Started,
+            TransitionKind::Crashed,
+            TransitionKind::Started,
+            TransitionKind::Stopped,
+        ]
+    );
+    assert_eq!(entries[1].cause, "unresponsive");
+    assert_eq!(entries[3].exit_code, Some(3));
+
+    let failing = FakeGateway::new(&[("mode", "exit"), ("exit_code", "1")]);
+    let manager = failing.manager(free_port());
+    manager.start().unwrap_err();
+    let entries = manager.history().query(10, None);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].kind, TransitionKind::StartFailed);
+}
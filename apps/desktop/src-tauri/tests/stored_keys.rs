@@ -0,0 +1,185 @@
+//! Keeping several API keys and picking the one in use.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use simplestclaw_desktop::config::{Config, ProfileConfig};
+use simplestclaw_desktop::keychain::{stored_key_account, KeychainError, SecretStore, API_KEY_ACCOUNT};
+use simplestclaw_desktop::profiles;
+use simplestclaw_desktop::stored_keys::{self, StoredKeyError, DEFAULT_LABEL};
+
+#[derive(Default)]
+struct MemoryStore(Mutex<HashMap<String, String>>);
+
+impl SecretStore for MemoryStore {
+    fn get(&self, account: &str) -> Result<Option<String>, KeychainError> {
+        Ok(self.0.lock().unwrap().get(account).cloned())
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<(), KeychainError> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<(), KeychainError> {
+        self.0.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+fn unset() -> Config {
+    Config {
+        anthropic_api_key: None,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn stored_keys_are_listed_by_label_without_the_key() {
+    let store = MemoryStore::default();
+    let mut config = unset();
+    stored_keys::add(&mut config, "work", "sk-ant-work-000000wxyz".to_string(), &store).unwrap();
+    stored_keys::add(&mut config, "personal", "sk-ant-mine-00000abcd".to_string(), &store)
+        .unwrap();
+
+    let keys = stored_keys::list(&config);
+    let labels: Vec<&str> = keys.iter().map(|k| k.label.as_str()).collect();
+    assert_eq!(labels, ["personal", "work"]);
+    assert_eq!(keys[0].hint, "sk-ant-…abcd");
+    assert!(keys.iter().all(|k| !k.active));
+    // In the keychain, not the file, and nothing is in use yet
+    assert_eq!(
+        store.get(&stored_key_account("work")).unwrap().as_deref(),
+        Some("sk-ant-work-000000wxyz")
+    );
+    assert!(config.api_keys.iter().all(|k| k.key.is_none() && k.key_in_keychain));
+    assert_eq!(config.anthropic_api_key, None);
+}
+
+#[test]
+fn activating_a_key_makes_it_the_one_in_use() {
+    let store = MemoryStore::default();
+    let mut config = unset();
+    stored_keys::add(&mut config, "work", "sk-ant-work".to_string(), &store).unwrap();
+    stored_keys::add(&mut config, "trial", "sk-ant-trial".to_string(), &store).unwrap();
+
+    stored_keys::activate(&mut config, "work", &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-work"));
+    assert_eq!(config.active_api_key.as_deref(), Some("work"));
+    assert_eq!(store.get(API_KEY_ACCOUNT).unwrap().as_deref(), Some("sk-ant-work"));
+
+    // Replacing the active key replaces the key in use
+    stored_keys::add(&mut config, "work", "sk-ant-work-rotated".to_string(), &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-work-rotated"));
+    assert_eq!(config.api_keys.len(), 2);
+
+    stored_keys::activate(&mut config, "trial", &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-trial"));
+    let active: Vec<String> = stored_keys::list(&config)
+        .into_iter()
+        .filter(|k| k.active)
+        .map(|k| k.label)
+        .collect();
+    assert_eq!(active, ["trial"]);
+}
+
+#[test]
+fn the_key_in_use_cant_be_removed() {
+    let store = MemoryStore::default();
+    let mut config = unset();
+    stored_keys::add(&mut config, "work", "sk-ant-work".to_string(), &store).unwrap();
+    stored_keys::add(&mut config, "trial", "sk-ant-trial".to_string(), &store).unwrap();
+    stored_keys::activate(&mut config, "work", &store).unwrap();
+
+    assert_eq!(
+        stored_keys::remove(&mut config, "work", &store),
+        Err(StoredKeyError::Active("work".to_string()))
+    );
+    stored_keys::remove(&mut config, "trial", &store).unwrap();
+    assert_eq!(store.get(&stored_key_account("trial")).unwrap(), None);
+    assert_eq!(config.api_keys.len(), 1);
+
+    assert_eq!(
+        stored_keys::remove(&mut config, "trial", &store),
+        Err(StoredKeyError::Unknown("trial".to_string()))
+    );
+    assert_eq!(
+        stored_keys::activate(&mut config, "trial", &store),
+        Err(StoredKeyError::Unknown("trial".to_string()))
+    );
+}
+
+#[test]
+fn labels_are_checked() {
+    let store = MemoryStore::default();
+    let mut config = unset();
+    let long = "k".repeat(65);
+    for label in ["", "my key", "../work", long.as_str()] {
+        let error = stored_keys::add(&mut config, label, "sk-ant".to_string(), &store).unwrap_err();
+        assert_eq!(error, StoredKeyError::InvalidLabel(label.to_string()));
+    }
+    assert!(config.api_keys.is_empty());
+    assert!(stored_keys::validate_label("team_2-trial").is_ok());
+}
+
+#[test]
+fn a_key_set_before_stored_keys_becomes_the_default_one() {
+    let store = MemoryStore::default();
+    let mut config = Config {
+        anthropic_api_key: Some("sk-ant-existing".to_string()),
+        ..Config::default()
+    };
+    assert!(stored_keys::adopt_current(&mut config, &store).unwrap());
+    assert_eq!(config.active_api_key.as_deref(), Some(DEFAULT_LABEL));
+    assert_eq!(
+        store.get(&stored_key_account(DEFAULT_LABEL)).unwrap().as_deref(),
+        Some("sk-ant-existing")
+    );
+    // Only once
+    assert!(!stored_keys::adopt_current(&mut config, &store).unwrap());
+
+    let mut config = unset();
+    assert!(!stored_keys::adopt_current(&mut config, &store).unwrap());
+    assert!(config.api_keys.is_empty());
+}
+
+#[test]
+fn with_store_api_key_in_file_stored_keys_stay_in_the_file() {
+    let store = MemoryStore::default();
+    let mut config = Config {
+        store_api_key_in_file: true,
+        ..unset()
+    };
+    stored_keys::add(&mut config, "work", "sk-ant-work".to_string(), &store).unwrap();
+    assert_eq!(config.api_keys[0].key.as_deref(), Some("sk-ant-work"));
+    assert!(!config.api_keys[0].key_in_keychain);
+    assert_eq!(store.get(&stored_key_account("work")).unwrap(), None);
+
+    stored_keys::activate(&mut config, "work", &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-work"));
+}
+
+#[test]
+fn switching_profile_leaves_no_stored_key_active() {
+    let store = MemoryStore::default();
+    let mut config = unset();
+    stored_keys::add(&mut config, "work", "sk-ant-work".to_string(), &store).unwrap();
+    stored_keys::activate(&mut config, "work", &store).unwrap();
+    config.profiles.insert(
+        "side".to_string(),
+        ProfileConfig {
+            api_key: Some("sk-ant-side".to_string()),
+            api_key_in_keychain: false,
+            gateway_port: 18900,
+            gateway_extra_args: Vec::new(),
+        },
+    );
+
+    profiles::switch(&mut config, "side", &store).unwrap();
+    assert_eq!(config.anthropic_api_key.as_deref(), Some("sk-ant-side"));
+    assert_eq!(config.active_api_key, None);
+    assert!(stored_keys::list(&config).iter().all(|k| !k.active));
+}
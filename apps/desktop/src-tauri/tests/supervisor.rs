@@ -0,0 +1,122 @@
+//! Crash restarts: the backoff and what `supervise` does with a crash.
+
+mod support;
+
+use std::time::{Duration, Instant};
+
+use simplestclaw_desktop::sidecar::SidecarManager;
+use simplestclaw_desktop::supervisor::{self, CrashEvent, Notice, RestartBackoff};
+use support::{free_port, FakeGateway};
+
+const STABLE: Duration = Duration::from_secs(60);
+
+fn quick_backoff() -> RestartBackoff {
+    RestartBackoff::new(Duration::from_millis(10), Duration::from_millis(40), STABLE)
+}
+
+/// Wait up to ten seconds for the gateway to exit
+fn crashed(manager: &SidecarManager) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while manager.status().running {
+        if Instant::now() > deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    true
+}
+
+fn notices(manager: &SidecarManager) -> Vec<Notice> {
+    let mut notices = Vec::new();
+    supervisor::supervise(manager, |notice| notices.push(notice));
+    notices
+}
+
+#[test]
+fn delays_double_up_to_the_cap_and_reset_after_a_stable_run() {
+    let mut backoff = RestartBackoff::default();
+    let delays: Vec<u64> = (0..7)
+        .map(|_| backoff.next_delay(Some(Duration::from_secs(1))).as_secs())
+        .collect();
+    assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+    assert_eq!(backoff.consecutive(), 7);
+
+    assert_eq!(backoff.next_delay(Some(STABLE)), Duration::from_secs(1));
+    assert_eq!(backoff.consecutive(), 1);
+    backoff.reset();
+    assert_eq!(backoff.next_delay(None), Duration::from_secs(1));
+}
+
+#[test]
+fn a_crashed_gateway_comes_back_with_the_same_token() {
+    let fake = FakeGateway::new(&[("exit_after_ms", "2000"), ("exit_code", "3")]);
+    let manager = fake
+        .manager(free_port())
+        .with_restart_backoff(quick_backoff());
+    let token = manager.start().expect("start failed").info.token;
+    assert!(crashed(&manager));
+
+    let notices = notices(&manager);
+    let [Notice::Crashed(crash), Notice::Restarted(restart)] = &notices[..] else {
+        panic!("unexpected notices: {:?}", notices);
+    };
+    assert_eq!(
+        *crash,
+        CrashEvent {
+            generation: 1,
+            exit_code: Some(3),
+            signal: None,
+            restarts: 1,
+            restart_in_ms: Some(10),
+            stderr_tail: crash.stderr_tail.clone(),
+            at: crash.at,
+            launch: manager.launch_info(),
+        }
+    );
+    assert!(crash.launch.is_some());
+    assert_eq!(restart.reason, "crashed");
+    let result = restart.result.as_ref().expect("restart failed");
+    assert_eq!(result.info.token, token);
+    assert_eq!(result.info.generation, crash.generation + 1);
+    let status = manager.status();
+    assert!(status.running);
+    assert_eq!(status.crash_restarts, 1);
+
+    // An explicit stop starts the count over
+    manager.stop().unwrap();
+    assert_eq!(manager.status().crash_restarts, 0);
+}
+
+#[test]
+fn a_stop_is_not_a_crash() {
+    let fake = FakeGateway::new(&[("exit_after_ms", "2000")]);
+    let manager = fake
+        .manager(free_port())
+        .with_restart_backoff(quick_backoff());
+    manager.start().expect("start failed");
+    manager.stop().unwrap();
+    assert!(notices(&manager).is_empty());
+
+    // Nor is a crash the gateway was stopped after
+    manager.start().expect("start failed");
+    assert!(crashed(&manager));
+    manager.stop().unwrap();
+    assert!(notices(&manager).is_empty());
+}
+
+#[test]
+fn crashes_are_only_reported_when_auto_restart_is_off() {
+    let fake = FakeGateway::new(&[("exit_after_ms", "2000"), ("exit_code", "3")]);
+    let manager = fake
+        .manager_with(free_port(), |config| config.auto_restart_gateway = false)
+        .with_restart_backoff(quick_backoff());
+    manager.start().expect("start failed");
+    assert!(crashed(&manager));
+
+    let notices = notices(&manager);
+    let [Notice::Crashed(crash)] = &notices[..] else {
+        panic!("unexpected notices: {:?}", notices);
+    };
+    assert_eq!(crash.restart_in_ms, None);
+    assert!(!manager.status().running);
+}
@@ -0,0 +1,187 @@
+//! In-process stand-in for the OS process launcher.
+//!
+//! `MockLauncher` hands out `MockProcess`es whose behaviour is driven through
+//! a shared `MockControl`, so tests can make spawning, `try_wait` or `kill`
+//! fail, or make a running gateway "exit", without starting real processes.
+//! A serving mock binds the `--port` from the launch spec so the manager's
+//! readiness check passes.
+
+use std::io::{self, Cursor, Read};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::process::{GatewayProcess, LaunchSpec, ProcessExit, ProcessLauncher};
+use simplestclaw_desktop::sidecar::SidecarManager;
+
+use super::TEST_API_KEY;
+
+#[derive(Default)]
+pub struct MockControl {
+    /// Error kind returned by `spawn`, if spawning should fail
+    pub spawn_error: Option<io::ErrorKind>,
+    /// Exit reported by `try_wait` for the current process once set
+    pub exit: Option<ProcessExit>,
+    /// Exit every newly spawned process starts out with
+    pub exit_on_spawn: Option<ProcessExit>,
+    /// Close the current process's port while it keeps running
+    pub closed: bool,
+    /// Make `try_wait` return an error
+    pub try_wait_error: bool,
+    /// Make `kill` return an error
+    pub kill_error: bool,
+    /// Whether the current process is suspended
+    pub suspended: bool,
+    /// Number of `resume` calls
+    pub resumed: usize,
+    /// Text handed out as the process's stderr
+    pub stderr: String,
+    /// Number of successful spawns
+    pub spawned: usize,
+    /// Number of `kill` calls
+    pub killed: usize,
+    /// Number of processes dropped. On Windows the job handle closes with
+    /// the process, so this counts kill-on-close releases.
+    pub dropped: usize,
+    /// Spec of the most recent spawn
+    pub last_spec: Option<LaunchSpec>,
+}
+
+#[derive(Clone, Default)]
+pub struct MockLauncher {
+    pub control: Arc<Mutex<MockControl>>,
+}
+
+impl MockLauncher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A manager on `port` that spawns through this launcher
+    pub fn manager(&self, port: u16) -> SidecarManager {
+        self.manager_with(port, |_| {})
+    }
+
+    /// Like `manager`, with the config adjusted by `configure`
+    pub fn manager_with(&self, port: u16, configure: impl FnOnce(&mut Config)) -> SidecarManager {
+        let mut config = Config {
+            anthropic_api_key: Some(TEST_API_KEY.to_string()),
+            gateway_port: port,
+            // Not spawned for real, but still created at start
+            gateway_state_dir: Some(std::env::temp_dir().join("simplestclaw-mock-gateway-state")),
+            // The mock accepts connections but speaks no protocol
+            self_test_on_start: false,
+            // A failing start fails at once unless a test is about retrying
+            start_max_retries: 0,
+            ..Config::default()
+        };
+        configure(&mut config);
+        SidecarManager::with_binary("mock-gateway", config)
+            .with_launcher(Box::new(self.clone()))
+            .with_leftover_sweep(false)
+    }
+
+    pub fn control(&self) -> std::sync::MutexGuard<'_, MockControl> {
+        self.control.lock().unwrap()
+    }
+}
+
+impl ProcessLauncher for MockLauncher {
+    fn spawn(&self, spec: &LaunchSpec) -> io::Result<Box<dyn GatewayProcess>> {
+        let mut control = self.control();
+        if let Some(kind) = control.spawn_error {
+            return Err(io::Error::from(kind));
+        }
+
+        // Only listen while the mock is meant to be running
+        control.exit = control.exit_on_spawn;
+        let listener = if control.exit.is_none() {
+            let port = port_arg(spec).expect("launch spec without --port");
+            Some(TcpListener::bind(("127.0.0.1", port))?)
+        } else {
+            None
+        };
+
+        control.spawned += 1;
+        control.last_spec = Some(spec.clone());
+        Ok(Box::new(MockProcess {
+            id: 10_000 + control.spawned as u32,
+            control: self.control.clone(),
+            listener,
+        }))
+    }
+}
+
+pub struct MockProcess {
+    id: u32,
+    control: Arc<Mutex<MockControl>>,
+    listener: Option<TcpListener>,
+}
+
+impl GatewayProcess for MockProcess {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ProcessExit>> {
+        let control = self.control.lock().unwrap();
+        if control.try_wait_error {
+            return Err(io::Error::other("try_wait failed"));
+        }
+        if control.exit.is_some() || control.closed {
+            self.listener = None;
+        }
+        Ok(control.exit)
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        let mut control = self.control.lock().unwrap();
+        control.killed += 1;
+        if control.kill_error {
+            return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+        }
+        self.listener = None;
+        control.exit.get_or_insert(ProcessExit {
+            code: None,
+            signal: Some(9),
+        });
+        Ok(())
+    }
+
+    fn wait(&mut self) -> io::Result<ProcessExit> {
+        let control = self.control.lock().unwrap();
+        Ok(control.exit.unwrap_or(ProcessExit::from_code(0)))
+    }
+
+    fn suspend(&mut self) -> io::Result<()> {
+        self.control.lock().unwrap().suspended = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> io::Result<()> {
+        let mut control = self.control.lock().unwrap();
+        control.suspended = false;
+        control.resumed += 1;
+        Ok(())
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        None
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        let stderr = self.control.lock().unwrap().stderr.clone();
+        Some(Box::new(Cursor::new(stderr.into_bytes())))
+    }
+}
+
+impl Drop for MockProcess {
+    fn drop(&mut self) {
+        self.control.lock().unwrap().dropped += 1;
+    }
+}
+
+fn port_arg(spec: &LaunchSpec) -> Option<u16> {
+    let index = spec.args.iter().position(|arg| arg == "--port")?;
+    spec.args.get(index + 1)?.parse().ok()
+}
@@ -0,0 +1,141 @@
+//! Shared helpers for the integration tests.
+//!
+//! `FakeGateway` copies the `fake_gateway` example binary into a temporary
+//! directory together with its `.conf` file, so every test gets its own
+//! independently configured gateway without touching the environment or the
+//! user's config.json.
+
+#![allow(dead_code)]
+
+pub mod mock;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::paths::DiskSpace;
+use simplestclaw_desktop::sidecar::SidecarManager;
+use tempfile::TempDir;
+
+pub const TEST_API_KEY: &str = "sk-ant-test-key";
+
+pub struct FakeGateway {
+    dir: TempDir,
+    binary: PathBuf,
+}
+
+impl FakeGateway {
+    /// A fake gateway that binds its port and serves until killed
+    pub fn serving() -> Self {
+        Self::new(&[])
+    }
+
+    /// A fake gateway configured with the given `.conf` entries
+    /// (see `examples/fake_gateway.rs` for the supported keys)
+    pub fn new(conf: &[(&str, &str)]) -> Self {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let binary = dir
+            .path()
+            .join(format!("fake_gateway{}", std::env::consts::EXE_SUFFIX));
+        fs::copy(fake_gateway_binary(), &binary).expect("failed to copy fake gateway");
+
+        let env_dump = dir.path().join("env.txt");
+        let mut contents = format!("env_dump={}\n", env_dump.display());
+        for (key, value) in conf {
+            contents.push_str(&format!("{}={}\n", key, value));
+        }
+        fs::write(binary.with_extension("conf"), contents).expect("failed to write conf");
+
+        Self { dir, binary }
+    }
+
+    /// A manager that runs this fake gateway on `port`
+    pub fn manager(&self, port: u16) -> SidecarManager {
+        self.manager_with(port, |_| {})
+    }
+
+    /// Like `manager`, with the config adjusted by `configure`
+    pub fn manager_with(&self, port: u16, configure: impl FnOnce(&mut Config)) -> SidecarManager {
+        let mut config = Config {
+            anthropic_api_key: Some(TEST_API_KEY.to_string()),
+            gateway_port: port,
+            gateway_state_dir: Some(self.dir.path().join("gateway-state")),
+            // A failing start fails at once unless a test is about retrying
+            start_max_retries: 0,
+            ..Config::default()
+        };
+        configure(&mut config);
+        // Never the developer's own gateway
+        SidecarManager::with_binary(&self.binary, config).with_leftover_sweep(false)
+    }
+
+    pub fn binary(&self) -> &Path {
+        &self.binary
+    }
+
+    /// Environment the fake gateway was started with
+    pub fn env(&self) -> HashMap<String, String> {
+        let contents = fs::read_to_string(self.dir.path().join("env.txt")).unwrap_or_default();
+        contents
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+}
+
+/// Volumes mounted at the given paths with the given free bytes. Paths on
+/// no volume fail like an unreadable filesystem.
+pub struct FakeDisk(pub Vec<(PathBuf, u64)>);
+
+impl FakeDisk {
+    /// One volume holding everything
+    pub fn everywhere(available: u64) -> Self {
+        Self(vec![(PathBuf::from("/"), available)])
+    }
+}
+
+impl DiskSpace for FakeDisk {
+    fn available(&self, path: &Path) -> io::Result<u64> {
+        self.0
+            .iter()
+            .filter(|(mount, _)| path.starts_with(mount))
+            .max_by_key(|(mount, _)| mount.components().count())
+            .map(|(_, available)| *available)
+            .ok_or_else(|| io::Error::other("no volume"))
+    }
+}
+
+/// A port nothing is currently listening on
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .expect("failed to find a free port")
+}
+
+pub fn port_is_listening(port: u16) -> bool {
+    std::net::TcpStream::connect(("127.0.0.1", port)).is_ok()
+}
+
+/// Path of the compiled `fake_gateway` example. Cargo builds examples for
+/// `cargo test`, next to the `deps` directory holding the test binary.
+fn fake_gateway_binary() -> PathBuf {
+    let test_exe = std::env::current_exe().expect("no current exe");
+    let profile_dir = test_exe
+        .parent()
+        .and_then(|deps| deps.parent())
+        .expect("unexpected test binary location");
+    let binary = profile_dir
+        .join("examples")
+        .join(format!("fake_gateway{}", std::env::consts::EXE_SUFFIX));
+    assert!(
+        binary.exists(),
+        "fake gateway not built at {} - run through `cargo test`",
+        binary.display()
+    );
+    binary
+}
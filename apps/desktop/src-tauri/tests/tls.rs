@@ -0,0 +1,113 @@
+//! Starting a gateway that serves TLS.
+
+mod support;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use simplestclaw_desktop::config::TlsConfig;
+use simplestclaw_desktop::tls;
+use support::{free_port, port_is_listening, FakeGateway};
+use support::mock::MockLauncher;
+
+#[test]
+fn tls_gateway_gets_its_files_and_a_wss_url() {
+    let dir = tempfile::tempdir().unwrap();
+    let tls = TlsConfig {
+        cert_path: dir.path().join("cert.pem"),
+        key_path: dir.path().join("key.pem"),
+    };
+    std::fs::write(&tls.cert_path, "certificate").unwrap();
+    std::fs::write(&tls.key_path, "key").unwrap();
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager_with(free_port(), |config| config.tls = Some(tls.clone()));
+
+    let info = manager.start().expect("start").info;
+    assert!(info.url.starts_with("wss://"), "{}", info.url);
+    let args = launcher
+        .control()
+        .last_spec
+        .as_ref()
+        .unwrap()
+        .args
+        .join(" ");
+    let flags = format!(
+        "--tls-cert {} --tls-key {}",
+        tls.cert_path.display(),
+        tls.key_path.display()
+    );
+    assert!(args.contains(&flags), "{}", args);
+    manager.stop().unwrap();
+}
+
+#[test]
+fn missing_certificate_refuses_the_start() {
+    let dir = tempfile::tempdir().unwrap();
+    let key_path = dir.path().join("key.pem");
+    std::fs::write(&key_path, "key").unwrap();
+    let cert_path = dir.path().join("missing.pem");
+    let launcher = MockLauncher::new();
+    let manager = launcher.manager_with(free_port(), |config| {
+        config.tls = Some(TlsConfig {
+            cert_path: cert_path.clone(),
+            key_path,
+        })
+    });
+
+    let err = manager.start().expect_err("start should fail");
+    assert_eq!(err.code(), "tls_config_invalid");
+    assert_eq!(err.data()["path"], cert_path.display().to_string());
+    assert_eq!(launcher.control().spawned, 0);
+    assert_eq!(
+        manager
+            .metrics()
+            .snapshot()
+            .start_failures_by_reason
+            .get("tls"),
+        Some(&1)
+    );
+}
+
+#[test]
+fn a_gateway_not_serving_tls_fails_the_start_and_is_counted() {
+    let dir = tempfile::tempdir().unwrap();
+    let tls = TlsConfig {
+        cert_path: dir.path().join("cert.pem"),
+        key_path: dir.path().join("key.pem"),
+    };
+    std::fs::write(&tls.cert_path, "certificate").unwrap();
+    std::fs::write(&tls.key_path, "key").unwrap();
+    // Takes the flags, and serves plain HTTP anyway
+    let fake = FakeGateway::serving();
+    let port = free_port();
+    let manager = fake.manager_with(port, |config| config.tls = Some(tls.clone()));
+
+    let err = manager.start().expect_err("start should fail");
+    assert!(err.to_string().contains("TLS handshake failed"), "{}", err);
+    assert!(!manager.status().running);
+    assert!(!port_is_listening(port));
+    assert_eq!(
+        manager
+            .metrics()
+            .snapshot()
+            .start_failures_by_reason
+            .get("tls_handshake"),
+        Some(&1)
+    );
+}
+
+#[test]
+fn handshake_fails_against_plain_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        if let Ok((mut stream, _)) = listener.accept() {
+            let _ = stream.read(&mut [0; 1024]);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        }
+    });
+
+    let err = tls::handshake(address, Duration::from_secs(5)).expect_err("plain HTTP");
+    assert!(err.starts_with("the TLS handshake failed"), "{}", err);
+}
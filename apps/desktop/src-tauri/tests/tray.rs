@@ -0,0 +1,130 @@
+//! Tray menu state updates.
+
+use simplestclaw_desktop::config::Config;
+use simplestclaw_desktop::tray::{
+    gateway_label, reduce, TrayMenu, TrayState, TrayToggle, TrayUpdate,
+};
+
+fn fold(updates: &[TrayUpdate]) -> TrayState {
+    updates
+        .iter()
+        .fold(TrayState::default(), |state, update| reduce(state, *update))
+}
+
+#[test]
+fn config_sets_both_checkboxes_from_disk() {
+    let config = Config {
+        auto_start_gateway: false,
+        keep_running_in_background: true,
+        ..Config::default()
+    };
+    assert_eq!(
+        fold(&[TrayUpdate::from_config(&config)]),
+        TrayState {
+            auto_start: false,
+            keep_running: true,
+            gateway_running: false,
+            gateway_port: None,
+        }
+    );
+}
+
+#[test]
+fn toggles_flip_only_their_own_setting() {
+    let state = fold(&[
+        TrayUpdate::Config {
+            auto_start: true,
+            keep_running: false,
+        },
+        TrayUpdate::Toggled(TrayToggle::KeepRunning),
+    ]);
+    assert!(state.auto_start);
+    assert!(state.keep_running);
+
+    let state = reduce(state, TrayUpdate::Toggled(TrayToggle::AutoStart));
+    assert!(!state.auto_start);
+    assert!(state.keep_running);
+}
+
+#[test]
+fn gateway_updates_interleaved_with_a_toggle_are_kept() {
+    // A start lands between the click and the saved config coming back
+    let state = fold(&[
+        TrayUpdate::Config {
+            auto_start: false,
+            keep_running: false,
+        },
+        TrayUpdate::Toggled(TrayToggle::AutoStart),
+        TrayUpdate::Gateway {
+            running: true,
+            port: Some(18789),
+        },
+        TrayUpdate::Config {
+            auto_start: true,
+            keep_running: false,
+        },
+    ]);
+    assert_eq!(
+        state,
+        TrayState {
+            auto_start: true,
+            keep_running: false,
+            gateway_running: true,
+            gateway_port: Some(18789),
+        }
+    );
+
+    // And a settings change doesn't reset the gateway state
+    let state = reduce(
+        state,
+        TrayUpdate::Config {
+            auto_start: true,
+            keep_running: true,
+        },
+    );
+    assert!(state.gateway_running);
+}
+
+#[test]
+fn a_failed_save_is_undone_by_the_config_on_disk() {
+    let menu = TrayMenu::default();
+    let on_disk = TrayUpdate::Config {
+        auto_start: true,
+        keep_running: false,
+    };
+    menu.apply(on_disk);
+    assert!(
+        !menu
+            .apply(TrayUpdate::Toggled(TrayToggle::AutoStart))
+            .auto_start
+    );
+
+    // The setting wasn't saved, so re-reading config.json restores it
+    menu.apply(on_disk);
+    assert!(menu.state().auto_start);
+}
+
+#[test]
+fn the_status_line_shows_the_port_only_while_running() {
+    let running = fold(&[TrayUpdate::Gateway {
+        running: true,
+        port: Some(18800),
+    }]);
+    assert_eq!(
+        gateway_label(running.gateway_running, running.gateway_port),
+        "Gateway: running on port 18800"
+    );
+
+    let stopped = reduce(
+        running,
+        TrayUpdate::Gateway {
+            running: false,
+            port: Some(18800),
+        },
+    );
+    assert_eq!(stopped.gateway_port, None);
+    assert_eq!(
+        gateway_label(stopped.gateway_running, stopped.gateway_port),
+        "Gateway: stopped"
+    );
+}
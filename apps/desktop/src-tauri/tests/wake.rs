@@ -0,0 +1,72 @@
+//! Noticing a wake from sleep and checking the gateway after it.
+
+mod support;
+
+use std::time::{Duration, Instant, SystemTime};
+
+use simplestclaw_desktop::wake::{WakeDetector, WAKE_DEBOUNCE, WAKE_GAP, WAKE_PROBE_TIMEOUT};
+use support::{free_port, FakeGateway};
+
+const TICK: Duration = Duration::from_secs(5);
+
+#[test]
+fn a_sleep_is_noticed_from_either_clock() {
+    let wall = SystemTime::now();
+    let now = Instant::now();
+    let mut detector = WakeDetector::new(TICK);
+    assert_eq!(detector.observe(wall, now), None);
+    // An ordinary tick, a little late
+    assert_eq!(detector.observe(wall + TICK * 2, now + TICK * 2), None);
+
+    // The monotonic clock stood still for ten minutes
+    let slept = Duration::from_secs(600);
+    let (wall, now) = (wall + TICK * 3 + slept, now + TICK * 3);
+    assert_eq!(detector.observe(wall, now), Some(slept));
+
+    // Both clocks ran on, the tick came late
+    let mut detector = WakeDetector::new(TICK);
+    let wall = SystemTime::now();
+    let now = Instant::now();
+    detector.observe(wall, now);
+    let late = TICK + WAKE_GAP * 2;
+    assert_eq!(detector.observe(wall + late, now + late), Some(WAKE_GAP * 2));
+}
+
+#[test]
+fn wakes_in_quick_succession_count_once() {
+    let wall = SystemTime::now();
+    let now = Instant::now();
+    let mut detector = WakeDetector::new(TICK);
+    detector.observe(wall, now);
+
+    let gap = WAKE_GAP * 2;
+    let (mut wall, mut now) = (wall + TICK + gap, now + TICK);
+    assert!(detector.observe(wall, now).is_some());
+    wall += TICK + gap;
+    now += TICK;
+    assert_eq!(detector.observe(wall, now), None);
+
+    wall += WAKE_DEBOUNCE + gap;
+    now += WAKE_DEBOUNCE;
+    assert!(detector.observe(wall, now).is_some());
+}
+
+#[test]
+fn a_gateway_that_doesnt_answer_is_found_out() {
+    let fake = FakeGateway::serving();
+    let manager = fake.manager(free_port());
+    assert_eq!(manager.check_responsive(WAKE_PROBE_TIMEOUT), None);
+    manager.start().expect("start failed");
+    assert_eq!(manager.check_responsive(WAKE_PROBE_TIMEOUT), Some(Ok(())));
+    // Paused on purpose
+    if manager.pause().is_ok() {
+        assert_eq!(manager.check_responsive(WAKE_PROBE_TIMEOUT), None);
+    }
+    manager.stop().unwrap();
+
+    let rejecting = FakeGateway::new(&[("auth", "reject")]);
+    let manager = rejecting.manager_with(free_port(), |config| config.self_test_on_start = false);
+    manager.start().expect("start failed");
+    assert!(matches!(manager.check_responsive(WAKE_PROBE_TIMEOUT), Some(Err(_))));
+    manager.stop().unwrap();
+}
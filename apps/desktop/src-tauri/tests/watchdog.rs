@@ -0,0 +1,84 @@
+//! Memory limit trigger logic and process-tree accounting with fake samples.
+
+use simplestclaw_desktop::resources::{parse_ps_output, tree_rss_bytes, ProcessSample};
+use simplestclaw_desktop::sidecar::{node_options_with_flag, node_options_with_heap_limit};
+use simplestclaw_desktop::watchdog::MemoryLimitMonitor;
+
+const MB: u64 = 1024 * 1024;
+
+#[test]
+fn triggers_only_after_consecutive_breaches() {
+    let mut monitor = MemoryLimitMonitor::new(100, 3);
+    assert!(!monitor.observe(150 * MB));
+    assert!(!monitor.observe(150 * MB));
+    assert!(monitor.observe(150 * MB));
+}
+
+#[test]
+fn a_sample_under_the_limit_resets_the_count() {
+    let mut monitor = MemoryLimitMonitor::new(100, 3);
+    assert!(!monitor.observe(150 * MB));
+    assert!(!monitor.observe(150 * MB));
+    assert!(!monitor.observe(90 * MB));
+    assert!(!monitor.observe(150 * MB));
+    assert!(!monitor.observe(150 * MB));
+    assert!(monitor.observe(150 * MB));
+}
+
+#[test]
+fn count_resets_after_triggering() {
+    let mut monitor = MemoryLimitMonitor::new(100, 2);
+    assert!(!monitor.observe(101 * MB));
+    assert!(monitor.observe(101 * MB));
+    assert!(!monitor.observe(101 * MB));
+    assert!(monitor.observe(101 * MB));
+}
+
+#[test]
+fn exactly_at_the_limit_is_not_a_breach() {
+    let mut monitor = MemoryLimitMonitor::new(100, 1);
+    assert!(!monitor.observe(100 * MB));
+    assert!(monitor.observe(100 * MB + 1));
+}
+
+#[test]
+fn tree_rss_sums_descendants_only() {
+    let samples = parse_ps_output(
+        "    1     0   1000 00:00:09 /sbin/launchd\n\
+         \x20 100     1    100 00:00:01 node\n\
+         \x20 101   100    200 00:00:02 node\n\
+         \x20 102   101    300 00:00:03 Google Chrome Helper\n\
+         \x20 200     1   5000 00:00:04 other\n",
+    );
+    assert_eq!(samples.len(), 5);
+    assert_eq!(samples[3].name, "Google Chrome Helper");
+    assert_eq!(tree_rss_bytes(&samples, 100), 600 * 1024);
+    assert_eq!(tree_rss_bytes(&samples, 102), 300 * 1024);
+}
+
+#[test]
+fn tree_rss_survives_pid_cycles() {
+    let samples = [
+        ProcessSample { pid: 1, ppid: 2, rss_bytes: 10, cpu_ms: 0, name: "a".to_string() },
+        ProcessSample { pid: 2, ppid: 1, rss_bytes: 20, cpu_ms: 0, name: "b".to_string() },
+    ];
+    assert_eq!(tree_rss_bytes(&samples, 1), 30);
+}
+
+#[test]
+fn heap_limit_is_appended_to_node_options() {
+    assert_eq!(
+        node_options_with_heap_limit("", 512).as_deref(),
+        Some("--max-old-space-size=512")
+    );
+    assert_eq!(
+        node_options_with_heap_limit(" --enable-source-maps ", 512).as_deref(),
+        Some("--enable-source-maps --max-old-space-size=512")
+    );
+    assert_eq!(node_options_with_heap_limit("--max-old-space-size=2048", 512), None);
+    assert_eq!(
+        node_options_with_flag("--max-old-space-size=512", "--openssl-legacy-provider").as_deref(),
+        Some("--max-old-space-size=512 --openssl-legacy-provider")
+    );
+    assert_eq!(node_options_with_flag("--openssl-legacy-provider", "--openssl-legacy-provider"), None);
+}
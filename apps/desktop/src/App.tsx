@@ -5,7 +5,7 @@ import { Loading } from './components/Loading';
 import { Onboarding } from './components/Onboarding';
 import { SettingsPanel } from './components/SettingsPanel';
 import { useAppStore } from './lib/store';
-import { tauri } from './lib/tauri';
+import { errorMessage, isReadyError, tauri } from './lib/tauri';
 
 function App() {
   const { screen, setScreen, setGatewayStatus, setRuntimeStatus, setApiKeyConfigured, setError } =
@@ -52,28 +52,48 @@ function App() {
   // Start gateway after runtime is ready
   const startApp = useCallback(async () => {
     try {
-      // Check if API key is configured
-      const hasKey = await tauri.hasApiKey();
-      setApiKeyConfigured(hasKey);
-
-      if (!hasKey) {
-        setScreen('onboarding');
-        return;
-      }
-
-      // API key exists, try to start gateway
       setGatewayStatus({ type: 'starting' });
-      const info = await tauri.startGateway();
+      const { info } = await tauri.ensureReady();
+      setApiKeyConfigured(true);
       setGatewayStatus({ type: 'running', info });
       setScreen('chat');
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      if (isReadyError(err) && err.stage === 'credentials') {
+        setApiKeyConfigured(false);
+        setGatewayStatus({ type: 'stopped' });
+        setScreen('onboarding');
+        return;
+      }
+      const message = errorMessage(err);
       setError(message);
       setGatewayStatus({ type: 'error', message });
       setScreen('onboarding');
     }
   }, [setScreen, setGatewayStatus, setApiKeyConfigured, setError]);
 
+  // Follow status changes pushed by the backend, e.g. a gateway that exited
+  useEffect(() => {
+    let stop: (() => void) | undefined;
+    let cancelled = false;
+    tauri
+      .onGatewayStatus((status) => {
+        const current = useAppStore.getState().gatewayStatus;
+        if (status.running && status.info) {
+          if (current.type !== 'starting') setGatewayStatus({ type: 'running', info: status.info });
+        } else if (current.type === 'running') {
+          setGatewayStatus({ type: 'stopped' });
+        }
+      })
+      .then((unlisten) => {
+        if (cancelled) unlisten();
+        else stop = unlisten;
+      });
+    return () => {
+      cancelled = true;
+      stop?.();
+    };
+  }, [setGatewayStatus]);
+
   useEffect(() => {
     // Prevent double initialization from React Strict Mode
     if (isInitializedRef.current) {
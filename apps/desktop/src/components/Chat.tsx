@@ -9,6 +9,9 @@ import ReactMarkdown from 'react-markdown';
 import { useAppStore } from '../lib/store';
 import { tauri } from '../lib/tauri';
 
+// How often request activity is reported to the backend
+const ACTIVITY_REPORT_INTERVAL_MS = 10000;
+
 // Error Toast Component
 function ErrorToast({ message, onDismiss }: { message: string; onDismiss: () => void }) {
   useEffect(() => {
@@ -152,6 +155,11 @@ export function Chat() {
 
     clientRef.current = client;
 
+    // Let the backend notice a gateway that stops answering requests
+    const activityInterval = setInterval(() => {
+      tauri.reportGatewayActivity(client.getActivity()).catch(() => {});
+    }, ACTIVITY_REPORT_INTERVAL_MS);
+
     // Small delay to avoid React Strict Mode double-invoke issues
     const connectTimeout = setTimeout(() => {
       console.log('[Chat] Attempting to connect, isActive:', isActive);
@@ -165,6 +173,7 @@ export function Chat() {
     return () => {
       isActive = false;
       clearTimeout(connectTimeout);
+      clearInterval(activityInterval);
       // Only disconnect if actually connected
       if (client.getState() !== 'disconnected') {
         client.disconnect();
@@ -1,7 +1,7 @@
 import { AlertCircle, Check, Download, Loader2 } from 'lucide-react';
 import { useEffect, useState } from 'react';
 import { type RuntimeStatus, useAppStore } from '../lib/store';
-import { type RuntimeStatus as TauriRuntimeStatus, tauri } from '../lib/tauri';
+import { type RuntimeStatus as TauriRuntimeStatus, errorMessage, tauri } from '../lib/tauri';
 
 type Provider = 'anthropic' | 'openai' | 'google' | 'openrouter' | null;
 
@@ -230,7 +230,7 @@ export function Onboarding() {
     try {
       await tauri.installRuntime();
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setRuntimeStatus({ type: 'error', message });
     }
   };
@@ -247,14 +247,14 @@ export function Onboarding() {
     setError(null);
 
     try {
-      await tauri.setApiKey(apiKey.trim());
+      await tauri.setApiKey(apiKey.trim(), true);
       setApiKeyConfigured(true);
       setGatewayStatus({ type: 'starting' });
-      const info = await tauri.startGateway();
+      const { info } = await tauri.startGateway();
       setGatewayStatus({ type: 'running', info });
       setScreen('chat');
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setError(message);
       setGatewayStatus({ type: 'error', message });
     } finally {
@@ -6,6 +6,7 @@ import {
   type AppDataInfo,
   type Provider,
   type RuntimeStatus as TauriRuntimeStatus,
+  errorMessage,
   tauri,
 } from '../../lib/tauri';
 
@@ -222,8 +223,9 @@ function GatewayStatusSection() {
   const statusDisplay = getStatusDisplay();
   // Include token in URL so Control UI auto-authenticates
   // The token is stored in localStorage after first load and removed from URL
+  // A gateway on a Unix socket has no port for the browser to open
   const dashboardUrl =
-    gatewayStatus.type === 'running'
+    gatewayStatus.type === 'running' && gatewayStatus.info.port != null
       ? `http://localhost:${gatewayStatus.info.port}/?token=${encodeURIComponent(gatewayStatus.info.token)}`
       : null;
 
@@ -244,23 +246,32 @@ function GatewayStatusSection() {
           <>
             <div className="flex items-center justify-between">
               <span className="text-[14px] text-white/60">URL</span>
-              <span className="text-[14px] text-white/80 font-mono">{gatewayStatus.info.url}</span>
-            </div>
-            <div className="flex items-center justify-between">
-              <span className="text-[14px] text-white/60">Port</span>
-              <span className="text-[14px] text-white/80 font-mono">{gatewayStatus.info.port}</span>
+              {/* Without the query, which may carry the token (tokenInUrl) */}
+              <span className="text-[14px] text-white/80 font-mono">
+                {gatewayStatus.info.url.split('?')[0]}
+              </span>
             </div>
             <div className="flex items-center justify-between">
-              <span className="text-[14px] text-white/60">Control UI</span>
-              <a
-                href={dashboardUrl ?? '#'}
-                target="_blank"
-                rel="noopener noreferrer"
-                className="text-[14px] text-blue-400 hover:text-blue-300 transition-colors underline"
-              >
-                Open Dashboard
-              </a>
+              <span className="text-[14px] text-white/60">
+                {gatewayStatus.info.socketPath ? 'Socket' : 'Port'}
+              </span>
+              <span className="text-[14px] text-white/80 font-mono">
+                {gatewayStatus.info.socketPath ?? gatewayStatus.info.port}
+              </span>
             </div>
+            {dashboardUrl && (
+              <div className="flex items-center justify-between">
+                <span className="text-[14px] text-white/60">Control UI</span>
+                <a
+                  href={dashboardUrl}
+                  target="_blank"
+                  rel="noopener noreferrer"
+                  className="text-[14px] text-blue-400 hover:text-blue-300 transition-colors underline"
+                >
+                  Open Dashboard
+                </a>
+              </div>
+            )}
           </>
         )}
 
@@ -606,7 +617,7 @@ function ResetDataSection() {
       // Navigate to success screen where user can choose to restart or quit
       setScreen('delete-success');
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setError(message);
       console.error('Failed to delete app data:', err);
       setIsDeleting(false);
@@ -725,7 +736,7 @@ export function GeneralTab() {
         status: 'success',
       });
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setError(message);
     } finally {
       setSaving(false);
@@ -754,7 +765,7 @@ export function GeneralTab() {
       setTimeout(() => setSaved(false), 2000);
       setApiKey('sk-ant-api03-••••••••••••••••••••••••••••••••');
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err);
+      const message = errorMessage(err);
       setError(message);
       addActivityLog({
         operationType: 'api_call',
@@ -769,9 +780,8 @@ export function GeneralTab() {
   const handleLogout = async () => {
     setLoggingOut(true);
     try {
-      await tauri.stopGateway();
+      await tauri.deleteApiKey();
       setGatewayStatus({ type: 'stopped' });
-      await tauri.setApiKey('');
       setApiKeyConfigured(false);
       addActivityLog({
         operationType: 'gateway',
@@ -4,9 +4,19 @@ import { create } from 'zustand';
 export type AppScreen = 'loading' | 'onboarding' | 'chat' | 'settings' | 'delete-success';
 
 export interface GatewayInfo {
+  /** ws+unix://<path> for a gateway on a Unix socket */
   url: string;
-  port: number;
+  /** null for a gateway on a Unix socket */
+  port: number | null;
+  /** The socket of the unix transport */
+  socketPath?: string | null;
   token: string;
+  /** Listening beyond loopback, so other devices can connect */
+  lanExposed?: boolean;
+  /** Directory the agent works in; null for an external gateway */
+  workspace?: string | null;
+  /** The start that spawned this gateway; 0 for an external one */
+  generation?: number;
 }
 
 export type GatewayStatus =
@@ -1,13 +1,147 @@
+import type { RequestActivity } from '@simplestclaw/openclaw-client';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import type { ActivityLogEntry, GatewayInfo } from './store';
 
 export type Provider = 'anthropic' | 'openai' | 'google' | 'openrouter';
 
+/** How the gateway takes connections */
+export type Transport = 'tcp' | 'unix';
+
 export interface Config {
+  configVersion: number;
   provider: Provider;
+  /** Only the prefix and last 4 characters, e.g. sk-ant-…abcd */
   anthropicApiKey: string | null;
+  apiKeyInKeychain: boolean;
+  /** Keep the key in secrets.json next to config.json, for machines without a keychain */
+  storeApiKeyInFile: boolean;
+  /** Keys for providers besides `provider`, redacted like anthropicApiKey */
+  providerKeys: Partial<Record<Provider, string>>;
+  providerKeysInKeychain: Provider[];
   gatewayPort: number;
+  /** Address the gateway listens on; 0.0.0.0 exposes it to the LAN */
+  gatewayHost: string;
+  /** unix listens on a socket only this user can open instead of gatewayPort */
+  transport: Transport;
+  /** The socket of the unix transport; null for gateway.sock in the runtime dir */
+  gatewaySocketPath: string | null;
+  /** Start on a free port when gatewayPort is taken, instead of failing */
+  autoSelectPort: boolean;
   autoStartGateway: boolean;
+  /** Hide the window to the tray on close instead of stopping the gateway */
+  keepRunningInBackground: boolean;
+  /** Whether the app starts at login, as the system has it; see setLaunchAtLogin */
+  launchAtLogin: boolean;
+  /** Also write gateway output to gateway.log; takes effect on the next launch */
+  fileLoggingEnabled: boolean;
+  /** Size at which gateway.log is rotated */
+  gatewayLogMaxFileMb: number;
+  /** Rotated gateway logs kept */
+  gatewayLogKeepFiles: number;
+  /** Gateway tools disabled at the next start */
+  blockedTools: string[];
+  /** Unix account the gateway runs as; the app must run as root */
+  runAsUser: string | null;
+  maxConcurrentSessions: number | null;
+  maxConcurrentRequests: number | null;
+  /** Dollars a gateway may spend on the API; no limit when null */
+  spendLimitUsd: number | null;
+  /** Share of spendLimitUsd at which gateway://spend-warning is emitted, default 0.8 */
+  warnAtFraction: number;
+  /** Stop the gateway when its estimated spend reaches spendLimitUsd */
+  hardStopOnLimit: boolean;
+  /** Where the gateway keeps sessions; defaults to gateway-state in app data */
+  gatewayStateDir: string | null;
+  /** Warn at start below this much free disk space; 0 turns the warning off */
+  minFreeDiskMb: number;
+  /** Seconds between gateway://metrics events; none when null */
+  metricsIntervalSecs: number | null;
+  /** How long a start waits for the gateway to listen before stopping it, 3 to 120 */
+  gatewayStartupTimeoutSecs: number;
+  /** Further tries of a start that failed for a reason that may pass, at most 5 */
+  startMaxRetries: number;
+  /** Start the gateway again, with backoff, when it crashes */
+  autoRestartGateway: boolean;
+  /** Restart a running gateway when config.json is edited outside the app */
+  autoRestartOnConfigChange: boolean;
+  /** Restart a gateway that stopped answering after the machine slept */
+  restartOnWake: boolean;
+  /** Restart an idle gateway after this many hours of uptime, null for never */
+  autoRestartIntervalHours: number | null;
+  /** Minutes without a request before a gateway with clients counts as idle */
+  autoRestartQuietMinutes: number;
+  /** Look for a newer openclaw in the npm registry once a day */
+  checkOpenclawUpdates: boolean;
+  /** Unix timestamp (ms) of the last successful update check */
+  lastOpenclawUpdateCheck: number | null;
+  /** How long a stopped gateway gets to shut down before it is killed */
+  gatewayStopGraceSecs: number;
+  /** Check the gateway accepts this app's token after every start */
+  selfTestOnStart: boolean;
+  /** Start with the same token every time until it is regenerated */
+  persistGatewayToken: boolean;
+  /** Let getConnectionString and copyConnectionString hand out the token */
+  allowExternalClients: boolean;
+  /** The kept token, redacted */
+  gatewayToken: string | null;
+  /** Node.js binary to run the gateway with instead of searching for one */
+  nodePath: string | null;
+  /** openclaw CLI to run directly, e.g. a local build, instead of through npx */
+  openclawPath: string | null;
+  /** Run npx openclaw even when the app bundles openclaw */
+  preferGlobalOpenclaw: boolean;
+  /** Start openclaw releases older than the app supports, for forks */
+  skipOpenclawVersionCheck: boolean;
+  /** What a start would run: openclawPath or the node found; null when nothing */
+  effectiveProgram: string | null;
+  /** Fields forced by SIMPLESTCLAW_* environment variables, e.g. gatewayPort */
+  overridden: string[];
+  /** Start node with --openssl-legacy-provider; a workaround, off by default */
+  opensslLegacyProvider: boolean;
+  /** Locale for the gateway, e.g. en-GB; the system's when null */
+  locale: string | null;
+  /** IANA timezone for the gateway, e.g. Europe/Berlin; the system's when null */
+  timezone: string | null;
+  /** Model the agent uses, e.g. anthropic/claude-sonnet-4-5; openclaw's choice when null */
+  defaultModel: string | null;
+  /** Model for quick background work like titles */
+  smallModel: string | null;
+  /** The gateway's own log level; its default when null */
+  gatewayLogLevel: GatewayLogLevel | null;
+  /** HTTP proxy for the gateway and the key check; the app's own proxy variables when null */
+  proxy: ProxyConfig | null;
+  /** Anthropic-compatible API to use instead of Anthropic's, e.g. a company LLM proxy */
+  anthropicBaseUrl: string | null;
+  /** Header the key goes in instead of x-api-key; `Authorization` sends it as a bearer token */
+  anthropicAuthHeader: string | null;
+  /** Serve `wss://` with this certificate and key; plain `ws://` when null */
+  tls: TlsConfig | null;
+  /** Connect to this gateway instead of starting one; the token is redacted */
+  externalGateway: ExternalGateway | null;
+  /** Profiles not in use; the active one's settings are the fields here */
+  profiles: Record<string, ProfileConfig>;
+  /** Name of the profile in use; 'default' when null */
+  activeProfile: string | null;
+  /** Passed to the gateway after the app's own arguments */
+  gatewayExtraArgs: string[];
+  /** Set for the gateway after the app's own variables, also in clean mode */
+  gatewayExtraEnv: Record<string, string>;
+}
+
+export type ApiKeyErrorKind =
+  | 'malformed'
+  | 'unauthorized'
+  | 'network'
+  | 'rate-limited'
+  | 'unexpected'
+  | 'settings';
+
+export interface ApiKeyCheck {
+  valid: boolean;
+  errorKind: ApiKeyErrorKind | null;
+  message: string | null;
+  organizationId: string | null;
 }
 
 export interface RuntimeStatus {
@@ -20,6 +154,783 @@ export interface RuntimeStatus {
   error: string | null;
 }
 
+export interface StartWarning {
+  code: string;
+  message: string;
+}
+
+export interface StartResult {
+  info: GatewayInfo;
+  warnings: StartWarning[];
+}
+
+/** Error returned by every command: a stable code, its parameters and English text */
+export interface AppError {
+  code: string;
+  message: string;
+  /** The underlying cause, e.g. an OS error; always English, not to be translated */
+  detail: string | null;
+  data: Record<string, string>;
+}
+
+/** One entry of `getErrorCatalog`, for translating an error code */
+export interface ErrorDescriptor {
+  code: string;
+  /** Names of the `data` parameters the error comes with */
+  params: string[];
+  /** `params` has a `detail`, which isn't translated */
+  hasDetail: boolean;
+  /** The English template, with `{name}` for each parameter it shows */
+  fallback: string;
+}
+
+/**
+ * Codes `startGateway` fails with; `gateway_start_failed` covers anything
+ * without its own. `gateway_not_found` has the directories searched in `data.searched`, one
+ * per line, and `port_in_use` the `data.port`. `port_not_permitted` and `port_blocked` have
+ * a free `data.suggested` port for `applySuggestedPort`. `external_gateway_detected` is a
+ * gateway the user started on `data.port` (`data.pid`, if found), see `adoptExternalGateway`.
+ */
+export type StartErrorCode =
+  | 'config_dir_unavailable'
+  | 'config_io'
+  | 'config_read_only'
+  | 'config_unavailable'
+  | 'config_invalid'
+  | 'config_too_new'
+  | 'invalid_config_override'
+  | 'runtime_not_installed'
+  | 'api_key_missing'
+  | 'api_key_unreadable'
+  | 'invalid_openclaw_path'
+  | 'invalid_gateway_option'
+  | 'invalid_proxy_url'
+  | 'invalid_external_gateway_url'
+  | 'external_gateway_unreachable'
+  | 'gateway_not_found'
+  | 'port_in_use'
+  | 'port_in_use_by_instance'
+  | 'port_not_permitted'
+  | 'port_blocked'
+  | 'external_gateway_detected'
+  | 'transport_unsupported'
+  | 'socket_in_use'
+  | 'gateway_spawn_failed'
+  | 'gateway_start_cancelled'
+  | 'auth_self_test_failed'
+  | 'tool_block_unsupported'
+  | 'gateway_flag_unsupported'
+  | 'openclaw_too_old'
+  | 'invalid_locale'
+  | 'invalid_timezone'
+  | 'invalid_setting'
+  | 'disk_full'
+  | 'gateway_start_failed';
+
+export function isAppError(err: unknown): err is AppError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err;
+}
+
+/** Text to show for an error thrown by a command */
+export function errorMessage(err: unknown): string {
+  if (isAppError(err)) return err.message;
+  return err instanceof Error ? err.message : String(err);
+}
+
+/** Stages of `ensureReady`, in order */
+export type ReadyStage = 'config' | 'credentials' | 'binary' | 'gateway' | 'self-test';
+
+/** Payload of `ready://progress`, sent as each stage begins */
+export interface ReadyProgress {
+  stage: ReadyStage;
+  step: number;
+  steps: number;
+}
+
+export interface ReadyResult {
+  info: GatewayInfo;
+  /** No gateway state existed yet, so there are no sessions to restore */
+  firstRun: boolean;
+  warnings: StartWarning[];
+}
+
+/** Error from `ensureReady`: an AppError and the stage it stopped at */
+export interface ReadyError extends AppError {
+  stage: ReadyStage;
+}
+
+export function isReadyError(err: unknown): err is ReadyError {
+  return isAppError(err) && 'stage' in err;
+}
+
+export type GatewayHealth = 'stopped' | 'starting' | 'idle' | 'healthy' | 'unresponsive';
+
+/** Payload of `gateway://unhealthy` */
+export interface UnhealthyEvent {
+  /** Of the unresponsive gateway, as in GatewayInfo */
+  generation: number;
+  unresponsiveForMs: number;
+  /** false when the port refuses connections, not just requests unanswered */
+  acceptingConnections: boolean;
+}
+
+/** What the sandbox workspace actually restricts */
+export interface SandboxStatus {
+  workspace: string;
+  applied: string[];
+  notEnforced: string[];
+}
+
+/** Why the gateway's port differs from the configured one */
+export interface PortMismatch {
+  source: 'configured' | 'auto-selected' | 'gateway-override' | 'adopted';
+  message: string;
+  restartResolves: boolean;
+}
+
+export interface PortChange {
+  port: number;
+  restartRequired: boolean;
+  /** Other software commonly uses the port */
+  warning: string | null;
+}
+
+/** Who enforces a concurrency limit; `app` only counts sessions started from the app */
+export type LimitEnforcement = 'gateway' | 'app' | 'not-enforced';
+
+export interface ConcurrencyLimit {
+  max: number;
+  enforcedBy: LimitEnforcement;
+}
+
+export interface ConcurrencyLimits {
+  sessions: ConcurrencyLimit | null;
+  requests: ConcurrencyLimit | null;
+}
+
+export interface BlockedToolsChange {
+  blockedTools: string[];
+  restartRequired: boolean;
+}
+
+/** A profile not in use; `apiKey` is redacted and only set with storeApiKeyInFile */
+export interface ProfileConfig {
+  apiKey: string | null;
+  apiKeyInKeychain: boolean;
+  gatewayPort: number;
+  gatewayExtraArgs: string[];
+}
+
+export interface ProfileInfo {
+  name: string;
+  active: boolean;
+  hasApiKey: boolean;
+  gatewayPort: number;
+  gatewayExtraArgs: string[];
+}
+
+export interface ProfileSwitch {
+  profiles: ProfileInfo[];
+  /** The gateway restarted with the new profile, when one was running */
+  started: StartResult | null;
+}
+
+/** A stored API key, without the key */
+export interface KeySummary {
+  label: string;
+  /** Unix timestamp (ms) it was added */
+  createdAt: number;
+  /** Like sk-ant-…abcd */
+  hint: string;
+  /** It is the key in use */
+  active: boolean;
+}
+
+export interface KeySwitch {
+  keys: KeySummary[];
+  /** The gateway restarted with the key, when one was running */
+  started: StartResult | null;
+}
+
+/** A gateway managed outside the app, e.g. under systemd */
+export interface ExternalGateway {
+  /** Like ws://localhost:18789 */
+  url: string;
+  token: string;
+}
+
+/** How an unreadable config.json was recovered from */
+export interface ConfigRecovery {
+  detail: string;
+  /** Otherwise the settings are back to their defaults */
+  fromBackup: boolean;
+  /** Where the unreadable file was moved */
+  movedTo: string;
+}
+
+/** config.json was readable by others and has been made private */
+export interface PermissionsFix {
+  path: string;
+  /** The mode it had, in octal like 644 */
+  previousMode: string;
+}
+
+export interface ProxyConfig {
+  httpProxy: string | null;
+  httpsProxy: string | null;
+  /** Comma-separated hosts reached directly, like `localhost,.corp` */
+  noProxy: string | null;
+}
+
+/** PEM files, by absolute path */
+export interface TlsConfig {
+  certPath: string;
+  keyPath: string;
+}
+
+export interface GatewayOptionsChange {
+  restartRequired: boolean;
+}
+
+export interface WorkspaceDirChange {
+  restartRequired: boolean;
+}
+
+export type GatewayLogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export interface ModelConfigChange {
+  restartRequired: boolean;
+}
+
+/**
+ * Settings `setConfig` can change; missing ones stay as they are and null
+ * resets the nullable ones. Keys, tokens, profiles, the proxy and the
+ * external gateway have their own commands.
+ */
+export interface ConfigPatch {
+  provider?: Provider;
+  /** 1024 to 65535 */
+  gatewayPort?: number;
+  /** An IP address, or localhost for 127.0.0.1 */
+  gatewayHost?: string;
+  transport?: Transport;
+  gatewaySocketPath?: string | null;
+  autoSelectPort?: boolean;
+  autoStartGateway?: boolean;
+  keepRunningInBackground?: boolean;
+  fileLoggingEnabled?: boolean;
+  gatewayMaxMemoryMb?: number | null;
+  gatewayLowPriority?: boolean;
+  gatewayHeartbeatTimeoutSecs?: number;
+  gatewayStartupTimeoutSecs?: number;
+  startMaxRetries?: number;
+  autoRestartGateway?: boolean;
+  autoRestartOnConfigChange?: boolean;
+  restartOnWake?: boolean;
+  /** Must be more than 0, null turns scheduled restarts off */
+  autoRestartIntervalHours?: number | null;
+  autoRestartQuietMinutes?: number;
+  checkOpenclawUpdates?: boolean;
+  gatewayStopGraceSecs?: number;
+  sandboxWorkspace?: boolean;
+  /** Must be an existing directory */
+  workspaceDir?: string | null;
+  nodePath?: string | null;
+  openclawPath?: string | null;
+  preferGlobalOpenclaw?: boolean;
+  skipOpenclawVersionCheck?: boolean;
+  locale?: string | null;
+  timezone?: string | null;
+  defaultModel?: string | null;
+  smallModel?: string | null;
+  gatewayLogLevel?: GatewayLogLevel | null;
+  gatewayExtraArgs?: string[];
+  gatewayExtraEnv?: Record<string, string>;
+  cleanEnvironment?: boolean;
+  forwardDevEnv?: boolean;
+  tokenInUrl?: boolean;
+  allowExternalClients?: boolean;
+  selfTestOnStart?: boolean;
+  blockedTools?: string[];
+  minFreeDiskMb?: number;
+  /** Seconds between gateway://metrics events; null turns them off */
+  metricsIntervalSecs?: number | null;
+  /** null removes the limit */
+  spendLimitUsd?: number | null;
+  warnAtFraction?: number;
+  hardStopOnLimit?: boolean;
+}
+
+export interface ConfigUpdate {
+  config: Config;
+  /** The settings that changed, e.g. gatewayPort */
+  changed: string[];
+  /** The running gateway was started with some of the old settings */
+  restartRequired: boolean;
+  /** Worth telling about the new settings, like a port other software commonly uses */
+  warnings: string[];
+}
+
+/**
+ * How `importConfig` treats the current settings: `merge` takes the file's
+ * over them, `replace` over the defaults. Keys not in the file are kept.
+ */
+export type ImportMode = 'merge' | 'replace';
+
+export interface LogSearchOptions {
+  caseInsensitive?: boolean;
+  /** Match as a plain substring instead of a regex */
+  literal?: boolean;
+  context?: number;
+  limit?: number;
+}
+
+export interface LogMatch {
+  /** 'memory' or the log file name */
+  source: string;
+  lineNumber: number;
+  timestamp: number | null;
+  stream: string;
+  text: string;
+  before: string[];
+  after: string[];
+}
+
+export interface LogSearchResult {
+  /** Newest first */
+  matches: LogMatch[];
+  truncated: boolean;
+}
+
+export type LogLevel = 'debug' | 'info' | 'warn' | 'error';
+
+/** Which live log entries a subscription receives; unset fields match all */
+export interface LogFilter {
+  minLevel?: LogLevel;
+  stream?: 'stdout' | 'stderr';
+  /** Regex on the leading [component] tag */
+  component?: string;
+}
+
+export interface LogEntry {
+  timestamp: number | null;
+  stream: string;
+  level: LogLevel;
+  component: string | null;
+  text: string;
+}
+
+/** Payload of gateway://restarted and gateway://restarted-after-wake */
+export interface GatewayRestartEvent {
+  /** e.g. `unresponsive`, `memory-limit`, `wake` */
+  reason: string;
+  message: string;
+  /** The new gateway, null when it didn't come back up */
+  result: StartResult | null;
+  error: string | null;
+}
+
+/** Payload of gateway://scheduled-restart, before the restart and after it */
+export interface ScheduledRestartEvent {
+  phase: 'restarting' | 'restarted' | 'failed' | 'cancelled';
+  /** How long the restarted gateway had run */
+  uptimeSecs: number;
+  /** The new gateway, once restarted */
+  result: StartResult | null;
+  error: string | null;
+}
+
+/** Payload of gateway://crashed */
+export interface GatewayCrashEvent {
+  /** Of the crashed gateway, as in GatewayInfo */
+  generation: number;
+  exitCode: number | null;
+  signal: number | null;
+  /** Consecutive restarts, counting the one this crash gets */
+  restarts: number;
+  /** null when autoRestartGateway is off or the gateway is running again */
+  restartInMs: number | null;
+  /** The gateway's last lines of stderr, oldest first */
+  stderrTail: string[];
+  /** Unix ms */
+  at: number;
+  launch: LaunchDescriptor | null;
+}
+
+/** How the most recent gateway was launched; env values are never included */
+export interface LaunchDescriptor {
+  /** openclaw itself, or node for npx */
+  binaryPath: string;
+  /** The token is redacted */
+  args: string[];
+  /** Names of the variables the app set */
+  envKeys: string[];
+  /** Unix ms of the spawn */
+  startedAt: number;
+  cwd: string | null;
+}
+
+/** The last crash, kept until a start succeeds */
+export interface CrashInfo {
+  exitCode: number | null;
+  signal: number | null;
+  /** Up to 100 lines, oldest first */
+  stderrTail: string[];
+  /** Unix ms */
+  at: number;
+}
+
+/** `forced` when the gateway had to be killed, `detached` when an adopted one was let go of */
+export type StopKind = 'graceful' | 'forced' | 'detached';
+
+/** A buffered line of gateway output, as returned by get_gateway_logs */
+export interface LogLine {
+  /** Increases by one per line since the app started */
+  seq: number | null;
+  timestamp: number | null;
+  /** `marker` for the line recorded between two gateway processes */
+  stream: 'stdout' | 'stderr' | 'marker';
+  text: string;
+}
+
+/** Every line of gateway output, pushed as gateway://log */
+export interface GatewayLogEvent {
+  stream: 'stdout' | 'stderr';
+  line: string;
+  /** Unix ms */
+  timestamp: number;
+}
+
+/** Lines held back while the gateway floods its output, pushed together as gateway://log-batch */
+export interface GatewayLogBatch {
+  lines: GatewayLogEvent[];
+  /** Lines left out of the batch; they are still in getGatewayLogs */
+  dropped: number;
+}
+
+/** Where an openclaw comes from: shipped with the app, openclawPath, or npx */
+export type OpenclawKind = 'bundled' | 'config' | 'global';
+
+export interface OpenclawSource {
+  /** What the running gateway was started from; null when none runs */
+  running: OpenclawKind | null;
+  /** What the next start would use */
+  next: OpenclawKind;
+  bundledPath: string | null;
+  /** Why the bundled openclaw isn't used, when it isn't */
+  bundledUnavailable: string | null;
+}
+
+export interface OpenclawVersionInfo {
+  /** null when it couldn't be read */
+  version: string | null;
+  /** The program asked: openclaw itself, or node for npx */
+  path: string | null;
+  usesNpx: boolean;
+  minimum: string;
+  /** Whether version is minimum or newer; null when unknown */
+  supported: boolean | null;
+  checkSkipped: boolean;
+}
+
+/** Onboarding's checklist; each detail says why, null when there is nothing to add */
+export interface SetupStatus {
+  hasApiKey: boolean;
+  apiKeyDetail: string | null;
+  openclawFound: boolean;
+  /** What a start would run: openclaw itself, or node for npx */
+  openclawPath: string | null;
+  openclawDetail: string | null;
+  /** null until a start or getOpenclawVersion has read it */
+  openclawVersion: string | null;
+  /** false only for a known version a start would refuse */
+  versionSupported: boolean;
+  versionDetail: string | null;
+  portAvailable: boolean;
+  portDetail: string | null;
+  gatewayRunning: boolean;
+  gatewayDetail: string | null;
+  /** There is an openclaw CLI config `importFromOpenclaw` can read */
+  cliConfigDetected: boolean;
+}
+
+/** What `importFromOpenclaw` did */
+export interface ImportSummary {
+  /** The CLI config read */
+  source: string;
+  /** Fields written, e.g. anthropicApiKey, defaultModel, gatewayPort */
+  imported: string[];
+  skipped: { field: string; reason: string }[];
+}
+
+/** One line of npm's output, pushed as openclaw://install-progress */
+export interface InstallProgress {
+  stream: 'stdout' | 'stderr';
+  line: string;
+}
+
+/** Result of checkForOpenclawUpdate, and payload of openclaw://update-available */
+export interface OpenclawUpdateInfo {
+  /** null when openclaw isn't installed or its version can't be read */
+  current: string | null;
+  latest: string;
+  updateAvailable: boolean;
+  /** upgradeOpenclaw has a package manager to upgrade with */
+  canAutoUpgrade: boolean;
+}
+
+export interface InstallResult {
+  installer: 'npm' | 'pnpm' | 'bun';
+  /** The openclaw found afterwards; null when it isn't anywhere the app looks */
+  path: string | null;
+  version: string | null;
+  /** A running gateway was restarted onto the upgrade */
+  restarted: boolean;
+}
+
+/** Full gateway status, as returned by get_gateway_status and pushed as gateway://status */
+export interface GatewayStatusSnapshot {
+  running: boolean;
+  /** Connected to externalGateway; running then means it can be reached */
+  external: boolean;
+  /** False for an adopted or external gateway, which the app doesn't stop on quit */
+  managed: boolean;
+  info: GatewayInfo | null;
+  pid: number | null;
+  effectiveUid: number | null;
+  sandbox: SandboxStatus | null;
+  /** Tools the running gateway has disabled */
+  blockedTools: string[];
+  limits: ConcurrencyLimits;
+  configuredPort: number | null;
+  effectivePort: number | null;
+  portMismatch: PortMismatch | null;
+  health: GatewayHealth;
+  /** Whether the port accepted a connection within the last second; null when stopped */
+  acceptingConnections: boolean | null;
+  lastResponseAt: number | null;
+  startedAt: number | null;
+  /** null in gateway://status events, which would otherwise change every second */
+  uptimeSecs: number | null;
+  /** Of the last gateway that exited on its own; null when killed by a signal */
+  lastExitCode: number | null;
+  lastExitSignal: number | null;
+  /** Unix ms of that exit, kept after the gateway is gone */
+  lastExitAt: number | null;
+  /** Not set by a stop; cleared by the next successful start */
+  lastCrash: CrashInfo | null;
+  /** Consecutive restarts after crashes; reset by a stable run or a stop */
+  crashRestarts: number;
+  /** Suspended by `pauseGateway`; still running, health is `idle` */
+  paused: boolean;
+  /** A start is in progress; the fields above describe no gateway until it finishes */
+  starting: boolean;
+  /** Spawn attempts so far; info.generation once the last one is ready */
+  generation: number;
+  /** e.g. `node_openssl_unsupported` after a recognized crash */
+  error: string | null;
+}
+
+/** Answer of validateGatewayInfo */
+export interface ValidationResult {
+  /** The info held describes the running gateway */
+  valid: boolean;
+  /** Of the running gateway, null when none runs */
+  generation: number | null;
+  /** The running gateway's info, when the held one is stale */
+  info: GatewayInfo | null;
+}
+
+/** One gateway instance's status, as listed by listGatewayInstances */
+export interface InstanceStatus extends GatewayStatusSnapshot {
+  /** 'default' for the active profile, otherwise the profile's name */
+  instance: string;
+}
+
+export type GatewayPhase = 'starting' | 'running' | 'exited' | 'restarting' | 'stopped';
+
+/** Payload of setup://key-received, from a simplestclaw://set-key?key=… link */
+export interface KeyReceivedEvent {
+  key: string;
+}
+
+/** One stage of run_connectivity_check */
+export interface ConnectivityStageResult {
+  stage: 'loopback' | 'handshake' | 'lan';
+  passed: boolean;
+  /** What happened, e.g. "upgrade rejected: token mismatch" */
+  detail: string;
+  /** What to do about a failure */
+  hint: string | null;
+}
+
+/** Whether clients can reach the gateway; `lan` only when gatewayHost isn't loopback */
+export interface ConnectivityReport {
+  port: number;
+  passed: boolean;
+  stages: ConnectivityStageResult[];
+}
+
+/** Clients and requests of the running gateway, counted from its output; zero again with the next one */
+export interface GatewayStats {
+  clientsConnected: number;
+  totalRequests: number;
+  /** Unix timestamp (ms) */
+  lastRequestAt: number | null;
+}
+
+/** Model usage of the running gateway, estimated from its output; zero again with the next one */
+export interface UsageEstimate {
+  inputTokens: number;
+  outputTokens: number;
+  /** Reported costs, plus other usage at a mid-range model's rates */
+  estimatedUsd: number;
+  /** Lines of output that reported usage */
+  reports: number;
+  /** Unix timestamp (ms) */
+  lastReportAt: number | null;
+}
+
+/** Payload of gateway://spend-warning and gateway://spend-limit-reached */
+export interface SpendEvent {
+  generation: number;
+  spentUsd: number;
+  limitUsd: number;
+  inputTokens: number;
+  outputTokens: number;
+  /** Stopped because hardStopOnLimit is on */
+  stopped: boolean;
+}
+
+/** What the running gateway uses, as returned by get_gateway_metrics and pushed as gateway://metrics */
+export interface GatewayMetrics {
+  pid: number;
+  /** Resident memory of the gateway and its child processes */
+  rssBytes: number;
+  /** Percent of one core since the previous sample, so above 100 on several cores; null on the first */
+  cpuPercent: number | null;
+  uptimeSecs: number;
+  /** Established connections on the gateway's port; null when they can't be listed */
+  openConnections: number | null;
+  sampledAt: number;
+}
+
+/** Payload of gateway://status-changed */
+export interface StatusChange {
+  /** One per spawn attempt; never decreases */
+  generation: number;
+  phase: GatewayPhase;
+  /** For `exited`; null when killed by a signal */
+  exitCode: number | null;
+  status: GatewayStatusSnapshot;
+}
+
+/** What a start is doing, in this order */
+export type StartPhase = 'locating-binary' | 'checking-port' | 'spawning' | 'waiting-ready';
+
+/** Payload of gateway://starting */
+export interface StartPhaseEvent {
+  /** As in StatusChange */
+  generation: number;
+  phase: StartPhase;
+  /** Unix timestamp (ms) the phase began */
+  at: number;
+  /** Since the start began */
+  elapsedMs: number;
+}
+
+/** Payload of gateway://ready */
+export interface StartReadyEvent {
+  generation: number;
+  at: number;
+  elapsedMs: number;
+  /** null for a gateway on a socket */
+  port: number | null;
+}
+
+/** Payload of gateway://start-retrying: a try failed in a way that may pass */
+export interface StartRetryEvent {
+  /** Of the try that failed */
+  generation: number;
+  /** The try that failed, from 1 */
+  attempt: number;
+  /** Including the first */
+  maxAttempts: number;
+  retryInMs: number;
+  at: number;
+  error: AppError;
+}
+
+/**
+ * Payload of gateway://start-failed: the error with where and when the
+ * start failed. Only the error when the start on launch failed before
+ * its first phase.
+ */
+export interface StartFailedEvent extends AppError {
+  generation?: number;
+  /** null when it failed before the first phase */
+  phase?: StartPhase | null;
+  at?: number;
+  elapsedMs?: number;
+}
+
+export type TransitionKind =
+  | 'started'
+  | 'start-failed'
+  | 'stopped'
+  | 'restarting'
+  | 'crashed'
+  | 'health';
+
+/** One entry of the gateway's status history */
+export interface StatusTransition {
+  /** Unix ms */
+  timestamp: number;
+  kind: TransitionKind;
+  cause: string;
+  exitCode?: number;
+}
+
+/** Sessions of the previous gateway, by whether the restarted one has them */
+export interface SessionCheck {
+  restored: string[];
+  lost: string[];
+}
+
+export type EnvSource =
+  | 'runtime'
+  | 'credentials'
+  | 'node-options'
+  | 'sandbox'
+  | 'gateway-state'
+  | 'run-as'
+  | 'locale'
+  | 'model'
+  | 'log-level'
+  | 'spend-limit'
+  | 'proxy'
+  | 'anthropic-endpoint'
+  | 'dev-env'
+  | 'extra-env'
+  | 'allow-list';
+
+/** A variable the gateway got; `value` is `•••` unless the name is known to be safe */
+export interface EnvVarReport {
+  name: string;
+  source: EnvSource;
+  value: string;
+  redacted: boolean;
+  length: number;
+}
+
+export interface EffectiveEnv {
+  /** False when no gateway was spawned yet and this is what the next start would set */
+  spawned: boolean;
+  /** When true `vars` is the whole environment, not just what the app added */
+  cleanEnvironment: boolean;
+  vars: EnvVarReport[];
+}
+
 export interface AppDataInfo {
   configPath: string | null;
   dataPath: string | null;
@@ -27,41 +938,672 @@ export interface AppDataInfo {
   totalSizeFormatted: string;
 }
 
+export interface PurgeOptions {
+  keepConfig?: boolean;
+  keepSessions?: boolean;
+  dryRun?: boolean;
+  nonce?: string;
+}
+
+export interface PurgeItem {
+  path: string;
+  kind: 'config' | 'data' | 'cache' | 'npx-cache' | 'sessions' | 'legacy-workspace';
+  sizeBytes: number;
+}
+
+export interface PurgePreparation {
+  nonce: string;
+  expiresInSecs: number;
+  items: PurgeItem[];
+}
+
+export interface PurgeReport {
+  dryRun: boolean;
+  removed: PurgeItem[];
+  failed: { item: PurgeItem; reason: string }[];
+}
+
 export const tauri = {
   // Config
   async getConfig(): Promise<Config> {
     return invoke('get_config');
   },
 
-  async setApiKey(key: string): Promise<void> {
-    return invoke('set_api_key', { key });
+  /** Set once after config.json had to be recovered, then null again */
+  async takeConfigRecovery(): Promise<ConfigRecovery | null> {
+    return invoke('take_config_recovery');
+  },
+
+  /** Set once after config.json was found readable by others, then null again */
+  async takeConfigPermissionsFix(): Promise<PermissionsFix | null> {
+    return invoke('take_config_permissions_fix');
+  },
+
+  /**
+   * Rejects with `keychain_failed` when the keychain can't store it. With
+   * `validate`, also with `api_key_rejected` for a malformed or refused key;
+   * one that can't be checked, e.g. offline, is still saved.
+   */
+  async setApiKey(key: string, validate = false): Promise<void> {
+    return invoke('set_api_key', { key, validate });
+  },
+
+  /**
+   * A simplestclaw://set-key link was opened. Show the key for the user to
+   * confirm; nothing is saved until setApiKey.
+   */
+  async onKeyReceived(callback: (event: KeyReceivedEvent) => void): Promise<() => void> {
+    return listen<KeyReceivedEvent>('setup://key-received', (event) => callback(event.payload));
+  },
+
+  /** The key of the last link, once; for a link opened before the window listened */
+  async takeReceivedKey(): Promise<string | null> {
+    return invoke('take_received_key');
+  },
+
+  /** Check a key against the Anthropic API, or `anthropicBaseUrl`, without saving it */
+  async testApiKey(key: string): Promise<ApiKeyCheck> {
+    return invoke('test_api_key', { key });
+  },
+
+  /** Remove the key and stop the gateway */
+  async deleteApiKey(): Promise<void> {
+    return invoke('delete_api_key');
   },
 
   async setProvider(provider: Provider): Promise<void> {
     return invoke('set_provider', { provider });
   },
 
+  /** For the selected provider this sets the API key, unchecked */
+  async setProviderKey(provider: Provider, key: string): Promise<void> {
+    return invoke('set_provider_key', { provider, key });
+  },
+
+  async hasProviderKey(provider: Provider): Promise<boolean> {
+    return invoke('has_provider_key', { provider });
+  },
+
+  /** Stops the gateway when it is the selected provider's key */
+  async deleteProviderKey(provider: Provider): Promise<void> {
+    return invoke('delete_provider_key', { provider });
+  },
+
+  async setGatewayPort(port: number): Promise<PortChange> {
+    return invoke('set_gateway_port', { port });
+  },
+
+  /**
+   * Save `port`, the `data.suggested` of `port_not_permitted` or `port_blocked`, and restart
+   * the gateway on it (or start it). Rejects with `port_in_use` if it was taken since.
+   */
+  async applySuggestedPort(port: number): Promise<StartResult> {
+    return invoke('apply_suggested_port', { port });
+  },
+
+  /** Rejects with `unknown_tool` for ids the gateway doesn't have */
+  async setBlockedTools(tools: string[]): Promise<BlockedToolsChange> {
+    return invoke('set_blocked_tools', { tools });
+  },
+
+  /**
+   * Rejects with `invalid_gateway_option` for `--port`, `--token`, `--host` or
+   * the token and API key variables; `data.field` names the setting
+   */
+  /** Rejects with `invalid_setting`, naming the setting in `data.field` */
+  async setConfig(patch: ConfigPatch): Promise<ConfigUpdate> {
+    return invoke('set_config', { patch });
+  },
+
+  /**
+   * Write the settings to `path` for another machine, with the API keys in
+   * plain text only when `includeSecrets`. Resolves to the path written.
+   */
+  async exportConfig(path: string, includeSecrets: boolean): Promise<string> {
+    return invoke('export_config', { path, includeSecrets });
+  },
+
+  /**
+   * Import a file written by `exportConfig`. Rejects with `config_too_new`
+   * or a validation error without changing anything.
+   */
+  async importConfig(path: string, mode?: ImportMode): Promise<ConfigUpdate> {
+    return invoke('import_config', { path, mode: mode ?? null });
+  },
+
+  async setGatewayOptions(args: string[], env: Record<string, string>): Promise<GatewayOptionsChange> {
+    return invoke('set_gateway_options', { args, env });
+  },
+
+  /**
+   * Pin the gateway's models; null leaves the choice to openclaw. Rejects with `invalid_setting`,
+   * naming `defaultModel` or `smallModel`, for something that isn't a model name.
+   */
+  async setModelConfig(
+    defaultModel: string | null,
+    smallModel: string | null
+  ): Promise<ModelConfigChange> {
+    return invoke('set_model_config', { defaultModel, smallModel });
+  },
+
+  /**
+   * Set the gateway's log level, null for its default, restarting a running gateway onto it.
+   * Resolves to the restarted gateway's info, null when none was running.
+   */
+  async setGatewayLogLevel(level: GatewayLogLevel | null): Promise<GatewayInfo | null> {
+    return invoke('set_gateway_log_level', { level });
+  },
+
+  /** Rejects with `invalid_locale` or `invalid_timezone`; null means the system's */
+  async setLocale(locale: string | null, timezone: string | null): Promise<void> {
+    return invoke('set_locale', { locale, timezone });
+  },
+
+  /** Rejects with `invalid_openclaw_path` unless it's an executable; null clears it */
+  async setOpenclawPath(path: string | null): Promise<void> {
+    return invoke('set_openclaw_path', { path });
+  },
+
+  /**
+   * Rejects with `invalid_setting` for `workspaceDir` unless it's an existing, writable directory;
+   * null goes back to the home directory
+   */
+  async setWorkspaceDir(path: string | null): Promise<WorkspaceDirChange> {
+    return invoke('set_workspace_dir', { path });
+  },
+
+  /**
+   * Send the gateway's Anthropic requests and the key check to `baseUrl`, with the key in
+   * `authHeader`. Rejects with `invalid_setting`; null or empty goes back to Anthropic's API.
+   */
+  async setAnthropicEndpoint(baseUrl: string | null, authHeader: string | null): Promise<void> {
+    return invoke('set_anthropic_endpoint', { baseUrl, authHeader });
+  },
+
+  /** Rejects with `invalid_proxy_url`; null, or every field empty, clears it */
+  async setProxyConfig(proxy: ProxyConfig | null): Promise<void> {
+    return invoke('set_proxy_config', { proxy });
+  },
+
+  /**
+   * Serve the gateway over TLS from the next start, or plain `ws://` with null.
+   * Rejects with `tls_config_invalid`, naming the file, unless both can be read.
+   */
+  async setTlsConfig(tls: TlsConfig | null): Promise<void> {
+    return invoke('set_tls_config', { tls });
+  },
+
+  /**
+   * Connect to `external` instead of starting a gateway, or start one again
+   * with null. A gateway the app started is stopped when switching to an
+   * external one. Rejects with `invalid_external_gateway_url`.
+   */
+  async setExternalGateway(external: ExternalGateway | null): Promise<void> {
+    return invoke('set_external_gateway', { external });
+  },
+
+  async listProfiles(): Promise<ProfileInfo[]> {
+    return invoke('list_profiles');
+  },
+
+  /**
+   * Without a port or args the profile gets the active one's. Rejects with
+   * `invalid_profile_name`, `profile_exists` or `invalid_gateway_option`.
+   */
+  async createProfile(
+    name: string,
+    options: { gatewayPort?: number; gatewayExtraArgs?: string[]; apiKey?: string } = {}
+  ): Promise<ProfileInfo[]> {
+    return invoke('create_profile', { name, ...options });
+  },
+
+  /** Rejects with `profile_active` for the profile in use */
+  async deleteProfile(name: string): Promise<ProfileInfo[]> {
+    return invoke('delete_profile', { name });
+  },
+
+  /** Restarts a running gateway with the profile's settings */
+  async switchProfile(name: string): Promise<ProfileSwitch> {
+    return invoke('switch_profile', { name });
+  },
+
+  async listApiKeys(): Promise<KeySummary[]> {
+    return invoke('list_api_keys');
+  },
+
+  /** Store `key` as `label`, replacing a key of that label */
+  async addApiKey(label: string, key: string): Promise<KeySummary[]> {
+    return invoke('add_api_key', { label, key });
+  },
+
+  /** Rejects with `api_key_in_use` for the key in use */
+  async removeApiKey(label: string): Promise<KeySummary[]> {
+    return invoke('remove_api_key', { label });
+  },
+
+  /** Use stored key `label`, restarting a running gateway with it */
+  async setActiveApiKey(label: string): Promise<KeySwitch> {
+    return invoke('set_active_api_key', { label });
+  },
+
+  /** Call before creating a session; rejects with `session_limit_reached` */
+  async checkSessionLimit(liveSessions: number): Promise<void> {
+    return invoke('check_session_limit', { liveSessions });
+  },
+
+  // Also toggled from the tray menu; either way `config://changed` follows
+  async setAutoStartGateway(enabled: boolean): Promise<void> {
+    return invoke('set_auto_start_gateway', { enabled });
+  },
+
+  async setKeepRunningInBackground(enabled: boolean): Promise<void> {
+    return invoke('set_keep_running_in_background', { enabled });
+  },
+
+  /** Rejects with `launch_at_login_failed`, saving nothing, when the system refuses */
+  async setLaunchAtLogin(enabled: boolean): Promise<void> {
+    return invoke('set_launch_at_login', { enabled });
+  },
+
+  /** Turning it on keeps the running gateway's token; off forgets it */
+  async setPersistGatewayToken(enabled: boolean): Promise<void> {
+    return invoke('set_persist_gateway_token', { enabled });
+  },
+
+  /** After any change, also to config.json from outside the app */
+  async onConfigChanged(callback: (config: Config) => void): Promise<() => void> {
+    return listen<Config>('config://changed', (event) => callback(event.payload));
+  },
+
   async hasApiKey(): Promise<boolean> {
     return invoke('has_api_key');
   },
 
   // Gateway
-  async startGateway(): Promise<GatewayInfo> {
-    return invoke('start_gateway');
+  /**
+   * Start the gateway if needed and wait until it accepts the app's token.
+   * Rejects with a ReadyError; stage `credentials` means onboarding is needed.
+   */
+  async ensureReady(): Promise<ReadyResult> {
+    return invoke('ensure_ready');
+  },
+
+  async onReadyProgress(callback: (progress: ReadyProgress) => void): Promise<() => void> {
+    return listen<ReadyProgress>('ready://progress', (event) => callback(event.payload));
+  },
+
+  /** A restart, if any, follows as gateway://restarted */
+  async onGatewayCrashed(callback: (event: GatewayCrashEvent) => void): Promise<() => void> {
+    return listen<GatewayCrashEvent>('gateway://crashed', (event) => callback(event.payload));
+  },
+
+  /** The gateway stopped answering after the machine slept and was restarted */
+  async onGatewayRestartedAfterWake(
+    callback: (event: GatewayRestartEvent) => void
+  ): Promise<() => void> {
+    return listen<GatewayRestartEvent>('gateway://restarted-after-wake', (event) =>
+      callback(event.payload)
+    );
+  },
+
+  async onScheduledRestart(
+    callback: (event: ScheduledRestartEvent) => void
+  ): Promise<() => void> {
+    return listen<ScheduledRestartEvent>('gateway://scheduled-restart', (event) =>
+      callback(event.payload)
+    );
+  },
+
+  /** Any start, including the one on launch when autoStartGateway is on */
+  async onGatewayStarted(callback: (result: StartResult) => void): Promise<() => void> {
+    return listen<StartResult>('gateway://started', (event) => callback(event.payload));
+  },
+
+  /** A start failed; the AppError's code is a `StartErrorCode` */
+  async onGatewayStartFailed(callback: (event: StartFailedEvent) => void): Promise<() => void> {
+    return listen<StartFailedEvent>('gateway://start-failed', (event) => callback(event.payload));
+  },
+
+  /** Each phase a start enters, so a slow start shows what it waits on */
+  async onGatewayStarting(callback: (event: StartPhaseEvent) => void): Promise<() => void> {
+    return listen<StartPhaseEvent>('gateway://starting', (event) => callback(event.payload));
+  },
+
+  /** A start finished with the gateway accepting connections */
+  async onGatewayReady(callback: (event: StartReadyEvent) => void): Promise<() => void> {
+    return listen<StartReadyEvent>('gateway://ready', (event) => callback(event.payload));
+  },
+
+  /** A start failed in a way that may pass and is about to try again */
+  async onGatewayStartRetrying(callback: (event: StartRetryEvent) => void): Promise<() => void> {
+    return listen<StartRetryEvent>('gateway://start-retrying', (event) => callback(event.payload));
+  },
+
+  /** The gateway has stayed unresponsive; a good time to offer a restart */
+  async onGatewayUnhealthy(callback: (event: UnhealthyEvent) => void): Promise<() => void> {
+    return listen<UnhealthyEvent>('gateway://unhealthy', (event) => callback(event.payload));
+  },
+
+  /**
+   * Rejects with an AppError whose code is a `StartErrorCode`. `instance`
+   * starts another profile next to the active one instead.
+   * `startupTimeoutSecs` (3 to 120) replaces gatewayStartupTimeoutSecs for
+   * this start, e.g. for a slower first run; out of range it rejects with
+   * `invalid_setting`.
+   */
+  async startGateway(instance?: string, startupTimeoutSecs?: number): Promise<StartResult> {
+    return invoke('start_gateway', { instance, startupTimeoutSecs: startupTimeoutSecs ?? null });
+  },
+
+  /** Pick up config changes; the token stays the same, so only reconnect */
+  async restartGateway(): Promise<StartResult> {
+    return invoke('restart_gateway');
   },
 
-  async stopGateway(): Promise<void> {
-    return invoke('stop_gateway');
+  /** Restart with a new token; the old one stops working, so reconnect with this one */
+  async regenerateToken(): Promise<StartResult> {
+    return invoke('regenerate_token');
   },
 
-  async getGatewayStatus(): Promise<{
-    running: boolean;
-    info: GatewayInfo | null;
-    error: string | null;
-  }> {
+  /** How the gateway was stopped, null when it wasn't running */
+  async stopGateway(instance?: string): Promise<StopKind | null> {
+    return invoke('stop_gateway', { instance });
+  },
+
+  async getGatewayStatus(instance = 'default'): Promise<GatewayStatusSnapshot> {
+    return invoke('get_gateway_status', { instance });
+  },
+
+  /**
+   * The running gateway's info without starting one, e.g. after the webview reloaded. Fails
+   * with `gateway_not_running` when `startGateway` is needed.
+   */
+  async getGatewayInfo(instance?: string): Promise<GatewayInfo> {
+    return invoke('get_gateway_info', { instance });
+  },
+
+  /**
+   * Use the gateway `external_gateway_detected` found, with the `token` it was started with,
+   * as the running one. It isn't managed: stopping only lets go of it. Rejects with
+   * `auth_self_test_failed` for a wrong token, `gateway_not_running` when it has gone.
+   */
+  async adoptExternalGateway(token: string, instance?: string): Promise<StartResult> {
+    return invoke('adopt_external_gateway', { token, instance });
+  },
+
+  /** Whether `info` is still the running gateway's; after a restart moved it, the current one */
+  async validateGatewayInfo(info: GatewayInfo, instance?: string): Promise<ValidationResult> {
+    return invoke('validate_gateway_info', { info, instance });
+  },
+
+  /** Every error code with its parameters and English template */
+  async getErrorCatalog(): Promise<ErrorDescriptor[]> {
+    return invoke('get_error_catalog');
+  },
+
+  /** The last crash with the end of its stderr, null after a successful start */
+  async getLastCrash(): Promise<CrashInfo | null> {
+    return invoke('get_last_crash');
+  },
+
+  /** How the most recent gateway was launched, also after it exited; null before any */
+  async getLaunchInfo(): Promise<LaunchDescriptor | null> {
+    return invoke('get_launch_info');
+  },
+
+  /**
+   * Suspend the gateway so it uses no CPU; stopping or starting it resumes it.
+   * Rejects with `gateway_not_running` or, on Windows, `gateway_pause_unsupported`.
+   */
+  async pauseGateway(instance?: string): Promise<void> {
+    return invoke('pause_gateway', { instance });
+  },
+
+  async resumeGateway(instance?: string): Promise<void> {
+    return invoke('resume_gateway', { instance });
+  },
+
+  /**
+   * The running gateway's URL with its token, for other clients. Rejects with
+   * `external_clients_not_allowed` unless allowExternalClients is on, and with
+   * `gateway_not_running`.
+   */
+  async getConnectionString(): Promise<string> {
+    return invoke('get_connection_string');
+  },
+
+  /** Like getConnectionString, but copied to the clipboard instead of returned */
+  async copyConnectionString(): Promise<void> {
+    return invoke('copy_connection_string');
+  },
+
+  /**
+   * Try the connections clients make to the gateway, with a hint for each
+   * that fails. Rejects with `gateway_not_running`.
+   */
+  async runConnectivityCheck(): Promise<ConnectivityReport> {
+    return invoke('run_connectivity_check');
+  },
+
+  /** Rejects with `gateway_not_running` when there is no gateway */
+  async getGatewayMetrics(): Promise<GatewayMetrics> {
+    return invoke('get_gateway_metrics');
+  },
+
+  async getGatewayStats(): Promise<GatewayStats> {
+    return invoke('get_gateway_stats');
+  },
+
+  async getUsageEstimate(): Promise<UsageEstimate> {
+    return invoke('get_usage_estimate');
+  },
+
+  /** Once per gateway, when its spend reaches warnAtFraction of spendLimitUsd */
+  async onSpendWarning(callback: (event: SpendEvent) => void): Promise<() => void> {
+    return listen<SpendEvent>('gateway://spend-warning', (event) => callback(event.payload));
+  },
+
+  async onSpendLimitReached(callback: (event: SpendEvent) => void): Promise<() => void> {
+    return listen<SpendEvent>('gateway://spend-limit-reached', (event) => callback(event.payload));
+  },
+
+  /** Every metricsIntervalSecs while a gateway runs; nothing when that is null */
+  async onGatewayMetrics(callback: (metrics: GatewayMetrics) => void): Promise<() => void> {
+    return listen<GatewayMetrics>('gateway://metrics', (event) => callback(event.payload));
+  },
+
+  /** Whether the gateway runs the bundled openclaw or a global one */
+  async getOpenclawSource(): Promise<OpenclawSource> {
+    return invoke('get_openclaw_source');
+  },
+
+  /** Everything onboarding checks, without starting or spawning anything */
+  async getSetupStatus(): Promise<SetupStatus> {
+    return invoke('get_setup_status');
+  },
+
+  /**
+   * Copy the API key, default model and port from the openclaw CLI's config
+   * where none is set. Rejects with `cli_config_not_found` without one.
+   */
+  async importFromOpenclaw(): Promise<ImportSummary> {
+    return invoke('import_from_openclaw');
+  },
+
+  /** Rejects like a start when there is no openclaw to ask */
+  async getOpenclawVersion(): Promise<OpenclawVersionInfo> {
+    return invoke('get_openclaw_version');
+  },
+
+  /**
+   * Install openclaw globally. Rejects with `installer_not_found`, `install_permission_denied`
+   * (with a fix for the npm prefix), `install_network_failed` or `install_failed`.
+   */
+  async installOpenclaw(): Promise<InstallResult> {
+    return invoke('install_openclaw');
+  },
+
+  /** Like installOpenclaw, with openclaw@latest; optionally restarts a running gateway */
+  async upgradeOpenclaw(restartGateway = false): Promise<InstallResult> {
+    return invoke('upgrade_openclaw', { restartGateway });
+  },
+
+  async onInstallProgress(callback: (progress: InstallProgress) => void): Promise<() => void> {
+    return listen<InstallProgress>('openclaw://install-progress', (event) => callback(event.payload));
+  },
+
+  /**
+   * Ask the npm registry for the latest openclaw now; also emits
+   * openclaw://update-available when it is newer. Rejects with `update_check_failed`.
+   */
+  async checkForOpenclawUpdate(): Promise<OpenclawUpdateInfo> {
+    return invoke('check_for_openclaw_update');
+  },
+
+  async onOpenclawUpdateAvailable(
+    callback: (info: OpenclawUpdateInfo) => void
+  ): Promise<() => void> {
+    return listen<OpenclawUpdateInfo>('openclaw://update-available', (event) =>
+      callback(event.payload)
+    );
+  },
+
+  /** Every gateway instance, the default one first */
+  async listGatewayInstances(): Promise<InstanceStatus[]> {
     return invoke('get_gateway_status');
   },
 
+  /**
+   * Call `onStatus` with the current status and then on every change.
+   * Re-hydrates when an event was missed. Call the result to stop.
+   */
+  async onGatewayStatus(onStatus: (status: GatewayStatusSnapshot) => void): Promise<() => void> {
+    let lastSeq: number | null = null;
+    const unlisten = await listen<{ seq: number; status: GatewayStatusSnapshot }>(
+      'gateway://status',
+      async (event) => {
+        const { seq, status } = event.payload;
+        const missed = lastSeq !== null && seq !== lastSeq + 1;
+        lastSeq = seq;
+        onStatus(missed ? await tauri.getGatewayStatus() : status);
+      }
+    );
+    onStatus(await tauri.getGatewayStatus());
+    return unlisten;
+  },
+
+  /** Every start, exit, restart and stop as it happens, older generations dropped */
+  async onGatewayStatusChanged(callback: (change: StatusChange) => void): Promise<() => void> {
+    let generation = 0;
+    return listen<StatusChange>('gateway://status-changed', (event) => {
+      if (event.payload.generation < generation) return;
+      generation = event.payload.generation;
+      callback(event.payload);
+    });
+  },
+
+  async getEffectiveGatewayEnv(): Promise<EffectiveEnv | null> {
+    return invoke('get_effective_gateway_env');
+  },
+
+  /** Newest first; `since` is unix ms */
+  async getStatusHistory(limit: number, since?: number): Promise<StatusTransition[]> {
+    return invoke('get_status_history', { limit, since: since ?? null });
+  },
+
+  /**
+   * Write a zip for a bug report, with secrets removed, to `dest` (a `.zip`, or a folder to put
+   * one in) or the downloads folder. Resolves to its path.
+   */
+  async exportDiagnostics(dest?: string): Promise<string> {
+    return invoke('export_diagnostics', { dest: dest ?? null });
+  },
+
+  /** The newest `limit` buffered lines, or all of them, oldest first */
+  async getGatewayLogs(limit?: number): Promise<LogLine[]> {
+    return invoke('get_gateway_logs', { limit: limit ?? null });
+  },
+
+  async searchGatewayLogs(pattern: string, options?: LogSearchOptions): Promise<LogSearchResult> {
+    return invoke('search_gateway_logs', { pattern, options });
+  },
+
+  /**
+   * Stream matching gateway output to `onEntry`; call the result to stop. Lines held back
+   * during a flood arrive as an array, which is passed on one entry at a time.
+   */
+  async subscribeLogs(filter: LogFilter, onEntry: (entry: LogEntry) => void): Promise<() => void> {
+    const id: number = await invoke('subscribe_logs', { filter });
+    const unlisten = await listen<LogEntry | LogEntry[]>(`logs://${id}`, (event) => {
+      const entries = Array.isArray(event.payload) ? event.payload : [event.payload];
+      entries.forEach(onEntry);
+    });
+    return () => {
+      unlisten();
+      void invoke('unsubscribe_logs', { id });
+    };
+  },
+
+  /** Where gateway.log and its rotations are */
+  async getLogDirectory(): Promise<string> {
+    return invoke('get_log_directory');
+  },
+
+  /** Delete the gateway log files; returns how many were removed */
+  async clearGatewayLogs(): Promise<number> {
+    return invoke('clear_gateway_logs');
+  },
+
+  /** Writing gateway.log failed and the output is now kept in memory only */
+  async onFileLoggingDisabled(
+    callback: (event: { dir: string; error: string }) => void
+  ): Promise<() => void> {
+    return listen<{ dir: string; error: string }>('gateway://file-logging-disabled', (event) =>
+      callback(event.payload)
+    );
+  },
+
+  /** Every line of gateway output, unfiltered, with the batches of a flood taken apart */
+  async onGatewayLog(callback: (event: GatewayLogEvent) => void): Promise<() => void> {
+    const unlistenLine = await listen<GatewayLogEvent>('gateway://log', (event) =>
+      callback(event.payload)
+    );
+    const unlistenBatch = await listen<GatewayLogBatch>('gateway://log-batch', (event) =>
+      event.payload.lines.forEach(callback)
+    );
+    return () => {
+      unlistenLine();
+      unlistenBatch();
+    };
+  },
+
+  async reportGatewayActivity(report: RequestActivity): Promise<void> {
+    return invoke('report_gateway_activity', { report });
+  },
+
+  /**
+   * Report the session ids the gateway lists after connecting. After a
+   * restart this emits `gateway://sessions-restored` or `gateway://sessions-lost`.
+   */
+  async reportSessions(sessions: string[]): Promise<SessionCheck | null> {
+    return invoke('report_sessions', { sessions });
+  },
+
+  async onSessionsChecked(
+    callback: (check: SessionCheck, restored: boolean) => void
+  ): Promise<() => void> {
+    const restored = await listen<SessionCheck>('gateway://sessions-restored', (event) =>
+      callback(event.payload, true)
+    );
+    const lost = await listen<SessionCheck>('gateway://sessions-lost', (event) =>
+      callback(event.payload, false)
+    );
+    return () => {
+      restored();
+      lost();
+    };
+  },
+
   // Runtime
   async getRuntimeStatus(): Promise<RuntimeStatus> {
     return invoke('get_runtime_status');
@@ -110,4 +1652,13 @@ export const tauri = {
   async deleteAllAppData(): Promise<void> {
     return invoke('delete_all_app_data');
   },
+
+  // Purge: prepare returns the nonce purgeAppData needs (except for a dry run)
+  async preparePurge(options?: PurgeOptions): Promise<PurgePreparation> {
+    return invoke('prepare_purge', { options });
+  },
+
+  async purgeAppData(options: PurgeOptions): Promise<PurgeReport> {
+    return invoke('purge_app_data', { options });
+  },
 };
@@ -9,6 +9,7 @@ import type {
   GatewayRequest,
   GatewayResponse,
   Message,
+  RequestActivity,
   StreamingChunk,
   ToolCall,
 } from './types';
@@ -48,6 +49,7 @@ export class OpenClawClient {
   private tickTimer: ReturnType<typeof setInterval> | null = null;
   private healthCheckTimer: ReturnType<typeof setInterval> | null = null;
   private sessionKey = 'agent:main:main'; // Default session key
+  private activity: RequestActivity = { attempts: 0, successes: 0, lastSuccessAt: null };
 
   // Track pending chat messages by runId
   private pendingChats = new Map<
@@ -84,6 +86,11 @@ export class OpenClawClient {
     return this.state;
   }
 
+  /** Requests sent and answered since this client was created */
+  getActivity(): RequestActivity {
+    return { ...this.activity };
+  }
+
   /** Connect to the Gateway */
   async connect(): Promise<void> {
     if (this.state === 'connected' || this.state === 'connecting') {
@@ -198,6 +205,14 @@ export class OpenClawClient {
     });
   }
 
+  /** Keys of the sessions the Gateway has, e.g. to check they survived a restart */
+  async listSessions(): Promise<string[]> {
+    const response = (await this.request('sessions.list', {})) as {
+      sessions?: { key: string }[];
+    };
+    return (response.sessions ?? []).map((session) => session.key);
+  }
+
   /** Send a request and wait for response */
   private async request<T = Record<string, unknown>>(method: string, params?: T): Promise<unknown> {
     if (!this.ws || this.state !== 'connected') {
@@ -238,6 +253,7 @@ export class OpenClawClient {
         reject(new Error(`Failed to send ${method}: ${sendErr}`));
         return;
       }
+      this.activity.attempts++;
 
       // Timeout after 30s
       setTimeout(() => {
@@ -426,6 +442,9 @@ export class OpenClawClient {
     if (!pending) return;
 
     this.pendingRequests.delete(response.id);
+    // Any response, even an error, shows the gateway is processing requests
+    this.activity.successes++;
+    this.activity.lastSuccessAt = Date.now();
 
     if (response.ok) {
       pending.resolve(response.payload);
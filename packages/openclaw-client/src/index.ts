@@ -12,6 +12,7 @@ export type {
   GatewayEventHandlers,
   ConnectionState,
   Message,
+  RequestActivity,
   ToolCall,
 } from './types';
 export { OpenClawClient } from './client';
@@ -145,3 +145,13 @@ export interface Usage {
   outputTokens: number;
   totalTokens: number;
 }
+
+/** Cumulative request counters, used by the host app to detect a hung gateway */
+export interface RequestActivity {
+  /** Requests sent */
+  attempts: number;
+  /** Responses received, including error responses */
+  successes: number;
+  /** Timestamp (ms) of the last response */
+  lastSuccessAt: number | null;
+}